@@ -0,0 +1,109 @@
+// Peak memory and time of parsing a package-lock.json style document from all of its tokens, as
+// they are lexed with `parse_str`, and into a `compact::Document`, and of a log export of escaped
+// strings with and without `lazy_strings`, run with `cargo bench --bench lockfile`
+extern crate joxide;
+
+use joxide::parser::ParserOptions;
+use joxide::{compact, lexer, parser};
+use std::time::Instant;
+
+#[path = "../src/bench/counting.rs"]
+mod counting;
+
+const PACKAGES: usize = 50_000;
+const RECORDS: usize = 100_000;
+
+fn lockfile() -> String {
+    let mut raw =
+        String::from("{\n  \"name\": \"app\",\n  \"lockfileVersion\": 3,\n  \"packages\": {\n");
+
+    for i in 0..PACKAGES {
+        if i != 0 {
+            raw += ",\n";
+        }
+
+        raw += &format!(
+            "    \"node_modules/package-{i}\": {{\n      \"version\": \"1.{v}.0\",\n      \"resolved\": \"https://registry.npmjs.org/package-{i}/-/package-{i}-1.{v}.0.tgz\",\n      \"integrity\": \"sha512-{i:064x}\",\n      \"dev\": true,\n      \"dependencies\": {{\n        \"package-{d}\": \"^1.0.0\"\n      }}\n    }}",
+            i = i,
+            v = i % 20,
+            d = (i * 7) % PACKAGES
+        );
+    }
+
+    raw + "\n  }\n}\n"
+}
+
+// Log records whose messages hold stringified JSON, so nearly every byte is in an escaped string
+fn logs() -> String {
+    let mut raw = String::from("[\n");
+
+    for i in 0..RECORDS {
+        if i != 0 {
+            raw += ",\n";
+        }
+
+        raw += &format!(
+            "  {{\"level\": \"info\", \"message\": \"{{\\\"user\\\": {i}, \\\"path\\\": \\\"/api/items/{i}\\\", \\\"agent\\\": \\\"Mozilla/5.0 (X11; Linux x86_64)\\\", \\\"took\\\": {t}}}\\n\"}}",
+            i = i,
+            t = i % 500
+        );
+    }
+
+    raw + "\n]\n"
+}
+
+// Keeps what `parse` returns alive until the peak memory has been read
+fn measure<'a, T>(name: &str, raw: &'a str, parse: impl FnOnce(&'a str) -> T) {
+    let counts = counting::start();
+
+    let start = Instant::now();
+    let parsed = parse(raw);
+    let elapsed = start.elapsed();
+
+    let (_, _, peak) = counting::stop(counts, 1);
+    let mb = raw.len() as f64 / 1e6;
+
+    println!(
+        "{:10} {:.3} s, {:6.1} MB/s, peak memory {:6.1} MB, {:.1}x the input",
+        name,
+        elapsed.as_secs_f64(),
+        mb / elapsed.as_secs_f64(),
+        peak as f64 / 1e6,
+        peak as f64 / raw.len() as f64
+    );
+
+    drop(parsed);
+}
+
+fn main() {
+    let raw = lockfile();
+    let tokens = lexer::lex(&raw).unwrap().len();
+
+    println!(
+        "input:     {:.1} MB, {} tokens",
+        raw.len() as f64 / 1e6,
+        tokens
+    );
+
+    measure("tokens:", &raw, |raw| {
+        let tokens = lexer::lex(raw).unwrap();
+        let value = parser::parse(&tokens).unwrap();
+        (tokens, value)
+    });
+    measure("streamed:", &raw, |raw| parser::parse_str(raw).unwrap());
+    measure("compact:", &raw, |raw| compact::parse(raw).unwrap());
+
+    let raw = logs();
+    println!("logs:      {:.1} MB", raw.len() as f64 / 1e6);
+
+    measure("eager:", &raw, |raw| {
+        parser::parse_str_with_options(raw, ParserOptions::default()).unwrap()
+    });
+    measure("lazy:", &raw, |raw| {
+        let options = ParserOptions {
+            lazy_strings: true,
+            ..ParserOptions::default()
+        };
+        parser::parse_str_with_options(raw, options).unwrap()
+    });
+}
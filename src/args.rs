@@ -1,8 +1,115 @@
 use argh::FromArgs;
+use std::path::Path;
+
+// Like `argh::from_env`, but a lone `-` is taken as the standard input path instead of an
+// unknown switch, by moving it after `--`
+pub fn from_env() -> JoxideArgs {
+    let mut strings: Vec<String> = std::env::args().collect();
+
+    if !strings.iter().any(|s| s == "--") {
+        if let Some(index) = strings.iter().position(|s| s == "-") {
+            strings.remove(index);
+            strings.extend(["--".to_string(), "-".to_string()]);
+        }
+    }
+
+    let cmd = Path::new(&strings[0])
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(&strings[0]);
+    let strs: Vec<&str> = strings.iter().map(|s| s.as_str()).collect();
+
+    JoxideArgs::from_args(&[cmd], &strs[1..]).unwrap_or_else(|early_exit| {
+        std::process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} --help for more information.",
+                    early_exit.output, cmd
+                );
+                1
+            }
+        })
+    })
+}
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// joxide, CLI tool for formatting and validating JSON files
 pub struct JoxideArgs {
+    #[argh(option)]
+    /// path to the config file, defaults to joxide.toml if present
+    pub config: Option<String>,
+
+    #[argh(option)]
+    /// language of error messages (en, es), defaults to JOXIDE_LOCALE or LANG
+    pub locale: Option<String>,
+
+    #[argh(option)]
+    /// encoding of the input file, detected when not given: utf-8, latin-1, windows-1252,
+    /// utf-16le, utf-16be, utf-32le or utf-32be
+    pub encoding: Option<String>,
+
+    #[argh(switch)]
+    /// read bytes that are not valid in the encoding as U+FFFD replacement characters instead of
+    /// failing
+    pub lossy: bool,
+
+    #[argh(option, default = "0")]
+    /// lines before the input in its host document, added to reported positions
+    pub line_offset: usize,
+
+    #[argh(option, default = "0")]
+    /// columns before the input on each line of its host document, added to reported positions
+    pub col_offset: usize,
+
+    #[argh(option)]
+    /// stop with an error once the parsed document needs roughly this many bytes
+    pub memory_limit: Option<usize>,
+
+    #[argh(option)]
+    /// stop with an error at arrays and objects nested deeper than this, defaults to 512, at most
+    /// 4096
+    pub max_depth: Option<usize>,
+
+    #[argh(switch)]
+    /// keep numbers as written, like 0.1000000000000000055 or 1e400, instead of reading them as 64-bit values
+    pub raw_numbers: bool,
+
+    #[argh(switch)]
+    /// ignore anything after the document instead of reporting it as an error
+    pub allow_trailing_content: bool,
+
+    #[argh(switch)]
+    /// accept // and /* */ comments, as JSONC files like tsconfig.json have
+    pub allow_comments: bool,
+
+    #[argh(switch)]
+    /// accept a comma before a closing bracket, reporting it as a warning instead of an error
+    pub allow_trailing_commas: bool,
+
+    #[argh(switch)]
+    /// accept NaN, Infinity and -Infinity as numbers, as JavaScript and Python write them
+    pub allow_non_finite: bool,
+
+    #[argh(switch)]
+    /// report a byte order mark at the start of the input as a warning, it is skipped either way
+    pub warn_bom: bool,
+
+    #[argh(option, default = "String::from(\"error\")")]
+    /// what to do with a key repeated in an object: error (default), or keep the first-wins or last-wins value with a warning
+    pub duplicate_keys: String,
+
+    #[argh(option)]
+    /// also write the results to this file, as JSON, SARIF (.sarif) or JUnit XML (.xml)
+    pub report_file: Option<String>,
+
+    #[argh(option)]
+    /// format of the report file: json, sarif or junit, defaults to its extension
+    pub report_format: Option<String>,
+
     #[argh(subcommand)]
     pub sub_command: JoxideSubcommand,
 }
@@ -12,22 +119,62 @@ pub struct JoxideArgs {
 pub enum JoxideSubcommand {
     Format(FormatArgs),
     Validate(ValidateArgs),
+    Convert(ConvertArgs),
+    ExplainPath(ExplainPathArgs),
+    Dupes(DupesArgs),
+    Diff(DiffArgs),
+    Merge(MergeArgs),
+    Merge3(Merge3Args),
+    GitMergeDriver(GitMergeDriverArgs),
+    GitTextconv(GitTextconvArgs),
+    Init(InitArgs),
+    Assert(AssertArgs),
+    Watch(WatchArgs),
+    Query(QueryArgs),
+    Tail(TailArgs),
+    Bench(BenchArgs),
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// format json file
 #[argh(subcommand, name = "format")]
 pub struct FormatArgs {
-    #[argh(option, default = "4")]
-    /// indent length, default is 4
-    pub indent_length: usize,
+    #[argh(option)]
+    /// indent length, defaults to the config file or 4
+    pub indent_length: Option<usize>,
 
     #[argh(switch)]
     /// modify the file instead of printing to console
     pub write: bool,
 
+    #[argh(option)]
+    /// format strings holding JSON objects or arrays, either "inline" or "escaped"
+    pub format_embedded_json: Option<String>,
+
+    #[argh(switch)]
+    /// sort object keys instead of keeping the order of the input
+    pub sort_keys: bool,
+
+    #[argh(option)]
+    /// sort an array of objects by a key, as "<array pointer>=<key pointer>" like
+    /// "/contributors=/name", where "*" in the array pointer matches any key or index
+    pub sort_array: Vec<String>,
+
+    #[argh(option)]
+    /// format every document of an input holding several, separated by "blank" lines,
+    /// "nul" bytes or "rs" characters
+    pub delimiter: Option<String>,
+
+    #[argh(switch)]
+    /// leave out the byte order mark the input starts with instead of writing it back
+    pub strip_bom: bool,
+
+    #[argh(switch)]
+    /// highlight the output with the colors of the config file, ignored with --write
+    pub color: bool,
+
     #[argh(positional)]
-    /// path to the file you want to format
+    /// path to the file you want to format, - for standard input
     pub file: String,
 }
 
@@ -35,7 +182,358 @@ pub struct FormatArgs {
 /// validate json file for syntax errors
 #[argh(subcommand, name = "validate")]
 pub struct ValidateArgs {
+    #[argh(switch)]
+    /// report every duplicate key with all of its occurrences
+    pub all_duplicates: bool,
+
+    #[argh(switch)]
+    /// report every syntax error instead of stopping at the first one
+    pub all_errors: bool,
+
+    #[argh(switch)]
+    /// treat every line as a separate document, lines are validated in parallel
+    pub ndjson: bool,
+
+    #[argh(switch)]
+    /// check the file is a JSON Schema valid against the meta-schema of its draft
+    pub as_schema: bool,
+
+    #[argh(option)]
+    /// check the file is valid against the JSON Schema in this file, reporting each value that
+    /// fails with the keyword of the schema that failed it
+    pub schema: Option<String>,
+
+    #[argh(option)]
+    /// JSON Schema draft of --as-schema and --schema instead of the one of '$schema': 2020-12,
+    /// 2019-09, 07, 06 or 04
+    pub schema_draft: Option<String>,
+
+    #[argh(switch)]
+    /// fail values of --schema that do not match their 'format' instead of taking it as an
+    /// annotation
+    pub format_assertion: bool,
+
+    #[argh(option)]
+    /// check the file is a valid document of a known format, one of: openapi
+    pub preset: Option<String>,
+
+    #[argh(option)]
+    /// file listing paths, directories or glob patterns to validate, one per line
+    pub files_from: Option<String>,
+
+    #[argh(option, default = "String::from(\"warning\")")]
+    /// lowest severity that makes validation fail: warning (default) or error, problems below
+    /// it are still reported
+    pub min_severity: String,
+
+    #[argh(positional)]
+    /// path to the file you want to validate, a directory to validate every .json file in it,
+    /// or a glob pattern like 'configs/**/*.json'
+    pub file: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// convert json file to another format
+#[argh(subcommand, name = "convert")]
+pub struct ConvertArgs {
+    #[argh(option)]
+    /// output format, one of: parquet
+    pub to: String,
+
+    #[argh(option, short = 'o')]
+    /// path to the file to write
+    pub output: String,
+
+    #[argh(positional)]
+    /// path to the file you want to convert
+    pub file: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// show where the value at a JSON pointer is defined
+#[argh(subcommand, name = "explain-path")]
+pub struct ExplainPathArgs {
+    #[argh(option, default = "2")]
+    /// lines of context shown around the value, default is 2
+    pub context: usize,
+
+    #[argh(positional)]
+    /// JSON pointer of the value, like /items/0/name
+    pub pointer: String,
+
+    #[argh(positional)]
+    /// path to the file containing the value
+    pub file: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// find json files with the same content in a directory tree
+#[argh(subcommand, name = "dupes")]
+pub struct DupesArgs {
+    #[argh(option)]
+    /// JSON pointer of a value to leave out of the comparison, where "*" matches any key or
+    /// index, can be repeated
+    pub ignore: Vec<String>,
+
+    #[argh(positional)]
+    /// directory to search for .json files
+    pub dir: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// show the differences between two json files
+#[argh(subcommand, name = "diff")]
+pub struct DiffArgs {
+    #[argh(option, default = "String::from(\"unified\")")]
+    /// how to show the differences: unified, side-by-side or tree, default is unified
+    pub view: String,
+
+    #[argh(option, short = 'U', default = "3")]
+    /// lines of context around each change in the unified and side-by-side views, default is 3
+    pub context: usize,
+
+    #[argh(switch)]
+    /// only print how many values were changed, added and removed
+    pub stat: bool,
+
+    #[argh(option)]
+    /// JSON pointer of the key of array items, like /id, to match items by it
+    pub by: Option<String>,
+
+    #[argh(option)]
+    /// JSON pointer of values that are never changes, like /generatedAt or /items/*/id, where *
+    /// matches any key or index, can be repeated
+    pub ignore_paths: Vec<String>,
+
+    #[argh(switch)]
+    /// color removed lines red and added lines green
+    pub color: bool,
+
+    #[argh(positional)]
+    /// the file before the changes
+    pub from: String,
+
+    #[argh(positional)]
+    /// the file after the changes
+    pub to: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// merge two json files with no common ancestor
+#[argh(subcommand, name = "merge")]
+pub struct MergeArgs {
+    #[argh(option)]
+    /// indent length of the merged file, default is the indent of ours
+    pub indent_length: Option<usize>,
+
+    #[argh(option)]
+    /// what to do with conflicts: "error" to fail, "ours" or "theirs" to take that side,
+    /// defaults to the config file or "error"
+    pub on_conflict: Option<String>,
+
+    #[argh(option, short = 'o')]
+    /// path to write the merged file to instead of printing it
+    pub output: Option<String>,
+
+    #[argh(positional)]
+    /// our file
+    pub ours: String,
+
+    #[argh(positional)]
+    /// their file
+    pub theirs: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// merge two edits of a json file
+#[argh(subcommand, name = "merge3")]
+pub struct Merge3Args {
+    #[argh(option)]
+    /// indent length of the merged file, default is the indent of ours
+    pub indent_length: Option<usize>,
+
+    #[argh(option)]
+    /// what to do with conflicts: "error" to fail, "ours" or "theirs" to take that side,
+    /// defaults to the config file or "error"
+    pub on_conflict: Option<String>,
+
+    #[argh(option, short = 'o')]
+    /// path to write the merged file to instead of printing it
+    pub output: Option<String>,
+
+    #[argh(positional)]
+    /// the common ancestor of both edits
+    pub base: String,
+
+    #[argh(positional)]
+    /// our edit
+    pub ours: String,
+
+    #[argh(positional)]
+    /// their edit
+    pub theirs: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// git merge driver, called as: joxide git-merge-driver %O %A %B
+#[argh(subcommand, name = "git-merge-driver")]
+pub struct GitMergeDriverArgs {
+    #[argh(option)]
+    /// indent length of the merged file, default is the indent of ours
+    pub indent_length: Option<usize>,
+
+    #[argh(option)]
+    /// what to do with conflicts: "error" to fail, "ours" or "theirs" to take that side,
+    /// defaults to the config file or "error"
+    pub on_conflict: Option<String>,
+
+    #[argh(positional)]
+    /// the common ancestor (%O)
+    pub base: String,
+
+    #[argh(positional)]
+    /// our version (%A), replaced by the merge result
+    pub ours: String,
+
+    #[argh(positional)]
+    /// their version (%B)
+    pub theirs: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// git textconv filter printing json with sorted keys
+#[argh(subcommand, name = "git-textconv")]
+pub struct GitTextconvArgs {
+    #[argh(option)]
+    /// JSON pointer of a value to leave out so diffs do not show it, like "/items/*/updatedAt",
+    /// can be repeated
+    pub ignore: Vec<String>,
+
+    #[argh(positional)]
+    /// path to the file to convert
+    pub file: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// write a starter joxide.toml
+#[argh(subcommand, name = "init")]
+pub struct InitArgs {
+    #[argh(switch)]
+    /// overwrite an existing joxide.toml
+    pub force: bool,
+
+    #[argh(switch)]
+    /// also use joxide as merge driver and diff filter for .json files in .gitattributes
+    pub git: bool,
+
+    #[argh(switch)]
+    /// print a pre-commit hook entry validating the json files of the repository
+    pub pre_commit: bool,
+
+    #[argh(positional, default = "String::from(\".\")")]
+    /// directory to initialize, its json files are used to infer the indent length
+    pub dir: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// check an assertion like '$.version == "2"' or 'count($.items[*]) > 0' holds for a json file
+#[argh(subcommand, name = "assert")]
+pub struct AssertArgs {
+    #[argh(positional)]
+    /// the assertion, a path or count(path) optionally compared with ==, !=, <, <=, > or >=
+    pub expression: String,
+
+    #[argh(positional)]
+    /// path to the file to check
+    pub file: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// print the values at a path like '$.records[*].id', one per line, without loading the
+/// whole document
+#[argh(subcommand, name = "query")]
+pub struct QueryArgs {
+    #[argh(positional)]
+    /// the path, made of .key, ["key"], [index], [*] and .* steps after $ to be read without
+    /// loading the document, or any other RFC 9535 JSONPath query
+    pub expression: String,
+
+    #[argh(switch)]
+    /// print strings holding a JSON object or array as the document they hold, indented
+    pub decode_nested: bool,
+
+    #[argh(option, default = "8")]
+    /// how many levels of strings in decoded strings are decoded as well, defaults to 8
+    pub decode_depth: usize,
+
+    #[argh(positional)]
+    /// path to the file to query, - for standard input
+    pub file: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// print the last lines of a json lines file, validating each of them
+#[argh(subcommand, name = "tail")]
+pub struct TailArgs {
+    #[argh(switch, short = 'f')]
+    /// keep printing lines as they are appended to the file
+    pub follow: bool,
+
+    #[argh(option, short = 'n', default = "10")]
+    /// number of lines to print before following, defaults to 10
+    pub lines: usize,
+
+    #[argh(option)]
+    /// only print lines for which an assertion like '$.level == "error"' holds
+    pub filter: Option<String>,
+
+    #[argh(option, default = "0")]
+    /// indent length, defaults to 0 to print each line on one line
+    pub indent_length: usize,
+
+    #[argh(switch)]
+    /// highlight the printed lines with the colors of the config file
+    pub color: bool,
+
+    #[argh(option, default = "500")]
+    /// milliseconds between checks for new lines, defaults to 500
+    pub interval: u64,
+
+    #[argh(positional)]
+    /// path to the json lines file
+    pub file: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// measure how fast json files are lexed, parsed and formatted
+#[argh(subcommand, name = "bench")]
+pub struct BenchArgs {
+    #[argh(option, short = 'n', default = "10")]
+    /// number of times each file is processed, defaults to 10
+    pub iterations: usize,
+
+    #[argh(switch)]
+    /// print the results as JSON instead of a table
+    pub json: bool,
+
+    #[argh(positional)]
+    /// paths to the files to measure
+    pub files: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// validate a json file every time it changes
+#[argh(subcommand, name = "watch")]
+pub struct WatchArgs {
+    #[argh(option)]
+    /// shell command to run each time the file changes and is valid
+    pub exec: Option<String>,
+
+    #[argh(option, default = "500")]
+    /// milliseconds between checks for changes, default is 500
+    pub interval: u64,
+
     #[argh(positional)]
-    /// path to the file you want to validate
+    /// path to the file to watch
     pub file: String,
 }
@@ -0,0 +1,538 @@
+use joxide::escape::unescape;
+use joxide::parser::Json;
+use joxide::{formatter, pointer, Number};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+// Assertions compare a path or count(path) against another operand, like `$.items[0].id == 1`
+// or `count($.items[*]) > 0`. A path without a comparison checks the values are not false or null
+
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+#[derive(Debug, PartialEq)]
+enum Operand<'e> {
+    Path(Vec<Step>),
+    Count(Vec<Step>),
+    Literal(Json<'e>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        match self {
+            Op::Eq => ordering == Some(Ordering::Equal),
+            Op::Ne => ordering != Some(Ordering::Equal),
+            Op::Lt => ordering == Some(Ordering::Less),
+            Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Op::Gt => ordering == Some(Ordering::Greater),
+            Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Assertion<'e> {
+    left: Operand<'e>,
+    comparison: Option<(Op, Operand<'e>)>,
+}
+
+// Why an assertion does not hold, with the pointer of the offending value. Counts and paths that
+// match nothing point at the deepest value their path leads to, comparisons of literals at the
+// whole document
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    pub pointer: String,
+    pub message: String,
+}
+
+struct ExpressionParser<'e> {
+    source: &'e str,
+    pos: usize,
+}
+
+impl<'e> ExpressionParser<'e> {
+    fn rest(&self) -> &'e str {
+        &self.source[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error<T>(&self) -> Result<T, String> {
+        match self.rest().chars().next() {
+            Some(c) => Err(format!(
+                "Unexpected '{}' at character {} of the expression",
+                c,
+                self.source[..self.pos].chars().count() + 1
+            )),
+            None => Err("Unexpected end of the expression".to_string()),
+        }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), String> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            self.error()
+        }
+    }
+
+    // Takes the longest prefix of characters matching `f`
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'e str {
+        let rest = self.rest();
+        let end = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    // Escaped content of a string literal, the opening quote is already consumed
+    fn string(&mut self) -> Result<&'e str, String> {
+        let rest = self.rest();
+        let mut escaped = false;
+
+        for (i, c) in rest.char_indices() {
+            match c {
+                '"' if !escaped => {
+                    self.pos += i + 1;
+                    return Ok(&rest[..i]);
+                }
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+
+        Err("Missing closing quote in the expression".to_string())
+    }
+
+    fn path(&mut self) -> Result<Vec<Step>, String> {
+        self.expect("$")?;
+        let mut steps = vec![];
+
+        loop {
+            if self.rest().starts_with(".*") {
+                self.pos += 2;
+                steps.push(Step::Wildcard);
+            } else if self.rest().starts_with('.') {
+                self.pos += 1;
+                let key = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-');
+
+                if key.is_empty() {
+                    return self.error();
+                }
+
+                steps.push(Step::Key(key.to_string()));
+            } else if self.eat("[") {
+                if self.eat("*") {
+                    steps.push(Step::Wildcard);
+                } else if self.eat("\"") {
+                    let key = self.string()?;
+                    steps.push(Step::Key(unescape(key).into_owned()));
+                } else {
+                    match self.take_while(|c| c.is_ascii_digit()).parse() {
+                        Ok(index) => steps.push(Step::Index(index)),
+                        Err(_) => return self.error(),
+                    }
+                }
+
+                self.expect("]")?;
+            } else {
+                return Ok(steps);
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand<'e>, String> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('$') {
+            return Ok(Operand::Path(self.path()?));
+        }
+
+        if self.eat("count(") {
+            let steps = self.path()?;
+            self.expect(")")?;
+            return Ok(Operand::Count(steps));
+        }
+
+        if self.eat("\"") {
+            return Ok(Operand::Literal(Json::String(unescape(self.string()?))));
+        }
+
+        let word = self.take_while(|c| c.is_alphanumeric() || "+-.".contains(c));
+
+        match word {
+            "null" => Ok(Operand::Literal(Json::Null)),
+            "true" => Ok(Operand::Literal(Json::Bool(true))),
+            "false" => Ok(Operand::Literal(Json::Bool(false))),
+            // Rust also parses words like `inf` and `NaN` as numbers
+            _ => match Number::parse(word) {
+                Some(n) if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                    Ok(Operand::Literal(Json::Number(n)))
+                }
+                _ => {
+                    self.pos -= word.len();
+                    self.error()
+                }
+            },
+        }
+    }
+
+    fn op(&mut self) -> Result<Option<Op>, String> {
+        // Two character operators first so `<=` is not read as `<`
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+
+        for (symbol, op) in ops {
+            if self.eat(symbol) {
+                return Ok(Some(op));
+            }
+        }
+
+        if self.rest().is_empty() {
+            Ok(None)
+        } else {
+            self.error()
+        }
+    }
+}
+
+pub fn parse(expression: &str) -> Result<Assertion<'_>, String> {
+    let mut parser = ExpressionParser {
+        source: expression,
+        pos: 0,
+    };
+
+    let left = parser.operand()?;
+    let comparison = match parser.op()? {
+        Some(op) => Some((op, parser.operand()?)),
+        None => None,
+    };
+
+    parser.skip_whitespace();
+
+    if !parser.rest().is_empty() {
+        return parser.error();
+    }
+
+    Ok(Assertion { left, comparison })
+}
+
+// A path on its own, like `$.records[*].id`
+pub fn parse_path(expression: &str) -> Result<Vec<Step>, String> {
+    let mut parser = ExpressionParser {
+        source: expression,
+        pos: 0,
+    };
+
+    parser.skip_whitespace();
+    let steps = parser.path()?;
+    parser.skip_whitespace();
+
+    if !parser.rest().is_empty() {
+        return parser.error();
+    }
+
+    Ok(steps)
+}
+
+// Values at the path with their JSON pointers
+fn select<'j, 'a>(value: &'j Json<'a>, steps: &[Step]) -> Vec<(String, &'j Json<'a>)> {
+    let mut current = vec![(String::new(), value)];
+
+    for step in steps {
+        let mut next = vec![];
+
+        for (path, value) in current {
+            match (step, value) {
+                (Step::Key(key), Json::Object(obj)) => {
+                    if let Some(item) = obj.get(key.as_str()) {
+                        next.push((pointer::push(&path, key), item));
+                    }
+                }
+                (Step::Index(index), Json::Array(arr)) => {
+                    if let Some(item) = arr.get(*index) {
+                        next.push((pointer::push(&path, &index.to_string()), item));
+                    }
+                }
+                (Step::Wildcard, Json::Object(obj)) => {
+                    for (key, item) in obj {
+                        next.push((pointer::push(&path, key), item));
+                    }
+                }
+                (Step::Wildcard, Json::Array(arr)) => {
+                    for (index, item) in arr.iter().enumerate() {
+                        next.push((pointer::push(&path, &index.to_string()), item));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        current = next;
+    }
+
+    current
+}
+
+// Pointer of the deepest value the steps lead to before a wildcard or a missing key or index
+fn anchor(value: &Json, steps: &[Step]) -> String {
+    let mut path = String::new();
+    let mut current = value;
+
+    for step in steps {
+        let next = match (step, current) {
+            (Step::Key(key), Json::Object(obj)) => {
+                obj.get(key.as_str()).map(|item| (key.clone(), item))
+            }
+            (Step::Index(index), Json::Array(arr)) => {
+                arr.get(*index).map(|item| (index.to_string(), item))
+            }
+            _ => None,
+        };
+
+        match next {
+            Some((token, item)) => {
+                path = pointer::push(&path, &token);
+                current = item;
+            }
+            None => break,
+        }
+    }
+
+    path
+}
+
+fn compare(left: &Json, right: &Json) -> Option<Ordering> {
+    match (left, right) {
+        (Json::Number(a), Json::Number(b)) => a.partial_cmp(b),
+        (Json::String(a), Json::String(b)) => Some(a.cmp(b)),
+        (Json::Bool(a), Json::Bool(b)) => Some(a.cmp(b)),
+        (a, b) if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn describe(value: &Json) -> String {
+    formatter::format_json(value, 0)
+}
+
+// Values of an operand, each with its pointer when it comes from the document
+type Values<'j> = Vec<(Option<String>, Cow<'j, Json<'j>>)>;
+
+impl<'e> Assertion<'e> {
+    fn values<'j>(
+        &self,
+        operand: &'j Operand<'e>,
+        value: &'j Json<'j>,
+    ) -> Result<Values<'j>, Failure>
+    where
+        'e: 'j,
+    {
+        match operand {
+            Operand::Literal(literal) => Ok(vec![(None, Cow::Borrowed(literal))]),
+            Operand::Count(steps) => {
+                let count = select(value, steps).len() as u64;
+                let path = anchor(value, steps);
+                Ok(vec![(Some(path), Cow::Owned(Json::Number(count.into())))])
+            }
+            Operand::Path(steps) => {
+                let selected = select(value, steps);
+
+                if selected.is_empty() {
+                    return Err(Failure {
+                        pointer: anchor(value, steps),
+                        message: "No value matches the path".to_string(),
+                    });
+                }
+
+                Ok(selected
+                    .into_iter()
+                    .map(|(path, item)| (Some(path), Cow::Borrowed(item)))
+                    .collect())
+            }
+        }
+    }
+
+    // Holds when it holds for every value the paths match
+    pub fn check<'j>(&self, value: &'j Json<'j>) -> Result<(), Failure>
+    where
+        'e: 'j,
+    {
+        let left = self.values(&self.left, value)?;
+
+        let (op, right) = match self.comparison {
+            Some((op, ref right)) => (op, self.values(right, value)?),
+            None => {
+                for (path, item) in left {
+                    if matches!(*item, Json::Null | Json::Bool(false)) {
+                        return Err(Failure {
+                            pointer: path.unwrap_or_default(),
+                            message: format!("Expected a value, found {}", describe(&item)),
+                        });
+                    }
+                }
+
+                return Ok(());
+            }
+        };
+
+        for (left_path, left_item) in &left {
+            for (right_path, right_item) in &right {
+                if !op.holds(compare(left_item, right_item)) {
+                    return Err(Failure {
+                        pointer: left_path
+                            .as_ref()
+                            .or(right_path.as_ref())
+                            .cloned()
+                            .unwrap_or_default(),
+                        message: format!(
+                            "Expected {} {} {}",
+                            describe(left_item),
+                            op.symbol(),
+                            describe(right_item)
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::{lexer, parser};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("count($.items[*]) > 0"),
+            Ok(Assertion {
+                left: Operand::Count(vec![Step::Key("items".to_string()), Step::Wildcard]),
+                comparison: Some((Op::Gt, Operand::Literal(Json::Number(0.0.into())))),
+            })
+        );
+        assert_eq!(
+            parse("$[\"a b\"][1].*"),
+            Ok(Assertion {
+                left: Operand::Path(vec![
+                    Step::Key("a b".to_string()),
+                    Step::Index(1),
+                    Step::Wildcard
+                ]),
+                comparison: None,
+            })
+        );
+
+        let cases = vec![
+            ("$.a = 1", "Unexpected '=' at character 5 of the expression"),
+            ("$.a == ", "Unexpected end of the expression"),
+            ("$.a == \"x", "Missing closing quote in the expression"),
+            (
+                "$.a == yes",
+                "Unexpected 'y' at character 8 of the expression",
+            ),
+        ];
+
+        for (expression, expected) in cases {
+            assert_eq!(
+                parse(expression),
+                Err(expected.to_string()),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let tokens = lexer::lex(
+            "{\"version\": \"2\", \"items\": [{\"id\": 1}, {\"id\": 3}], \"draft\": false}",
+        )
+        .unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        for expression in [
+            "$.version == \"2\"",
+            "count($.items[*]) > 0",
+            "count($.items[*]) == 2",
+            "$.items[*].id >= 1",
+            "$.items[1].id != $.items[0].id",
+            "$.items",
+            "count($.missing) == 0",
+        ] {
+            let assertion = parse(expression).unwrap();
+            assert_eq!(assertion.check(&value), Ok(()), "{}", expression);
+        }
+
+        let cases = vec![
+            ("$.version == 2", "/version", "Expected \"2\" == 2"),
+            ("$.items[*].id < 2", "/items/1/id", "Expected 3 < 2"),
+            ("count($.items[*]) > 2", "/items", "Expected 2 > 2"),
+            ("count($.items[0].*) == 0", "/items/0", "Expected 1 == 0"),
+            ("$.draft", "/draft", "Expected a value, found false"),
+            ("$.missing == 1", "", "No value matches the path"),
+            ("$.items[5].id == 1", "/items", "No value matches the path"),
+            ("1 == 2", "", "Expected 1 == 2"),
+        ];
+
+        for (expression, pointer, message) in cases {
+            let assertion = parse(expression).unwrap();
+            assert_eq!(
+                assertion.check(&value),
+                Err(Failure {
+                    pointer: pointer.to_string(),
+                    message: message.to_string()
+                }),
+                "{}",
+                expression
+            );
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use joxide::escape::escape;
+use joxide::{formatter, lexer, parser};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+// Lexes, parses and formats files a number of times to measure joxide on real inputs. With the
+// count_allocations feature the allocator of the binary also counts allocations for it, which
+// costs a few atomic additions on every allocation of every subcommand, so it is off by default
+
+#[cfg(feature = "count_allocations")]
+mod counting;
+
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub file: String,
+    pub bytes: usize,
+    pub tokens: usize,
+    // Mean time of one run of each step
+    pub lex: Duration,
+    pub parse: Duration,
+    pub format: Duration,
+    // Per run of the three steps, only counted with the count_allocations feature
+    pub allocations: Option<usize>,
+    pub allocated: Option<usize>,
+    // Most heap in use at once above what was in use before the runs
+    pub peak_heap: Option<usize>,
+}
+
+impl Stats {
+    fn throughput(&self, time: Duration) -> f64 {
+        self.bytes as f64 / 1e6 / time.as_secs_f64().max(1e-9)
+    }
+}
+
+// Each run parses with the options `options` gives, like the other subcommands
+pub fn run<'o>(
+    file: &str,
+    raw: &str,
+    iterations: usize,
+    options: impl Fn() -> parser::ParserOptions<'o>,
+) -> Result<Stats, String> {
+    let (mut lex, mut parse, mut format) = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    let mut tokens_count = 0;
+
+    #[cfg(feature = "count_allocations")]
+    let counts = counting::start();
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let invalid = || format!("{} is not valid JSON, validate it first", file);
+        let tokens = lexer::lex(raw).map_err(|_| invalid())?;
+        lex += start.elapsed();
+
+        let start = Instant::now();
+        let value = parser::parse_with_options(&tokens, options()).map_err(|_| invalid())?;
+        parse += start.elapsed();
+
+        let start = Instant::now();
+        let formatted = formatter::format_json(&value, 4);
+        format += start.elapsed();
+
+        tokens_count = tokens.len();
+        drop(formatted);
+    }
+
+    let runs = iterations.max(1);
+
+    #[cfg(feature = "count_allocations")]
+    let (allocations, allocated, peak_heap) = {
+        let (allocations, allocated, peak_heap) = counting::stop(counts, runs);
+        (Some(allocations), Some(allocated), Some(peak_heap))
+    };
+    #[cfg(not(feature = "count_allocations"))]
+    let (allocations, allocated, peak_heap) = (None, None, None);
+
+    Ok(Stats {
+        file: file.to_string(),
+        bytes: raw.len(),
+        tokens: tokens_count,
+        lex: lex / runs as u32,
+        parse: parse / runs as u32,
+        format: format / runs as u32,
+        allocations,
+        allocated,
+        peak_heap,
+    })
+}
+
+// Most memory the process had resident, only known on Linux
+pub fn peak_rss() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: usize = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(kb * 1024)
+}
+
+fn megabytes(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / 1e6)
+}
+
+pub fn table(stats: &[Stats], rss: Option<usize>) -> String {
+    let width = stats
+        .iter()
+        .map(|s| s.file.chars().count())
+        .chain([4])
+        .max()
+        .unwrap();
+
+    let mut result = format!(
+        "{:width$}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}\n",
+        "file",
+        "size",
+        "tokens",
+        "lex MB/s",
+        "parse MB/s",
+        "format MB/s",
+        "allocs",
+        "peak heap",
+        width = width
+    );
+
+    for s in stats {
+        writeln!(
+            result,
+            "{:width$}  {:>10}  {:>10}  {:>10.1}  {:>10.1}  {:>11.1}  {:>10}  {:>10}",
+            s.file,
+            megabytes(s.bytes),
+            s.tokens,
+            s.throughput(s.lex),
+            s.throughput(s.parse),
+            s.throughput(s.format),
+            s.allocations.map_or("-".to_string(), |a| a.to_string()),
+            s.peak_heap.map_or("-".to_string(), megabytes),
+            width = width
+        )
+        .unwrap();
+    }
+
+    if let Some(rss) = rss {
+        writeln!(result, "peak RSS: {}", megabytes(rss)).unwrap();
+    }
+
+    result
+}
+
+fn optional(value: Option<usize>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+pub fn json(stats: &[Stats], rss: Option<usize>) -> String {
+    let mut result = String::from("{\n    \"files\": [");
+
+    for (index, s) in stats.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        write!(
+            result,
+            "{}\n        {{\"file\": \"{}\", \"bytes\": {}, \"tokens\": {}, \"lex_mb_s\": {:.1}, \"parse_mb_s\": {:.1}, \"format_mb_s\": {:.1}, \"allocations\": {}, \"allocated_bytes\": {}, \"peak_heap_bytes\": {}}}",
+            separator,
+            escape(&s.file),
+            s.bytes,
+            s.tokens,
+            s.throughput(s.lex),
+            s.throughput(s.parse),
+            s.throughput(s.format),
+            optional(s.allocations),
+            optional(s.allocated),
+            optional(s.peak_heap)
+        )
+        .unwrap();
+    }
+
+    result + &format!("\n    ],\n    \"peak_rss_bytes\": {}\n}}\n", optional(rss))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> Stats {
+        Stats {
+            file: "a.json".to_string(),
+            bytes: 2_000_000,
+            tokens: 1000,
+            lex: Duration::from_millis(10),
+            parse: Duration::from_millis(20),
+            format: Duration::from_millis(40),
+            allocations: Some(12),
+            allocated: Some(3_000_000),
+            peak_heap: Some(2_500_000),
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let stats = run(
+            "a.json",
+            "{\"a\": [1, 2, \"x\"]}",
+            3,
+            parser::ParserOptions::default,
+        )
+        .unwrap();
+
+        assert_eq!(stats.bytes, 18);
+        assert_eq!(stats.tokens, 11);
+        assert_eq!(
+            stats.allocations.is_some_and(|a| a > 0),
+            cfg!(feature = "count_allocations")
+        );
+        assert!(run("b.json", "[1,", 1, parser::ParserOptions::default).is_err());
+
+        let commented = "[1, /* two */ 2,]";
+        assert!(run("c.json", commented, 1, parser::ParserOptions::default).is_err());
+        assert!(run("c.json", commented, 1, parser::ParserOptions::lenient).is_ok());
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            table(&[stats()], Some(5_000_000)),
+            "file          size      tokens    lex MB/s  parse MB/s  format MB/s      allocs   peak heap
+a.json      2.0 MB        1000       200.0       100.0         50.0          12      2.5 MB
+peak RSS: 5.0 MB
+"
+        );
+    }
+
+    #[test]
+    fn test_table_uncounted() {
+        let stats = Stats {
+            allocations: None,
+            allocated: None,
+            peak_heap: None,
+            ..stats()
+        };
+
+        assert_eq!(
+            table(&[stats], None),
+            "file          size      tokens    lex MB/s  parse MB/s  format MB/s      allocs   peak heap
+a.json      2.0 MB        1000       200.0       100.0         50.0           -           -
+"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            json(&[stats()], None),
+            "{
+    \"files\": [
+        {\"file\": \"a.json\", \"bytes\": 2000000, \"tokens\": 1000, \"lex_mb_s\": 200.0, \"parse_mb_s\": 100.0, \"format_mb_s\": 50.0, \"allocations\": 12, \"allocated_bytes\": 3000000, \"peak_heap_bytes\": 2500000}
+    ],
+    \"peak_rss_bytes\": null
+}
+"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+// A global allocator counting allocations and the bytes in use, shared by `joxide bench` with
+// the count_allocations feature and by the lockfile benchmark, which includes this file with
+// `#[path]`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(size: usize) {
+    ALLOCATED.fetch_add(size, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        grow(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+
+        if new_size > layout.size() {
+            grow(new_size - layout.size());
+        } else {
+            CURRENT.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+        }
+
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+pub struct Counts {
+    allocations: usize,
+    allocated: usize,
+    baseline: usize,
+}
+
+// Starts counting from the heap in use now
+pub fn start() -> Counts {
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    Counts {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        allocated: ALLOCATED.load(Ordering::Relaxed),
+        baseline,
+    }
+}
+
+// Allocations and bytes allocated per run, and the peak heap above the baseline
+pub fn stop(counts: Counts, runs: usize) -> (usize, usize, usize) {
+    (
+        (ALLOCATIONS.load(Ordering::Relaxed) - counts.allocations) / runs,
+        (ALLOCATED.load(Ordering::Relaxed) - counts.allocated) / runs,
+        PEAK.load(Ordering::Relaxed).saturating_sub(counts.baseline),
+    )
+}
@@ -0,0 +1,196 @@
+use escape;
+use number::Number;
+use parser::Json;
+use pointer;
+use std::fmt;
+
+// A NaN or infinite number, which RFC 8785 has no text for
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanonicalError {
+    pub pointer: String,
+}
+
+impl fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Non-finite number at '{}'", self.pointer)
+    }
+}
+
+impl std::error::Error for CanonicalError {}
+
+// The number as ECMAScript's `Number.prototype.toString` writes the nearest `f64`, the shortest
+// text that reads back as the same value, `None` when it is not finite
+pub fn number(n: &Number) -> Option<String> {
+    let n = n.as_f64();
+
+    if !n.is_finite() {
+        return None;
+    }
+
+    if n == 0.0 {
+        return Some("0".to_string());
+    }
+
+    // Like `1.2345e-7`, the digits and the power of ten of the first one
+    let scientific = format!("{:e}", n.abs());
+    let (mantissa, exponent) = scientific.split_once('e')?;
+    let digits = mantissa.replace('.', "");
+    let exponent: i32 = exponent.parse().ok()?;
+    let len = digits.len() as i32;
+    // Position of the decimal point after the first `point` digits
+    let point = exponent + 1;
+
+    let text = if len <= point && point <= 21 {
+        digits + &"0".repeat((point - len) as usize)
+    } else if 0 < point && point <= 21 {
+        format!(
+            "{}.{}",
+            &digits[..point as usize],
+            &digits[point as usize..]
+        )
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+
+        match digits.split_at(1) {
+            (first, "") => format!("{}e{}{}", first, sign, exponent.abs()),
+            (first, rest) => format!("{}.{}e{}{}", first, rest, sign, exponent.abs()),
+        }
+    };
+
+    Some(if n < 0.0 { format!("-{}", text) } else { text })
+}
+
+fn write(value: &Json, path: &str, out: &mut String) -> Result<(), CanonicalError> {
+    match value {
+        Json::Null | Json::Error => out.push_str("null"),
+        Json::Raw(_) => write(&value.parse_raw(), path, out)?,
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::Number(n) => out.push_str(&number(n).ok_or_else(|| CanonicalError {
+            pointer: path.to_string(),
+        })?),
+        Json::String(s) => {
+            out.push('"');
+            out.push_str(&escape::escape(s));
+            out.push('"');
+        }
+        Json::Array(arr) => {
+            out.push('[');
+
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                write(item, &pointer::push(path, &i.to_string()), out)?;
+            }
+
+            out.push(']');
+        }
+        Json::Object(obj) => {
+            let mut members: Vec<_> = obj.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+
+            for (i, (key, item)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                out.push('"');
+                out.push_str(&escape::escape(key));
+                out.push_str("\":");
+                write(item, &pointer::push(path, key), out)?;
+            }
+
+            out.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+// The RFC 8785 JSON Canonicalization Scheme text of the value, to sign or hash it: no whitespace,
+// keys sorted by their UTF-16 code units, numbers as `f64` written like JavaScript does, and
+// only quotes, backslashes and control characters escaped. `Json::Error` is written as null
+pub fn canonicalize(value: &Json) -> Result<String, CanonicalError> {
+    let mut out = String::new();
+    write(value, "", &mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    #[test]
+    fn test_number() {
+        // Examples of RFC 8785 Appendix B
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (5e-324, "5e-324"),
+            (-5e-324, "-5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (9007199254740992.0, "9007199254740992"),
+            (-9007199254740992.0, "-9007199254740992"),
+            (295147905179352830000.0, "295147905179352830000"),
+            (9.999999999999997e22, "9.999999999999997e+22"),
+            (1e23, "1e+23"),
+            (1e21, "1e+21"),
+            (999999999999999700000.0, "999999999999999700000"),
+            (999999999999999900000.0, "999999999999999900000"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (333333333.3333333, "333333333.3333333"),
+            (4.35, "4.35"),
+            (0.002, "0.002"),
+        ];
+
+        for (n, expected) in cases {
+            assert_eq!(number(&Number::from(n)).unwrap(), expected, "{}", n);
+        }
+
+        assert_eq!(number(&Number::from(f64::NAN)), None);
+        assert_eq!(
+            number(&Number::from(u64::MAX)).unwrap(),
+            "18446744073709552000"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let value = parser::parse_str(
+            r#"{
+                "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+                "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+                "literals": [null, true, false]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            canonicalize(&value).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        let keys = parser::parse_str(
+            r#"{"\u20ac": 1, "\r": 2, "\ufb33": 3, "1": 4, "\ud83d\ude00": 5, "\u0080": 6, "\u00f6": 7}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonicalize(&keys).unwrap(),
+            "{\"\\r\":2,\"1\":4,\"\u{80}\":6,\"ö\":7,\"€\":1,\"😀\":5,\"\u{fb33}\":3}"
+        );
+
+        let infinite = Json::Array(vec![Json::Number(Number::from(f64::INFINITY))]);
+        assert_eq!(
+            canonicalize(&infinite),
+            Err(CanonicalError {
+                pointer: "/0".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,158 @@
+// Syntax highlighting of formatted JSON with ANSI escape codes
+
+const RESET: &str = "\x1b[0m";
+
+// SGR parameters of each kind of token, like "1;34" for bold blue
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub key: String,
+    pub string: String,
+    pub number: String,
+    pub literal: String,
+}
+
+// The names `[colors] theme` accepts in the config file
+#[cfg(feature = "toml")]
+pub const THEMES: [&str; 3] = ["default", "high-contrast", "light"];
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Theme> {
+        let (key, string, number, literal) = match name {
+            "default" => ("34", "32", "33", "35"),
+            "high-contrast" => ("1;97", "1;92", "1;93", "1;96"),
+            // Without yellow, which is hard to read on a white background
+            "light" => ("1;34", "32", "31", "35"),
+            _ => return None,
+        };
+
+        Some(Theme {
+            key: key.to_string(),
+            string: string.to_string(),
+            number: number.to_string(),
+            literal: literal.to_string(),
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_name("default").unwrap()
+    }
+}
+
+#[cfg(feature = "toml")]
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+// Reads a style like "bold bright-blue" or raw SGR parameters like "38;5;208"
+#[cfg(feature = "toml")]
+pub fn parse_style(style: &str) -> Option<String> {
+    let mut codes = vec![];
+
+    for word in style.split_whitespace() {
+        let code = match word {
+            "bold" => "1".to_string(),
+            "dim" => "2".to_string(),
+            "italic" => "3".to_string(),
+            "underline" => "4".to_string(),
+            _ if word.bytes().all(|b| b.is_ascii_digit() || b == b';') => word.to_string(),
+            _ => match word.strip_prefix("bright-") {
+                Some(color) => (90 + COLORS.iter().position(|c| *c == color)?).to_string(),
+                None => (30 + COLORS.iter().position(|c| *c == word)?).to_string(),
+            },
+        };
+
+        codes.push(code);
+    }
+
+    if codes.is_empty() {
+        None
+    } else {
+        Some(codes.join(";"))
+    }
+}
+
+// Expects the output of the formatter, strings are the only place punctuation can appear in
+pub fn colorize(formatted: &str, theme: &Theme) -> String {
+    let mut result = String::with_capacity(formatted.len() * 2);
+    let mut chars = formatted.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let end = match c {
+            '"' => {
+                let mut escaped = false;
+                let mut end = formatted.len();
+
+                for (i, c) in chars.by_ref() {
+                    match c {
+                        '"' if !escaped => {
+                            end = i + 1;
+                            break;
+                        }
+                        '\\' => escaped = !escaped,
+                        _ => escaped = false,
+                    }
+                }
+
+                end
+            }
+            c if c == '-' || c.is_ascii_alphanumeric() => {
+                while chars
+                    .next_if(|(_, c)| {
+                        *c == '-' || *c == '+' || *c == '.' || c.is_ascii_alphanumeric()
+                    })
+                    .is_some()
+                {}
+
+                chars.peek().map_or(formatted.len(), |(i, _)| *i)
+            }
+            _ => {
+                result.push(c);
+                continue;
+            }
+        };
+
+        let text = &formatted[start..end];
+        let color = match c {
+            '"' if formatted[end..].trim_start().starts_with(':') => &theme.key,
+            '"' => &theme.string,
+            't' | 'f' | 'n' => &theme.literal,
+            _ => &theme.number,
+        };
+
+        result += "\x1b[";
+        result += color;
+        result += "m";
+        result += text;
+        result += RESET;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorize() {
+        assert_eq!(
+            colorize(
+                "{\"a\": [\"x:\\\"\", -1.5e+3, null]}",
+                &Theme::default()
+            ),
+            "{\x1b[34m\"a\"\x1b[0m: [\x1b[32m\"x:\\\"\"\x1b[0m, \x1b[33m-1.5e+3\x1b[0m, \x1b[35mnull\x1b[0m]}"
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(parse_style("bold bright-blue"), Some("1;94".to_string()));
+        assert_eq!(parse_style("38;5;208"), Some("38;5;208".to_string()));
+        assert_eq!(parse_style("red"), Some("31".to_string()));
+        assert_eq!(parse_style("purple"), None);
+        assert_eq!(parse_style(""), None);
+    }
+}
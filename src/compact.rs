@@ -0,0 +1,416 @@
+use number::Number;
+use parser::{self, Json, Map, OwnedParseError, ParseErrorType, DEFAULT_MAX_DEPTH};
+use pointer;
+use reader::{EventType, Reader};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// A read only document for large inputs with many repeated keys and values, like
+// `package-lock.json`. It is built from `Reader` events as they are lexed, so the tokens are never
+// all held at once. Values are 12 byte nodes in a single vector instead of a tree of `Json`, and
+// each distinct key and string is kept once however often it repeats
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Null,
+    Bool(bool),
+    // Index into `numbers`
+    Number(u32),
+    // Index into `strings`, for string values and for the key that comes before each member value
+    String(u32),
+    Key(u32),
+    // Number of items or members, and the index of the node after the last of them
+    Array(u32, u32),
+    Object(u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document<'a> {
+    // Depth first, each array or object followed by its items, or by the key and value of each of
+    // its members
+    nodes: Vec<Node>,
+    // Distinct keys and strings, borrowing the input when they have no escapes
+    strings: Vec<Cow<'a, str>>,
+    numbers: Vec<Number<'a>>,
+}
+
+// An id that fits in a node, documents have fewer than 2^32 values unless they are many GB
+fn id(len: usize, line: usize, col: usize) -> Result<u32, OwnedParseError> {
+    u32::try_from(len).map_err(|_| parser::error_at(ParseErrorType::MemoryLimitExceeded, line, col))
+}
+
+// The id of a key or string, the one it was given before when it has been seen already
+fn intern<'a>(
+    strings: &mut Vec<Cow<'a, str>>,
+    interned: &mut HashMap<Cow<'a, str>, u32>,
+    s: Cow<'a, str>,
+    line: usize,
+    col: usize,
+) -> Result<u32, OwnedParseError> {
+    if let Some(&string) = interned.get(&s) {
+        return Ok(string);
+    }
+
+    let string = id(strings.len(), line, col)?;
+    strings.push(s.clone());
+    interned.insert(s, string);
+    Ok(string)
+}
+
+// Parses with the default options like `parse_str`, with the same errors
+pub fn parse(raw: &str) -> Result<Document<'_>, OwnedParseError> {
+    let mut document = Document {
+        nodes: vec![],
+        strings: vec![],
+        numbers: vec![],
+    };
+
+    // Only needed while parsing, to find the id of a string seen before
+    let mut interned: HashMap<Cow<str>, u32> = HashMap::new();
+    // The open arrays and objects, with where the key ids of an object start in `keys`
+    let mut stack: Vec<(usize, usize)> = vec![];
+    let mut keys: Vec<u32> = vec![];
+
+    for event in Reader::new(raw) {
+        let event = event?;
+        let (line, col) = (event.line, event.col);
+
+        // Items of an array are counted when they start, members of an object when their key is
+        // read
+        let counted = match event.event_type {
+            EventType::Key(_) => true,
+            EventType::EndObject | EventType::EndArray => false,
+            _ => matches!(
+                stack.last().map(|&(index, _)| document.nodes[index]),
+                Some(Node::Array(..))
+            ),
+        };
+
+        if let (true, Some(&(index, _))) = (counted, stack.last()) {
+            if let Node::Array(len, _) | Node::Object(len, _) = &mut document.nodes[index] {
+                *len += 1;
+            }
+        }
+
+        let node = match event.event_type {
+            EventType::StartObject | EventType::StartArray => {
+                if stack.len() >= DEFAULT_MAX_DEPTH {
+                    return Err(parser::error_at(
+                        ParseErrorType::DepthLimitExceeded,
+                        line,
+                        col,
+                    ));
+                }
+
+                stack.push((document.nodes.len(), keys.len()));
+
+                match event.event_type {
+                    EventType::StartObject => Node::Object(0, 0),
+                    _ => Node::Array(0, 0),
+                }
+            }
+            EventType::EndObject | EventType::EndArray => {
+                let (index, start) = stack.pop().unwrap();
+                let next = id(document.nodes.len(), line, col)?;
+
+                if let Node::Array(_, end) | Node::Object(_, end) = &mut document.nodes[index] {
+                    *end = next;
+                }
+
+                // Keys are not kept with their position, so the input is read again by `parse_str`
+                // for the error at the duplicate
+                let members = &mut keys[start..];
+                members.sort_unstable();
+
+                if members.windows(2).any(|pair| pair[0] == pair[1]) {
+                    return Err(parser::parse_str(raw).err().unwrap());
+                }
+
+                keys.truncate(start);
+                continue;
+            }
+            EventType::Key(s) => {
+                let key = intern(&mut document.strings, &mut interned, s, line, col)?;
+                keys.push(key);
+                Node::Key(key)
+            }
+            EventType::String(s) => {
+                Node::String(intern(&mut document.strings, &mut interned, s, line, col)?)
+            }
+            EventType::Null => Node::Null,
+            EventType::Bool(b) => Node::Bool(b),
+            EventType::Number(n) => {
+                document.numbers.push(n);
+                Node::Number(id(document.numbers.len() - 1, line, col)?)
+            }
+        };
+
+        id(document.nodes.len(), line, col)?;
+        document.nodes.push(node);
+    }
+
+    document.nodes.shrink_to_fit();
+    document.strings.shrink_to_fit();
+    document.numbers.shrink_to_fit();
+    Ok(document)
+}
+
+impl<'a> Document<'a> {
+    pub fn root(&self) -> Value<'_, 'a> {
+        Value {
+            document: self,
+            index: 0,
+        }
+    }
+
+    // Number of distinct keys and strings
+    pub fn strings(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn to_json(&self) -> Json<'a> {
+        self.root().to_json()
+    }
+}
+
+// A value of a `Document`, which is cheap to copy
+#[derive(Debug, Clone, Copy)]
+pub struct Value<'d, 'a> {
+    document: &'d Document<'a>,
+    index: usize,
+}
+
+impl<'d, 'a> Value<'d, 'a> {
+    fn node(&self) -> Node {
+        self.document.nodes[self.index]
+    }
+
+    fn at(&self, index: usize) -> Value<'d, 'a> {
+        Value {
+            document: self.document,
+            index,
+        }
+    }
+
+    // Index of the node after this value and everything in it
+    fn end(&self) -> usize {
+        match self.node() {
+            Node::Array(_, end) | Node::Object(_, end) => end as usize,
+            _ => self.index + 1,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.node() == Node::Null
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node() {
+            Node::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&'d Number<'a>> {
+        match self.node() {
+            Node::Number(number) => Some(&self.document.numbers[number as usize]),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'d str> {
+        match self.node() {
+            Node::String(string) => Some(&self.document.strings[string as usize]),
+            _ => None,
+        }
+    }
+
+    // Number of items of an array or members of an object
+    pub fn len(&self) -> Option<usize> {
+        match self.node() {
+            Node::Array(len, _) | Node::Object(len, _) => Some(len as usize),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    // Items of an array, nothing for other values
+    pub fn items(&self) -> impl Iterator<Item = Value<'d, 'a>> {
+        let (len, first) = match self.node() {
+            Node::Array(len, _) => (len as usize, self.index + 1),
+            _ => (0, self.index + 1),
+        };
+
+        let mut next = self.at(first);
+
+        (0..len).map(move |_| {
+            let item = next;
+            next = item.at(item.end());
+            item
+        })
+    }
+
+    // Key ids and values of the members of an object, nothing for other values
+    fn entries(&self) -> impl Iterator<Item = (u32, Value<'d, 'a>)> {
+        let (len, first) = match self.node() {
+            Node::Object(len, _) => (len as usize, self.index + 1),
+            _ => (0, self.index + 1),
+        };
+
+        let mut next = self.at(first);
+
+        (0..len).map(move |_| {
+            let key = match next.node() {
+                Node::Key(key) => key,
+                _ => unreachable!(),
+            };
+
+            let value = next.at(next.index + 1);
+            next = value.at(value.end());
+            (key, value)
+        })
+    }
+
+    // Keys and values of an object in the order of the input, nothing for other values
+    pub fn members(&self) -> impl Iterator<Item = (&'d str, Value<'d, 'a>)> {
+        let strings = &self.document.strings;
+        self.entries()
+            .map(move |(key, value)| (&*strings[key as usize], value))
+    }
+
+    // The value of a key of an object, found by going through the members
+    pub fn get(&self, key: &str) -> Option<Value<'d, 'a>> {
+        self.members()
+            .find(|&(member, _)| member == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn index(&self, index: usize) -> Option<Value<'d, 'a>> {
+        self.items().nth(index)
+    }
+
+    // Looks up a value by JSON pointer, like `Json::pointer`
+    pub fn pointer(&self, pointer: &str) -> Option<Value<'d, 'a>> {
+        let mut current = *self;
+
+        for token in pointer::split(pointer)? {
+            current = match current.node() {
+                Node::Object(..) => current.get(&token)?,
+                Node::Array(..) => current.index(pointer::index(&token)?)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    // Nesting is limited by `DEFAULT_MAX_DEPTH` when parsing, so the recursion stays within the
+    // stack
+    pub fn to_json(&self) -> Json<'a> {
+        match self.node() {
+            Node::Null => Json::Null,
+            Node::Bool(b) => Json::Bool(b),
+            Node::Number(_) => Json::Number(self.as_number().unwrap().clone()),
+            Node::String(string) => Json::String(self.document.strings[string as usize].clone()),
+            Node::Array(..) => Json::Array(self.items().map(|item| item.to_json()).collect()),
+            Node::Object(..) => Json::Object(
+                self.entries()
+                    .map(|(key, value)| {
+                        let key = self.document.strings[key as usize].clone();
+                        (key, value.to_json())
+                    })
+                    .collect::<Map<_, _>>(),
+            ),
+            // Values are never keys
+            Node::Key(_) => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn test_parse() {
+        let raw = r#"{"a": [1, "x", null, true], "b": {"c": "x"}, "d": []}"#;
+        let document = parse(raw).unwrap();
+        let root = document.root();
+
+        assert_eq!(document.to_json(), parser::parse_str(raw).unwrap());
+        assert_eq!(root.len(), Some(3));
+        assert_eq!(root.pointer("/a/1").unwrap().as_str(), Some("x"));
+        assert_eq!(
+            root.pointer("/a/0").unwrap().as_number(),
+            Some(&Number::PosInt(1))
+        );
+        assert!(root.pointer("/a/2").unwrap().is_null());
+        assert_eq!(root.pointer("/a/3").unwrap().as_bool(), Some(true));
+        assert_eq!(root.pointer("/b/c").unwrap().as_str(), Some("x"));
+        assert_eq!(root.pointer("/d").unwrap().is_empty(), Some(true));
+        assert!(root.pointer("/a/4").is_none());
+        assert!(root.pointer("/e").is_none());
+
+        let keys: Vec<_> = root.members().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_parse_scalar() {
+        let document = parse(" \"\\u00e9\" ").unwrap();
+
+        assert_eq!(document.root().as_str(), Some("é"));
+        assert_eq!(document.root().len(), None);
+        assert_eq!(document.root().items().count(), 0);
+    }
+
+    #[test]
+    fn test_interning() {
+        let document = parse(r#"[{"v": "1.0"}, {"v": "1.0"}, {"v": "2.0", "w": "v"}]"#).unwrap();
+
+        // `v` and `1.0` are kept once, and the key `v` and the string `v` are the same string
+        assert_eq!(document.strings(), 4);
+        assert_eq!(document.root().pointer("/2/w").unwrap().as_str(), Some("v"));
+        assert!(matches!(
+            document
+                .to_json()
+                .pointer("/0")
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .keys()
+                .next(),
+            Some(Cow::Borrowed("v"))
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for raw in [
+            "{\"a\": 1,}",
+            "[1 2]",
+            "{\"a\": 1, \"b\": {\"c\": 2, \"c\": 3}}",
+            "\"a",
+            "[] []",
+            "",
+        ] {
+            assert_eq!(parse(raw).unwrap_err(), parser::parse_str(raw).unwrap_err());
+        }
+
+        let deep = "[".repeat(DEFAULT_MAX_DEPTH + 1) + &"]".repeat(DEFAULT_MAX_DEPTH + 1);
+        assert_eq!(
+            parse(&deep).unwrap_err(),
+            parser::parse_str(&deep).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_node_size() {
+        assert_eq!(mem::size_of::<Node>(), 12);
+    }
+}
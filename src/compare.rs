@@ -0,0 +1,211 @@
+use parser::{Json, Map};
+use pointer;
+use std::borrow::Cow;
+use std::fmt;
+
+// How loosely `semantic_eq` compares values. The default only ignores formatting: key order and
+// how numbers are written, so `1.0` equals `1`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EqOptions<'a> {
+    // Numbers are equal when they differ by at most this much
+    pub epsilon: f64,
+    // Arrays are equal when they hold the same items in any order, each as many times
+    pub ignore_array_order: bool,
+    // A member set to `null` equals a missing one
+    pub null_equals_missing: bool,
+    // Values at these JSON pointers, where `*` matches any key or index, are equal whatever they
+    // are and members there may be missing. Items of arrays compared in any order are at their
+    // index in `a`
+    pub ignore_paths: &'a [String],
+}
+
+pub fn semantic_eq(a: &Json, b: &Json, options: EqOptions) -> bool {
+    eq_at(a, b, "", options)
+}
+
+pub(crate) fn ignored(path: &str, ignore_paths: &[String]) -> bool {
+    ignore_paths
+        .iter()
+        .any(|pattern| pointer::matches(pattern, path))
+}
+
+// The pointer of a member or item, only built when there are paths to ignore
+fn step(path: &str, token: impl fmt::Display, options: EqOptions) -> String {
+    if options.ignore_paths.is_empty() {
+        String::new()
+    } else {
+        pointer::push(path, &token.to_string())
+    }
+}
+
+fn eq_at(a: &Json, b: &Json, path: &str, options: EqOptions) -> bool {
+    if ignored(path, options.ignore_paths) {
+        return true;
+    }
+
+    match (a, b) {
+        (Json::Raw(_), _) | (_, Json::Raw(_)) => {
+            eq_at(&a.parse_raw(), &b.parse_raw(), path, options)
+        }
+        (Json::Null, Json::Null) | (Json::Error, Json::Error) => true,
+        (Json::Bool(a), Json::Bool(b)) => a == b,
+        (Json::Number(a), Json::Number(b)) => {
+            a == b || (options.epsilon > 0.0 && (a.as_f64() - b.as_f64()).abs() <= options.epsilon)
+        }
+        (Json::String(a), Json::String(b)) => a == b,
+        (Json::Array(a), Json::Array(b)) if options.ignore_array_order => {
+            same_items(a, b, path, options)
+        }
+        (Json::Array(a), Json::Array(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .enumerate()
+                    .all(|(i, (a, b))| eq_at(a, b, &step(path, i, options), options))
+        }
+        (Json::Object(a), Json::Object(b)) => same_members(a, b, path, options),
+        _ => false,
+    }
+}
+
+// Each item is paired with a different one of the other array
+fn same_items(a: &[Json], b: &[Json], path: &str, options: EqOptions) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut used = vec![false; b.len()];
+
+    a.iter().enumerate().all(|(i, item)| {
+        let item_path = step(path, i, options);
+        let found = (0..b.len()).find(|&j| !used[j] && eq_at(item, &b[j], &item_path, options));
+
+        match found {
+            Some(i) => {
+                used[i] = true;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+// Every member of `a` has an equal one in `b`
+fn contained(
+    a: &Map<Cow<str>, Json>,
+    b: &Map<Cow<str>, Json>,
+    path: &str,
+    options: EqOptions,
+) -> bool {
+    a.iter().all(|(key, item)| {
+        let member = step(path, key, options);
+
+        match b.get(key) {
+            Some(other) => eq_at(item, other, &member, options),
+            None => {
+                (options.null_equals_missing && matches!(item, Json::Null))
+                    || ignored(&member, options.ignore_paths)
+            }
+        }
+    })
+}
+
+fn same_members(
+    a: &Map<Cow<str>, Json>,
+    b: &Map<Cow<str>, Json>,
+    path: &str,
+    options: EqOptions,
+) -> bool {
+    if !options.null_equals_missing && options.ignore_paths.is_empty() && a.len() != b.len() {
+        return false;
+    }
+
+    contained(a, b, path, options) && contained(b, a, path, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse_str;
+
+    fn eq(a: &str, b: &str, options: EqOptions) -> bool {
+        semantic_eq(&parse_str(a).unwrap(), &parse_str(b).unwrap(), options)
+    }
+
+    #[test]
+    fn test_semantic_eq() {
+        let strict = EqOptions::default();
+        assert!(eq(
+            r#"{"a": 1, "b": "é"}"#,
+            r#"{"b": "é", "a": 1.0}"#,
+            strict
+        ));
+        assert!(!eq("[1, 2]", "[2, 1]", strict));
+        assert!(!eq("0.1", "0.10001", strict));
+        assert!(!eq(r#"{"a": null}"#, "{}", strict));
+        assert!(!eq("[null]", "[]", strict));
+
+        let epsilon = EqOptions {
+            epsilon: 0.001,
+            ..strict
+        };
+        assert!(eq("0.1", "0.10001", epsilon));
+        assert!(!eq("0.1", "0.2", epsilon));
+
+        let unordered = EqOptions {
+            ignore_array_order: true,
+            ..strict
+        };
+        assert!(eq("[1, [2, 3], 1]", "[[3, 2], 1, 1]", unordered));
+        assert!(!eq("[1, 1, 2]", "[1, 2, 2]", unordered));
+        assert!(!eq("[1]", "[1, 1]", unordered));
+
+        let missing = EqOptions {
+            null_equals_missing: true,
+            ..strict
+        };
+        assert!(eq(
+            r#"{"a": null, "b": {"c": null}}"#,
+            r#"{"b": {}}"#,
+            missing
+        ));
+        assert!(!eq(r#"{"a": 1}"#, "{}", missing));
+    }
+
+    #[test]
+    fn test_ignore_paths() {
+        let ignore_paths = ["/generatedAt".to_string(), "/items/*/id".to_string()];
+        let ignoring = EqOptions {
+            ignore_paths: &ignore_paths,
+            ..EqOptions::default()
+        };
+
+        assert!(eq(
+            r#"{"generatedAt": 1, "items": [{"id": 1, "a": true}, {"id": 2}]}"#,
+            r#"{"items": [{"id": 3, "a": true}, {}]}"#,
+            ignoring
+        ));
+        assert!(!eq(
+            r#"{"items": [{"id": 1, "a": true}]}"#,
+            r#"{"items": [{"id": 1, "a": false}]}"#,
+            ignoring
+        ));
+        assert!(!eq(
+            r#"{"items": [{"id": 1}]}"#,
+            r#"{"items": []}"#,
+            ignoring
+        ));
+        assert!(!eq(r#"{"other": 1}"#, "{}", ignoring));
+        assert!(!eq(r#"{"generatedAt": 1}"#, "{}", EqOptions::default()));
+
+        let unordered = EqOptions {
+            ignore_array_order: true,
+            ..ignoring
+        };
+        assert!(eq(
+            r#"{"items": [{"id": 1, "a": 1}, {"id": 2, "a": 2}]}"#,
+            r#"{"items": [{"id": 3, "a": 2}, {"id": 4, "a": 1}]}"#,
+            unordered
+        ));
+    }
+}
@@ -0,0 +1,368 @@
+#[cfg(feature = "toml")]
+use color;
+use color::Theme;
+use joxide::diagnostic::MessageId;
+use joxide::formatter::ArraySort;
+use joxide::merge::OnConflict;
+use joxide::pointer;
+use std::collections::HashMap;
+use std::path::Path;
+#[cfg(feature = "toml")]
+use toml::{Table, Value};
+
+pub const DEFAULT_CONFIG_FILE: &str = "joxide.toml";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    messages: HashMap<&'static str, String>,
+    pub indent_length: Option<usize>,
+    pub sort_keys: bool,
+    pub sort_arrays: Vec<ArraySort>,
+    pub theme: Theme,
+    pub on_conflict: Option<OnConflict>,
+    // Pointer patterns with what to do with the conflicts at them, ahead of `on_conflict`
+    pub on_conflict_at: Vec<(String, OnConflict)>,
+    // The dialect of the files, like `--allow-comments` and `--allow-trailing-commas`
+    pub allow_comments: bool,
+    pub allow_trailing_commas: bool,
+}
+
+impl Config {
+    #[cfg(feature = "toml")]
+    pub fn parse(content: &str) -> Result<Config, String> {
+        let table: Table = content.parse().map_err(|err| format!("{}", err))?;
+        let mut config = Config::default();
+
+        if let Some(format) = table.get("format") {
+            let format = match format {
+                Value::Table(format) => format,
+                _ => return Err("'format' should be a table".to_string()),
+            };
+
+            for (key, value) in format {
+                match (key.as_str(), value) {
+                    ("indent-length", Value::Integer(n)) if *n >= 0 => {
+                        config.indent_length = Some(*n as usize)
+                    }
+                    ("indent-length", _) => {
+                        return Err("'indent-length' should be a number of spaces".to_string())
+                    }
+                    ("sort-keys", Value::Boolean(b)) => config.sort_keys = *b,
+                    ("sort-keys", _) => return Err("'sort-keys' should be a boolean".to_string()),
+                    ("sort-arrays", Value::Table(sorts)) => {
+                        for (array, key) in sorts {
+                            let key = match key {
+                                Value::String(key)
+                                    if pointer::split(array).is_some()
+                                        && pointer::split(key).is_some() =>
+                                {
+                                    key
+                                }
+                                _ => {
+                                    return Err(format!(
+                                        "'{}' in [format.sort-arrays] should map a JSON pointer to a JSON pointer",
+                                        array
+                                    ))
+                                }
+                            };
+
+                            config.sort_arrays.push(ArraySort {
+                                array: array.clone(),
+                                key: key.clone(),
+                            });
+                        }
+                    }
+                    ("sort-arrays", _) => return Err("'sort-arrays' should be a table".to_string()),
+                    _ => return Err(format!("Unknown option '{}' in [format]", key)),
+                }
+            }
+        }
+
+        if let Some(parse) = table.get("parse") {
+            let parse = match parse {
+                Value::Table(parse) => parse,
+                _ => return Err("'parse' should be a table".to_string()),
+            };
+
+            for (key, value) in parse {
+                let allowed = match key.as_str() {
+                    "allow-comments" => &mut config.allow_comments,
+                    "allow-trailing-commas" => &mut config.allow_trailing_commas,
+                    _ => return Err(format!("Unknown option '{}' in [parse]", key)),
+                };
+
+                *allowed = match value {
+                    Value::Boolean(b) => *b,
+                    _ => return Err(format!("'{}' should be a boolean", key)),
+                };
+            }
+        }
+
+        if let Some(merge) = table.get("merge") {
+            let merge = match merge {
+                Value::Table(merge) => merge,
+                _ => return Err("'merge' should be a table".to_string()),
+            };
+            let on_conflict = |key: &str, value: &Value| {
+                match value {
+                    Value::String(name) => OnConflict::from_name(name),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("'{}' should be \"error\", \"ours\" or \"theirs\"", key))
+            };
+
+            for (key, value) in merge {
+                match (key.as_str(), value) {
+                    ("on-conflict", _) => config.on_conflict = Some(on_conflict(key, value)?),
+                    ("on-conflict-at", Value::Table(overrides)) => {
+                        for (pattern, value) in overrides {
+                            if pointer::split(pattern).is_none() {
+                                return Err(format!(
+                                    "'{}' in [merge.on-conflict-at] should be a JSON pointer",
+                                    pattern
+                                ));
+                            }
+
+                            config
+                                .on_conflict_at
+                                .push((pattern.clone(), on_conflict(pattern, value)?));
+                        }
+                    }
+                    ("on-conflict-at", _) => {
+                        return Err("'on-conflict-at' should be a table".to_string())
+                    }
+                    _ => return Err(format!("Unknown option '{}' in [merge]", key)),
+                }
+            }
+        }
+
+        if let Some(colors) = table.get("colors") {
+            let colors = match colors {
+                Value::Table(colors) => colors,
+                _ => return Err("'colors' should be a table".to_string()),
+            };
+
+            // The theme first, the colors of single tokens override it
+            match colors.get("theme") {
+                None => {}
+                Some(Value::String(name)) => {
+                    config.theme = Theme::from_name(name).ok_or_else(|| {
+                        format!(
+                            "Unknown theme '{}', expected {}",
+                            name,
+                            color::THEMES.join(", ")
+                        )
+                    })?
+                }
+                Some(_) => return Err("'theme' should be a string".to_string()),
+            }
+
+            for (key, value) in colors {
+                let style = match key.as_str() {
+                    "theme" => continue,
+                    "key" => &mut config.theme.key,
+                    "string" => &mut config.theme.string,
+                    "number" => &mut config.theme.number,
+                    "literal" => &mut config.theme.literal,
+                    _ => return Err(format!("Unknown option '{}' in [colors]", key)),
+                };
+
+                *style = match value {
+                    Value::String(value) => color::parse_style(value),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    format!(
+                        "'{}' should be a style like \"bold blue\" or \"38;5;208\"",
+                        key
+                    )
+                })?;
+            }
+        }
+
+        if let Some(messages) = table.get("messages") {
+            let messages = match messages {
+                Value::Table(messages) => messages,
+                _ => return Err("'messages' should be a table".to_string()),
+            };
+
+            for (code, template) in messages {
+                let id = match MessageId::from_code(code) {
+                    Some(id) => id,
+                    None => return Err(format!("Unknown error code '{}' in [messages]", code)),
+                };
+
+                match template {
+                    Value::String(template) => config.messages.insert(id.code(), template.clone()),
+                    _ => {
+                        return Err(format!(
+                            "Message template for '{}' should be a string",
+                            code
+                        ))
+                    }
+                };
+            }
+        }
+
+        Ok(config)
+    }
+
+    #[cfg(not(feature = "toml"))]
+    pub fn parse(_content: &str) -> Result<Config, String> {
+        Err("joxide was built without the toml feature".to_string())
+    }
+
+    // Reads the given config file, or joxide.toml from the working directory if it exists
+    pub fn load(path: Option<&str>) -> Result<Config, String> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => DEFAULT_CONFIG_FILE,
+            None => return Ok(Config::default()),
+        };
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to open config file {}, reason: {}", path, err))?;
+
+        Config::parse(&content).map_err(|err| format!("Invalid config file {}: {}", path, err))
+    }
+
+    pub fn message(&self, id: MessageId) -> Option<&str> {
+        self.messages.get(id.code()).map(String::as_str)
+    }
+
+    // What to do with a merge conflict at the pointer, by the first pattern matching it
+    pub fn on_conflict_at(&self, at: &str) -> Option<OnConflict> {
+        self.on_conflict_at
+            .iter()
+            .find(|(pattern, _)| pointer::matches(pattern, at))
+            .map(|(_, on_conflict)| *on_conflict)
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            "[messages]\nduplicate-key = \"Duplicate '{token}', see https://wiki/json\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.indent_length, None);
+        assert_eq!(
+            config.message(MessageId::DuplicateKey),
+            Some("Duplicate '{token}', see https://wiki/json")
+        );
+        assert_eq!(config.message(MessageId::TrailingComma), None);
+
+        let config = Config::parse("[format]\nindent-length = 2\n").unwrap();
+        assert_eq!(config.indent_length, Some(2));
+
+        let config = Config::parse(
+            "[format]\nsort-keys = true\n[format.sort-arrays]\n\"/contributors\" = \"/name\"\n",
+        )
+        .unwrap();
+        assert!(config.sort_keys);
+        assert_eq!(
+            config.sort_arrays,
+            [ArraySort {
+                array: "/contributors".to_string(),
+                key: "/name".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_merge() {
+        let config = Config::parse(
+            "[merge]\non-conflict = \"ours\"\n[merge.on-conflict-at]\n\"/version\" = \"theirs\"\n\"/items/*/id\" = \"error\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.on_conflict, Some(OnConflict::Ours));
+        assert_eq!(config.on_conflict_at("/version"), Some(OnConflict::Theirs));
+        assert_eq!(
+            config.on_conflict_at("/items/3/id"),
+            Some(OnConflict::Error)
+        );
+        assert_eq!(config.on_conflict_at("/items/3"), None);
+    }
+
+    #[test]
+    fn test_parse_dialect() {
+        let config = Config::parse(
+            "[parse]
+allow-comments = true
+",
+        )
+        .unwrap();
+
+        assert!(config.allow_comments);
+        assert!(!config.allow_trailing_commas);
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        let config =
+            Config::parse("[colors]\ntheme = \"light\"\nkey = \"bold bright-cyan\"\n").unwrap();
+
+        assert_eq!(
+            config.theme,
+            Theme {
+                key: "1;96".to_string(),
+                ..Theme::from_name("light").unwrap()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = vec![
+            ("messages = 1", "'messages' should be a table"),
+            (
+                "[format]\nindent-length = -1",
+                "'indent-length' should be a number of spaces",
+            ),
+            ("[format]\ntabs = true", "Unknown option 'tabs' in [format]"),
+            (
+                "[format.sort-arrays]\n\"/a\" = 1",
+                "'/a' in [format.sort-arrays] should map a JSON pointer to a JSON pointer",
+            ),
+            (
+                "[parse]\nallow-comments = 1",
+                "'allow-comments' should be a boolean",
+            ),
+            ("[parse]\njson5 = true", "Unknown option 'json5' in [parse]"),
+            (
+                "[merge]\non-conflict = \"mine\"",
+                "'on-conflict' should be \"error\", \"ours\" or \"theirs\"",
+            ),
+            (
+                "[merge.on-conflict-at]\nversion = \"ours\"",
+                "'version' in [merge.on-conflict-at] should be a JSON pointer",
+            ),
+            (
+                "[colors]\ntheme = \"dark\"",
+                "Unknown theme 'dark', expected default, high-contrast, light",
+            ),
+            (
+                "[colors]\nnumber = \"orange\"",
+                "'number' should be a style like \"bold blue\" or \"38;5;208\"",
+            ),
+            (
+                "[messages]\nno-such-code = \"x\"",
+                "Unknown error code 'no-such-code' in [messages]",
+            ),
+            (
+                "[messages]\ntrailing-comma = 1",
+                "Message template for 'trailing-comma' should be a string",
+            ),
+        ];
+
+        for (content, expected) in cases {
+            assert_eq!(Config::parse(content), Err(expected.to_string()));
+        }
+    }
+}
@@ -0,0 +1,623 @@
+use super::{
+    as_integer, ConversionError, ConversionErrorType, DecodeError, DecodeErrorType, Reader,
+};
+use number::Number;
+use parser::{Json, Map};
+use pointer;
+use std::borrow::Cow;
+
+const FORMAT: &str = "BSON";
+
+const DOUBLE: u8 = 0x01;
+const STRING: u8 = 0x02;
+const DOCUMENT: u8 = 0x03;
+const ARRAY: u8 = 0x04;
+const OBJECT_ID: u8 = 0x07;
+const BOOL: u8 = 0x08;
+const DATETIME: u8 = 0x09;
+const NULL: u8 = 0x0a;
+const INT32: u8 = 0x10;
+const INT64: u8 = 0x12;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// Days since the unix epoch for a proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400;
+
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn digits(text: &str, start: usize, length: usize) -> Option<i64> {
+    let digits = text.get(start..start + length)?;
+
+    if digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    }
+}
+
+// Parses `YYYY-MM-DDTHH:MM:SS[.fff][Z|+HH:MM|-HH:MM]` into milliseconds since the epoch
+fn parse_iso8601(text: &str) -> Option<i64> {
+    let bytes = text.as_bytes();
+
+    for (index, separator) in [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')] {
+        if bytes.get(index) != Some(&separator) {
+            return None;
+        }
+    }
+
+    let (year, month, day) = (
+        digits(text, 0, 4)?,
+        digits(text, 5, 2)?,
+        digits(text, 8, 2)?,
+    );
+    let (hour, minute, second) = (
+        digits(text, 11, 2)?,
+        digits(text, 14, 2)?,
+        digits(text, 17, 2)?,
+    );
+
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    let mut rest = &text[19..];
+    let mut millis = 0;
+
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let length = fraction.bytes().take_while(u8::is_ascii_digit).count();
+
+        if length == 0 {
+            return None;
+        }
+
+        let padded = format!("{:0<3}", &fraction[..length.min(3)]);
+        millis = digits(&padded, 0, 3)?;
+        rest = &fraction[length..];
+    }
+
+    let offset_minutes = match rest {
+        "Z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let (hours, minutes) = (digits(rest, 1, 2)?, digits(rest, 4, 2)?);
+
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+
+            let minutes = hours * 60 + minutes;
+
+            match rest.as_bytes()[0] {
+                b'+' => minutes,
+                b'-' => -minutes,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let seconds = ((days_from_civil(year, month, day) * 24 + hour) * 60 + minute - offset_minutes)
+        * 60
+        + second;
+
+    Some(seconds * 1000 + millis)
+}
+
+fn format_iso8601(millis: i64) -> Option<String> {
+    let (year, month, day) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+
+    // Relaxed extended JSON only uses the ISO form for years 1970 to 9999
+    if !(1970..=9999).contains(&year) {
+        return None;
+    }
+
+    let time = millis.rem_euclid(MILLIS_PER_DAY);
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3_600_000,
+        time / 60_000 % 60,
+        time / 1000 % 60,
+        time % 1000
+    ))
+}
+
+fn object_id(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() != 24 || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..24)
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+fn date(val: &Json) -> Option<i64> {
+    match val {
+        Json::String(s) => parse_iso8601(s),
+        Json::Number(n) => as_integer(n),
+        Json::Object(obj) if obj.len() == 1 => match obj.get("$numberLong") {
+            Some(Json::String(s)) => s.parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Returns the BSON type and payload for `{"$oid": ...}` and `{"$date": ...}` wrappers
+fn extended(
+    obj: &Map<Cow<str>, Json>,
+    pointer: &str,
+) -> Option<Result<(u8, Vec<u8>), ConversionError>> {
+    if obj.len() != 1 {
+        return None;
+    }
+
+    let (key, val) = obj.iter().next()?;
+    let invalid = || {
+        Err(ConversionError::new(
+            ConversionErrorType::InvalidExtendedJson,
+            FORMAT,
+            pointer,
+        ))
+    };
+
+    match key.as_ref() {
+        "$oid" => Some(match val {
+            Json::String(s) => object_id(s).map_or_else(invalid, |id| Ok((OBJECT_ID, id))),
+            _ => invalid(),
+        }),
+        "$date" => Some(date(val).map_or_else(invalid, |millis| {
+            Ok((DATETIME, millis.to_le_bytes().to_vec()))
+        })),
+        _ => None,
+    }
+}
+
+fn cstring(out: &mut Vec<u8>, text: &str, pointer: &str) -> Result<(), ConversionError> {
+    if text.contains('\0') {
+        return Err(ConversionError::new(
+            ConversionErrorType::NulInKey,
+            FORMAT,
+            pointer,
+        ));
+    }
+
+    out.extend_from_slice(text.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+fn element(out: &mut Vec<u8>, key: &str, val: &Json, pointer: &str) -> Result<(), ConversionError> {
+    let type_index = out.len();
+    out.push(NULL);
+    cstring(out, key, pointer)?;
+
+    out[type_index] = match val {
+        Json::Null | Json::Error => NULL,
+        Json::Raw(_) => {
+            out.truncate(type_index);
+            return element(out, key, &val.parse_raw(), pointer);
+        }
+        Json::Bool(b) => {
+            out.push(*b as u8);
+            BOOL
+        }
+        Json::Number(n) => match as_integer(n) {
+            // Integers past an int64 would lose digits as a double
+            None if matches!(n.parsed(), Number::PosInt(_)) => {
+                return Err(ConversionError::new(
+                    ConversionErrorType::IntegerOutOfRange,
+                    FORMAT,
+                    pointer,
+                ))
+            }
+            Some(i) if i >= i32::MIN as i64 && i <= i32::MAX as i64 => {
+                out.extend_from_slice(&(i as i32).to_le_bytes());
+                INT32
+            }
+            Some(i) => {
+                out.extend_from_slice(&i.to_le_bytes());
+                INT64
+            }
+            None => {
+                out.extend_from_slice(&n.as_f64().to_le_bytes());
+                DOUBLE
+            }
+        },
+        Json::String(s) => {
+            out.extend_from_slice(&(s.len() as i32 + 1).to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+            out.push(0);
+            STRING
+        }
+        Json::Array(arr) => {
+            let entries = arr
+                .iter()
+                .enumerate()
+                .map(|(index, item)| (Cow::Owned(index.to_string()), item));
+
+            document(out, entries, pointer)?;
+            ARRAY
+        }
+        Json::Object(obj) => match extended(obj, pointer) {
+            Some(extended) => {
+                let (element_type, payload) = extended?;
+
+                out.extend_from_slice(&payload);
+                element_type
+            }
+            None => {
+                document(
+                    out,
+                    obj.iter()
+                        .map(|(key, item)| (Cow::Borrowed(key.as_ref()), item)),
+                    pointer,
+                )?;
+                DOCUMENT
+            }
+        },
+    };
+
+    Ok(())
+}
+
+fn document<'a, 'b, I>(out: &mut Vec<u8>, entries: I, pointer: &str) -> Result<(), ConversionError>
+where
+    I: Iterator<Item = (Cow<'b, str>, &'a Json<'a>)>,
+{
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+
+    for (key, item) in entries {
+        element(out, &key, item, &pointer::push(pointer, &key))?;
+    }
+
+    out.push(0);
+
+    let length = (out.len() - start) as i32;
+    out[start..start + 4].copy_from_slice(&length.to_le_bytes());
+
+    Ok(())
+}
+
+fn wrapped<'a>(key: &'static str, val: Json<'a>) -> Json<'a> {
+    Json::Object(Map::from([(Cow::Borrowed(key), val)]))
+}
+
+fn decode_element<'a>(
+    reader: &mut Reader<'a>,
+    element_type: u8,
+    offset: usize,
+) -> Result<Json<'a>, DecodeError> {
+    match element_type {
+        DOUBLE => {
+            let bits = reader.uint_le(8)?;
+            reader.number(f64::from_bits(bits), offset)
+        }
+        STRING => {
+            let length_offset = reader.offset();
+            let length = reader.uint_le(4)? as u32 as i32;
+
+            if length < 1 {
+                return Err(reader.error(DecodeErrorType::LengthMismatch, length_offset));
+            }
+
+            let text = reader.text(length as usize - 1)?;
+
+            match reader.byte()? {
+                0 => Ok(Json::String(text)),
+                _ => Err(reader.error(DecodeErrorType::LengthMismatch, length_offset)),
+            }
+        }
+        DOCUMENT => reader.nested(offset, |reader| decode_document(reader, false)),
+        ARRAY => reader.nested(offset, |reader| decode_document(reader, true)),
+        OBJECT_ID => {
+            let hex: String = reader
+                .take(12)?
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+
+            Ok(wrapped("$oid", Json::String(Cow::Owned(hex))))
+        }
+        BOOL => Ok(Json::Bool(reader.byte()? != 0)),
+        DATETIME => {
+            let millis = reader.uint_le(8)? as i64;
+
+            Ok(wrapped(
+                "$date",
+                match format_iso8601(millis) {
+                    Some(iso) => Json::String(Cow::Owned(iso)),
+                    None => wrapped("$numberLong", Json::String(Cow::Owned(millis.to_string()))),
+                },
+            ))
+        }
+        NULL => Ok(Json::Null),
+        INT32 => Ok(Json::Number(
+            (reader.uint_le(4)? as u32 as i32 as i64).into(),
+        )),
+        INT64 => Ok(Json::Number((reader.uint_le(8)? as i64).into())),
+        _ => Err(reader.error(DecodeErrorType::UnsupportedType(element_type), offset)),
+    }
+}
+
+fn decode_document<'a>(reader: &mut Reader<'a>, is_array: bool) -> Result<Json<'a>, DecodeError> {
+    let start = reader.offset();
+    let length = reader.uint_le(4)? as usize;
+    let mut array = vec![];
+    let mut object = Map::new();
+
+    loop {
+        let offset = reader.offset();
+        let element_type = reader.byte()?;
+
+        if element_type == 0 {
+            break;
+        }
+
+        let key = reader.cstring()?;
+        let value = decode_element(reader, element_type, offset)?;
+
+        if is_array {
+            array.push(value);
+        } else if object.insert(key, value).is_some() {
+            return Err(reader.error(DecodeErrorType::DuplicateKey, offset));
+        }
+    }
+
+    if reader.offset() - start != length {
+        return Err(reader.error(DecodeErrorType::LengthMismatch, start));
+    }
+
+    Ok(if is_array {
+        Json::Array(array)
+    } else {
+        Json::Object(object)
+    })
+}
+
+// Object ids and dates in extended JSON form are stored as native BSON types
+pub fn to_bson(val: &Json) -> Result<Vec<u8>, ConversionError> {
+    let mut out = vec![];
+
+    match &*val.parse_raw() {
+        Json::Object(obj) if extended(obj, "").is_none() => {
+            document(
+                &mut out,
+                obj.iter()
+                    .map(|(key, item)| (Cow::Borrowed(key.as_ref()), item)),
+                "",
+            )?;
+            Ok(out)
+        }
+        _ => Err(ConversionError::new(
+            ConversionErrorType::NotADocument,
+            FORMAT,
+            "",
+        )),
+    }
+}
+
+// Object ids and dates are decoded to relaxed extended JSON
+pub fn from_bson(bytes: &[u8]) -> Result<Json<'_>, DecodeError> {
+    let mut reader = Reader::new(bytes, FORMAT);
+    let value = reader.nested(0, |reader| decode_document(reader, false))?;
+
+    reader.finish(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parser;
+    use parser::DEFAULT_MAX_DEPTH;
+
+    fn parse_and<F: FnOnce(Json)>(raw: &str, f: F) {
+        let tokens = lexer::lex(raw).unwrap();
+        f(parser::parse(&tokens).unwrap());
+    }
+
+    #[test]
+    fn test_to_bson() {
+        parse_and("{\"hello\": \"world\"}", |value| {
+            let expected = b"\x16\x00\x00\x00\x02hello\x00\x06\x00\x00\x00world\x00\x00";
+
+            assert_eq!(to_bson(&value), Ok(expected.to_vec()));
+            assert_eq!(from_bson(expected), Ok(value));
+        });
+
+        parse_and(
+            "{\"a\": [1, 2.5, true, null], \"b\": {\"c\": 5000000000}, \"d\": [9223372036854775807, -9223372036854775808]}",
+            |value| {
+                let encoded = to_bson(&value).unwrap();
+
+                assert_eq!(from_bson(&encoded), Ok(value));
+            },
+        );
+    }
+
+    #[test]
+    fn test_extended_json() {
+        parse_and(
+            "{\"_id\": {\"$oid\": \"5f1a2b3c4d5e6f7a8b9c0d1e\"}, \"at\": {\"$date\": \"2020-07-23T10:00:00.500+02:00\"}}",
+            |value| {
+                let encoded = to_bson(&value).unwrap();
+
+                assert_eq!(encoded[4], OBJECT_ID);
+                assert_eq!(&encoded[9..21], b"\x5f\x1a\x2b\x3c\x4d\x5e\x6f\x7a\x8b\x9c\x0d\x1e");
+                assert_eq!(encoded[21], DATETIME);
+                assert_eq!(&encoded[25..33], &1595491200500i64.to_le_bytes());
+
+                parse_and(
+                    "{\"_id\": {\"$oid\": \"5f1a2b3c4d5e6f7a8b9c0d1e\"}, \"at\": {\"$date\": \"2020-07-23T08:00:00.500Z\"}}",
+                    |expected| assert_eq!(from_bson(&encoded), Ok(expected)),
+                );
+            },
+        );
+
+        parse_and(
+            "{\"old\": {\"$date\": {\"$numberLong\": \"-1000\"}}}",
+            |value| {
+                let encoded = to_bson(&value).unwrap();
+
+                assert_eq!(from_bson(&encoded), Ok(value));
+            },
+        );
+    }
+
+    #[test]
+    fn test_to_bson_errors() {
+        let cases = vec![
+            ("[1]", ConversionErrorType::NotADocument, ""),
+            (
+                "{\"a\": {\"$oid\": \"xyz\"}}",
+                ConversionErrorType::InvalidExtendedJson,
+                "/a",
+            ),
+            (
+                "{\"a\": {\"$date\": true}}",
+                ConversionErrorType::InvalidExtendedJson,
+                "/a",
+            ),
+            (
+                "{\"a\": {\"$date\": \"2020-02-30T00:00:00Z\"}}",
+                ConversionErrorType::InvalidExtendedJson,
+                "/a",
+            ),
+            (
+                "{\"a\": {\"$date\": {\"$numberLong\": \"9223372036854775808\"}}}",
+                ConversionErrorType::InvalidExtendedJson,
+                "/a",
+            ),
+            (
+                "{\"a\": [18446744073709551615]}",
+                ConversionErrorType::IntegerOutOfRange,
+                "/a/0",
+            ),
+            ("{\"a\\u0000\": 1}", ConversionErrorType::NulInKey, "/a\0"),
+        ];
+
+        for (raw, error_type, pointer) in cases {
+            parse_and(raw, |value| {
+                assert_eq!(
+                    to_bson(&value),
+                    Err(ConversionError::new(error_type, FORMAT, pointer))
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_from_bson_errors() {
+        let cases: Vec<(&[u8], DecodeError)> = vec![
+            (
+                b"\x05\x00\x00",
+                DecodeError::new(DecodeErrorType::UnexpectedEnd, FORMAT, 3),
+            ),
+            (
+                b"\x06\x00\x00\x00\x00",
+                DecodeError::new(DecodeErrorType::LengthMismatch, FORMAT, 0),
+            ),
+            (
+                b"\x0d\x00\x00\x00\x05a\x00\x00\x00\x00\x00\x00\x00",
+                DecodeError::new(DecodeErrorType::UnsupportedType(0x05), FORMAT, 4),
+            ),
+        ];
+
+        for (bytes, expected) in cases {
+            assert_eq!(from_bson(bytes), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_iso8601() {
+        assert_eq!(parse_iso8601("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_iso8601("2000-02-29T12:30:15.25Z"), Some(951827415250));
+        assert_eq!(parse_iso8601("1970-01-01T00:00:00"), None);
+        assert_eq!(parse_iso8601("1970-13-01T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("2021-02-29T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("2021-04-31T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("2021-01-01T00:00:60Z"), None);
+        assert_eq!(parse_iso8601("2021-01-01T00:00:00+24:00"), None);
+        assert!(parse_iso8601("1900-02-28T00:00:00Z").is_some());
+        assert_eq!(parse_iso8601("1900-02-29T00:00:00Z"), None);
+        assert_eq!(
+            format_iso8601(951827415250).as_deref(),
+            Some("2000-02-29T12:30:15.250Z")
+        );
+        assert_eq!(format_iso8601(-1), None);
+    }
+
+    #[test]
+    fn test_from_bson_depth() {
+        // Test threads get a small stack, decode on one the size of the main thread's
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let mut nested = Json::Object(Map::new());
+                for _ in 1..DEFAULT_MAX_DEPTH {
+                    nested = wrapped("a", nested);
+                }
+                assert_eq!(from_bson(&to_bson(&nested).unwrap()), Ok(nested));
+
+                // Lengths are only checked once a document ends
+                let deep = [0, 0, 0, 0, DOCUMENT, 0].repeat(100_000);
+                assert_eq!(
+                    from_bson(&deep),
+                    Err(DecodeError::new(
+                        DecodeErrorType::DepthLimitExceeded,
+                        FORMAT,
+                        6 * (DEFAULT_MAX_DEPTH - 1) + 4
+                    ))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
@@ -0,0 +1,379 @@
+use super::{as_integer, DecodeError, DecodeErrorType, Reader};
+use number::Number;
+use parser::{Json, Map};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+const FORMAT: &str = "CBOR";
+
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const NEGATIVE: u8 = 1;
+const TEXT: u8 = 3;
+const UNSIGNED: u8 = 0;
+
+const BREAK: u8 = 0xff;
+const INDEFINITE: u8 = 31;
+
+fn header(out: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+
+    if argument < 24 {
+        out.push(major | argument as u8);
+    } else if argument <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(argument as u8);
+    } else if argument <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(argument as u16).to_be_bytes());
+    } else if argument <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(argument as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&argument.to_be_bytes());
+    }
+}
+
+fn text(out: &mut Vec<u8>, text: &str) {
+    header(out, TEXT, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn encode(val: &Json, out: &mut Vec<u8>) {
+    match val {
+        Json::Null | Json::Error => out.push(0xf6),
+        Json::Raw(_) => encode(&val.parse_raw(), out),
+        Json::Bool(false) => out.push(0xf4),
+        Json::Bool(true) => out.push(0xf5),
+        Json::Number(n) => match (as_integer(n), n.as_u64()) {
+            (Some(i), _) if i >= 0 => header(out, UNSIGNED, i as u64),
+            (Some(i), _) => header(out, NEGATIVE, (-1 - i) as u64),
+            (None, Some(u)) => header(out, UNSIGNED, u),
+            (None, None) => {
+                out.push(0xfb);
+                out.extend_from_slice(&n.as_f64().to_be_bytes());
+            }
+        },
+        Json::String(s) => text(out, s),
+        Json::Array(arr) => {
+            header(out, ARRAY, arr.len() as u64);
+
+            for item in arr {
+                encode(item, out);
+            }
+        }
+        Json::Object(obj) => {
+            header(out, MAP, obj.len() as u64);
+
+            for (key, item) in obj {
+                text(out, key);
+                encode(item, out);
+            }
+        }
+    }
+}
+
+fn half_to_f64(bits: u16) -> f64 {
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f64;
+
+    let value = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+
+    if bits & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+fn argument(reader: &mut Reader, initial: u8, offset: usize) -> Result<u64, DecodeError> {
+    match initial & 0x1f {
+        info @ 0..=23 => Ok(info as u64),
+        24 => reader.uint(1),
+        25 => reader.uint(2),
+        26 => reader.uint(4),
+        27 => reader.uint(8),
+        _ => Err(reader.error(DecodeErrorType::UnsupportedType(initial), offset)),
+    }
+}
+
+fn length(reader: &mut Reader, initial: u8, offset: usize) -> Result<Option<u64>, DecodeError> {
+    if initial & 0x1f == INDEFINITE {
+        Ok(None)
+    } else {
+        argument(reader, initial, offset).map(Some)
+    }
+}
+
+// Calls `item` for each entry of a definite or indefinite length container
+fn for_each_item<'a, F>(
+    reader: &mut Reader<'a>,
+    length: Option<u64>,
+    mut item: F,
+) -> Result<(), DecodeError>
+where
+    F: FnMut(&mut Reader<'a>) -> Result<(), DecodeError>,
+{
+    match length {
+        Some(length) => {
+            for _ in 0..length {
+                item(reader)?;
+            }
+        }
+        None => {
+            while reader.peek() != Some(BREAK) {
+                item(reader)?;
+            }
+
+            reader.byte()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn key<'a>(reader: &mut Reader<'a>) -> Result<Cow<'a, str>, DecodeError> {
+    let offset = reader.offset();
+    let initial = reader.byte()?;
+
+    if initial >> 5 != TEXT || initial & 0x1f == INDEFINITE {
+        return Err(reader.error(DecodeErrorType::NonStringKey, offset));
+    }
+
+    let length = argument(reader, initial, offset)?;
+    reader.text(length as usize)
+}
+
+fn decode<'a>(reader: &mut Reader<'a>) -> Result<Json<'a>, DecodeError> {
+    let offset = reader.offset();
+    let initial = reader.byte()?;
+    let unsupported = DecodeErrorType::UnsupportedType(initial);
+
+    match initial >> 5 {
+        UNSIGNED => Ok(Json::Number(argument(reader, initial, offset)?.into())),
+        NEGATIVE => {
+            let argument = argument(reader, initial, offset)?;
+
+            Ok(Json::Number(match i64::try_from(argument) {
+                Ok(n) => (-1 - n).into(),
+                Err(_) => Number::Float(-1.0 - argument as f64),
+            }))
+        }
+        TEXT => match length(reader, initial, offset)? {
+            Some(length) => reader.text(length as usize).map(Json::String),
+            None => Err(reader.error(unsupported, offset)),
+        },
+        ARRAY => {
+            let length = length(reader, initial, offset)?;
+            let mut array = vec![];
+
+            reader.nested(offset, |reader| {
+                for_each_item(reader, length, |reader| {
+                    array.push(decode(reader)?);
+                    Ok(())
+                })
+            })?;
+
+            Ok(Json::Array(array))
+        }
+        MAP => {
+            let length = length(reader, initial, offset)?;
+            let mut object = Map::new();
+
+            reader.nested(offset, |reader| {
+                for_each_item(reader, length, |reader| {
+                    let key_offset = reader.offset();
+                    let key = key(reader)?;
+                    let value = decode(reader)?;
+
+                    match object.insert(key, value) {
+                        Some(_) => Err(reader.error(DecodeErrorType::DuplicateKey, key_offset)),
+                        None => Ok(()),
+                    }
+                })
+            })?;
+
+            Ok(Json::Object(object))
+        }
+        // Tags are dropped, the tagged value is decoded as is
+        6 => {
+            argument(reader, initial, offset)?;
+            reader.nested(offset, decode)
+        }
+        7 => match initial & 0x1f {
+            20 => Ok(Json::Bool(false)),
+            21 => Ok(Json::Bool(true)),
+            22 | 23 => Ok(Json::Null),
+            25 => {
+                let bits = reader.uint(2)? as u16;
+                reader.number(half_to_f64(bits), offset)
+            }
+            26 => {
+                let bits = reader.uint(4)? as u32;
+                reader.number(f32::from_bits(bits) as f64, offset)
+            }
+            27 => {
+                let bits = reader.uint(8)?;
+                reader.number(f64::from_bits(bits), offset)
+            }
+            _ => Err(reader.error(unsupported, offset)),
+        },
+        _ => Err(reader.error(unsupported, offset)),
+    }
+}
+
+pub fn to_cbor(val: &Json) -> Vec<u8> {
+    let mut out = vec![];
+    encode(val, &mut out);
+    out
+}
+
+// Text is borrowed from `bytes` unless it needs escaping
+pub fn from_cbor(bytes: &[u8]) -> Result<Json<'_>, DecodeError> {
+    let mut reader = Reader::new(bytes, FORMAT);
+    let value = decode(&mut reader)?;
+
+    reader.finish(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parser;
+    use parser::DEFAULT_MAX_DEPTH;
+
+    #[test]
+    fn test_to_cbor() {
+        let cases: Vec<(&str, &[u8])> = vec![
+            ("0", &[0x00]),
+            ("23", &[0x17]),
+            ("24", &[0x18, 0x18]),
+            ("1000", &[0x19, 0x03, 0xe8]),
+            ("-1000", &[0x39, 0x03, 0xe7]),
+            (
+                "1.1",
+                &[0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a],
+            ),
+            ("null", &[0xf6]),
+            ("\"a\\n\"", &[0x62, 0x61, 0x0a]),
+            ("[1, [2, 3]]", &[0x82, 0x01, 0x82, 0x02, 0x03]),
+            ("{\"a\": true}", &[0xa1, 0x61, 0x61, 0xf5]),
+        ];
+
+        for (raw, expected) in cases {
+            let tokens = lexer::lex(raw).unwrap();
+            let value = parser::parse(&tokens).unwrap();
+
+            assert_eq!(to_cbor(&value), expected, "{}", raw);
+            assert_eq!(from_cbor(expected), Ok(value), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_from_cbor() {
+        let cases: Vec<(&[u8], Result<Json, DecodeError>)> = vec![
+            (&[0xf9, 0x3c, 0x00], Ok(Json::Number(1.0.into()))),
+            (
+                &[0xfa, 0x3f, 0xc0, 0x00, 0x00],
+                Ok(Json::Number(1.5.into())),
+            ),
+            (
+                &[0x9f, 0x01, 0x02, 0xff],
+                Ok(Json::Array(vec![
+                    Json::Number(1.0.into()),
+                    Json::Number(2.0.into()),
+                ])),
+            ),
+            (
+                &[0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0],
+                Ok(Json::Number(1363896240.0.into())),
+            ),
+            (
+                &[0x62, 0x22, 0x61],
+                Ok(Json::String(Cow::Owned("\"a".to_string()))),
+            ),
+            (
+                &[0x82, 0x01],
+                Err(DecodeError::new(DecodeErrorType::UnexpectedEnd, FORMAT, 2)),
+            ),
+            (
+                &[0x01, 0x02],
+                Err(DecodeError::new(DecodeErrorType::TrailingBytes, FORMAT, 1)),
+            ),
+            (
+                &[0xa1, 0x01, 0x02],
+                Err(DecodeError::new(DecodeErrorType::NonStringKey, FORMAT, 1)),
+            ),
+            (
+                &[0xa2, 0x61, 0x61, 0x01, 0x61, 0x61, 0x02],
+                Err(DecodeError::new(DecodeErrorType::DuplicateKey, FORMAT, 4)),
+            ),
+            (
+                &[0x61, 0xff],
+                Err(DecodeError::new(DecodeErrorType::InvalidUtf8, FORMAT, 1)),
+            ),
+            (
+                &[0xf9, 0x7c, 0x00],
+                Err(DecodeError::new(
+                    DecodeErrorType::NonFiniteNumber,
+                    FORMAT,
+                    0,
+                )),
+            ),
+            (
+                &[0x41, 0x00],
+                Err(DecodeError::new(
+                    DecodeErrorType::UnsupportedType(0x41),
+                    FORMAT,
+                    0,
+                )),
+            ),
+        ];
+
+        for (bytes, expected) in cases {
+            assert_eq!(from_cbor(bytes), expected, "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_from_cbor_depth() {
+        // Test threads get a small stack, decode on one the size of the main thread's
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let mut nested = vec![0x81; DEFAULT_MAX_DEPTH];
+                nested.push(0x01);
+                assert!(from_cbor(&nested).is_ok());
+
+                let deep = vec![0x81; 100_000];
+                assert_eq!(
+                    from_cbor(&deep),
+                    Err(DecodeError::new(
+                        DecodeErrorType::DepthLimitExceeded,
+                        FORMAT,
+                        DEFAULT_MAX_DEPTH
+                    ))
+                );
+
+                let tags = vec![0xc6; 100_000];
+                assert_eq!(
+                    from_cbor(&tags),
+                    Err(DecodeError::new(
+                        DecodeErrorType::DepthLimitExceeded,
+                        FORMAT,
+                        DEFAULT_MAX_DEPTH
+                    ))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
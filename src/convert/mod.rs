@@ -0,0 +1,307 @@
+#[cfg(any(
+    feature = "bson",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "parquet",
+    feature = "toml",
+    feature = "yaml"
+))]
+use number::Number;
+#[cfg(any(feature = "bson", feature = "cbor", feature = "msgpack"))]
+use parser::{Json, DEFAULT_MAX_DEPTH};
+#[cfg(any(feature = "bson", feature = "cbor", feature = "msgpack"))]
+use std::borrow::Cow;
+use std::fmt;
+
+#[cfg(feature = "bson")]
+mod bson;
+#[cfg(feature = "cbor")]
+mod cbor;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "serde_json")]
+mod serde_json;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+#[cfg(feature = "bson")]
+pub use self::bson::{from_bson, to_bson};
+#[cfg(feature = "cbor")]
+pub use self::cbor::{from_cbor, to_cbor};
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::{from_msgpack, to_msgpack};
+#[cfg(feature = "parquet")]
+pub use self::parquet::{to_parquet, ParquetExportError};
+
+#[derive(Debug, PartialEq)]
+pub enum ConversionErrorType {
+    Null,
+    NotADocument,
+    NulInKey,
+    InvalidExtendedJson,
+    NotATable,
+    IntegerOutOfRange,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConversionError {
+    pub error_type: ConversionErrorType,
+    pub format: &'static str,
+    pub pointer: String,
+}
+
+impl ConversionError {
+    pub fn new(error_type: ConversionErrorType, format: &'static str, pointer: &str) -> Self {
+        ConversionError {
+            error_type,
+            format,
+            pointer: pointer.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let location = if self.pointer.is_empty() {
+            "the document root"
+        } else {
+            &self.pointer
+        };
+
+        match self.error_type {
+            ConversionErrorType::Null => write!(
+                f,
+                "Value at {} is null, {} has no null type",
+                location, self.format
+            ),
+            ConversionErrorType::NotADocument => write!(
+                f,
+                "Value at {} is not an object, {} can only store documents",
+                location, self.format
+            ),
+            ConversionErrorType::NulInKey => write!(
+                f,
+                "Key at {} contains a NUL character, which {} keys cannot hold",
+                location, self.format
+            ),
+            ConversionErrorType::InvalidExtendedJson => {
+                write!(f, "Value at {} is not valid extended JSON", location)
+            }
+            ConversionErrorType::NotATable => write!(
+                f,
+                "Value at {} should be a non empty array of objects to be written as {}",
+                location, self.format
+            ),
+            ConversionErrorType::IntegerOutOfRange => write!(
+                f,
+                "Value at {} is an integer too large for {}, which holds 64 bit signed integers",
+                location, self.format
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeErrorType {
+    UnexpectedEnd,
+    TrailingBytes,
+    InvalidUtf8,
+    NonFiniteNumber,
+    NonStringKey,
+    DuplicateKey,
+    LengthMismatch,
+    DepthLimitExceeded,
+    UnsupportedType(u8),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DecodeError {
+    pub error_type: DecodeErrorType,
+    pub format: &'static str,
+    pub offset: usize,
+}
+
+impl DecodeError {
+    pub fn new(error_type: DecodeErrorType, format: &'static str, offset: usize) -> Self {
+        DecodeError {
+            error_type,
+            format,
+            offset,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.error_type {
+            DecodeErrorType::UnexpectedEnd => "Input ended unexpectedly".to_string(),
+            DecodeErrorType::TrailingBytes => "Unexpected bytes after the value".to_string(),
+            DecodeErrorType::InvalidUtf8 => "Text is not valid UTF-8".to_string(),
+            DecodeErrorType::NonFiniteNumber => "NaN and infinity are not valid JSON".to_string(),
+            DecodeErrorType::NonStringKey => "Map keys should be text".to_string(),
+            DecodeErrorType::DuplicateKey => "Duplicate keys are not valid".to_string(),
+            DecodeErrorType::LengthMismatch => "Length does not match the content".to_string(),
+            DecodeErrorType::DepthLimitExceeded => {
+                "Values are nested deeper than the limit".to_string()
+            }
+            DecodeErrorType::UnsupportedType(byte) => {
+                format!("Type 0x{:02x} has no JSON equivalent", byte)
+            }
+        };
+
+        write!(
+            f,
+            "{} at byte {} of {} input",
+            message, self.offset, self.format
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// Cursor over binary input, shared by the decoders
+#[cfg(any(feature = "bson", feature = "cbor", feature = "msgpack"))]
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    format: &'static str,
+    depth: usize,
+}
+
+#[cfg(any(feature = "bson", feature = "cbor", feature = "msgpack"))]
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], format: &'static str) -> Reader<'a> {
+        Reader {
+            bytes,
+            offset: 0,
+            format,
+            depth: 0,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn error(&self, error_type: DecodeErrorType, offset: usize) -> DecodeError {
+        DecodeError::new(error_type, self.format, offset)
+    }
+
+    pub fn take(&mut self, length: usize) -> Result<&'a [u8], DecodeError> {
+        match self
+            .bytes
+            .get(self.offset..self.offset.saturating_add(length))
+        {
+            Some(bytes) => {
+                self.offset += length;
+                Ok(bytes)
+            }
+            None => Err(self.error(DecodeErrorType::UnexpectedEnd, self.bytes.len())),
+        }
+    }
+
+    // Runs `decode` one level deeper, the decoders recurse so nesting is capped
+    pub fn nested<T>(
+        &mut self,
+        offset: usize,
+        decode: impl FnOnce(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        if self.depth >= DEFAULT_MAX_DEPTH {
+            return Err(self.error(DecodeErrorType::DepthLimitExceeded, offset));
+        }
+
+        self.depth += 1;
+        let result = decode(self);
+        self.depth -= 1;
+        result
+    }
+
+    pub fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    #[cfg(feature = "cbor")]
+    pub fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.offset).copied()
+    }
+
+    #[cfg(any(feature = "cbor", feature = "msgpack"))]
+    pub fn uint(&mut self, length: usize) -> Result<u64, DecodeError> {
+        Ok(self
+            .take(length)?
+            .iter()
+            .fold(0, |acc, byte| (acc << 8) | *byte as u64))
+    }
+
+    #[cfg(feature = "bson")]
+    pub fn uint_le(&mut self, length: usize) -> Result<u64, DecodeError> {
+        Ok(self
+            .take(length)?
+            .iter()
+            .rev()
+            .fold(0, |acc, byte| (acc << 8) | *byte as u64))
+    }
+
+    // Reads a NUL terminated string, the terminator is consumed but not returned
+    #[cfg(feature = "bson")]
+    pub fn cstring(&mut self) -> Result<Cow<'a, str>, DecodeError> {
+        match self.bytes[self.offset..].iter().position(|byte| *byte == 0) {
+            Some(length) => {
+                let text = self.text(length)?;
+                self.offset += 1;
+                Ok(text)
+            }
+            None => Err(self.error(DecodeErrorType::UnexpectedEnd, self.bytes.len())),
+        }
+    }
+
+    pub fn text(&mut self, length: usize) -> Result<Cow<'a, str>, DecodeError> {
+        let offset = self.offset;
+
+        match std::str::from_utf8(self.take(length)?) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(_) => Err(self.error(DecodeErrorType::InvalidUtf8, offset)),
+        }
+    }
+
+    pub fn number(&self, number: f64, offset: usize) -> Result<Json<'a>, DecodeError> {
+        if number.is_finite() {
+            Ok(Json::Number(Number::Float(number)))
+        } else {
+            Err(self.error(DecodeErrorType::NonFiniteNumber, offset))
+        }
+    }
+
+    pub fn finish(&self, value: Json<'a>) -> Result<Json<'a>, DecodeError> {
+        if self.offset == self.bytes.len() {
+            Ok(value)
+        } else {
+            Err(self.error(DecodeErrorType::TrailingBytes, self.offset))
+        }
+    }
+}
+
+// Integers and floats without a fractional part that fit an i64 are treated as integers
+#[cfg(any(
+    feature = "bson",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "parquet",
+    feature = "toml",
+    feature = "yaml"
+))]
+pub(crate) fn as_integer(number: &Number) -> Option<i64> {
+    match number.parsed() {
+        Number::Float(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+            Some(n as i64)
+        }
+        Number::Float(_) => None,
+        _ => number.as_i64(),
+    }
+}
@@ -0,0 +1,322 @@
+use super::{as_integer, DecodeError, DecodeErrorType, Reader};
+use parser::{Json, Map};
+use std::borrow::Cow;
+
+const FORMAT: &str = "MessagePack";
+
+fn integer(out: &mut Vec<u8>, i: i64) {
+    if (0..=0x7f).contains(&i) || (-32..0).contains(&i) {
+        out.push(i as u8);
+    } else if i >= 0 {
+        let u = i as u64;
+
+        if u <= u8::MAX as u64 {
+            out.push(0xcc);
+            out.push(u as u8);
+        } else if u <= u16::MAX as u64 {
+            out.push(0xcd);
+            out.extend_from_slice(&(u as u16).to_be_bytes());
+        } else if u <= u32::MAX as u64 {
+            out.push(0xce);
+            out.extend_from_slice(&(u as u32).to_be_bytes());
+        } else {
+            out.push(0xcf);
+            out.extend_from_slice(&u.to_be_bytes());
+        }
+    } else if i >= i8::MIN as i64 {
+        out.push(0xd0);
+        out.push(i as u8);
+    } else if i >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend_from_slice(&(i as i16).to_be_bytes());
+    } else if i >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend_from_slice(&(i as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&i.to_be_bytes());
+    }
+}
+
+// Writes a length using the fix, 16 bit or 32 bit form of a container or string type
+fn length(out: &mut Vec<u8>, length: usize, fix: u8, fix_max: usize, wide: u8) {
+    if length <= fix_max {
+        out.push(fix | length as u8);
+    } else if length <= u16::MAX as usize {
+        out.push(wide);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+    } else {
+        out.push(wide + 1);
+        out.extend_from_slice(&(length as u32).to_be_bytes());
+    }
+}
+
+fn text(out: &mut Vec<u8>, text: &str) {
+    if text.len() >= 32 && text.len() <= u8::MAX as usize {
+        out.push(0xd9);
+        out.push(text.len() as u8);
+    } else {
+        length(out, text.len(), 0xa0, 31, 0xda);
+    }
+
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn encode(val: &Json, out: &mut Vec<u8>) {
+    match val {
+        Json::Null | Json::Error => out.push(0xc0),
+        Json::Raw(_) => encode(&val.parse_raw(), out),
+        Json::Bool(false) => out.push(0xc2),
+        Json::Bool(true) => out.push(0xc3),
+        Json::Number(n) => match (as_integer(n), n.as_u64()) {
+            (Some(i), _) => integer(out, i),
+            (None, Some(u)) => {
+                out.push(0xcf);
+                out.extend_from_slice(&u.to_be_bytes());
+            }
+            (None, None) => {
+                out.push(0xcb);
+                out.extend_from_slice(&n.as_f64().to_be_bytes());
+            }
+        },
+        Json::String(s) => text(out, s),
+        Json::Array(arr) => {
+            length(out, arr.len(), 0x90, 15, 0xdc);
+
+            for item in arr {
+                encode(item, out);
+            }
+        }
+        Json::Object(obj) => {
+            length(out, obj.len(), 0x80, 15, 0xde);
+
+            for (key, item) in obj {
+                text(out, key);
+                encode(item, out);
+            }
+        }
+    }
+}
+
+fn str_length(reader: &mut Reader, marker: u8) -> Option<Result<u64, DecodeError>> {
+    match marker {
+        0xa0..=0xbf => Some(Ok((marker & 0x1f) as u64)),
+        0xd9 => Some(reader.uint(1)),
+        0xda => Some(reader.uint(2)),
+        0xdb => Some(reader.uint(4)),
+        _ => None,
+    }
+}
+
+fn key<'a>(reader: &mut Reader<'a>) -> Result<Cow<'a, str>, DecodeError> {
+    let offset = reader.offset();
+    let marker = reader.byte()?;
+
+    match str_length(reader, marker) {
+        Some(length) => reader.text(length? as usize),
+        None => Err(reader.error(DecodeErrorType::NonStringKey, offset)),
+    }
+}
+
+fn array<'a>(reader: &mut Reader<'a>, length: u64, offset: usize) -> Result<Json<'a>, DecodeError> {
+    reader.nested(offset, |reader| {
+        let mut array = vec![];
+
+        for _ in 0..length {
+            array.push(decode(reader)?);
+        }
+
+        Ok(Json::Array(array))
+    })
+}
+
+fn object<'a>(
+    reader: &mut Reader<'a>,
+    length: u64,
+    offset: usize,
+) -> Result<Json<'a>, DecodeError> {
+    reader.nested(offset, |reader| {
+        let mut object = Map::new();
+
+        for _ in 0..length {
+            let key_offset = reader.offset();
+            let key = key(reader)?;
+            let value = decode(reader)?;
+
+            if object.insert(key, value).is_some() {
+                return Err(reader.error(DecodeErrorType::DuplicateKey, key_offset));
+            }
+        }
+
+        Ok(Json::Object(object))
+    })
+}
+
+fn decode<'a>(reader: &mut Reader<'a>) -> Result<Json<'a>, DecodeError> {
+    let offset = reader.offset();
+    let marker = reader.byte()?;
+
+    if let Some(length) = str_length(reader, marker) {
+        return reader.text(length? as usize).map(Json::String);
+    }
+
+    match marker {
+        0x00..=0x7f => Ok(Json::Number((marker as i64).into())),
+        0xe0..=0xff => Ok(Json::Number((marker as i8 as i64).into())),
+        0x80..=0x8f => object(reader, (marker & 0x0f) as u64, offset),
+        0x90..=0x9f => array(reader, (marker & 0x0f) as u64, offset),
+        0xc0 => Ok(Json::Null),
+        0xc2 => Ok(Json::Bool(false)),
+        0xc3 => Ok(Json::Bool(true)),
+        0xca => {
+            let bits = reader.uint(4)? as u32;
+            reader.number(f32::from_bits(bits) as f64, offset)
+        }
+        0xcb => {
+            let bits = reader.uint(8)?;
+            reader.number(f64::from_bits(bits), offset)
+        }
+        0xcc => Ok(Json::Number(reader.uint(1)?.into())),
+        0xcd => Ok(Json::Number(reader.uint(2)?.into())),
+        0xce => Ok(Json::Number(reader.uint(4)?.into())),
+        0xcf => Ok(Json::Number(reader.uint(8)?.into())),
+        0xd0 => Ok(Json::Number((reader.uint(1)? as u8 as i8 as i64).into())),
+        0xd1 => Ok(Json::Number((reader.uint(2)? as u16 as i16 as i64).into())),
+        0xd2 => Ok(Json::Number((reader.uint(4)? as u32 as i32 as i64).into())),
+        0xd3 => Ok(Json::Number((reader.uint(8)? as i64).into())),
+        0xdc => {
+            let length = reader.uint(2)?;
+            array(reader, length, offset)
+        }
+        0xdd => {
+            let length = reader.uint(4)?;
+            array(reader, length, offset)
+        }
+        0xde => {
+            let length = reader.uint(2)?;
+            object(reader, length, offset)
+        }
+        0xdf => {
+            let length = reader.uint(4)?;
+            object(reader, length, offset)
+        }
+        _ => Err(reader.error(DecodeErrorType::UnsupportedType(marker), offset)),
+    }
+}
+
+pub fn to_msgpack(val: &Json) -> Vec<u8> {
+    let mut out = vec![];
+    encode(val, &mut out);
+    out
+}
+
+// Text is borrowed from `bytes` unless it needs escaping
+pub fn from_msgpack(bytes: &[u8]) -> Result<Json<'_>, DecodeError> {
+    let mut reader = Reader::new(bytes, FORMAT);
+    let value = decode(&mut reader)?;
+
+    reader.finish(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parser;
+    use parser::DEFAULT_MAX_DEPTH;
+
+    #[test]
+    fn test_to_msgpack() {
+        let cases: Vec<(&str, &[u8])> = vec![
+            ("7", &[0x07]),
+            ("-3", &[0xfd]),
+            ("200", &[0xcc, 0xc8]),
+            ("-200", &[0xd1, 0xff, 0x38]),
+            ("70000", &[0xce, 0x00, 0x01, 0x11, 0x70]),
+            (
+                "0.5",
+                &[0xcb, 0x3f, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            ),
+            ("[null, false]", &[0x92, 0xc0, 0xc2]),
+            ("{\"a\": \"\\t\"}", &[0x81, 0xa1, 0x61, 0xa1, 0x09]),
+        ];
+
+        for (raw, expected) in cases {
+            let tokens = lexer::lex(raw).unwrap();
+            let value = parser::parse(&tokens).unwrap();
+
+            assert_eq!(to_msgpack(&value), expected, "{}", raw);
+            assert_eq!(from_msgpack(expected), Ok(value), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_to_msgpack_long_string() {
+        let raw = "a".repeat(40);
+        let value = Json::String(Cow::Borrowed(&raw));
+        let encoded = to_msgpack(&value);
+
+        assert_eq!(&encoded[..2], &[0xd9, 40]);
+        assert_eq!(from_msgpack(&encoded), Ok(value));
+    }
+
+    #[test]
+    fn test_from_msgpack() {
+        let cases: Vec<(&[u8], Result<Json, DecodeError>)> = vec![
+            (
+                &[0xca, 0x3f, 0xc0, 0x00, 0x00],
+                Ok(Json::Number(1.5.into())),
+            ),
+            (&[0xd0, 0xfe], Ok(Json::Number((-2.0).into()))),
+            (
+                &[0xdc, 0x00, 0x01, 0xc3],
+                Ok(Json::Array(vec![Json::Bool(true)])),
+            ),
+            (
+                &[0x92, 0x01],
+                Err(DecodeError::new(DecodeErrorType::UnexpectedEnd, FORMAT, 2)),
+            ),
+            (
+                &[0x81, 0x01, 0x02],
+                Err(DecodeError::new(DecodeErrorType::NonStringKey, FORMAT, 1)),
+            ),
+            (
+                &[0xc4, 0x00],
+                Err(DecodeError::new(
+                    DecodeErrorType::UnsupportedType(0xc4),
+                    FORMAT,
+                    0,
+                )),
+            ),
+        ];
+
+        for (bytes, expected) in cases {
+            assert_eq!(from_msgpack(bytes), expected, "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_from_msgpack_depth() {
+        // Test threads get a small stack, decode on one the size of the main thread's
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let mut nested = vec![0x91; DEFAULT_MAX_DEPTH];
+                nested.push(0x01);
+                assert!(from_msgpack(&nested).is_ok());
+
+                let deep = vec![0x91; 100_000];
+                assert_eq!(
+                    from_msgpack(&deep),
+                    Err(DecodeError::new(
+                        DecodeErrorType::DepthLimitExceeded,
+                        FORMAT,
+                        DEFAULT_MAX_DEPTH
+                    ))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
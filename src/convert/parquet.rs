@@ -0,0 +1,297 @@
+use super::{as_integer, ConversionError, ConversionErrorType};
+use formatter::format_json;
+use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::types::Type;
+use parquet::{
+    column::writer::ColumnWriterImpl, data_type::DataType, file::writer::SerializedRowGroupWriter,
+};
+use parser::{Json, Map};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+
+const FORMAT: &str = "Parquet";
+
+// Rows are written in groups so that only one group of column values is buffered at a time
+const ROW_GROUP_SIZE: usize = 8192;
+
+#[derive(Debug)]
+pub enum ParquetExportError {
+    Conversion(ConversionError),
+    Parquet(ParquetError),
+}
+
+impl fmt::Display for ParquetExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParquetExportError::Conversion(err) => write!(f, "{}", err),
+            ParquetExportError::Parquet(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ParquetExportError {}
+
+impl From<ParquetError> for ParquetExportError {
+    fn from(err: ParquetError) -> Self {
+        ParquetExportError::Parquet(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Boolean,
+    Int64,
+    Double,
+    // Strings, and values of mixed or nested types encoded as JSON text
+    Text,
+}
+
+impl ColumnType {
+    fn of(val: &Json) -> Option<ColumnType> {
+        match val {
+            Json::Null | Json::Error => None,
+            Json::Bool(_) => Some(ColumnType::Boolean),
+            Json::Number(n) if as_integer(n).is_some() => Some(ColumnType::Int64),
+            Json::Number(_) => Some(ColumnType::Double),
+            _ => Some(ColumnType::Text),
+        }
+    }
+
+    fn merge(current: Option<ColumnType>, next: Option<ColumnType>) -> Option<ColumnType> {
+        match (current, next) {
+            (None, other) | (other, None) => other,
+            (Some(a), Some(b)) if a == b => Some(a),
+            (Some(ColumnType::Int64), Some(ColumnType::Double))
+            | (Some(ColumnType::Double), Some(ColumnType::Int64)) => Some(ColumnType::Double),
+            _ => Some(ColumnType::Text),
+        }
+    }
+}
+
+struct Column<'a> {
+    key: &'a str,
+    column_type: Option<ColumnType>,
+}
+
+fn rows<'a, 'b>(
+    val: &'b Json<'a>,
+) -> Result<Vec<&'b Map<Cow<'a, str>, Json<'a>>>, ConversionError> {
+    let not_a_table =
+        |pointer: &str| ConversionError::new(ConversionErrorType::NotATable, FORMAT, pointer);
+
+    match val {
+        Json::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(index, item)| match item {
+                Json::Object(obj) => Ok(obj),
+                _ => Err(not_a_table(&format!("/{}", index))),
+            })
+            .collect(),
+        _ => Err(not_a_table("")),
+    }
+}
+
+// Columns are ordered by the first appearance of their key
+fn infer_columns<'a>(rows: &[&'a Map<Cow<'a, str>, Json<'a>>]) -> Vec<Column<'a>> {
+    let mut columns: Vec<Column> = vec![];
+
+    for row in rows {
+        for (key, val) in row.iter() {
+            let column_type = ColumnType::of(val);
+
+            match columns.iter_mut().find(|column| column.key == key) {
+                Some(column) => {
+                    column.column_type = ColumnType::merge(column.column_type, column_type)
+                }
+                None => columns.push(Column { key, column_type }),
+            }
+        }
+    }
+
+    columns
+}
+
+fn schema(columns: &[Column]) -> Result<Type, ParquetError> {
+    let fields = columns
+        .iter()
+        .map(|column| {
+            let (physical_type, logical_type) = match column.column_type {
+                Some(ColumnType::Boolean) => (PhysicalType::BOOLEAN, None),
+                Some(ColumnType::Int64) => (PhysicalType::INT64, None),
+                Some(ColumnType::Double) => (PhysicalType::DOUBLE, None),
+                Some(ColumnType::Text) | None => {
+                    (PhysicalType::BYTE_ARRAY, Some(LogicalType::String))
+                }
+            };
+
+            Type::primitive_type_builder(column.key, physical_type)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(logical_type)
+                .build()
+                .map(Arc::new)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()
+}
+
+// Writes the present values of a column, missing keys and nulls become parquet nulls
+fn write_column<T, F>(
+    writer: &mut SerializedColumnWriter,
+    rows: &[&Map<Cow<str>, Json>],
+    key: &str,
+    value: F,
+) -> Result<(), ParquetError>
+where
+    T: DataType,
+    F: Fn(&Json) -> T::T,
+{
+    let mut values = vec![];
+    let mut definition_levels = vec![];
+
+    for row in rows {
+        match row.get(key) {
+            None | Some(Json::Null | Json::Error) => definition_levels.push(0),
+            Some(val) => {
+                values.push(value(val));
+                definition_levels.push(1);
+            }
+        }
+    }
+
+    let typed: &mut ColumnWriterImpl<T> = writer.typed::<T>();
+    typed.write_batch(&values, Some(&definition_levels), None)?;
+    Ok(())
+}
+
+fn write_row_group<W: Write + Send>(
+    row_group: &mut SerializedRowGroupWriter<W>,
+    rows: &[&Map<Cow<str>, Json>],
+    columns: &[Column],
+) -> Result<(), ParquetError> {
+    for column in columns {
+        let mut writer = match row_group.next_column()? {
+            Some(writer) => writer,
+            None => break,
+        };
+
+        match column.column_type {
+            Some(ColumnType::Boolean) => {
+                write_column::<BoolType, _>(&mut writer, rows, column.key, |val| {
+                    matches!(val, Json::Bool(true))
+                })?
+            }
+            Some(ColumnType::Int64) => {
+                write_column::<Int64Type, _>(&mut writer, rows, column.key, |val| match val {
+                    Json::Number(n) => as_integer(n).unwrap_or_default(),
+                    _ => 0,
+                })?
+            }
+            Some(ColumnType::Double) => {
+                write_column::<DoubleType, _>(&mut writer, rows, column.key, |val| match val {
+                    Json::Number(n) => n.as_f64(),
+                    _ => 0.0,
+                })?
+            }
+            Some(ColumnType::Text) | None => {
+                write_column::<ByteArrayType, _>(&mut writer, rows, column.key, |val| match val {
+                    Json::String(s) => ByteArray::from(s.as_bytes().to_vec()),
+                    _ => ByteArray::from(format_json(val, 0).into_bytes()),
+                })?
+            }
+        }
+
+        writer.close()?;
+    }
+
+    Ok(())
+}
+
+// Writes an array of objects as a table, one column per key
+pub fn to_parquet<W: Write + Send>(val: &Json, out: W) -> Result<(), ParquetExportError> {
+    let val = val.parse_raw();
+    let rows = rows(&val).map_err(ParquetExportError::Conversion)?;
+    let columns = infer_columns(&rows);
+
+    if columns.is_empty() {
+        return Err(ParquetExportError::Conversion(ConversionError::new(
+            ConversionErrorType::NotATable,
+            FORMAT,
+            "",
+        )));
+    }
+
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(out, Arc::new(schema(&columns)?), properties)?;
+
+    for chunk in rows.chunks(ROW_GROUP_SIZE) {
+        let mut row_group = writer.next_row_group()?;
+        write_row_group(&mut row_group, chunk, &columns)?;
+        row_group.close()?;
+    }
+
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parquet::file::reader::FileReader;
+    use parquet::file::serialized_reader::SerializedFileReader;
+    use parser;
+    use std::fs::File;
+
+    #[test]
+    fn test_to_parquet() {
+        let raw = "[{\"id\": 1, \"name\": \"a\\\"b\", \"ok\": true, \"score\": 1.5, \"tags\": [1]},
+                   {\"extra\": null, \"id\": 2, \"score\": 2, \"tags\": \"x\"}]";
+        let tokens = lexer::lex(raw).unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        let path = std::env::temp_dir().join(format!("joxide-test-{}.parquet", std::process::id()));
+        to_parquet(&value, File::create(&path).unwrap()).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            rows,
+            [
+                "{id: 1, name: \"a\"b\", ok: true, score: 1.5, tags: \"[1]\", extra: null}",
+                "{id: 2, name: null, ok: null, score: 2.0, tags: \"x\", extra: null}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_parquet_not_a_table() {
+        for (raw, pointer) in [("{}", ""), ("[{\"a\": 1}, 2]", "/1"), ("[]", "")] {
+            let tokens = lexer::lex(raw).unwrap();
+            let value = parser::parse(&tokens).unwrap();
+
+            match to_parquet(&value, vec![]) {
+                Err(ParquetExportError::Conversion(err)) => assert_eq!(
+                    err,
+                    ConversionError::new(ConversionErrorType::NotATable, FORMAT, pointer)
+                ),
+                other => panic!("{}: unexpected {:?}", raw, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use number::Number;
+use parser::{Json, Map};
+use serde_json::{Map as ValueMap, Number as ValueNumber, Value};
+use std::borrow::Cow;
+
+// Numbers outside of 64 bit integers become floats, and those that do not fit one are null like
+// `Json::Error` since `serde_json` has no infinity
+impl<'a> From<&Json<'a>> for Value {
+    fn from(val: &Json<'a>) -> Self {
+        match val {
+            Json::Null | Json::Error => Value::Null,
+            Json::Raw(_) => Value::from(&*val.parse_raw()),
+            Json::Bool(b) => Value::Bool(*b),
+            Json::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(u), _) => Value::from(u),
+                (None, Some(i)) => Value::from(i),
+                (None, None) => {
+                    ValueNumber::from_f64(n.as_f64()).map_or(Value::Null, Value::Number)
+                }
+            },
+            Json::String(s) => Value::String(s.to_string()),
+            Json::Array(arr) => Value::Array(arr.iter().map(Value::from).collect()),
+            Json::Object(obj) => Value::Object(
+                obj.iter()
+                    .map(|(key, item)| (key.to_string(), Value::from(item)))
+                    .collect::<ValueMap<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl<'a> From<Json<'a>> for Value {
+    fn from(val: Json<'a>) -> Self {
+        Value::from(&val)
+    }
+}
+
+// Strings and keys borrow the text of the value
+impl<'a> From<&'a Value> for Json<'a> {
+    fn from(val: &'a Value) -> Self {
+        match val {
+            Value::Null => Json::Null,
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Number(n) => Json::Number(match (n.as_u64(), n.as_i64()) {
+                (Some(u), _) => Number::from(u),
+                (None, Some(i)) => Number::from(i),
+                (None, None) => Number::from(n.as_f64().unwrap_or_default()),
+            }),
+            Value::String(s) => Json::from(s.as_str()),
+            Value::Array(arr) => Json::Array(arr.iter().map(Json::from).collect()),
+            Value::Object(obj) => Json::Object(
+                obj.iter()
+                    .map(|(key, item)| (Cow::Borrowed(key.as_str()), Json::from(item)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl From<Value> for Json<'static> {
+    fn from(val: Value) -> Self {
+        Json::from(&val).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    #[test]
+    fn test_serde_json() {
+        let raw = r#"{"a\tb": [1, -2, 2.5, "x\"yé"], "c": {"d": true, "e": null}}"#;
+        let value = parser::parse_str(raw).unwrap();
+
+        let expected: Value = serde_json::from_str(raw).unwrap();
+        assert_eq!(Value::from(&value), expected);
+
+        let back = Json::from(&expected);
+        assert!(back.semantic_eq(&value, Default::default()));
+        assert!(matches!(back["c"], Json::Object(_)));
+        assert_eq!(Json::from(expected.clone()), back);
+
+        let big = parser::parse_str("[18446744073709551616, 1e400]").unwrap();
+        assert_eq!(
+            Value::from(big),
+            serde_json::json!([18446744073709551616.0, null])
+        );
+    }
+}
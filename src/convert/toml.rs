@@ -0,0 +1,90 @@
+use super::{as_integer, ConversionError, ConversionErrorType};
+use parser::Json;
+use pointer;
+use std::convert::TryFrom;
+use toml::{map::Map as TomlMap, Value as TomlValue};
+
+fn value(val: &Json, pointer: &str) -> Result<TomlValue, ConversionError> {
+    match val {
+        Json::Null | Json::Error => Err(ConversionError::new(
+            ConversionErrorType::Null,
+            "TOML",
+            pointer,
+        )),
+        Json::Raw(_) => value(&val.parse_raw(), pointer),
+        Json::Bool(b) => Ok(TomlValue::Boolean(*b)),
+        Json::Number(n) => Ok(match as_integer(n) {
+            Some(i) => TomlValue::Integer(i),
+            None => TomlValue::Float(n.as_f64()),
+        }),
+        Json::String(s) => Ok(TomlValue::String(s.to_string())),
+        Json::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(index, item)| value(item, &pointer::push(pointer, &index.to_string())))
+            .collect::<Result<Vec<_>, _>>()
+            .map(TomlValue::Array),
+        Json::Object(obj) => {
+            let mut table = TomlMap::new();
+
+            for (key, item) in obj {
+                let item = value(item, &pointer::push(pointer, key))?;
+                table.insert(key.to_string(), item);
+            }
+
+            Ok(TomlValue::Table(table))
+        }
+    }
+}
+
+// `toml::Value` has an inherent serde based `try_from`, use `(&json).try_into()` to select this impl
+impl<'a> TryFrom<&Json<'a>> for TomlValue {
+    type Error = ConversionError;
+
+    fn try_from(val: &Json<'a>) -> Result<Self, Self::Error> {
+        value(val, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parser;
+    use std::convert::TryInto;
+
+    #[test]
+    fn test_to_toml() {
+        let tokens = lexer::lex("{\"name\": \"a\\tb\", \"n\": [1, 2.5], \"ok\": true}").unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        let mut expected = TomlMap::new();
+        expected.insert("name".to_string(), TomlValue::String("a\tb".to_string()));
+        expected.insert(
+            "n".to_string(),
+            TomlValue::Array(vec![TomlValue::Integer(1), TomlValue::Float(2.5)]),
+        );
+        expected.insert("ok".to_string(), TomlValue::Boolean(true));
+
+        let actual: Result<TomlValue, _> = (&value).try_into();
+
+        assert_eq!(actual, Ok(TomlValue::Table(expected)));
+    }
+
+    #[test]
+    fn test_to_toml_null() {
+        let tokens = lexer::lex("{\"a/b\": [true, null]}").unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        let actual: Result<TomlValue, _> = (&value).try_into();
+
+        assert_eq!(
+            actual,
+            Err(ConversionError::new(
+                ConversionErrorType::Null,
+                "TOML",
+                "/a~1b/1"
+            ))
+        );
+    }
+}
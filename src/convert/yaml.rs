@@ -0,0 +1,60 @@
+use super::as_integer;
+use parser::Json;
+use serde_yaml::{Mapping, Number, Value as YamlValue};
+
+// Every JSON value has a YAML equivalent, `TryFrom` is provided through the blanket impl
+impl<'a> From<&Json<'a>> for YamlValue {
+    fn from(val: &Json<'a>) -> Self {
+        match val {
+            Json::Null | Json::Error => YamlValue::Null,
+            Json::Raw(_) => YamlValue::from(&*val.parse_raw()),
+            Json::Bool(b) => YamlValue::Bool(*b),
+            Json::Number(n) => YamlValue::Number(match as_integer(n) {
+                Some(i) => Number::from(i),
+                None => match n.as_u64() {
+                    Some(u) => Number::from(u),
+                    None => Number::from(n.as_f64()),
+                },
+            }),
+            Json::String(s) => YamlValue::String(s.to_string()),
+            Json::Array(arr) => YamlValue::Sequence(arr.iter().map(YamlValue::from).collect()),
+            Json::Object(obj) => YamlValue::Mapping(
+                obj.iter()
+                    .map(|(key, item)| {
+                        let key = YamlValue::String(key.to_string());
+                        (key, YamlValue::from(item))
+                    })
+                    .collect::<Mapping>(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parser;
+
+    #[test]
+    fn test_to_yaml() {
+        let tokens = lexer::lex("{\"list\": [1, 2.5, null], \"text\": \"caf\\u00e9\"}").unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        let mut expected = Mapping::new();
+        expected.insert(
+            YamlValue::String("list".to_string()),
+            YamlValue::Sequence(vec![
+                YamlValue::Number(Number::from(1)),
+                YamlValue::Number(Number::from(2.5)),
+                YamlValue::Null,
+            ]),
+        );
+        expected.insert(
+            YamlValue::String("text".to_string()),
+            YamlValue::String("café".to_string()),
+        );
+
+        assert_eq!(YamlValue::from(&value), YamlValue::Mapping(expected));
+    }
+}
@@ -0,0 +1,586 @@
+use number::Number;
+use parser::{self, Json, Map, OwnedParseError, ParseErrorType, DEFAULT_MAX_DEPTH};
+use reader::{Event, EventType, Reader};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use std::borrow::Cow;
+use std::fmt;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+
+// Deserializes serde types straight from the events of a `Reader`, without building a `Json`.
+// Errors carry the position of the value they are about, like the parse errors do
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub message: String,
+    // Zero based position, `None` when the input ended too early
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+}
+
+impl Error {
+    // Sets the position unless the error already has a more precise one
+    fn at(mut self, event: &Event) -> Self {
+        if self.line.is_none() {
+            self.line = Some(event.line);
+            self.col = Some(event.col);
+        }
+
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.col) {
+            (Some(line), Some(col)) => write!(f, "{} at {}:{}", self.message, line + 1, col + 1),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            message: msg.to_string(),
+            line: None,
+            col: None,
+        }
+    }
+}
+
+impl From<OwnedParseError> for Error {
+    fn from(parse_error: OwnedParseError) -> Self {
+        Error {
+            message: parse_error.message,
+            line: parse_error.line,
+            col: parse_error.col,
+        }
+    }
+}
+
+pub struct Deserializer<'de> {
+    reader: Peekable<Reader<'de>>,
+    // Open arrays and objects, limited like in `parse_str` since visitors recurse into them
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(s: &'de str) -> Self {
+        Deserializer {
+            reader: Reader::new(s).peekable(),
+            depth: 0,
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Event<'de>, Error> {
+        let event = match self.reader.next() {
+            Some(event) => event?,
+            None => return Err(de::Error::custom("Unexpected end of input")),
+        };
+
+        match event.event_type {
+            EventType::StartObject | EventType::StartArray => {
+                if self.depth >= DEFAULT_MAX_DEPTH {
+                    let error_type = ParseErrorType::DepthLimitExceeded;
+                    return Err(parser::error_at(error_type, event.line, event.col).into());
+                }
+
+                self.depth += 1;
+            }
+            EventType::EndObject | EventType::EndArray => self.depth -= 1,
+            _ => {}
+        }
+
+        Ok(event)
+    }
+
+    fn peek_event_type(&mut self) -> Result<&EventType<'de>, Error> {
+        if let Some(Err(_)) | None = self.reader.peek() {
+            return Err(self.next_event().unwrap_err());
+        }
+
+        match self.reader.peek() {
+            Some(Ok(event)) => Ok(&event.event_type),
+            _ => unreachable!(),
+        }
+    }
+
+    // Checks that nothing but whitespace is left after the value
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.reader.next() {
+            Some(Err(parse_error)) => Err(parse_error.into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Skips the rest of the array or object whose start was just read
+    fn skip_container(&mut self) -> Result<(), Error> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.next_event()?.event_type {
+                EventType::StartObject | EventType::StartArray => depth += 1,
+                EventType::EndObject | EventType::EndArray => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn from_str<'de, T: de::Deserialize<'de>>(s: &'de str) -> Result<T, Error> {
+    let mut deserializer = Deserializer::new(s);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+fn visit_str<'de, V: Visitor<'de>>(s: Cow<'de, str>, visitor: V) -> Result<V::Value, Error> {
+    match s {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_string(s),
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(number: &Number, visitor: V) -> Result<V::Value, Error> {
+    match (number.as_u64(), number.as_i64()) {
+        (Some(n), _) => visitor.visit_u64(n),
+        (None, Some(n)) => visitor.visit_i64(n),
+        (None, None) => visitor.visit_f64(number.as_f64()),
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let event = self.next_event()?;
+
+        let value = match event.event_type {
+            EventType::Null => visitor.visit_unit(),
+            EventType::Bool(b) => visitor.visit_bool(b),
+            EventType::Number(ref number) => visit_number(number, visitor),
+            EventType::String(ref s) => visit_str(s.clone(), visitor),
+            EventType::StartArray => visitor.visit_seq(Items(self)).and_then(|value| {
+                match self.next_event()?.event_type {
+                    EventType::EndArray => Ok(value),
+                    _ => Err(de::Error::custom("Did not expect more items")),
+                }
+            }),
+            EventType::StartObject => visitor.visit_map(Members(self)).and_then(|value| match self
+                .next_event()?
+                .event_type
+            {
+                EventType::EndObject => Ok(value),
+                _ => Err(de::Error::custom("Did not expect more members")),
+            }),
+            // The reader only gives keys and ends inside arrays and objects, where they are read
+            // by `Items` and `Members`
+            EventType::Key(_) | EventType::EndObject | EventType::EndArray => unreachable!(),
+        };
+
+        value.map_err(|error| error.at(&event))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.peek_event_type()? {
+            EventType::Null => {
+                self.next_event()?;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // A unit variant is a string, any other is an object with the variant as its only key
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let event = self.next_event()?;
+
+        let value =
+            match event.event_type {
+                EventType::String(ref s) => visitor.visit_enum(s.to_string().into_deserializer()),
+                EventType::StartObject => visitor.visit_enum(Variant(self)).and_then(|value| {
+                    match self.next_event()?.event_type {
+                        EventType::EndObject => Ok(value),
+                        _ => Err(de::Error::custom("Expected an object with a single key")),
+                    }
+                }),
+                _ => Err(de::Error::custom("Expected a string or an object")),
+            };
+
+        value.map_err(|error| error.at(&event))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.next_event()?.event_type {
+            EventType::StartObject | EventType::StartArray => self.skip_container()?,
+            _ => {}
+        }
+
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct Items<'a, 'de>(&'a mut Deserializer<'de>);
+
+impl<'de> de::SeqAccess<'de> for Items<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.peek_event_type()? {
+            EventType::EndArray => Ok(None),
+            _ => seed.deserialize(&mut *self.0).map(Some),
+        }
+    }
+}
+
+struct Members<'a, 'de>(&'a mut Deserializer<'de>);
+
+impl<'de> de::MapAccess<'de> for Members<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if let EventType::EndObject = self.0.peek_event_type()? {
+            return Ok(None);
+        }
+
+        let event = self.0.next_event()?;
+
+        let key = match event.event_type {
+            EventType::Key(ref key) => match key.clone() {
+                Cow::Borrowed(key) => {
+                    seed.deserialize(de::value::BorrowedStrDeserializer::new(key))
+                }
+                Cow::Owned(key) => seed.deserialize(key.into_deserializer()),
+            },
+            _ => unreachable!(),
+        };
+
+        key.map(Some).map_err(|error: Error| error.at(&event))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.0)
+    }
+}
+
+struct Variant<'a, 'de>(&'a mut Deserializer<'de>);
+
+impl<'de> de::EnumAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = de::MapAccess::next_key_seed(&mut Members(&mut *self.0), seed)?;
+
+        match variant {
+            Some(variant) => Ok((variant, self)),
+            None => Err(de::Error::custom("Expected an object with a single key")),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self.0)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.0, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.0, visitor)
+    }
+}
+
+// Strings are copied, so `JsonOwned` is `DeserializeOwned`. Of repeated keys the last one wins
+impl<'de, 'a> de::Deserialize<'de> for Json<'a> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonVisitor::<'a, Own>(PhantomData))
+    }
+}
+
+// For `#[serde(borrow, deserialize_with = "joxide::de::borrow")]`, strings without escapes borrow
+// the input instead of being copied
+pub fn borrow<'de, D: de::Deserializer<'de>>(deserializer: D) -> Result<Json<'de>, D::Error> {
+    deserializer.deserialize_any(JsonVisitor::<'de, Borrow>(PhantomData))
+}
+
+// Whether the strings the deserializer lends are copied or kept borrowed
+trait Strings<'de, 'a> {
+    fn borrowed(s: &'de str) -> Json<'a>;
+}
+
+struct Own;
+struct Borrow;
+
+impl<'a> Strings<'_, 'a> for Own {
+    fn borrowed(s: &str) -> Json<'a> {
+        Json::from(s.to_string())
+    }
+}
+
+impl<'de> Strings<'de, 'de> for Borrow {
+    fn borrowed(s: &'de str) -> Json<'de> {
+        Json::from(s)
+    }
+}
+
+struct JsonVisitor<'a, S>(PhantomData<(Json<'a>, S)>);
+
+impl<'de, 'a, S: Strings<'de, 'a>> DeserializeSeed<'de> for JsonVisitor<'a, S> {
+    type Value = Json<'a>;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Json<'a>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a, S: Strings<'de, 'a>> Visitor<'de> for JsonVisitor<'a, S> {
+    type Value = Json<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Json<'a>, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Json<'a>, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Json<'a>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Json<'a>, E> {
+        Ok(Json::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Json<'a>, E> {
+        Ok(Json::from(n))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Json<'a>, E> {
+        Ok(Json::from(n))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Json<'a>, E> {
+        Ok(Json::from(n))
+    }
+
+    fn visit_borrowed_str<E>(self, s: &'de str) -> Result<Json<'a>, E> {
+        Ok(S::borrowed(s))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Json<'a>, E> {
+        Ok(Json::from(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Json<'a>, E> {
+        Ok(Json::from(s))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Json<'a>, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(item) = seq.next_element_seed(JsonVisitor::<'a, S>(PhantomData))? {
+            items.push(item);
+        }
+
+        Ok(Json::Array(items))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Json<'a>, A::Error> {
+        let mut object = Map::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let item = map.next_value_seed(JsonVisitor::<'a, S>(PhantomData))?;
+            object.insert(Cow::Owned(key), item);
+        }
+
+        Ok(Json::Object(object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config<'a> {
+        name: String,
+        #[serde(borrow)]
+        tag: Cow<'a, str>,
+        port: u16,
+        ratio: f32,
+        debug: Option<bool>,
+        shapes: Vec<Shape>,
+        limits: HashMap<String, i64>,
+    }
+
+    #[test]
+    fn test_from_str() {
+        let raw = r#"{
+            "name": "a\nb",
+            "tag": "plain",
+            "port": 8080,
+            "ratio": 1,
+            "debug": null,
+            "extra": [{"ignored": [1, 2]}],
+            "shapes": ["Point", {"Circle": 2.5}, {"Rect": {"w": 1, "h": 2}}],
+            "limits": {"low": -1}
+        }"#;
+        let config: Config = from_str(raw).unwrap();
+
+        assert_eq!(config.name, "a\nb");
+        assert!(matches!(config.tag, Cow::Borrowed("plain")));
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.ratio, 1.0);
+        assert_eq!(config.debug, None);
+        assert_eq!(
+            config.shapes,
+            [Shape::Point, Shape::Circle(2.5), Shape::Rect { w: 1, h: 2 }]
+        );
+        assert_eq!(config.limits["low"], -1);
+        assert_eq!(
+            from_str::<(u8, String)>(r#"[1, "x"]"#).unwrap(),
+            (1, "x".into())
+        );
+    }
+
+    #[test]
+    fn test_from_str_errors() {
+        let error = from_str::<HashMap<String, u8>>("{\"a\": 1,\n \"b\": 300}").unwrap_err();
+        assert_eq!((error.line, error.col), (Some(1), Some(6)));
+        assert!(error.message.contains("300"), "{}", error.message);
+
+        let error = from_str::<Vec<u8>>("[1, 2,]").unwrap_err();
+        assert_eq!(error.to_string(), "Trailing commas are not valid at 1:6");
+
+        let error = from_str::<(u8,)>("[1, 2]").unwrap_err();
+        assert_eq!(error.message, "Did not expect more items");
+
+        let error = from_str::<u8>("1 2").unwrap_err();
+        assert_eq!((error.line, error.col), (Some(0), Some(2)));
+
+        assert!(from_str::<Shape>("\"Square\"").is_err());
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Message<'a> {
+        kind: String,
+        #[serde(borrow, deserialize_with = "borrow")]
+        payload: Json<'a>,
+    }
+
+    // Only depends on the input while deserializing
+    fn from_string<T: de::DeserializeOwned>(raw: String) -> T {
+        from_str(&raw).unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_json() {
+        let raw = r#"{"kind": "x", "payload": {"a\tb": ["c\"d", 1.5, null, {}]}}"#;
+        let message: Message = from_str(raw).unwrap();
+
+        assert_eq!(message.kind, "x");
+        assert_eq!(
+            message.payload,
+            parser::parse_str(r#"{"a\tb": ["c\"d", 1.5, null, {}]}"#).unwrap()
+        );
+        assert_eq!(from_str::<Json>("[-1, true]").unwrap(), json!([-1, true]));
+
+        let message: Message = from_str(r#"{"kind": "x", "payload": ["plain"]}"#).unwrap();
+        assert!(matches!(
+            message.payload[0],
+            Json::String(Cow::Borrowed("plain"))
+        ));
+
+        let owned: parser::JsonOwned = from_string(r#"{"a": ["b"]}"#.to_string());
+        assert!(matches!(owned["a"][0], Json::String(Cow::Owned(_))));
+        assert_eq!(owned, json!({"a": ["b"]}));
+    }
+
+    #[test]
+    fn test_from_str_depth() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+
+        // Visitors recurse into arrays, more than the 2 MB stack of test threads holds in debug
+        // builds at the limit
+        let check = move || {
+            let error = from_str::<parser::JsonOwned>(&"[".repeat(200_000)).unwrap_err();
+            assert_eq!((error.line, error.col), (Some(0), Some(DEFAULT_MAX_DEPTH)));
+            assert!(error.message.contains("nested"), "{}", error.message);
+
+            let limit = nested(DEFAULT_MAX_DEPTH);
+            assert!(from_str::<parser::JsonOwned>(&limit).is_ok());
+            assert!(from_str::<parser::JsonOwned>(&nested(DEFAULT_MAX_DEPTH + 1)).is_err());
+            assert!(from_str::<de::IgnoredAny>(&nested(DEFAULT_MAX_DEPTH + 1)).is_err());
+
+            #[cfg(feature = "serde_json")]
+            {
+                assert!(from_str::<serde_json::Value>(&limit).is_ok());
+                assert!(from_str::<serde_json::Value>(&"[".repeat(200_000)).is_err());
+            }
+        };
+
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(check)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
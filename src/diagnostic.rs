@@ -1,36 +1,365 @@
 use crate::{
-    lexer::TokenType,
+    encoding::InvalidUtf8,
+    lexer::{LexError, LexErrorKind, TokenType},
     parser::{ParseError, ParseErrorType},
 };
 
-fn get_message_unexpected_token<'a>(parse_error: &'a ParseError<'a>) -> String {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    English,
+    Spanish,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::Spanish];
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Spanish => "es",
+        }
+    }
+
+    // Accepts plain tags like `es` as well as POSIX locales like `es_ES.UTF-8`
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        Locale::ALL
+            .iter()
+            .copied()
+            .find(|locale| locale.tag() == language)
+    }
+
+    // Checks JOXIDE_LOCALE, then the usual POSIX variables, defaulting to English
+    pub fn from_env() -> Locale {
+        ["JOXIDE_LOCALE", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_tag(&value))
+            .unwrap_or(Locale::English)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageId {
+    UnexpectedEnd,
+    UnexpectedWord,
+    UnexpectedToken,
+    UnexpectedTokenExpected,
+    ForgotComma,
+    TrailingComma,
+    DuplicateKey,
+    KeyNotInQuotes,
+    MissingColon,
+    Cancelled,
+    MemoryLimitExceeded,
+    Comment,
+    DepthLimitExceeded,
+    UnterminatedString,
+    InvalidEscape,
+    MalformedNumber,
+    UnknownCharacter,
+    UnterminatedComment,
+    ControlCharacter,
+    InvalidUtf8,
+}
+
+impl MessageId {
+    pub const ALL: [MessageId; 20] = [
+        MessageId::UnexpectedEnd,
+        MessageId::UnexpectedWord,
+        MessageId::UnexpectedToken,
+        MessageId::UnexpectedTokenExpected,
+        MessageId::ForgotComma,
+        MessageId::TrailingComma,
+        MessageId::DuplicateKey,
+        MessageId::KeyNotInQuotes,
+        MessageId::MissingColon,
+        MessageId::Cancelled,
+        MessageId::MemoryLimitExceeded,
+        MessageId::Comment,
+        MessageId::DepthLimitExceeded,
+        MessageId::UnterminatedString,
+        MessageId::InvalidEscape,
+        MessageId::MalformedNumber,
+        MessageId::UnknownCharacter,
+        MessageId::UnterminatedComment,
+        MessageId::ControlCharacter,
+        MessageId::InvalidUtf8,
+    ];
+
+    // Stable identifier used to override messages from the config file
+    pub fn code(self) -> &'static str {
+        match self {
+            MessageId::UnexpectedEnd => "unexpected-end",
+            MessageId::UnexpectedWord => "unexpected-word",
+            MessageId::UnexpectedToken => "unexpected-token",
+            MessageId::UnexpectedTokenExpected => "unexpected-token-expected",
+            MessageId::ForgotComma => "forgot-comma",
+            MessageId::TrailingComma => "trailing-comma",
+            MessageId::DuplicateKey => "duplicate-key",
+            MessageId::KeyNotInQuotes => "key-not-in-quotes",
+            MessageId::MissingColon => "missing-colon",
+            MessageId::Cancelled => "cancelled",
+            MessageId::MemoryLimitExceeded => "memory-limit-exceeded",
+            MessageId::Comment => "comment",
+            MessageId::DepthLimitExceeded => "depth-limit-exceeded",
+            MessageId::UnterminatedString => "unterminated-string",
+            MessageId::InvalidEscape => "invalid-escape",
+            MessageId::MalformedNumber => "malformed-number",
+            MessageId::UnknownCharacter => "unknown-character",
+            MessageId::UnterminatedComment => "unterminated-comment",
+            MessageId::ControlCharacter => "control-character",
+            MessageId::InvalidUtf8 => "invalid-utf8",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<MessageId> {
+        MessageId::ALL.iter().copied().find(|id| id.code() == code)
+    }
+}
+
+// Templates may use the {token}, {expected}, {path}, {line} and {col} placeholders, {char} for
+// unknown characters and {byte} and {encoding} for invalid UTF-8
+pub fn template(locale: Locale, id: MessageId) -> &'static str {
+    match locale {
+        Locale::English => match id {
+            MessageId::UnexpectedEnd => "File ended unexpectedly",
+            MessageId::UnexpectedWord => "Did not expect this word or character",
+            MessageId::UnexpectedToken => "Did not expect '{token}'",
+            MessageId::UnexpectedTokenExpected => "Did not expect '{token}', expected '{expected}'",
+            MessageId::ForgotComma => {
+                "Did not expect '{token}', expected '{expected}'. Forgot a comma maybe?"
+            }
+            MessageId::TrailingComma => "Trailing commas are not valid",
+            MessageId::DuplicateKey => "Duplicate keys are not valid",
+            MessageId::KeyNotInQuotes => "Key should be in quotes",
+            MessageId::MissingColon => "Missing a ':' separator",
+            MessageId::Cancelled => "Parsing was cancelled",
+            MessageId::MemoryLimitExceeded => "Document is larger than the memory limit",
+            MessageId::Comment => "Comments are not valid",
+            MessageId::DepthLimitExceeded => "Arrays and objects are nested deeper than the limit",
+            MessageId::UnterminatedString => "Unterminated string",
+            MessageId::InvalidEscape => "Invalid escape sequence",
+            MessageId::MalformedNumber => "Malformed number",
+            MessageId::UnknownCharacter => "Unknown character '{char}'",
+            MessageId::UnterminatedComment => "Unterminated comment",
+            MessageId::ControlCharacter => "Control characters must be escaped in strings",
+            MessageId::InvalidUtf8 => {
+                "Input is not valid UTF-8 at byte {byte}, it may be {encoding}"
+            }
+        },
+        Locale::Spanish => match id {
+            MessageId::UnexpectedEnd => "El archivo terminó inesperadamente",
+            MessageId::UnexpectedWord => "No se esperaba esta palabra o carácter",
+            MessageId::UnexpectedToken => "No se esperaba '{token}'",
+            MessageId::UnexpectedTokenExpected => {
+                "No se esperaba '{token}', se esperaba '{expected}'"
+            }
+            MessageId::ForgotComma => {
+                "No se esperaba '{token}', se esperaba '{expected}'. ¿Falta una coma quizás?"
+            }
+            MessageId::TrailingComma => "Las comas finales no son válidas",
+            MessageId::DuplicateKey => "Las claves duplicadas no son válidas",
+            MessageId::KeyNotInQuotes => "La clave debe ir entre comillas",
+            MessageId::MissingColon => "Falta el separador ':'",
+            MessageId::Cancelled => "Se canceló el análisis",
+            MessageId::MemoryLimitExceeded => "El documento supera el límite de memoria",
+            MessageId::Comment => "Los comentarios no son válidos",
+            MessageId::DepthLimitExceeded => {
+                "Los arrays y objetos superan el límite de anidamiento"
+            }
+            MessageId::UnterminatedString => "Cadena sin terminar",
+            MessageId::InvalidEscape => "Secuencia de escape no válida",
+            MessageId::MalformedNumber => "Número mal formado",
+            MessageId::UnknownCharacter => "Carácter desconocido '{char}'",
+            MessageId::UnterminatedComment => "Comentario sin terminar",
+            MessageId::ControlCharacter => {
+                "Los caracteres de control deben escaparse en las cadenas"
+            }
+            MessageId::InvalidUtf8 => {
+                "La entrada no es UTF-8 válido en el byte {byte}, puede ser {encoding}"
+            }
+        },
+    }
+}
+
+fn get_message_id_unexpected_token(parse_error: &ParseError) -> MessageId {
     match parse_error.token {
-        None => "Did not expect this word or character".to_string(),
-        Some(token) => match parse_error.expected {
-            None => format!("Did not expect '{}'", token.token_type),
-            Some(expected) => match expected {
-                TokenType::CloseCurly | TokenType::CloseSquare => format!(
-                    "Did not expect '{}', expected '{}'. Forgot a comma maybe?",
-                    token.token_type, expected
-                ),
-                _ => format!(
-                    "Did not expect '{}', expected '{}'",
-                    token.token_type, expected
-                ),
-            },
+        None => MessageId::UnexpectedWord,
+        Some(_) => match parse_error.expected {
+            None => MessageId::UnexpectedToken,
+            Some(TokenType::CloseCurly) | Some(TokenType::CloseSquare) => MessageId::ForgotComma,
+            Some(_) => MessageId::UnexpectedTokenExpected,
         },
     }
 }
 
-pub fn get_message<'a>(parse_error: &'a ParseError<'a>) -> String {
+pub fn get_message_id(parse_error: &ParseError) -> MessageId {
     match parse_error.error_type {
-        ParseErrorType::UnexpectedEnd => "File ended unexpectedly".to_string(),
+        ParseErrorType::UnexpectedEnd => MessageId::UnexpectedEnd,
         ParseErrorType::UnexpectedToken
         | ParseErrorType::MissingCloseCurly
-        | ParseErrorType::MissingCloseSquare => get_message_unexpected_token(parse_error),
-        ParseErrorType::TrailingComma => "Trailing commas are not valid".to_string(),
-        ParseErrorType::DuplicateKey => "Duplicate keys are not valid".to_string(),
-        ParseErrorType::KeyNotInQuotes => "Key should be in quotes".to_string(),
-        ParseErrorType::MissingColon => "Missing a ':' separator".to_string(),
+        | ParseErrorType::MissingCloseSquare => get_message_id_unexpected_token(parse_error),
+        ParseErrorType::TrailingComma => MessageId::TrailingComma,
+        ParseErrorType::DuplicateKey => MessageId::DuplicateKey,
+        ParseErrorType::KeyNotInQuotes => MessageId::KeyNotInQuotes,
+        ParseErrorType::MissingColon => MessageId::MissingColon,
+        ParseErrorType::Cancelled => MessageId::Cancelled,
+        ParseErrorType::MemoryLimitExceeded => MessageId::MemoryLimitExceeded,
+        ParseErrorType::Comment => MessageId::Comment,
+        ParseErrorType::DepthLimitExceeded => MessageId::DepthLimitExceeded,
+        ParseErrorType::Lex(kind) => get_lex_message_id(kind),
+        ParseErrorType::InvalidUtf8(_) => MessageId::InvalidUtf8,
+    }
+}
+
+pub fn get_lex_message_id(kind: LexErrorKind) -> MessageId {
+    match kind {
+        LexErrorKind::UnterminatedString => MessageId::UnterminatedString,
+        LexErrorKind::InvalidEscape => MessageId::InvalidEscape,
+        LexErrorKind::MalformedNumber => MessageId::MalformedNumber,
+        LexErrorKind::UnknownCharacter(_) => MessageId::UnknownCharacter,
+        LexErrorKind::UnterminatedComment => MessageId::UnterminatedComment,
+        LexErrorKind::ControlCharacter => MessageId::ControlCharacter,
+    }
+}
+
+fn render_position(template: &str, path: &str, line: usize, col: usize) -> String {
+    template
+        .replace("{path}", path)
+        .replace("{line}", &(line + 1).to_string())
+        .replace("{col}", &(col + 1).to_string())
+}
+
+// `path` is the file being checked, line and column are one based
+pub fn render(template: &str, parse_error: &ParseError, path: &str) -> String {
+    let mut message = template.replace("{path}", path);
+
+    if let Some(token) = parse_error.token {
+        message = message
+            .replace("{token}", &token.token_type.to_string())
+            .replace("{line}", &(token.line + 1).to_string())
+            .replace("{col}", &(token.col + 1).to_string());
+    }
+
+    if let Some(expected) = parse_error.expected {
+        message = message.replace("{expected}", &expected.to_string());
+    }
+
+    message
+}
+
+pub fn render_lex(template: &str, lex_error: &LexError, path: &str) -> String {
+    let message = render_position(template, path, lex_error.line, lex_error.col);
+
+    match lex_error.kind {
+        LexErrorKind::UnknownCharacter(c) => message.replace("{char}", &c.to_string()),
+        _ => message,
+    }
+}
+
+pub fn render_invalid_utf8(template: &str, invalid: &InvalidUtf8, path: &str) -> String {
+    render_position(template, path, invalid.line, invalid.col)
+        .replace("{byte}", &invalid.offset.to_string())
+        .replace("{encoding}", invalid.hint.label())
+}
+
+pub fn get_message<'a>(parse_error: &'a ParseError<'a>, locale: Locale) -> String {
+    render(
+        template(locale, get_message_id(parse_error)),
+        parse_error,
+        "",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding;
+    use lexer;
+    use parser;
+
+    #[test]
+    fn test_locale_from_tag() {
+        let cases = vec![
+            ("en", Some(Locale::English)),
+            ("es_ES.UTF-8", Some(Locale::Spanish)),
+            ("ES-mx", Some(Locale::Spanish)),
+            ("C", None),
+            ("", None),
+        ];
+
+        for (tag, expected) in cases {
+            assert_eq!(Locale::from_tag(tag), expected, "{}", tag);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let tokens = lexer::lex("{\n  \"a\": 1,\n  \"a\": 2\n}").unwrap();
+        let parse_error = parser::parse(&tokens).unwrap_err();
+
+        assert_eq!(
+            render(
+                "{path}:{line}:{col} duplicate {token}, see https://example.com/{token}",
+                &parse_error,
+                "config.json"
+            ),
+            "config.json:3:3 duplicate a, see https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn test_message_codes() {
+        for id in MessageId::ALL {
+            assert_eq!(MessageId::from_code(id.code()), Some(id));
+        }
+    }
+
+    #[test]
+    fn test_get_message() {
+        let tokens = lexer::lex("[1 2]").unwrap();
+        let parse_error = parser::parse(&tokens).unwrap_err();
+
+        assert_eq!(
+            get_message(&parse_error, Locale::English),
+            "Did not expect '2', expected ']'. Forgot a comma maybe?"
+        );
+        assert_eq!(
+            get_message(&parse_error, Locale::Spanish),
+            "No se esperaba '2', se esperaba ']'. ¿Falta una coma quizás?"
+        );
+    }
+
+    #[test]
+    fn test_lex_messages() {
+        let lex_error = lexer::lex("[1,\n #]").unwrap_err();
+        let id = get_lex_message_id(lex_error.kind);
+
+        assert_eq!(id.code(), lex_error.kind.code());
+        assert_eq!(
+            render_lex(template(Locale::Spanish, id), &lex_error, "a.json"),
+            "Carácter desconocido '#'"
+        );
+        assert_eq!(
+            render_lex("{path}:{line}:{col} {char}", &lex_error, "a.json"),
+            "a.json:2:2 #"
+        );
+
+        let invalid = encoding::from_utf8(b"[\"\xe9\"]").unwrap_err();
+        assert_eq!(
+            render_invalid_utf8(
+                template(Locale::English, MessageId::InvalidUtf8),
+                &invalid,
+                ""
+            ),
+            "Input is not valid UTF-8 at byte 2, it may be windows-1252"
+        );
     }
 }
@@ -0,0 +1,445 @@
+use compare::{self, EqOptions};
+use parser::{Json, Map};
+use pointer;
+use std::borrow::Cow;
+use std::iter;
+
+// One operation of an RFC 6902 JSON Patch, paths are JSON pointers. `diff` never makes `Test`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation<'a> {
+    Add { path: String, value: Json<'a> },
+    Remove { path: String },
+    Replace { path: String, value: Json<'a> },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Json<'a> },
+}
+
+impl<'a> Operation<'a> {
+    pub fn path(&self) -> &str {
+        match self {
+            Operation::Add { path, .. }
+            | Operation::Remove { path }
+            | Operation::Replace { path, .. }
+            | Operation::Move { path, .. }
+            | Operation::Copy { path, .. }
+            | Operation::Test { path, .. } => path,
+        }
+    }
+
+    // The operation as a member of a JSON Patch document, like `{"op": "remove", "path": "/a"}`
+    pub fn to_json(&self) -> Json<'a> {
+        let (op, from, value) = match self {
+            Operation::Add { value, .. } => ("add", None, Some(value)),
+            Operation::Remove { .. } => ("remove", None, None),
+            Operation::Replace { value, .. } => ("replace", None, Some(value)),
+            Operation::Move { from, .. } => ("move", Some(from), None),
+            Operation::Copy { from, .. } => ("copy", Some(from), None),
+            Operation::Test { value, .. } => ("test", None, Some(value)),
+        };
+
+        let mut obj = Map::new();
+        obj.insert(Cow::Borrowed("op"), Json::from(op));
+        if let Some(from) = from {
+            obj.insert(Cow::Borrowed("from"), Json::from(from.clone()));
+        }
+        obj.insert(Cow::Borrowed("path"), Json::from(self.path().to_string()));
+        if let Some(value) = value {
+            obj.insert(Cow::Borrowed("value"), value.clone());
+        }
+
+        Json::Object(obj)
+    }
+}
+
+// Which operations `diff` may use besides add, remove and replace, and how array items are
+// matched
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffOptions {
+    // A member removed and another added with the same value in the same object becomes a
+    // `move`, and a member added with the same value as an unchanged one becomes a `copy`
+    pub detect_moves: bool,
+    // JSON pointer, like `/id`, of the key of array items. Items with equal keys are the same
+    // item, diffed member by member, and items with other keys are never diffed with each other
+    pub by: Option<String>,
+    // Values at these JSON pointers, where `*` matches any key or index, are never changes, like
+    // timestamps or generated IDs
+    pub ignore_paths: Vec<String>,
+}
+
+// Arrays with more pairs of items to compare than this are diffed by position
+const MAX_ALIGNED: usize = 1 << 22;
+
+// The JSON Patch document of the operations
+pub fn to_patch<'a>(operations: &[Operation<'a>]) -> Json<'a> {
+    Json::Array(operations.iter().map(Operation::to_json).collect())
+}
+
+// Operations that turn `from` into `to` when applied in order. Values are compared with
+// `semantic_eq`, so key order and how numbers are written are not changes. Arrays keep the
+// longest run of items they have in the same order, like `diff` keeps lines, so an item inserted
+// near the start is one `add`. The items in between are diffed by position
+pub fn diff<'a>(from: &Json<'a>, to: &Json<'a>, options: &DiffOptions) -> Vec<Operation<'a>> {
+    let mut operations = vec![];
+    diff_value(from, to, "", options, &mut operations);
+    operations
+}
+
+fn same(a: &Json, b: &Json) -> bool {
+    compare::semantic_eq(a, b, EqOptions::default())
+}
+
+fn diff_value<'a>(
+    from: &Json<'a>,
+    to: &Json<'a>,
+    path: &str,
+    options: &DiffOptions,
+    operations: &mut Vec<Operation<'a>>,
+) {
+    if compare::ignored(path, &options.ignore_paths) {
+        return;
+    }
+
+    match (from, to) {
+        (Json::Raw(_), _) | (_, Json::Raw(_)) => diff_value(
+            &from.parse_raw(),
+            &to.parse_raw(),
+            path,
+            options,
+            operations,
+        ),
+        (Json::Object(from), Json::Object(to)) => diff_objects(from, to, path, options, operations),
+        (Json::Array(from), Json::Array(to)) => diff_arrays(from, to, path, options, operations),
+        _ if same(from, to) => {}
+        _ => operations.push(Operation::Replace {
+            path: path.to_string(),
+            value: to.clone(),
+        }),
+    }
+}
+
+fn diff_objects<'a>(
+    from: &Map<Cow<'a, str>, Json<'a>>,
+    to: &Map<Cow<'a, str>, Json<'a>>,
+    path: &str,
+    options: &DiffOptions,
+    operations: &mut Vec<Operation<'a>>,
+) {
+    let ignored = |key: &str| compare::ignored(&pointer::push(path, key), &options.ignore_paths);
+    let mut added: Vec<_> = to
+        .iter()
+        .filter(|(key, _)| !from.contains_key(*key) && !ignored(key))
+        .map(Some)
+        .collect();
+
+    for (key, item) in from {
+        let member = pointer::push(path, key);
+
+        match to.get(key) {
+            Some(other) => diff_value(item, other, &member, options, operations),
+            None if ignored(key) => {}
+            None => {
+                let renamed = added
+                    .iter_mut()
+                    .find(|added| {
+                        options.detect_moves
+                            && matches!(added, Some((_, value)) if same(item, value))
+                    })
+                    .and_then(Option::take);
+
+                operations.push(match renamed {
+                    Some((new_key, _)) => Operation::Move {
+                        from: member,
+                        path: pointer::push(path, new_key),
+                    },
+                    None => Operation::Remove { path: member },
+                });
+            }
+        }
+    }
+
+    for (key, item) in added.into_iter().flatten() {
+        let unchanged = from.iter().find(|(other, value)| {
+            options.detect_moves
+                && same(value, item)
+                && to.get(*other).is_some_and(|to| same(value, to))
+        });
+
+        operations.push(match unchanged {
+            Some((other, _)) => Operation::Copy {
+                from: pointer::push(path, other),
+                path: pointer::push(path, key),
+            },
+            None => Operation::Add {
+                path: pointer::push(path, key),
+                value: item.clone(),
+            },
+        });
+    }
+}
+
+fn diff_arrays<'a>(
+    from: &[Json<'a>],
+    to: &[Json<'a>],
+    path: &str,
+    options: &DiffOptions,
+    operations: &mut Vec<Operation<'a>>,
+) {
+    let start = from.iter().zip(to).take_while(|(a, b)| same(a, b)).count();
+    let end = from[start..]
+        .iter()
+        .rev()
+        .zip(to[start..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let from = &from[start..from.len() - end];
+    let to = &to[start..to.len() - end];
+    let mut index = start;
+    let mut last = (0, 0);
+
+    let kept = align(from, to, options.by.as_deref());
+    for (i, j) in kept.into_iter().chain(iter::once((from.len(), to.len()))) {
+        let (removed, added) = (&from[last.0..i], &to[last.1..j]);
+        let paired = match options.by {
+            Some(_) => 0,
+            None => removed.len().min(added.len()),
+        };
+
+        for (a, b) in removed.iter().zip(added).take(paired) {
+            diff_value(
+                a,
+                b,
+                &pointer::push(path, &index.to_string()),
+                options,
+                operations,
+            );
+            index += 1;
+        }
+
+        // From the last one so the indexes of the others don't shift
+        for k in (paired..removed.len()).rev() {
+            operations.push(Operation::Remove {
+                path: pointer::push(path, &(index + k - paired).to_string()),
+            });
+        }
+
+        for item in &added[paired..] {
+            operations.push(Operation::Add {
+                path: pointer::push(path, &index.to_string()),
+                value: item.clone(),
+            });
+            index += 1;
+        }
+
+        if let (Some(a), Some(b)) = (from.get(i), to.get(j)) {
+            diff_value(
+                a,
+                b,
+                &pointer::push(path, &index.to_string()),
+                options,
+                operations,
+            );
+            index += 1;
+        }
+
+        last = (i + 1, j + 1);
+    }
+}
+
+// The indexes of the items of `from` and `to` that are kept, the longest run of them in order.
+// Items are kept when they are equal, or with `by` when their keys are. Items without a key are
+// kept when they are equal
+pub(crate) fn align(from: &[Json], to: &[Json], by: Option<&str>) -> Vec<(usize, usize)> {
+    let (n, m) = (from.len(), to.len());
+    if n.saturating_mul(m) > MAX_ALIGNED {
+        return vec![];
+    }
+
+    let matches = |a: &Json, b: &Json| match by.map(|by| (a.pointer(by), b.pointer(by))) {
+        Some((Some(a), Some(b))) => same(a, b),
+        Some((None, None)) | None => same(a, b),
+        Some(_) => false,
+    };
+
+    // The length of the longest run kept of `from[i..]` and `to[j..]`
+    let width = m + 1;
+    let mut lengths = vec![0u32; (n + 1) * width];
+    let mut matched = vec![false; n * m];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * width + j] = if matches(&from[i], &to[j]) {
+                matched[i * m + j] = true;
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut kept = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if matched[i * m + j] {
+            kept.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i * width + j + 1] >= lengths[(i + 1) * width + j] {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formatter;
+    use parser;
+
+    #[test]
+    fn test_diff() {
+        let from = parser::parse_str(
+            r#"{"a": 1, "b": {"c": "x", "d": true}, "e": [1, 2, 3, 4], "f": [1], "g": 1.0}"#,
+        )
+        .unwrap();
+        let to = parser::parse_str(
+            r#"{"a": 1, "b": {"c": "y", "e/f": null}, "e": [1, 5, 4], "f": [0, 1], "g": 1}"#,
+        )
+        .unwrap();
+
+        let operations = diff(&from, &to, &DiffOptions::default());
+        assert_eq!(
+            to_patch(&operations),
+            parser::parse_str(
+                r#"[
+                    {"op": "replace", "path": "/b/c", "value": "y"},
+                    {"op": "remove", "path": "/b/d"},
+                    {"op": "add", "path": "/b/e~1f", "value": null},
+                    {"op": "replace", "path": "/e/1", "value": 5},
+                    {"op": "remove", "path": "/e/2"},
+                    {"op": "add", "path": "/f/0", "value": 0}
+                ]"#
+            )
+            .unwrap()
+        );
+
+        assert_eq!(diff(&from, &from, &DiffOptions::default()), []);
+        assert_eq!(
+            diff(&Json::from(1), &Json::from("1"), &DiffOptions::default()),
+            [Operation::Replace {
+                path: "".to_string(),
+                value: Json::from("1")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_moves() {
+        let from = parser::parse_str(r#"{"a": {"x": [1]}, "b": 2, "c": 3}"#).unwrap();
+        let to = parser::parse_str(r#"{"z": {"x": [1]}, "b": 2, "c": 3, "d": 2}"#).unwrap();
+        let options = DiffOptions {
+            detect_moves: true,
+            ..DiffOptions::default()
+        };
+
+        assert_eq!(
+            diff(&from, &to, &options),
+            [
+                Operation::Move {
+                    from: "/a".to_string(),
+                    path: "/z".to_string()
+                },
+                Operation::Copy {
+                    from: "/b".to_string(),
+                    path: "/d".to_string()
+                },
+            ]
+        );
+        assert_eq!(diff(&from, &to, &DiffOptions::default()).len(), 3);
+    }
+
+    #[test]
+    fn test_diff_arrays() {
+        let patch = |from: &str, to: &str, by: Option<&str>| {
+            let (from, to) = (
+                parser::parse_str(from).unwrap(),
+                parser::parse_str(to).unwrap(),
+            );
+            let options = DiffOptions {
+                by: by.map(String::from),
+                ..DiffOptions::default()
+            };
+            let operations = diff(&from, &to, &options);
+
+            let mut value = from.clone();
+            value.apply_patch(&to_patch(&operations)).unwrap();
+            assert_eq!(value, to);
+
+            formatter::format_json(&to_patch(&operations), 0)
+        };
+
+        // An item inserted at the start does not shift the others
+        assert_eq!(
+            patch("[1, 2, 3, 4, 5]", "[0, 1, 2, 3, 4, 6]", None),
+            r#"[{"op":"add","path":"/0","value":0},{"op":"replace","path":"/5","value":6}]"#
+        );
+        assert_eq!(
+            patch("[1, 2, 3, 4]", "[4, 2, 5]", None),
+            r#"[{"op":"replace","path":"/0","value":4},{"op":"replace","path":"/2","value":5},{"op":"remove","path":"/3"}]"#
+        );
+        assert_eq!(
+            patch("[1, 2, 3, 4, 5]", "[5, 2]", None),
+            r#"[{"op":"replace","path":"/0","value":5},{"op":"remove","path":"/4"},{"op":"remove","path":"/3"},{"op":"remove","path":"/2"}]"#
+        );
+
+        let from = r#"[{"id": 1, "v": 1}, {"id": 2, "v": 2}]"#;
+        let to = r#"[{"id": 2, "v": 3}, {"id": 3}]"#;
+        assert_eq!(
+            patch(from, to, Some("/id")),
+            r#"[{"op":"remove","path":"/0"},{"op":"replace","path":"/0/v","value":3},{"op":"add","path":"/1","value":{"id":3}}]"#
+        );
+        assert_eq!(patch(from, to, None).matches("replace").count(), 3);
+        assert_eq!(
+            patch(
+                r#"[{"id": 1}, 2, {"id": 1}]"#,
+                r#"[2, {"id": 1, "a": 0}]"#,
+                Some("/id")
+            ),
+            r#"[{"op":"remove","path":"/0"},{"op":"add","path":"/1/a","value":0}]"#
+        );
+    }
+    #[test]
+    fn test_diff_ignore_paths() {
+        let from = parser::parse_str(
+            r#"{"generatedAt": 1, "items": [{"id": 1, "v": 1}, {"id": 2}], "old": 0}"#,
+        )
+        .unwrap();
+        let to =
+            parser::parse_str(r#"{"items": [{"id": 3, "v": 2}, {"id": 4}], "new": 0}"#).unwrap();
+        let options = DiffOptions {
+            ignore_paths: vec![
+                "/generatedAt".to_string(),
+                "/new".to_string(),
+                "/items/*/id".to_string(),
+            ],
+            ..DiffOptions::default()
+        };
+
+        assert_eq!(
+            diff(&from, &to, &options),
+            [
+                Operation::Replace {
+                    path: "/items/0/v".to_string(),
+                    value: Json::from(2)
+                },
+                Operation::Remove {
+                    path: "/old".to_string()
+                },
+            ]
+        );
+        assert_eq!(diff(&from, &to, &DiffOptions::default()).len(), 6);
+    }
+}
@@ -0,0 +1,411 @@
+use joxide::diff::Operation;
+use joxide::parser::Json;
+use joxide::patch;
+use std::ops::Range;
+use std::slice;
+
+// Rendering of `joxide diff`: line views of both documents formatted the same way, and the
+// structural changes one value per line
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+// Texts with more pairs of lines to compare than this only keep their common start and end
+const MAX_ALIGNED: usize = 1 << 22;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    // Like `diff -u`, changed lines with the lines around them
+    Unified,
+    // The lines of both documents next to each other
+    SideBySide,
+    // One line per value added, removed or changed, with its JSON pointer
+    Tree,
+}
+
+impl View {
+    pub fn from_name(name: &str) -> Option<View> {
+        match name {
+            "unified" => Some(View::Unified),
+            "side-by-side" => Some(View::SideBySide),
+            "tree" => Some(View::Tree),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same,
+    Removed,
+    Added,
+}
+
+// A line of the edit script, with how many lines of each text come before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Line<'t> {
+    edit: Edit,
+    text: &'t str,
+    from: usize,
+    to: usize,
+}
+
+fn paint(text: &str, color: &str, colored: bool) -> String {
+    if colored {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+// The lines of both texts in order, kept on their longest common run like `diff::diff` keeps
+// array items, the others removed before the ones added in their place
+fn script<'t>(from: &'t str, to: &'t str) -> Vec<Line<'t>> {
+    let from: Vec<&str> = from.lines().collect();
+    let to: Vec<&str> = to.lines().collect();
+    let start = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let end = from[start..]
+        .iter()
+        .rev()
+        .zip(to[start..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (middle_from, middle_to) = (&from[start..from.len() - end], &to[start..to.len() - end]);
+
+    let mut edits = vec![Edit::Same; start];
+    let (n, m) = (middle_from.len(), middle_to.len());
+
+    if n.saturating_mul(m) > MAX_ALIGNED {
+        edits.extend((0..n).map(|_| Edit::Removed));
+        edits.extend((0..m).map(|_| Edit::Added));
+    } else {
+        // The length of the longest common run of `middle_from[i..]` and `middle_to[j..]`
+        let width = m + 1;
+        let mut lengths = vec![0u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i * width + j] = if middle_from[i] == middle_to[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && middle_from[i] == middle_to[j] {
+                edits.push(Edit::Same);
+                i += 1;
+                j += 1;
+            } else if j == m
+                || (i < n && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+            {
+                edits.push(Edit::Removed);
+                i += 1;
+            } else {
+                edits.push(Edit::Added);
+                j += 1;
+            }
+        }
+    }
+
+    edits.extend((0..end).map(|_| Edit::Same));
+
+    let (mut i, mut j) = (0, 0);
+    edits
+        .into_iter()
+        .map(|edit| {
+            let text = match edit {
+                Edit::Added => to[j],
+                _ => from[i],
+            };
+            let line = Line {
+                edit,
+                text,
+                from: i,
+                to: j,
+            };
+
+            if edit != Edit::Added {
+                i += 1;
+            }
+            if edit != Edit::Removed {
+                j += 1;
+            }
+
+            line
+        })
+        .collect()
+}
+
+// The parts of the script with changes and `context` lines around them, parts closer than that
+// are one
+fn hunks(script: &[Line], context: usize) -> Vec<Range<usize>> {
+    let mut hunks: Vec<Range<usize>> = vec![];
+
+    for (i, line) in script.iter().enumerate() {
+        if line.edit == Edit::Same {
+            continue;
+        }
+
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(script.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    hunks
+}
+
+// Like `@@ -3,4 +3,5 @@`, an empty side starts at the line before it
+fn header(hunk: &[Line]) -> String {
+    let count = |edit| hunk.iter().filter(|line| line.edit != edit).count();
+    let (from_count, to_count) = (count(Edit::Added), count(Edit::Removed));
+    let start = |first: usize, count| if count == 0 { first } else { first + 1 };
+
+    format!(
+        "@@ -{},{} +{},{} @@",
+        start(hunk[0].from, from_count),
+        from_count,
+        start(hunk[0].to, to_count),
+        to_count
+    )
+}
+
+pub fn unified(
+    from: &str,
+    to: &str,
+    labels: (&str, &str),
+    context: usize,
+    colored: bool,
+) -> String {
+    let script = script(from, to);
+    let hunks = hunks(&script, context);
+
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut result = paint(
+        &format!("--- {}\n+++ {}", labels.0, labels.1),
+        "\x1b[1m",
+        colored,
+    );
+    result.push('\n');
+
+    for hunk in hunks {
+        let hunk = &script[hunk];
+        result += &paint(&header(hunk), CYAN, colored);
+        result.push('\n');
+
+        for line in hunk {
+            let text = match line.edit {
+                Edit::Same => format!(" {}", line.text),
+                Edit::Removed => paint(&format!("-{}", line.text), RED, colored),
+                Edit::Added => paint(&format!("+{}", line.text), GREEN, colored),
+            };
+            result += &text;
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+// Rows of the lines of `from` on the left and those of `to` on the right. Like `sdiff`, the
+// middle column is `|` for a changed line, `<` for a removed one and `>` for an added one
+pub fn side_by_side(from: &str, to: &str, context: usize, colored: bool) -> String {
+    let script = script(from, to);
+    let hunks = hunks(&script, context);
+    let width = script
+        .iter()
+        .filter(|line| line.edit != Edit::Added)
+        .map(|line| line.text.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut result = String::new();
+
+    for hunk in hunks {
+        let hunk = &script[hunk];
+        result += &paint(&header(hunk), CYAN, colored);
+        result.push('\n');
+
+        let mut rows = vec![];
+        let mut i = 0;
+        while i < hunk.len() {
+            if hunk[i].edit == Edit::Same {
+                rows.push((Some(hunk[i].text), ' ', Some(hunk[i].text)));
+                i += 1;
+                continue;
+            }
+
+            // The lines removed and the ones added in their place are paired row by row
+            let removed = hunk[i..]
+                .iter()
+                .take_while(|line| line.edit == Edit::Removed);
+            let removed: Vec<_> = removed.map(|line| line.text).collect();
+            i += removed.len();
+            let added = hunk[i..].iter().take_while(|line| line.edit == Edit::Added);
+            let added: Vec<_> = added.map(|line| line.text).collect();
+            i += added.len();
+
+            for k in 0..removed.len().max(added.len()) {
+                let marker = match (removed.get(k), added.get(k)) {
+                    (Some(_), Some(_)) => '|',
+                    (Some(_), None) => '<',
+                    _ => '>',
+                };
+                rows.push((removed.get(k).copied(), marker, added.get(k).copied()));
+            }
+        }
+
+        for (left, marker, right) in rows {
+            let left = left.unwrap_or_default();
+            let padding = " ".repeat(width - left.chars().count());
+            let (left, right) = match marker {
+                ' ' => (left.to_string(), right.unwrap_or_default().to_string()),
+                _ => (
+                    paint(left, RED, colored),
+                    paint(right.unwrap_or_default(), GREEN, colored),
+                ),
+            };
+
+            let row = format!("{}{} {} {}", left, padding, marker, right);
+            result += row.trim_end();
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+fn describe(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+// One line per operation, with the value it replaces or removes. The operations are applied to a
+// copy of `from` one after the other, as their paths are those of the document at that point
+pub fn tree(from: &Json, operations: &[Operation], colored: bool) -> String {
+    let mut current = from.clone();
+    let mut result = String::new();
+
+    for operation in operations {
+        let old = current.pointer(operation.path()).map(Json::to_string);
+        let old = old.unwrap_or_default();
+
+        let line = match operation {
+            Operation::Add { path, value } => {
+                paint(&format!("+ {}: {}", describe(path), value), GREEN, colored)
+            }
+            Operation::Remove { path } => {
+                paint(&format!("- {}: {}", describe(path), old), RED, colored)
+            }
+            Operation::Replace { path, value } => paint(
+                &format!("~ {}: {} -> {}", describe(path), old, value),
+                YELLOW,
+                colored,
+            ),
+            Operation::Move { from, path } => format!("> {} -> {}", from, path),
+            Operation::Copy { from, path } => format!("= {} -> {}", from, path),
+            Operation::Test { .. } => continue,
+        };
+
+        result += &line;
+        result.push('\n');
+
+        let _ = patch::apply(&mut current, slice::from_ref(operation));
+    }
+
+    result
+}
+
+// How many values the operations change, add and remove
+pub fn stat(operations: &[Operation]) -> String {
+    let count = |f: fn(&Operation) -> bool| operations.iter().filter(|op| f(op)).count();
+
+    format!(
+        "{} changed, {} added, {} removed\n",
+        count(|op| matches!(op, Operation::Replace { .. })),
+        count(|op| matches!(op, Operation::Add { .. } | Operation::Copy { .. })),
+        count(|op| matches!(op, Operation::Remove { .. })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::diff::{self, DiffOptions};
+    use joxide::parser;
+
+    const FROM: &str = "{\n  \"a\": 1,\n  \"b\": [\n    1,\n    2\n  ],\n  \"c\": true\n}";
+    const TO: &str = "{\n  \"a\": 2,\n  \"b\": [\n    1,\n    2\n  ],\n  \"d\": null\n}";
+
+    #[test]
+    fn test_unified() {
+        assert_eq!(
+            unified(FROM, TO, ("a.json", "b.json"), 1, false),
+            "--- a.json\n+++ b.json\n\
+             @@ -1,3 +1,3 @@\n {\n-  \"a\": 1,\n+  \"a\": 2,\n   \"b\": [\n\
+             @@ -6,3 +6,3 @@\n   ],\n-  \"c\": true\n+  \"d\": null\n }\n"
+        );
+        assert_eq!(
+            unified(FROM, TO, ("a.json", "b.json"), 3, false)
+                .matches("@@ -")
+                .count(),
+            1
+        );
+        assert_eq!(unified(FROM, FROM, ("a.json", "b.json"), 3, false), "");
+        assert_eq!(
+            unified("[\n  1\n]", "[\n  1,\n  2\n]", ("a", "b"), 0, false),
+            "--- a\n+++ b\n@@ -2,1 +2,2 @@\n-  1\n+  1,\n+  2\n"
+        );
+        assert_eq!(
+            unified("[\n  1,\n  2\n]", "[\n  1\n]", ("a", "b"), 0, true),
+            "\x1b[1m--- a\n+++ b\x1b[0m\n\x1b[36m@@ -2,2 +2,1 @@\x1b[0m\n\
+             \x1b[31m-  1,\x1b[0m\n\x1b[31m-  2\x1b[0m\n\x1b[32m+  1\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        assert_eq!(
+            side_by_side(FROM, TO, 0, false),
+            "@@ -2,1 +2,1 @@\n  \"a\": 1,   |   \"a\": 2,\n\
+             @@ -7,1 +7,1 @@\n  \"c\": true |   \"d\": null\n"
+        );
+        assert_eq!(
+            side_by_side("[\n  1\n]", "[\n  0,\n  1\n]", 1, false),
+            "@@ -1,2 +1,3 @@\n[     [\n    >   0,\n  1     1\n"
+        );
+    }
+
+    #[test]
+    fn test_tree() {
+        let from = parser::parse_str(r#"{"a": 1, "b": [1, 2, 3], "c": true}"#).unwrap();
+        let to = parser::parse_str(r#"{"a": 2, "b": [0, 1, 3], "d": null}"#).unwrap();
+        let operations = diff::diff(&from, &to, &DiffOptions::default());
+
+        assert_eq!(
+            tree(&from, &operations, false),
+            "~ /a: 1 -> 2\n+ /b/0: 0\n- /b/2: 2\n- /c: true\n+ /d: null\n"
+        );
+        assert_eq!(stat(&operations), "1 changed, 2 added, 2 removed\n");
+
+        let operations = diff::diff(&Json::from(1), &Json::from(2), &DiffOptions::default());
+        assert_eq!(
+            tree(&Json::from(1), &operations, false),
+            "~ (root): 1 -> 2\n"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use joxide::encoding::Encoding;
+use joxide::escape::escape;
+use joxide::parser::Json;
+use joxide::{lexer, parser, pointer};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+// Compact JSON with sorted keys, equal for equal values
+fn canonical(value: &Json, out: &mut String) {
+    match value {
+        Json::Null | Json::Error => out.push_str("null"),
+        Json::Raw(_) => canonical(&value.parse_raw(), out),
+        Json::Bool(b) => out.push_str(&b.to_string()),
+        Json::Number(n) => out.push_str(&n.to_string()),
+        Json::String(s) => {
+            out.push('"');
+            out.push_str(&escape(s));
+            out.push('"');
+        }
+        Json::Array(arr) => {
+            out.push('[');
+
+            for (index, item) in arr.iter().enumerate() {
+                if index != 0 {
+                    out.push(',');
+                }
+
+                canonical(item, out);
+            }
+
+            out.push(']');
+        }
+        Json::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+
+            for (index, (key, item)) in entries.into_iter().enumerate() {
+                if index != 0 {
+                    out.push(',');
+                }
+
+                out.push('"');
+                out.push_str(&escape(key));
+                out.push_str("\":");
+                canonical(item, out);
+            }
+
+            out.push('}');
+        }
+    }
+}
+
+fn hash(raw: &str, ignore: &[String]) -> Option<u64> {
+    let tokens = lexer::lex(raw).ok()?;
+    let mut value = parser::parse(&tokens).ok()?;
+
+    for path in ignore {
+        pointer::remove_all(&mut value, path);
+    }
+
+    let mut out = String::new();
+    canonical(&value, &mut out);
+
+    let mut hasher = DefaultHasher::new();
+    out.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+pub fn json_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            json_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// Groups of at least two files with the same content, values at the `ignore` pointers are
+// left out of the comparison. Files that are not valid JSON are returned separately. Without an
+// encoding, each file's is detected from its bytes
+pub fn find(
+    dir: &Path,
+    ignore: &[String],
+    encoding: Option<Encoding>,
+) -> std::io::Result<(Vec<Vec<PathBuf>>, Vec<PathBuf>)> {
+    let mut files = vec![];
+    json_files(dir, &mut files)?;
+    files.sort();
+
+    let mut groups: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut invalid = vec![];
+
+    for path in files {
+        let bytes = std::fs::read(&path)?;
+        let raw = encoding
+            .unwrap_or_else(|| Encoding::detect(&bytes))
+            .decode(&bytes)
+            .ok();
+
+        match raw.and_then(|raw| hash(&raw, ignore)) {
+            Some(hash) => groups.entry(hash).or_default().push(path),
+            None => invalid.push(path),
+        }
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    groups.sort();
+
+    Ok((groups, invalid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical() {
+        let cases = vec![
+            (
+                "{\"b\": [1, 2.50], \"a\": \"x\"}",
+                "{\"a\":\"x\",\"b\":[1,2.5]}",
+            ),
+            ("\"\\u0041\\/\"", "\"A/\""),
+        ];
+
+        for (raw, expected) in cases {
+            let tokens = lexer::lex(raw).unwrap();
+            let mut out = String::new();
+            canonical(&parser::parse(&tokens).unwrap(), &mut out);
+
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let dir = std::env::temp_dir().join(format!("joxide-dupes-{}", std::process::id()));
+        let files = [
+            ("a.json", "{\"id\": 1, \"name\": \"x\"}"),
+            ("nested/b.json", "{\"name\": \"x\",\n \"id\": 1}"),
+            ("c.json", "{\"id\": 2, \"name\": \"x\"}"),
+            ("d.json", "{\"id\": "),
+            ("e.txt", "{\"id\": 1, \"name\": \"x\"}"),
+        ];
+
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+
+        let (groups, invalid) = find(&dir, &[], None).unwrap();
+        assert_eq!(groups, [[dir.join("a.json"), dir.join("nested/b.json")]]);
+        assert_eq!(invalid, [dir.join("d.json")]);
+
+        let (groups, _) = find(&dir, &["/id".to_string()], None).unwrap();
+        assert_eq!(
+            groups,
+            [[
+                dir.join("a.json"),
+                dir.join("c.json"),
+                dir.join("nested/b.json")
+            ]]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
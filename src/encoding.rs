@@ -0,0 +1,554 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+// Characters for 0x80..0xA0, the bytes windows-1252 leaves undefined map to the C1 controls
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+#[derive(Debug, PartialEq)]
+pub struct EncodingError {
+    pub encoding: Encoding,
+    // Byte offset when decoding, character offset when encoding
+    pub offset: usize,
+    pub decoding: bool,
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.decoding {
+            write!(
+                f,
+                "Input is not valid {} at byte {}",
+                self.encoding.label(),
+                self.offset
+            )
+        } else {
+            write!(
+                f,
+                "Character {} can not be written as {}",
+                self.offset,
+                self.encoding.label()
+            )
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+// Input that is not UTF-8, at the first invalid byte with its zero based line and column in the
+// valid text before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidUtf8 {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+    // The encoding the input is more likely in, see `Encoding::guess`
+    pub hint: Encoding,
+}
+
+impl fmt::Display for InvalidUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Input is not valid UTF-8 at byte {}, it may be {}",
+            self.offset,
+            self.hint.label()
+        )
+    }
+}
+
+impl std::error::Error for InvalidUtf8 {}
+
+// The input as text, or where it stops being UTF-8
+pub fn from_utf8(bytes: &[u8]) -> Result<&str, InvalidUtf8> {
+    std::str::from_utf8(bytes).map_err(|err| {
+        let offset = err.valid_up_to();
+        // Everything before the error is valid
+        let valid = std::str::from_utf8(&bytes[..offset]).unwrap_or_default();
+
+        InvalidUtf8 {
+            offset,
+            line: valid.matches('\n').count(),
+            col: valid[valid.rfind('\n').map_or(0, |i| i + 1)..]
+                .chars()
+                .count(),
+            hint: Encoding::guess(bytes),
+        }
+    })
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 7] = [
+        Encoding::Utf8,
+        Encoding::Latin1,
+        Encoding::Windows1252,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Utf32Le,
+        Encoding::Utf32Be,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin-1",
+            Encoding::Windows1252 => "windows-1252",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Utf32Le => "utf-32le",
+            Encoding::Utf32Be => "utf-32be",
+        }
+    }
+
+    // Case insensitive, accepts a few common aliases like `iso-8859-1` and `cp1252`
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        match label.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            "utf-16le" => Some(Encoding::Utf16Le),
+            "utf-16be" => Some(Encoding::Utf16Be),
+            "utf-32le" => Some(Encoding::Utf32Le),
+            "utf-32be" => Some(Encoding::Utf32Be),
+            _ => None,
+        }
+    }
+
+    // The encoding of a JSON text from its byte order mark, otherwise from the zero bytes around
+    // its first two characters, which are ASCII in JSON as RFC 4627 points out. UTF-8 when neither
+    // tells, as for an empty input
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        match bytes {
+            [0, 0, 0xFE, 0xFF, ..] => Encoding::Utf32Be,
+            [0xFF, 0xFE, 0, 0, ..] => Encoding::Utf32Le,
+            [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+            [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+            [0, 0, 0, _, ..] => Encoding::Utf32Be,
+            [_, 0, 0, 0, ..] => Encoding::Utf32Le,
+            [0, _, 0, _, ..] | [0, _] => Encoding::Utf16Be,
+            [_, 0, _, 0, ..] | [_, 0] => Encoding::Utf16Le,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    // For input that is not UTF-8, the detected encoding, or windows-1252 which every byte is valid
+    // in and which older Windows tools write
+    pub fn guess(bytes: &[u8]) -> Encoding {
+        match Encoding::detect(bytes) {
+            Encoding::Utf8 => Encoding::Windows1252,
+            detected => detected,
+        }
+    }
+
+    // Whether newlines are single `\n` bytes, so the bytes can be split into lines before decoding
+    pub fn is_ascii_compatible(self) -> bool {
+        matches!(
+            self,
+            Encoding::Utf8 | Encoding::Latin1 | Encoding::Windows1252
+        )
+    }
+
+    fn decoding_error(self, offset: usize) -> EncodingError {
+        EncodingError {
+            encoding: self,
+            offset,
+            decoding: true,
+        }
+    }
+
+    fn encoding_error(self, offset: usize) -> EncodingError {
+        EncodingError {
+            encoding: self,
+            offset,
+            decoding: false,
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> Result<String, EncodingError> {
+        self.decode_with(bytes, false)
+    }
+
+    // Decodes what can not be decoded as U+FFFD replacement characters instead of failing
+    pub fn decode_lossy(self, bytes: &[u8]) -> String {
+        self.decode_with(bytes, true).unwrap_or_default()
+    }
+
+    fn decode_with(self, bytes: &[u8], lossy: bool) -> Result<String, EncodingError> {
+        match self {
+            Encoding::Utf8 if lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(text.to_string()),
+                Err(err) => Err(self.decoding_error(err.valid_up_to())),
+            },
+            Encoding::Latin1 => Ok(bytes.iter().map(|byte| *byte as char).collect()),
+            Encoding::Windows1252 => Ok(bytes
+                .iter()
+                .map(|byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252[(byte - 0x80) as usize],
+                    _ => *byte as char,
+                })
+                .collect()),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) && !lossy {
+                    return Err(self.decoding_error(bytes.len() - 1));
+                }
+
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+
+                let mut text = String::with_capacity(bytes.len() / 2);
+                let mut offset = 0;
+
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => {
+                            text.push(c);
+                            offset += c.len_utf16() * 2;
+                        }
+                        Err(_) if lossy => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            offset += 2;
+                        }
+                        Err(_) => return Err(self.decoding_error(offset)),
+                    }
+                }
+
+                // A last odd byte
+                if !bytes.len().is_multiple_of(2) {
+                    text.push(char::REPLACEMENT_CHARACTER);
+                }
+
+                Ok(text)
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                let mut text = String::with_capacity(bytes.len() / 4);
+
+                for (index, quad) in bytes.chunks(4).enumerate() {
+                    let unit = match (self, quad) {
+                        (Encoding::Utf32Le, &[a, b, c, d]) => {
+                            Some(u32::from_le_bytes([a, b, c, d]))
+                        }
+                        (_, &[a, b, c, d]) => Some(u32::from_be_bytes([a, b, c, d])),
+                        _ => None,
+                    };
+
+                    match unit.and_then(char::from_u32) {
+                        Some(c) => text.push(c),
+                        None if lossy => text.push(char::REPLACEMENT_CHARACTER),
+                        None => return Err(self.decoding_error(index * 4)),
+                    }
+                }
+
+                Ok(text)
+            }
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Latin1 | Encoding::Windows1252 => text
+                .chars()
+                .enumerate()
+                .map(|(index, c)| {
+                    let byte = match self {
+                        Encoding::Windows1252 => WINDOWS_1252
+                            .iter()
+                            .position(|mapped| *mapped == c)
+                            .map(|position| position as u32 + 0x80)
+                            .or_else(|| Some(c as u32).filter(|c| !(0x80..0xA0).contains(c))),
+                        _ => Some(c as u32),
+                    };
+
+                    match byte {
+                        Some(byte) if byte <= 0xFF => Ok(byte as u8),
+                        _ => Err(self.encoding_error(index)),
+                    }
+                })
+                .collect(),
+            Encoding::Utf16Le => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Utf32Le => Ok(text
+                .chars()
+                .flat_map(|c| (c as u32).to_le_bytes())
+                .collect()),
+            Encoding::Utf32Be => Ok(text
+                .chars()
+                .flat_map(|c| (c as u32).to_be_bytes())
+                .collect()),
+        }
+    }
+
+    // How many bytes at the start of input read in parts hold whole characters, the rest belongs
+    // with the next part. Invalid bytes count as whole, so decoding reports them
+    pub fn complete_len(self, bytes: &[u8]) -> usize {
+        match self {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                _ => bytes.len(),
+            },
+            Encoding::Latin1 | Encoding::Windows1252 => bytes.len(),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let even = bytes.len() & !1;
+                let last = even.checked_sub(2).map(|i| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([bytes[i], bytes[i + 1]]),
+                    _ => u16::from_be_bytes([bytes[i], bytes[i + 1]]),
+                });
+
+                // A high surrogate waits for its low half
+                if last.is_some_and(|unit| (0xD800..0xDC00).contains(&unit)) {
+                    even - 2
+                } else {
+                    even
+                }
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => bytes.len() & !3,
+        }
+    }
+
+    // Encodes JSON text, characters the encoding can not represent are written as `\uXXXX` escapes.
+    // Those only mean the same inside strings, which is where formatted output has them
+    pub fn encode_json(self, json: &str) -> Vec<u8> {
+        if let Ok(bytes) = self.encode(json) {
+            return bytes;
+        }
+
+        let mut bytes = Vec::with_capacity(json.len());
+
+        for c in json.chars() {
+            match self.encode(c.encode_utf8(&mut [0; 4])) {
+                Ok(encoded) => bytes.extend(encoded),
+                Err(_) => {
+                    for unit in c.encode_utf16(&mut [0; 2]) {
+                        bytes.extend(format!("\\u{:04x}", unit).bytes());
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
+
+    fn width(self, c: char) -> usize {
+        match self {
+            Encoding::Utf8 => c.len_utf8(),
+            Encoding::Latin1 | Encoding::Windows1252 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => c.len_utf16() * 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+
+    // Offset in the original bytes of a zero based line and character column of the decoded text
+    pub fn byte_offset(self, text: &str, line: usize, col: usize) -> usize {
+        let mut offset = 0;
+
+        for (line_no, line_str) in text.split_terminator('\n').enumerate() {
+            if line_no == line {
+                return offset
+                    + line_str
+                        .chars()
+                        .take(col)
+                        .map(|c| self.width(c))
+                        .sum::<usize>();
+            }
+
+            offset += line_str.chars().map(|c| self.width(c)).sum::<usize>() + self.width('\n');
+        }
+
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let cases: Vec<(Encoding, &[u8], &str)> = vec![
+            (Encoding::Utf8, b"{\"a\": \"\xc3\xa9\"}", "{\"a\": \"é\"}"),
+            (Encoding::Latin1, b"[\"\xe9\x80\"]", "[\"é\u{80}\"]"),
+            (Encoding::Windows1252, b"[\"\xe9\x80\x93\"]", "[\"é€“\"]"),
+            (Encoding::Utf16Le, b"[\x00=\xd8\x00\xde]\x00", "[😀]"),
+            (Encoding::Utf16Be, b"\x00[\xd8=\xde\x00\x00]", "[😀]"),
+            (
+                Encoding::Utf32Le,
+                b"[\x00\x00\x00\x00\xf6\x01\x00]\x00\x00\x00",
+                "[😀]",
+            ),
+            (
+                Encoding::Utf32Be,
+                b"\x00\x00\x00[\x00\x01\xf6\x00\x00\x00\x00]",
+                "[😀]",
+            ),
+        ];
+
+        for (encoding, bytes, expected) in cases {
+            assert_eq!(encoding.decode(bytes).as_deref(), Ok(expected));
+            assert_eq!(encoding.encode(expected).as_deref(), Ok(bytes));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases: Vec<(Encoding, &[u8], usize)> = vec![
+            (Encoding::Utf8, b"[\"\xff\"]", 2),
+            (Encoding::Utf16Le, b"[\x00]", 2),
+            (Encoding::Utf16Le, b"[\x00\x00\xdc]\x00", 2),
+            (Encoding::Utf32Le, b"[\x00\x00\x00]\x00", 4),
+            (Encoding::Utf32Be, b"\x00\x00\x00[\x00\x00\xd8\x00", 4),
+        ];
+
+        for (encoding, bytes, offset) in cases {
+            assert_eq!(
+                encoding.decode(bytes),
+                Err(EncodingError {
+                    encoding,
+                    offset,
+                    decoding: true
+                })
+            );
+        }
+
+        assert_eq!(
+            Encoding::Latin1.encode("a€"),
+            Err(EncodingError {
+                encoding: Encoding::Latin1,
+                offset: 1,
+                decoding: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_lossy() {
+        let cases: Vec<(Encoding, &[u8], &str)> = vec![
+            (Encoding::Utf8, b"[\"\xff\"]", "[\"\u{fffd}\"]"),
+            (Encoding::Utf16Le, b"[\x00\x00\xdc]\x00", "[\u{fffd}]"),
+            (Encoding::Utf16Le, b"[\x00]", "[\u{fffd}"),
+            (
+                Encoding::Utf32Be,
+                b"\x00\x00\x00[\x00\x00\xd8\x00",
+                "[\u{fffd}",
+            ),
+            (Encoding::Latin1, b"[\xe9]", "[é]"),
+        ];
+
+        for (encoding, bytes, expected) in cases {
+            assert_eq!(encoding.decode_lossy(bytes), expected);
+        }
+    }
+
+    #[test]
+    fn test_from_utf8() {
+        assert_eq!(from_utf8(b"{\"a\": 1}"), Ok("{\"a\": 1}"));
+
+        let invalid = from_utf8(b"{\n  \"\xc3\xa9\xe9\": 1}").unwrap_err();
+        assert_eq!(
+            invalid,
+            InvalidUtf8 {
+                offset: 7,
+                line: 1,
+                col: 4,
+                hint: Encoding::Windows1252
+            }
+        );
+        assert_eq!(
+            invalid.to_string(),
+            "Input is not valid UTF-8 at byte 7, it may be windows-1252"
+        );
+
+        assert_eq!(
+            from_utf8(b"\xff\xfe{\x00}\x00").map_err(|invalid| invalid.hint),
+            Err(Encoding::Utf16Le)
+        );
+    }
+
+    #[test]
+    fn test_detect() {
+        let cases: Vec<(&[u8], Encoding)> = vec![
+            (b"{\"a\": 1}", Encoding::Utf8),
+            (b"\xef\xbb\xbf{}", Encoding::Utf8),
+            (b"", Encoding::Utf8),
+            (b"1", Encoding::Utf8),
+            (b"\xff\xfe{\x00}\x00", Encoding::Utf16Le),
+            (b"\xfe\xff\x00{\x00}", Encoding::Utf16Be),
+            (b"{\x00}\x00", Encoding::Utf16Le),
+            (b"\x00{\x00}", Encoding::Utf16Be),
+            (b"1\x00", Encoding::Utf16Le),
+            (b"\x001", Encoding::Utf16Be),
+            (b"\xff\xfe\x00\x00{\x00\x00\x00", Encoding::Utf32Le),
+            (b"\x00\x00\xfe\xff\x00\x00\x00{", Encoding::Utf32Be),
+            (b"{\x00\x00\x00}\x00\x00\x00", Encoding::Utf32Le),
+            (b"\x00\x00\x00{\x00\x00\x00}", Encoding::Utf32Be),
+        ];
+
+        for (bytes, expected) in cases {
+            assert_eq!(Encoding::detect(bytes), expected, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_complete_len() {
+        let cases: Vec<(Encoding, &[u8], usize)> = vec![
+            (Encoding::Utf8, b"[\"\xc3\xa9", 4),
+            (Encoding::Utf8, b"[\"\xc3", 2),
+            (Encoding::Utf8, b"[\"\xff", 3),
+            (Encoding::Latin1, b"[\"\xc3", 3),
+            (Encoding::Utf16Le, b"[\x00=", 2),
+            (Encoding::Utf16Le, b"[\x00=\xd8", 2),
+            (Encoding::Utf16Le, b"[\x00=\xd8\x00\xde", 6),
+            (Encoding::Utf16Be, b"\x00[\xd8=", 2),
+            (Encoding::Utf32Le, b"[\x00\x00\x00\x00\xf6", 4),
+        ];
+
+        for (encoding, bytes, expected) in cases {
+            assert_eq!(encoding.complete_len(bytes), expected, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_encode_json() {
+        assert_eq!(
+            Encoding::Latin1.encode_json("[\"é€😀\"]"),
+            b"[\"\xe9\\u20ac\\ud83d\\ude00\"]"
+        );
+        assert_eq!(Encoding::Windows1252.encode_json("[\"€\"]"), b"[\"\x80\"]");
+        assert_eq!(Encoding::Utf8.encode_json("[\"€\"]"), "[\"€\"]".as_bytes());
+    }
+
+    #[test]
+    fn test_byte_offset() {
+        let text = "{\n  \"é\": x\n}";
+
+        assert_eq!(Encoding::Utf8.byte_offset(text, 1, 7), 10);
+        assert_eq!(Encoding::Latin1.byte_offset(text, 1, 7), 9);
+        assert_eq!(Encoding::Utf16Le.byte_offset(text, 1, 7), 18);
+    }
+
+    #[test]
+    fn test_from_label() {
+        for encoding in Encoding::ALL {
+            assert_eq!(Encoding::from_label(encoding.label()), Some(encoding));
+        }
+
+        assert_eq!(Encoding::from_label("CP1252"), Some(Encoding::Windows1252));
+        assert_eq!(Encoding::from_label("ebcdic"), None);
+    }
+}
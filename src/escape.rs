@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+// `from_str_radix` also takes a sign, like `+041`
+fn hex_code_unit(hex: &str) -> Option<u32> {
+    if hex.len() != 4 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u32::from_str_radix(hex, 16).ok()
+}
+
+// Decodes the escape sequences of a raw string token, invalid sequences and lone surrogates,
+// which the lexer rejects, are kept as written
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut result = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let decoded = match rest.as_bytes().get(1) {
+            Some(b'"') => Some(('"', 2)),
+            Some(b'\\') => Some(('\\', 2)),
+            Some(b'/') => Some(('/', 2)),
+            Some(b'b') => Some(('\u{8}', 2)),
+            Some(b'f') => Some(('\u{c}', 2)),
+            Some(b'n') => Some(('\n', 2)),
+            Some(b'r') => Some(('\r', 2)),
+            Some(b't') => Some(('\t', 2)),
+            Some(b'u') => unescape_unicode(rest),
+            _ => None,
+        };
+
+        match decoded {
+            Some((c, length)) => {
+                result.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    Cow::Owned(result)
+}
+
+// Decodes the `\uXXXX` escape `rest` starts with, or a surrogate pair of two, with its length.
+// A surrogate without its other half is not a character and has no text to decode to
+pub(crate) fn unescape_unicode(rest: &str) -> Option<(char, usize)> {
+    let high = hex_code_unit(rest.get(2..6)?)?;
+
+    if !(0xD800..0xDC00).contains(&high) {
+        return char::from_u32(high).map(|c| (c, 6));
+    }
+
+    let low = match rest.get(6..8) {
+        Some("\\u") => rest.get(8..12).and_then(hex_code_unit),
+        _ => None,
+    };
+
+    match low {
+        Some(low) if (0xDC00..0xE000).contains(&low) => {
+            char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).map(|c| (c, 12))
+        }
+        _ => None,
+    }
+}
+
+fn needs_escape(c: char) -> bool {
+    c == '"' || c == '\\' || c < ' '
+}
+
+// Encodes text as the contents of a JSON string token
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(needs_escape) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = String::with_capacity(text.len() + 2);
+
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+
+    Cow::Owned(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        let cases = vec![
+            ("plain", "plain"),
+            ("a\\nb", "a\nb"),
+            ("\\\"quoted\\\" \\\\ \\/", "\"quoted\" \\ /"),
+            ("\\b\\f\\r\\t", "\u{8}\u{c}\r\t"),
+            ("caf\\u00e9", "café"),
+            ("\\ud83d\\ude00", "😀"),
+            ("\\ud83d alone", "\\ud83d alone"),
+            ("\\udc00", "\\udc00"),
+            ("\\ud800\\u0041", "\\ud800A"),
+            ("\\ud800\\ud800", "\\ud800\\ud800"),
+            ("\\x", "\\x"),
+            ("\\u+041", "\\u+041"),
+            ("\\ud83d\\u+e00", "\\ud83d\\u+e00"),
+            ("trailing \\", "trailing \\"),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(unescape(raw), expected);
+        }
+
+        assert!(matches!(unescape("plain"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_escape() {
+        let cases = vec![
+            ("plain", "plain"),
+            ("a\nb", "a\\nb"),
+            ("\"quoted\" \\ /", "\\\"quoted\\\" \\\\ /"),
+            ("\u{1}\t", "\\u0001\\t"),
+            ("café", "café"),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(escape(text), expected);
+            assert_eq!(unescape(&escape(text)), text);
+        }
+
+        assert!(matches!(escape("plain"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,127 @@
+use joxide::lexer::Token;
+use joxide::{formatter, parser, pointer};
+
+// Shows where the value at a JSON pointer is in the source, for `explain-path`
+pub struct Explain<'r> {
+    pub file_path: &'r str,
+    pub raw: &'r str,
+    pub line_offset: usize,
+    pub col_offset: usize,
+    pub context_lines: usize,
+}
+
+impl Explain<'_> {
+    fn position(&self, token: &Token) -> String {
+        format!("{}:{}:{}", self.file_path, token.line + 1, token.col + 1)
+    }
+
+    // Source lines around the token, numbered as in the host document
+    fn context(&self, token: &Token) -> String {
+        let line = token.line.saturating_sub(self.line_offset);
+        let col = token.col.saturating_sub(self.col_offset);
+        let last = line + self.context_lines;
+        let width = (last + self.line_offset + 1).to_string().len();
+        let mut result = String::new();
+
+        for (line_no, line_str) in self.raw.split_terminator('\n').enumerate() {
+            if line_no + self.context_lines < line || line_no > last {
+                continue;
+            }
+
+            let marker = if line_no == line { '>' } else { ' ' };
+            let number = line_no + self.line_offset + 1;
+            result += &format!("{} {:>width$} | {}\n", marker, number, line_str);
+
+            if line_no == line {
+                result += &format!("  {} | {}^\n", " ".repeat(width), " ".repeat(col));
+            }
+        }
+
+        result
+    }
+
+    pub fn explain(
+        &self,
+        tokens: &[Token],
+        value: &parser::Json,
+        path: &str,
+    ) -> Result<String, String> {
+        if pointer::split(path).is_none() {
+            return Err(format!(
+                "'{}' is not a JSON pointer, pointers look like /items/0/name",
+                path
+            ));
+        }
+
+        let (location, found) = match (pointer::locate(tokens, path), value.pointer(path)) {
+            (Some(location), Some(found)) => (location, found),
+            _ => return Err(format!("No value at '{}' in {}", path, self.file_path)),
+        };
+
+        let name = if path.is_empty() {
+            "Document root"
+        } else {
+            path
+        };
+        let mut result = format!("{} at {}\n", name, self.position(location.token));
+
+        if location.steps.len() > 1 {
+            result += "Parents:\n";
+
+            for (parent, token) in &location.steps[..location.steps.len() - 1] {
+                result += &format!("  {} at {}\n", parent, self.position(token));
+            }
+        }
+
+        result += &self.context(location.token);
+        result += "Value:\n";
+        result += &formatter::format_json(found, 4);
+        result += "\n";
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::lexer;
+
+    #[test]
+    fn test_explain() {
+        let raw = "{\n  \"items\": [\n    {\"name\": \"a\"},\n    {\"name\": \"b\"}\n  ]\n}";
+        let mut tokens = lexer::lex(raw).unwrap();
+        lexer::offset(&mut tokens, 8, 0);
+        let value = parser::parse(&tokens).unwrap();
+
+        let explain = Explain {
+            file_path: "f.json",
+            raw,
+            line_offset: 8,
+            col_offset: 0,
+            context_lines: 1,
+        };
+
+        assert_eq!(
+            explain.explain(&tokens, &value, "/items/1/name"),
+            Ok("/items/1/name at f.json:12:14
+Parents:
+  /items at f.json:10:3
+  /items/1 at f.json:12:5
+  11 |     {\"name\": \"a\"},
+> 12 |     {\"name\": \"b\"}
+     |              ^
+  13 |   ]
+Value:
+\"b\"
+"
+            .to_string())
+        );
+
+        assert_eq!(
+            explain.explain(&tokens, &value, "/items/2"),
+            Err("No value at '/items/2' in f.json".to_string())
+        );
+        assert!(explain.explain(&tokens, &value, "items").is_err());
+    }
+}
@@ -0,0 +1,1635 @@
+use escape::{unescape, unescape_unicode};
+use merge::{self, MergeStrategy};
+use number::Number;
+use parser::{Json, JsonOwned, Map};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+// A small subset of the jq language, like `.items[] | select(.price > 3) | {id, name}`, evaluated
+// over a parsed document. Every filter turns one input into any number of outputs: paths, `|`,
+// `,`, `//`, `[...]` and `{...}` construction, arithmetic, comparisons, `and`, `or`, `?`,
+// `if ... then ... elif ... else ... end`, `"\(...)"` string interpolation, and the builtins in
+// `FUNCTIONS`
+
+// Where compiling or running the filter failed, `position` counts characters from zero and
+// points at the part of the expression that failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at character {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for FilterError {}
+
+// Names and the number of arguments of the builtins
+const FUNCTIONS: &[(&str, usize)] = &[
+    ("add", 0),
+    ("all", 0),
+    ("any", 0),
+    ("ascii_downcase", 0),
+    ("ascii_upcase", 0),
+    ("empty", 0),
+    ("first", 0),
+    ("from_entries", 0),
+    ("has", 1),
+    ("join", 1),
+    ("keys", 0),
+    ("last", 0),
+    ("length", 0),
+    ("map", 1),
+    ("max", 0),
+    ("min", 0),
+    ("not", 0),
+    ("reverse", 0),
+    ("select", 1),
+    ("sort", 0),
+    ("sort_by", 1),
+    ("to_entries", 0),
+    ("tonumber", 0),
+    ("tostring", 0),
+    ("type", 0),
+    ("unique", 0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// The `usize` of the nodes that can fail at run time is their position
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Identity,
+    Recurse,
+    Literal(JsonOwned),
+    Index(Box<Expr>, Box<Expr>, usize),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>, usize),
+    Iterate(Box<Expr>, usize),
+    Optional(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    // Kept flat, lists like `.a, .b, .c` can be long
+    Comma(Vec<Expr>),
+    Alternative(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>, usize),
+    Neg(Box<Expr>, usize),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(Expr, Expr)>, usize),
+    If(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    Call(&'static str, Vec<Expr>, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+// Deepest nesting of a filter. The parser and `eval` recurse once per level, with frames of tens
+// of KB in debug builds, which still fit in the 2 MB stack of spawned threads
+const MAX_DEPTH: usize = 64;
+
+struct ExpressionParser<'e> {
+    source: &'e str,
+    pos: usize,
+    // Levels of the expression being parsed, parentheses and operands of operators alike
+    depth: usize,
+}
+
+impl<'e> ExpressionParser<'e> {
+    fn rest(&self) -> &'e str {
+        &self.source[self.pos..]
+    }
+
+    fn position(&self) -> usize {
+        self.source[..self.pos].chars().count()
+    }
+
+    // Enters one more level, the caller sets `depth` back when it is done with it
+    fn nest(&mut self) -> Result<(), FilterError> {
+        if self.depth >= MAX_DEPTH {
+            return self.error("The filter is nested too deeply");
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, FilterError> {
+        Err(FilterError {
+            position: self.position(),
+            message: message.to_string(),
+        })
+    }
+
+    fn unexpected<T>(&self) -> Result<T, FilterError> {
+        match self.rest().chars().next() {
+            Some(c) => self.error(&format!("Unexpected '{}'", c)),
+            None => self.error("Unexpected end of the filter"),
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // Skips blanks first
+    fn eat(&mut self, prefix: &str) -> bool {
+        self.skip_blank();
+
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), FilterError> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    fn is_name_first(c: char) -> bool {
+        c.is_ascii_alphabetic() || c == '_'
+    }
+
+    fn peek_name(&mut self) -> Option<&'e str> {
+        self.skip_blank();
+        let rest = self.rest();
+
+        if !rest.starts_with(Self::is_name_first) {
+            return None;
+        }
+
+        let end = rest
+            .find(|c: char| !Self::is_name_first(c) && !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        Some(&rest[..end])
+    }
+
+    fn name(&mut self) -> Option<&'e str> {
+        let name = self.peek_name()?;
+        self.pos += name.len();
+        Some(name)
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_name() == Some(keyword) {
+            self.pos += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), FilterError> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            self.error(&format!("Expected '{}'", keyword))
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, FilterError> {
+        self.nest()?;
+        let left = self.comma()?;
+
+        let expr = if self.eat("|") {
+            Expr::Pipe(Box::new(left), Box::new(self.pipe()?))
+        } else {
+            left
+        };
+
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn comma(&mut self) -> Result<Expr, FilterError> {
+        let mut items = vec![self.alternative()?];
+
+        while self.eat(",") {
+            items.push(self.alternative()?);
+        }
+
+        if items.len() == 1 {
+            return Ok(items.pop().unwrap());
+        }
+
+        Ok(Expr::Comma(items))
+    }
+
+    fn alternative(&mut self) -> Result<Expr, FilterError> {
+        let left = self.or()?;
+
+        if self.eat("//") {
+            self.nest()?;
+            let right = self.alternative()?;
+            self.depth -= 1;
+            return Ok(Expr::Alternative(Box::new(left), Box::new(right)));
+        }
+
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let depth = self.depth;
+        let mut left = self.and()?;
+
+        while self.keyword("or") {
+            self.nest()?;
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let depth = self.depth;
+        let mut left = self.comparison()?;
+
+        while self.keyword("and") {
+            self.nest()?;
+            left = Expr::And(Box::new(left), Box::new(self.comparison()?));
+        }
+
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, FilterError> {
+        let left = self.additive()?;
+        self.skip_blank();
+        let start = self.position();
+
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+
+        for (text, op) in ops {
+            if self.eat(text) {
+                let right = self.additive()?;
+                return Ok(Expr::Binary(Box::new(left), op, Box::new(right), start));
+            }
+        }
+
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, FilterError> {
+        let depth = self.depth;
+        let mut left = self.multiplicative()?;
+
+        loop {
+            self.skip_blank();
+            let start = self.position();
+
+            let op = if self.eat("+") {
+                Op::Add
+            } else if self.eat("-") {
+                Op::Sub
+            } else {
+                self.depth = depth;
+                return Ok(left);
+            };
+
+            self.nest()?;
+            let right = self.multiplicative()?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right), start);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, FilterError> {
+        let depth = self.depth;
+        let mut left = self.unary()?;
+
+        loop {
+            self.skip_blank();
+            let start = self.position();
+
+            let op = if self.eat("*") {
+                Op::Mul
+            } else if !self.rest().starts_with("//") && self.eat("/") {
+                Op::Div
+            } else if self.eat("%") {
+                Op::Mod
+            } else {
+                self.depth = depth;
+                return Ok(left);
+            };
+
+            self.nest()?;
+            let right = self.unary()?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right), start);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        self.skip_blank();
+        let start = self.position();
+
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.postfix()?), start));
+        }
+
+        self.postfix()
+    }
+
+    // A term followed by `.name`, `[...]` and `?`
+    fn postfix(&mut self) -> Result<Expr, FilterError> {
+        let depth = self.depth;
+        let mut expr = self.term()?;
+
+        loop {
+            self.skip_blank();
+
+            if self.rest().starts_with('.') && !self.rest().starts_with("..") {
+                self.nest()?;
+                let start = self.position();
+                self.pos += 1;
+                expr = self.field(expr, start)?;
+            } else if self.rest().starts_with('[') {
+                self.nest()?;
+                expr = self.bracket(expr)?;
+            } else if self.eat("?") {
+                self.nest()?;
+                expr = Expr::Optional(Box::new(expr));
+            } else {
+                self.depth = depth;
+                return Ok(expr);
+            }
+        }
+    }
+
+    // What follows a `.` at `start`: a name, a quoted name or brackets
+    fn field(&mut self, target: Expr, start: usize) -> Result<Expr, FilterError> {
+        if self.rest().starts_with('[') {
+            return self.bracket(target);
+        }
+
+        let key = if self.rest().starts_with('"') {
+            self.string()?
+        } else {
+            match self.name() {
+                Some(name) => Expr::Literal(Json::from(name.to_string())),
+                None => return self.unexpected(),
+            }
+        };
+
+        Ok(Expr::Index(Box::new(target), Box::new(key), start))
+    }
+
+    // `[]`, `[index]` or `[from:to]`
+    fn bracket(&mut self, target: Expr) -> Result<Expr, FilterError> {
+        let start = self.position();
+        self.expect("[")?;
+
+        if self.eat("]") {
+            return Ok(Expr::Iterate(Box::new(target), start));
+        }
+
+        let from = if self.rest().trim_start().starts_with(':') {
+            None
+        } else {
+            Some(Box::new(self.pipe()?))
+        };
+
+        if !self.eat(":") {
+            self.expect("]")?;
+
+            return match from {
+                Some(index) => Ok(Expr::Index(Box::new(target), index, start)),
+                None => self.unexpected(),
+            };
+        }
+
+        let to = if self.eat("]") {
+            None
+        } else {
+            let to = self.pipe()?;
+            self.expect("]")?;
+            Some(Box::new(to))
+        };
+
+        if from.is_none() && to.is_none() {
+            return self.error("A slice needs a start or an end");
+        }
+
+        Ok(Expr::Slice(Box::new(target), from, to, start))
+    }
+
+    fn term(&mut self) -> Result<Expr, FilterError> {
+        self.skip_blank();
+        let start = self.position();
+
+        if self.eat("..") {
+            return Ok(Expr::Recurse);
+        }
+
+        if self.eat(".") {
+            let rest = self.rest();
+
+            if rest.starts_with(Self::is_name_first) || rest.starts_with(['"', '[']) {
+                return self.field(Expr::Identity, start);
+            }
+
+            return Ok(Expr::Identity);
+        }
+
+        if self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            return self.number();
+        }
+
+        if self.rest().starts_with('"') {
+            return self.string();
+        }
+
+        if self.eat("[") {
+            if self.eat("]") {
+                return Ok(Expr::Array(None));
+            }
+
+            let items = self.pipe()?;
+            self.expect("]")?;
+            return Ok(Expr::Array(Some(Box::new(items))));
+        }
+
+        if self.eat("{") {
+            return self.object(start);
+        }
+
+        if self.eat("(") {
+            let expr = self.pipe()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+
+        if self.keyword("if") {
+            return self.conditional();
+        }
+
+        let name = match self.name() {
+            Some(name) => name,
+            None => return self.unexpected(),
+        };
+
+        match name {
+            "null" => return Ok(Expr::Literal(Json::Null)),
+            "true" => return Ok(Expr::Literal(Json::Bool(true))),
+            "false" => return Ok(Expr::Literal(Json::Bool(false))),
+            _ => {}
+        }
+
+        let mut args = vec![];
+
+        if self.eat("(") {
+            loop {
+                args.push(self.pipe()?);
+
+                if self.eat(")") {
+                    break;
+                }
+
+                self.expect(";")?;
+            }
+        }
+
+        match FUNCTIONS.iter().find(|(known, _)| *known == name) {
+            Some((name, arity)) if *arity == args.len() => Ok(Expr::Call(name, args, start)),
+            _ => Err(FilterError {
+                position: start,
+                message: format!("Unknown function {}/{}", name, args.len()),
+            }),
+        }
+    }
+
+    // After the `if`, `elif` is an `if` in the `else` branch
+    fn conditional(&mut self) -> Result<Expr, FilterError> {
+        let condition = self.pipe()?;
+        self.expect_keyword("then")?;
+        let then = self.pipe()?;
+
+        let otherwise = if self.keyword("elif") {
+            self.nest()?;
+            let otherwise = self.conditional()?;
+            self.depth -= 1;
+            Some(Box::new(otherwise))
+        } else if self.keyword("else") {
+            let otherwise = self.pipe()?;
+            self.expect_keyword("end")?;
+            Some(Box::new(otherwise))
+        } else {
+            self.expect_keyword("end")?;
+            None
+        };
+
+        Ok(Expr::If(Box::new(condition), Box::new(then), otherwise))
+    }
+
+    // Members are `key: value`, `"key": value`, `(expression): value`, or `key` alone for
+    // `key: .key`
+    fn object(&mut self, start: usize) -> Result<Expr, FilterError> {
+        let mut entries = vec![];
+
+        if self.eat("}") {
+            return Ok(Expr::Object(entries, start));
+        }
+
+        loop {
+            self.skip_blank();
+
+            let (key, shorthand) = if self.eat("(") {
+                let key = self.pipe()?;
+                self.expect(")")?;
+                (key, None)
+            } else if self.rest().starts_with('"') {
+                // Interpolated keys have no shorthand
+                match self.string()? {
+                    Expr::Literal(Json::String(key)) => {
+                        let shorthand = key.to_string();
+                        (Expr::Literal(Json::String(key)), Some(shorthand))
+                    }
+                    key => (key, None),
+                }
+            } else {
+                match self.name() {
+                    Some(name) => (
+                        Expr::Literal(Json::from(name.to_string())),
+                        Some(name.to_string()),
+                    ),
+                    None => return self.unexpected(),
+                }
+            };
+
+            let value = match shorthand {
+                Some(name) if !self.eat(":") => Expr::Index(
+                    Box::new(Expr::Identity),
+                    Box::new(Expr::Literal(Json::from(name))),
+                    self.position(),
+                ),
+                _ => {
+                    if !matches!(key, Expr::Literal(_)) {
+                        self.expect(":")?;
+                    }
+
+                    self.alternative()?
+                }
+            };
+
+            entries.push((key, value));
+
+            if self.eat("}") {
+                return Ok(Expr::Object(entries, start));
+            }
+
+            self.expect(",")?;
+        }
+    }
+
+    fn number(&mut self) -> Result<Expr, FilterError> {
+        let rest = self.rest();
+        let mut end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+
+        if rest[end..].starts_with(['e', 'E']) {
+            let exponent = rest[end + 1..].strip_prefix(['+', '-']).map_or(1, |_| 2);
+            let digits = rest[end + exponent..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - end - exponent);
+            end += exponent + digits;
+        }
+
+        let text = &rest[..end];
+        // Leading zeros and a trailing `.` are allowed like in jq
+        let number = Number::parse(text).or_else(|| text.parse::<f64>().ok().map(Number::from));
+
+        match number {
+            Some(number) => {
+                self.pos += end;
+                Ok(Expr::Literal(Json::Number(number)))
+            }
+            None => self.error("Invalid number"),
+        }
+    }
+
+    // Decoded text of a string up to its closing quote or a `\(`, and whether it was a `\(`.
+    // Escapes are checked like in JSON strings
+    fn segment(&mut self, quote: usize) -> Result<(String, bool), FilterError> {
+        let rest = self.rest();
+        let mut escaped = false;
+        let mut chars = rest.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            if escaped {
+                escaped = false;
+
+                let valid = match c {
+                    '(' => {
+                        self.pos += i + 1;
+                        return Ok((unescape(&rest[..i - 1]).into_owned(), true));
+                    }
+                    '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => true,
+                    'u' => match unescape_unicode(&rest[i - 1..]) {
+                        Some((_, length)) => chars.nth(length - 3).is_some(),
+                        None => false,
+                    },
+                    _ => false,
+                };
+
+                if !valid {
+                    return Err(FilterError {
+                        position: self.source[..self.pos + i - 1].chars().count(),
+                        message: "Invalid escape sequence".to_string(),
+                    });
+                }
+
+                continue;
+            }
+
+            match c {
+                '\\' => escaped = true,
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok((unescape(&rest[..i]).into_owned(), false));
+                }
+                _ => {}
+            }
+        }
+
+        Err(FilterError {
+            position: quote,
+            message: "Missing closing quote".to_string(),
+        })
+    }
+
+    // A JSON string, decoded. Each `\(filter)` in it is replaced by an output of the filter as
+    // `tostring` gives it, with a string for every combination of outputs like jq
+    fn string(&mut self) -> Result<Expr, FilterError> {
+        let quote = self.position();
+        self.pos += 1;
+
+        let (text, mut interpolated) = self.segment(quote)?;
+        let mut expr = Expr::Literal(Json::from(text));
+
+        let depth = self.depth;
+
+        while interpolated {
+            self.nest()?;
+            self.skip_blank();
+            let start = self.position();
+
+            let inner = self.pipe()?;
+            self.expect(")")?;
+
+            let tostring = Expr::Call("tostring", vec![], start);
+            let value = Expr::Pipe(Box::new(inner), Box::new(tostring));
+            expr = Expr::Binary(Box::new(expr), Op::Add, Box::new(value), start);
+
+            let (text, more) = self.segment(quote)?;
+
+            if !text.is_empty() {
+                self.nest()?;
+                let text = Expr::Literal(Json::from(text));
+                expr = Expr::Binary(Box::new(expr), Op::Add, Box::new(text), start);
+            }
+
+            interpolated = more;
+        }
+
+        self.depth = depth;
+        Ok(expr)
+    }
+}
+
+impl Filter {
+    pub fn parse(expression: &str) -> Result<Filter, FilterError> {
+        let mut parser = ExpressionParser {
+            source: expression,
+            pos: 0,
+            depth: 0,
+        };
+
+        let expr = parser.pipe()?;
+        parser.skip_blank();
+
+        if !parser.rest().is_empty() {
+            return parser.unexpected();
+        }
+
+        Ok(Filter { expr })
+    }
+
+    // The outputs in order, as jq would print them
+    pub fn run<'a>(&self, value: &Json<'a>) -> Result<Vec<Json<'a>>, FilterError> {
+        eval(&self.expr, value)
+    }
+}
+
+// Parses the filter to run it on any number of documents
+pub fn compile(expression: &str) -> Result<Filter, FilterError> {
+    Filter::parse(expression)
+}
+
+fn fail<T>(position: usize, message: String) -> Result<T, FilterError> {
+    Err(FilterError { position, message })
+}
+
+fn truthy(value: &Json) -> bool {
+    !matches!(value, Json::Null | Json::Error | Json::Bool(false))
+}
+
+// Whole numbers that fit are integers again, so `1 + 1` is `2` rather than `2.0`
+fn number<'a>(n: f64) -> Json<'a> {
+    if n.fract() == 0.0 && n.abs() < 9007199254740992.0 {
+        Json::from(n as i64)
+    } else {
+        Json::from(n)
+    }
+}
+
+fn rank(value: &Json) -> u8 {
+    match value {
+        Json::Null | Json::Error | Json::Raw(_) => 0,
+        Json::Bool(false) => 1,
+        Json::Bool(true) => 2,
+        Json::Number(_) => 3,
+        Json::String(_) => 4,
+        Json::Array(_) => 5,
+        Json::Object(_) => 6,
+    }
+}
+
+// jq's order: null, false, true, numbers, strings, arrays, then objects, which compare their
+// sorted keys first and then the values of those keys
+fn order(a: &Json, b: &Json) -> Ordering {
+    let (a, b) = (a.parse_raw(), b.parse_raw());
+
+    match (&*a, &*b) {
+        (Json::Number(a), Json::Number(b)) => a.total_cmp(b),
+        (Json::String(a), Json::String(b)) => a.cmp(b),
+        (Json::Array(a), Json::Array(b)) => {
+            for (a, b) in a.iter().zip(b) {
+                match order(a, b) {
+                    Ordering::Equal => {}
+                    unequal => return unequal,
+                }
+            }
+
+            a.len().cmp(&b.len())
+        }
+        (Json::Object(a), Json::Object(b)) => {
+            let mut a: Vec<_> = a.iter().collect();
+            let mut b: Vec<_> = b.iter().collect();
+            a.sort_by_key(|(key, _)| *key);
+            b.sort_by_key(|(key, _)| *key);
+
+            let keys = a
+                .iter()
+                .map(|(key, _)| key)
+                .cmp(b.iter().map(|(key, _)| key));
+
+            keys.then_with(|| {
+                a.iter()
+                    .zip(&b)
+                    .map(|((_, a), (_, b))| order(a, b))
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn index<'a>(target: &Json<'a>, key: &Json, position: usize) -> Result<Json<'a>, FilterError> {
+    match (&*target.parse_raw(), key) {
+        (Json::Null, Json::String(_) | Json::Number(_)) => Ok(Json::Null),
+        (Json::Object(obj), Json::String(key)) => {
+            Ok(obj.get(key.as_ref()).cloned().unwrap_or_default())
+        }
+        (Json::Array(arr), Json::Number(n)) => {
+            let i = n.as_f64().floor() as i64;
+            let i = if i < 0 { arr.len() as i64 + i } else { i };
+            Ok(usize::try_from(i)
+                .ok()
+                .and_then(|i| arr.get(i))
+                .cloned()
+                .unwrap_or_default())
+        }
+        (target, key) => fail(
+            position,
+            format!("Cannot index {} with {}", target.type_name(), key),
+        ),
+    }
+}
+
+fn slice<'a>(
+    target: &Json<'a>,
+    from: Option<&Json>,
+    to: Option<&Json>,
+    position: usize,
+) -> Result<Json<'a>, FilterError> {
+    let target = target.parse_raw();
+    let len = match &*target {
+        Json::Null => return Ok(Json::Null),
+        Json::Array(arr) => arr.len(),
+        Json::String(s) => s.chars().count(),
+        target => return fail(position, format!("Cannot slice {}", target.type_name())),
+    };
+
+    let bound = |bound: Option<&Json>, default: usize| match bound {
+        None | Some(Json::Null) => Ok(default),
+        Some(Json::Number(n)) => {
+            let i = n.as_f64().floor() as i64;
+            let i = if i < 0 { len as i64 + i } else { i };
+            Ok(i.clamp(0, len as i64) as usize)
+        }
+        Some(other) => fail(position, format!("Cannot slice with {}", other.type_name())),
+    };
+
+    let from = bound(from, 0)?;
+    let to = bound(to, len)?.max(from);
+
+    Ok(match &*target {
+        Json::Array(arr) => Json::Array(arr[from..to].to_vec()),
+        Json::String(s) => Json::from(s.chars().skip(from).take(to - from).collect::<String>()),
+        _ => unreachable!(),
+    })
+}
+
+fn iterate<'a>(target: &Json<'a>, position: usize) -> Result<Vec<Json<'a>>, FilterError> {
+    match &*target.parse_raw() {
+        Json::Array(arr) => Ok(arr.clone()),
+        Json::Object(obj) => Ok(obj.values().cloned().collect()),
+        target => fail(
+            position,
+            format!("Cannot iterate over {}", target.type_name()),
+        ),
+    }
+}
+
+fn descendants<'a>(value: &Json<'a>, out: &mut Vec<Json<'a>>) {
+    let value = value.parse_raw();
+    out.push(value.clone().into_owned());
+
+    match &*value {
+        Json::Array(arr) => arr.iter().for_each(|item| descendants(item, out)),
+        Json::Object(obj) => obj.values().for_each(|item| descendants(item, out)),
+        _ => {}
+    }
+}
+
+fn binary<'a>(
+    left: Json<'a>,
+    op: Op,
+    right: Json<'a>,
+    position: usize,
+) -> Result<Json<'a>, FilterError> {
+    let left = left.parse_raw().into_owned();
+    let right = right.parse_raw().into_owned();
+
+    let ordering = order(&left, &right);
+
+    match op {
+        Op::Eq => return Ok(Json::Bool(ordering == Ordering::Equal)),
+        Op::Ne => return Ok(Json::Bool(ordering != Ordering::Equal)),
+        Op::Lt => return Ok(Json::Bool(ordering == Ordering::Less)),
+        Op::Le => return Ok(Json::Bool(ordering != Ordering::Greater)),
+        Op::Gt => return Ok(Json::Bool(ordering == Ordering::Greater)),
+        Op::Ge => return Ok(Json::Bool(ordering != Ordering::Less)),
+        _ => {}
+    }
+
+    let result = match (op, left, right) {
+        (Op::Add, Json::Null, other) | (Op::Add, other, Json::Null) => other,
+        (Op::Add, Json::Number(a), Json::Number(b)) => number(a.as_f64() + b.as_f64()),
+        (Op::Add, Json::String(a), Json::String(b)) => Json::from(a.into_owned() + &b),
+        (Op::Add, Json::Array(mut a), Json::Array(b)) => {
+            a.extend(b);
+            Json::Array(a)
+        }
+        (Op::Add, Json::Object(mut a), Json::Object(b)) => {
+            a.extend(b);
+            Json::Object(a)
+        }
+        (Op::Sub, Json::Number(a), Json::Number(b)) => number(a.as_f64() - b.as_f64()),
+        (Op::Sub, Json::Array(a), Json::Array(b)) => Json::Array(
+            a.into_iter()
+                .filter(|item| !b.iter().any(|other| order(item, other) == Ordering::Equal))
+                .collect(),
+        ),
+        (Op::Mul, Json::Number(a), Json::Number(b)) => number(a.as_f64() * b.as_f64()),
+        (Op::Mul, mut a @ Json::Object(_), b @ Json::Object(_)) => {
+            merge::merge(&mut a, b, MergeStrategy::default());
+            a
+        }
+        (Op::Div | Op::Mod, Json::Number(_), Json::Number(b)) if b.as_f64() == 0.0 => {
+            return fail(position, "Division by zero".to_string());
+        }
+        (Op::Div, Json::Number(a), Json::Number(b)) => number(a.as_f64() / b.as_f64()),
+        (Op::Div, Json::String(a), Json::String(b)) => Json::Array(
+            a.split(b.as_ref())
+                .map(|part| Json::from(part.to_string()))
+                .collect(),
+        ),
+        (Op::Mod, Json::Number(a), Json::Number(b)) => {
+            Json::from((a.as_f64() as i64).wrapping_rem(b.as_f64() as i64))
+        }
+        (op, left, right) => {
+            let verb = match op {
+                Op::Add => "added to",
+                Op::Sub => "subtracted from",
+                Op::Mul => "multiplied by",
+                _ => "divided by",
+            };
+
+            return fail(
+                position,
+                format!(
+                    "{} ({}) cannot be {} {} ({})",
+                    right.type_name(),
+                    right,
+                    verb,
+                    left.type_name(),
+                    left
+                ),
+            );
+        }
+    };
+
+    Ok(result)
+}
+
+fn array_of<'j, 'a>(
+    value: &'j Json<'a>,
+    name: &str,
+    position: usize,
+) -> Result<&'j [Json<'a>], FilterError> {
+    match value {
+        Json::Array(arr) => Ok(arr),
+        _ => fail(
+            position,
+            format!("{} ({}) has no {}", value.type_name(), value, name),
+        ),
+    }
+}
+
+fn sorted<'a>(mut items: Vec<(Json<'a>, Json<'a>)>) -> Vec<Json<'a>> {
+    items.sort_by(|(a, _), (b, _)| order(a, b));
+    items.into_iter().map(|(_, item)| item).collect()
+}
+
+fn call<'a>(
+    name: &str,
+    args: &[Expr],
+    input: &Json<'a>,
+    position: usize,
+) -> Result<Vec<Json<'a>>, FilterError> {
+    let parsed = input.parse_raw();
+    let value = &*parsed;
+
+    let result = match name {
+        "empty" => return Ok(vec![]),
+        "select" => {
+            let mut outputs = vec![];
+
+            for condition in eval(&args[0], input)? {
+                if truthy(&condition) {
+                    outputs.push(input.clone());
+                }
+            }
+
+            return Ok(outputs);
+        }
+        "map" => {
+            let mut items = vec![];
+
+            for item in iterate(value, position)? {
+                items.extend(eval(&args[0], &item)?);
+            }
+
+            Json::Array(items)
+        }
+        "has" => {
+            let mut outputs = vec![];
+
+            for key in eval(&args[0], input)? {
+                let has = match (value, &key) {
+                    (Json::Object(obj), Json::String(key)) => obj.contains_key(key.as_ref()),
+                    (Json::Array(arr), Json::Number(n)) => {
+                        n.as_f64() >= 0.0 && n.as_f64() < arr.len() as f64
+                    }
+                    _ => {
+                        return fail(
+                            position,
+                            format!(
+                                "Cannot check whether {} has a {} key",
+                                value.type_name(),
+                                key.type_name()
+                            ),
+                        )
+                    }
+                };
+
+                outputs.push(Json::Bool(has));
+            }
+
+            return Ok(outputs);
+        }
+        "join" => {
+            let mut outputs = vec![];
+            let items = array_of(value, "items to join", position)?;
+
+            for separator in eval(&args[0], input)? {
+                let separator = match &separator {
+                    Json::String(s) => s.to_string(),
+                    other => {
+                        return fail(position, format!("Cannot join with {}", other.type_name()))
+                    }
+                };
+
+                let mut parts = vec![];
+
+                for item in items {
+                    parts.push(match item {
+                        Json::Null => String::new(),
+                        Json::String(s) => s.to_string(),
+                        Json::Number(_) | Json::Bool(_) => item.to_string(),
+                        other => {
+                            return fail(position, format!("Cannot join {}", other.type_name()))
+                        }
+                    });
+                }
+
+                outputs.push(Json::from(parts.join(&separator)));
+            }
+
+            return Ok(outputs);
+        }
+        "sort_by" => {
+            let mut keyed = vec![];
+
+            for item in array_of(value, "items to sort", position)? {
+                keyed.push((Json::Array(eval(&args[0], item)?), item.clone()));
+            }
+
+            Json::Array(sorted(keyed))
+        }
+        "length" => match value {
+            Json::Null | Json::Error => Json::from(0),
+            Json::Number(n) => number(n.as_f64().abs()),
+            Json::String(s) => Json::from(s.chars().count()),
+            Json::Array(arr) => Json::from(arr.len()),
+            Json::Object(obj) => Json::from(obj.len()),
+            _ => return fail(position, format!("{} has no length", value.type_name())),
+        },
+        "keys" => match value {
+            Json::Object(obj) => {
+                let mut keys: Vec<_> = obj.keys().map(|key| Json::from(key.to_string())).collect();
+                keys.sort_by(order);
+                Json::Array(keys)
+            }
+            Json::Array(arr) => Json::Array((0..arr.len()).map(Json::from).collect()),
+            _ => return fail(position, format!("{} has no keys", value.type_name())),
+        },
+        "type" => Json::from(value.type_name()),
+        "not" => Json::Bool(!truthy(value)),
+        "add" => {
+            let mut sum = Json::Null;
+
+            for item in array_of(value, "items to add", position)? {
+                sum = binary(sum, Op::Add, item.clone(), position)?;
+            }
+
+            sum
+        }
+        "any" => Json::Bool(array_of(value, "items", position)?.iter().any(truthy)),
+        "all" => Json::Bool(array_of(value, "items", position)?.iter().all(truthy)),
+        "first" => index(value, &Json::from(0), position)?,
+        "last" => index(value, &Json::from(-1), position)?,
+        "sort" => {
+            let items = array_of(value, "items to sort", position)?;
+            Json::Array(sorted(
+                items
+                    .iter()
+                    .map(|item| (item.clone(), item.clone()))
+                    .collect(),
+            ))
+        }
+        "unique" => {
+            let items = array_of(value, "items", position)?;
+            let mut items = sorted(
+                items
+                    .iter()
+                    .map(|item| (item.clone(), item.clone()))
+                    .collect(),
+            );
+            items.dedup_by(|a, b| order(a, b) == Ordering::Equal);
+            Json::Array(items)
+        }
+        "reverse" => match value {
+            Json::Null => Json::Array(vec![]),
+            Json::String(s) => Json::from(s.chars().rev().collect::<String>()),
+            _ => Json::Array(
+                array_of(value, "items", position)?
+                    .iter()
+                    .rev()
+                    .cloned()
+                    .collect(),
+            ),
+        },
+        "min" | "max" => {
+            let items = array_of(value, "items", position)?;
+            let extreme = if name == "min" {
+                items.iter().min_by(|a, b| order(a, b))
+            } else {
+                items.iter().max_by(|a, b| order(a, b))
+            };
+
+            extreme.cloned().unwrap_or_default()
+        }
+        "tostring" => match value {
+            Json::String(_) => value.clone(),
+            _ => Json::from(value.to_string()),
+        },
+        "tonumber" => match value {
+            Json::Number(_) => value.clone(),
+            Json::String(s) => match Number::parse(s.trim()) {
+                Some(n) => Json::Number(n),
+                None => return fail(position, format!("Cannot parse '{}' as a number", s)),
+            },
+            _ => {
+                return fail(
+                    position,
+                    format!("{} cannot be parsed as a number", value.type_name()),
+                )
+            }
+        },
+        "ascii_downcase" | "ascii_upcase" => match value {
+            Json::String(s) if name == "ascii_downcase" => Json::from(s.to_ascii_lowercase()),
+            Json::String(s) => Json::from(s.to_ascii_uppercase()),
+            _ => {
+                return fail(
+                    position,
+                    format!("{} cannot be case converted", value.type_name()),
+                )
+            }
+        },
+        "to_entries" => match value {
+            Json::Object(obj) => Json::Array(
+                obj.iter()
+                    .map(|(key, item)| {
+                        let mut entry = Map::new();
+                        entry.insert(Cow::Borrowed("key"), Json::from(key.to_string()));
+                        entry.insert(Cow::Borrowed("value"), item.clone());
+                        Json::Object(entry)
+                    })
+                    .collect(),
+            ),
+            _ => return fail(position, format!("{} has no entries", value.type_name())),
+        },
+        "from_entries" => {
+            let mut obj = Map::new();
+
+            for entry in array_of(value, "entries", position)? {
+                let key = ["key", "k", "name", "Name", "Key", "K"]
+                    .iter()
+                    .find_map(|name| entry.get(name).filter(|key| truthy(key)));
+                let key = match key {
+                    Some(Json::String(key)) => key.to_string(),
+                    Some(key @ (Json::Number(_) | Json::Bool(_))) => key.to_string(),
+                    _ => return fail(position, "Entries need a string key".to_string()),
+                };
+                let item = ["value", "v", "Value", "V"]
+                    .iter()
+                    .find_map(|name| entry.get(name))
+                    .cloned()
+                    .unwrap_or_default();
+
+                obj.insert(Cow::Owned(key), item);
+            }
+
+            Json::Object(obj)
+        }
+        _ => unreachable!("{} is not in FUNCTIONS", name),
+    };
+
+    Ok(vec![result])
+}
+
+fn eval<'a>(expr: &Expr, input: &Json<'a>) -> Result<Vec<Json<'a>>, FilterError> {
+    Ok(match expr {
+        Expr::Identity => vec![input.clone()],
+        Expr::Recurse => {
+            let mut outputs = vec![];
+            descendants(input, &mut outputs);
+            outputs
+        }
+        Expr::Literal(value) => vec![value.clone()],
+        Expr::Index(target, key, position) => {
+            let mut outputs = vec![];
+
+            for target in eval(target, input)? {
+                for key in eval(key, input)? {
+                    outputs.push(index(&target, &key, *position)?);
+                }
+            }
+
+            outputs
+        }
+        Expr::Slice(target, from, to, position) => {
+            let bounds = |bound: &Option<Box<Expr>>| match bound {
+                Some(bound) => {
+                    eval(bound, input).map(|values| values.into_iter().map(Some).collect())
+                }
+                None => Ok(vec![None]),
+            };
+            let (froms, tos) = (bounds(from)?, bounds(to)?);
+            let mut outputs = vec![];
+
+            for target in eval(target, input)? {
+                for to in &tos {
+                    for from in &froms {
+                        outputs.push(slice(&target, from.as_ref(), to.as_ref(), *position)?);
+                    }
+                }
+            }
+
+            outputs
+        }
+        Expr::Iterate(target, position) => {
+            let mut outputs = vec![];
+
+            for target in eval(target, input)? {
+                outputs.extend(iterate(&target, *position)?);
+            }
+
+            outputs
+        }
+        Expr::Optional(expr) => eval(expr, input).unwrap_or_default(),
+        Expr::Pipe(left, right) => {
+            let mut outputs = vec![];
+
+            for value in eval(left, input)? {
+                outputs.extend(eval(right, &value)?);
+            }
+
+            outputs
+        }
+        Expr::Comma(items) => {
+            let mut outputs = vec![];
+
+            for item in items {
+                outputs.extend(eval(item, input)?);
+            }
+
+            outputs
+        }
+        Expr::Alternative(left, right) => {
+            let outputs: Vec<_> = eval(left, input)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(truthy)
+                .collect();
+
+            if outputs.is_empty() {
+                eval(right, input)?
+            } else {
+                outputs
+            }
+        }
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            let is_and = matches!(expr, Expr::And(..));
+            let mut outputs = vec![];
+
+            for left in eval(left, input)? {
+                if truthy(&left) != is_and {
+                    outputs.push(Json::Bool(!is_and));
+                    continue;
+                }
+
+                for right in eval(right, input)? {
+                    outputs.push(Json::Bool(truthy(&right)));
+                }
+            }
+
+            outputs
+        }
+        Expr::Binary(left, op, right, position) => {
+            let lefts = eval(left, input)?;
+            let mut outputs = vec![];
+
+            for right in eval(right, input)? {
+                for left in &lefts {
+                    outputs.push(binary(left.clone(), *op, right.clone(), *position)?);
+                }
+            }
+
+            outputs
+        }
+        Expr::Neg(expr, position) => {
+            let mut outputs = vec![];
+
+            for value in eval(expr, input)? {
+                match value.parse_raw().as_ref() {
+                    Json::Number(n) => outputs.push(number(-n.as_f64())),
+                    other => {
+                        return fail(
+                            *position,
+                            format!("{} cannot be negated", other.type_name()),
+                        )
+                    }
+                }
+            }
+
+            outputs
+        }
+        Expr::Array(None) => vec![Json::Array(vec![])],
+        Expr::Array(Some(items)) => vec![Json::Array(eval(items, input)?)],
+        Expr::Object(entries, position) => {
+            let mut objects = vec![Map::new()];
+
+            for (key, value) in entries {
+                let keys = eval(key, input)?;
+                let values = eval(value, input)?;
+                let mut next = vec![];
+
+                for obj in &objects {
+                    for key in &keys {
+                        let key = match key {
+                            Json::String(key) => key.to_string(),
+                            other => {
+                                return fail(
+                                    *position,
+                                    format!(
+                                        "Object keys must be strings, not {}",
+                                        other.type_name()
+                                    ),
+                                )
+                            }
+                        };
+
+                        for value in &values {
+                            let mut obj = obj.clone();
+                            obj.insert(Cow::Owned(key.clone()), value.clone());
+                            next.push(obj);
+                        }
+                    }
+                }
+
+                objects = next;
+            }
+
+            objects.into_iter().map(Json::Object).collect()
+        }
+        Expr::If(condition, then, otherwise) => {
+            let mut outputs = vec![];
+
+            for condition in eval(condition, input)? {
+                if truthy(&condition) {
+                    outputs.extend(eval(then, input)?);
+                } else if let Some(otherwise) = otherwise {
+                    outputs.extend(eval(otherwise, input)?);
+                } else {
+                    outputs.push(input.clone());
+                }
+            }
+
+            outputs
+        }
+        Expr::Call(name, args, position) => call(name, args, input, *position)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    fn run(filter: &str, input: &str) -> Vec<String> {
+        let value = parser::parse_str(input).unwrap();
+        let filter = compile(filter).unwrap_or_else(|error| panic!("{}: {}", filter, error));
+
+        filter
+            .run(&value)
+            .unwrap_or_else(|error| panic!("{}", error))
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        let input = r#"{"a": {"b": [1, 2, 3]}, "c d": null}"#;
+
+        let cases = [
+            (".", vec![r#"{"a":{"b":[1,2,3]},"c d":null}"#]),
+            (".a.b", vec!["[1,2,3]"]),
+            (".a.b[]", vec!["1", "2", "3"]),
+            (".a.b[-1]", vec!["3"]),
+            (".a.b[1:]", vec!["[2,3]"]),
+            (".a[\"b\"][0]", vec!["1"]),
+            (".\"c d\", .x", vec!["null", "null"]),
+            (".[]?", vec![r#"{"b":[1,2,3]}"#, "null"]),
+            (".a.b[]?.x?", vec![]),
+            ("[..] | length", vec!["7"]),
+        ];
+
+        for (filter, expected) in cases {
+            assert_eq!(run(filter, input), expected, "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let input = r#"{"items": [
+            {"id": 1, "name": "a", "x": 5, "tags": ["p"]},
+            {"id": 2, "name": "b", "x": 2, "tags": []},
+            {"id": 3, "name": "c", "x": 4}
+        ]}"#;
+
+        let cases = [
+            (
+                ".items[] | select(.x > 3) | {id, name}",
+                vec![r#"{"id":1,"name":"a"}"#, r#"{"id":3,"name":"c"}"#],
+            ),
+            ("[.items[].x] | add", vec!["11"]),
+            (".items | map(.x * 2 + 1)", vec!["[11,5,9]"]),
+            (".items | sort_by(.x) | map(.id)", vec!["[2,3,1]"]),
+            (
+                ".items | map(.tags // \"none\")",
+                vec![r#"[["p"],[],"none"]"#],
+            ),
+            (".items[0] | keys", vec![r#"["id","name","tags","x"]"#]),
+            (".items | map(has(\"tags\"))", vec!["[true,true,false]"]),
+            (
+                ".items[] | if .x > 4 then \"big\" elif .x > 2 then \"mid\" else \"small\" end",
+                vec!["\"big\"", "\"small\"", "\"mid\""],
+            ),
+            ("[.items[].name] | join(\"-\")", vec!["\"a-b-c\""]),
+            ("{(.items[0].name): .items[1].id}", vec![r#"{"a":2}"#]),
+            ("(1, 2) + (10, 20)", vec!["11", "12", "21", "22"]),
+            (
+                "[3, 1, null, \"a\", [1]] | sort",
+                vec![r#"[null,1,3,"a",[1]]"#],
+            ),
+            ("[1, 1.0, 2] | unique", vec!["[1,2]"]),
+            (
+                "{\"a\": {\"b\": 1}} * {\"a\": {\"c\": 2}}",
+                vec![r#"{"a":{"b":1,"c":2}}"#],
+            ),
+            ("\"a,b\" / \",\"", vec![r#"["a","b"]"#]),
+            ("\"\\ud83d\\ude00\" | length", vec!["1"]),
+            (
+                ".items[] | \"\\(.name) has \\(.tags | length)\"",
+                vec!["\"a has 1\"", "\"b has 0\"", "\"c has 0\""],
+            ),
+            (
+                "\"\\(1, 2)-\\(\"x\\(3)\")\\\\(\"",
+                vec!["\"1-x3\\\\(\"", "\"2-x3\\\\(\""],
+            ),
+            (
+                ".items[0] | {\"\\(.name)_id\": .id, \"n\\(1)\": .x} | .\"a_\\(\"id\")\"",
+                vec!["1"],
+            ),
+            ("-(1 - 3) % 2, 7 % 3, 1 / 4", vec!["0", "1", "0.25"]),
+            (
+                ".items | length > 2 and (.[0].x == 5 or false)",
+                vec!["true"],
+            ),
+            (
+                "[.items[] | .id | tostring] | map(tonumber) | max",
+                vec!["3"],
+            ),
+            (
+                ".items[0] | to_entries[0]",
+                vec![r#"{"key":"id","value":1}"#],
+            ),
+            (
+                "[{key: \"a\", value: 1}] | from_entries",
+                vec![r#"{"a":1}"#],
+            ),
+            (
+                "\"AbC\" | ascii_downcase, ascii_upcase, type",
+                vec!["\"abc\"", "\"ABC\"", "\"string\""],
+            ),
+            ("[1, 2] | first, last, reverse", vec!["1", "2", "[2,1]"]),
+            (
+                "[empty, 1] | any, all, (map(not) | .[0])",
+                vec!["true", "true", "false"],
+            ),
+        ];
+
+        for (filter, expected) in cases {
+            assert_eq!(run(filter, input), expected, "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            (".a |", 4, "Unexpected end of the filter"),
+            (".a ]", 3, "Unexpected ']'"),
+            ("foo(1)", 0, "Unknown function foo/1"),
+            ("if . then 1", 11, "Expected 'end'"),
+            ("\"abc", 0, "Missing closing quote"),
+            ("\"a\\(.b) c", 0, "Missing closing quote"),
+            ("\"a\\(.b\"", 6, "Unexpected '\"'"),
+            ("\"a\\u+041\"", 2, "Invalid escape sequence"),
+            ("\"a\\ud800\"", 2, "Invalid escape sequence"),
+            ("\"\\(1)\\x\"", 5, "Invalid escape sequence"),
+        ];
+
+        for (filter, position, message) in cases {
+            let error = compile(filter).unwrap_err();
+            assert_eq!(
+                (error.position, error.message.as_str()),
+                (position, message),
+                "{}",
+                filter
+            );
+        }
+
+        let deep = [
+            "(".repeat(100_000),
+            "[".repeat(100_000),
+            "1".to_string() + &" + 1".repeat(100_000),
+            ".a".repeat(100_000),
+            "if . then 1 ".to_string() + &"elif . then 1 ".repeat(100_000),
+            "\"".to_string() + &"\\(1)x".repeat(100_000) + "\"",
+        ];
+
+        for filter in deep {
+            let error = compile(&filter).unwrap_err();
+            assert_eq!(error.message, "The filter is nested too deeply");
+        }
+
+        let nested = "(".repeat(MAX_DEPTH - 1) + "1" + &")".repeat(MAX_DEPTH - 1);
+        let value = parser::parse_str("null").unwrap();
+        let outputs = compile(&nested).unwrap().run(&value).unwrap();
+        assert_eq!(outputs, [Json::from(1)]);
+        assert!(compile(&("(".repeat(MAX_DEPTH) + "1" + &")".repeat(MAX_DEPTH))).is_err());
+
+        // Each level is a parenthesis and an addition, which `eval` recurses into
+        let sums = "(1 + ".repeat(MAX_DEPTH / 2 - 1) + "1" + &")".repeat(MAX_DEPTH / 2 - 1);
+        let outputs = compile(&sums).unwrap().run(&value).unwrap();
+        assert_eq!(outputs, [Json::from(MAX_DEPTH / 2)]);
+
+        // Lists are not nested
+        let list = "[1".to_string() + &", 1".repeat(10_000) + "] | length";
+        let outputs = compile(&list).unwrap().run(&value).unwrap();
+        assert_eq!(outputs, [Json::from(10_001)]);
+
+        let value = parser::parse_str(r#"{"a": [1, "x"]}"#).unwrap();
+        let error = compile(".a | .[1].b").unwrap().run(&value).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot index string with \"b\" at character 10"
+        );
+
+        let error = compile(".a[] + 1").unwrap().run(&value).unwrap_err();
+        assert_eq!(
+            error.message,
+            "number (1) cannot be added to string (\"x\")"
+        );
+        assert_eq!(
+            compile(".a[] + 1")
+                .unwrap()
+                .run(&value)
+                .map_err(|error| error.position),
+            Err(5)
+        );
+
+        let error = compile("1 / 0").unwrap().run(&value).unwrap_err();
+        assert_eq!(error.message, "Division by zero");
+    }
+}
@@ -0,0 +1,343 @@
+use parser::{Json, Map, DEFAULT_MAX_DEPTH};
+use pointer;
+use std::fmt;
+use std::mem;
+
+// How `flatten` writes paths, like `a.b[0].c` by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlattenOptions {
+    // Between object keys, `__` for environment variables for example
+    pub separator: String,
+    // Array indexes as `[0]`, otherwise like keys with the separator, `a.0`. Without brackets,
+    // `unflatten` reads keys made of digits as indexes
+    pub bracket_indexes: bool,
+    // Paths with more steps are an error of `unflatten`, like `ParserOptions::max_depth` for
+    // nesting in the input
+    pub max_depth: usize,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        FlattenOptions {
+            separator: ".".to_string(),
+            bracket_indexes: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+// Why `unflatten` could not rebuild a document, with the offending path
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnflattenError {
+    // A `\` at the end, or a `[` without a number and `]`
+    InvalidPath(String),
+    // A value already set at the path, or a parent that is not an object or array like the path
+    // needs, like both `a` and `a.b`
+    Conflict(String),
+    // A path with more steps than `max_depth`
+    DepthLimitExceeded(String),
+}
+
+impl fmt::Display for UnflattenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnflattenError::InvalidPath(path) => write!(f, "Invalid path '{}'", path),
+            UnflattenError::Conflict(path) => write!(f, "Conflicting value at '{}'", path),
+            UnflattenError::DepthLimitExceeded(path) => {
+                write!(f, "Path '{}' is nested too deep", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnflattenError {}
+
+// The separator, `[` and `\` are escaped with a `\`
+fn escape_key(key: &str, options: &FlattenOptions) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    let mut rest = key;
+
+    while let Some(c) = rest.chars().next() {
+        if !options.separator.is_empty() && rest.starts_with(&options.separator) {
+            escaped.push('\\');
+            escaped.push_str(&options.separator);
+            rest = &rest[options.separator.len()..];
+            continue;
+        }
+
+        if c == '\\' || (c == '[' && options.bracket_indexes) {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    escaped
+}
+
+fn flatten_into<'a>(
+    value: &Json<'a>,
+    path: String,
+    options: &FlattenOptions,
+    flat: &mut Map<String, Json<'a>>,
+) {
+    match value {
+        Json::Raw(_) => flatten_into(&value.parse_raw(), path, options, flat),
+        Json::Object(obj) if !obj.is_empty() => {
+            for (key, item) in obj {
+                let key = escape_key(key, options);
+                let path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}{}{}", path, options.separator, key)
+                };
+                flatten_into(item, path, options, flat);
+            }
+        }
+        Json::Array(arr) if !arr.is_empty() => {
+            for (index, item) in arr.iter().enumerate() {
+                let path = match (options.bracket_indexes, path.is_empty()) {
+                    (true, _) => format!("{}[{}]", path, index),
+                    (false, true) => index.to_string(),
+                    (false, false) => format!("{}{}{}", path, options.separator, index),
+                };
+                flatten_into(item, path, options, flat);
+            }
+        }
+        _ => {
+            flat.insert(path, value.clone());
+        }
+    }
+}
+
+// Every scalar, empty array and empty object of the document by its path, like
+// `{"a.b[0].c": 1}`. A document that is a scalar has the empty path
+pub fn flatten<'a>(value: &Json<'a>, options: &FlattenOptions) -> Map<String, Json<'a>> {
+    let mut flat = Map::new();
+    flatten_into(value, String::new(), options, &mut flat);
+    flat
+}
+
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+fn steps(path: &str, options: &FlattenOptions) -> Result<Vec<Step>, UnflattenError> {
+    let invalid = || UnflattenError::InvalidPath(path.to_string());
+    let mut steps = vec![];
+    let mut key = String::new();
+    // Whether a key ends at the next separator, `[` or the end, it does not after a `]`
+    let mut in_key = !path.is_empty();
+    let mut rest = path;
+
+    while let Some(c) = rest.chars().next() {
+        if !options.separator.is_empty() && rest.starts_with(&options.separator) {
+            if in_key {
+                steps.push(Step::Key(mem::take(&mut key)));
+            }
+
+            in_key = true;
+            rest = &rest[options.separator.len()..];
+        } else if c == '\\' {
+            let escaped = rest[1..].chars().next().ok_or_else(invalid)?;
+            key.push(escaped);
+            rest = &rest[1 + escaped.len_utf8()..];
+        } else if c == '[' && options.bracket_indexes {
+            if in_key {
+                steps.push(Step::Key(mem::take(&mut key)));
+            }
+
+            let end = rest.find(']').ok_or_else(invalid)?;
+            steps.push(Step::Index(
+                pointer::index(&rest[1..end]).ok_or_else(invalid)?,
+            ));
+            in_key = false;
+            rest = &rest[end + 1..];
+        } else if !in_key {
+            return Err(invalid());
+        } else {
+            key.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    if in_key {
+        steps.push(Step::Key(key));
+    }
+
+    if !options.bracket_indexes {
+        for step in &mut steps {
+            if let Step::Key(key) = step {
+                if let Some(index) = pointer::index(key) {
+                    *step = Step::Index(index);
+                }
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+// `Null` is a place nothing was set yet, missing array items before an index are filled with it
+fn child<'j, 'a>(parent: &'j mut Json<'a>, step: &Step) -> Option<&'j mut Json<'a>> {
+    if let Json::Null = parent {
+        *parent = match step {
+            Step::Key(_) => Json::Object(Map::new()),
+            Step::Index(_) => Json::Array(vec![]),
+        };
+    }
+
+    match (parent, step) {
+        (Json::Object(obj), Step::Key(key)) => {
+            let key = pointer::find_key(obj, key);
+            Some(obj.entry(key).or_insert(Json::Null))
+        }
+        (Json::Array(arr), Step::Index(index)) => {
+            if arr.len() <= *index {
+                arr.resize(index + 1, Json::Null);
+            }
+
+            Some(&mut arr[*index])
+        }
+        _ => None,
+    }
+}
+
+// Rebuilds the document `flatten` made the paths of, in any order
+pub fn unflatten<'a, I, S>(flat: I, options: &FlattenOptions) -> Result<Json<'a>, UnflattenError>
+where
+    I: IntoIterator<Item = (S, Json<'a>)>,
+    S: AsRef<str>,
+{
+    let mut root = Json::Null;
+    let mut empty = true;
+
+    for (path, value) in flat {
+        let path = path.as_ref();
+        let conflict = || UnflattenError::Conflict(path.to_string());
+        let mut current = &mut root;
+
+        let steps = steps(path, options)?;
+
+        if steps.len() > options.max_depth {
+            return Err(UnflattenError::DepthLimitExceeded(path.to_string()));
+        }
+
+        for step in steps {
+            current = child(current, &step).ok_or_else(conflict)?;
+        }
+
+        if !matches!(current, Json::Null) {
+            return Err(conflict());
+        }
+
+        *current = value;
+        empty = false;
+    }
+
+    Ok(if empty {
+        Json::Object(Map::new())
+    } else {
+        root
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    #[test]
+    fn test_flatten() {
+        let value = parser::parse_str(
+            r#"{"a": {"b": [{"c": 1}, [true, null]]}, "d.e": "x", "f": {}, "g[0]": [], "h\\": 2}"#,
+        )
+        .unwrap();
+        let options = FlattenOptions::default();
+
+        let flat = value.flatten(&options);
+        let paths: Vec<_> = flat.keys().map(String::as_str).collect();
+        let mut expected = vec![
+            "a.b[0].c",
+            "a.b[1][0]",
+            "a.b[1][1]",
+            r"d\.e",
+            "f",
+            r"g\[0]",
+            r"h\\",
+        ];
+        expected.sort();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(sorted, expected);
+        assert_eq!(flat["a.b[1][0]"], Json::Bool(true));
+
+        assert_eq!(Json::unflatten(flat, &options).unwrap(), value);
+
+        let env = FlattenOptions {
+            separator: "__".to_string(),
+            bracket_indexes: false,
+            ..FlattenOptions::default()
+        };
+        let flat = value.flatten(&env);
+        assert_eq!(flat["a__b__1__0"], Json::Bool(true));
+        assert_eq!(Json::unflatten(flat, &env).unwrap(), value);
+
+        assert_eq!(
+            Json::from(1)
+                .flatten(&options)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [(String::new(), Json::from(1))]
+        );
+    }
+
+    #[test]
+    fn test_unflatten() {
+        let options = FlattenOptions::default();
+
+        let unordered = vec![
+            ("a[2]", Json::from(3)),
+            ("a[0]", Json::from(1)),
+            ("a[1]", Json::from(2)),
+        ];
+        assert_eq!(
+            Json::unflatten(unordered, &options).unwrap(),
+            parser::parse_str(r#"{"a": [1, 2, 3]}"#).unwrap()
+        );
+        assert_eq!(
+            Json::unflatten(Vec::<(String, Json)>::new(), &options).unwrap(),
+            Json::Object(Map::new())
+        );
+
+        let cases = [
+            ("a", "a.b", UnflattenError::Conflict("a.b".to_string())),
+            ("a.b", "a", UnflattenError::Conflict("a".to_string())),
+            ("a.b", "a[0]", UnflattenError::Conflict("a[0]".to_string())),
+            ("a", "b[x]", UnflattenError::InvalidPath("b[x]".to_string())),
+            (
+                "a",
+                "b[0]c",
+                UnflattenError::InvalidPath("b[0]c".to_string()),
+            ),
+            ("a", "b\\", UnflattenError::InvalidPath("b\\".to_string())),
+        ];
+
+        for (first, second, expected) in cases {
+            let flat = vec![(first, Json::from(1)), (second, Json::from(2))];
+            assert_eq!(Json::unflatten(flat, &options), Err(expected), "{}", second);
+        }
+
+        let deep = "a.".repeat(100_000) + "b";
+        assert_eq!(
+            Json::unflatten(vec![(deep.as_str(), Json::from(1))], &options),
+            Err(UnflattenError::DepthLimitExceeded(deep.clone()))
+        );
+
+        let limit = "a.".repeat(DEFAULT_MAX_DEPTH - 1) + "b";
+        let value = Json::unflatten(vec![(limit.as_str(), Json::from(1))], &options).unwrap();
+        assert_eq!(value.flatten(&options).len(), 1);
+    }
+}
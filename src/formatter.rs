@@ -1,6 +1,60 @@
-use crate::lexer::TokenType;
-use parser::Json;
-use std::{collections::BTreeMap, usize};
+use crate::lexer::{self, TokenType};
+use escape::escape;
+use parser::{self, Json, Map};
+use pointer;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+// How string values that contain a JSON object or array are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmbeddedJson {
+    // Replaced by the formatted document, the output no longer holds the original string
+    Inline,
+    // Kept as a string holding the formatted document
+    Escaped,
+}
+
+// Sorts the arrays at `array`, a JSON pointer where `*` stands for any key or index, by the
+// value at the `key` pointer of each item
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArraySort {
+    pub array: String,
+    pub key: String,
+}
+
+// What happens to a byte order mark at the start of the input, see `with_bom`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bom {
+    // Written back when the input has one
+    Preserve,
+    Strip,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub indent_length: usize,
+    pub embedded_json: Option<EmbeddedJson>,
+    // How many levels of strings held in formatted embedded JSON are formatted as well, deeper
+    // ones are written as strings
+    pub max_embedded_depth: usize,
+    // Objects keep the order of the input unless built without the `preserve_order` feature
+    pub sort_keys: bool,
+    pub sort_arrays: Vec<ArraySort>,
+    pub bom: Bom,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_length: 4,
+            embedded_json: None,
+            max_embedded_depth: 8,
+            sort_keys: false,
+            sort_arrays: vec![],
+            bom: Bom::Preserve,
+        }
+    }
+}
 
 fn comma_builder<I, T, F>(
     length: usize,
@@ -8,12 +62,13 @@ fn comma_builder<I, T, F>(
     open: TokenType,
     formatter: F,
     ilvl: usize,
-    ilen: usize,
+    options: &FormatOptions,
 ) -> String
 where
-    F: Fn(T, usize, usize) -> String,
+    F: Fn(T, usize) -> String,
     I: std::iter::Iterator<Item = T>,
 {
+    let ilen = options.indent_length;
     let mut result = match open {
         TokenType::OpenCurly => "{".to_string(),
         _ => "[".to_string(),
@@ -24,11 +79,11 @@ where
             result += format!(
                 "\n{}{}",
                 " ".repeat((ilvl + 1) * ilen),
-                formatter(item, ilvl + 1, ilen)
+                formatter(item, ilvl + 1)
             )
             .as_str();
         } else {
-            result += formatter(item, ilvl, ilen).as_str();
+            result += formatter(item, ilvl).as_str();
         }
 
         if index != length - 1 {
@@ -49,76 +104,211 @@ where
     result
 }
 
-fn array(arr: Vec<Json>, ilvl: usize, ilen: usize) -> String {
+fn array(arr: &[Json], ilvl: usize, options: &FormatOptions) -> String {
     comma_builder(
         arr.len(),
-        arr.into_iter(),
+        arr.iter(),
         TokenType::OpenSquare,
-        value,
+        |val, ilvl| value(val, ilvl, options),
         ilvl,
-        ilen,
+        options,
     )
 }
 
-fn object(obj: BTreeMap<&str, Json>, ilvl: usize, ilen: usize) -> String {
-    let formatter = |item: (&str, Json), ilvl: usize, ilen: usize| -> String {
+fn object(obj: &Map<Cow<str>, Json>, ilvl: usize, options: &FormatOptions) -> String {
+    let formatter = |item: (&Cow<str>, &Json), ilvl: usize| -> String {
         let (key, val) = item;
 
-        if ilen != 0 {
-            format!("\"{}\": {}", key, value(val, ilvl, ilen))
+        if options.indent_length != 0 {
+            format!("\"{}\": {}", escape(key), value(val, ilvl, options))
         } else {
-            format!("\"{}\":{}", key, value(val, ilvl, ilen))
+            format!("\"{}\":{}", escape(key), value(val, ilvl, options))
         }
     };
 
+    let mut entries: Vec<_> = obj.iter().collect();
+
+    if options.sort_keys {
+        entries.sort_by_key(|(key, _)| *key);
+    }
+
     comma_builder(
-        obj.len(),
-        obj.into_iter(),
+        entries.len(),
+        entries.into_iter(),
         TokenType::OpenCurly,
         formatter,
         ilvl,
-        ilen,
+        options,
     )
 }
 
-fn value(val: Json, ilvl: usize, ilen: usize) -> String {
+// Formats the string as JSON if it holds an object or array, scalars are left alone
+fn embedded(text: &str, ilvl: usize, options: &FormatOptions) -> Option<String> {
+    let mode = options.embedded_json?;
+    let trimmed = text.trim_start();
+
+    if options.max_embedded_depth == 0 || !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+
+    let tokens = lexer::lex(text).ok()?;
+    let embedded_value = parser::parse(&tokens).ok()?;
+    let options = &FormatOptions {
+        max_embedded_depth: options.max_embedded_depth - 1,
+        ..options.clone()
+    };
+
+    match mode {
+        EmbeddedJson::Inline => Some(value(&embedded_value, ilvl, options)),
+        EmbeddedJson::Escaped => {
+            let formatted = value(&embedded_value, 0, options);
+            Some(format!("\"{}\"", escape(&formatted)))
+        }
+    }
+}
+
+fn value(val: &Json, ilvl: usize, options: &FormatOptions) -> String {
     match val {
-        Json::Null => "null".to_string(),
+        Json::Null | Json::Error => "null".to_string(),
         Json::Bool(b) => format!("{}", b),
         Json::Number(n) => format!("{}", n),
-        Json::String(s) => format!("\"{}\"", s),
-        Json::Object(obj) => object(obj, ilvl, ilen),
-        Json::Array(arr) => array(arr, ilvl, ilen),
+        Json::String(s) => {
+            embedded(s, ilvl, options).unwrap_or_else(|| format!("\"{}\"", escape(s)))
+        }
+        Json::Object(obj) => object(obj, ilvl, options),
+        Json::Array(arr) => array(arr, ilvl, options),
+        Json::Raw(raw) => raw.as_str().to_string(),
+    }
+}
+
+pub fn format_json(val: &Json, indent_length: usize) -> String {
+    let options = FormatOptions {
+        indent_length,
+        ..FormatOptions::default()
+    };
+
+    format_json_with_options(val, &options)
+}
+
+pub fn format_json_with_options(val: &Json, options: &FormatOptions) -> String {
+    if options.sort_arrays.is_empty() {
+        return value(val, 0, options);
+    }
+
+    let mut sorted = val.clone();
+    sort_arrays(&mut sorted, &mut vec![], &options.sort_arrays);
+    value(&sorted, 0, options)
+}
+
+// Puts the byte order mark of `raw` back in front of its formatted output, unless stripped.
+// The formatted value itself never starts with one
+pub fn with_bom(raw: &str, formatted: String, options: &FormatOptions) -> String {
+    if options.bom == Bom::Preserve && raw.starts_with(lexer::BOM) {
+        lexer::BOM.to_string() + &formatted
+    } else {
+        formatted
+    }
+}
+
+fn rank(val: Option<&Json>) -> u8 {
+    match val {
+        Some(Json::Null | Json::Error) => 0,
+        Some(Json::Bool(_)) => 1,
+        Some(Json::Number(_)) => 2,
+        Some(Json::String(_)) => 3,
+        Some(Json::Array(_)) => 4,
+        Some(Json::Object(_)) => 5,
+        Some(Json::Raw(_)) => 6,
+        // Items without the key go last
+        None => 7,
     }
 }
 
-pub fn format_json(val: Json, indent_length: usize) -> String {
-    value(val, 0, indent_length)
+// A total order over sort keys, values of different types are ordered by type. Raw values are
+// ordered by what their text holds
+fn compare(a: Option<&Json>, b: Option<&Json>) -> Ordering {
+    let (a, b) = (a.map(Json::contents), b.map(Json::contents));
+
+    match (a, b) {
+        (Some(Json::Bool(a)), Some(Json::Bool(b))) => a.cmp(b),
+        (Some(Json::Number(a)), Some(Json::Number(b))) => a.total_cmp(b),
+        (Some(Json::String(a)), Some(Json::String(b))) => a.cmp(b),
+        (Some(a @ Json::Array(_)), Some(b @ Json::Array(_)))
+        | (Some(a @ Json::Object(_)), Some(b @ Json::Object(_))) => {
+            format_json(a, 0).cmp(&format_json(b, 0))
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn sort_arrays(val: &mut Json, path: &mut Vec<String>, sorts: &[ArraySort]) {
+    match val {
+        Json::Object(obj) => {
+            for (key, item) in obj.iter_mut() {
+                path.push(key.to_string());
+                sort_arrays(item, path, sorts);
+                path.pop();
+            }
+        }
+        Json::Array(arr) => {
+            for (index, item) in arr.iter_mut().enumerate() {
+                path.push(index.to_string());
+                sort_arrays(item, path, sorts);
+                path.pop();
+            }
+
+            let sort = sorts.iter().find(|sort| {
+                pointer::split(&sort.array).is_some_and(|tokens| {
+                    tokens.len() == path.len()
+                        && tokens
+                            .iter()
+                            .zip(path.iter())
+                            .all(|(t, p)| t == "*" || t == p)
+                })
+            });
+
+            // A stable sort, items with equal keys keep their order
+            if let Some(sort) = sort {
+                arr.sort_by(|a, b| compare(a.pointer(&sort.key), b.pointer(&sort.key)));
+            }
+        }
+        _ => {}
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{lexer, parser};
 
-    use super::format_json;
+    use super::*;
 
     #[test]
     fn test_formatter() {
         let raw = "{\"foo\":[1,{\"bar\":{\"foo\":\"bar\"},\"foo\":[{\"foo\":\"bar\"},{\"foo\":\"bar\"}]},3,4],\"hello\":\"world\",\"qaz\":\"{\\\"bar\\\":0}\"}";
 
         for i in 0..10 {
-            let tokens = lexer::lex(raw);
+            let tokens = lexer::lex(raw).unwrap();
             let value = parser::parse(&tokens).unwrap();
-            let formatted = format_json(value, i);
+            let formatted = format_json(&value, i);
 
-            let tokens_rev = lexer::lex(&formatted);
+            let tokens_rev = lexer::lex(&formatted).unwrap();
             let value_rev = parser::parse(&tokens_rev).unwrap();
-            let formatted_rev = format_json(value_rev, 0);
+            let formatted_rev = format_json(&value_rev, 0);
 
             assert_eq!(formatted_rev, raw);
         }
     }
 
+    #[test]
+    fn test_lone_surrogates() {
+        // Rejected rather than formatted back as other text
+        assert!(parser::parse_str(r#"["\ud800"]"#).is_err());
+        assert!(parser::parse_str(r#"["\uD83D x"]"#).is_err());
+
+        let value = parser::parse_str(r#"["\\ud800", "\ud83d\ude00"]"#).unwrap();
+        assert_eq!(format_json(&value, 0), "[\"\\\\ud800\",\"\u{1f600}\"]");
+    }
+
     #[test]
     fn test_indent() {
         let expected = [
@@ -132,11 +322,125 @@ mod tests {
         for i in 0..5 {
             let raw = expected[0];
 
-            let tokens = lexer::lex(raw);
+            let tokens = lexer::lex(raw).unwrap();
             let value = parser::parse(&tokens).unwrap();
-            let formatted = format_json(value, i);
+            let formatted = format_json(&value, i);
 
             assert_eq!(formatted, expected[i]);
         }
     }
+
+    #[test]
+    fn test_embedded_json() {
+        let raw = r#"{"log":"{\"a\":[1,\"x\"]}","n":"12","s":"[not json"}"#;
+        let tokens = lexer::lex(raw).unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        let format = |indent_length, embedded_json| {
+            let options = FormatOptions {
+                indent_length,
+                embedded_json,
+                ..FormatOptions::default()
+            };
+            format_json_with_options(&value, &options)
+        };
+
+        assert_eq!(format(0, None), raw);
+        assert_eq!(
+            format(0, Some(EmbeddedJson::Inline)),
+            r#"{"log":{"a":[1,"x"]},"n":"12","s":"[not json"}"#
+        );
+        assert_eq!(
+            format(2, Some(EmbeddedJson::Escaped)),
+            r#"{
+  "log": "{\n  \"a\": [\n    1,\n    \"x\"\n  ]\n}",
+  "n": "12",
+  "s": "[not json"
+}"#
+        );
+
+        let nested = Json::from(r#"{"a": "[\"{}\", \"[1]\"]"}"#);
+        let format = |max_embedded_depth| {
+            let options = FormatOptions {
+                indent_length: 0,
+                embedded_json: Some(EmbeddedJson::Inline),
+                max_embedded_depth,
+                ..FormatOptions::default()
+            };
+            format_json_with_options(&nested, &options)
+        };
+        assert_eq!(format(2), r#"{"a":["{}","[1]"]}"#);
+        assert_eq!(format(3), r#"{"a":[{},[1]]}"#);
+    }
+
+    #[test]
+    fn test_sort_arrays() {
+        let tokens = lexer::lex(
+            r#"{
+                "contributors": [{"name": "b"}, {"id": 1}, {"name": "a", "ids": [3, 1]}],
+                "packages": [{"deps": [2, 10, 1]}],
+                "tags": ["y", "x"]
+            }"#,
+        )
+        .unwrap();
+        let value = parser::parse(&tokens).unwrap();
+        let sort = |array: &str, key: &str| ArraySort {
+            array: array.to_string(),
+            key: key.to_string(),
+        };
+        let options = FormatOptions {
+            indent_length: 0,
+            sort_keys: true,
+            sort_arrays: vec![
+                sort("/contributors", "/name"),
+                sort("/contributors/*/ids", ""),
+                sort("/packages/*/deps", ""),
+            ],
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_json_with_options(&value, &options),
+            concat!(
+                r#"{"contributors":[{"ids":[1,3],"name":"a"},{"name":"b"},{"id":1}],"#,
+                r#""packages":[{"deps":[1,2,10]}],"tags":["y","x"]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_with_bom() {
+        let raw = "\u{feff}{\"a\": 1}";
+        let value = parser::parse_str(raw).unwrap();
+        let mut options = FormatOptions {
+            indent_length: 0,
+            ..FormatOptions::default()
+        };
+
+        let formatted = format_json_with_options(&value, &options);
+        assert_eq!(
+            with_bom(raw, formatted.clone(), &options),
+            "\u{feff}{\"a\":1}"
+        );
+        assert_eq!(with_bom("{}", formatted.clone(), &options), "{\"a\":1}");
+
+        options.bom = Bom::Strip;
+        assert_eq!(with_bom(raw, formatted, &options), "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let tokens = lexer::lex("{\"b\": {\"d\": 1, \"c\": 2}, \"a\": 3}").unwrap();
+        let value = parser::parse(&tokens).unwrap();
+        let options = FormatOptions {
+            indent_length: 0,
+            sort_keys: true,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_json_with_options(&value, &options),
+            "{\"a\":3,\"b\":{\"c\":2,\"d\":1}}"
+        );
+    }
 }
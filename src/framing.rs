@@ -0,0 +1,105 @@
+// How documents are separated when several are given in one input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delimiter {
+    // One or more empty lines between documents
+    BlankLine,
+    // A NUL byte after each document, as written by `find -print0`
+    Nul,
+    // An RS character before each document and a newline after it, as in RFC 7464
+    Rs,
+}
+
+impl Delimiter {
+    pub fn from_name(name: &str) -> Option<Delimiter> {
+        match name {
+            "blank" => Some(Delimiter::BlankLine),
+            "nul" => Some(Delimiter::Nul),
+            "rs" => Some(Delimiter::Rs),
+            _ => None,
+        }
+    }
+
+    // The documents with their byte offsets, empty ones are skipped
+    pub fn split(self, raw: &str) -> Vec<(usize, &str)> {
+        let mut documents = vec![];
+        let mut start = 0;
+
+        let mut push = |start: usize, end: usize| {
+            if !raw[start..end].trim().is_empty() {
+                documents.push((start, &raw[start..end]));
+            }
+        };
+
+        match self {
+            Delimiter::BlankLine => {
+                let mut offset = 0;
+
+                for line in raw.split_inclusive('\n') {
+                    if line.trim().is_empty() {
+                        push(start, offset);
+                        start = offset + line.len();
+                    }
+
+                    offset += line.len();
+                }
+
+                push(start, raw.len());
+            }
+            Delimiter::Nul | Delimiter::Rs => {
+                let separator = if self == Delimiter::Nul { '\0' } else { '\x1e' };
+
+                for (offset, _) in raw.match_indices(separator) {
+                    push(start, offset);
+                    start = offset + 1;
+                }
+
+                push(start, raw.len());
+            }
+        }
+
+        documents
+    }
+
+    pub fn join(self, documents: &[String]) -> String {
+        match self {
+            Delimiter::BlankLine => documents.join("\n\n") + "\n",
+            Delimiter::Nul => documents.iter().map(|d| format!("{}\0", d)).collect(),
+            Delimiter::Rs => documents.iter().map(|d| format!("\x1e{}\n", d)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let cases = vec![
+            (
+                Delimiter::BlankLine,
+                "{\"a\": 1}\n\n  \n[1,\n2]\n",
+                vec![(0, "{\"a\": 1}\n"), (13, "[1,\n2]\n")],
+            ),
+            (Delimiter::Nul, "1\0{}\0", vec![(0, "1"), (2, "{}")]),
+            (
+                Delimiter::Rs,
+                "\x1e{\"a\": 1}\n\x1e[]\n",
+                vec![(1, "{\"a\": 1}\n"), (11, "[]\n")],
+            ),
+        ];
+
+        for (delimiter, raw, expected) in cases {
+            assert_eq!(delimiter.split(raw), expected, "{:?}", delimiter);
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let documents = ["1".to_string(), "[]".to_string()];
+
+        assert_eq!(Delimiter::BlankLine.join(&documents), "1\n\n[]\n");
+        assert_eq!(Delimiter::Nul.join(&documents), "1\0[]\0");
+        assert_eq!(Delimiter::Rs.join(&documents), "\x1e1\n\x1e[]\n");
+    }
+}
@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+
+// Expands `*`, `?`, `[abc]` and `**` in paths without relying on the shell, which matters on
+// Windows and for patterns read from files. Like in shells, wildcards do not match a leading `.`
+
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+fn separators(c: char) -> bool {
+    c == '/' || (cfg!(windows) && c == '\\')
+}
+
+// Whether a single path component matches a pattern component
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| matches(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let end = match pattern.iter().position(|c| *c == ']') {
+                Some(end) if end > 1 => end,
+                // An unclosed bracket is taken literally
+                _ => return name.first() == Some(&'[') && matches(&pattern[1..], &name[1..]),
+            };
+
+            let (negated, class) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..end]),
+                _ => (false, &pattern[1..end]),
+            };
+
+            let c = match name.first() {
+                Some(c) => *c,
+                None => return false,
+            };
+
+            let mut found = false;
+            let mut i = 0;
+
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+
+            found != negated && matches(&pattern[end + 1..], &name[1..])
+        }
+        Some(p) => name.first() == Some(p) && matches(&pattern[1..], &name[1..]),
+    }
+}
+
+fn matches_name(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+fn entries(dir: &Path) -> Vec<(String, PathBuf)> {
+    let read_dir = match std::fs::read_dir(if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return vec![],
+    };
+
+    read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = dir.join(&name);
+            (name, path)
+        })
+        .collect()
+}
+
+fn walk(dir: &Path, components: &[&str], found: &mut Vec<PathBuf>) {
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            if dir.exists() {
+                found.push(dir.to_path_buf());
+            }
+            return;
+        }
+    };
+
+    if *component == "**" {
+        // Zero or more directories
+        walk(dir, rest, found);
+
+        for (name, path) in entries(dir) {
+            if name.starts_with('.') {
+                continue;
+            }
+
+            if path.is_dir() {
+                walk(&path, components, found);
+            } else if rest.is_empty() {
+                found.push(path);
+            }
+        }
+    } else if is_pattern(component) {
+        for (name, path) in entries(dir) {
+            if matches_name(component, &name) && (rest.is_empty() || path.is_dir()) {
+                walk(&path, rest, found);
+            }
+        }
+    } else {
+        walk(&dir.join(component), rest, found);
+    }
+}
+
+// Files and directories matching the pattern, sorted
+pub fn expand(pattern: &str) -> Vec<PathBuf> {
+    let (root, relative) = match pattern.strip_prefix(separators) {
+        Some(relative) => (
+            PathBuf::from(&pattern[..pattern.len() - relative.len()]),
+            relative,
+        ),
+        None => (PathBuf::new(), pattern),
+    };
+
+    let components: Vec<&str> = relative
+        .split(separators)
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    let mut found = vec![];
+    walk(&root, &components, &mut found);
+
+    found.sort();
+    found.dedup();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_name() {
+        let cases = vec![
+            ("*.json", "a.json", true),
+            ("*.json", "a.json5", false),
+            ("*.json", ".hidden.json", false),
+            (".*.json", ".hidden.json", true),
+            ("a?c", "abc", true),
+            ("a?c", "ac", false),
+            ("[ab]*", "b.json", true),
+            ("[!ab]*", "b.json", false),
+            ("[a-c]x", "bx", true),
+            ("[x", "[x", true),
+        ];
+
+        for (pattern, name, expected) in cases {
+            assert_eq!(
+                matches_name(pattern, name),
+                expected,
+                "{} {}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let dir = std::env::temp_dir().join(format!("joxide-glob-{}", std::process::id()));
+        let files = ["a.json", "b.txt", "x/c.json", "x/y/d.json", ".git/e.json"];
+
+        for file in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "{}").unwrap();
+        }
+
+        let base = dir.to_string_lossy();
+        let expand = |pattern: &str| expand(&format!("{}/{}", base, pattern));
+
+        assert_eq!(
+            expand("**/*.json"),
+            [
+                dir.join("a.json"),
+                dir.join("x/c.json"),
+                dir.join("x/y/d.json")
+            ]
+        );
+        assert_eq!(expand("*/*.json"), [dir.join("x/c.json")]);
+        assert_eq!(
+            expand("x/**"),
+            [
+                dir.join("x"),
+                dir.join("x/c.json"),
+                dir.join("x/y"),
+                dir.join("x/y/d.json")
+            ]
+        );
+        assert_eq!(expand("*.yaml"), Vec::<PathBuf>::new());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
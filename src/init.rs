@@ -0,0 +1,264 @@
+use dupes;
+use joxide::diagnostic::MessageId;
+use joxide::encoding::Encoding;
+use joxide::lexer::{self, TokenType};
+use joxide::parser;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+// How many json files are read to infer the indent length
+const SAMPLE_FILES: usize = 50;
+
+pub const GITATTRIBUTES: &str = "*.json merge=joxide diff=joxide";
+
+pub const GIT_CONFIG: &str = "git config merge.joxide.name \"joxide structural JSON merge\"
+git config merge.joxide.driver \"joxide git-merge-driver %O %A %B\"
+git config diff.joxide.textconv \"joxide git-textconv\"
+";
+
+pub const PRE_COMMIT_HOOK: &str = "# .pre-commit-config.yaml
+repos:
+  - repo: local
+    hooks:
+      - id: joxide
+        name: joxide validate
+        entry: joxide validate .
+        language: system
+        files: \\.json$
+        pass_filenames: false
+";
+
+// How a file is indented, see `indent`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+// Indent of the first nested line, `None` for single line documents
+pub fn indent(raw: &str) -> Option<Indent> {
+    let lines: Vec<&str> = raw.lines().collect();
+
+    for pair in lines.windows(2) {
+        let opened = pair[0].trim_end();
+
+        if !(opened.ends_with('{') || opened.ends_with('[')) || pair[1].trim().is_empty() {
+            continue;
+        }
+
+        if pair[1].starts_with('\t') {
+            return Some(Indent::Tabs);
+        }
+
+        let outer = opened.len() - opened.trim_start_matches(' ').len();
+        let inner = pair[1].len() - pair[1].trim_start_matches(' ').len();
+
+        return match inner.checked_sub(outer) {
+            Some(0) | None => None,
+            Some(_) if pair[1][inner..].starts_with('\t') => Some(Indent::Tabs),
+            Some(n) => Some(Indent::Spaces(n)),
+        };
+    }
+
+    None
+}
+
+// What the json files already in a directory tree have in common
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Inferred {
+    // The most common indent length
+    pub indent_length: Option<usize>,
+    // Whether any of the files has comments or trailing commas
+    pub comments: bool,
+    pub trailing_commas: bool,
+}
+
+// Whether the file has comments and whether it has trailing commas, files that can't be lexed
+// have neither
+pub fn dialect(raw: &str) -> (bool, bool) {
+    match lexer::lex(raw) {
+        Ok(tokens) => (
+            tokens
+                .iter()
+                .any(|token| matches!(token.token_type, TokenType::Comment(_))),
+            !parser::find_trailing_commas(&tokens).is_empty(),
+        ),
+        Err(_) => (false, false),
+    }
+}
+
+// Reads the json files in the directory tree, decoded as `encoding` or as detected from each
+// file's bytes
+pub fn infer(dir: &Path, encoding: Option<Encoding>) -> std::io::Result<Inferred> {
+    let mut files = vec![];
+    dupes::json_files(dir, &mut files)?;
+    files.sort();
+
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    let mut inferred = Inferred::default();
+
+    for path in files.iter().take(SAMPLE_FILES) {
+        let bytes = std::fs::read(path)?;
+        let raw = match encoding
+            .unwrap_or_else(|| Encoding::detect(&bytes))
+            .decode(&bytes)
+        {
+            Ok(raw) => raw,
+            Err(_) => continue,
+        };
+
+        // Only space indents have a length
+        if let Some(Indent::Spaces(n)) = indent(&raw) {
+            *counts.entry(n).or_default() += 1;
+        }
+
+        let (comments, trailing_commas) = dialect(&raw);
+        inferred.comments |= comments;
+        inferred.trailing_commas |= trailing_commas;
+    }
+
+    inferred.indent_length = counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(n, _)| n);
+
+    Ok(inferred)
+}
+
+// Commented config file, with what was inferred from the existing files
+pub fn starter(inferred: &Inferred) -> String {
+    let mut result = String::from(
+        "# joxide configuration, see https://github.com/RainingComputers/joxide#configuration\n\n",
+    );
+
+    result += "[format]\n# Indent length of `joxide format` when --indent-length is not given\n";
+
+    match inferred.indent_length {
+        Some(n) => {
+            result += &format!(
+                "# Inferred from the existing json files\nindent-length = {}\n",
+                n
+            )
+        }
+        None => result += "# indent-length = 4\n",
+    }
+
+    result += "\n[parse]\n# Comments and trailing commas are errors unless the files are allowed to have them\n";
+
+    if inferred.comments || inferred.trailing_commas {
+        result += "# Inferred from the existing json files\n";
+    }
+
+    for (key, allowed) in [
+        ("allow-comments", inferred.comments),
+        ("allow-trailing-commas", inferred.trailing_commas),
+    ] {
+        match allowed {
+            true => result += &format!("{} = true\n", key),
+            false => result += &format!("# {} = true\n", key),
+        }
+    }
+
+    result += "\n[colors]\n# Colors of --color, a theme (default, high-contrast or light) and styles like\n";
+    result += "# \"bold blue\" or \"38;5;208\" for each of key, string, number and literal\n";
+    result += "# theme = \"default\"\n";
+
+    result += "\n[messages]\n";
+    result +=
+        "# Replace error messages per error code, templates can use the {token}, {expected},\n";
+    result +=
+        "# {path}, {line} and {col} placeholders, {char} for unknown-character and {byte} and\n";
+    result += "# {encoding} for invalid-utf8\n";
+
+    for id in MessageId::ALL {
+        result += &format!("# {} = \"\"\n", id.code());
+    }
+
+    result
+}
+
+// Adds the joxide attributes to .gitattributes in `dir`, `false` if they were already there
+pub fn add_gitattributes(dir: &Path) -> std::io::Result<bool> {
+    let path = dir.join(".gitattributes");
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    if existing.lines().any(|line| line.trim() == GITATTRIBUTES) {
+        return Ok(false);
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(file)?;
+    }
+
+    writeln!(file, "{}", GITATTRIBUTES)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "toml")]
+    use config::Config;
+
+    #[test]
+    fn test_indent() {
+        let cases = vec![
+            ("{\n  \"a\": 1\n}", Some(Indent::Spaces(2))),
+            (
+                "[\n\n    {\n        \"a\": 1\n    }\n]",
+                Some(Indent::Spaces(4)),
+            ),
+            ("{\"a\": [\n   1\n]}", Some(Indent::Spaces(3))),
+            ("{\n\t\"a\": 1\n}", Some(Indent::Tabs)),
+            ("{\"a\": 1}", None),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(indent(raw), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_dialect() {
+        assert_eq!(dialect("{\"a\": [1, 2]}"), (false, false));
+        assert_eq!(dialect("{\n  // a\n  \"a\": 1\n}"), (true, false));
+        assert_eq!(dialect("{\"a\": [1, 2, /* b */]}"), (true, true));
+        assert_eq!(dialect("{\"a\": \"x,}\",}"), (false, true));
+        assert_eq!(dialect("{\"a\": \"unterminated"), (false, false));
+    }
+
+    #[test]
+    fn test_starter() {
+        let config = starter(&Inferred {
+            indent_length: Some(2),
+            comments: true,
+            trailing_commas: false,
+        });
+
+        assert!(config.contains("\nindent-length = 2\n"));
+        assert!(config.contains("\nallow-comments = true\n"));
+        assert!(config.contains("\n# allow-trailing-commas = true\n"));
+        assert!(config.contains("\n# trailing-comma = \"\"\n"));
+
+        #[cfg(feature = "toml")]
+        {
+            let parsed = Config::parse(&config).unwrap();
+            assert_eq!(parsed.indent_length, Some(2));
+            assert!(parsed.allow_comments);
+            assert!(!parsed.allow_trailing_commas);
+
+            let parsed = Config::parse(&starter(&Inferred::default())).unwrap();
+            assert_eq!(parsed, Config::default());
+        }
+    }
+}
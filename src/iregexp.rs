@@ -0,0 +1,668 @@
+// The I-Regexp subset of regular expressions from RFC 9485, used by the `match` and `search`
+// functions of JSONPath filters, and the ECMA-262 regular expressions of JSON Schema `pattern`
+// without backreferences. Matching backtracks, patterns come from the query or the schema rather
+// than from the document
+
+#[derive(Debug, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Category(Category, bool),
+}
+
+// Unicode general categories, approximated with the properties the standard library knows
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Category {
+    Letter,
+    Uppercase,
+    Lowercase,
+    Number,
+    Separator,
+    Control,
+    // `\d`, `\w` and `\s` of ECMA-262
+    Digit,
+    Word,
+    Space,
+}
+
+impl Category {
+    fn from_name(name: &str) -> Option<Category> {
+        match name {
+            "L" => Some(Category::Letter),
+            "Lu" => Some(Category::Uppercase),
+            "Ll" => Some(Category::Lowercase),
+            "N" | "Nd" => Some(Category::Number),
+            "Z" | "Zs" => Some(Category::Separator),
+            "Cc" => Some(Category::Control),
+            _ => None,
+        }
+    }
+
+    fn contains(self, c: char) -> bool {
+        match self {
+            Category::Letter => c.is_alphabetic(),
+            Category::Uppercase => c.is_uppercase(),
+            Category::Lowercase => c.is_lowercase(),
+            Category::Number => c.is_numeric(),
+            Category::Separator => c.is_whitespace() && !c.is_control(),
+            Category::Control => c.is_control(),
+            Category::Digit => c.is_ascii_digit(),
+            Category::Word => c.is_ascii_alphanumeric() || c == '_',
+            Category::Space => c.is_whitespace() || c == '\u{feff}',
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Atom {
+    Char(char),
+    // `.` matches anything but line breaks
+    Any,
+    Class {
+        negated: bool,
+        items: Vec<ClassItem>,
+    },
+    Group(Vec<Vec<Piece>>),
+    // The assertions of ECMA-262: `^`, `$`, `\b` or `\B` and lookarounds
+    Start,
+    End,
+    Boundary(bool),
+    Look {
+        behind: bool,
+        negated: bool,
+        branches: Vec<Vec<Piece>>,
+    },
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(expected) => c == *expected,
+            Atom::Any => c != '\n' && c != '\r',
+            Atom::Class { negated, items } => {
+                let found = items.iter().any(|item| match *item {
+                    ClassItem::Range(low, high) => low <= c && c <= high,
+                    ClassItem::Category(category, negated) => category.contains(c) != negated,
+                });
+
+                found != *negated
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Piece {
+    atom: Atom,
+    min: usize,
+    max: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Regex {
+    branches: Vec<Vec<Piece>>,
+}
+
+struct Compiler<'p> {
+    chars: std::iter::Peekable<std::str::Chars<'p>>,
+    ecma: bool,
+}
+
+impl Compiler<'_> {
+    fn eat(&mut self, c: char) -> bool {
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn branches(&mut self) -> Option<Vec<Vec<Piece>>> {
+        let mut branches = vec![self.branch()?];
+
+        while self.eat('|') {
+            branches.push(self.branch()?);
+        }
+
+        Some(branches)
+    }
+
+    fn branch(&mut self) -> Option<Vec<Piece>> {
+        let mut pieces = vec![];
+
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            let atom = self.atom()?;
+            let (min, max) = self.quantifier()?;
+
+            if self.ecma {
+                let quantified = (min, max) != (1, Some(1));
+
+                // Only lookaheads can be repeated, lazy repetitions match the same texts
+                if quantified
+                    && matches!(
+                        atom,
+                        Atom::Start
+                            | Atom::End
+                            | Atom::Boundary(_)
+                            | Atom::Look { behind: true, .. }
+                    )
+                {
+                    return None;
+                }
+
+                if quantified {
+                    self.eat('?');
+                }
+            }
+
+            pieces.push(Piece { atom, min, max });
+        }
+
+        Some(pieces)
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let mut digits = String::new();
+
+        while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+            digits.push(c);
+        }
+
+        digits.parse().ok()
+    }
+
+    fn quantifier(&mut self) -> Option<(usize, Option<usize>)> {
+        if self.eat('*') {
+            return Some((0, None));
+        }
+
+        if self.eat('+') {
+            return Some((1, None));
+        }
+
+        if self.eat('?') {
+            return Some((0, Some(1)));
+        }
+
+        // In ECMA-262 a `{` that does not start a quantifier is a character
+        if self.chars.peek() != Some(&'{') || (self.ecma && !self.braced_quantifier()) {
+            return Some((1, Some(1)));
+        }
+
+        self.chars.next();
+
+        let min = self.number()?;
+        let max = if !self.eat(',') {
+            Some(min)
+        } else if self.chars.peek() == Some(&'}') {
+            None
+        } else {
+            Some(self.number()?)
+        };
+
+        if !self.eat('}') || max.is_some_and(|max| max < min) {
+            return None;
+        }
+
+        Some((min, max))
+    }
+
+    // Whether a `{` is followed by the rest of a quantifier like `{2}`, `{2,}` or `{2,3}`
+    fn braced_quantifier(&self) -> bool {
+        let mut ahead = self.chars.clone();
+        ahead.next();
+
+        let digits = |ahead: &mut std::iter::Peekable<std::str::Chars>| {
+            let mut found = false;
+            while ahead.next_if(char::is_ascii_digit).is_some() {
+                found = true;
+            }
+            found
+        };
+
+        if !digits(&mut ahead) {
+            return false;
+        }
+
+        if ahead.next_if_eq(&',').is_some() {
+            digits(&mut ahead);
+        }
+
+        ahead.next() == Some('}')
+    }
+
+    fn hex(&mut self, digits: usize) -> Option<u32> {
+        let mut value = 0;
+
+        for _ in 0..digits {
+            value = value * 16 + self.chars.next()?.to_digit(16)?;
+        }
+
+        Some(value)
+    }
+
+    // `\u` escapes, `\u{..}` and the surrogate pairs of two `\uXXXX`
+    fn unicode(&mut self) -> Option<char> {
+        if self.eat('{') {
+            let mut value: u32 = 0;
+
+            while let Some(c) = self.chars.next_if(|c| *c != '}') {
+                value = value.checked_mul(16)?.checked_add(c.to_digit(16)?)?;
+            }
+
+            self.eat('}').then_some(())?;
+            return char::from_u32(value);
+        }
+
+        let high = self.hex(4)?;
+
+        if (0xd800..0xdc00).contains(&high) {
+            let mut ahead = self.chars.clone();
+
+            if ahead.next() == Some('\\') && ahead.next() == Some('u') {
+                let low = (0..4)
+                    .map(|_| ahead.next().and_then(|c| c.to_digit(16)))
+                    .try_fold(0, |value, digit| Some(value * 16 + digit?));
+
+                if let Some(low @ 0xdc00..=0xdfff) = low {
+                    self.chars = ahead;
+                    return char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00));
+                }
+            }
+        }
+
+        // A lone surrogate can never match a character of a string
+        Some(char::from_u32(high).unwrap_or('\u{fffd}'))
+    }
+
+    // The escapes of ECMA-262 on top of those of I-Regexp. Any other character than a letter or
+    // digit escapes itself, backreferences are not supported
+    fn ecma_escape(&mut self, c: char, in_class: bool) -> Option<ClassItem> {
+        let category = |category| Some(ClassItem::Category(category, c.is_ascii_uppercase()));
+
+        let c = match c {
+            'd' | 'D' => return category(Category::Digit),
+            'w' | 'W' => return category(Category::Word),
+            's' | 'S' => return category(Category::Space),
+            'b' if in_class => '\u{8}',
+            'v' => '\u{b}',
+            'f' => '\u{c}',
+            '0' if !self.chars.peek().is_some_and(char::is_ascii_digit) => '\0',
+            'c' => match self.chars.next_if(char::is_ascii_alphabetic) {
+                Some(letter) => char::from(letter as u8 % 32),
+                None => return None,
+            },
+            'x' => char::from_u32(self.hex(2)?)?,
+            'u' => self.unicode()?,
+            c if c.is_ascii_alphanumeric() => return None,
+            c => c,
+        };
+
+        Some(ClassItem::Range(c, c))
+    }
+
+    // The character after a `\`, or the category of `\p{..}` and `\P{..}`
+    fn escape(&mut self, in_class: bool) -> Option<ClassItem> {
+        let c = self.chars.next()?;
+
+        if self.ecma && !matches!(c, 'n' | 'r' | 't' | 'p' | 'P') {
+            return self.ecma_escape(c, in_class);
+        }
+
+        let c = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'p' | 'P' => {
+                if !self.eat('{') {
+                    return None;
+                }
+
+                let mut name = String::new();
+
+                while let Some(c) = self.chars.next_if(|c| *c != '}') {
+                    name.push(c);
+                }
+
+                self.eat('}').then_some(())?;
+                return Some(ClassItem::Category(Category::from_name(&name)?, c == 'P'));
+            }
+            '(' | ')' | '*' | '+' | '.' | '?' | '[' | '\\' | ']' | '^' | '{' | '|' | '}' | '-' => c,
+            _ => return None,
+        };
+
+        Some(ClassItem::Range(c, c))
+    }
+
+    // `(?:..)`, lookarounds and named groups of ECMA-262, after the `(`
+    fn group(&mut self) -> Option<Atom> {
+        let look = if !self.ecma || !self.eat('?') || self.eat(':') {
+            None
+        } else if self.eat('=') {
+            Some((false, false))
+        } else if self.eat('!') {
+            Some((false, true))
+        } else if self.eat('<') {
+            if self.eat('=') {
+                Some((true, false))
+            } else if self.eat('!') {
+                Some((true, true))
+            } else {
+                let mut name = String::new();
+
+                while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+
+                (!name.is_empty() && self.eat('>')).then_some(())?;
+                None
+            }
+        } else {
+            return None;
+        };
+
+        let branches = self.branches()?;
+        self.eat(')').then_some(())?;
+
+        Some(match look {
+            Some((behind, negated)) => Atom::Look {
+                behind,
+                negated,
+                branches,
+            },
+            None => Atom::Group(branches),
+        })
+    }
+
+    fn atom(&mut self) -> Option<Atom> {
+        if self.ecma {
+            let next = *self.chars.peek()?;
+
+            match next {
+                '.' => {
+                    self.chars.next();
+                    let items = ['\n', '\r', '\u{2028}', '\u{2029}']
+                        .map(|c| ClassItem::Range(c, c))
+                        .into();
+                    return Some(Atom::Class {
+                        negated: true,
+                        items,
+                    });
+                }
+                '^' => {
+                    self.chars.next();
+                    return Some(Atom::Start);
+                }
+                '$' => {
+                    self.chars.next();
+                    return Some(Atom::End);
+                }
+                '{' if !self.braced_quantifier() => {
+                    self.chars.next();
+                    return Some(Atom::Char('{'));
+                }
+                '}' | ']' => return self.chars.next().map(Atom::Char),
+                '\\' => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+
+                    if let Some(c @ ('b' | 'B')) = ahead.next() {
+                        self.chars = ahead;
+                        return Some(Atom::Boundary(c == 'B'));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match self.chars.next()? {
+            '.' => Some(Atom::Any),
+            '(' => self.group(),
+            '[' => self.class(),
+            '\\' => match self.escape(false)? {
+                ClassItem::Range(c, _) => Some(Atom::Char(c)),
+                category => Some(Atom::Class {
+                    negated: false,
+                    items: vec![category],
+                }),
+            },
+            '*' | '+' | '?' | '{' | '}' | ']' | ')' => None,
+            c => Some(Atom::Char(c)),
+        }
+    }
+
+    fn class_char(&mut self) -> Option<ClassItem> {
+        match self.chars.next()? {
+            '\\' => self.escape(true),
+            '[' if self.ecma => Some(ClassItem::Range('[', '[')),
+            '[' | ']' => None,
+            c => Some(ClassItem::Range(c, c)),
+        }
+    }
+
+    fn class(&mut self) -> Option<Atom> {
+        let negated = self.eat('^');
+        let mut items = vec![];
+
+        while !self.eat(']') {
+            let item = self.class_char()?;
+
+            // A `-` right before the closing bracket is a character of its own
+            let mut ahead = self.chars.clone();
+            let range = ahead.next() == Some('-') && ahead.next().is_some_and(|c| c != ']');
+
+            let item = match item {
+                ClassItem::Range(low, _) if range => {
+                    self.chars.next();
+
+                    match self.class_char()? {
+                        ClassItem::Range(high, _) if low <= high => ClassItem::Range(low, high),
+                        _ => return None,
+                    }
+                }
+                item => item,
+            };
+
+            items.push(item);
+        }
+
+        Some(Atom::Class { negated, items })
+    }
+}
+
+impl Regex {
+    // `None` when the pattern is not a valid I-Regexp
+    pub fn new(pattern: &str) -> Option<Regex> {
+        Regex::compile(pattern, false)
+    }
+
+    // `None` when the pattern is not a valid ECMA-262 regular expression or uses backreferences
+    pub fn ecma(pattern: &str) -> Option<Regex> {
+        Regex::compile(pattern, true)
+    }
+
+    fn compile(pattern: &str, ecma: bool) -> Option<Regex> {
+        let mut compiler = Compiler {
+            chars: pattern.chars().peekable(),
+            ecma,
+        };
+        let branches = compiler.branches()?;
+
+        compiler
+            .chars
+            .peek()
+            .is_none()
+            .then_some(Regex { branches })
+    }
+
+    // Whether the whole text matches, like the `match` function
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        alternatives(&self.branches, &chars, 0, &mut |end| end == chars.len())
+    }
+
+    // Whether some part of the text matches, like the `search` function
+    pub fn is_found(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        (0..=chars.len()).any(|start| alternatives(&self.branches, &chars, start, &mut |_| true))
+    }
+}
+
+// Each matcher calls `next` with every position a match can end at until it returns true
+fn alternatives(
+    branches: &[Vec<Piece>],
+    chars: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    branches
+        .iter()
+        .any(|branch| sequence(branch, chars, pos, next))
+}
+
+fn sequence(
+    pieces: &[Piece],
+    chars: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match pieces.split_first() {
+        Some((piece, rest)) => repeat(piece, 0, chars, pos, &mut |end| {
+            sequence(rest, chars, end, next)
+        }),
+        None => next(pos),
+    }
+}
+
+// Greedy, one more repetition is tried before stopping
+fn repeat(
+    piece: &Piece,
+    count: usize,
+    chars: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    let more = piece.max.is_none_or(|max| count < max)
+        && atom(&piece.atom, chars, pos, &mut |end| {
+            // Repeating an empty match would never end
+            (end != pos || count < piece.min) && repeat(piece, count + 1, chars, end, next)
+        });
+
+    more || (count >= piece.min && next(pos))
+}
+
+fn is_word(chars: &[char], pos: usize) -> bool {
+    chars.get(pos).is_some_and(|c| Category::Word.contains(*c))
+}
+
+fn atom(atom: &Atom, chars: &[char], pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+    match atom {
+        Atom::Group(branches) => alternatives(branches, chars, pos, next),
+        Atom::Start => pos == 0 && next(pos),
+        Atom::End => pos == chars.len() && next(pos),
+        Atom::Boundary(negated) => {
+            let boundary = pos
+                .checked_sub(1)
+                .is_some_and(|before| is_word(chars, before))
+                != is_word(chars, pos);
+            boundary != *negated && next(pos)
+        }
+        Atom::Look {
+            behind: false,
+            negated,
+            branches,
+        } => alternatives(branches, chars, pos, &mut |_| true) != *negated && next(pos),
+        Atom::Look {
+            behind: true,
+            negated,
+            branches,
+        } => {
+            let found =
+                (0..=pos).any(|start| alternatives(branches, chars, start, &mut |end| end == pos));
+            found != *negated && next(pos)
+        }
+        _ => chars.get(pos).is_some_and(|c| atom.matches(*c)) && next(pos + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_match() {
+        let cases = [
+            ("a.c", "abc", true),
+            ("a.c", "abcd", false),
+            ("(ab|cd)+e?", "abcdab", true),
+            ("[a-c]{2,3}", "cab", true),
+            ("[a-c]{2,3}", "cabc", false),
+            ("[^0-9]*", "abc", true),
+            ("[^0-9]*", "a1", false),
+            ("\\p{Lu}\\p{Ll}*", "Émile", true),
+            ("a\\.b", "a.b", true),
+            ("a\\.b", "axb", false),
+            ("(a*)*b", "aaab", true),
+            ("[a-]", "-", true),
+            ("x{2}", "xx", true),
+        ];
+
+        for (pattern, text, expected) in cases {
+            let regex = Regex::new(pattern).unwrap();
+            assert_eq!(regex.is_match(text), expected, "{} {}", pattern, text);
+        }
+    }
+
+    #[test]
+    fn test_is_found() {
+        let regex = Regex::new("b+").unwrap();
+        assert!(regex.is_found("abbc"));
+        assert!(!regex.is_found("ac"));
+        assert!(Regex::new("").unwrap().is_found("x"));
+    }
+
+    #[test]
+    fn test_ecma() {
+        let cases = [
+            ("^a+$", "aaa", true),
+            ("^a+$", "aab", false),
+            ("\\d{3}-\\w+", "call 555-line", true),
+            ("^(?:ab)+$", "abab", true),
+            ("^[^]$", "\n", true),
+            ("^.$", "\n", false),
+            ("\\bcat\\b", "a cat!", true),
+            ("\\bcat\\b", "concat", false),
+            ("^(?=.*\\d)[a-z\\d]+$", "abc1", true),
+            ("^(?=.*\\d)[a-z\\d]+$", "abc", false),
+            ("^(?!x).*", "xy", false),
+            ("(?<=\\$)\\d+", "$42", true),
+            ("(?<!\\$)\\b\\d+", "$42", false),
+            ("^a{2,}?$", "aaa", true),
+            ("^x{$", "x{", true),
+            ("^\\u00e9\\x41\\/$", "éA/", true),
+            ("^\\uD83D\\uDE00$", "😀", true),
+            ("^(?<year>\\d{4})-[\\s\\S]$", "2024- ", true),
+            ("[\\d-]", "-", true),
+        ];
+
+        for (pattern, text, expected) in cases {
+            let regex = Regex::ecma(pattern).unwrap();
+            assert_eq!(regex.is_found(text), expected, "{} {}", pattern, text);
+        }
+
+        for pattern in [
+            "(a", "a)", "*a", "[a", "a{2,1}", "^*", "\\1", "(?<=a)+", "a**", "(?x)",
+        ] {
+            assert_eq!(Regex::ecma(pattern), None, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for pattern in [
+            "(a", "a)", "*a", "[a", "a{2,1}", "\\d", "\\p{Xx}", "[z-a]", "a{",
+        ] {
+            assert_eq!(Regex::new(pattern), None, "{}", pattern);
+        }
+    }
+}
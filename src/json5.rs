@@ -0,0 +1,587 @@
+use escape::unescape_unicode;
+use lexer::{Token, TokenType};
+use number::Number;
+use parser::{Json, Map, OwnedParseError, ParseError, ParseErrorType, DEFAULT_MAX_DEPTH};
+use std::borrow::Cow;
+
+// Parses JSON5 (https://spec.json5.org): comments, keys that are identifiers, single quoted
+// strings with line continuations and the escapes of JavaScript, hexadecimal numbers, numbers with
+// a leading `+` or a leading or trailing decimal point, `Infinity`, `NaN` and trailing commas.
+// `Infinity` and `NaN` are kept as `Number::Raw`, so formatting writes them back as they are. Of
+// repeated keys the last one wins, like in JavaScript
+pub fn parse_json5(raw: &str) -> Result<Json<'_>, OwnedParseError> {
+    Json5Parser {
+        s: raw,
+        pos: 0,
+        line: 0,
+        col: 0,
+    }
+    .parse()
+}
+
+enum Frame<'s> {
+    Array(Vec<Json<'s>>),
+    Object(Map<Cow<'s, str>, Json<'s>>, Cow<'s, str>),
+}
+
+impl<'s> Frame<'s> {
+    fn add(&mut self, value: Json<'s>) {
+        match self {
+            Frame::Array(array) => array.push(value),
+            Frame::Object(object, key) => {
+                object.insert(key.clone(), value);
+            }
+        }
+    }
+
+    fn close(&self) -> char {
+        match self {
+            Frame::Array(_) => ']',
+            Frame::Object(..) => '}',
+        }
+    }
+
+    fn into_json(self) -> Json<'s> {
+        match self {
+            Frame::Array(array) => Json::Array(array),
+            Frame::Object(object, _) => Json::Object(object),
+        }
+    }
+}
+
+// Where a token starts, as a byte offset and the zero based line and column the lexer would give
+#[derive(Clone, Copy)]
+struct Mark {
+    pos: usize,
+    line: usize,
+    col: usize,
+}
+
+struct Json5Parser<'s> {
+    s: &'s str,
+    pos: usize,
+    line: usize,
+    col: usize,
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "{}[]:,\"'/\u{feff}".contains(c)
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+// Integer part without leading zeros, an optional fraction and exponent, with digits on at least
+// one side of the decimal point
+fn is_decimal(text: &str) -> bool {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+
+    let (int, fraction) = match mantissa.find('.') {
+        Some(index) => (&mantissa[..index], &mantissa[index + 1..]),
+        None => (mantissa, ""),
+    };
+
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    let exponent_ok = exponent.is_none_or(|exponent| {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        !exponent.is_empty() && digits(exponent)
+    });
+
+    digits(int)
+        && digits(fraction)
+        && !(int.is_empty() && fraction.is_empty())
+        && !(int.len() > 1 && int.starts_with('0'))
+        && exponent_ok
+}
+
+impl<'s> Json5Parser<'s> {
+    fn mark(&self) -> Mark {
+        Mark {
+            pos: self.pos,
+            line: self.line,
+            col: self.col,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.col = 0;
+        } else {
+            self.col += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error(
+        &self,
+        error_type: ParseErrorType,
+        start: Mark,
+        expected: Option<&TokenType>,
+    ) -> OwnedParseError {
+        let text = self.s[start.pos..]
+            .split(is_delimiter)
+            .next()
+            .unwrap_or_default();
+        let text = if text.is_empty() {
+            self.s[start.pos..]
+                .chars()
+                .next()
+                .map_or("", |c| &self.s[start.pos..start.pos + c.len_utf8()])
+        } else {
+            text
+        };
+
+        let token = Token {
+            token_type: TokenType::Invalid(text),
+            line: start.line,
+            col: start.col,
+            start: start.pos,
+            end: start.pos + text.len(),
+            end_line: start.line,
+            end_col: start.col + text.chars().count(),
+        };
+
+        ParseError::new(error_type, Some(&token), expected).into()
+    }
+
+    fn unexpected_end(&self) -> OwnedParseError {
+        ParseError::new(ParseErrorType::UnexpectedEnd, None, None).into()
+    }
+
+    fn skip_trivia(&mut self) -> Result<(), OwnedParseError> {
+        loop {
+            let rest = &self.s[self.pos..];
+
+            if rest.starts_with("//") {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.bump();
+                }
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let start = self.mark();
+
+                match comment.find("*/") {
+                    Some(end) => {
+                        while self.pos < start.pos + end + 4 {
+                            self.bump();
+                        }
+                    }
+                    None => return Err(self.error(ParseErrorType::UnexpectedToken, start, None)),
+                }
+            } else if self
+                .peek()
+                .is_some_and(|c| c.is_whitespace() || c == '\u{feff}')
+            {
+                self.bump();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // A run of characters up to the next delimiter, for literals, numbers and identifiers
+    fn word(&mut self) -> &'s str {
+        let start = self.pos;
+
+        while self.peek().is_some_and(|c| !is_delimiter(c)) {
+            self.bump();
+        }
+
+        &self.s[start..self.pos]
+    }
+
+    // Reads a quoted string and returns its decoded content, borrowed when it has no escapes
+    fn string(&mut self) -> Result<Cow<'s, str>, OwnedParseError> {
+        let start = self.mark();
+        let quote = self.bump().unwrap();
+        let content_start = self.pos;
+        let mut decoded: Option<String> = None;
+
+        loop {
+            let before = self.pos;
+
+            let c = match self.bump() {
+                Some('\n' | '\r') | None => {
+                    return Err(self.error(ParseErrorType::UnexpectedToken, start, None))
+                }
+                Some(c) => c,
+            };
+
+            if c == quote {
+                return Ok(match decoded {
+                    Some(text) => Cow::Owned(text),
+                    None => Cow::Borrowed(&self.s[content_start..before]),
+                });
+            }
+
+            if c != '\\' {
+                if let Some(text) = decoded.as_mut() {
+                    text.push(c);
+                }
+                continue;
+            }
+
+            let text = decoded.get_or_insert_with(|| self.s[content_start..before].to_string());
+            let escape_start = self.mark();
+
+            match self.bump() {
+                None => return Err(self.unexpected_end()),
+                // Line continuations
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                Some('\r') => {
+                    if self.peek() == Some('\n') {
+                        self.bump();
+                    }
+                }
+                Some('b') => text.push('\u{8}'),
+                Some('f') => text.push('\u{c}'),
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('v') => text.push('\u{b}'),
+                Some('0') if !self.peek().is_some_and(|c| c.is_ascii_digit()) => text.push('\0'),
+                Some('x') => {
+                    let digits = self.s.get(self.pos..self.pos + 2).unwrap_or_default();
+
+                    match u8::from_str_radix(digits, 16) {
+                        Ok(byte) if digits.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                            self.bump();
+                            self.bump();
+                            text.push(char::from(byte));
+                        }
+                        _ => {
+                            return Err(self.error(
+                                ParseErrorType::UnexpectedToken,
+                                escape_start,
+                                None,
+                            ))
+                        }
+                    }
+                }
+                Some('u') => {
+                    // A surrogate pair takes both escapes, a lone surrogate is no character
+                    let (c, length) = match unescape_unicode(&self.s[before..]) {
+                        Some(decoded) => decoded,
+                        None => {
+                            return Err(self.error(
+                                ParseErrorType::UnexpectedToken,
+                                escape_start,
+                                None,
+                            ))
+                        }
+                    };
+                    text.push(c);
+
+                    for _ in 2..length {
+                        self.bump();
+                    }
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    return Err(self.error(ParseErrorType::UnexpectedToken, escape_start, None))
+                }
+                // Any other character stands for itself, like `\'` and `\"`
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    fn number(&self, word: &'s str) -> Option<Number<'s>> {
+        let (negative, unsigned) = match word.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, word.strip_prefix('+').unwrap_or(word)),
+        };
+
+        if unsigned == "Infinity" || unsigned == "NaN" {
+            let text = if negative { word } else { unsigned };
+            return Some(Number::Raw(Cow::Borrowed(text)));
+        }
+
+        if let Some(hex) = unsigned
+            .strip_prefix("0x")
+            .or_else(|| unsigned.strip_prefix("0X"))
+        {
+            let n = u64::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| !hex.starts_with('+'))?;
+
+            return match negative {
+                false => Some(Number::from(n)),
+                true if n == 0 => Some(Number::Float(-0.0)),
+                true => 0i64.checked_sub_unsigned(n).map(Number::from),
+            };
+        }
+
+        if !is_decimal(unsigned) {
+            return None;
+        }
+
+        let text = if negative { word } else { unsigned };
+        let text = match (
+            text.starts_with('-'),
+            text.trim_start_matches('-').starts_with('.'),
+        ) {
+            (_, false) => Cow::Borrowed(text),
+            (true, true) => Cow::Owned(format!("-0{}", &text[1..])),
+            (false, true) => Cow::Owned(format!("0{}", text)),
+        };
+
+        // A trailing dot like `5.` or `5.e3` gets the zero JSON needs after it
+        let text = match text.find('.') {
+            Some(dot) if !text[dot + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                Cow::Owned(format!("{}0{}", &text[..=dot], &text[dot + 1..]))
+            }
+            _ => text,
+        };
+
+        Number::parse(&text)
+    }
+
+    fn value(&mut self, start: Mark) -> Result<Option<Json<'s>>, OwnedParseError> {
+        match self.peek() {
+            None => Err(self.unexpected_end()),
+            Some('"' | '\'') => Ok(Some(Json::String(self.string()?))),
+            Some('{' | '[') => Ok(None),
+            Some(_) => {
+                let word = self.word();
+
+                let value = match word {
+                    "null" => Json::Null,
+                    "true" => Json::Bool(true),
+                    "false" => Json::Bool(false),
+                    _ => match self.number(word) {
+                        Some(number) => Json::Number(number),
+                        None => {
+                            return Err(self.error(ParseErrorType::UnexpectedToken, start, None))
+                        }
+                    },
+                };
+
+                Ok(Some(value))
+            }
+        }
+    }
+
+    // Reads a member's key and the colon after it
+    fn key(&mut self) -> Result<Cow<'s, str>, OwnedParseError> {
+        let start = self.mark();
+
+        let key = match self.peek() {
+            None => return Err(self.unexpected_end()),
+            Some('"' | '\'') => self.string()?,
+            Some(c) if is_identifier_start(c) => {
+                let word = self.word();
+
+                if !word
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '$' || c == '_')
+                {
+                    return Err(self.error(ParseErrorType::KeyNotInQuotes, start, None));
+                }
+
+                Cow::Borrowed(word)
+            }
+            Some(_) => return Err(self.error(ParseErrorType::KeyNotInQuotes, start, None)),
+        };
+
+        self.skip_trivia()?;
+        let colon = self.mark();
+
+        match self.bump() {
+            Some(':') => Ok(key),
+            None => Err(self.unexpected_end()),
+            Some(_) => {
+                Err(self.error(ParseErrorType::MissingColon, colon, Some(&TokenType::Colon)))
+            }
+        }
+    }
+
+    // Opens an array or object, returning it when it is empty
+    fn open(
+        &mut self,
+        stack: &mut Vec<Frame<'s>>,
+        start: Mark,
+    ) -> Result<Option<Json<'s>>, OwnedParseError> {
+        if stack.len() >= DEFAULT_MAX_DEPTH {
+            return Err(self.error(ParseErrorType::DepthLimitExceeded, start, None));
+        }
+
+        let open = self.bump().unwrap();
+        self.skip_trivia()?;
+
+        let frame = if open == '[' {
+            Frame::Array(vec![])
+        } else if self.peek() == Some('}') {
+            Frame::Object(Map::new(), Cow::Borrowed(""))
+        } else {
+            // After a key a value is needed, so the object can not close until it is added
+            stack.push(Frame::Object(Map::new(), self.key()?));
+            return Ok(None);
+        };
+
+        if self.peek() == Some(frame.close()) {
+            self.bump();
+            return Ok(Some(frame.into_json()));
+        }
+
+        stack.push(frame);
+        Ok(None)
+    }
+
+    fn parse(&mut self) -> Result<Json<'s>, OwnedParseError> {
+        let mut stack: Vec<Frame<'s>> = vec![];
+
+        loop {
+            self.skip_trivia()?;
+            let start = self.mark();
+
+            let mut value = match self.value(start)? {
+                Some(value) => value,
+                None => match self.open(&mut stack, start)? {
+                    Some(value) => value,
+                    None => continue,
+                },
+            };
+
+            // Adds the value to the array or object it is in, and closes those that end after it
+            loop {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => {
+                        self.skip_trivia()?;
+
+                        return match self.peek() {
+                            None => Ok(value),
+                            Some(_) => {
+                                Err(self.error(ParseErrorType::UnexpectedToken, self.mark(), None))
+                            }
+                        };
+                    }
+                };
+
+                frame.add(value);
+                let close = frame.close();
+                self.skip_trivia()?;
+                let next = self.mark();
+
+                match self.bump() {
+                    None => return Err(self.unexpected_end()),
+                    Some(',') => {
+                        self.skip_trivia()?;
+
+                        if self.peek() != Some(close) {
+                            if let Some(Frame::Object(_, key)) = stack.last_mut() {
+                                *key = self.key()?;
+                            }
+
+                            break;
+                        }
+
+                        self.bump();
+                    }
+                    Some(c) if c == close => {}
+                    Some(_) => {
+                        let (error_type, expected) = match close {
+                            ']' => (ParseErrorType::MissingCloseSquare, &TokenType::CloseSquare),
+                            _ => (ParseErrorType::MissingCloseCurly, &TokenType::CloseCurly),
+                        };
+
+                        return Err(self.error(error_type, next, Some(expected)));
+                    }
+                }
+
+                value = stack.pop().unwrap().into_json();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    #[test]
+    fn test_parse_json5() {
+        let raw = "// config
+{
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use \"double quotes\" here',
+  lineBreaks: \"Look, Mom! \\
+No \\\\n's!\",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  negativeHex: -0x10,
+  escapes: '\\x41\\v\\0\\'',
+  /* block */ trailingComma: 'in objects', andIn: ['arrays',],
+  \"backwardsCompatible\": \"with JSON\",
+}";
+        let value = parse_json5(raw).unwrap();
+
+        let expected = parser::parse_str(
+            r#"{
+                "unquoted": "and you can quote me on that",
+                "singleQuotes": "I can use \"double quotes\" here",
+                "lineBreaks": "Look, Mom! No \\n's!",
+                "hexadecimal": 912559,
+                "leadingDecimalPoint": 0.8675309,
+                "andTrailing": 8675309.0,
+                "positiveSign": 1,
+                "negativeHex": -16,
+                "escapes": "A\u000b\u0000'",
+                "trailingComma": "in objects",
+                "andIn": ["arrays"],
+                "backwardsCompatible": "with JSON"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(value, expected);
+        assert!(matches!(value["unquoted"], Json::String(Cow::Borrowed(_))));
+        assert_eq!(value["escapes"].as_str(), Some("A\u{b}\0'"));
+
+        let special = parse_json5("[Infinity, -Infinity, NaN, {a: {}}, []]").unwrap();
+        assert_eq!(
+            special.to_string(),
+            "[Infinity,-Infinity,NaN,{\"a\":{}},[]]"
+        );
+        assert_eq!(special[1].as_f64(), Some(f64::NEG_INFINITY));
+        assert_eq!(parse_json5("{a: 1, a: 2}").unwrap()["a"], Json::from(2));
+    }
+
+    #[test]
+    fn test_parse_json5_errors() {
+        let cases = [
+            ("[1 2]", ParseErrorType::MissingCloseSquare, Some((0, 3))),
+            ("{a 1}", ParseErrorType::MissingColon, Some((0, 3))),
+            ("{1: 2}", ParseErrorType::KeyNotInQuotes, Some((0, 1))),
+            ("[01]", ParseErrorType::UnexpectedToken, Some((0, 1))),
+            ("[1,,]", ParseErrorType::UnexpectedToken, Some((0, 3))),
+            ("'abc\n'", ParseErrorType::UnexpectedToken, Some((0, 0))),
+            ("'\\1'", ParseErrorType::UnexpectedToken, Some((0, 2))),
+            ("['\\ud800']", ParseErrorType::UnexpectedToken, Some((0, 3))),
+            ("[1] x", ParseErrorType::UnexpectedToken, Some((0, 4))),
+            ("{a: [1", ParseErrorType::UnexpectedEnd, None),
+            ("{a:}", ParseErrorType::UnexpectedToken, Some((0, 3))),
+            ("{a: 1, b:}", ParseErrorType::UnexpectedToken, Some((0, 9))),
+        ];
+
+        for &(raw, ref error_type, position) in &cases {
+            let error = parse_json5(raw).unwrap_err();
+            assert_eq!(&error.error_type, error_type, "{}", raw);
+            assert_eq!(error.line.zip(error.col), position, "{}", raw);
+        }
+    }
+}
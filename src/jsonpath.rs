@@ -0,0 +1,1286 @@
+use compare::{semantic_eq, EqOptions};
+use iregexp::Regex;
+use number::Number;
+use parser::Json;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+// JSONPath queries as specified by RFC 9535, like `$.store.book[?@.price < 10].title`,
+// evaluated over a parsed document. Each match comes with its normalized path, like
+// `$['store']['book'][0]['title']`
+
+// Where the expression stopped making sense, `position` counts characters from zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at character {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for PathError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    // Unescaped
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Logical),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    // `..` visits the node and all of its descendants
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+// Starts at the root with `$`, or at the node being filtered with `@`
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    // Selects at most one node, which is what comparisons need
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors.as_slice(),
+                    [Selector::Name(_)] | [Selector::Index(_)]
+                )
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionName {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Argument {
+    Value(Comparable),
+    Nodes(Query),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Function {
+    name: FunctionName,
+    arguments: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Comparable {
+    Literal(Json<'static>),
+    Query(Query),
+    Function(Function),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Logical {
+    Or(Vec<Logical>),
+    And(Vec<Logical>),
+    Not(Box<Logical>),
+    // True when the query selects at least one node
+    Exists(Query),
+    // `match` or `search`
+    Test(Function),
+    Compare(Comparable, Op, Comparable),
+}
+
+// A value matched by a query and its normalized path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<'j, 'a> {
+    pub path: String,
+    pub value: &'j Json<'a>,
+}
+
+// A parsed query that can be run over any number of documents
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    query: Query,
+}
+
+// Integers in queries are limited to the ones a double holds exactly, as in I-JSON
+const MAX_INT: i64 = (1 << 53) - 1;
+
+// Filters, parentheses and function calls nest this deep at most, so parsing and
+// evaluating stay well within the stack
+const MAX_DEPTH: usize = 64;
+
+struct ExpressionParser<'e> {
+    source: &'e str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'e> ExpressionParser<'e> {
+    fn rest(&self) -> &'e str {
+        &self.source[self.pos..]
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, PathError> {
+        Err(PathError {
+            position: self.source[..self.pos].chars().count(),
+            message: message.to_string(),
+        })
+    }
+
+    // Enters one more level, the caller sets `depth` back when it is done with it
+    fn nest(&mut self) -> Result<(), PathError> {
+        if self.depth >= MAX_DEPTH {
+            return self.error("The query is nested too deeply");
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn unexpected<T>(&self) -> Result<T, PathError> {
+        match self.rest().chars().next() {
+            Some(c) => self.error(&format!("Unexpected '{}'", c)),
+            None => self.error("Unexpected end of the query"),
+        }
+    }
+
+    // Blanks are spaces, tabs and line breaks
+    fn skip_blank(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), PathError> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'e str {
+        let rest = self.rest();
+        let end = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn is_name_first(c: char) -> bool {
+        c.is_ascii_alphabetic() || c == '_' || c >= '\u{80}'
+    }
+
+    fn query(&mut self) -> Result<Query, PathError> {
+        let relative = if self.eat("@") {
+            true
+        } else {
+            self.expect("$")?;
+            false
+        };
+
+        let mut segments = vec![];
+
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+
+            let segment = if self.eat("..") {
+                let selectors = if self.eat("*") {
+                    vec![Selector::Wildcard]
+                } else if self.rest().starts_with('[') {
+                    self.bracketed()?
+                } else {
+                    vec![self.shorthand()?]
+                };
+
+                Segment {
+                    descendant: true,
+                    selectors,
+                }
+            } else if self.eat(".") {
+                let selector = if self.eat("*") {
+                    Selector::Wildcard
+                } else {
+                    self.shorthand()?
+                };
+
+                Segment {
+                    descendant: false,
+                    selectors: vec![selector],
+                }
+            } else if self.rest().starts_with('[') {
+                Segment {
+                    descendant: false,
+                    selectors: self.bracketed()?,
+                }
+            } else {
+                self.pos = start;
+                return Ok(Query { relative, segments });
+            };
+
+            segments.push(segment);
+        }
+    }
+
+    // A member name without quotes, like `name` in `$.name`
+    fn shorthand(&mut self) -> Result<Selector, PathError> {
+        if !self.rest().starts_with(Self::is_name_first) {
+            return self.unexpected();
+        }
+
+        let name = self.take_while(|c| Self::is_name_first(c) || c.is_ascii_digit());
+        Ok(Selector::Name(name.to_string()))
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.expect("[")?;
+        let mut selectors = vec![];
+
+        loop {
+            self.skip_blank();
+            selectors.push(self.selector()?);
+            self.skip_blank();
+
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+
+            self.expect(",")?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, PathError> {
+        if self.rest().starts_with(['\'', '"']) {
+            return Ok(Selector::Name(self.string()?));
+        }
+
+        if self.eat("*") {
+            return Ok(Selector::Wildcard);
+        }
+
+        if self.eat("?") {
+            self.nest()?;
+            self.skip_blank();
+            let logical = self.logical()?;
+            self.depth -= 1;
+            return Ok(Selector::Filter(logical));
+        }
+
+        let start = self.int()?;
+        self.skip_blank();
+
+        if !self.eat(":") {
+            return match start {
+                Some(index) => Ok(Selector::Index(index)),
+                None => self.unexpected(),
+            };
+        }
+
+        self.skip_blank();
+        let end = self.int()?;
+        self.skip_blank();
+
+        let step = if self.eat(":") {
+            self.skip_blank();
+            self.int()?
+        } else {
+            None
+        };
+
+        Ok(Selector::Slice { start, end, step })
+    }
+
+    // An integer without leading zeros, `None` when there is none
+    fn int(&mut self) -> Result<Option<i64>, PathError> {
+        let start = self.pos;
+        let negative = self.eat("-");
+        let digits = self.take_while(|c| c.is_ascii_digit());
+
+        if digits.is_empty() {
+            if negative {
+                return self.unexpected();
+            }
+
+            return Ok(None);
+        }
+
+        if (digits.len() > 1 && digits.starts_with('0')) || (negative && digits == "0") {
+            self.pos = start;
+            return self.error("Integers can not start with a zero");
+        }
+
+        match self.source[start..self.pos].parse::<i64>() {
+            Ok(n) if n.abs() <= MAX_INT => Ok(Some(n)),
+            _ => {
+                self.pos = start;
+                self.error("Integer out of range")
+            }
+        }
+    }
+
+    // A quoted string, decoded, in single or double quotes
+    fn string(&mut self) -> Result<String, PathError> {
+        let quote = self.rest().chars().next().unwrap();
+        self.pos += 1;
+        let mut result = String::new();
+
+        loop {
+            let c = match self.rest().chars().next() {
+                Some(c) => c,
+                None => return self.error("Missing closing quote"),
+            };
+
+            if c == quote {
+                self.pos += 1;
+                return Ok(result);
+            }
+
+            if c < ' ' {
+                return self.error("Control characters have to be escaped");
+            }
+
+            if c != '\\' {
+                self.pos += c.len_utf8();
+                result.push(c);
+                continue;
+            }
+
+            self.pos += 1;
+
+            let escaped = match self.rest().chars().next() {
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some(c @ ('/' | '\\')) => c,
+                Some(c) if c == quote => c,
+                Some('u') => {
+                    self.pos += 1;
+                    result.push(self.unicode_escape()?);
+                    continue;
+                }
+                _ => return self.error("Invalid escape sequence"),
+            };
+
+            self.pos += 1;
+            result.push(escaped);
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, PathError> {
+        let hex = self.rest().get(..4).unwrap_or_default();
+
+        match u32::from_str_radix(hex, 16) {
+            Ok(n) if hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                self.pos += 4;
+                Ok(n)
+            }
+            _ => self.error("Invalid unicode escape"),
+        }
+    }
+
+    // The digits of `\uXXXX`, with the second half of a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, PathError> {
+        let high = self.hex4()?;
+
+        let code = match high {
+            0xd800..=0xdbff => {
+                if !self.eat("\\u") {
+                    return self.error("Missing low surrogate");
+                }
+
+                match self.hex4()? {
+                    low @ 0xdc00..=0xdfff => 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00),
+                    _ => return self.error("Invalid low surrogate"),
+                }
+            }
+            0xdc00..=0xdfff => return self.error("Unexpected low surrogate"),
+            code => code,
+        };
+
+        Ok(char::from_u32(code).unwrap())
+    }
+
+    fn logical(&mut self) -> Result<Logical, PathError> {
+        let mut alternatives = vec![self.and()?];
+
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+
+            if !self.eat("||") {
+                self.pos = start;
+                break;
+            }
+
+            self.skip_blank();
+            alternatives.push(self.and()?);
+        }
+
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Logical::Or(alternatives),
+        })
+    }
+
+    fn and(&mut self) -> Result<Logical, PathError> {
+        let mut conditions = vec![self.basic()?];
+
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+
+            if !self.eat("&&") {
+                self.pos = start;
+                break;
+            }
+
+            self.skip_blank();
+            conditions.push(self.basic()?);
+        }
+
+        Ok(match conditions.len() {
+            1 => conditions.pop().unwrap(),
+            _ => Logical::And(conditions),
+        })
+    }
+
+    fn basic(&mut self) -> Result<Logical, PathError> {
+        if self.eat("!") {
+            self.skip_blank();
+
+            let negated = if self.rest().starts_with('(') {
+                self.parenthesized()?
+            } else {
+                self.test()?
+            };
+
+            return Ok(Logical::Not(Box::new(negated)));
+        }
+
+        if self.rest().starts_with('(') {
+            return self.parenthesized();
+        }
+
+        let start = self.pos;
+        let left = self.comparable()?;
+        let before_op = self.pos;
+        self.skip_blank();
+
+        let op = match self.op() {
+            Some(op) => op,
+            None => {
+                self.pos = before_op;
+
+                return match left {
+                    Comparable::Query(query) => Ok(Logical::Exists(query)),
+                    Comparable::Function(function) if function.name.is_logical() => {
+                        Ok(Logical::Test(function))
+                    }
+                    _ => {
+                        self.pos = start;
+                        self.error("Expected a comparison or a test")
+                    }
+                };
+            }
+        };
+
+        self.skip_blank();
+        let right_start = self.pos;
+        let right = self.comparable()?;
+
+        for (operand, position) in [(&left, start), (&right, right_start)] {
+            let valid = match operand {
+                Comparable::Query(query) => query.is_singular(),
+                Comparable::Function(function) => !function.name.is_logical(),
+                Comparable::Literal(_) => true,
+            };
+
+            if !valid {
+                self.pos = position;
+                return self.error("Only single values can be compared");
+            }
+        }
+
+        Ok(Logical::Compare(left, op, right))
+    }
+
+    fn parenthesized(&mut self) -> Result<Logical, PathError> {
+        self.nest()?;
+        self.expect("(")?;
+        self.skip_blank();
+        let logical = self.logical()?;
+        self.skip_blank();
+        self.expect(")")?;
+        self.depth -= 1;
+        Ok(logical)
+    }
+
+    // A query or a function returning a logical value, after `!`
+    fn test(&mut self) -> Result<Logical, PathError> {
+        let start = self.pos;
+
+        match self.comparable()? {
+            Comparable::Query(query) => Ok(Logical::Exists(query)),
+            Comparable::Function(function) if function.name.is_logical() => {
+                Ok(Logical::Test(function))
+            }
+            _ => {
+                self.pos = start;
+                self.error("Expected a test")
+            }
+        }
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+
+        ops.iter()
+            .find(|(symbol, _)| self.eat(symbol))
+            .map(|&(_, op)| op)
+    }
+
+    fn comparable(&mut self) -> Result<Comparable, PathError> {
+        let rest = self.rest();
+
+        if rest.starts_with(['@', '$']) {
+            return Ok(Comparable::Query(self.query()?));
+        }
+
+        if rest.starts_with(['\'', '"']) {
+            let text = self.string()?;
+            let literal = Json::String(Cow::Owned(text));
+            return Ok(Comparable::Literal(literal));
+        }
+
+        if rest.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+            return self.number();
+        }
+
+        for (word, literal) in [
+            ("true", Json::Bool(true)),
+            ("false", Json::Bool(false)),
+            ("null", Json::Null),
+        ] {
+            if self.eat(word) {
+                return Ok(Comparable::Literal(literal));
+            }
+        }
+
+        if rest.starts_with(|c: char| c.is_ascii_lowercase()) {
+            return Ok(Comparable::Function(self.function()?));
+        }
+
+        self.unexpected()
+    }
+
+    // Like JSON numbers, with `-0` allowed
+    fn number(&mut self) -> Result<Comparable, PathError> {
+        let start = self.pos;
+        self.eat("-");
+
+        let int = self.take_while(|c| c.is_ascii_digit());
+
+        if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+            self.pos = start;
+            return self.error("Invalid number");
+        }
+
+        if self.eat(".") && self.take_while(|c| c.is_ascii_digit()).is_empty() {
+            return self.unexpected();
+        }
+
+        if self.eat("e") || self.eat("E") {
+            let _ = self.eat("+") || self.eat("-");
+
+            if self.take_while(|c| c.is_ascii_digit()).is_empty() {
+                return self.unexpected();
+            }
+        }
+
+        match Number::parse(&self.source[start..self.pos]) {
+            Some(number) => Ok(Comparable::Literal(Json::Number(number))),
+            None => {
+                self.pos = start;
+                self.error("Invalid number")
+            }
+        }
+    }
+
+    fn function(&mut self) -> Result<Function, PathError> {
+        let start = self.pos;
+        let name = self.take_while(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+        let name = match name {
+            "length" => FunctionName::Length,
+            "count" => FunctionName::Count,
+            "match" => FunctionName::Match,
+            "search" => FunctionName::Search,
+            "value" => FunctionName::Value,
+            _ => {
+                self.pos = start;
+                return self.error(&format!("Unknown function '{}'", name));
+            }
+        };
+
+        self.nest()?;
+        self.expect("(")?;
+        let mut arguments = vec![];
+
+        for (index, nodes) in name.parameters().iter().enumerate() {
+            self.skip_blank();
+
+            if index > 0 {
+                self.expect(",")?;
+                self.skip_blank();
+            }
+
+            let argument_start = self.pos;
+            let argument = self.comparable()?;
+
+            let argument = match (nodes, argument) {
+                (true, Comparable::Query(query)) => Argument::Nodes(query),
+                (false, Comparable::Query(query)) if !query.is_singular() => {
+                    self.pos = argument_start;
+                    return self.error("Expected a single value");
+                }
+                (false, Comparable::Function(function)) if function.name.is_logical() => {
+                    self.pos = argument_start;
+                    return self.error("Expected a value");
+                }
+                (false, argument) => Argument::Value(argument),
+                (true, _) => {
+                    self.pos = argument_start;
+                    return self.error("Expected a query");
+                }
+            };
+
+            arguments.push(argument);
+        }
+
+        self.skip_blank();
+        self.expect(")")?;
+        self.depth -= 1;
+
+        Ok(Function { name, arguments })
+    }
+}
+
+impl FunctionName {
+    // Whether each parameter takes the nodes of a query rather than a single value
+    fn parameters(self) -> &'static [bool] {
+        match self {
+            FunctionName::Length => &[false],
+            FunctionName::Count | FunctionName::Value => &[true],
+            FunctionName::Match | FunctionName::Search => &[false, false],
+        }
+    }
+
+    fn is_logical(self) -> bool {
+        matches!(self, FunctionName::Match | FunctionName::Search)
+    }
+}
+
+// Names in single quotes with the escapes of RFC 9535, so every node has exactly one path
+fn push_name(path: &str, name: &str) -> String {
+    let mut result = format!("{}['", path);
+
+    for c in name.chars() {
+        match c {
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\'' => result.push_str("\\'"),
+            '\\' => result.push_str("\\\\"),
+            c if c < ' ' => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result + "']"
+}
+
+fn less(a: &Json, b: &Json) -> bool {
+    match (a, b) {
+        (Json::Number(a), Json::Number(b)) => a.partial_cmp(b) == Some(Ordering::Less),
+        (Json::String(a), Json::String(b)) => a < b,
+        _ => false,
+    }
+}
+
+// Indexes of the items of an array of `len` items selected by a slice
+fn slice(start: Option<i64>, end: Option<i64>, step: Option<i64>, len: usize) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indexes = vec![];
+
+    if step > 0 {
+        let lower = start.map_or(0, normalize).clamp(0, len);
+        let upper = end.map_or(len, normalize).clamp(0, len);
+        let mut i = lower;
+
+        while i < upper {
+            indexes.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut i = upper;
+
+        while lower < i {
+            indexes.push(i as usize);
+            i += step;
+        }
+    }
+
+    indexes
+}
+
+// Paths are only built for the result, not for the queries inside filters
+struct Evaluator<'j, 'a> {
+    root: &'j Json<'a>,
+    paths: bool,
+}
+
+impl<'j, 'a> Evaluator<'j, 'a> {
+    fn query(&self, query: &Query, current: &'j Json<'a>) -> Vec<Node<'j, 'a>> {
+        let start = if query.relative { current } else { self.root };
+
+        let mut nodes = vec![Node {
+            path: if self.paths {
+                "$".to_string()
+            } else {
+                String::new()
+            },
+            value: start,
+        }];
+
+        for segment in &query.segments {
+            let mut selected = vec![];
+
+            for node in &nodes {
+                if segment.descendant {
+                    self.descend(&segment.selectors, node, &mut selected);
+                } else {
+                    self.select_all(&segment.selectors, node, &mut selected);
+                }
+            }
+
+            nodes = selected;
+        }
+
+        nodes
+    }
+
+    fn child_path(&self, node: &Node, name: Option<&str>, index: usize) -> String {
+        match (self.paths, name) {
+            (false, _) => String::new(),
+            (true, Some(name)) => push_name(&node.path, name),
+            (true, None) => format!("{}[{}]", node.path, index),
+        }
+    }
+
+    // The children of an object or array, with their paths
+    fn children(&self, node: &Node<'j, 'a>) -> Vec<Node<'j, 'a>> {
+        match node.value {
+            Json::Object(obj) => obj
+                .iter()
+                .map(|(key, value)| Node {
+                    path: self.child_path(node, Some(key), 0),
+                    value,
+                })
+                .collect(),
+            Json::Array(arr) => arr
+                .iter()
+                .enumerate()
+                .map(|(index, value)| Node {
+                    path: self.child_path(node, None, index),
+                    value,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn select_all(&self, selectors: &[Selector], node: &Node<'j, 'a>, out: &mut Vec<Node<'j, 'a>>) {
+        for selector in selectors {
+            self.select(selector, node, out);
+        }
+    }
+
+    // Applies the selectors to the node and then to each of its descendants, in document order
+    fn descend(&self, selectors: &[Selector], node: &Node<'j, 'a>, out: &mut Vec<Node<'j, 'a>>) {
+        self.select_all(selectors, node, out);
+
+        for child in self.children(node) {
+            self.descend(selectors, &child, out);
+        }
+    }
+
+    fn select(&self, selector: &Selector, node: &Node<'j, 'a>, out: &mut Vec<Node<'j, 'a>>) {
+        match (selector, node.value) {
+            (Selector::Name(name), Json::Object(obj)) => {
+                if let Some(value) = obj.get(name.as_str()) {
+                    out.push(Node {
+                        path: self.child_path(node, Some(name), 0),
+                        value,
+                    });
+                }
+            }
+            (Selector::Wildcard, _) => out.extend(self.children(node)),
+            (Selector::Index(index), Json::Array(arr)) => {
+                let len = arr.len() as i64;
+                let index = if *index < 0 { len + index } else { *index };
+
+                if (0..len).contains(&index) {
+                    out.push(Node {
+                        path: self.child_path(node, None, index as usize),
+                        value: &arr[index as usize],
+                    });
+                }
+            }
+            (Selector::Slice { start, end, step }, Json::Array(arr)) => {
+                out.extend(
+                    slice(*start, *end, *step, arr.len())
+                        .into_iter()
+                        .map(|index| Node {
+                            path: self.child_path(node, None, index),
+                            value: &arr[index],
+                        }),
+                );
+            }
+            (Selector::Filter(logical), _) => out.extend(
+                self.children(node)
+                    .into_iter()
+                    .filter(|child| self.test(logical, child.value)),
+            ),
+            _ => {}
+        }
+    }
+
+    fn test(&self, logical: &Logical, current: &'j Json<'a>) -> bool {
+        match logical {
+            Logical::Or(alternatives) => alternatives.iter().any(|l| self.test(l, current)),
+            Logical::And(conditions) => conditions.iter().all(|l| self.test(l, current)),
+            Logical::Not(logical) => !self.test(logical, current),
+            Logical::Exists(query) => !self.nodes(query, current).is_empty(),
+            Logical::Test(function) => self.matches(function, current),
+            Logical::Compare(left, op, right) => {
+                let left = self.value(left, current);
+                let right = self.value(right, current);
+
+                let is_equal = match (&left, &right) {
+                    (Some(a), Some(b)) => semantic_eq(a, b, EqOptions::default()),
+                    (None, None) => true,
+                    _ => false,
+                };
+                let is_less = match (&left, &right) {
+                    (Some(a), Some(b)) => less(a, b),
+                    _ => false,
+                };
+                let is_greater = match (&left, &right) {
+                    (Some(a), Some(b)) => less(b, a),
+                    _ => false,
+                };
+
+                match op {
+                    Op::Eq => is_equal,
+                    Op::Ne => !is_equal,
+                    Op::Lt => is_less,
+                    Op::Le => is_less || is_equal,
+                    Op::Gt => is_greater,
+                    Op::Ge => is_greater || is_equal,
+                }
+            }
+        }
+    }
+
+    // The nodes of a query inside a filter, without paths
+    fn nodes(&self, query: &Query, current: &'j Json<'a>) -> Vec<&'j Json<'a>> {
+        let evaluator = Evaluator {
+            root: self.root,
+            paths: false,
+        };
+
+        evaluator
+            .query(query, current)
+            .into_iter()
+            .map(|node| node.value)
+            .collect()
+    }
+
+    // `None` is the absence of a value, like a missing key
+    fn value<'v>(
+        &self,
+        comparable: &'v Comparable,
+        current: &'j Json<'a>,
+    ) -> Option<Cow<'v, Json<'a>>>
+    where
+        'j: 'v,
+    {
+        match comparable {
+            Comparable::Literal(literal) => Some(Cow::Borrowed(literal)),
+            Comparable::Query(query) => self
+                .nodes(query, current)
+                .first()
+                .map(|v| Cow::Borrowed(*v)),
+            Comparable::Function(function) => self.call(function, current),
+        }
+    }
+
+    fn argument<'v>(
+        &self,
+        argument: &'v Argument,
+        current: &'j Json<'a>,
+    ) -> Option<Cow<'v, Json<'a>>>
+    where
+        'j: 'v,
+    {
+        match argument {
+            Argument::Value(comparable) => self.value(comparable, current),
+            Argument::Nodes(_) => None,
+        }
+    }
+
+    fn call<'v>(&self, function: &'v Function, current: &'j Json<'a>) -> Option<Cow<'v, Json<'a>>>
+    where
+        'j: 'v,
+    {
+        let nodes = match function.arguments.first() {
+            Some(Argument::Nodes(query)) => self.nodes(query, current),
+            _ => vec![],
+        };
+
+        match function.name {
+            FunctionName::Length => {
+                let len = match self.argument(&function.arguments[0], current)?.as_ref() {
+                    Json::String(s) => s.chars().count(),
+                    Json::Array(arr) => arr.len(),
+                    Json::Object(obj) => obj.len(),
+                    _ => return None,
+                };
+
+                Some(Cow::Owned(Json::Number((len as u64).into())))
+            }
+            FunctionName::Count => Some(Cow::Owned(Json::Number((nodes.len() as u64).into()))),
+            FunctionName::Value => match nodes.as_slice() {
+                [value] => Some(Cow::Borrowed(*value)),
+                _ => None,
+            },
+            FunctionName::Match | FunctionName::Search => None,
+        }
+    }
+
+    fn matches(&self, function: &Function, current: &'j Json<'a>) -> bool {
+        let text = self.argument(&function.arguments[0], current);
+        let pattern = self.argument(&function.arguments[1], current);
+
+        let (text, pattern) = match (text.as_deref(), pattern.as_deref()) {
+            (Some(Json::String(text)), Some(Json::String(pattern))) => (text, pattern),
+            _ => return false,
+        };
+
+        // An invalid pattern matches nothing
+        match Regex::new(pattern) {
+            Some(regex) if function.name == FunctionName::Match => regex.is_match(text),
+            Some(regex) => regex.is_found(text),
+            None => false,
+        }
+    }
+}
+
+impl JsonPath {
+    pub fn parse(expression: &str) -> Result<JsonPath, PathError> {
+        let mut parser = ExpressionParser {
+            source: expression,
+            pos: 0,
+            depth: 0,
+        };
+
+        if !expression.starts_with('$') {
+            return parser.error("Queries start with '$'");
+        }
+
+        let query = parser.query()?;
+
+        if !parser.rest().is_empty() {
+            return parser.unexpected();
+        }
+
+        Ok(JsonPath { query })
+    }
+
+    // The matches in document order, a node can be matched more than once
+    pub fn query<'j, 'a>(&self, value: &'j Json<'a>) -> Vec<Node<'j, 'a>> {
+        let evaluator = Evaluator {
+            root: value,
+            paths: true,
+        };
+
+        evaluator.query(&self.query, value)
+    }
+}
+
+// Parses the expression and runs it once
+pub fn query<'j, 'a>(
+    value: &'j Json<'a>,
+    expression: &str,
+) -> Result<Vec<Node<'j, 'a>>, PathError> {
+    Ok(JsonPath::parse(expression)?.query(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    const STORE: &str = r#"{
+        "store": {
+            "book": [
+                {"category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95},
+                {"category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99},
+                {"category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99},
+                {"category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99}
+            ],
+            "bicycle": {"color": "red", "price": 399}
+        }
+    }"#;
+
+    fn paths(raw: &str, expression: &str) -> Vec<String> {
+        let value = parser::parse_str(raw).unwrap();
+        query(&value, expression)
+            .unwrap()
+            .into_iter()
+            .map(|node| node.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_query() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            (
+                "$.store.book[*].author",
+                vec![
+                    "$['store']['book'][0]['author']",
+                    "$['store']['book'][1]['author']",
+                    "$['store']['book'][2]['author']",
+                    "$['store']['book'][3]['author']",
+                ],
+            ),
+            (
+                "$..author",
+                vec![
+                    "$['store']['book'][0]['author']",
+                    "$['store']['book'][1]['author']",
+                    "$['store']['book'][2]['author']",
+                    "$['store']['book'][3]['author']",
+                ],
+            ),
+            (
+                "$.store.book..price",
+                vec![
+                    "$['store']['book'][0]['price']",
+                    "$['store']['book'][1]['price']",
+                    "$['store']['book'][2]['price']",
+                    "$['store']['book'][3]['price']",
+                ],
+            ),
+            ("$..book[2]", vec!["$['store']['book'][2]"]),
+            ("$..book[-1]", vec!["$['store']['book'][3]"]),
+            (
+                "$..book[0,1]",
+                vec!["$['store']['book'][0]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[:2]",
+                vec!["$['store']['book'][0]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[::-2]",
+                vec!["$['store']['book'][3]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[?@.isbn]",
+                vec!["$['store']['book'][2]", "$['store']['book'][3]"],
+            ),
+            (
+                "$.store.book[?@.price < 10].title",
+                vec![
+                    "$['store']['book'][0]['title']",
+                    "$['store']['book'][2]['title']",
+                ],
+            ),
+            (
+                "$..book[?@.price<10 && @.category=='fiction']",
+                vec!["$['store']['book'][2]"],
+            ),
+            (
+                "$..book[?!(@.price > 10) || @.author == $.store.book[3].author]",
+                vec![
+                    "$['store']['book'][0]",
+                    "$['store']['book'][2]",
+                    "$['store']['book'][3]",
+                ],
+            ),
+            (
+                "$..book[?match(@.author, 'J.*')]",
+                vec!["$['store']['book'][3]"],
+            ),
+            (
+                "$..book[?search(@.title, \"[Ss]word\")]",
+                vec!["$['store']['book'][1]"],
+            ),
+            (
+                "$..book[?length(@.title) == 9]",
+                vec!["$['store']['book'][2]"],
+            ),
+            ("$.store[?count(@.*) == 2]", vec!["$['store']['bicycle']"]),
+            (
+                "$..book[?value(@..isbn) == '0-553-21311-3']",
+                vec!["$['store']['book'][2]"],
+            ),
+            (
+                "$.store.bicycle[?@ == 'red']",
+                vec!["$['store']['bicycle']['color']"],
+            ),
+            (
+                "$['store']['bicycle'].*",
+                vec![
+                    "$['store']['bicycle']['color']",
+                    "$['store']['bicycle']['price']",
+                ],
+            ),
+            ("$.store.missing", vec![]),
+            ("$", vec!["$"]),
+        ];
+
+        for (expression, expected) in cases {
+            assert_eq!(paths(STORE, expression), expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_query_values() {
+        let value =
+            parser::parse_str("{\"a\": [1, 2.0, \"x\", null, {\"b\": 1}], \"it's\\n\": true}")
+                .unwrap();
+
+        let matched: Vec<_> = query(&value, "$.a[?@ == 1 || @ == null]")
+            .unwrap()
+            .into_iter()
+            .map(|node| node.value.clone())
+            .collect();
+        assert_eq!(matched, [Json::Number(1.0.into()), Json::Null]);
+
+        assert_eq!(paths("{\"it's\\n\": 1}", "$.*"), ["$['it\\'s\\n']"]);
+        assert_eq!(paths("{\"a\": {\"b\": 1}}", "$[?@.b == 1]"), ["$['a']"]);
+        assert_eq!(
+            paths("[{\"b\": [1]}, {\"b\": [1]}]", "$[?@.b == $[0].b]").len(),
+            2
+        );
+        assert_eq!(paths("{\"a\": 1}", "$[?@.x == @.y]"), ["$['a']"]);
+        assert_eq!(paths("[\"b\", \"a\"]", "$[?@ < 'b']"), ["$[1]"]);
+        assert_eq!(paths("{\"é\": 1}", "$['\\u00e9']"), ["$['é']"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("", 0),
+            ("a", 0),
+            ("$.", 2),
+            ("$[01]", 2),
+            ("$[-0]", 2),
+            ("$[?@.a]]", 7),
+            ("$[?@.* == 1]", 3),
+            ("$[?length(@.*) == 1]", 10),
+            ("$[?count(1) == 1]", 9),
+            ("$[?match(@.a, 'x') == true]", 3),
+            ("$[?foo(@)]", 3),
+            ("$['a\\x']", 5),
+            ("$[1] ", 4),
+            ("$[9007199254740992]", 2),
+            ("$['\\u+041']", 5),
+        ];
+
+        for (expression, position) in cases {
+            let error = JsonPath::parse(expression).unwrap_err();
+            assert_eq!(error.position, position, "{} {}", expression, error);
+        }
+    }
+
+    #[test]
+    fn test_parse_depth() {
+        let parens = |n| format!("$[?{}@.a{}]", "(".repeat(n), ")".repeat(n));
+        let filters = |n| format!("${}{}", "[?@".repeat(n), "]".repeat(n));
+        let functions = |n| format!("$[?{}@.a{} == 1]", "length(".repeat(n), ")".repeat(n));
+
+        for nested in [parens, filters, functions] {
+            assert!(JsonPath::parse(&nested(MAX_DEPTH - 1)).is_ok());
+
+            let error = JsonPath::parse(&nested(100_000)).unwrap_err();
+            assert_eq!(error.message, "The query is nested too deeply");
+        }
+
+        let value = parser::parse_str(r#"[{"a": 1}]"#).unwrap();
+        let query = JsonPath::parse(&parens(MAX_DEPTH - 1)).unwrap();
+        assert_eq!(query.query(&value).len(), 1);
+    }
+
+    #[test]
+    fn test_slice() {
+        assert_eq!(slice(Some(1), Some(3), None, 5), [1, 2]);
+        assert_eq!(slice(None, None, Some(-1), 3), [2, 1, 0]);
+        assert_eq!(slice(Some(-2), None, None, 5), [3, 4]);
+        assert_eq!(slice(None, None, Some(0), 5), Vec::<usize>::new());
+        assert_eq!(slice(Some(10), Some(-10), Some(-3), 5), [4, 1]);
+    }
+}
@@ -0,0 +1,2397 @@
+// JSON Schema validation for drafts 4, 6, 7, 2019-09 and 2020-12. The draft comes from the
+// options or from `$schema`, 2020-12 without either. The meta-schemas of the drafts are built in,
+// references to other documents are not fetched
+use compare::{semantic_eq, EqOptions};
+use iregexp::Regex;
+use number::Number;
+use parser::{self, Json, JsonOwned};
+use pointer;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Draft {
+    Draft4,
+    Draft6,
+    Draft7,
+    Draft2019_09,
+    Draft2020_12,
+}
+
+pub const DRAFTS: [Draft; 5] = [
+    Draft::Draft4,
+    Draft::Draft6,
+    Draft::Draft7,
+    Draft::Draft2019_09,
+    Draft::Draft2020_12,
+];
+
+// Each keyword with the first and the last draft defining it. `definitions` stays a place for
+// subschemas after 2019-09 replaced it with `$defs`, `dependencies` is no longer evaluated
+const KEYWORDS: [(&str, Draft, Draft); 63] = [
+    ("$anchor", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("$comment", Draft::Draft7, Draft::Draft2020_12),
+    ("$defs", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("$dynamicAnchor", Draft::Draft2020_12, Draft::Draft2020_12),
+    ("$dynamicRef", Draft::Draft2020_12, Draft::Draft2020_12),
+    ("$id", Draft::Draft6, Draft::Draft2020_12),
+    ("$recursiveAnchor", Draft::Draft2019_09, Draft::Draft2019_09),
+    ("$recursiveRef", Draft::Draft2019_09, Draft::Draft2019_09),
+    ("$ref", Draft::Draft4, Draft::Draft2020_12),
+    ("$schema", Draft::Draft4, Draft::Draft2020_12),
+    ("$vocabulary", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("additionalItems", Draft::Draft4, Draft::Draft2019_09),
+    ("additionalProperties", Draft::Draft4, Draft::Draft2020_12),
+    ("allOf", Draft::Draft4, Draft::Draft2020_12),
+    ("anyOf", Draft::Draft4, Draft::Draft2020_12),
+    ("const", Draft::Draft6, Draft::Draft2020_12),
+    ("contains", Draft::Draft6, Draft::Draft2020_12),
+    ("contentEncoding", Draft::Draft7, Draft::Draft2020_12),
+    ("contentMediaType", Draft::Draft7, Draft::Draft2020_12),
+    ("contentSchema", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("default", Draft::Draft4, Draft::Draft2020_12),
+    ("definitions", Draft::Draft4, Draft::Draft2020_12),
+    ("dependencies", Draft::Draft4, Draft::Draft7),
+    (
+        "dependentRequired",
+        Draft::Draft2019_09,
+        Draft::Draft2020_12,
+    ),
+    ("dependentSchemas", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("deprecated", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("description", Draft::Draft4, Draft::Draft2020_12),
+    ("else", Draft::Draft7, Draft::Draft2020_12),
+    ("enum", Draft::Draft4, Draft::Draft2020_12),
+    ("examples", Draft::Draft6, Draft::Draft2020_12),
+    ("exclusiveMaximum", Draft::Draft4, Draft::Draft2020_12),
+    ("exclusiveMinimum", Draft::Draft4, Draft::Draft2020_12),
+    ("format", Draft::Draft4, Draft::Draft2020_12),
+    ("id", Draft::Draft4, Draft::Draft4),
+    ("if", Draft::Draft7, Draft::Draft2020_12),
+    ("items", Draft::Draft4, Draft::Draft2020_12),
+    ("maxContains", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("maxItems", Draft::Draft4, Draft::Draft2020_12),
+    ("maxLength", Draft::Draft4, Draft::Draft2020_12),
+    ("maxProperties", Draft::Draft4, Draft::Draft2020_12),
+    ("maximum", Draft::Draft4, Draft::Draft2020_12),
+    ("minContains", Draft::Draft2019_09, Draft::Draft2020_12),
+    ("minItems", Draft::Draft4, Draft::Draft2020_12),
+    ("minLength", Draft::Draft4, Draft::Draft2020_12),
+    ("minProperties", Draft::Draft4, Draft::Draft2020_12),
+    ("minimum", Draft::Draft4, Draft::Draft2020_12),
+    ("multipleOf", Draft::Draft4, Draft::Draft2020_12),
+    ("not", Draft::Draft4, Draft::Draft2020_12),
+    ("oneOf", Draft::Draft4, Draft::Draft2020_12),
+    ("pattern", Draft::Draft4, Draft::Draft2020_12),
+    ("patternProperties", Draft::Draft4, Draft::Draft2020_12),
+    ("prefixItems", Draft::Draft2020_12, Draft::Draft2020_12),
+    ("properties", Draft::Draft4, Draft::Draft2020_12),
+    ("propertyNames", Draft::Draft6, Draft::Draft2020_12),
+    ("readOnly", Draft::Draft7, Draft::Draft2020_12),
+    ("required", Draft::Draft4, Draft::Draft2020_12),
+    ("then", Draft::Draft7, Draft::Draft2020_12),
+    ("title", Draft::Draft4, Draft::Draft2020_12),
+    ("type", Draft::Draft4, Draft::Draft2020_12),
+    ("unevaluatedItems", Draft::Draft2019_09, Draft::Draft2020_12),
+    (
+        "unevaluatedProperties",
+        Draft::Draft2019_09,
+        Draft::Draft2020_12,
+    ),
+    ("uniqueItems", Draft::Draft4, Draft::Draft2020_12),
+    ("writeOnly", Draft::Draft7, Draft::Draft2020_12),
+];
+
+impl Draft {
+    // The names `--schema-draft` takes
+    pub fn from_name(name: &str) -> Option<Draft> {
+        match name {
+            "04" | "4" => Some(Draft::Draft4),
+            "06" | "6" => Some(Draft::Draft6),
+            "07" | "7" => Some(Draft::Draft7),
+            "2019-09" => Some(Draft::Draft2019_09),
+            "2020-12" => Some(Draft::Draft2020_12),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Draft::Draft4 => "04",
+            Draft::Draft6 => "06",
+            Draft::Draft7 => "07",
+            Draft::Draft2019_09 => "2019-09",
+            Draft::Draft2020_12 => "2020-12",
+        }
+    }
+
+    // The `$id` of the meta-schema of the draft
+    pub fn uri(self) -> &'static str {
+        match self {
+            Draft::Draft4 => "http://json-schema.org/draft-04/schema#",
+            Draft::Draft6 => "http://json-schema.org/draft-06/schema#",
+            Draft::Draft7 => "http://json-schema.org/draft-07/schema#",
+            Draft::Draft2019_09 => "https://json-schema.org/draft/2019-09/schema",
+            Draft::Draft2020_12 => "https://json-schema.org/draft/2020-12/schema",
+        }
+    }
+
+    // The draft of a `$schema`, with either scheme and with or without the empty fragment
+    pub fn from_uri(uri: &str) -> Option<Draft> {
+        let strip = |uri: &str| {
+            let uri = uri.strip_suffix('#').unwrap_or(uri);
+            uri.strip_prefix("https://")
+                .or_else(|| uri.strip_prefix("http://"))
+                .map(str::to_string)
+        };
+
+        let path = strip(uri)?;
+        DRAFTS
+            .iter()
+            .copied()
+            .find(|draft| strip(draft.uri()).as_ref() == Some(&path))
+    }
+
+    pub fn has_keyword(self, keyword: &str) -> bool {
+        KEYWORDS
+            .iter()
+            .any(|(name, first, last)| *name == keyword && *first <= self && self <= *last)
+    }
+
+    pub fn keywords(self) -> Vec<&'static str> {
+        KEYWORDS
+            .iter()
+            .filter(|(_, first, last)| *first <= self && self <= *last)
+            .map(|(name, _, _)| *name)
+            .collect()
+    }
+
+    fn id_keyword(self) -> &'static str {
+        match self {
+            Draft::Draft4 => "id",
+            _ => "$id",
+        }
+    }
+}
+
+const META_SCHEMAS: [(Draft, &str); 18] = [
+    (Draft::Draft4, include_str!("jsonschema/draft-04.json")),
+    (Draft::Draft6, include_str!("jsonschema/draft-06.json")),
+    (Draft::Draft7, include_str!("jsonschema/draft-07.json")),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/schema.json"),
+    ),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/meta/applicator.json"),
+    ),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/meta/content.json"),
+    ),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/meta/core.json"),
+    ),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/meta/format.json"),
+    ),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/meta/meta-data.json"),
+    ),
+    (
+        Draft::Draft2019_09,
+        include_str!("jsonschema/2019-09/meta/validation.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/schema.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/applicator.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/content.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/core.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/format-annotation.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/meta-data.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/unevaluated.json"),
+    ),
+    (
+        Draft::Draft2020_12,
+        include_str!("jsonschema/2020-12/meta/validation.json"),
+    ),
+];
+
+fn meta_schemas() -> &'static [(Draft, JsonOwned)] {
+    static PARSED: OnceLock<Vec<(Draft, JsonOwned)>> = OnceLock::new();
+
+    PARSED.get_or_init(|| {
+        META_SCHEMAS
+            .iter()
+            .map(|(draft, text)| (*draft, parser::parse_str(text).unwrap()))
+            .collect()
+    })
+}
+
+// The meta-schema a schema of the draft is valid against
+pub fn meta_schema(draft: Draft) -> &'static Json<'static> {
+    let uri = draft.uri();
+
+    meta_schemas()
+        .iter()
+        .map(|(_, schema)| schema)
+        .find(
+            |schema| matches!(schema.get(draft.id_keyword()), Some(Json::String(id)) if id == uri),
+        )
+        .unwrap()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Options {
+    // Overrides the `$schema` of the schema
+    pub draft: Option<Draft>,
+    // `format` fails the values that do not match the formats `check_format` knows, instead of
+    // only being an annotation
+    pub format_assertion: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    // JSON pointer of the value in the instance
+    pub instance_path: String,
+    // JSON pointer of the keyword that failed, through the `$ref` evaluation followed, like
+    // `/properties/tags/$ref/items/type`
+    pub keyword_path: String,
+    // Absolute URI of the keyword that failed, its resource and a JSON pointer within it, like
+    // `#/definitions/tag/type` for a schema without `$id`
+    pub schema_location: String,
+    pub message: String,
+    // The types a failed `type` expected, merged when every branch of an `anyOf` fails with one
+    types: Vec<&'static str>,
+}
+
+// What the successful keywords of a schema went through, for `unevaluatedProperties` and
+// `unevaluatedItems`
+#[derive(Default)]
+struct Evaluated {
+    properties: HashSet<String>,
+    items: HashSet<usize>,
+    all_items: bool,
+}
+
+impl Evaluated {
+    fn merge(&mut self, other: Evaluated) {
+        self.properties.extend(other.properties);
+        self.items.extend(other.items);
+        self.all_items |= other.all_items;
+    }
+}
+
+// The schema resources evaluation went through, innermost first, for `$dynamicRef` and
+// `$recursiveRef`
+struct Scope {
+    resource: String,
+    parent: Option<Rc<Scope>>,
+}
+
+#[derive(Clone)]
+struct Context {
+    instance_path: String,
+    keyword_path: String,
+    // The resource the schema is in, which relative references resolve against, and the
+    // pointer to the schema within it
+    resource: String,
+    pointer: String,
+    draft: Draft,
+    scope: Option<Rc<Scope>>,
+    // References followed since the last step into the instance, a cycle never ends
+    refs: usize,
+    // How messages name the value, like "Property 'a'"
+    subject: String,
+}
+
+const MAX_REFS: usize = 64;
+
+impl Context {
+    fn keyword(&self, keyword: &str) -> Context {
+        Context {
+            keyword_path: pointer::push(&self.keyword_path, keyword),
+            pointer: pointer::push(&self.pointer, keyword),
+            ..self.clone()
+        }
+    }
+
+    // A subschema of the keyword, by index or name
+    fn subschema(&self, keyword: &str, token: &str) -> Context {
+        let context = self.keyword(keyword);
+
+        Context {
+            keyword_path: pointer::push(&context.keyword_path, token),
+            pointer: pointer::push(&context.pointer, token),
+            ..context
+        }
+    }
+
+    fn property(&self, name: &str) -> Context {
+        Context {
+            instance_path: pointer::push(&self.instance_path, name),
+            refs: 0,
+            subject: format!("Property '{}'", name),
+            ..self.clone()
+        }
+    }
+
+    fn item(&self, index: usize) -> Context {
+        Context {
+            instance_path: pointer::push(&self.instance_path, &index.to_string()),
+            refs: 0,
+            subject: format!("Item {}", index),
+            ..self.clone()
+        }
+    }
+
+    fn error(&self, keyword: &str, message: String) -> ValidationError {
+        self.keyword(keyword).failure(message)
+    }
+
+    // An error of the schema or keyword the context is at
+    fn failure(&self, message: String) -> ValidationError {
+        ValidationError {
+            instance_path: self.instance_path.clone(),
+            keyword_path: self.keyword_path.clone(),
+            schema_location: format!("{}#{}", self.resource, self.pointer),
+            message,
+            types: vec![],
+        }
+    }
+}
+
+// A schema found by a reference, with where it is
+struct Target<'s> {
+    schema: &'s Json<'s>,
+    resource: String,
+    pointer: String,
+    draft: Draft,
+    dynamic: bool,
+}
+
+pub struct Validator<'s> {
+    // Schema resources by their URI without fragment, the root one is "" without `$id`
+    resources: HashMap<String, (&'s Json<'s>, Draft)>,
+    // `$anchor`, `$dynamicAnchor` and the `$id` fragments of older drafts by full URI
+    anchors: HashMap<String, Target<'s>>,
+    root: &'s Json<'s>,
+    draft: Draft,
+    options: Options,
+    patterns: RefCell<HashMap<String, Option<Regex>>>,
+}
+
+impl<'s> Validator<'s> {
+    pub fn new(schema: &'s Json<'s>, options: Options) -> Validator<'s> {
+        let draft = match (options.draft, schema.get("$schema")) {
+            (Some(draft), _) => draft,
+            (None, Some(Json::String(uri))) => Draft::from_uri(uri).unwrap_or(Draft::Draft2020_12),
+            _ => Draft::Draft2020_12,
+        };
+
+        let mut validator = Validator {
+            resources: HashMap::new(),
+            anchors: HashMap::new(),
+            root: schema,
+            draft,
+            options,
+            patterns: RefCell::new(HashMap::new()),
+        };
+
+        for (draft, meta_schema) in meta_schemas() {
+            validator.scan(meta_schema, "", "", *draft);
+        }
+
+        validator.resources.insert(String::new(), (schema, draft));
+        validator.scan(schema, "", "", draft);
+        validator
+    }
+
+    pub fn draft(&self) -> Draft {
+        self.draft
+    }
+
+    // Every way the instance fails the schema, none when it is valid
+    pub fn validate(&self, instance: &Json) -> Vec<ValidationError> {
+        let context = Context {
+            instance_path: String::new(),
+            keyword_path: String::new(),
+            resource: String::new(),
+            pointer: String::new(),
+            draft: self.draft,
+            scope: Some(Rc::new(Scope {
+                resource: String::new(),
+                parent: None,
+            })),
+            refs: 0,
+            subject: "The value".to_string(),
+        };
+
+        self.evaluate(self.root, instance, &context, &mut Evaluated::default())
+    }
+
+    pub fn is_valid(&self, instance: &Json) -> bool {
+        self.validate(instance).is_empty()
+    }
+
+    // Registers the resources and anchors of a schema and its subschemas
+    fn scan(&mut self, schema: &'s Json<'s>, resource: &str, pointer: &str, draft: Draft) {
+        let obj = match schema {
+            Json::Object(obj) => obj,
+            _ => return,
+        };
+
+        let mut resource = resource.to_string();
+        let mut pointer = pointer.to_string();
+        let mut draft = draft;
+
+        if let Some(Json::String(id)) = obj.get(draft.id_keyword()) {
+            let uri = resolve(&resource, id);
+            let (base, fragment) = split_fragment(&uri);
+
+            if draft <= Draft::Draft7 && id.starts_with('#') {
+                self.anchors.insert(
+                    uri.clone(),
+                    Target {
+                        schema,
+                        resource: resource.clone(),
+                        pointer: pointer.clone(),
+                        draft,
+                        dynamic: false,
+                    },
+                );
+            } else if fragment.is_empty() {
+                if let Some(Json::String(uri)) = obj.get("$schema") {
+                    draft = Draft::from_uri(uri).unwrap_or(draft);
+                }
+
+                resource = base.to_string();
+                pointer = String::new();
+                self.resources.insert(resource.clone(), (schema, draft));
+            }
+        }
+
+        if draft >= Draft::Draft2019_09 {
+            for (keyword, dynamic) in [("$anchor", false), ("$dynamicAnchor", true)] {
+                if let Some(Json::String(name)) = obj.get(keyword) {
+                    let target = Target {
+                        schema,
+                        resource: resource.clone(),
+                        pointer: pointer.clone(),
+                        draft,
+                        dynamic,
+                    };
+                    self.anchors
+                        .insert(format!("{}#{}", resource, name), target);
+                }
+            }
+        }
+
+        for (keyword, value) in obj {
+            let keyword_pointer = pointer::push(&pointer, keyword);
+
+            match (keyword.as_ref(), value) {
+                (keyword, Json::Array(arr)) if ARRAY_KEYWORDS.contains(&keyword) => {
+                    for (index, item) in arr.iter().enumerate() {
+                        let item_pointer = pointer::push(&keyword_pointer, &index.to_string());
+                        self.scan(item, &resource, &item_pointer, draft);
+                    }
+                }
+                (keyword, Json::Object(map)) if MAP_KEYWORDS.contains(&keyword) => {
+                    for (name, item) in map {
+                        let item_pointer = pointer::push(&keyword_pointer, name);
+                        self.scan(item, &resource, &item_pointer, draft);
+                    }
+                }
+                (keyword, _) if SCHEMA_KEYWORDS.contains(&keyword) => {
+                    self.scan(value, &resource, &keyword_pointer, draft)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // The schema a resolved reference leads to. A JSON pointer fragment goes through the
+    // subschemas from the resource, those with an `$id` start resources of their own
+    fn lookup(&self, uri: &str) -> Option<Target<'s>> {
+        if let Some(target) = self.anchors.get(uri) {
+            return Some(Target {
+                resource: target.resource.clone(),
+                pointer: target.pointer.clone(),
+                ..*target
+            });
+        }
+
+        let (base, fragment) = split_fragment(uri);
+        let (root, draft) = *self.resources.get(base)?;
+        let fragment = percent_decode(fragment);
+
+        if !fragment.is_empty() && !fragment.starts_with('/') {
+            return None;
+        }
+
+        let mut target = Target {
+            schema: root,
+            resource: base.to_string(),
+            pointer: String::new(),
+            draft,
+            dynamic: false,
+        };
+
+        for token in pointer::split(&fragment)? {
+            if target.pointer.is_empty() && target.schema.get(draft.id_keyword()).is_some() {
+                // The resource root itself
+            } else if let Some(Json::String(id)) = target.schema.get(draft.id_keyword()) {
+                let (id_base, id_fragment) = split_fragment(id);
+
+                if !id_base.is_empty() && id_fragment.is_empty() {
+                    target.resource = split_fragment(&resolve(&target.resource, id_base))
+                        .0
+                        .to_string();
+                    target.pointer = String::new();
+                }
+            }
+
+            target.schema = match target.schema {
+                Json::Object(obj) => obj.get(token.as_str())?,
+                Json::Array(arr) => arr.get(token.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+            target.pointer = pointer::push(&target.pointer, &token);
+        }
+
+        Some(target)
+    }
+
+    fn regex(&self, pattern: &str, text: &str) -> Option<bool> {
+        let mut patterns = self.patterns.borrow_mut();
+        let regex = patterns
+            .entry(pattern.to_string())
+            .or_insert_with(|| Regex::ecma(pattern));
+
+        regex.as_ref().map(|regex| regex.is_found(text))
+    }
+
+    fn evaluate(
+        &self,
+        schema: &'s Json<'s>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let obj = match schema {
+            Json::Object(obj) => obj,
+            Json::Bool(false) => {
+                let message = format!("{} is not allowed", context.subject);
+                return vec![context.failure(message)];
+            }
+            _ => return vec![],
+        };
+
+        let instance = instance.contents();
+        let mut context = context.clone();
+
+        // A schema with an `$id` starts a resource, and a `$schema` next to it can change the draft
+        if let Some(Json::String(id)) = obj.get(context.draft.id_keyword()) {
+            let (base, fragment) = split_fragment(id);
+
+            if !base.is_empty() && fragment.is_empty() {
+                let resource = split_fragment(&resolve(&context.resource, base))
+                    .0
+                    .to_string();
+
+                if let Some((_, draft)) = self.resources.get(&resource) {
+                    context.draft = *draft;
+                }
+
+                context.scope = Some(Rc::new(Scope {
+                    resource: resource.clone(),
+                    parent: context.scope.take(),
+                }));
+                context.resource = resource;
+                context.pointer = String::new();
+            }
+        }
+
+        let mut errors = vec![];
+
+        // Before 2019-09 the keywords next to `$ref` are ignored
+        if let Some(Json::String(reference)) = obj.get("$ref") {
+            errors.extend(self.reference(reference, instance, &context, evaluated));
+
+            if context.draft <= Draft::Draft7 {
+                return errors;
+            }
+        }
+
+        for (keyword, value) in obj {
+            let keyword = keyword.as_ref();
+
+            if !context.draft.has_keyword(keyword) {
+                continue;
+            }
+
+            errors.extend(self.keyword(keyword, value, obj, instance, &context, evaluated));
+        }
+
+        // The unevaluated keywords come last, they depend on what the others went through
+        if context.draft >= Draft::Draft2019_09 {
+            errors.extend(self.unevaluated(obj, instance, &context, evaluated));
+        }
+
+        errors
+    }
+
+    fn reference(
+        &self,
+        reference: &str,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let uri = resolve(&context.resource, reference);
+        let target = self.lookup(&uri);
+        self.follow("$ref", reference, target, instance, context, evaluated)
+    }
+
+    // `$dynamicRef` to an anchor that is a `$dynamicAnchor` goes to the outermost resource of the
+    // dynamic scope with that `$dynamicAnchor`. `$recursiveRef` does the same with the resources
+    // that have `"$recursiveAnchor": true` when the resource it leads to has one
+    fn dynamic_reference(
+        &self,
+        keyword: &str,
+        reference: &str,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let uri = resolve(&context.resource, reference);
+        let mut target = self.lookup(&uri);
+
+        let mut scopes = vec![];
+        let mut scope = context.scope.as_ref();
+
+        while let Some(current) = scope {
+            scopes.push(current.resource.as_str());
+            scope = current.parent.as_ref();
+        }
+
+        if keyword == "$dynamicRef" {
+            let (_, name) = split_fragment(&uri);
+
+            if target.as_ref().is_some_and(|target| target.dynamic) {
+                let outermost = scopes.iter().rev().find_map(|resource| {
+                    self.anchors
+                        .get(&format!("{}#{}", resource, name))
+                        .filter(|anchor| anchor.dynamic)
+                });
+
+                if let Some(anchor) = outermost {
+                    target = self.lookup(&format!("{}#{}", anchor.resource, name));
+                }
+            }
+        } else {
+            let recursive = |target: &Target| {
+                matches!(
+                    target.schema.get("$recursiveAnchor"),
+                    Some(Json::Bool(true))
+                )
+            };
+
+            if target.as_ref().is_some_and(recursive) {
+                let outermost = scopes.iter().rev().find_map(|resource| {
+                    self.lookup(resource)
+                        .filter(|target| target.pointer.is_empty() && recursive(target))
+                });
+
+                if outermost.is_some() {
+                    target = outermost;
+                }
+            }
+        }
+
+        self.follow(keyword, reference, target, instance, context, evaluated)
+    }
+
+    fn follow(
+        &self,
+        keyword: &str,
+        reference: &str,
+        target: Option<Target<'s>>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let target = match target {
+            Some(target) => target,
+            None => {
+                let message = format!("'{}' to '{}' does not resolve", keyword, reference);
+                return vec![context.error(keyword, message)];
+            }
+        };
+
+        if context.refs >= MAX_REFS {
+            let message = format!("'{}' to '{}' loops without end", keyword, reference);
+            return vec![context.error(keyword, message)];
+        }
+
+        let mut scope = context.scope.clone();
+
+        if scope.as_ref().map(|scope| scope.resource.as_str()) != Some(target.resource.as_str()) {
+            scope = Some(Rc::new(Scope {
+                resource: target.resource.clone(),
+                parent: scope,
+            }));
+        }
+
+        let next = Context {
+            keyword_path: pointer::push(&context.keyword_path, keyword),
+            resource: target.resource,
+            pointer: target.pointer,
+            draft: target.draft,
+            scope,
+            refs: context.refs + 1,
+            ..context.clone()
+        };
+
+        self.evaluate(target.schema, instance, &next, evaluated)
+    }
+
+    // Evaluates a subschema in place, its evaluated properties and items count when it is valid
+    fn apply(
+        &self,
+        schema: &'s Json<'s>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let mut found = Evaluated::default();
+        let errors = self.evaluate(schema, instance, context, &mut found);
+
+        if errors.is_empty() {
+            evaluated.merge(found);
+        }
+
+        errors
+    }
+
+    fn keyword(
+        &self,
+        keyword: &str,
+        value: &'s Json<'s>,
+        schema: &'s parser::Map<std::borrow::Cow<'s, str>, Json<'s>>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        match (keyword, value) {
+            ("$dynamicRef" | "$recursiveRef", Json::String(reference)) => {
+                self.dynamic_reference(keyword, reference, instance, context, evaluated)
+            }
+            ("type", _) => self.type_keyword(value, instance, context),
+            ("enum", Json::Array(values)) => {
+                if values.iter().any(|value| equal(value, instance)) {
+                    return vec![];
+                }
+
+                let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+                let message = format!("Expected one of {}", values.join(", "));
+                vec![context.error(keyword, message)]
+            }
+            ("const", _) if !equal(value, instance) => {
+                vec![context.error(keyword, format!("Expected {}", value))]
+            }
+            ("allOf" | "anyOf" | "oneOf", Json::Array(schemas)) => {
+                self.combination(keyword, schemas, instance, context, evaluated)
+            }
+            ("not", _) => {
+                let errors = self.evaluate(
+                    value,
+                    instance,
+                    &context.keyword(keyword),
+                    &mut Evaluated::default(),
+                );
+
+                if !errors.is_empty() {
+                    return vec![];
+                }
+
+                let message = "Should not match the 'not' schema".to_string();
+                vec![context.error(keyword, message)]
+            }
+            ("if", _) => self.condition(value, schema, instance, context, evaluated),
+            ("format", Json::String(format)) if self.options.format_assertion => match instance {
+                Json::String(text) if check_format(format, text) == Some(false) => {
+                    let message = format!("Not a valid {}", format);
+                    vec![context.error(keyword, message)]
+                }
+                _ => vec![],
+            },
+            _ => match instance {
+                Json::Number(n) => self.number_keyword(keyword, value, schema, n, context),
+                Json::String(text) => self.string_keyword(keyword, value, text, context),
+                Json::Array(arr) => {
+                    self.array_keyword(keyword, value, schema, arr, context, evaluated)
+                }
+                Json::Object(_) => {
+                    self.object_keyword(keyword, value, schema, instance, context, evaluated)
+                }
+                _ => vec![],
+            },
+        }
+    }
+
+    fn type_keyword(
+        &self,
+        value: &Json,
+        instance: &Json,
+        context: &Context,
+    ) -> Vec<ValidationError> {
+        let names: Vec<&str> = match value {
+            Json::String(name) => vec![name],
+            Json::Array(names) => names
+                .iter()
+                .filter_map(|name| match name {
+                    Json::String(name) => Some(name.as_ref()),
+                    _ => None,
+                })
+                .collect(),
+            _ => return vec![],
+        };
+
+        if names
+            .iter()
+            .any(|name| has_type(instance, name, context.draft))
+        {
+            return vec![];
+        }
+
+        let types: Vec<&'static str> = names
+            .iter()
+            .filter_map(|name| TYPES.iter().find(|(other, _)| other == name))
+            .map(|(_, article)| *article)
+            .collect();
+
+        let mut error = context.error("type", type_message(&types, instance));
+        error.types = types;
+        vec![error]
+    }
+
+    fn combination(
+        &self,
+        keyword: &str,
+        schemas: &'s [Json<'s>],
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let results: Vec<Vec<ValidationError>> = schemas
+            .iter()
+            .enumerate()
+            .map(|(index, schema)| {
+                let context = context.subschema(keyword, &index.to_string());
+                self.apply(schema, instance, &context, evaluated)
+            })
+            .collect();
+
+        let valid: Vec<usize> = (0..results.len())
+            .filter(|index| results[*index].is_empty())
+            .collect();
+
+        match keyword {
+            "allOf" => results.into_iter().flatten().collect(),
+            _ if valid.is_empty() => {
+                // A single failing branch says more than the summary
+                if let [errors] = results.as_slice() {
+                    return errors.clone();
+                }
+
+                // Branches that only differ by type make one error listing the types
+                let types: Option<Vec<Vec<&'static str>>> = results
+                    .iter()
+                    .map(|errors| match errors.as_slice() {
+                        [error]
+                            if !error.types.is_empty()
+                                && error.instance_path == context.instance_path =>
+                        {
+                            Some(error.types.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+
+                let error = match types {
+                    Some(branches) => {
+                        let mut types: Vec<&'static str> = vec![];
+
+                        for article in branches.into_iter().flatten() {
+                            if !types.contains(&article) {
+                                types.push(article);
+                            }
+                        }
+
+                        let mut error = context.error(keyword, type_message(&types, instance));
+                        error.types = types;
+                        error
+                    }
+                    None => context.error(
+                        keyword,
+                        format!("Matches none of the '{}' schemas", keyword),
+                    ),
+                };
+
+                vec![error]
+            }
+            "oneOf" if valid.len() > 1 => {
+                let message = format!(
+                    "Matches the 'oneOf' schemas {} and {}, expected only one",
+                    valid[0], valid[1]
+                );
+                vec![context.error(keyword, message)]
+            }
+            _ => vec![],
+        }
+    }
+
+    // `if` with `then` and `else`, each only applies when the draft has it
+    fn condition(
+        &self,
+        condition: &'s Json<'s>,
+        schema: &'s parser::Map<std::borrow::Cow<'s, str>, Json<'s>>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let mut found = Evaluated::default();
+        let holds = self
+            .evaluate(condition, instance, &context.keyword("if"), &mut found)
+            .is_empty();
+
+        if holds {
+            evaluated.merge(found);
+        }
+
+        let branch = if holds { "then" } else { "else" };
+
+        match schema.get(branch) {
+            Some(branch_schema) => {
+                self.apply(branch_schema, instance, &context.keyword(branch), evaluated)
+            }
+            None => vec![],
+        }
+    }
+
+    fn number_keyword(
+        &self,
+        keyword: &str,
+        value: &Json,
+        schema: &parser::Map<std::borrow::Cow<str>, Json>,
+        n: &Number,
+        context: &Context,
+    ) -> Vec<ValidationError> {
+        let limit = match value {
+            Json::Number(limit) => limit,
+            // Draft 4 makes `maximum` and `minimum` exclusive with a boolean
+            _ => return vec![],
+        };
+
+        let draft4_exclusive = |name: &str| {
+            context.draft == Draft::Draft4 && matches!(schema.get(name), Some(Json::Bool(true)))
+        };
+
+        let (valid, message) = match keyword {
+            "multipleOf" => (
+                is_multiple(n, limit),
+                format!("Expected a multiple of {}", limit),
+            ),
+            "maximum" if draft4_exclusive("exclusiveMaximum") => {
+                (n < limit, format!("Expected less than {}", limit))
+            }
+            "maximum" => (n <= limit, format!("Expected at most {}", limit)),
+            "exclusiveMaximum" => (n < limit, format!("Expected less than {}", limit)),
+            "minimum" if draft4_exclusive("exclusiveMinimum") => {
+                (n > limit, format!("Expected more than {}", limit))
+            }
+            "minimum" => (n >= limit, format!("Expected at least {}", limit)),
+            "exclusiveMinimum" => (n > limit, format!("Expected more than {}", limit)),
+            _ => return vec![],
+        };
+
+        if valid {
+            return vec![];
+        }
+
+        vec![context.error(keyword, message)]
+    }
+
+    fn string_keyword(
+        &self,
+        keyword: &str,
+        value: &Json,
+        text: &str,
+        context: &Context,
+    ) -> Vec<ValidationError> {
+        let length = || text.chars().count();
+
+        let message = match (keyword, value) {
+            ("maxLength", Json::Number(limit)) if exceeds(length(), limit) => {
+                format!("Expected at most {} characters, found {}", limit, length())
+            }
+            ("minLength", Json::Number(limit)) if falls_short(length(), limit) => {
+                format!("Expected at least {} characters, found {}", limit, length())
+            }
+            // A pattern that is not a valid regular expression is the schema's problem
+            ("pattern", Json::String(pattern)) if self.regex(pattern, text) == Some(false) => {
+                format!("Does not match the pattern '{}'", pattern)
+            }
+            _ => return vec![],
+        };
+
+        vec![context.error(keyword, message)]
+    }
+
+    fn array_keyword(
+        &self,
+        keyword: &str,
+        value: &'s Json<'s>,
+        schema: &'s parser::Map<std::borrow::Cow<'s, str>, Json<'s>>,
+        arr: &[Json],
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        // Items from this index on are left to `items`, or `additionalItems` before 2020-12
+        let prefix = |keyword: &str| match schema.get(keyword) {
+            Some(Json::Array(schemas)) => schemas.len(),
+            _ => 0,
+        };
+
+        match (keyword, value) {
+            ("maxItems", Json::Number(limit)) if exceeds(arr.len(), limit) => {
+                let message = format!("Expected at most {} items, found {}", limit, arr.len());
+                vec![context.error(keyword, message)]
+            }
+            ("minItems", Json::Number(limit)) if falls_short(arr.len(), limit) => {
+                let message = format!("Expected at least {} items, found {}", limit, arr.len());
+                vec![context.error(keyword, message)]
+            }
+            ("uniqueItems", Json::Bool(true)) => {
+                for (i, a) in arr.iter().enumerate() {
+                    if let Some(j) = (i + 1..arr.len()).find(|j| equal(a, &arr[*j])) {
+                        let message = format!("Items {} and {} are equal", i, j);
+                        return vec![context.error(keyword, message)];
+                    }
+                }
+
+                vec![]
+            }
+            ("prefixItems", Json::Array(schemas)) => {
+                self.items(keyword, schemas, arr, 0, context, evaluated)
+            }
+            // Before 2020-12 an array of schemas applies to the items at the same index
+            ("items", Json::Array(schemas)) if context.draft < Draft::Draft2020_12 => {
+                self.items(keyword, schemas, arr, 0, context, evaluated)
+            }
+            ("items", _) => {
+                let start = match context.draft {
+                    Draft::Draft2020_12 => prefix("prefixItems"),
+                    _ => 0,
+                };
+                self.rest(keyword, value, arr, start, context, evaluated)
+            }
+            ("additionalItems", _) if matches!(schema.get("items"), Some(Json::Array(_))) => {
+                self.rest(keyword, value, arr, prefix("items"), context, evaluated)
+            }
+            ("contains", _) => self.contains(value, schema, arr, context, evaluated),
+            _ => vec![],
+        }
+    }
+
+    // Each schema of the array applies to the item at its index
+    fn items(
+        &self,
+        keyword: &str,
+        schemas: &'s [Json<'s>],
+        arr: &[Json],
+        start: usize,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        for (index, (schema, item)) in schemas.iter().zip(arr).enumerate() {
+            let index = start + index;
+            let item_context = context.subschema(keyword, &index.to_string()).item(index);
+
+            errors.extend(self.evaluate(schema, item, &item_context, &mut Evaluated::default()));
+            evaluated.items.insert(index);
+        }
+
+        errors
+    }
+
+    // A schema that applies to every item from `start` on
+    fn rest(
+        &self,
+        keyword: &str,
+        schema: &'s Json<'s>,
+        arr: &[Json],
+        start: usize,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        let keyword_context = context.keyword(keyword);
+
+        for (index, item) in arr.iter().enumerate().skip(start) {
+            let item_context = keyword_context.item(index);
+            errors.extend(self.evaluate(schema, item, &item_context, &mut Evaluated::default()));
+        }
+
+        evaluated.all_items = true;
+        errors
+    }
+
+    fn contains(
+        &self,
+        schema: &'s Json<'s>,
+        parent: &parser::Map<std::borrow::Cow<str>, Json>,
+        arr: &[Json],
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let keyword_context = context.keyword("contains");
+        let matching: Vec<usize> = (0..arr.len())
+            .filter(|index| {
+                let item_context = keyword_context.item(*index);
+                self.evaluate(
+                    schema,
+                    &arr[*index],
+                    &item_context,
+                    &mut Evaluated::default(),
+                )
+                .is_empty()
+            })
+            .collect();
+
+        let bound = |keyword: &str| match (context.draft, parent.get(keyword)) {
+            (Draft::Draft2019_09 | Draft::Draft2020_12, Some(Json::Number(n))) => Some(n),
+            _ => None,
+        };
+
+        if let Some(min) = bound("minContains") {
+            if falls_short(matching.len(), min) {
+                let message = format!(
+                    "Expected at least {} items to match 'contains', found {}",
+                    min,
+                    matching.len()
+                );
+                return vec![context.error("minContains", message)];
+            }
+        } else if matching.is_empty() {
+            let message = "No item matches the 'contains' schema".to_string();
+            return vec![context.error("contains", message)];
+        }
+
+        if let Some(max) = bound("maxContains") {
+            if exceeds(matching.len(), max) {
+                let message = format!(
+                    "Expected at most {} items to match 'contains', found {}",
+                    max,
+                    matching.len()
+                );
+                return vec![context.error("maxContains", message)];
+            }
+        }
+
+        evaluated.items.extend(matching);
+        vec![]
+    }
+
+    fn object_keyword(
+        &self,
+        keyword: &str,
+        value: &'s Json<'s>,
+        schema: &'s parser::Map<std::borrow::Cow<'s, str>, Json<'s>>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let obj = match instance {
+            Json::Object(obj) => obj,
+            _ => return vec![],
+        };
+
+        let mut errors = vec![];
+
+        match (keyword, value) {
+            ("maxProperties", Json::Number(limit)) if exceeds(obj.len(), limit) => {
+                let message = format!("Expected at most {} properties, found {}", limit, obj.len());
+                errors.push(context.error(keyword, message));
+            }
+            ("minProperties", Json::Number(limit)) if falls_short(obj.len(), limit) => {
+                let message = format!(
+                    "Expected at least {} properties, found {}",
+                    limit,
+                    obj.len()
+                );
+                errors.push(context.error(keyword, message));
+            }
+            ("required", Json::Array(names)) => {
+                for name in names {
+                    match name {
+                        Json::String(name) if !obj.contains_key(name.as_ref()) => {
+                            let message = format!("Missing required property '{}'", name);
+                            errors.push(context.error(keyword, message));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ("properties", Json::Object(schemas)) => {
+                for (name, item) in obj {
+                    if let Some(schema) = schemas.get(name.as_ref()) {
+                        let item_context = context.subschema(keyword, name).property(name);
+                        errors.extend(self.evaluate(
+                            schema,
+                            item,
+                            &item_context,
+                            &mut Evaluated::default(),
+                        ));
+                        evaluated.properties.insert(name.to_string());
+                    }
+                }
+            }
+            ("patternProperties", Json::Object(schemas)) => {
+                for (pattern, schema) in schemas {
+                    for (name, item) in obj {
+                        if self.regex(pattern, name) == Some(true) {
+                            let item_context = context.subschema(keyword, pattern).property(name);
+                            errors.extend(self.evaluate(
+                                schema,
+                                item,
+                                &item_context,
+                                &mut Evaluated::default(),
+                            ));
+                            evaluated.properties.insert(name.to_string());
+                        }
+                    }
+                }
+            }
+            ("additionalProperties", _) => {
+                let keyword_context = context.keyword(keyword);
+
+                for (name, item) in obj {
+                    let listed = matches!(schema.get("properties"), Some(Json::Object(schemas)) if schemas.contains_key(name.as_ref()));
+                    let matched = match schema.get("patternProperties") {
+                        Some(Json::Object(schemas)) => schemas
+                            .keys()
+                            .any(|pattern| self.regex(pattern, name) == Some(true)),
+                        _ => false,
+                    };
+
+                    if !listed && !matched {
+                        errors.extend(self.evaluate(
+                            value,
+                            item,
+                            &keyword_context.property(name),
+                            &mut Evaluated::default(),
+                        ));
+                        evaluated.properties.insert(name.to_string());
+                    }
+                }
+            }
+            ("propertyNames", _) => {
+                let keyword_context = context.keyword(keyword);
+
+                for name in obj.keys() {
+                    let name_context = keyword_context.property(name);
+                    let name_value = Json::String(name.clone());
+                    errors.extend(self.evaluate(
+                        value,
+                        &name_value,
+                        &name_context,
+                        &mut Evaluated::default(),
+                    ));
+                }
+            }
+            ("dependentRequired", Json::Object(dependencies)) => {
+                errors.extend(self.required_by(keyword, dependencies, obj, context));
+            }
+            ("dependentSchemas", Json::Object(dependencies)) => {
+                for (name, schema) in dependencies {
+                    if obj.contains_key(name.as_ref()) {
+                        let context = context.subschema(keyword, name);
+                        errors.extend(self.apply(schema, instance, &context, evaluated));
+                    }
+                }
+            }
+            // Draft 4 to 7 take property names or a schema for each property
+            ("dependencies", Json::Object(dependencies)) => {
+                let (names, schemas): (Vec<_>, Vec<_>) = dependencies
+                    .iter()
+                    .partition(|(_, dependency)| matches!(dependency, Json::Array(_)));
+
+                let names = names
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                errors.extend(self.required_by(keyword, &names, obj, context));
+
+                for (name, schema) in schemas {
+                    if obj.contains_key(name.as_ref()) {
+                        let context = context.subschema(keyword, name);
+                        errors.extend(self.apply(schema, instance, &context, evaluated));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        errors
+    }
+
+    // `dependentRequired`: the properties each present property needs
+    fn required_by(
+        &self,
+        keyword: &str,
+        dependencies: &parser::Map<std::borrow::Cow<str>, Json>,
+        obj: &parser::Map<std::borrow::Cow<str>, Json>,
+        context: &Context,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        for (name, required) in dependencies {
+            if !obj.contains_key(name.as_ref()) {
+                continue;
+            }
+
+            if let Json::Array(required) = required {
+                for other in required {
+                    if let Json::String(other) = other {
+                        if !obj.contains_key(other.as_ref()) {
+                            let message = format!("Property '{}' requires '{}'", name, other);
+                            errors.push(context.subschema(keyword, name).failure(message));
+                        }
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    fn unevaluated(
+        &self,
+        schema: &'s parser::Map<std::borrow::Cow<'s, str>, Json<'s>>,
+        instance: &Json,
+        context: &Context,
+        evaluated: &mut Evaluated,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        match (instance, schema.get("unevaluatedProperties")) {
+            (Json::Object(obj), Some(value)) => {
+                let keyword_context = context.keyword("unevaluatedProperties");
+
+                for (name, item) in obj {
+                    if !evaluated.properties.contains(name.as_ref()) {
+                        errors.extend(self.evaluate(
+                            value,
+                            item,
+                            &keyword_context.property(name),
+                            &mut Evaluated::default(),
+                        ));
+                    }
+                }
+
+                evaluated
+                    .properties
+                    .extend(obj.keys().map(|name| name.to_string()));
+            }
+            (Json::Array(arr), _) => {
+                if let Some(value) = schema.get("unevaluatedItems") {
+                    let keyword_context = context.keyword("unevaluatedItems");
+
+                    if !evaluated.all_items {
+                        for (index, item) in arr.iter().enumerate() {
+                            if !evaluated.items.contains(&index) {
+                                errors.extend(self.evaluate(
+                                    value,
+                                    item,
+                                    &keyword_context.item(index),
+                                    &mut Evaluated::default(),
+                                ));
+                            }
+                        }
+                    }
+
+                    evaluated.all_items = true;
+                }
+            }
+            _ => {}
+        }
+
+        errors
+    }
+}
+
+// Keywords whose value is a schema
+const SCHEMA_KEYWORDS: [&str; 13] = [
+    "additionalItems",
+    "additionalProperties",
+    "contains",
+    "contentSchema",
+    "else",
+    "if",
+    "items",
+    "not",
+    "propertyNames",
+    "then",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+    "$ref",
+];
+
+// Keywords whose value is an array of schemas
+const ARRAY_KEYWORDS: [&str; 5] = ["allOf", "anyOf", "items", "oneOf", "prefixItems"];
+
+// Keywords whose value is an object of schemas
+const MAP_KEYWORDS: [&str; 6] = [
+    "$defs",
+    "definitions",
+    "dependencies",
+    "dependentSchemas",
+    "patternProperties",
+    "properties",
+];
+
+// The types with how messages name them
+const TYPES: [(&str, &str); 7] = [
+    ("null", "null"),
+    ("boolean", "a boolean"),
+    ("object", "an object"),
+    ("array", "an array"),
+    ("number", "a number"),
+    ("integer", "an integer"),
+    ("string", "a string"),
+];
+
+fn type_message(types: &[&str], instance: &Json) -> String {
+    let found = match instance {
+        Json::Null => "null",
+        Json::Bool(_) => "a boolean",
+        Json::Number(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Array(_) => "an array",
+        Json::Object(_) | Json::Raw(_) => "an object",
+        Json::Error => "an error",
+    };
+
+    let expected = match types.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => "nothing".to_string(),
+    };
+
+    format!("Expected {}, found {}", expected, found)
+}
+
+// Draft 4 counts only numbers written without a fraction as integers, later drafts any number
+// with a zero fraction
+fn has_type(instance: &Json, name: &str, draft: Draft) -> bool {
+    match (name, instance) {
+        ("null", Json::Null) | ("boolean", Json::Bool(_)) | ("number", Json::Number(_)) => true,
+        ("string", Json::String(_)) | ("array", Json::Array(_)) => true,
+        ("object", Json::Object(_)) => true,
+        ("integer", Json::Number(n)) => match n.parsed() {
+            Number::Float(f) => draft > Draft::Draft4 && f.is_finite() && f.fract() == 0.0,
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+// Like `enum` and `const` compare: numbers by value, objects without member order
+fn equal(a: &Json, b: &Json) -> bool {
+    semantic_eq(a, b, EqOptions::default())
+}
+
+fn exceeds(count: usize, limit: &Number) -> bool {
+    count as f64 > limit.as_f64()
+}
+
+fn falls_short(count: usize, limit: &Number) -> bool {
+    (count as f64) < limit.as_f64()
+}
+
+// Integers exactly, other numbers within the rounding of dividing floats
+fn is_multiple(n: &Number, divisor: &Number) -> bool {
+    if let (Some(n), Some(divisor)) = (n.as_i64(), divisor.as_i64()) {
+        return divisor == 0 || n % divisor == 0;
+    }
+
+    let quotient = n.as_f64() / divisor.as_f64();
+
+    quotient.is_finite()
+        && (quotient - quotient.round()).abs() <= quotient.abs().max(1.0) * f64::EPSILON * 4.0
+}
+
+// The parts of a URI reference from RFC 3986, section 3
+struct UriParts<'u> {
+    scheme: Option<&'u str>,
+    authority: Option<&'u str>,
+    path: &'u str,
+    query: Option<&'u str>,
+    fragment: Option<&'u str>,
+}
+
+fn parse_uri(uri: &str) -> UriParts<'_> {
+    let (rest, fragment) = match uri.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (uri, None),
+    };
+
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (rest, None),
+    };
+
+    let (scheme, rest) = match rest.split_once(':') {
+        Some((scheme, rest))
+            if scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) =>
+        {
+            (Some(scheme), rest)
+        }
+        _ => (None, rest),
+    };
+
+    let (authority, path) = match rest.strip_prefix("//") {
+        Some(rest) => {
+            let end = rest.find('/').unwrap_or(rest.len());
+            (Some(&rest[..end]), &rest[end..])
+        }
+        None => (None, rest),
+    };
+
+    UriParts {
+        scheme,
+        authority,
+        path,
+        query,
+        fragment,
+    }
+}
+
+// RFC 3986, section 5.2.4
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = vec![];
+    let segments: Vec<&str> = path.split('/').collect();
+
+    for (index, segment) in segments.iter().enumerate() {
+        let last = index == segments.len() - 1;
+
+        match *segment {
+            "." if last => output.push(""),
+            "." => {}
+            ".." => {
+                if output.len() > 1 || output.first().is_some_and(|first| !first.is_empty()) {
+                    output.pop();
+                }
+
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+
+    let joined = output.join("/");
+
+    if path.starts_with('/') && !joined.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+// A reference resolved against a base URI like RFC 3986, section 5.2.2 does
+fn resolve(base: &str, reference: &str) -> String {
+    let r = parse_uri(reference);
+    let b = parse_uri(base);
+
+    let (scheme, authority, path, query) = if r.scheme.is_some() {
+        (r.scheme, r.authority, remove_dot_segments(r.path), r.query)
+    } else if r.authority.is_some() {
+        (b.scheme, r.authority, remove_dot_segments(r.path), r.query)
+    } else if r.path.is_empty() {
+        (
+            b.scheme,
+            b.authority,
+            b.path.to_string(),
+            r.query.or(b.query),
+        )
+    } else if r.path.starts_with('/') {
+        (b.scheme, b.authority, remove_dot_segments(r.path), r.query)
+    } else {
+        let merged = if b.authority.is_some() && b.path.is_empty() {
+            format!("/{}", r.path)
+        } else {
+            match b.path.rfind('/') {
+                Some(end) => format!("{}{}", &b.path[..=end], r.path),
+                None => r.path.to_string(),
+            }
+        };
+        (b.scheme, b.authority, remove_dot_segments(&merged), r.query)
+    };
+
+    let mut uri = String::new();
+
+    if let Some(scheme) = scheme {
+        uri.push_str(scheme);
+        uri.push(':');
+    }
+
+    if let Some(authority) = authority {
+        uri.push_str("//");
+        uri.push_str(authority);
+    }
+
+    uri.push_str(&path);
+
+    if let Some(query) = query {
+        uri.push('?');
+        uri.push_str(query);
+    }
+
+    if let Some(fragment) = r.fragment {
+        uri.push('#');
+        uri.push_str(fragment);
+    }
+
+    uri
+}
+
+fn split_fragment(uri: &str) -> (&str, &str) {
+    uri.split_once('#').unwrap_or((uri, ""))
+}
+
+// `%XX` escapes in a URI fragment, invalid ones are kept as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn digits(text: &str, count: usize) -> Option<u32> {
+    (text.len() == count && text.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| text.parse().ok())
+        .flatten()
+}
+
+// `YYYY-MM-DD` with a day that exists in that month
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+
+    let (year, month, day) = match parts.as_slice() {
+        [year, month, day] => match (digits(year, 4), digits(month, 2), digits(day, 2)) {
+            (Some(year), Some(month), Some(day)) => (year, month, day),
+            _ => return false,
+        },
+        _ => return false,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    (1..=days).contains(&day)
+}
+
+// `HH:MM:SS` with an optional fraction and a required offset. A leap second has to be the last
+// second of a day in UTC
+fn is_time(text: &str) -> bool {
+    let upper = text.to_ascii_uppercase();
+
+    let (time, offset) = if let Some(time) = upper.strip_suffix('Z') {
+        (time, 0)
+    } else {
+        let sign = match upper.rfind(['+', '-']) {
+            Some(sign) => sign,
+            None => return false,
+        };
+
+        let (hours, minutes) = match upper[sign + 1..].split_once(':') {
+            Some((hours, minutes)) => match (digits(hours, 2), digits(minutes, 2)) {
+                (Some(hours), Some(minutes)) if hours < 24 && minutes < 60 => (hours, minutes),
+                _ => return false,
+            },
+            None => return false,
+        };
+
+        let offset = (hours * 60 + minutes) as i32;
+        let offset = if &upper[sign..=sign] == "-" {
+            -offset
+        } else {
+            offset
+        };
+
+        (&upper[..sign], offset)
+    };
+
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+
+    if fraction.is_some_and(|fraction| {
+        fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit())
+    }) {
+        return false;
+    }
+
+    let parts: Vec<Option<u32>> = time.split(':').map(|part| digits(part, 2)).collect();
+
+    match parts.as_slice() {
+        [Some(hours), Some(minutes), Some(seconds)] if *hours < 24 && *minutes < 60 => {
+            let utc = ((*hours * 60 + *minutes) as i32 - offset).rem_euclid(24 * 60);
+            *seconds < 60 || (*seconds == 60 && utc == 24 * 60 - 1)
+        }
+        _ => false,
+    }
+}
+
+// ISO 8601 durations like `P1Y2M3DT4H5M6S` or `P2W`
+fn is_duration(text: &str) -> bool {
+    let rest = match text.strip_prefix('P') {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    if let Some(weeks) = rest.strip_suffix('W') {
+        return !weeks.is_empty() && weeks.bytes().all(|b| b.is_ascii_digit());
+    }
+
+    let (date, time) = match rest.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (rest, None),
+    };
+
+    // Each unit once, in order, with a number before it
+    let units = |part: &str, order: &str| {
+        let mut rest = order;
+        let mut number = false;
+        let mut count = 0;
+
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                number = true;
+            } else {
+                match rest.find(c) {
+                    Some(position) if number => {
+                        rest = &rest[position + 1..];
+                        number = false;
+                        count += 1;
+                    }
+                    _ => return None,
+                }
+            }
+        }
+
+        (!number).then_some(count)
+    };
+
+    match (units(date, "YMD"), time.map(|time| units(time, "HMS"))) {
+        (Some(_), Some(Some(count))) => count > 0,
+        (Some(count), None) => count > 0,
+        _ => false,
+    }
+}
+
+fn is_hostname(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= 253
+        && text.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+}
+
+fn is_email(text: &str) -> bool {
+    let (local, domain) = match text.rsplit_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let local_valid = if local.starts_with('"') && local.ends_with('"') && local.len() > 1 {
+        true
+    } else {
+        !local.is_empty()
+            && !local.starts_with('.')
+            && !local.ends_with('.')
+            && !local.contains("..")
+            && local
+                .chars()
+                .all(|c| c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c))
+    };
+
+    let domain_valid = match domain.strip_prefix('[').and_then(|ip| ip.strip_suffix(']')) {
+        Some(ip) => match ip.strip_prefix("IPv6:") {
+            Some(ip) => ip.parse::<Ipv6Addr>().is_ok(),
+            None => ip.parse::<Ipv4Addr>().is_ok(),
+        },
+        None => is_hostname(domain),
+    };
+
+    local_valid && domain_valid
+}
+
+// The characters RFC 3986 allows in a URI, with `%XX` escapes, and those of IRIs when `iri`
+fn is_uri_text(text: &str, iri: bool) -> bool {
+    let bytes = text.as_bytes();
+
+    text.char_indices().all(|(i, c)| match c {
+        '%' => {
+            bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+                && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit)
+        }
+        c if c.is_ascii_alphanumeric() => true,
+        c if !c.is_ascii() => iri && !c.is_whitespace() && !c.is_control(),
+        c => "-._~:/?#[]@!$&'()*+,;=".contains(c),
+    }) && text.matches('#').count() <= 1
+}
+
+fn is_uri(text: &str, iri: bool) -> bool {
+    let parts = parse_uri(text);
+    parts.scheme.is_some()
+        && is_uri_text(text, iri)
+        && parts.authority.is_none_or(|authority| {
+            !authority.contains(['[', ']']) || authority.contains('[') && authority.contains(']')
+        })
+}
+
+fn is_json_pointer(text: &str) -> bool {
+    (text.is_empty() || text.starts_with('/'))
+        && text
+            .split('~')
+            .skip(1)
+            .all(|part| part.starts_with(['0', '1']))
+}
+
+fn is_relative_json_pointer(text: &str) -> bool {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, rest) = text.split_at(end);
+
+    !number.is_empty()
+        && (number == "0" || !number.starts_with('0'))
+        && (rest == "#" || is_json_pointer(rest))
+}
+
+fn is_uuid(text: &str) -> bool {
+    let groups: Vec<&str> = text.split('-').collect();
+
+    groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|group| group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+// Whether a string is valid for a `format`, `None` for the formats that are not checked
+pub fn check_format(format: &str, text: &str) -> Option<bool> {
+    Some(match format {
+        "date" => is_date(text),
+        "time" => is_time(text),
+        "date-time" => match text.split_once(['T', 't']) {
+            Some((date, time)) => is_date(date) && is_time(time),
+            None => false,
+        },
+        "duration" => is_duration(text),
+        "email" | "idn-email" => is_email(text),
+        "hostname" | "idn-hostname" => is_hostname(text),
+        "ipv4" => text.parse::<Ipv4Addr>().is_ok(),
+        "ipv6" => text.parse::<Ipv6Addr>().is_ok(),
+        "uri" => is_uri(text, false),
+        "iri" => is_uri(text, true),
+        "uri-reference" => is_uri_text(text, false),
+        "iri-reference" => is_uri_text(text, true),
+        "uuid" => is_uuid(text),
+        "json-pointer" => is_json_pointer(text),
+        "relative-json-pointer" => is_relative_json_pointer(text),
+        "regex" => Regex::ecma(text).is_some(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sorted, the keywords of a schema are evaluated in the order of its map
+    fn errors(schema: &str, instance: &str) -> Vec<String> {
+        let schema = parser::parse_str(schema).unwrap();
+        let instance = parser::parse_str(instance).unwrap();
+
+        let mut errors: Vec<String> = Validator::new(&schema, Options::default())
+            .validate(&instance)
+            .into_iter()
+            .map(|error| format!("{} {}", error.instance_path, error.message))
+            .collect();
+        errors.sort();
+        errors
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = r##"{
+            "type": "object",
+            "required": ["id", "tags"],
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}, "uniqueItems": true},
+                "size": {"enum": ["s", "m", 3]}
+            },
+            "additionalProperties": false,
+            "$defs": {"tag": {"type": "string", "pattern": "^[a-z]+$", "maxLength": 5}}
+        }"##;
+
+        assert!(errors(schema, r#"{"id": 1, "tags": ["a", "b"], "size": 3.0}"#).is_empty());
+        assert_eq!(
+            errors(
+                schema,
+                r#"{"id": 0.5, "tags": ["a", "B", "abcdef", "a"], "size": "l", "x": 1}"#
+            ),
+            [
+                "/id Expected an integer, found a number",
+                "/id Expected at least 1",
+                "/size Expected one of \"s\", \"m\", 3",
+                "/tags Items 0 and 3 are equal",
+                "/tags/1 Does not match the pattern '^[a-z]+$'",
+                "/tags/2 Expected at most 5 characters, found 6",
+                "/x Property 'x' is not allowed",
+            ]
+        );
+        assert_eq!(
+            errors(schema, "[]"),
+            [" Expected an object, found an array"]
+        );
+        assert_eq!(
+            errors(schema, "{}"),
+            [
+                " Missing required property 'id'",
+                " Missing required property 'tags'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locations() {
+        let schema = parser::parse_str(
+            r##"{"properties": {"a": {"$ref": "#/definitions/a"}}, "definitions": {"a": {"type": "string"}}}"##,
+        )
+        .unwrap();
+        let instance = parser::parse_str(r#"{"a": 1}"#).unwrap();
+        let errors = Validator::new(&schema, Options::default()).validate(&instance);
+
+        assert_eq!(errors[0].instance_path, "/a");
+        assert_eq!(errors[0].keyword_path, "/properties/a/$ref/type");
+        assert_eq!(errors[0].schema_location, "#/definitions/a/type");
+    }
+
+    #[test]
+    fn test_combinations() {
+        let cases = [
+            (
+                r#"{"anyOf": [{"type": "string"}, {"type": "null"}]}"#,
+                "1",
+                vec!["Expected a string or null, found a number"],
+            ),
+            (
+                r#"{"anyOf": [{"type": "string"}, {"minimum": 2}]}"#,
+                "1",
+                vec!["Matches none of the 'anyOf' schemas"],
+            ),
+            (
+                r#"{"oneOf": [{"type": "integer"}, {"minimum": 0}]}"#,
+                "1",
+                vec!["Matches the 'oneOf' schemas 0 and 1, expected only one"],
+            ),
+            (
+                r#"{"oneOf": [{"type": "integer"}, {"minimum": 0}]}"#,
+                "-1",
+                vec![],
+            ),
+            (
+                r#"{"allOf": [{"type": "integer"}, {"minimum": 0}]}"#,
+                "-1.5",
+                vec!["Expected an integer, found a number", "Expected at least 0"],
+            ),
+            (
+                r#"{"not": {"type": "string"}}"#,
+                "\"a\"",
+                vec!["Should not match the 'not' schema"],
+            ),
+            (
+                r#"{"if": {"minimum": 10}, "then": {"multipleOf": 5}, "else": {"const": 1}}"#,
+                "12",
+                vec!["Expected a multiple of 5"],
+            ),
+            (
+                r#"{"if": {"minimum": 10}, "then": {"multipleOf": 5}, "else": {"const": 1}}"#,
+                "2",
+                vec!["Expected 1"],
+            ),
+        ];
+
+        for (schema, instance, expected) in cases {
+            // All at the root, whose pointer is empty
+            let errors = errors(schema, instance);
+            let messages: Vec<&str> = errors.iter().map(|error| error.trim_start()).collect();
+            assert_eq!(messages, expected, "{} {}", schema, instance);
+        }
+    }
+
+    #[test]
+    fn test_drafts() {
+        let cases = [
+            // Draft 4 has boolean `exclusiveMaximum` and no `const`
+            (
+                r#"{"$schema": "http://json-schema.org/draft-04/schema#", "maximum": 3, "exclusiveMaximum": true, "const": 1}"#,
+                "3",
+                1,
+            ),
+            (
+                r#"{"$schema": "http://json-schema.org/draft-04/schema#", "type": "integer"}"#,
+                "1.0",
+                1,
+            ),
+            (
+                r#"{"$schema": "http://json-schema.org/draft-06/schema#", "type": "integer"}"#,
+                "1.0",
+                0,
+            ),
+            (
+                r#"{"$schema": "http://json-schema.org/draft-06/schema#", "exclusiveMaximum": 3}"#,
+                "3",
+                1,
+            ),
+            // `$ref` ignores the keywords next to it before 2019-09
+            (
+                r##"{"$schema": "http://json-schema.org/draft-07/schema#", "definitions": {"a": {}}, "$ref": "#/definitions/a", "type": "string"}"##,
+                "1",
+                0,
+            ),
+            (
+                r##"{"$schema": "https://json-schema.org/draft/2019-09/schema", "$defs": {"a": {}}, "$ref": "#/$defs/a", "type": "string"}"##,
+                "1",
+                1,
+            ),
+            // Array form `items` and `additionalItems` until 2019-09, `prefixItems` since 2020-12
+            (
+                r#"{"$schema": "https://json-schema.org/draft/2019-09/schema", "items": [{"type": "string"}], "additionalItems": false}"#,
+                "[\"a\", 1]",
+                1,
+            ),
+            (
+                r#"{"prefixItems": [{"type": "string"}], "items": false}"#,
+                "[\"a\", 1]",
+                1,
+            ),
+            (
+                r#"{"prefixItems": [{"type": "string"}], "items": false}"#,
+                "[\"a\"]",
+                0,
+            ),
+            (
+                r#"{"$schema": "http://json-schema.org/draft-07/schema#", "dependencies": {"a": ["b"]}}"#,
+                r#"{"a": 1}"#,
+                1,
+            ),
+            (r#"{"dependencies": {"a": ["b"]}}"#, r#"{"a": 1}"#, 0),
+            (r#"{"dependentRequired": {"a": ["b"]}}"#, r#"{"a": 1}"#, 1),
+        ];
+
+        for (schema, instance, count) in cases {
+            assert_eq!(
+                errors(schema, instance).len(),
+                count,
+                "{} {}",
+                schema,
+                instance
+            );
+        }
+    }
+
+    #[test]
+    fn test_unevaluated() {
+        let schema = r#"{
+            "allOf": [{"properties": {"a": true}}],
+            "anyOf": [{"properties": {"b": true}}, {"properties": {"c": true}}],
+            "if": {"properties": {"d": {"const": 1}}},
+            "then": {"properties": {"e": true}},
+            "unevaluatedProperties": false
+        }"#;
+
+        assert!(errors(schema, r#"{"a": 1, "b": 1, "c": 1, "d": 1, "e": 1}"#).is_empty());
+        assert_eq!(
+            errors(schema, r#"{"a": 1, "d": 2, "e": 1}"#),
+            [
+                "/d Property 'd' is not allowed",
+                "/e Property 'e' is not allowed",
+            ]
+        );
+
+        let items =
+            r#"{"prefixItems": [true], "contains": {"type": "string"}, "unevaluatedItems": false}"#;
+        assert!(errors(items, r#"[1, "a", "b"]"#).is_empty());
+        assert_eq!(
+            errors(items, r#"[1, "a", 2]"#),
+            ["/2 Item 2 is not allowed"]
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        // A recursive tree, and a `$dynamicRef` extension point taken from the outermost schema
+        let tree = r##"{
+            "$id": "https://example.com/tree",
+            "$dynamicAnchor": "node",
+            "type": "object",
+            "properties": {"children": {"type": "array", "items": {"$dynamicRef": "#node"}}}
+        }"##;
+        assert!(errors(tree, r#"{"children": [{"children": [{}]}]}"#).is_empty());
+
+        let strict = r##"{
+            "$id": "https://example.com/strict-tree",
+            "$dynamicAnchor": "node",
+            "$ref": "tree",
+            "unevaluatedProperties": false,
+            "$defs": {"tree": {
+                "$id": "tree",
+                "$dynamicAnchor": "node",
+                "type": "object",
+                "properties": {"children": {"type": "array", "items": {"$dynamicRef": "#node"}}}
+            }}
+        }"##;
+        assert_eq!(
+            errors(strict, r#"{"children": [{"daat": 1}]}"#),
+            ["/children/0/daat Property 'daat' is not allowed"]
+        );
+
+        let anchors =
+            r##"{"$ref": "#item", "$defs": {"a": {"$anchor": "item", "type": "string"}}}"##;
+        assert!(errors(anchors, "\"a\"").is_empty());
+        assert_eq!(errors(anchors, "1").len(), 1);
+
+        assert_eq!(
+            errors(r##"{"$ref": "#/$defs/missing"}"##, "1"),
+            [" '$ref' to '#/$defs/missing' does not resolve"]
+        );
+        assert_eq!(
+            errors(r##"{"$ref": "#"}"##, "1"),
+            [" '$ref' to '#' loops without end"]
+        );
+
+        // References to the built in meta-schemas
+        let meta = r#"{"$ref": "http://json-schema.org/draft-07/schema#"}"#;
+        assert!(errors(meta, r#"{"type": "string"}"#).is_empty());
+        assert_eq!(errors(meta, r#"{"type": 1}"#).len(), 1);
+    }
+
+    #[test]
+    fn test_deep() {
+        let check = || {
+            let depth = parser::DEFAULT_MAX_DEPTH;
+            let instance = format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+            let schema = r##"{"$ref": "#/$defs/node", "$defs": {"node": {"type": "array", "items": {"$ref": "#/$defs/node"}}}}"##;
+
+            assert_eq!(
+                errors(schema, &instance),
+                [format!(
+                    "{} Expected an array, found a number",
+                    "/0".repeat(depth)
+                )]
+            );
+        };
+
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(check)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_meta_schemas() {
+        for draft in DRAFTS {
+            let meta_schema = meta_schema(draft);
+            let validator = Validator::new(meta_schema, Options::default());
+
+            assert_eq!(validator.draft(), draft);
+            assert_eq!(validator.validate(meta_schema), [], "{:?}", draft);
+            assert!(!validator.is_valid(&parser::parse_str(r#"{"minLength": -1}"#).unwrap()));
+        }
+
+        let items = parser::parse_str(r#"{"items": [{}]}"#).unwrap();
+        assert!(
+            !Validator::new(meta_schema(Draft::Draft2020_12), Options::default()).is_valid(&items)
+        );
+        assert!(
+            Validator::new(meta_schema(Draft::Draft2019_09), Options::default()).is_valid(&items)
+        );
+    }
+
+    #[test]
+    fn test_formats() {
+        let cases = [
+            ("date", "2020-02-29", true),
+            ("date", "2021-02-29", false),
+            ("date-time", "2020-01-01T23:59:60Z", true),
+            ("date-time", "2020-01-01T12:00:60Z", false),
+            ("date-time", "2020-01-01t10:00:00.5+02:00", true),
+            ("time", "10:00:00", false),
+            ("duration", "P1Y2M3DT4H", true),
+            ("duration", "PT", false),
+            ("duration", "P2W", true),
+            ("email", "joe.bloggs@example.com", true),
+            ("email", "joe..bloggs@example.com", false),
+            ("hostname", "-a.example", false),
+            ("ipv4", "192.168.0.1", true),
+            ("ipv4", "192.168.0.01", false),
+            ("ipv6", "::1", true),
+            ("uri", "https://example.com/a?b#c", true),
+            ("uri", "//example.com", false),
+            ("uri-reference", "../a b", false),
+            ("uuid", "2eb8aa08-aa98-11ea-b4aa-73b441d16380", true),
+            ("json-pointer", "/a~2", false),
+            ("relative-json-pointer", "1/a", true),
+            ("regex", "^a(b", false),
+        ];
+
+        for (format, text, valid) in cases {
+            assert_eq!(
+                check_format(format, text),
+                Some(valid),
+                "{} {}",
+                format,
+                text
+            );
+        }
+
+        assert_eq!(check_format("color", "red"), None);
+
+        let schema = parser::parse_str(r#"{"format": "date"}"#).unwrap();
+        let instance = parser::parse_str("\"2021-02-29\"").unwrap();
+        assert!(Validator::new(&schema, Options::default()).is_valid(&instance));
+
+        let options = Options {
+            format_assertion: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            Validator::new(&schema, options).validate(&instance)[0].message,
+            "Not a valid date"
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let cases = [
+            ("https://example.com/a/b", "c", "https://example.com/a/c"),
+            (
+                "https://example.com/a/b",
+                "../c#x",
+                "https://example.com/c#x",
+            ),
+            (
+                "https://example.com/a/b",
+                "#/d",
+                "https://example.com/a/b#/d",
+            ),
+            ("https://example.com/a/b", "/e", "https://example.com/e"),
+            ("https://example.com", "f", "https://example.com/f"),
+            ("", "#/g", "#/g"),
+            ("urn:example:root", "#h", "urn:example:root#h"),
+        ];
+
+        for (base, reference, expected) in cases {
+            assert_eq!(resolve(base, reference), expected);
+        }
+    }
+
+    #[test]
+    fn test_draft_names() {
+        assert_eq!(
+            Draft::from_uri("https://json-schema.org/draft-07/schema"),
+            Some(Draft::Draft7)
+        );
+        assert_eq!(
+            Draft::from_uri("http://json-schema.org/draft/2020-12/schema#"),
+            Some(Draft::Draft2020_12)
+        );
+        assert_eq!(Draft::from_uri("https://example.com/schema"), None);
+        assert_eq!(Draft::from_name("2019-09"), Some(Draft::Draft2019_09));
+        assert!(Draft::Draft2020_12.has_keyword("prefixItems"));
+        assert!(!Draft::Draft7.has_keyword("prefixItems"));
+        assert!(!Draft::Draft2020_12.has_keyword("dependencies"));
+    }
+}
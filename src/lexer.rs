@@ -1,12 +1,23 @@
-use std::fmt;
+use crate::escape;
+use crate::number::Number;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::{fmt, iter, str};
 
-#[derive(Debug, PartialEq)]
+// Byte order mark some editors put at the start of UTF-8 files, skipped at the start of the input
+// without counting towards columns
+pub const BOM: char = '\u{feff}';
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenType<'a> {
     Null,
     Bool(bool),
-    Number(f64),
+    // The number and its text
+    Number(Number<'a>, &'a str),
     String(&'a str),
     Invalid(&'a str),
+    // A `// line` or `/* block */` comment, only accepted by lenient parsing
+    Comment(&'a str),
     OpenCurly,
     CloseCurly,
     OpenSquare,
@@ -20,9 +31,10 @@ impl fmt::Display for TokenType<'_> {
         match self {
             TokenType::Null => write!(f, "null"),
             TokenType::Bool(b) => write!(f, "{}", b),
-            TokenType::Number(n) => write!(f, "{}", n),
+            TokenType::Number(_, text) => f.write_str(text),
             TokenType::String(s) => write!(f, "{}", s),
             TokenType::Invalid(s) => write!(f, "{}", s),
+            TokenType::Comment(s) => write!(f, "{}", s),
             TokenType::OpenCurly => write!(f, "{{"),
             TokenType::CloseCurly => write!(f, "}}"),
             TokenType::OpenSquare => write!(f, "["),
@@ -33,15 +45,103 @@ impl fmt::Display for TokenType<'_> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// What is wrong with the text at a position, found before parsing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LexErrorKind {
+    // A string without its closing quote on the same line
+    UnterminatedString,
+    // A `\` in a string not followed by one of `"\/bfnrt` or `u` and four hex digits, or a
+    // `\uXXXX` surrogate without its other half
+    InvalidEscape,
+    // Text starting like a number that is not one, like `01`, `1.` or `-`
+    MalformedNumber,
+    // A character that cannot start or be part of any token
+    UnknownCharacter(char),
+    // A `/*` comment without its `*/`
+    UnterminatedComment,
+    // A character below U+0020 written as is in a string, JSON needs it escaped
+    ControlCharacter,
+}
+
+impl LexErrorKind {
+    // Stable identifier for machine readable reports
+    pub fn code(self) -> &'static str {
+        match self {
+            LexErrorKind::UnterminatedString => "unterminated-string",
+            LexErrorKind::InvalidEscape => "invalid-escape",
+            LexErrorKind::MalformedNumber => "malformed-number",
+            LexErrorKind::UnknownCharacter(_) => "unknown-character",
+            LexErrorKind::UnterminatedComment => "unterminated-comment",
+            LexErrorKind::ControlCharacter => "control-character",
+        }
+    }
+}
+
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexErrorKind::UnterminatedString => write!(f, "Unterminated string"),
+            LexErrorKind::InvalidEscape => write!(f, "Invalid escape sequence"),
+            LexErrorKind::MalformedNumber => write!(f, "Malformed number"),
+            LexErrorKind::UnknownCharacter(c) => write!(f, "Unknown character '{}'", c),
+            LexErrorKind::UnterminatedComment => write!(f, "Unterminated comment"),
+            LexErrorKind::ControlCharacter => write!(f, "Unescaped control character in string"),
+        }
+    }
+}
+
+// Zero based position of the offending character, like the one of a token
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl LexError {
+    // Like `offset` for the tokens of the same input
+    pub fn offset(&mut self, line_offset: usize, col_offset: usize) {
+        self.line += line_offset;
+        self.col += col_offset;
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}:{}", self.kind, self.line + 1, self.col + 1)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token<'a> {
     pub token_type: TokenType<'a>,
     pub line: usize,
     pub col: usize,
+    // Byte offsets of the first byte and one past the last in the lexed input, with the quotes of
+    // strings, so `&input[token.start..token.end]` is the text of the token
+    pub start: usize,
+    pub end: usize,
+    // Line and column in chars one past the last char, on the line of the token except for block
+    // comments over several lines
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+// Where text starting at the line and column ends, `\r\n` is one newline like `\n`
+fn end_of(text: &str, line: usize, col: usize) -> (usize, usize) {
+    match text.rfind('\n') {
+        Some(last) => (
+            line + text.matches('\n').count(),
+            text[last + 1..].chars().count(),
+        ),
+        None => (line, col + text.chars().count()),
+    }
 }
 
 impl<'a> Token<'a> {
-    fn from_punctuator(c: char, line: usize, col: usize) -> Token<'a> {
+    fn from_punctuator(c: char, start: usize, line: usize, col: usize) -> Token<'a> {
         let token_type = match c {
             '{' => TokenType::OpenCurly,
             '}' => TokenType::CloseCurly,
@@ -56,24 +156,48 @@ impl<'a> Token<'a> {
             token_type,
             line,
             col,
+            start,
+            end: start + 1,
+            end_line: line,
+            end_col: col + 1,
         }
     }
 
-    fn from_quoted_str(string: &'a str, line: usize, col: usize) -> Token {
+    fn from_quoted_str(string: &'a str, start: usize, line: usize, col: usize) -> Token<'a> {
         let token_string = &string[1..string.len() - 1];
 
+        Token::from_text(TokenType::String(token_string), string, start, line, col)
+    }
+
+    fn from_text(
+        token_type: TokenType<'a>,
+        text: &str,
+        start: usize,
+        line: usize,
+        col: usize,
+    ) -> Token<'a> {
+        let (end_line, end_col) = end_of(text, line, col);
+
         Token {
-            token_type: TokenType::String(token_string),
+            token_type,
             line,
             col,
+            start,
+            end: start + text.len(),
+            end_line,
+            end_col,
         }
     }
 
-    fn from_key_or_val(symbol: &str, line: usize, col: usize) -> Token {
-        fn get_token_type(symbol: &str) -> TokenType {
-            match symbol.parse::<f64>() {
-                Ok(number) => TokenType::Number(number),
-                _ => match symbol {
+    fn from_key_or_val(symbol: &'a str, start: usize, line: usize, col: usize) -> Token<'a> {
+        fn get_token_type(symbol: &str) -> TokenType<'_> {
+            match Number::parse(symbol) {
+                // Too large for a double, the text is kept so formatting writes it back
+                Some(Number::Float(n)) if n.is_infinite() => {
+                    TokenType::Number(Number::Raw(Cow::Borrowed(symbol)), symbol)
+                }
+                Some(number) => TokenType::Number(number, symbol),
+                None => match symbol {
                     "null" => TokenType::Null,
                     "true" => TokenType::Bool(true),
                     "false" => TokenType::Bool(false),
@@ -82,13 +206,7 @@ impl<'a> Token<'a> {
             }
         }
 
-        let token_type = get_token_type(symbol);
-
-        Token {
-            token_type,
-            line,
-            col,
-        }
+        Token::from_text(get_token_type(symbol), symbol, start, line, col)
     }
 }
 
@@ -100,78 +218,370 @@ fn is_quote(c: char) -> bool {
     c == '"'
 }
 
-pub fn lex(s: &str) -> Vec<Token> {
-    let mut tokens = vec![];
+// Produces the tokens of the input one at a time, so it can be read without holding all of them.
+// At most the tokens of a single character are queued
+pub struct Lexer<'a> {
+    s: &'a str,
+    lines: iter::Enumerate<str::SplitTerminator<'a, char>>,
+    // The line being read, its number and where it starts in `s`
+    line: Option<(usize, &'a str, usize)>,
+    // Position of the next character of the line, as a byte offset and a column
+    byte_no: usize,
+    col_no: usize,
+    start: usize,
+    start_col: usize,
+    building: bool,
+    inside_quotes: bool,
+    prev_char_escape: bool,
+    // Start of the block comment being read, as a byte offset into `s` and its position
+    comment: Option<(usize, usize, usize)>,
+    prev_char_star: bool,
+    pending: VecDeque<Token<'a>>,
+    finished: bool,
+}
 
-    let mut start: usize = 0;
-    let mut building = false;
-    let mut inside_quotes = false;
-    let mut prev_char_escape = false;
+impl<'a> Lexer<'a> {
+    pub fn new(s: &'a str) -> Self {
+        Lexer {
+            s,
+            lines: s.split_terminator('\n').enumerate(),
+            line: None,
+            byte_no: 0,
+            col_no: 0,
+            start: 0,
+            start_col: 0,
+            building: false,
+            inside_quotes: false,
+            prev_char_escape: false,
+            comment: None,
+            prev_char_star: false,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
 
-    for (line_no, line_str) in s.split_terminator('\n').enumerate() {
-        for (col_no, c) in line_str.chars().enumerate() {
-            if !building {
-                if c.is_whitespace() {
-                    continue;
-                }
+    fn step(&mut self, line_no: usize, line_str: &'a str, line_start: usize, c: char) {
+        let s = self.s;
+        let byte_no = self.byte_no;
+        let col_no = self.col_no;
+        self.byte_no += c.len_utf8();
+        self.col_no += 1;
 
-                start = col_no;
-                building = true;
+        if let Some((comment_start, comment_line, comment_col)) = self.comment {
+            if self.prev_char_star && c == '/' {
+                let text = &s[comment_start..line_start + byte_no + 1];
+                self.pending.push_back(Token::from_text(
+                    TokenType::Comment(text),
+                    text,
+                    comment_start,
+                    comment_line,
+                    comment_col,
+                ));
+                self.comment = None;
             }
 
-            if !inside_quotes {
-                if is_punctuator(c) {
-                    if start != col_no {
-                        let token =
-                            Token::from_key_or_val(&line_str[start..col_no], line_no, start);
+            // The star opening the comment does not count towards closing it
+            self.prev_char_star = c == '*' && line_start + byte_no > comment_start + 1;
+            return;
+        }
 
-                        tokens.push(token);
-                    }
+        let rest = &line_str[byte_no..];
 
-                    tokens.push(Token::from_punctuator(c, line_no, col_no));
-                    building = false;
-                } else if c.is_whitespace() {
-                    let token = Token::from_key_or_val(&line_str[start..col_no], line_no, start);
+        if !self.inside_quotes && (rest.starts_with("//") || rest.starts_with("/*")) {
+            if self.building && self.start != byte_no {
+                let token = Token::from_key_or_val(
+                    &line_str[self.start..byte_no],
+                    line_start + self.start,
+                    line_no,
+                    self.start_col,
+                );
 
-                    tokens.push(token);
-                    building = false;
-                } else if col_no == line_str.len() - 1 {
-                    let token =
-                        Token::from_key_or_val(&line_str[start..col_no + 1], line_no, start);
+                self.pending.push_back(token);
+            }
 
-                    tokens.push(token);
-                    building = false;
-                }
+            self.building = false;
+
+            if rest.starts_with("//") {
+                let text = rest.trim_end();
+                self.pending.push_back(Token::from_text(
+                    TokenType::Comment(text),
+                    text,
+                    line_start + byte_no,
+                    line_no,
+                    col_no,
+                ));
+                self.byte_no = line_str.len();
+                return;
             }
 
-            if is_quote(c) {
-                if prev_char_escape {
-                    prev_char_escape = false;
-                    continue;
-                }
+            self.comment = Some((line_start + byte_no, line_no, col_no));
+            self.prev_char_star = false;
+            return;
+        }
+
+        if !self.building {
+            if c.is_whitespace() {
+                return;
+            }
+
+            self.start = byte_no;
+            self.start_col = col_no;
+            self.building = true;
+        }
 
-                if !inside_quotes {
-                    inside_quotes = true;
-                    continue;
+        if !self.inside_quotes {
+            if is_punctuator(c) {
+                if self.start != byte_no {
+                    let token = Token::from_key_or_val(
+                        &line_str[self.start..byte_no],
+                        line_start + self.start,
+                        line_no,
+                        self.start_col,
+                    );
+
+                    self.pending.push_back(token);
                 }
 
-                tokens.push(Token::from_quoted_str(
-                    &line_str[start..col_no + 1],
+                let token = Token::from_punctuator(c, line_start + byte_no, line_no, col_no);
+                self.pending.push_back(token);
+                self.building = false;
+            } else if c.is_whitespace() {
+                let token = Token::from_key_or_val(
+                    &line_str[self.start..byte_no],
+                    line_start + self.start,
                     line_no,
-                    start,
-                ));
+                    self.start_col,
+                );
+
+                self.pending.push_back(token);
+                self.building = false;
+            } else if byte_no + c.len_utf8() == line_str.len() {
+                let token = Token::from_key_or_val(
+                    &line_str[self.start..],
+                    line_start + self.start,
+                    line_no,
+                    self.start_col,
+                );
 
-                building = false;
-                inside_quotes = false;
+                self.pending.push_back(token);
+                self.building = false;
             }
+        }
+
+        if self.prev_char_escape {
+            self.prev_char_escape = false;
+            return;
+        }
 
-            if c == '\\' {
-                prev_char_escape = true
+        if is_quote(c) {
+            if !self.inside_quotes {
+                self.inside_quotes = true;
+                return;
             }
+
+            self.pending.push_back(Token::from_quoted_str(
+                &line_str[self.start..byte_no + 1],
+                line_start + self.start,
+                line_no,
+                self.start_col,
+            ));
+
+            self.building = false;
+            self.inside_quotes = false;
+        }
+
+        if self.inside_quotes && c == '\\' {
+            self.prev_char_escape = true
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(token);
+            }
+
+            match self.line {
+                Some((line_no, line_str, line_start)) if self.byte_no < line_str.len() => {
+                    let c = line_str[self.byte_no..].chars().next().unwrap();
+                    self.step(line_no, line_str, line_start, c);
+                }
+                Some((line_no, line_str, line_start)) if self.inside_quotes => {
+                    // A string cannot hold a line break, what was read of it is kept as invalid
+                    let text = line_str[self.start..].trim_end();
+                    self.pending.push_back(Token::from_text(
+                        TokenType::Invalid(text),
+                        text,
+                        line_start + self.start,
+                        line_no,
+                        self.start_col,
+                    ));
+                    self.building = false;
+                    self.inside_quotes = false;
+                    self.prev_char_escape = false;
+                }
+                _ => match self.lines.next() {
+                    Some((line_no, line_str)) => {
+                        let line_start = line_str.as_ptr() as usize - self.s.as_ptr() as usize;
+                        self.line = Some((line_no, line_str, line_start));
+                        self.byte_no = if line_no == 0 && line_str.starts_with(BOM) {
+                            BOM.len_utf8()
+                        } else {
+                            0
+                        };
+                        self.col_no = 0;
+                    }
+                    None if self.finished => return None,
+                    None => {
+                        self.finished = true;
+
+                        // An unterminated block comment is not a comment
+                        if let Some((comment_start, line, col)) = self.comment.take() {
+                            let text = self.s[comment_start..].trim_end();
+                            self.pending.push_back(Token::from_text(
+                                TokenType::Invalid(text),
+                                text,
+                                comment_start,
+                                line,
+                                col,
+                            ));
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// Escapes and control characters in the text of a string, without its quotes
+fn check_string(string: &str, line: usize, col: usize) -> Result<(), LexError> {
+    let mut chars = string.char_indices().enumerate();
+
+    while let Some((i, (_, c))) = chars.next() {
+        if c < ' ' {
+            return Err(LexError {
+                kind: LexErrorKind::ControlCharacter,
+                line,
+                col: col + 1 + i,
+            });
+        }
+
+        if c != '\\' {
+            continue;
+        }
+
+        let valid = match chars.next() {
+            Some((_, (_, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't'))) => true,
+            // Both escapes of a surrogate pair, the escape of one half alone is no character
+            Some((_, (u, 'u'))) => match escape::unescape_unicode(&string[u - 1..]) {
+                Some((_, length)) => chars.nth(length - 3).is_some(),
+                None => false,
+            },
+            _ => false,
+        };
+
+        if !valid {
+            // After the opening quote
+            return Err(LexError {
+                kind: LexErrorKind::InvalidEscape,
+                line,
+                col: col + 1 + i,
+            });
         }
     }
 
-    tokens
+    Ok(())
+}
+
+// The error of a token the parser could never accept. Words like `NaN` or unquoted keys are left
+// to the parser since its options may allow them
+pub fn check(token: &Token) -> Result<(), LexError> {
+    let error = |kind, col| {
+        Err(LexError {
+            kind,
+            line: token.line,
+            col,
+        })
+    };
+
+    let text = match token.token_type {
+        TokenType::String(s) => return check_string(s, token.line, token.col),
+        TokenType::Invalid(text) => text,
+        _ => return Ok(()),
+    };
+
+    if text.starts_with('"') {
+        return error(LexErrorKind::UnterminatedString, token.col);
+    }
+
+    if text.starts_with("/*") {
+        return error(LexErrorKind::UnterminatedComment, token.col);
+    }
+
+    let word = text.strip_prefix(['-', '+']).unwrap_or(text);
+
+    let digit = |c: char| c.is_ascii_digit();
+
+    if word.is_empty()
+        || word.starts_with(digit)
+        || word
+            .strip_prefix('.')
+            .is_some_and(|rest| rest.starts_with(digit))
+    {
+        return error(LexErrorKind::MalformedNumber, token.col);
+    }
+
+    let sign = text.len() - word.len();
+
+    match word.chars().enumerate().find(|(_, c)| !is_word_char(*c)) {
+        Some((i, c)) => error(LexErrorKind::UnknownCharacter(c), token.col + sign + i),
+        None => Ok(()),
+    }
+}
+
+// The tokens of the input as they are read, checked like `lex` does. Nothing is read past the
+// first error, which is the last item
+pub fn tokens(s: &str) -> impl Iterator<Item = Result<Token<'_>, LexError>> {
+    let mut failed = false;
+
+    Lexer::new(s).map_while(move |token| {
+        if failed {
+            return None;
+        }
+
+        let checked = check(&token).map(|_| token);
+        failed = checked.is_err();
+        Some(checked)
+    })
+}
+
+// The tokens of the whole input, or the first error in it
+pub fn lex(s: &str) -> Result<Vec<Token<'_>>, LexError> {
+    let mut tokens = tokens(s).collect::<Result<Vec<_>, _>>()?;
+
+    // The tokens stay alive as long as the parsed document, give back the unused capacity
+    tokens.shrink_to_fit();
+    Ok(tokens)
+}
+
+// Shifts token positions for input taken out of a larger document, so they point into it.
+// The column offset applies to every line, as for an indented block with the indent removed.
+// Byte offsets are left alone, they still index the lexed input
+pub fn offset(tokens: &mut [Token], line_offset: usize, col_offset: usize) {
+    for token in tokens.iter_mut() {
+        token.line += line_offset;
+        token.col += col_offset;
+        token.end_line += line_offset;
+        token.end_col += col_offset;
+    }
 }
 
 #[cfg(test)]
@@ -180,33 +590,53 @@ mod tests {
 
     #[test]
     fn test_lexer_1() {
-        let tokens = lex("\"foo\" : \n [ \"bar\" }");
+        let tokens = lex("\"foo\" : \n [ \"bar\" }").unwrap();
 
         let expected = vec![
             Token {
                 token_type: TokenType::String("foo"),
                 line: 0,
                 col: 0,
+                start: 0,
+                end: 5,
+                end_line: 0,
+                end_col: 5,
             },
             Token {
                 token_type: TokenType::Colon,
                 line: 0,
                 col: 6,
+                start: 6,
+                end: 7,
+                end_line: 0,
+                end_col: 7,
             },
             Token {
                 token_type: TokenType::OpenSquare,
                 line: 1,
                 col: 1,
+                start: 10,
+                end: 11,
+                end_line: 1,
+                end_col: 2,
             },
             Token {
                 token_type: TokenType::String("bar"),
                 line: 1,
                 col: 3,
+                start: 12,
+                end: 17,
+                end_line: 1,
+                end_col: 8,
             },
             Token {
                 token_type: TokenType::CloseCurly,
                 line: 1,
                 col: 9,
+                start: 18,
+                end: 19,
+                end_line: 1,
+                end_col: 10,
             },
         ];
 
@@ -215,23 +645,35 @@ mod tests {
 
     #[test]
     fn test_lexer_2() {
-        let tokens = lex("\n { \"bar\" ]");
+        let tokens = lex("\n { \"bar\" ]").unwrap();
 
         let expected = vec![
             Token {
                 token_type: TokenType::OpenCurly,
                 line: 1,
                 col: 1,
+                start: 2,
+                end: 3,
+                end_line: 1,
+                end_col: 2,
             },
             Token {
                 token_type: TokenType::String("bar"),
                 line: 1,
                 col: 3,
+                start: 4,
+                end: 9,
+                end_line: 1,
+                end_col: 8,
             },
             Token {
                 token_type: TokenType::CloseSquare,
                 line: 1,
                 col: 9,
+                start: 10,
+                end: 11,
+                end_line: 1,
+                end_col: 10,
             },
         ];
 
@@ -240,28 +682,44 @@ mod tests {
 
     #[test]
     fn test_lexer_3() {
-        let tokens = lex("\n {\"bar\" ] ,");
+        let tokens = lex("\n {\"bar\" ] ,").unwrap();
 
         let expected = vec![
             Token {
                 token_type: TokenType::OpenCurly,
                 line: 1,
                 col: 1,
+                start: 2,
+                end: 3,
+                end_line: 1,
+                end_col: 2,
             },
             Token {
                 token_type: TokenType::String("bar"),
                 line: 1,
                 col: 2,
+                start: 3,
+                end: 8,
+                end_line: 1,
+                end_col: 7,
             },
             Token {
                 token_type: TokenType::CloseSquare,
                 line: 1,
                 col: 8,
+                start: 9,
+                end: 10,
+                end_line: 1,
+                end_col: 9,
             },
             Token {
                 token_type: TokenType::Comma,
                 line: 1,
                 col: 10,
+                start: 11,
+                end: 12,
+                end_line: 1,
+                end_col: 11,
             },
         ];
 
@@ -270,23 +728,35 @@ mod tests {
 
     #[test]
     fn test_lexer_4() {
-        let tokens = lex("\n {\"bar\"]");
+        let tokens = lex("\n {\"bar\"]").unwrap();
 
         let expected = vec![
             Token {
                 token_type: TokenType::OpenCurly,
                 line: 1,
                 col: 1,
+                start: 2,
+                end: 3,
+                end_line: 1,
+                end_col: 2,
             },
             Token {
                 token_type: TokenType::String("bar"),
                 line: 1,
                 col: 2,
+                start: 3,
+                end: 8,
+                end_line: 1,
+                end_col: 7,
             },
             Token {
                 token_type: TokenType::CloseSquare,
                 line: 1,
                 col: 7,
+                start: 8,
+                end: 9,
+                end_line: 1,
+                end_col: 8,
             },
         ];
 
@@ -295,33 +765,53 @@ mod tests {
 
     #[test]
     fn test_lexer_5() {
-        let tokens = lex("\n {bar]:\n\"foo\"");
+        let tokens = lex("\n {bar]:\n\"foo\"").unwrap();
 
         let expected = vec![
             Token {
                 token_type: TokenType::OpenCurly,
                 line: 1,
                 col: 1,
+                start: 2,
+                end: 3,
+                end_line: 1,
+                end_col: 2,
             },
             Token {
                 token_type: TokenType::Invalid("bar"),
                 line: 1,
                 col: 2,
+                start: 3,
+                end: 6,
+                end_line: 1,
+                end_col: 5,
             },
             Token {
                 token_type: TokenType::CloseSquare,
                 line: 1,
                 col: 5,
+                start: 6,
+                end: 7,
+                end_line: 1,
+                end_col: 6,
             },
             Token {
                 token_type: TokenType::Colon,
                 line: 1,
                 col: 6,
+                start: 7,
+                end: 8,
+                end_line: 1,
+                end_col: 7,
             },
             Token {
                 token_type: TokenType::String("foo"),
                 line: 2,
                 col: 0,
+                start: 9,
+                end: 14,
+                end_line: 2,
+                end_col: 5,
             },
         ];
 
@@ -330,12 +820,16 @@ mod tests {
 
     #[test]
     fn test_lexer_6() {
-        let tokens = lex("bar");
+        let tokens = lex("bar").unwrap();
 
         let expected = vec![Token {
             token_type: TokenType::Invalid("bar"),
             line: 0,
             col: 0,
+            start: 0,
+            end: 3,
+            end_line: 0,
+            end_col: 3,
         }];
 
         assert_eq!(tokens, expected);
@@ -343,12 +837,16 @@ mod tests {
 
     #[test]
     fn test_lexer_7() {
-        let tokens = lex("\"bar\"");
+        let tokens = lex("\"bar\"").unwrap();
 
         let expected = vec![Token {
             token_type: TokenType::String("bar"),
             line: 0,
             col: 0,
+            start: 0,
+            end: 5,
+            end_line: 0,
+            end_col: 5,
         }];
 
         assert_eq!(tokens, expected);
@@ -356,12 +854,16 @@ mod tests {
 
     #[test]
     fn test_lexer_8() {
-        let tokens = lex("2345");
+        let tokens = lex("2345").unwrap();
 
         let expected = vec![Token {
-            token_type: TokenType::Number(2345.0),
+            token_type: TokenType::Number(Number::PosInt(2345), "2345"),
             line: 0,
             col: 0,
+            start: 0,
+            end: 4,
+            end_line: 0,
+            end_col: 4,
         }];
 
         assert_eq!(tokens, expected);
@@ -369,18 +871,26 @@ mod tests {
 
     #[test]
     fn test_lexer_9() {
-        let tokens = lex("2345}");
+        let tokens = lex("2345}").unwrap();
 
         let expected = vec![
             Token {
-                token_type: TokenType::Number(2345.0),
+                token_type: TokenType::Number(Number::PosInt(2345), "2345"),
                 line: 0,
                 col: 0,
+                start: 0,
+                end: 4,
+                end_line: 0,
+                end_col: 4,
             },
             Token {
                 token_type: TokenType::CloseCurly,
                 line: 0,
                 col: 4,
+                start: 4,
+                end: 5,
+                end_line: 0,
+                end_col: 5,
             },
         ];
 
@@ -389,23 +899,35 @@ mod tests {
 
     #[test]
     fn test_lexer_10() {
-        let tokens = lex("2345      } 456 ");
+        let tokens = lex("2345      } 456 ").unwrap();
 
         let expected = vec![
             Token {
-                token_type: TokenType::Number(2345.0),
+                token_type: TokenType::Number(Number::PosInt(2345), "2345"),
                 line: 0,
                 col: 0,
+                start: 0,
+                end: 4,
+                end_line: 0,
+                end_col: 4,
             },
             Token {
                 token_type: TokenType::CloseCurly,
                 line: 0,
                 col: 10,
+                start: 10,
+                end: 11,
+                end_line: 0,
+                end_col: 11,
             },
             Token {
-                token_type: TokenType::Number(456.0),
+                token_type: TokenType::Number(Number::PosInt(456), "456"),
                 line: 0,
                 col: 12,
+                start: 12,
+                end: 15,
+                end_line: 0,
+                end_col: 15,
             },
         ];
 
@@ -414,26 +936,269 @@ mod tests {
 
     #[test]
     fn test_lexer_11() {
-        let tokens = lex("\"foo\": \"{\\\"bar\\\":0}\"");
+        let tokens = lex("\"foo\": \"{\\\"bar\\\":0}\"").unwrap();
 
         let expected = vec![
             Token {
                 token_type: TokenType::String("foo"),
                 line: 0,
                 col: 0,
+                start: 0,
+                end: 5,
+                end_line: 0,
+                end_col: 5,
             },
             Token {
                 token_type: TokenType::Colon,
                 line: 0,
                 col: 5,
+                start: 5,
+                end: 6,
+                end_line: 0,
+                end_col: 6,
             },
             Token {
                 token_type: TokenType::String("{\\\"bar\\\":0}"),
                 line: 0,
                 col: 7,
+                start: 7,
+                end: 20,
+                end_line: 0,
+                end_col: 20,
             },
         ];
 
         assert_eq!(tokens, expected);
     }
+
+    #[test]
+    fn test_lexer_12() {
+        let tokens = lex("[\"a\\nb\", \"\\\\\"]").unwrap();
+
+        let expected = vec![
+            Token {
+                token_type: TokenType::OpenSquare,
+                line: 0,
+                col: 0,
+                start: 0,
+                end: 1,
+                end_line: 0,
+                end_col: 1,
+            },
+            Token {
+                token_type: TokenType::String("a\\nb"),
+                line: 0,
+                col: 1,
+                start: 1,
+                end: 7,
+                end_line: 0,
+                end_col: 7,
+            },
+            Token {
+                token_type: TokenType::Comma,
+                line: 0,
+                col: 7,
+                start: 7,
+                end: 8,
+                end_line: 0,
+                end_col: 8,
+            },
+            Token {
+                token_type: TokenType::String("\\\\"),
+                line: 0,
+                col: 9,
+                start: 9,
+                end: 13,
+                end_line: 0,
+                end_col: 13,
+            },
+            Token {
+                token_type: TokenType::CloseSquare,
+                line: 0,
+                col: 13,
+                start: 13,
+                end: 14,
+                end_line: 0,
+                end_col: 14,
+            },
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_lexer_comments() {
+        let tokens: Vec<_> = Lexer::new("{a// x\r\n/* b\n*/ \"//\": /**/1 /*/ c").collect();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.line, t.col))
+            .collect();
+
+        assert_eq!(
+            types,
+            [
+                (&TokenType::OpenCurly, 0, 0),
+                (&TokenType::Invalid("a"), 0, 1),
+                (&TokenType::Comment("// x"), 0, 2),
+                (&TokenType::Comment("/* b\n*/"), 1, 0),
+                (&TokenType::String("//"), 2, 3),
+                (&TokenType::Colon, 2, 7),
+                (&TokenType::Comment("/**/"), 2, 9),
+                (&TokenType::Number(Number::PosInt(1), "1"), 2, 13),
+                (&TokenType::Invalid("/*/ c"), 2, 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let raw = "{\"é\\\"\": -1.5e3, // c\r\n  \"k\":\ttrue /* x\n*/ }";
+        let texts: Vec<_> = Lexer::new(raw)
+            .map(|token| &raw[token.start..token.end])
+            .collect();
+
+        assert_eq!(
+            texts,
+            [
+                "{",
+                "\"é\\\"\"",
+                ":",
+                "-1.5e3",
+                ",",
+                "// c",
+                "\"k\"",
+                ":",
+                "true",
+                "/* x\n*/",
+                "}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_positions() {
+        let raw = "{\"é\\\"\": -1.5e3, // c\r\n  \"k\":\ttrue /* x\r\n*/ }";
+        let positions: Vec<_> = Lexer::new(raw)
+            .map(|token| (token.line, token.col, token.end_line, token.end_col))
+            .collect();
+
+        assert_eq!(
+            positions,
+            [
+                (0, 0, 0, 1),
+                (0, 1, 0, 6),
+                (0, 6, 0, 7),
+                (0, 8, 0, 14),
+                (0, 14, 0, 15),
+                (0, 16, 0, 20),
+                (1, 2, 1, 5),
+                (1, 5, 1, 6),
+                (1, 7, 1, 11),
+                (1, 12, 2, 2),
+                (2, 3, 2, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multibyte_words() {
+        // Words are sliced at byte offsets and placed at char columns, whether they end at a
+        // punctuator, at whitespace or at the end of a line
+        let raw = "[é,\n ñé ,\n日本\n]";
+        let tokens: Vec<_> = Lexer::new(raw)
+            .map(|token| (token.token_type, token.col, token.start, token.end))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            [
+                (TokenType::OpenSquare, 0, 0, 1),
+                (TokenType::Invalid("é"), 1, 1, 3),
+                (TokenType::Comma, 2, 3, 4),
+                (TokenType::Invalid("ñé"), 1, 6, 10),
+                (TokenType::Comma, 4, 11, 12),
+                (TokenType::Invalid("日本"), 0, 13, 19),
+                (TokenType::CloseSquare, 0, 20, 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bom() {
+        let tokens = lex("\u{feff}[1]").unwrap();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!((tokens[0].line, tokens[0].col), (0, 0));
+        assert_eq!((tokens[0].start, tokens[0].end), (3, 4));
+
+        // Only at the start of the input
+        assert!(lex("[1,\n\u{feff}2]").is_err());
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        let cases = [
+            ("[\"abc", LexErrorKind::UnterminatedString, 0, 1),
+            ("{\"a\n\": 1}", LexErrorKind::UnterminatedString, 0, 1),
+            ("[\"a\\qb\"]", LexErrorKind::InvalidEscape, 0, 3),
+            ("\"\\u00e\"", LexErrorKind::InvalidEscape, 0, 1),
+            ("[\"\\ud800\"]", LexErrorKind::InvalidEscape, 0, 2),
+            ("[\"a\\ud83d\\u0041\"]", LexErrorKind::InvalidEscape, 0, 3),
+            ("[\"é\\udc00\"]", LexErrorKind::InvalidEscape, 0, 3),
+            ("[1, 01]", LexErrorKind::MalformedNumber, 0, 4),
+            ("\n  -.5", LexErrorKind::MalformedNumber, 1, 2),
+            ("[1.]", LexErrorKind::MalformedNumber, 0, 1),
+            ("{'a': 1}", LexErrorKind::UnknownCharacter('\''), 0, 1),
+            ("[ab#c]", LexErrorKind::UnknownCharacter('#'), 0, 3),
+            ("[é, .]", LexErrorKind::UnknownCharacter('.'), 0, 4),
+            ("1 /* x", LexErrorKind::UnterminatedComment, 0, 2),
+            ("[\"a\tb\"]", LexErrorKind::ControlCharacter, 0, 3),
+            ("[\"é\u{1}\"]", LexErrorKind::ControlCharacter, 0, 3),
+            ("{\"\0\": 1}", LexErrorKind::ControlCharacter, 0, 2),
+            ("[1, \"a\rb\"]", LexErrorKind::ControlCharacter, 0, 6),
+        ];
+
+        for (raw, kind, line, col) in cases {
+            assert_eq!(lex(raw), Err(LexError { kind, line, col }), "{}", raw);
+        }
+
+        // Left to the parser and its options
+        assert!(lex("{a: NaN, b: -Infinity, c: $x, \"\\u00e9\\/\": 1e5}").is_ok());
+        assert!(lex("[\"a\\tb\\u0001\", \"\u{7f}\"]").is_ok());
+        assert!(lex("\"\\ud83d\\ude00 \\uD83D\\uDE00\"").is_ok());
+        assert_eq!(
+            lex("\"a\\x").unwrap_err().to_string(),
+            "Unterminated string at 1:1"
+        );
+    }
+
+    #[test]
+    fn test_tokens() {
+        let mut tokens = tokens("[1, \"a\\x\", 2]");
+
+        assert_eq!(
+            tokens.next().unwrap().unwrap().token_type,
+            TokenType::OpenSquare
+        );
+        assert!(tokens.next().unwrap().is_ok());
+        assert!(tokens.next().unwrap().is_ok());
+        assert_eq!(
+            tokens.next(),
+            Some(Err(LexError {
+                kind: LexErrorKind::InvalidEscape,
+                line: 0,
+                col: 6,
+            }))
+        );
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn test_offset() {
+        let mut tokens = lex("[\n  1]").unwrap();
+        offset(&mut tokens, 10, 4);
+
+        let positions: Vec<_> = tokens.iter().map(|t| (t.line, t.col)).collect();
+        assert_eq!(positions, [(10, 4), (11, 6), (11, 7)]);
+    }
 }
@@ -0,0 +1,53 @@
+#[cfg(feature = "preserve_order")]
+extern crate indexmap;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "toml")]
+extern crate toml;
+
+#[macro_use]
+mod macros;
+
+pub mod canonical;
+pub mod compact;
+pub mod compare;
+pub mod convert;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diagnostic;
+pub mod diff;
+pub mod encoding;
+pub mod escape;
+pub mod filter;
+pub mod flatten;
+pub mod formatter;
+mod iregexp;
+pub mod json5;
+pub mod jsonpath;
+pub mod jsonschema;
+pub mod lexer;
+pub mod merge;
+pub mod normalize;
+pub mod number;
+pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod reader;
+#[cfg(feature = "serde")]
+mod ser;
+pub mod spanned;
+pub mod stats;
+pub mod visit;
+
+#[cfg(feature = "serde")]
+pub use de::from_str;
+pub use number::Number;
+pub use parser::{
+    parse_str, parse_str_with_options, validate, Json, JsonOwned, OwnedParseError, ParserOptions,
+};
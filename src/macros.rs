@@ -0,0 +1,132 @@
+// Builds a `Json` from JSON-like syntax, with Rust expressions for any value or key:
+//
+//     let doc = json!({"name": name, "tags": ["a", tag], "size": {"w": 1, "h": h * 2}});
+//
+// Values are converted with `Json::from`, keys can be any expression that derefs to `str`. An
+// expression spanning several tokens as a key, like `prefix + "x"`, has to be put in parentheses
+#[macro_export]
+macro_rules! json {
+    (null) => {
+        $crate::Json::Null
+    };
+
+    ([ $($items:tt)* ]) => {
+        $crate::Json::Array($crate::json!(@array [] $($items)*))
+    };
+
+    ({ $($members:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut object = $crate::parser::Map::new();
+        $crate::json!(@object object $($members)*);
+        $crate::Json::Object(object)
+    }};
+
+    ($other:expr) => {
+        $crate::Json::from($other)
+    };
+
+    // Array items are collected as expressions until the closing bracket
+    (@array [$($done:expr,)*]) => {
+        vec![$($done),*]
+    };
+
+    (@array [$($done:expr,)*] , $($rest:tt)*) => {
+        $crate::json!(@array [$($done,)*] $($rest)*)
+    };
+
+    (@array [$($done:expr,)*] null $($rest:tt)*) => {
+        $crate::json!(@array [$($done,)* $crate::json!(null),] $($rest)*)
+    };
+
+    (@array [$($done:expr,)*] [ $($item:tt)* ] $($rest:tt)*) => {
+        $crate::json!(@array [$($done,)* $crate::json!([$($item)*]),] $($rest)*)
+    };
+
+    (@array [$($done:expr,)*] { $($item:tt)* } $($rest:tt)*) => {
+        $crate::json!(@array [$($done,)* $crate::json!({$($item)*}),] $($rest)*)
+    };
+
+    (@array [$($done:expr,)*] $next:expr , $($rest:tt)*) => {
+        $crate::json!(@array [$($done,)* $crate::json!($next),] $($rest)*)
+    };
+
+    (@array [$($done:expr,)*] $last:expr) => {
+        $crate::json!(@array [$($done,)* $crate::json!($last),])
+    };
+
+    // Members are inserted one at a time, a repeated key keeps the last value
+    (@object $object:ident) => {};
+
+    (@object $object:ident , $($rest:tt)*) => {
+        $crate::json!(@object $object $($rest)*)
+    };
+
+    (@object $object:ident $key:tt : null $($rest:tt)*) => {
+        $crate::json!(@insert $object $key, $crate::json!(null));
+        $crate::json!(@object $object $($rest)*)
+    };
+
+    (@object $object:ident $key:tt : [ $($value:tt)* ] $($rest:tt)*) => {
+        $crate::json!(@insert $object $key, $crate::json!([$($value)*]));
+        $crate::json!(@object $object $($rest)*)
+    };
+
+    (@object $object:ident $key:tt : { $($value:tt)* } $($rest:tt)*) => {
+        $crate::json!(@insert $object $key, $crate::json!({$($value)*}));
+        $crate::json!(@object $object $($rest)*)
+    };
+
+    (@object $object:ident $key:tt : $value:expr , $($rest:tt)*) => {
+        $crate::json!(@insert $object $key, $crate::json!($value));
+        $crate::json!(@object $object $($rest)*)
+    };
+
+    (@object $object:ident $key:tt : $value:expr) => {
+        $crate::json!(@insert $object $key, $crate::json!($value));
+    };
+
+    (@insert $object:ident $key:expr, $value:expr) => {
+        $object.insert(
+            ::std::borrow::Cow::Owned(::std::string::ToString::to_string(&$key)),
+            $value,
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use number::Number;
+    use parser::{self, Json};
+
+    #[test]
+    fn test_json() {
+        let name = "a \"b\"";
+        let tags = [json!(1), json!(null)];
+        let key = String::from("k");
+
+        let value = json!({
+            "name": name,
+            "tags": ["x", 2.5, -3, true, null, [], {}, tags.len()],
+            "nested": {"list": [{"a": null}], "sum": 1 + 2},
+            key: false,
+            ("prefix".to_string() + "ed"): "é\n",
+        });
+
+        let expected = parser::parse_str(
+            r#"{
+                "name": "a \"b\"",
+                "tags": ["x", 2.5, -3, true, null, [], {}, 2],
+                "nested": {"list": [{"a": null}], "sum": 3},
+                "k": false,
+                "prefixed": "é\n"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(value, expected);
+        assert_eq!(json!(null), Json::Null);
+        assert_eq!(json!([]), Json::Array(vec![]));
+        assert_eq!(json!(u64::MAX), Json::Number(Number::PosInt(u64::MAX)));
+        assert_eq!(json!([1, 2,]), json!([1, 2]));
+    }
+}
@@ -1,57 +1,1944 @@
 extern crate argh;
+extern crate joxide;
+#[cfg(feature = "toml")]
+extern crate toml;
+
 use crate::args::JoxideSubcommand;
-use std::process::ExitCode;
+use joxide::diagnostic::MessageId;
+use joxide::encoding::{self, Encoding};
+use joxide::{diagnostic, diff, formatter, jsonpath, jsonschema, lexer, merge, parser, pointer};
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
 
 mod args;
-mod diagnostic;
-mod formatter;
-mod lexer;
-mod parser;
+mod assertion;
+mod bench;
+mod color;
+mod config;
+mod diffview;
+mod dupes;
+mod explain;
+mod framing;
+mod glob;
+mod init;
+mod ndjson;
+mod openapi;
 mod pretty;
+mod query;
+mod report;
+mod schema;
+mod summary;
+mod tail;
 
-fn main() -> ExitCode {
-    let args: args::JoxideArgs = argh::from_env();
+#[cfg_attr(not(feature = "parquet"), allow(unused_variables))]
+fn convert(value: &parser::Json, convert_args: &args::ConvertArgs) -> Result<(), String> {
+    match convert_args.to.as_str() {
+        #[cfg(feature = "parquet")]
+        "parquet" => {
+            let file = std::fs::File::create(&convert_args.output)
+                .map_err(|err| format!("Unable to write to file, reason: {}", err))?;
 
-    let file_path = match args.sub_command {
-        JoxideSubcommand::Validate(ref validate_args) => &validate_args.file,
-        JoxideSubcommand::Format(ref format_args) => &format_args.file,
+            joxide::convert::to_parquet(value, file).map_err(|err| err.to_string())
+        }
+        #[cfg(not(feature = "parquet"))]
+        "parquet" => Err("joxide was built without the parquet feature".to_string()),
+        other => Err(format!("Unknown output format '{}'", other)),
+    }
+}
+
+// Renders diagnostics for one input file
+struct Reporter<'r> {
+    file_path: &'r str,
+    raw: &'r str,
+    encoding: Encoding,
+    line_offset: usize,
+    col_offset: usize,
+    config: &'r config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&'r report::Report>,
+}
+
+impl Reporter<'_> {
+    // Of a token or error, the offsets included
+    fn location(&self, position: (usize, usize)) -> String {
+        // Token positions include the offsets, the source lines do not
+        let line = position.0.saturating_sub(self.line_offset);
+        let col = position.1.saturating_sub(self.col_offset);
+
+        // Positions in the decoded text do not match the file bytes for other encodings
+        let byte = match self.encoding {
+            Encoding::Utf8 => String::new(),
+            encoding => format!(" (byte {})", encoding.byte_offset(self.raw, line, col)),
+        };
+
+        format!(
+            "At {}:{}:{}{}\n{}",
+            self.file_path,
+            position.0 + 1,
+            position.1 + 1,
+            byte,
+            pretty::location(self.raw, line, col)
+        )
+    }
+
+    // The location and message of a problem, also added to the machine readable report
+    fn problem(
+        &self,
+        position: Option<(usize, usize)>,
+        severity: report::Severity,
+        code: &'static str,
+        message: &str,
+    ) -> String {
+        if let Some(report) = self.report {
+            report.diagnostic(report::Diagnostic {
+                file: self.file_path.to_string(),
+                line: position.map(|(line, _)| line + 1),
+                col: position.map(|(_, col)| col + 1),
+                severity,
+                code,
+                message: message.to_string(),
+            });
+        }
+
+        let message = match severity {
+            report::Severity::Warning => Cow::Owned(format!("Warning: {}", message)),
+            report::Severity::Error => Cow::Borrowed(message),
+        };
+
+        match position {
+            Some(position) => format!("{}{}\n", self.location(position), message),
+            None => format!("{}\n", message),
+        }
+    }
+
+    fn parse_problem(
+        &self,
+        parse_error: &parser::ParseError,
+        severity: report::Severity,
+    ) -> String {
+        let position = parse_error.token.map(|token| (token.line, token.col));
+        self.parse_problem_at(parse_error, position, severity)
+    }
+
+    fn parse_problem_at(
+        &self,
+        parse_error: &parser::ParseError,
+        position: Option<(usize, usize)>,
+        severity: report::Severity,
+    ) -> String {
+        let id = diagnostic::get_message_id(parse_error);
+        let template = template(self.config, self.locale, id);
+        let message = diagnostic::render(template, parse_error, self.file_path);
+
+        self.problem(position, severity, id.code(), &message)
+    }
+
+    fn error(&self, parse_error: &parser::ParseError) -> String {
+        self.parse_problem(parse_error, report::Severity::Error)
+    }
+
+    fn lex_error(&self, lex_error: &lexer::LexError) -> String {
+        let id = diagnostic::get_lex_message_id(lex_error.kind);
+        let template = template(self.config, self.locale, id);
+        let message = diagnostic::render_lex(template, lex_error, self.file_path);
+
+        let position = Some((lex_error.line, lex_error.col));
+        self.problem(position, report::Severity::Error, id.code(), &message)
+    }
+
+    // Parses a document of the input starting at `line` and `col` as it is lexed, so its tokens are
+    // never all held at once. Positions get the offsets like `lexer::offset` gives them, the column
+    // only moves the first line as the lines after it start at the start of a line
+    fn parse<'s>(
+        &self,
+        document: &'s str,
+        (line, col): (usize, usize),
+        options: parser::ParserOptions,
+    ) -> Result<parser::Json<'s>, String> {
+        let (line_offset, col_offset) = (line + self.line_offset, self.col_offset);
+        let shift = move |token_line: &mut usize, token_col: &mut usize| {
+            if *token_line == 0 {
+                *token_col += col;
+            }
+
+            *token_line += line_offset;
+            *token_col += col_offset;
+        };
+
+        let tokens = lexer::tokens(document).map(move |token| {
+            token
+                .map(|mut token| {
+                    shift(&mut token.line, &mut token.col);
+                    shift(&mut token.end_line, &mut token.end_col);
+                    token
+                })
+                .map_err(|mut lex_error| {
+                    shift(&mut lex_error.line, &mut lex_error.col);
+                    lex_error
+                })
+        });
+
+        parser::parse_tokens_with_options(tokens, options).map_err(|error| match error {
+            parser::StreamError::Lex(lex_error) => self.lex_error(&lex_error),
+            parser::StreamError::Parse(token_error) => self.error(&token_error.parse_error()),
+        })
+    }
+}
+
+// The message of the config for `id`, or else the one of the locale
+fn template(config: &config::Config, locale: diagnostic::Locale, id: MessageId) -> &str {
+    config
+        .message(id)
+        .unwrap_or_else(|| diagnostic::template(locale, id))
+}
+
+// How files are turned into text, from --encoding and --lossy
+#[derive(Clone, Copy)]
+struct Decoding {
+    // Detected from the bytes of each file when not given
+    encoding: Option<Encoding>,
+    lossy: bool,
+}
+
+// The decoded file and its encoding
+// Invalid UTF-8 is explained with the message of the locale, or the one the config gives
+fn read_file(
+    file_path: &str,
+    decoding: Decoding,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+) -> Result<(String, Encoding), String> {
+    let bytes = if file_path == "-" {
+        let mut bytes = vec![];
+        std::io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        std::fs::read(file_path)
     };
 
-    let raw = match std::fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(err) => {
-            println!("Unable to open file, reason: {}", err);
+    match bytes {
+        Ok(bytes) => {
+            let encoding = decoding
+                .encoding
+                .unwrap_or_else(|| Encoding::detect(&bytes));
+
+            let decoded = match encoding {
+                _ if decoding.lossy => Ok(encoding.decode_lossy(&bytes)),
+                Encoding::Utf8 => {
+                    encoding::from_utf8(&bytes)
+                        .map(str::to_string)
+                        .map_err(|invalid| {
+                            let template = template(config, locale, MessageId::InvalidUtf8);
+                            diagnostic::render_invalid_utf8(template, &invalid, file_path)
+                        })
+                }
+                _ => encoding.decode(&bytes).map_err(|err| err.to_string()),
+            };
+
+            decoded
+                .map(|raw| (raw, encoding))
+                .map_err(|reason| format!("Unable to read {}, reason: {}", file_path, reason))
+        }
+        Err(err) => Err(format!("Unable to open file, reason: {}", err)),
+    }
+}
+
+// Reads and parses each file with the parser options of the command line, for the subcommands
+// comparing documents. Each document comes with its text, the diagnostic of the first file that
+// can not be read or parsed is the error
+fn load_documents(
+    paths: &[&str],
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> Result<Vec<(String, parser::JsonOwned)>, String> {
+    let mut documents = vec![];
+
+    for path in paths {
+        if let Some(report) = report {
+            report.file(path);
+        }
+
+        let (raw, encoding) = read_file(path, decoding, config, locale)
+            .map_err(|message| failure(path, "read", message, report) + "\n")?;
+        let reporter = Reporter {
+            file_path: path,
+            raw: &raw,
+            encoding,
+            line_offset: 0,
+            col_offset: 0,
+            config,
+            locale,
+            report,
+        };
+
+        let options = parser_options(args, config).map_err(|message| {
+            reporter.problem(None, report::Severity::Error, "options", &message)
+        })?;
+        let value = reporter.parse(&raw, (0, 0), options)?.into_owned();
+
+        documents.push((raw, value));
+    }
+
+    Ok(documents)
+}
+
+// Exits with an error when the files differ, like `diff`
+fn diff(
+    diff_args: &args::DiffArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> ExitCode {
+    let view = match diffview::View::from_name(&diff_args.view) {
+        Some(view) => view,
+        None => {
+            eprintln!(
+                "Unknown diff view '{}', expected unified, side-by-side or tree",
+                diff_args.view
+            );
             return ExitCode::FAILURE;
         }
     };
 
-    let tokens = lexer::lex(&raw);
+    if let Some(ref by) = diff_args.by {
+        if pointer::split(by).is_none() {
+            eprintln!("Invalid --by '{}', expected a JSON pointer like /id", by);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = diff_args
+        .ignore_paths
+        .iter()
+        .find(|path| pointer::split(path).is_none())
+    {
+        eprintln!(
+            "Invalid --ignore-paths '{}', expected a JSON pointer like /items/*/id",
+            path
+        );
+        return ExitCode::FAILURE;
+    }
 
-    let value = match parser::parse(&tokens) {
-        Ok(value) => value,
-        Err(parse_error) => {
-            if let Some(token) = parse_error.token {
-                println!("At {}:{}:{}", file_path, token.line + 1, token.col + 1);
-                pretty::print_location(&raw, token);
+    let paths = [diff_args.from.as_str(), diff_args.to.as_str()];
+    let mut values: Vec<_> = match load_documents(&paths, decoding, args, config, locale, report) {
+        Ok(documents) => documents.into_iter().map(|(_, value)| value).collect(),
+        Err(message) => {
+            eprint!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let options = diff::DiffOptions {
+        by: diff_args.by.clone(),
+        ignore_paths: diff_args.ignore_paths.clone(),
+        ..diff::DiffOptions::default()
+    };
+    let operations = diff::diff(&values[0], &values[1], &options);
+
+    if diff_args.stat {
+        print!("{}", diffview::stat(&operations));
+    } else if view == diffview::View::Tree {
+        print!(
+            "{}",
+            diffview::tree(&values[0], &operations, diff_args.color)
+        );
+    } else if !operations.is_empty() {
+        // Both files are formatted the same way so only changed values show as changed lines
+        for value in &mut values {
+            for path in &diff_args.ignore_paths {
+                pointer::remove_all(value, path);
             }
+        }
+
+        let options = formatter::FormatOptions {
+            indent_length: config.indent_length.unwrap_or(4),
+            sort_keys: true,
+            ..formatter::FormatOptions::default()
+        };
+        let from = formatter::format_json_with_options(&values[0], &options);
+        let to = formatter::format_json_with_options(&values[1], &options);
 
-            println!("{}", diagnostic::get_message(&parse_error));
+        if view == diffview::View::Unified {
+            let labels = (diff_args.from.as_str(), diff_args.to.as_str());
+            print!(
+                "{}",
+                diffview::unified(&from, &to, labels, diff_args.context, diff_args.color)
+            );
+        } else {
+            print!(
+                "{}",
+                diffview::side_by_side(&from, &to, diff_args.context, diff_args.color)
+            );
+        }
+    }
+
+    if operations.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// Merges two files with no common ancestor, see `merge::merge2`
+fn merge(
+    merge_args: &args::MergeArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> ExitCode {
+    let on_conflict = match on_conflict(merge_args.on_conflict.as_deref()) {
+        Ok(on_conflict) => on_conflict,
+        Err(message) => {
+            eprintln!("{}", message);
             return ExitCode::FAILURE;
         }
     };
 
-    if let JoxideSubcommand::Format(ref format_args) = args.sub_command {
-        let formatted = formatter::format_json(value, format_args.indent_length);
+    let paths = [merge_args.ours.as_str(), merge_args.theirs.as_str()];
+    let (raws, values): (Vec<_>, Vec<_>) =
+        match load_documents(&paths, decoding, args, config, locale, report) {
+            Ok(documents) => documents.into_iter().unzip(),
+            Err(message) => {
+                eprint!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        };
+
+    let merge = merge::merge2(&values[0], &values[1]);
+    let output = merge_args.output.as_deref();
+    write_merge(
+        merge,
+        on_conflict,
+        &raws[0],
+        merge_args.indent_length,
+        output,
+        config,
+    )
+}
+
+fn merge3(
+    merge_args: &args::Merge3Args,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> ExitCode {
+    let on_conflict = match on_conflict(merge_args.on_conflict.as_deref()) {
+        Ok(on_conflict) => on_conflict,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let paths = [
+        merge_args.base.as_str(),
+        merge_args.ours.as_str(),
+        merge_args.theirs.as_str(),
+    ];
+    let (raws, values): (Vec<_>, Vec<_>) =
+        match load_documents(&paths, decoding, args, config, locale, report) {
+            Ok(documents) => documents.into_iter().unzip(),
+            Err(message) => {
+                eprint!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        };
+
+    let merge = merge::merge3(&values[0], &values[1], &values[2]);
+    let output = merge_args.output.as_deref();
+    write_merge(
+        merge,
+        on_conflict,
+        &raws[1],
+        merge_args.indent_length,
+        output,
+        config,
+    )
+}
+
+// The strategy named by `--on-conflict`
+fn on_conflict(name: Option<&str>) -> Result<Option<merge::OnConflict>, String> {
+    match name {
+        None => Ok(None),
+        Some(name) => merge::OnConflict::from_name(name).map(Some).ok_or_else(|| {
+            format!(
+                "Unknown conflict strategy '{}', expected error, ours or theirs",
+                name
+            )
+        }),
+    }
+}
 
-        if format_args.write {
-            if let Err(err) = std::fs::write(file_path, formatted) {
-                println!("Unable to write to file, reason: {}", err);
+// Settles the conflicts of a merge and writes it formatted like our file, `ours` is its text
+fn write_merge(
+    mut merge: merge::Merge,
+    on_conflict: Option<merge::OnConflict>,
+    ours: &str,
+    indent_length: Option<usize>,
+    output: Option<&str>,
+    config: &config::Config,
+) -> ExitCode {
+    // Conflicts at a pointer of the config file are settled its way, the others by `--on-conflict`
+    let on_conflict = on_conflict
+        .or(config.on_conflict)
+        .unwrap_or(merge::OnConflict::Error);
+    merge.resolve(|at| config.on_conflict_at(at).unwrap_or(on_conflict));
+
+    let describe = |value: &Option<parser::Json>| match value {
+        Some(value) => value.to_string(),
+        None => "removed".to_string(),
+    };
+
+    for conflict in &merge.conflicts {
+        let pointer = if conflict.pointer.is_empty() {
+            "the document root"
+        } else {
+            &conflict.pointer
+        };
+
+        eprintln!(
+            "Conflict at {}: ours {}, theirs {}",
+            pointer,
+            describe(&conflict.ours),
+            describe(&conflict.theirs)
+        );
+    }
+
+    // Our file keeps its indent, tabs included, its final newline and its byte order mark
+    let style = init::indent(ours);
+    let tabs = indent_length.is_none() && style == Some(init::Indent::Tabs);
+    let indent_length = match (indent_length, style) {
+        (Some(n), _) => n,
+        (None, Some(init::Indent::Spaces(n))) => n,
+        (None, Some(init::Indent::Tabs)) => 1,
+        (None, None) if !ours.trim().contains('\n') => 0,
+        (None, None) => config.indent_length.unwrap_or(4),
+    };
+    let options = formatter::FormatOptions {
+        indent_length,
+        ..formatter::FormatOptions::default()
+    };
+
+    let mut formatted = merge::format_merge(&merge, &options);
+
+    if tabs {
+        formatted = formatted
+            .lines()
+            .map(|line| {
+                let spaces = line.len() - line.trim_start_matches(' ').len();
+                "\t".repeat(spaces) + &line[spaces..]
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    formatted = formatter::with_bom(ours, formatted, &options);
+
+    if ours.ends_with('\n') {
+        formatted.push('\n');
+    }
+
+    match output {
+        Some(output) => {
+            if let Err(err) = std::fs::write(output, formatted) {
+                eprintln!("Unable to write to file, reason: {}", err);
                 return ExitCode::FAILURE;
             }
+        }
+        None => {
+            if !formatted.ends_with('\n') {
+                formatted.push('\n');
+            }
+
+            print!("{}", formatted)
+        }
+    }
+
+    if merge.conflicts.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// Prints the file with sorted keys for git diff, files that are not valid JSON are printed as is
+fn git_textconv(
+    textconv_args: &args::GitTextconvArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+) -> ExitCode {
+    let raw = match read_file(&textconv_args.file, decoding, config, locale) {
+        Ok((raw, _)) => raw,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let options = parser_options(args, config).unwrap();
+    let value = parser::parse_tokens_with_options(lexer::tokens(&raw), options).ok();
+
+    match value {
+        Some(mut value) => {
+            for path in &textconv_args.ignore {
+                pointer::remove_all(&mut value, path);
+            }
+
+            let options = formatter::FormatOptions {
+                sort_keys: true,
+                ..formatter::FormatOptions::default()
+            };
+
+            println!("{}", formatter::format_json_with_options(&value, &options));
+        }
+        None => print!("{}", raw),
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn dupes(dupes_args: &args::DupesArgs, decoding: Decoding) -> ExitCode {
+    let (groups, invalid) = match dupes::find(
+        Path::new(&dupes_args.dir),
+        &dupes_args.ignore,
+        decoding.encoding,
+    ) {
+        Ok(found) => found,
+        Err(err) => {
+            eprintln!("Unable to read {}, reason: {}", dupes_args.dir, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for path in &invalid {
+        eprintln!("Skipped {}, it is not valid JSON", path.display());
+    }
+
+    for group in &groups {
+        println!("{} identical files:", group.len());
+
+        for path in group {
+            println!("  {}", path.display());
+        }
+    }
+
+    if groups.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// The code and severity of a problem that counts toward the exit status
+type Problem = (&'static str, report::Severity);
+
+// Problems that do not stop parsing, reported as warnings: keys the duplicate key policy let
+// through, trailing commas, a byte order mark and the lints of `validate --as-schema` and
+// `--preset`, where lints breaking the specification are errors. Returns the code of the first
+// one that counts toward the exit status with the highest severity, and the report of all of
+// them. Duplicate keys and trailing commas the options allow are reported but do not count
+fn warnings(
+    raw: &str,
+    tokens: &[lexer::Token],
+    value: &parser::Json,
+    duplicates: &[parser::DuplicateKey],
+    args: &args::JoxideArgs,
+    reporter: &Reporter,
+) -> Result<(Option<Problem>, String), String> {
+    let warning = report::Severity::Warning;
+    let mut severity = warning;
+    let mut codes = vec![];
+    let mut lints = String::new();
+    let mut warnings = String::new();
+
+    if let JoxideSubcommand::Validate(ref validate_args) = args.sub_command {
+        let draft = match validate_args.schema_draft.as_deref() {
+            None => None,
+            Some(name) => Some(jsonschema::Draft::from_name(name).ok_or_else(|| {
+                format!(
+                    "Unknown JSON Schema draft '{}', expected 2020-12, 2019-09, 07, 06 or 04",
+                    name
+                )
+            })?),
+        };
+
+        let (code, found) = match validate_args.preset.as_deref() {
+            None if validate_args.as_schema => ("schema", schema::lint(value, draft)),
+            None => ("", vec![]),
+            Some("openapi") => ("openapi", openapi::lint(value)),
+            Some(other) => {
+                return Err(format!(
+                    "Unknown preset '{}', available presets are openapi",
+                    other
+                ));
+            }
+        };
+        let mut found: Vec<_> = found.into_iter().map(|lint| (code, lint)).collect();
+
+        if let Some(ref schema_path) = validate_args.schema {
+            let options = jsonschema::Options {
+                draft,
+                format_assertion: validate_args.format_assertion,
+            };
+            let failures = schema::validate(value, load_schema(schema_path)?, options);
+            found.extend(failures.into_iter().map(|lint| ("schema-validation", lint)));
+        }
+
+        for &(code, ref lint) in &found {
+            let location = pointer::locate(tokens, &lint.pointer).unwrap();
+            let token = match (lint.key, location.steps.last()) {
+                (true, Some((_, key))) => key,
+                _ => location.token,
+            };
+
+            codes.push(code);
+            severity = severity.max(lint.severity);
+            lints += &reporter.problem(
+                Some((token.line, token.col)),
+                lint.severity,
+                code,
+                &lint.message,
+            );
+        }
+    }
+
+    // Keys the policy let through are warnings at each repeated occurrence
+    for duplicate in duplicates {
+        for token in &duplicate.occurrences[1..] {
+            let parse_error =
+                parser::ParseError::new(parser::ParseErrorType::DuplicateKey, Some(token), None);
+            warnings += &reporter.parse_problem(&parse_error, warning);
+        }
+    }
+
+    if args.allow_trailing_commas || reporter.config.allow_trailing_commas {
+        for token in parser::find_trailing_commas(tokens) {
+            let parse_error =
+                parser::ParseError::new(parser::ParseErrorType::TrailingComma, Some(token), None);
+            warnings += &reporter.parse_problem(&parse_error, warning);
+        }
+    }
+
+    if args.warn_bom && raw.starts_with(lexer::BOM) {
+        codes.push("bom");
+        warnings += &reporter.problem(
+            Some((reporter.line_offset, reporter.col_offset)),
+            warning,
+            "bom",
+            "Byte order mark at the start of the input",
+        );
+    }
+
+    let problem = codes.first().map(|&code| (code, severity));
+    Ok((problem, lints + &warnings))
+}
+
+// Whether a problem makes validation fail, the same rule for `validate` and `watch`
+fn fails(problem: Option<Problem>, min_severity: report::Severity) -> bool {
+    matches!(problem, Some((_, severity)) if severity >= min_severity)
+}
+
+// The schema of `validate --schema`, read once for all the files checked against it
+fn load_schema(path: &str) -> Result<&'static parser::JsonOwned, String> {
+    static SCHEMA: OnceLock<Result<parser::JsonOwned, String>> = OnceLock::new();
+
+    let schema = SCHEMA.get_or_init(|| {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to open schema {}, reason: {}", path, err))?;
+        parser::parse_str(&raw)
+            .map(parser::Json::into_owned)
+            .map_err(|err| format!("Invalid schema {}: {}", path, err))
+    });
+
+    schema.as_ref().map_err(String::clone)
+}
+
+fn min_severity(validate_args: &args::ValidateArgs) -> Result<report::Severity, String> {
+    report::Severity::from_name(&validate_args.min_severity).ok_or_else(|| {
+        format!(
+            "Unknown severity '{}', expected warning or error",
+            validate_args.min_severity
+        )
+    })
+}
+
+// A failure without a position, like a file that can not be read, also added to the machine
+// readable report so it is not left without diagnostics
+fn failure(
+    file_path: &str,
+    code: &'static str,
+    message: String,
+    report: Option<&report::Report>,
+) -> String {
+    if let Some(report) = report {
+        report.diagnostic(report::Diagnostic {
+            file: file_path.to_string(),
+            line: None,
+            col: None,
+            severity: report::Severity::Error,
+            code,
+            message: message.clone(),
+        });
+    }
+
+    message
+}
+
+// The code and severity of the first problem that counts and the report of all the problems, `Err`
+// if the file can not be read or the options are not valid
+fn validate_file(
+    file_path: &str,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> Result<(Option<Problem>, String), String> {
+    if let Some(report) = report {
+        report.file(file_path);
+    }
+
+    let (raw, encoding) = read_file(file_path, decoding, config, locale)
+        .map_err(|message| failure(file_path, "read", message, report))?;
+    let reporter = Reporter {
+        file_path,
+        raw: &raw,
+        encoding,
+        line_offset: 0,
+        col_offset: 0,
+        config,
+        locale,
+        report,
+    };
+
+    let error = report::Severity::Error;
+    let tokens = match lexer::lex(&raw) {
+        Ok(tokens) => tokens,
+        Err(lex_error) => {
+            let code = lex_error.kind.code();
+            return Ok((Some((code, error)), reporter.lex_error(&lex_error)));
+        }
+    };
+
+    let options = parser_options(args, config)?;
+    let parsed = match options.duplicate_keys {
+        parser::DuplicateKeys::Error => {
+            parser::parse_with_options(&tokens, options).map(|value| (value, vec![]))
+        }
+        _ => parser::parse_with_duplicates(&tokens, options),
+    };
+
+    let (value, duplicates) = match parsed {
+        Ok(parsed) => parsed,
+        Err(parse_error) => {
+            let code = diagnostic::get_message_id(&parse_error).code();
+            return Ok((Some((code, error)), reporter.error(&parse_error)));
+        }
+    };
+
+    warnings(&raw, &tokens, &value, &duplicates, args, &reporter)
+}
+
+// Directories and glob patterns stand for several files, a missing file is reported as such
+fn is_single_file(path: &str) -> bool {
+    !Path::new(path).is_dir() && (!glob::is_pattern(path) || Path::new(path).is_file())
+}
+
+// Adds the files a path argument stands for, every .json file for directories
+fn add_targets(path: &str, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let paths = if glob::is_pattern(path) && !Path::new(path).is_file() {
+        glob::expand(path)
+    } else {
+        vec![PathBuf::from(path)]
+    };
+
+    if paths.is_empty() {
+        return Err(format!("No files match '{}'", path));
+    }
+
+    for path in paths {
+        let mut found = vec![];
+
+        if path.is_dir() {
+            dupes::json_files(&path, &mut found)
+                .map_err(|err| format!("Unable to read {}, reason: {}", path.display(), err))?;
+            found.sort();
         } else {
-            println!("{}", formatted);
+            found.push(path);
+        }
+
+        for path in found {
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The files given as path, directory or glob pattern, and those listed in --files-from
+fn validate_targets(
+    validate_args: &args::ValidateArgs,
+    decoding: Decoding,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+
+    if let Some(ref file) = validate_args.file {
+        add_targets(file, &mut files)?;
+    }
+
+    if let Some(ref list) = validate_args.files_from {
+        let (raw, _) = read_file(list, decoding, config, locale)?;
+
+        for line in raw.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                add_targets(line, &mut files)?;
+            }
+        }
+    }
+
+    if files.is_empty() {
+        return Err("No files to validate".to_string());
+    }
+
+    Ok(files)
+}
+
+// Validates each file, reporting the first error or the warnings of each file and a summary.
+// Files with only warnings below `min_severity`, or only allowed syntax, count as valid
+fn validate_files(
+    files: &[PathBuf],
+    decoding: Decoding,
+    min_severity: report::Severity,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> ExitCode {
+    let mut summary = summary::Summary::default();
+
+    for path in files {
+        match validate_file(
+            &path.to_string_lossy(),
+            decoding,
+            args,
+            config,
+            locale,
+            report,
+        ) {
+            Ok((problem, report)) => {
+                eprint!("{}", report);
+
+                match problem {
+                    Some((code, _)) if fails(problem, min_severity) => summary.invalid(path, code),
+                    _ => summary.valid(),
+                }
+            }
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    print!("{}", summary.render());
+
+    if summary.is_valid() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn format_options(
+    format_args: &args::FormatArgs,
+    config: &config::Config,
+) -> Result<formatter::FormatOptions, String> {
+    let embedded_json = match format_args.format_embedded_json.as_deref() {
+        None => None,
+        Some("inline") => Some(formatter::EmbeddedJson::Inline),
+        Some("escaped") => Some(formatter::EmbeddedJson::Escaped),
+        Some(other) => {
+            return Err(format!(
+                "Unknown embedded JSON style '{}', expected inline or escaped",
+                other
+            ))
         }
+    };
+
+    let mut sort_arrays = config.sort_arrays.clone();
+
+    for sort in &format_args.sort_array {
+        let (array, key) = sort
+            .rsplit_once('=')
+            .filter(|(array, key)| pointer::split(array).is_some() && pointer::split(key).is_some())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid --sort-array '{}', expected <array pointer>=<key pointer>",
+                    sort
+                )
+            })?;
+
+        sort_arrays.push(formatter::ArraySort {
+            array: array.to_string(),
+            key: key.to_string(),
+        });
+    }
+
+    Ok(formatter::FormatOptions {
+        indent_length: format_args
+            .indent_length
+            .or(config.indent_length)
+            .unwrap_or(4),
+        embedded_json,
+        sort_keys: format_args.sort_keys || config.sort_keys,
+        sort_arrays,
+        bom: if format_args.strip_bom {
+            formatter::Bom::Strip
+        } else {
+            formatter::Bom::Preserve
+        },
+        ..formatter::FormatOptions::default()
+    })
+}
+
+// Writes back to the file with --write, otherwise prints with a trailing newline if `newline`
+fn write_formatted(
+    formatted: &str,
+    newline: bool,
+    format_args: &args::FormatArgs,
+    encoding: Encoding,
+    config: &config::Config,
+) -> ExitCode {
+    if !format_args.write {
+        let formatted = if format_args.color {
+            Cow::Owned(color::colorize(formatted, &config.theme))
+        } else {
+            Cow::Borrowed(formatted)
+        };
+
+        print!("{}{}", formatted, if newline { "\n" } else { "" });
+        return ExitCode::SUCCESS;
+    }
+
+    if format_args.file == "-" {
+        eprintln!("Can not use --write when reading from standard input");
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(err) = std::fs::write(&format_args.file, encoding.encode_json(formatted)) {
+        eprintln!("Unable to write to file, reason: {}", err);
+        return ExitCode::FAILURE;
     }
 
     ExitCode::SUCCESS
 }
+
+// Formats each document of a framed input, stopping at the first invalid one
+fn format_documents(
+    reporter: &Reporter,
+    delimiter: framing::Delimiter,
+    options: &formatter::FormatOptions,
+    args: &args::JoxideArgs,
+) -> Result<String, String> {
+    let mut formatted = vec![];
+
+    for (start, document) in delimiter.split(reporter.raw) {
+        let before = &reporter.raw[..start];
+        let line = before.matches('\n').count();
+        let col = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+            .chars()
+            .count();
+
+        let parser_options = parser_options(args, reporter.config).unwrap();
+        let value = reporter.parse(document, (line, col), parser_options)?;
+        formatted.push(formatter::format_json_with_options(&value, options));
+    }
+
+    Ok(delimiter.join(&formatted))
+}
+
+// The parser options of the arguments shared by every subcommand
+fn parser_options(
+    args: &args::JoxideArgs,
+    config: &config::Config,
+) -> Result<parser::ParserOptions<'static>, String> {
+    let duplicate_keys =
+        parser::DuplicateKeys::from_name(&args.duplicate_keys).ok_or_else(|| {
+            format!(
+                "Unknown duplicate key policy '{}', expected error, first-wins or last-wins",
+                args.duplicate_keys
+            )
+        })?;
+
+    // Formatting and dropping the document recurse, deeper would overflow the stack
+    let max_depth = args.max_depth.unwrap_or(parser::DEFAULT_MAX_DEPTH);
+    if max_depth > parser::MAX_SAFE_DEPTH {
+        return Err(format!(
+            "--max-depth can be at most {}",
+            parser::MAX_SAFE_DEPTH
+        ));
+    }
+
+    Ok(parser::ParserOptions {
+        memory_limit: args.memory_limit,
+        duplicate_keys,
+        max_depth,
+        raw_numbers: args.raw_numbers,
+        allow_trailing_content: args.allow_trailing_content,
+        allow_comments: args.allow_comments || config.allow_comments,
+        allow_trailing_commas: args.allow_trailing_commas || config.allow_trailing_commas,
+        allow_non_finite: args.allow_non_finite,
+        ..parser::ParserOptions::default()
+    })
+}
+
+// Prints the values at the path as they are found, reading the input in chunks so it is never
+// held whole. Only an invalid input is read again whole, for the parser to explain the error
+fn query(
+    query_args: &args::QueryArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+    report: Option<&report::Report>,
+) -> ExitCode {
+    let file_path = &query_args.file;
+
+    if let Some(report) = report {
+        report.file(file_path);
+    }
+
+    // Paths the walk can't follow, like `$..id`, filters or negative indexes, are run by
+    // `jsonpath` over the whole document
+    let (steps, json_path) = match assertion::parse_path(&query_args.expression) {
+        Ok(steps) => (steps, None),
+        Err(_) => match jsonpath::JsonPath::parse(&query_args.expression) {
+            Ok(json_path) => (vec![], Some(json_path)),
+            Err(path_error) => {
+                eprintln!("{}", path_error);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let options = match parser_options(args, config) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Strings holding JSON are printed as the document they hold, indented
+    let format_options = formatter::FormatOptions {
+        indent_length: config.indent_length.unwrap_or(4),
+        embedded_json: Some(formatter::EmbeddedJson::Inline),
+        max_embedded_depth: query_args.decode_depth,
+        ..formatter::FormatOptions::default()
+    };
+    let print = |value: &parser::Json| {
+        if query_args.decode_nested {
+            println!(
+                "{}",
+                formatter::format_json_with_options(value, &format_options)
+            )
+        } else {
+            println!("{}", value)
+        }
+    };
+
+    // The walk can't tell which value of a repeated key is kept last before its object ends
+    let streamed = json_path.is_none() && options.duplicate_keys != parser::DuplicateKeys::LastWins;
+
+    if streamed {
+        let input: Box<dyn Read> = if file_path == "-" {
+            Box::new(std::io::stdin().lock())
+        } else {
+            match std::fs::File::open(file_path) {
+                Ok(file) => Box::new(file),
+                Err(err) => {
+                    let message = format!("Unable to open file, reason: {}", err);
+                    eprintln!("{}", failure(file_path, "read", message, report));
+                    return ExitCode::FAILURE;
+                }
+            }
+        };
+
+        // Matches are printed as they are found, an error later in the document comes after them
+        let mut failed = false;
+        let valid = query::query(
+            input,
+            decoding.encoding,
+            decoding.lossy,
+            &steps,
+            &options,
+            |matched| {
+                let options = parser::ParserOptions {
+                    observer: None,
+                    cancellation: None,
+                    ..options
+                };
+
+                match parser::parse_str_with_options(&matched, options) {
+                    Ok(value) if !failed => print(&value),
+                    _ => failed = true,
+                }
+            },
+        );
+
+        match valid {
+            Ok(true) if !failed => return ExitCode::SUCCESS,
+            Err(message) if file_path == "-" => {
+                let message = format!("Unable to read {}, reason: {}", file_path, message);
+                eprintln!("{}", failure(file_path, "read", message, report));
+                return ExitCode::FAILURE;
+            }
+            _ if file_path == "-" => {
+                let message = "Standard input is not a valid document".to_string();
+                eprintln!("{}", failure(file_path, "invalid", message, report));
+                return ExitCode::FAILURE;
+            }
+            _ => {}
+        }
+    }
+
+    let (raw, encoding) = match read_file(file_path, decoding, config, locale) {
+        Ok(read) => read,
+        Err(message) => {
+            eprintln!("{}", failure(file_path, "read", message, report));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let reporter = Reporter {
+        file_path,
+        raw: &raw,
+        encoding,
+        line_offset: args.line_offset,
+        col_offset: args.col_offset,
+        config,
+        locale,
+        report,
+    };
+
+    let value = match reporter.parse(&raw, (0, 0), options) {
+        Ok(value) => value,
+        Err(message) => {
+            eprint!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // A file the walk stopped at is read again only for the parser to explain why, whatever was
+    // matched before that has been printed already
+    if streamed {
+        let message = "The file is not a valid document".to_string();
+        eprintln!("{}", failure(file_path, "invalid", message, report));
+        return ExitCode::FAILURE;
+    }
+
+    match json_path.or_else(|| jsonpath::JsonPath::parse(&query_args.expression).ok()) {
+        Some(json_path) => {
+            json_path
+                .query(&value)
+                .iter()
+                .for_each(|node| print(node.value));
+            ExitCode::SUCCESS
+        }
+        None => {
+            let message = "The path can only be followed without --duplicate-keys last-wins";
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: &str) -> std::io::Result<std::process::ExitStatus> {
+    if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).status()
+    } else {
+        Command::new("sh").args(["-c", command]).status()
+    }
+}
+
+// Validates the file every time it changes, running the command after each successful validation
+fn watch(
+    watch_args: &args::WatchArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+) -> ExitCode {
+    let file_path = &watch_args.file;
+    let mut last_modified = None;
+    let mut unavailable = false;
+
+    loop {
+        let modified = std::fs::metadata(file_path).and_then(|metadata| metadata.modified());
+
+        match modified {
+            Ok(modified) if Some(modified) != last_modified => {
+                last_modified = Some(modified);
+                unavailable = false;
+
+                match validate_file(file_path, decoding, args, config, locale, None) {
+                    // The default threshold of `validate`, so both agree on which files are valid
+                    Ok((problem, report)) if !fails(problem, report::Severity::Warning) => {
+                        eprint!("{}", report);
+                        println!("{} is valid", file_path);
+
+                        if let Some(ref command) = watch_args.exec {
+                            match run(command) {
+                                Ok(status) if !status.success() => {
+                                    eprintln!("'{}' failed, {}", command, status)
+                                }
+                                Ok(_) => {}
+                                Err(err) => {
+                                    eprintln!("Unable to run '{}', reason: {}", command, err)
+                                }
+                            }
+                        }
+                    }
+                    Ok((_, report)) => {
+                        eprint!("{}", report);
+
+                        if let Some(ref command) = watch_args.exec {
+                            eprintln!("Not running '{}', {} is not valid", command, file_path);
+                        }
+                    }
+                    Err(message) => eprintln!("{}", message),
+                }
+            }
+            Ok(_) => unavailable = false,
+            // Editors that save by replacing the file can make it missing for a moment, so it is
+            // checked again on the next poll, and validated again once it is back
+            Err(err) => {
+                if !unavailable {
+                    eprintln!("Unable to open file, reason: {}, retrying", err);
+                }
+
+                unavailable = true;
+                last_modified = None;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(watch_args.interval));
+    }
+}
+
+// Prints one line of a json lines file, false if it is not valid
+fn tail_line(
+    tail_args: &args::TailArgs,
+    args: &args::JoxideArgs,
+    filter: Option<&assertion::Assertion>,
+    reporter: &Reporter,
+) -> bool {
+    let options = parser_options(args, reporter.config).unwrap();
+    let value = match reporter.parse(reporter.raw, (0, 0), options) {
+        Ok(value) => value,
+        Err(message) => {
+            eprint!("{}", message);
+            return false;
+        }
+    };
+
+    if filter.is_some_and(|filter| filter.check(&value).is_err()) {
+        return true;
+    }
+
+    let formatted = formatter::format_json(&value, tail_args.indent_length);
+
+    if tail_args.color {
+        println!("{}", color::colorize(&formatted, &reporter.config.theme));
+    } else {
+        println!("{}", formatted);
+    }
+
+    true
+}
+
+fn tail(
+    tail_args: &args::TailArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+) -> ExitCode {
+    // Lines are split on newline bytes before decoding, there is no whole file to detect from
+    let encoding = decoding.encoding.unwrap_or(Encoding::Utf8);
+
+    if !encoding.is_ascii_compatible() {
+        eprintln!("tail does not support {} files", encoding.label());
+        return ExitCode::FAILURE;
+    }
+
+    let filter = match tail_args.filter.as_deref().map(assertion::parse) {
+        None => None,
+        Some(Ok(filter)) => Some(filter),
+        Some(Err(message)) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut follower = tail::Follower::new(&tail_args.file);
+    let mut first = true;
+    let mut valid = true;
+
+    loop {
+        let mut lines = match follower.read() {
+            Ok(lines) => lines,
+            Err(err) => {
+                eprintln!("Unable to open file, reason: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        if first {
+            if !tail_args.follow {
+                lines.extend(follower.partial());
+            }
+
+            lines.drain(..lines.len().saturating_sub(tail_args.lines));
+            first = false;
+        }
+
+        for (line_no, bytes) in lines {
+            let decoded = if decoding.lossy {
+                Ok(encoding.decode_lossy(&bytes))
+            } else {
+                encoding.decode(&bytes)
+            };
+
+            let line = match decoded {
+                Ok(line) => line,
+                Err(err) => {
+                    eprintln!("Unable to read line {}, reason: {}", line_no + 1, err);
+                    valid = false;
+                    continue;
+                }
+            };
+
+            let reporter = Reporter {
+                file_path: &tail_args.file,
+                raw: &line,
+                encoding,
+                line_offset: line_no,
+                col_offset: 0,
+                config,
+                locale,
+                report: None,
+            };
+
+            valid &= tail_line(tail_args, args, filter.as_ref(), &reporter);
+        }
+
+        if !tail_args.follow {
+            return if valid {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+
+        thread::sleep(Duration::from_millis(tail_args.interval));
+    }
+}
+
+fn bench(
+    bench_args: &args::BenchArgs,
+    decoding: Decoding,
+    args: &args::JoxideArgs,
+    config: &config::Config,
+    locale: diagnostic::Locale,
+) -> ExitCode {
+    if bench_args.files.is_empty() || bench_args.iterations == 0 {
+        eprintln!("Give at least one file and one iteration to measure");
+        return ExitCode::FAILURE;
+    }
+
+    let mut stats = vec![];
+
+    for file in &bench_args.files {
+        let result = read_file(file, decoding, config, locale).and_then(|(raw, _)| {
+            bench::run(file, &raw, bench_args.iterations, || {
+                parser_options(args, config).unwrap()
+            })
+        });
+
+        match result {
+            Ok(file_stats) => stats.push(file_stats),
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if bench_args.json {
+        print!("{}", bench::json(&stats, bench::peak_rss()));
+    } else {
+        print!("{}", bench::table(&stats, bench::peak_rss()));
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn init(init_args: &args::InitArgs, decoding: Decoding) -> ExitCode {
+    let dir = Path::new(&init_args.dir);
+    let path = dir.join(config::DEFAULT_CONFIG_FILE);
+
+    if path.exists() && !init_args.force {
+        eprintln!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let inferred = match init::infer(dir, decoding.encoding) {
+        Ok(inferred) => inferred,
+        Err(err) => {
+            eprintln!("Unable to read {}, reason: {}", dir.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(err) = std::fs::write(&path, init::starter(&inferred)) {
+        eprintln!("Unable to write to file, reason: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    println!("Wrote {}", path.display());
+
+    if init_args.git {
+        match init::add_gitattributes(dir) {
+            Ok(true) => println!("Added '{}' to .gitattributes", init::GITATTRIBUTES),
+            Ok(false) => println!(".gitattributes already uses joxide"),
+            Err(err) => {
+                eprintln!("Unable to write to .gitattributes, reason: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+
+        print!(
+            "Register the merge driver and diff filter with:\n{}",
+            init::GIT_CONFIG
+        );
+    }
+
+    if init_args.pre_commit {
+        print!("{}", init::PRE_COMMIT_HOOK);
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn joxide(args: &args::JoxideArgs, report: Option<&report::Report>) -> ExitCode {
+    let config = match config::Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let locale = match args.locale {
+        None => diagnostic::Locale::from_env(),
+        Some(ref tag) => match diagnostic::Locale::from_tag(tag) {
+            Some(locale) => locale,
+            None => {
+                eprintln!("Unknown locale '{}', available locales are en, es", tag);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let encoding = match args.encoding {
+        None => None,
+        Some(ref label) => match Encoding::from_label(label) {
+            Some(encoding) => Some(encoding),
+            None => {
+                let labels: Vec<_> = Encoding::ALL.iter().map(|e| e.label()).collect();
+                eprintln!(
+                    "Unknown encoding '{}', available encodings are {}",
+                    label,
+                    labels.join(", ")
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let decoding = Decoding {
+        encoding,
+        lossy: args.lossy,
+    };
+
+    // Every parse builds its options from the arguments with `parser_options`, which can only
+    // fail here
+    if let Err(message) = parser_options(args, &config) {
+        eprintln!("{}", message);
+        return ExitCode::FAILURE;
+    }
+
+    let file_path = match args.sub_command {
+        JoxideSubcommand::Validate(ref validate_args) => match validate_args.file {
+            Some(ref file) if validate_args.files_from.is_none() && is_single_file(file) => file,
+            _ => {
+                let targets = validate_targets(validate_args, decoding, &config, locale)
+                    .and_then(|files| Ok((files, min_severity(validate_args)?)));
+                let (files, min_severity) = match targets {
+                    Ok(targets) => targets,
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        return ExitCode::FAILURE;
+                    }
+                };
+
+                return validate_files(
+                    &files,
+                    decoding,
+                    min_severity,
+                    args,
+                    &config,
+                    locale,
+                    report,
+                );
+            }
+        },
+        JoxideSubcommand::Format(ref format_args) => &format_args.file,
+        JoxideSubcommand::Convert(ref convert_args) => &convert_args.file,
+        JoxideSubcommand::ExplainPath(ref explain_args) => &explain_args.file,
+        JoxideSubcommand::Assert(ref assert_args) => &assert_args.file,
+        JoxideSubcommand::Query(ref query_args) => {
+            return query(query_args, decoding, args, &config, locale, report)
+        }
+        JoxideSubcommand::Dupes(ref dupes_args) => return dupes(dupes_args, decoding),
+        JoxideSubcommand::Diff(ref diff_args) => {
+            return diff(diff_args, decoding, args, &config, locale, report)
+        }
+        JoxideSubcommand::Merge(ref merge_args) => {
+            return merge(merge_args, decoding, args, &config, locale, report)
+        }
+        JoxideSubcommand::Merge3(ref merge_args) => {
+            return merge3(merge_args, decoding, args, &config, locale, report)
+        }
+        JoxideSubcommand::GitMergeDriver(ref driver_args) => {
+            // git expects the result in the file holding our version
+            let merge_args = args::Merge3Args {
+                indent_length: driver_args.indent_length,
+                on_conflict: driver_args.on_conflict.clone(),
+                output: Some(driver_args.ours.clone()),
+                base: driver_args.base.clone(),
+                ours: driver_args.ours.clone(),
+                theirs: driver_args.theirs.clone(),
+            };
+
+            return merge3(&merge_args, decoding, args, &config, locale, report);
+        }
+        JoxideSubcommand::GitTextconv(ref textconv_args) => {
+            return git_textconv(textconv_args, decoding, args, &config, locale)
+        }
+        JoxideSubcommand::Init(ref init_args) => return init(init_args, decoding),
+        JoxideSubcommand::Watch(ref watch_args) => {
+            return watch(watch_args, decoding, args, &config, locale)
+        }
+        JoxideSubcommand::Tail(ref tail_args) => {
+            return tail(tail_args, decoding, args, &config, locale)
+        }
+        JoxideSubcommand::Bench(ref bench_args) => {
+            return bench(bench_args, decoding, args, &config, locale)
+        }
+    };
+
+    if let Some(report) = report {
+        report.file(file_path);
+    }
+
+    let (raw, encoding) = match read_file(file_path, decoding, &config, locale) {
+        Ok(read) => read,
+        Err(message) => {
+            eprintln!("{}", failure(file_path, "read", message, report));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let reporter = Reporter {
+        file_path,
+        raw: &raw,
+        encoding,
+        line_offset: args.line_offset,
+        col_offset: args.col_offset,
+        config: &config,
+        locale,
+        report,
+    };
+
+    if let JoxideSubcommand::Format(ref format_args) = args.sub_command {
+        if let Some(ref name) = format_args.delimiter {
+            let delimiter = match framing::Delimiter::from_name(name) {
+                Some(delimiter) => delimiter,
+                None => {
+                    eprintln!("Unknown delimiter '{}', expected blank, nul or rs", name);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let formatted = format_options(format_args, &config).and_then(|options| {
+                format_documents(&reporter, delimiter, &options, args)
+                    .map(|formatted| formatter::with_bom(&raw, formatted, &options))
+            });
+
+            return match formatted {
+                Ok(formatted) => write_formatted(&formatted, false, format_args, encoding, &config),
+                Err(message) => {
+                    eprint!("{}", message);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+    }
+
+    let options = match parser_options(args, &config) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Each line is lexed on its own, an error in one does not hide the others
+    if let JoxideSubcommand::Validate(ref validate_args) = args.sub_command {
+        if validate_args.ndjson {
+            let reports = ndjson::validate(
+                &raw,
+                args.line_offset,
+                args.col_offset,
+                || parser_options(args, &config).unwrap(),
+                |parse_error, position| {
+                    reporter.parse_problem_at(parse_error, Some(position), report::Severity::Error)
+                },
+                |lex_error| reporter.lex_error(lex_error),
+            );
+
+            for report in &reports {
+                eprint!("{}", report);
+            }
+
+            return if reports.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+    }
+
+    let mut tokens = match lexer::lex(&raw) {
+        Ok(tokens) => tokens,
+        Err(mut lex_error) => {
+            lex_error.offset(args.line_offset, args.col_offset);
+            eprint!("{}", reporter.lex_error(&lex_error));
+            return ExitCode::FAILURE;
+        }
+    };
+    lexer::offset(&mut tokens, args.line_offset, args.col_offset);
+
+    if let JoxideSubcommand::Validate(ref validate_args) = args.sub_command {
+        if validate_args.all_errors {
+            let errors = parser::find_errors(&tokens, options);
+
+            for parse_error in &errors {
+                eprint!("{}", reporter.error(parse_error));
+            }
+
+            return if errors.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+
+        if validate_args.all_duplicates {
+            let duplicates = match parser::find_duplicate_keys(&tokens, options) {
+                Ok(duplicates) => duplicates,
+                Err(parse_error) => {
+                    eprint!("{}", reporter.error(&parse_error));
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            for duplicate in &duplicates {
+                let (last, others) = duplicate.occurrences.split_last().unwrap();
+
+                for token in others {
+                    eprint!("{}", reporter.location((token.line, token.col)));
+                }
+
+                let parse_error =
+                    parser::ParseError::new(parser::ParseErrorType::DuplicateKey, Some(last), None);
+                eprint!("{}", reporter.error(&parse_error));
+            }
+
+            return if duplicates.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+    }
+
+    let parsed = match options.duplicate_keys {
+        parser::DuplicateKeys::Error => {
+            parser::parse_with_options(&tokens, options).map(|value| (value, vec![]))
+        }
+        _ => parser::parse_with_duplicates(&tokens, options),
+    };
+
+    let (value, duplicates) = match parsed {
+        Ok(parsed) => parsed,
+        Err(parse_error) => {
+            eprint!("{}", reporter.error(&parse_error));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (problem, warnings) = match warnings(&raw, &tokens, &value, &duplicates, args, &reporter) {
+        Ok(warnings) => warnings,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    eprint!("{}", warnings);
+
+    if let JoxideSubcommand::Validate(ref validate_args) = args.sub_command {
+        let min_severity = match min_severity(validate_args) {
+            Ok(severity) => severity,
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        // Syntax errors have already stopped before this point
+        if fails(problem, min_severity) {
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let JoxideSubcommand::Convert(ref convert_args) = args.sub_command {
+        if let Err(message) = convert(&value, convert_args) {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let JoxideSubcommand::ExplainPath(ref explain_args) = args.sub_command {
+        let explain = explain::Explain {
+            file_path,
+            raw: &raw,
+            line_offset: args.line_offset,
+            col_offset: args.col_offset,
+            context_lines: explain_args.context,
+        };
+
+        match explain.explain(&tokens, &value, &explain_args.pointer) {
+            Ok(explanation) => print!("{}", explanation),
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let JoxideSubcommand::Assert(ref assert_args) = args.sub_command {
+        let assertion = match assertion::parse(&assert_args.expression) {
+            Ok(assertion) => assertion,
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        if let Err(failure) = assertion.check(&value) {
+            let token = pointer::locate(&tokens, &failure.pointer)
+                .map(|location| (location.token.line, location.token.col));
+            let message = format!(
+                "Assertion {} failed: {}",
+                assert_args.expression, failure.message
+            );
+
+            eprint!(
+                "{}",
+                reporter.problem(token, report::Severity::Error, "assertion", &message)
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let JoxideSubcommand::Format(ref format_args) = args.sub_command {
+        let options = match format_options(format_args, &config) {
+            Ok(options) => options,
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let formatted = formatter::format_json_with_options(&value, &options);
+        let formatted = formatter::with_bom(&raw, formatted, &options);
+        return write_formatted(&formatted, true, format_args, encoding, &config);
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let args = args::from_env();
+
+    let report_format = match args.report_format.as_deref() {
+        None => args.report_file.as_deref().map(report::Format::from_path),
+        Some(name) => match report::Format::from_name(name) {
+            Some(format) => Some(format),
+            None => {
+                eprintln!(
+                    "Unknown report format '{}', expected json, sarif or junit",
+                    name
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let report = args.report_file.as_ref().map(|_| report::Report::default());
+    let exit_code = joxide(&args, report.as_ref());
+
+    if let (Some(path), Some(report), Some(format)) = (&args.report_file, report, report_format) {
+        if let Err(err) = std::fs::write(path, report.render(format)) {
+            eprintln!("Unable to write the report to {}, reason: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    exit_code
+}
@@ -0,0 +1,857 @@
+use diff;
+use formatter::{self, FormatOptions};
+use parser::{Json, Map};
+use pointer;
+use std::borrow::Cow;
+use std::iter;
+use std::mem;
+
+// A value both sides changed in different ways, `None` means the side removed it
+#[derive(Debug, PartialEq)]
+pub struct Conflict<'a> {
+    pub pointer: String,
+    pub ours: Option<Json<'a>>,
+    pub theirs: Option<Json<'a>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Merge<'a> {
+    // Conflicting values are left as they are in `ours`
+    pub value: Json<'a>,
+    pub conflicts: Vec<Conflict<'a>>,
+}
+
+// What `Merge::resolve` does with a conflict
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnConflict {
+    // Kept as a conflict, for the merge to fail
+    Error,
+    Ours,
+    Theirs,
+}
+
+impl OnConflict {
+    pub fn from_name(name: &str) -> Option<OnConflict> {
+        match name {
+            "error" => Some(OnConflict::Error),
+            "ours" => Some(OnConflict::Ours),
+            "theirs" => Some(OnConflict::Theirs),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Merge<'a> {
+    // Settles the conflicts `on_conflict` picks a side for given their pointer, the merged value
+    // takes the value of that side or loses the member when that side removed it
+    pub fn resolve<F>(&mut self, on_conflict: F)
+    where
+        F: Fn(&str) -> OnConflict,
+    {
+        for conflict in mem::take(&mut self.conflicts) {
+            match (on_conflict(&conflict.pointer), conflict.theirs) {
+                (OnConflict::Error, theirs) => self.conflicts.push(Conflict { theirs, ..conflict }),
+                (OnConflict::Ours, _) => {}
+                (OnConflict::Theirs, Some(theirs)) => {
+                    let _ = self.value.set_at(&conflict.pointer, theirs, false);
+                }
+                (OnConflict::Theirs, None) => {
+                    self.value.remove_at(&conflict.pointer);
+                }
+            }
+        }
+    }
+}
+
+fn object<'j, 'a>(value: Option<&'j Json<'a>>) -> Option<&'j Map<Cow<'a, str>, Json<'a>>> {
+    match value {
+        Some(Json::Object(obj)) => Some(obj),
+        _ => None,
+    }
+}
+
+fn merge_objects<'a>(
+    base: Option<&Map<Cow<'a, str>, Json<'a>>>,
+    ours: &Map<Cow<'a, str>, Json<'a>>,
+    theirs: &Map<Cow<'a, str>, Json<'a>>,
+    path: &str,
+    conflicts: &mut Vec<Conflict<'a>>,
+) -> Json<'a> {
+    let mut merged = Map::new();
+    let keys = ours
+        .keys()
+        .chain(theirs.keys().filter(|key| !ours.contains_key(*key)))
+        .chain(base.into_iter().flat_map(|base| {
+            base.keys()
+                .filter(|key| !ours.contains_key(*key) && !theirs.contains_key(*key))
+        }));
+
+    for key in keys {
+        let base_item = base.and_then(|base| base.get(key));
+        let item = merge_value(
+            base_item,
+            ours.get(key),
+            theirs.get(key),
+            &pointer::push(path, key),
+            conflicts,
+        );
+
+        if let Some(item) = item {
+            merged.insert(key.clone(), item);
+        }
+    }
+
+    Json::Object(merged)
+}
+
+// Aligns both sides on the base like `diff3`: the base items both sides kept split the arrays into
+// chunks, and a chunk only one side changed takes that side. A chunk both sides changed with as
+// many items on each is merged item by item, `None` when another one makes the array a conflict
+fn merge_arrays<'a>(
+    base: &[Json<'a>],
+    ours: &[Json<'a>],
+    theirs: &[Json<'a>],
+    path: &str,
+    conflicts: &mut Vec<Conflict<'a>>,
+) -> Option<Json<'a>> {
+    let mut theirs_kept = diff::align(base, theirs, None).into_iter().peekable();
+    let mut stable = vec![];
+
+    // Base items kept by both sides, with their index on each side
+    for (b, o) in diff::align(base, ours, None) {
+        while theirs_kept.next_if(|&(kept, _)| kept < b).is_some() {}
+
+        if let Some(&(_, t)) = theirs_kept.peek().filter(|&&(kept, _)| kept == b) {
+            stable.push((b, o, t));
+        }
+    }
+
+    let mut merged = vec![];
+    let mut item_conflicts = vec![];
+    let mut last = (0, 0, 0);
+    let ends = (base.len(), ours.len(), theirs.len());
+
+    for anchor in stable.into_iter().map(Some).chain(iter::once(None)) {
+        let (b, o, t) = anchor.unwrap_or(ends);
+        let (base_chunk, ours_chunk, theirs_chunk) =
+            (&base[last.0..b], &ours[last.1..o], &theirs[last.2..t]);
+
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            merged.extend_from_slice(theirs_chunk);
+        } else if theirs_chunk == base_chunk {
+            merged.extend_from_slice(ours_chunk);
+        } else if ours_chunk.len() == theirs_chunk.len() {
+            for (k, (ours_item, theirs_item)) in ours_chunk.iter().zip(theirs_chunk).enumerate() {
+                // Items only have a base when the base has as many of them
+                let base_item = base_chunk
+                    .get(k)
+                    .filter(|_| base_chunk.len() == ours_chunk.len());
+                let item = merge_value(
+                    base_item,
+                    Some(ours_item),
+                    Some(theirs_item),
+                    &pointer::push(path, &merged.len().to_string()),
+                    &mut item_conflicts,
+                );
+                merged.extend(item);
+            }
+        } else {
+            return None;
+        }
+
+        if anchor.is_some() {
+            merged.push(ours[o].clone());
+            last = (b + 1, o + 1, t + 1);
+        }
+    }
+
+    conflicts.append(&mut item_conflicts);
+    Some(Json::Array(merged))
+}
+
+fn merge_value<'a>(
+    base: Option<&Json<'a>>,
+    ours: Option<&Json<'a>>,
+    theirs: Option<&Json<'a>>,
+    path: &str,
+    conflicts: &mut Vec<Conflict<'a>>,
+) -> Option<Json<'a>> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+
+    if ours == base {
+        return theirs.cloned();
+    }
+
+    // Both sides changed the same object, a key added on both sides merges against nothing
+    if let (Some(ours_obj), Some(theirs_obj)) = (object(ours), object(theirs)) {
+        return Some(merge_objects(
+            object(base),
+            ours_obj,
+            theirs_obj,
+            path,
+            conflicts,
+        ));
+    }
+
+    if let (Some(Json::Array(ours_arr)), Some(Json::Array(theirs_arr))) = (ours, theirs) {
+        let base_arr = match base {
+            Some(Json::Array(base_arr)) => &base_arr[..],
+            _ => &[],
+        };
+
+        if let Some(merged) = merge_arrays(base_arr, ours_arr, theirs_arr, path, conflicts) {
+            return Some(merged);
+        }
+    }
+
+    conflicts.push(Conflict {
+        pointer: path.to_string(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    });
+
+    ours.cloned()
+}
+
+// Three way merge of two edits of `base`. Objects are merged key by key and arrays item by item
+// along the items both sides kept, any other value changed differently on both sides is a
+// conflict
+pub fn merge3<'a>(base: &Json<'a>, ours: &Json<'a>, theirs: &Json<'a>) -> Merge<'a> {
+    let mut conflicts = vec![];
+    let value = merge_value(Some(base), Some(ours), Some(theirs), "", &mut conflicts);
+
+    Merge {
+        value: value.unwrap_or_default(),
+        conflicts,
+    }
+}
+
+// Merge of two documents with no common ancestor, like `merge3` with nothing for a base: members
+// only one side has are kept and anything else both sides have differently is a conflict
+pub fn merge2<'a>(ours: &Json<'a>, theirs: &Json<'a>) -> Merge<'a> {
+    let mut conflicts = vec![];
+    let value = merge_value(None, Some(ours), Some(theirs), "", &mut conflicts);
+
+    Merge {
+        value: value.unwrap_or_default(),
+        conflicts,
+    }
+}
+
+// Stands for a conflict in the formatted merge until its lines are written
+const PLACEHOLDER: &str = "joxide-merge-conflict-";
+
+// The merged document formatted with `options`, with each conflict between git's conflict
+// markers: our value after `<<<<<<< ours`, theirs after `=======` and `>>>>>>> theirs` to end
+// it, where a side that removed the member has no line. A compact document is broken into lines
+// around each conflicting member only
+pub fn format_merge(merge: &Merge, options: &FormatOptions) -> String {
+    let format = |value: &Json| formatter::format_json_with_options(value, options);
+
+    if merge.conflicts.is_empty() {
+        return format(&merge.value);
+    }
+
+    let mut value = merge.value.clone();
+
+    for (index, conflict) in merge.conflicts.iter().enumerate() {
+        let placeholder = Json::String(format!("{}{}", PLACEHOLDER, index).into());
+        let _ = pointer::set(&mut value, &conflict.pointer, placeholder, false);
+    }
+
+    if options.indent_length == 0 {
+        return split_compact(&format(&value), merge, format);
+    }
+
+    let mut lines = vec![];
+
+    for line in format(&value).lines() {
+        let found = merge
+            .conflicts
+            .iter()
+            .enumerate()
+            .find_map(|(index, conflict)| {
+                let placeholder = format!("\"{}{}\"", PLACEHOLDER, index);
+                line.find(&placeholder)
+                    .map(|start| (conflict, start, start + placeholder.len()))
+            });
+
+        let (conflict, start, end) = match found {
+            Some(found) => found,
+            None => {
+                lines.push(line.to_string());
+                continue;
+            }
+        };
+
+        // Without the last member of an object, the one before it loses its comma
+        let removed = conflict.ours.is_none() || conflict.theirs.is_none();
+        let previous = match lines.last() {
+            Some(previous)
+                if removed
+                    && !line[end..].starts_with(',')
+                    && previous.ends_with(',')
+                    && !previous.starts_with(">>>>>>>") =>
+            {
+                lines.pop()
+            }
+            _ => None,
+        };
+
+        // Each side in place of the placeholder, with its key, comma and indent
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let side = |value: &Option<Json>, lines: &mut Vec<String>| match value {
+            Some(value) => {
+                lines.extend(previous.clone());
+                let formatted = format(value).replace('\n', &format!("\n{}", indent));
+                lines.push(format!("{}{}{}", &line[..start], formatted, &line[end..]));
+            }
+            None => lines.extend(
+                previous
+                    .as_ref()
+                    .map(|previous| previous.trim_end_matches(',').to_string()),
+            ),
+        };
+
+        lines.push("<<<<<<< ours".to_string());
+        side(&conflict.ours, &mut lines);
+        lines.push("=======".to_string());
+        side(&conflict.theirs, &mut lines);
+        lines.push(">>>>>>> theirs".to_string());
+    }
+
+    lines.join("\n")
+}
+
+// The start of the string `text` ends with, a key of compact output where quotes in it are escaped
+fn string_start(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut start = bytes.len() - 1;
+
+    loop {
+        start -= 1;
+        let backslashes = bytes[..start]
+            .iter()
+            .rev()
+            .take_while(|&&b| b == b'\\')
+            .count();
+
+        if bytes[start] == b'"' && backslashes % 2 == 0 {
+            return start;
+        }
+    }
+}
+
+// The lines of a compact merge: the text between conflicts as it is, and each conflicting member
+// with its key on a line of its own between the markers. A side without the member drops the
+// comma after it, or the one before it when it is the last member
+fn split_compact<F>(formatted: &str, merge: &Merge, format: F) -> String
+where
+    F: Fn(&Json) -> String,
+{
+    let mut lines = vec![];
+    let mut text = String::new();
+    let mut rest = formatted;
+
+    loop {
+        let found = merge
+            .conflicts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, conflict)| {
+                let placeholder = format!("\"{}{}\"", PLACEHOLDER, index);
+                rest.find(&placeholder)
+                    .map(|start| (start, start + placeholder.len(), conflict))
+            })
+            .min_by_key(|&(start, _, _)| start);
+
+        let (start, end, conflict) = match found {
+            Some(found) => found,
+            None => break,
+        };
+
+        let member_start = match rest[..start].strip_suffix(':') {
+            Some(key) => string_start(key),
+            None => start,
+        };
+        let member = &rest[member_start..start];
+        text += &rest[..member_start];
+        rest = &rest[end..];
+
+        let removed = conflict.ours.is_none() || conflict.theirs.is_none();
+        let (before, after) = if let Some(after_comma) = rest.strip_prefix(',') {
+            rest = after_comma;
+            ("", ",")
+        } else if removed && text.ends_with(',') {
+            text.pop();
+            (",", "")
+        } else {
+            ("", "")
+        };
+
+        lines.extend(Some(mem::take(&mut text)).filter(|text| !text.is_empty()));
+        let side = |value: &Option<Json>, lines: &mut Vec<String>| {
+            if let Some(value) = value {
+                lines.push(format!("{}{}{}{}", before, member, format(value), after));
+            }
+        };
+
+        lines.push("<<<<<<< ours".to_string());
+        side(&conflict.ours, &mut lines);
+        lines.push("=======".to_string());
+        side(&conflict.theirs, &mut lines);
+        lines.push(">>>>>>> theirs".to_string());
+    }
+
+    text += rest;
+    lines.extend(Some(text).filter(|text| !text.is_empty()));
+    lines.join("\n")
+}
+
+// What a null member of the other value does in `merge`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NullMerge {
+    // Replaces the member like any other value
+    #[default]
+    Set,
+    // Removes the member, like `merge_patch` does
+    Remove,
+    // Leaves the member as it is
+    Skip,
+}
+
+// How `merge` layers one value over another. Objects are always merged key by key, keeping the
+// keys of the target first
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MergeStrategy {
+    // Items of the other array are appended instead of replacing the array
+    pub concat_arrays: bool,
+    pub nulls: NullMerge,
+}
+
+// Merges `other` into `target`, values of `other` win over anything but objects
+pub fn merge<'a>(target: &mut Json<'a>, other: Json<'a>, strategy: MergeStrategy) {
+    if let Json::Raw(_) = target {
+        *target = target.parse_raw().into_owned();
+    }
+
+    let other = match other {
+        Json::Raw(_) => other.parse_raw().into_owned(),
+        other => other,
+    };
+
+    match (target, other) {
+        (Json::Object(obj), Json::Object(other)) => {
+            for (key, item) in other {
+                match (item, strategy.nulls) {
+                    (Json::Null, NullMerge::Skip) => {}
+                    (Json::Null, NullMerge::Remove) => {
+                        #[cfg(feature = "preserve_order")]
+                        obj.shift_remove(&key);
+                        #[cfg(not(feature = "preserve_order"))]
+                        obj.remove(&key);
+                    }
+                    (item, _) => match obj.get_mut(&key) {
+                        Some(existing) => merge(existing, item, strategy),
+                        None => {
+                            obj.insert(key, item);
+                        }
+                    },
+                }
+            }
+        }
+        (Json::Array(arr), Json::Array(other)) if strategy.concat_arrays => arr.extend(other),
+        (_, Json::Null) if strategy.nulls == NullMerge::Skip => {}
+        (target, other) => *target = other,
+    }
+}
+
+// Applies an RFC 7396 JSON Merge Patch: a null member removes the key, an object is merged into
+// the member (replacing it when that is not an object) and any other value replaces it
+pub fn merge_patch<'a>(target: &mut Json<'a>, patch: &Json) {
+    let patch = patch.parse_raw();
+    let members = match patch.as_object() {
+        Some(members) => members,
+        None => {
+            *target = patch.into_owned().into_owned();
+            return;
+        }
+    };
+
+    if let Json::Raw(_) = target {
+        *target = target.parse_raw().into_owned();
+    }
+
+    if target.as_object().is_none() {
+        *target = Json::Object(Map::new());
+    }
+
+    let obj = target.as_object_mut().unwrap();
+
+    for (key, item) in members {
+        match item {
+            Json::Null => {
+                #[cfg(feature = "preserve_order")]
+                obj.shift_remove(key.as_ref());
+                #[cfg(not(feature = "preserve_order"))]
+                obj.remove(key.as_ref());
+            }
+            item => {
+                let key = pointer::find_key(obj, key);
+                merge_patch(obj.entry(key).or_insert(Json::Null), item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer;
+    use parser;
+
+    #[test]
+    fn test_merge3() {
+        let base =
+            lexer::lex("{\"a\": 1, \"b\": {\"c\": 1, \"d\": 1}, \"e\": [1], \"f\": 1}").unwrap();
+        let ours =
+            lexer::lex("{\"a\": 2, \"b\": {\"c\": 2, \"d\": 1}, \"e\": [1, 2], \"g\": 1}").unwrap();
+        let theirs =
+            lexer::lex("{\"a\": 1, \"b\": {\"c\": 1, \"d\": 2}, \"e\": [1, 3], \"f\": 1}").unwrap();
+        let expected =
+            lexer::lex("{\"a\": 2, \"b\": {\"c\": 2, \"d\": 2}, \"e\": [1, 2], \"g\": 1}").unwrap();
+
+        let merge = merge3(
+            &parser::parse(&base).unwrap(),
+            &parser::parse(&ours).unwrap(),
+            &parser::parse(&theirs).unwrap(),
+        );
+
+        assert_eq!(merge.value, parser::parse(&expected).unwrap());
+        assert_eq!(
+            merge.conflicts,
+            [Conflict {
+                pointer: "/e/1".to_string(),
+                ours: Some(Json::from(2)),
+                theirs: Some(Json::from(3)),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge3_arrays() {
+        let merge = |base, ours, theirs| {
+            merge3(
+                &parser::parse_str(base).unwrap(),
+                &parser::parse_str(ours).unwrap(),
+                &parser::parse_str(theirs).unwrap(),
+            )
+        };
+
+        let head_and_tail = merge("[1, 2, 3]", "[0, 1, 2, 3]", "[1, 2, 3, 4]");
+        assert_eq!(
+            head_and_tail.value,
+            parser::parse_str("[0, 1, 2, 3, 4]").unwrap()
+        );
+        assert_eq!(head_and_tail.conflicts, []);
+
+        let removed_and_added = merge("[1, 2, 3]", "[1, 3]", "[1, 2, 3, 4]");
+        assert_eq!(
+            removed_and_added.value,
+            parser::parse_str("[1, 3, 4]").unwrap()
+        );
+        assert_eq!(removed_and_added.conflicts, []);
+
+        // Items changed on both sides are merged like any other value
+        let items = merge(
+            r#"[{"a": 1, "b": 1}, 2]"#,
+            r#"[{"a": 2, "b": 1}, 2, 3]"#,
+            r#"[{"a": 1, "b": 2}, 2]"#,
+        );
+        assert_eq!(
+            items.value,
+            parser::parse_str(r#"[{"a": 2, "b": 2}, 2, 3]"#).unwrap()
+        );
+        assert_eq!(items.conflicts, []);
+
+        let changed = merge("[1, 2, 3]", "[1, 5, 3]", "[1, 6, 3, 4]");
+        assert_eq!(changed.value, parser::parse_str("[1, 5, 3, 4]").unwrap());
+        assert_eq!(
+            changed.conflicts,
+            [Conflict {
+                pointer: "/1".to_string(),
+                ours: Some(Json::from(5)),
+                theirs: Some(Json::from(6)),
+            }]
+        );
+
+        // Changes of different lengths can not be told apart item by item
+        let uneven = merge("[1, 2]", "[1, 5, 6]", "[1, 7]");
+        assert_eq!(uneven.value, parser::parse_str("[1, 5, 6]").unwrap());
+        assert_eq!(
+            uneven.conflicts,
+            [Conflict {
+                pointer: "".to_string(),
+                ours: Some(parser::parse_str("[1, 5, 6]").unwrap()),
+                theirs: Some(parser::parse_str("[1, 7]").unwrap()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge3_removed() {
+        let base = lexer::lex("{\"a\": 1, \"b\": 1}").unwrap();
+        let ours = lexer::lex("{\"b\": 1}").unwrap();
+        let theirs = lexer::lex("{\"a\": 1, \"b\": 2, \"c\": {\"x\": 1}}").unwrap();
+        let expected = lexer::lex("{\"b\": 2, \"c\": {\"x\": 1}}").unwrap();
+
+        let merge = merge3(
+            &parser::parse(&base).unwrap(),
+            &parser::parse(&ours).unwrap(),
+            &parser::parse(&theirs).unwrap(),
+        );
+
+        assert_eq!(merge.value, parser::parse(&expected).unwrap());
+        assert_eq!(merge.conflicts, []);
+
+        let theirs = lexer::lex("{\"a\": 3, \"b\": 1}").unwrap();
+        let merge = merge3(
+            &parser::parse(&base).unwrap(),
+            &parser::parse(&ours).unwrap(),
+            &parser::parse(&theirs).unwrap(),
+        );
+
+        assert_eq!(
+            merge.conflicts,
+            [Conflict {
+                pointer: "/a".to_string(),
+                ours: None,
+                theirs: Some(Json::Number(3.0.into())),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = parser::parse_str(r#"{"a": 1, "b": 1, "c": {"d": 1, "e": 1}}"#).unwrap();
+        let ours = parser::parse_str(r#"{"b": 2, "c": {"d": 2, "e": 2}}"#).unwrap();
+        let theirs = parser::parse_str(r#"{"a": 3, "b": 3, "c": {"d": 3}}"#).unwrap();
+        let merge = || merge3(&base, &ours, &theirs);
+        let pointers = |merge: &Merge| -> Vec<String> {
+            merge
+                .conflicts
+                .iter()
+                .map(|conflict| conflict.pointer.clone())
+                .collect()
+        };
+
+        let mut ours_wins = merge();
+        ours_wins.resolve(|_| OnConflict::Ours);
+        assert_eq!(ours_wins.value, ours);
+        assert_eq!(ours_wins.conflicts, []);
+
+        let mut theirs_wins = merge();
+        theirs_wins.resolve(|_| OnConflict::Theirs);
+        assert!(theirs_wins.value.semantic_eq(&theirs, Default::default()));
+
+        let mut mixed = merge();
+        mixed.resolve(|pointer| match pointer {
+            "/a" => OnConflict::Theirs,
+            _ if pointer::matches("/c/*", pointer) => OnConflict::Ours,
+            _ => OnConflict::Error,
+        });
+        assert_eq!(
+            mixed.value,
+            parser::parse_str(r#"{"b": 2, "c": {"d": 2, "e": 2}, "a": 3}"#).unwrap()
+        );
+        assert_eq!(pointers(&mixed), ["/b"]);
+
+        let mut root = merge3(&Json::from(1), &Json::from(2), &Json::from(3));
+        root.resolve(|_| OnConflict::Theirs);
+        assert_eq!(root.value, Json::from(3));
+    }
+
+    #[test]
+    fn test_merge2() {
+        let ours = parser::parse_str(r#"{"a": 1, "b": {"c": 1, "d": [1]}, "e": [1, 2]}"#).unwrap();
+        let theirs =
+            parser::parse_str(r#"{"a": 1, "b": {"c": 2, "f": 3}, "e": [1, 3], "g": 4}"#).unwrap();
+
+        let mut merge = merge2(&ours, &theirs);
+        assert_eq!(
+            merge.value,
+            parser::parse_str(r#"{"a": 1, "b": {"c": 1, "d": [1], "f": 3}, "e": [1, 2], "g": 4}"#)
+                .unwrap()
+        );
+        let pointers: Vec<_> = merge.conflicts.iter().map(|c| c.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/b/c", "/e/1"]);
+
+        merge.resolve(|pointer| match pointer {
+            "/e/1" => OnConflict::Theirs,
+            _ => OnConflict::Error,
+        });
+        assert_eq!(merge.value["e"], parser::parse_str("[1, 3]").unwrap());
+        assert_eq!(merge.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn test_format_merge() {
+        let base = parser::parse_str(r#"{"a": {"b": 1, "c": 1}, "e": 1, "z": 1}"#).unwrap();
+        let ours = parser::parse_str(r#"{"a": {"b": [2, 3], "c": 1}, "e": 1}"#).unwrap();
+        let theirs = parser::parse_str(r#"{"a": {"b": 4, "c": 1}, "e": 1, "z": 5}"#).unwrap();
+        let merge = merge3(&base, &ours, &theirs);
+
+        assert_eq!(
+            format_merge(&merge, &FormatOptions::default()),
+            "{
+    \"a\": {
+<<<<<<< ours
+        \"b\": [
+            2,
+            3
+        ],
+=======
+        \"b\": 4,
+>>>>>>> theirs
+        \"c\": 1
+    },
+<<<<<<< ours
+    \"e\": 1
+=======
+    \"e\": 1,
+    \"z\": 5
+>>>>>>> theirs
+}"
+        );
+
+        let compact = FormatOptions {
+            indent_length: 0,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_merge(&merge, &compact),
+            r#"{"a":{
+<<<<<<< ours
+"b":[2,3],
+=======
+"b":4,
+>>>>>>> theirs
+"c":1},"e":1
+<<<<<<< ours
+=======
+,"z":5
+>>>>>>> theirs
+}"#
+        );
+
+        // Keys with escaped quotes stay with their value, in the same order whether or not the
+        // keys are sorted
+        let quoted = merge3(
+            &parser::parse_str(r#"{"a\"b": 1, "x": 1}"#).unwrap(),
+            &parser::parse_str(r#"{"a\"b": 2, "x": 2}"#).unwrap(),
+            &parser::parse_str(r#"{"a\"b": 3, "x": 1}"#).unwrap(),
+        );
+        assert_eq!(
+            format_merge(&quoted, &compact),
+            r#"{
+<<<<<<< ours
+"a\"b":2,
+=======
+"a\"b":3,
+>>>>>>> theirs
+"x":2}"#
+        );
+
+        let root = merge3(&Json::from(1), &Json::from(2), &Json::from(3));
+        assert_eq!(
+            format_merge(&root, &compact),
+            "<<<<<<< ours\n2\n=======\n3\n>>>>>>> theirs"
+        );
+
+        let merge = merge3(&base, &base, &base);
+        assert_eq!(
+            format_merge(&merge, &compact),
+            r#"{"a":{"b":1,"c":1},"e":1,"z":1}"#
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = || parser::parse_str(r#"{"a": 1, "b": {"c": [1], "d": 1}, "e": [1]}"#).unwrap();
+        let layer = || {
+            parser::parse_str(r#"{"b": {"c": [2], "d": null, "f": 1}, "e": 2, "g": null}"#).unwrap()
+        };
+
+        let mut value = base();
+        value.merge(layer(), MergeStrategy::default());
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"a": 1, "b": {"c": [2], "d": null, "f": 1}, "e": 2, "g": null}"#)
+                .unwrap()
+        );
+
+        let mut value = base();
+        let strategy = MergeStrategy {
+            concat_arrays: true,
+            nulls: NullMerge::Remove,
+        };
+        value.merge(layer(), strategy);
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"a": 1, "b": {"c": [1, 2], "f": 1}, "e": 2}"#).unwrap()
+        );
+
+        let mut value = base();
+        let strategy = MergeStrategy {
+            nulls: NullMerge::Skip,
+            ..MergeStrategy::default()
+        };
+        value.merge(layer(), strategy);
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"a": 1, "b": {"c": [2], "d": 1, "f": 1}, "e": 2}"#).unwrap()
+        );
+
+        let mut value = Json::from("x");
+        value.merge(Json::Null, strategy);
+        assert_eq!(value, Json::from("x"));
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // Examples of RFC 7396 Appendix A
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ];
+
+        for (target, patch, expected) in cases {
+            let mut value = parser::parse_str(target).unwrap();
+            value.merge_patch(&parser::parse_str(patch).unwrap());
+            assert_eq!(value, parser::parse_str(expected).unwrap(), "{}", patch);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use joxide::{lexer, parser};
+use std::thread;
+
+// Lines are split into one contiguous chunk per thread so the reports come back in line order.
+// Each line is parsed with the parser options `options` returns. Parse errors are reported with
+// their position, the end of the line for those at the end of a record
+pub fn validate<O, F, L>(
+    raw: &str,
+    line_offset: usize,
+    col_offset: usize,
+    options: O,
+    report: F,
+    report_lex: L,
+) -> Vec<String>
+where
+    O: Fn() -> parser::ParserOptions<'static> + Sync,
+    F: Fn(&parser::ParseError, (usize, usize)) -> String + Sync,
+    L: Fn(&lexer::LexError) -> String + Sync,
+{
+    let lines: Vec<(usize, &str)> = raw
+        .split_terminator('\n')
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect();
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = lines.len().div_ceil(threads).max(1);
+    let options = &options;
+    let report = &report;
+    let report_lex = &report_lex;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = lines
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    validate_chunk(chunk, line_offset, col_offset, options, report, report_lex)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+fn validate_chunk<O, F, L>(
+    lines: &[(usize, &str)],
+    line_offset: usize,
+    col_offset: usize,
+    options: &O,
+    report: &F,
+    report_lex: &L,
+) -> Vec<String>
+where
+    O: Fn() -> parser::ParserOptions<'static>,
+    F: Fn(&parser::ParseError, (usize, usize)) -> String,
+    L: Fn(&lexer::LexError) -> String,
+{
+    let mut reports = vec![];
+
+    for (line_no, line) in lines {
+        // Report positions relative to the whole file
+        let mut tokens = match lexer::lex(line) {
+            Ok(tokens) => tokens,
+            Err(mut lex_error) => {
+                lex_error.offset(line_no + line_offset, col_offset);
+                reports.push(report_lex(&lex_error));
+                continue;
+            }
+        };
+        lexer::offset(&mut tokens, line_no + line_offset, col_offset);
+
+        if let Err(parse_error) = parser::parse_with_options(&tokens, options()) {
+            let position = match parse_error.token {
+                Some(token) => (token.line, token.col),
+                None => (
+                    line_no + line_offset,
+                    col_offset + line.trim_end().chars().count(),
+                ),
+            };
+
+            reports.push(report(&parse_error, position));
+        }
+    }
+
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let raw = "{\"a\": 1}\n\n[1 2]\n{\"b\": true}\n{\"c\" 1}\n[01]\n{\"b\": \r\n";
+        let reports = validate(
+            raw,
+            0,
+            0,
+            parser::ParserOptions::default,
+            |parse_error, (line, col)| format!("{:?} {}:{}", parse_error.error_type, line, col),
+            |lex_error| format!("{:?} {}:{}", lex_error.kind, lex_error.line, lex_error.col),
+        );
+
+        assert_eq!(
+            reports,
+            [
+                "MissingCloseSquare 2:3",
+                "MissingColon 4:5",
+                "MalformedNumber 5:1",
+                "UnexpectedEnd 6:5"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let raw = "{\"a\": 1, \"a\": 2}\n[[1]]\n[1,]\n";
+        let report = |parse_error: &parser::ParseError, (line, col)| {
+            format!("{:?} {}:{}", parse_error.error_type, line, col)
+        };
+        let report_lex = |lex_error: &lexer::LexError| format!("{:?}", lex_error.kind);
+
+        assert_eq!(
+            validate(
+                raw,
+                0,
+                0,
+                parser::ParserOptions::default,
+                report,
+                report_lex
+            ),
+            ["DuplicateKey 0:9", "TrailingComma 2:2"]
+        );
+
+        let options = || parser::ParserOptions {
+            duplicate_keys: parser::DuplicateKeys::LastWins,
+            allow_trailing_commas: true,
+            max_depth: 1,
+            ..parser::ParserOptions::default()
+        };
+        assert_eq!(
+            validate(raw, 0, 0, options, report, report_lex),
+            ["DepthLimitExceeded 1:1"]
+        );
+    }
+}
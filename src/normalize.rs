@@ -0,0 +1,73 @@
+use parser::Json;
+use std::collections::HashSet;
+use visit;
+
+// Sorts the keys of every object. Without the `preserve_order` feature they always are
+pub fn sort_keys(value: &mut Json) {
+    #[cfg(feature = "preserve_order")]
+    visit::transform(value, |_, value| {
+        if let Json::Object(obj) = value {
+            obj.sort_keys();
+        }
+
+        true
+    });
+    #[cfg(not(feature = "preserve_order"))]
+    let _ = value;
+}
+
+// Removes the members of every object that are null. Null items of arrays are kept since their
+// position means something
+pub fn remove_nulls(value: &mut Json) {
+    visit::transform(value, |_, value| {
+        if let Json::Object(obj) = value {
+            obj.retain(|_, item| !matches!(item, Json::Null));
+        }
+
+        true
+    });
+}
+
+// Removes the items of every array that are equal to an earlier one. Nested arrays are deduped
+// first, so they compare by their remaining items
+pub fn dedupe_arrays(value: &mut Json) {
+    visit::transform(value, |_, value| {
+        if let Json::Array(arr) = value {
+            let mut seen = HashSet::new();
+            let mut keep: Vec<bool> = arr.iter().map(|item| seen.insert(item)).collect();
+            keep.reverse();
+            arr.retain(|_| keep.pop().unwrap_or(true));
+        }
+
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use parser;
+
+    #[test]
+    fn test_normalize() {
+        let raw = r#"{"b": [null, {"z": null, "a": [2, 1, 2]}, {"a": [2, 1]}, 1, 1.0], "a": null}"#;
+
+        let mut value = parser::parse_str(raw).unwrap();
+        value.sort_keys();
+        let keys: Vec<_> = value.entries().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a", "b"]);
+        let keys: Vec<_> = value["b"][1].entries().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a", "z"]);
+
+        value.remove_nulls();
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"b": [null, {"a": [2, 1, 2]}, {"a": [2, 1]}, 1, 1.0]}"#).unwrap()
+        );
+
+        value.dedupe_arrays();
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"b": [null, {"a": [2, 1]}, 1]}"#).unwrap()
+        );
+    }
+}
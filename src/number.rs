@@ -0,0 +1,441 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// A JSON number. Integers that fit in 64 bits are kept exactly instead of being rounded to the
+// nearest `f64`, which would change ids like 9007199254740993
+#[derive(Debug, Clone)]
+pub enum Number<'a> {
+    PosInt(u64),
+    // Only below zero, so every integer has a single representation
+    NegInt(i64),
+    Float(f64),
+    // The text of the input, kept by `ParserOptions::raw_numbers` and formatted as is
+    Raw(Cow<'a, str>),
+}
+
+// An optional minus, an integer part without leading zeros, then an optional fraction and exponent
+fn is_json_number(text: &str) -> bool {
+    let digits =
+        |part: &str| part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    let rest = text.strip_prefix('-').unwrap_or(text);
+    let int = digits(rest);
+
+    if int == 0 || (int > 1 && rest.starts_with('0')) {
+        return false;
+    }
+
+    let mut rest = &rest[int..];
+
+    if let Some(fraction) = rest.strip_prefix('.') {
+        if digits(fraction) == 0 {
+            return false;
+        }
+
+        rest = &fraction[digits(fraction)..];
+    }
+
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+
+        if digits(exponent) == 0 {
+            return false;
+        }
+
+        rest = &exponent[digits(exponent)..];
+    }
+
+    rest.is_empty()
+}
+
+impl<'a> Number<'a> {
+    // Reads the text of a number token, integers are the ones without a fraction or exponent.
+    // Only the JSON syntax is accepted, not the wider one of `f64::from_str` with `inf` or `.5`
+    pub fn parse(text: &str) -> Option<Number<'static>> {
+        if !is_json_number(text) {
+            return None;
+        }
+
+        let float: f64 = text.parse().ok()?;
+        let digits = text.strip_prefix('-').unwrap_or(text);
+
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            if let Ok(n) = text.parse::<u64>() {
+                return Some(Number::PosInt(n));
+            }
+
+            // `-0` stays a float to keep its sign
+            match text.parse::<i64>() {
+                Ok(n) if n < 0 => return Some(Number::NegInt(n)),
+                _ => {}
+            }
+        }
+
+        Some(Number::Float(float))
+    }
+
+    // Reads the `NaN`, `Infinity` and `-Infinity` words JavaScript writes for non-finite numbers
+    pub fn parse_non_finite(text: &str) -> Option<Number<'static>> {
+        match text {
+            "NaN" => Some(Number::Float(f64::NAN)),
+            "Infinity" => Some(Number::Float(f64::INFINITY)),
+            "-Infinity" => Some(Number::Float(f64::NEG_INFINITY)),
+            _ => None,
+        }
+    }
+
+    // Reads raw numbers, other numbers are returned as they are
+    pub fn parsed(&self) -> Number<'static> {
+        match *self {
+            Number::PosInt(n) => Number::PosInt(n),
+            Number::NegInt(n) => Number::NegInt(n),
+            Number::Float(n) => Number::Float(n),
+            Number::Raw(ref text) => Number::parse(text)
+                .or_else(|| Number::parse_non_finite(text))
+                .unwrap_or(Number::Float(f64::NAN)),
+        }
+    }
+
+    pub fn into_owned(self) -> Number<'static> {
+        match self {
+            Number::Raw(text) => Number::Raw(Cow::Owned(text.into_owned())),
+            _ => self.parsed(),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.parsed() {
+            Number::PosInt(n) => i64::try_from(n).ok(),
+            Number::NegInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.parsed() {
+            Number::PosInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    // Integers above 2^53 are rounded, like raw numbers with more digits than an `f64` holds
+    pub fn as_f64(&self) -> f64 {
+        match self.parsed() {
+            Number::PosInt(n) => n as f64,
+            Number::NegInt(n) => n as f64,
+            Number::Float(n) => n,
+            Number::Raw(_) => unreachable!(),
+        }
+    }
+
+    // Floats without a fraction count as integers, so `1.0` and `1` compare equal
+    fn as_i128(&self) -> Option<i128> {
+        match self.parsed() {
+            Number::PosInt(n) => Some(n as i128),
+            Number::NegInt(n) => Some(n as i128),
+            Number::Float(n) if n.fract() == 0.0 && n.abs() < 1e38 => Some(n as i128),
+            _ => None,
+        }
+    }
+
+    // A raw number too large for an `f64` as its sign, exponent and significant digits, so that
+    // `1e400` and `1e999` are told apart instead of both being infinity. The value is
+    // `0.digits * 10^exponent`, without leading or trailing zeros in `digits`
+    fn overflow(&self) -> Option<(bool, i64, String)> {
+        let text = match self {
+            Number::Raw(text) if is_json_number(text) && self.as_f64().is_infinite() => text,
+            _ => return None,
+        };
+
+        let negative = text.starts_with('-');
+        let text = text.trim_start_matches('-');
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(at) => (&text[..at], text[at + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all = format!("{}{}", int, fraction);
+        let digits = all.trim_start_matches('0');
+        let exponent = exponent + int.len() as i64 - (all.len() - digits.len()) as i64;
+
+        Some((negative, exponent, digits.trim_end_matches('0').to_string()))
+    }
+
+    // Orders NaN like `f64::total_cmp`, after all other numbers
+    pub fn total_cmp(&self, other: &Number) -> Ordering {
+        self.partial_cmp(other)
+            .unwrap_or_else(|| self.as_f64().total_cmp(&other.as_f64()))
+    }
+}
+
+impl PartialEq for Number<'_> {
+    fn eq(&self, other: &Number) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+// NaN is equal to itself, unlike `f64`, so `==` stays reflexive
+impl Eq for Number<'_> {}
+
+// Consistent with `==`: integers, and floats without a fraction, hash as the integer, so `1` and
+// `1.0` hash the same, every NaN the same, raw numbers too large for an `f64` by their digits and
+// other floats by their bits
+impl Hash for Number<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Some(overflow) = self.overflow() {
+            return overflow.hash(state);
+        }
+
+        match self.as_i128() {
+            Some(n) => n.hash(state),
+            None if self.as_f64().is_nan() => f64::NAN.to_bits().hash(state),
+            None => self.as_f64().to_bits().hash(state),
+        }
+    }
+}
+
+impl PartialOrd for Number<'_> {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        if let (Number::Raw(a), Number::Raw(b)) = (self, other) {
+            if a == b {
+                return Some(Ordering::Equal);
+            }
+        }
+
+        // Between the largest `f64` and infinity, ordered by their digits among themselves
+        let beyond = |negative: bool, other: f64| match (negative, other) {
+            _ if other.is_nan() => None,
+            (true, _) if other == f64::NEG_INFINITY => Some(Ordering::Greater),
+            (true, _) => Some(Ordering::Less),
+            (false, _) if other == f64::INFINITY => Some(Ordering::Less),
+            (false, _) => Some(Ordering::Greater),
+        };
+
+        match (self.overflow(), other.overflow()) {
+            (
+                Some((a_negative, a_exponent, a_digits)),
+                Some((b_negative, b_exponent, b_digits)),
+            ) => {
+                let magnitude = (a_exponent, a_digits).cmp(&(b_exponent, b_digits));
+
+                return Some(match (a_negative, b_negative) {
+                    (true, true) => magnitude.reverse(),
+                    (false, false) => magnitude,
+                    _ => b_negative.cmp(&a_negative),
+                });
+            }
+            (Some((negative, _, _)), None) => return beyond(negative, other.as_f64()),
+            (None, Some((negative, _, _))) => {
+                return beyond(negative, self.as_f64()).map(Ordering::reverse)
+            }
+            (None, None) => {}
+        }
+
+        match (self.as_i128(), other.as_i128()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ if self.as_f64().is_nan() && other.as_f64().is_nan() => Some(Ordering::Equal),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+}
+
+impl From<u64> for Number<'_> {
+    fn from(n: u64) -> Self {
+        Number::PosInt(n)
+    }
+}
+
+impl From<i64> for Number<'_> {
+    fn from(n: i64) -> Self {
+        if n < 0 {
+            Number::NegInt(n)
+        } else {
+            Number::PosInt(n as u64)
+        }
+    }
+}
+
+impl From<f64> for Number<'_> {
+    fn from(n: f64) -> Self {
+        Number::Float(n)
+    }
+}
+
+impl fmt::Display for Number<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::PosInt(n) => write!(f, "{}", n),
+            Number::NegInt(n) => write!(f, "{}", n),
+            // JSON has no text for them, so like jq NaN becomes `null` and infinities the
+            // largest doubles. `ParserOptions::allow_non_finite` keeps the words it reads as raw
+            Number::Float(n) if n.is_nan() => f.write_str("null"),
+            Number::Float(n) if n.is_infinite() => write!(f, "{:e}", f64::MAX.copysign(*n)),
+            Number::Float(n) => write!(f, "{}", n),
+            Number::Raw(text) => f.write_str(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let cases = vec![
+            ("9007199254740993", Some(Number::PosInt(9007199254740993))),
+            ("-9223372036854775808", Some(Number::NegInt(i64::MIN))),
+            (
+                "18446744073709551616",
+                Some(Number::Float(1.8446744073709552e19)),
+            ),
+            ("1.5e3", Some(Number::Float(1500.0))),
+            ("0", Some(Number::PosInt(0))),
+            ("abc", None),
+            ("01", None),
+            ("+1", None),
+            (".5", None),
+            ("1.", None),
+            ("1e", None),
+            ("inf", None),
+            ("NaN", None),
+            ("-0.5E-3", Some(Number::Float(-0.0005))),
+        ];
+
+        for (text, expected) in cases {
+            let number = Number::parse(text);
+            assert_eq!(number, expected, "{}", text);
+            assert_eq!(
+                format!("{:?}", number),
+                format!("{:?}", expected),
+                "{}",
+                text
+            );
+        }
+
+        assert_eq!(Number::parse("-0").unwrap().to_string(), "-0");
+        assert_eq!(
+            Number::parse("9007199254740993").unwrap().to_string(),
+            "9007199254740993"
+        );
+    }
+
+    #[test]
+    fn test_non_finite() {
+        for text in ["NaN", "Infinity", "-Infinity"] {
+            let number = Number::parse_non_finite(text).unwrap();
+            assert_eq!(Number::Raw(text.into()).parsed(), number);
+            assert!(!number.as_f64().is_finite());
+        }
+
+        assert_eq!(Number::Float(f64::NAN).to_string(), "null");
+        assert_eq!(
+            Number::Float(f64::INFINITY).to_string(),
+            "1.7976931348623157e308"
+        );
+        assert_eq!(
+            Number::Float(f64::NEG_INFINITY).to_string(),
+            "-1.7976931348623157e308"
+        );
+
+        assert_eq!(Number::parse_non_finite("inf"), None);
+        assert_eq!(Number::parse_non_finite("-NaN"), None);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let big = Number::PosInt(u64::MAX);
+        assert_eq!(big.as_u64(), Some(u64::MAX));
+        assert_eq!(big.as_i64(), None);
+        assert_eq!(Number::from(-2i64).as_i64(), Some(-2));
+        assert_eq!(Number::from(-2i64).as_u64(), None);
+        assert_eq!(Number::Float(2.5).as_f64(), 2.5);
+        assert_eq!(Number::Float(2.0).as_i64(), None);
+    }
+
+    #[test]
+    fn test_raw() {
+        let raw = Number::Raw("0.1000000000000000055".into());
+        assert_eq!(raw.to_string(), "0.1000000000000000055");
+        assert_eq!(raw.as_f64(), 0.1);
+        assert_eq!(raw, Number::Float(0.1));
+        assert_eq!(Number::Raw("1e400".into()).to_string(), "1e400");
+        assert_eq!(Number::Raw("1e400".into()), Number::Raw("1e400".into()));
+        assert_eq!(Number::Raw("12".into()).as_u64(), Some(12));
+    }
+
+    #[test]
+    fn test_raw_overflow() {
+        let raw = |text: &str| Number::Raw(text.to_string().into());
+
+        assert_ne!(raw("1e400"), raw("1e999"));
+        assert_ne!(raw("-1e400"), raw("-1e999"));
+        assert_ne!(raw("1e400"), raw("-1e400"));
+        assert_eq!(raw("1e400"), raw("10E399"));
+        assert_eq!(raw("-1.50e400"), raw("-0.15e401"));
+        assert_ne!(raw("1e400"), Number::Float(f64::INFINITY));
+        assert_ne!(raw("1e400"), Number::Float(f64::NAN));
+
+        assert!(raw("1e400") < raw("1e999"));
+        assert!(raw("-1e400") > raw("-1e999"));
+        assert!(raw("-1e999") < raw("1e400"));
+        assert!(raw("1e400") > Number::Float(f64::MAX));
+        assert!(raw("1e400") < Number::Float(f64::INFINITY));
+        assert!(raw("-1e400") > Number::Float(f64::NEG_INFINITY));
+        assert!(raw("-1e400") < Number::PosInt(0));
+        assert_eq!(raw("1e400").partial_cmp(&Number::Float(f64::NAN)), None);
+        assert_eq!(raw("Infinity"), Number::Float(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(Number::PosInt(1), Number::Float(1.0));
+        assert_ne!(
+            Number::PosInt(9007199254740993),
+            Number::Float(9007199254740992.0)
+        );
+        assert!(Number::NegInt(-1) < Number::PosInt(0));
+        assert!(Number::Float(0.5) < Number::PosInt(1));
+        assert_eq!(
+            Number::Float(f64::NAN).total_cmp(&Number::PosInt(1)),
+            Ordering::Greater
+        );
+        assert_eq!(Number::Float(f64::NAN), Number::Float(-f64::NAN));
+        assert_ne!(Number::Float(f64::NAN), Number::PosInt(1));
+        assert_eq!(
+            Number::Float(f64::NAN).partial_cmp(&Number::PosInt(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |n: Number| {
+            let mut hasher = DefaultHasher::new();
+            n.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        assert_eq!(hash(Number::PosInt(1)), hash(Number::Float(1.0)));
+        assert_eq!(hash(Number::Float(-0.0)), hash(Number::PosInt(0)));
+        assert_eq!(hash(Number::Raw("1.50".into())), hash(Number::Float(1.5)));
+        assert_ne!(hash(Number::Float(1.5)), hash(Number::Float(2.5)));
+        assert_eq!(
+            hash(Number::Float(f64::NAN)),
+            hash(Number::Float(-f64::NAN))
+        );
+        assert_ne!(
+            hash(Number::Raw("1e400".into())),
+            hash(Number::Raw("1e999".into()))
+        );
+        assert_eq!(
+            hash(Number::Raw("1e400".into())),
+            hash(Number::Raw("10e399".into()))
+        );
+    }
+}
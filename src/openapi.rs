@@ -0,0 +1,813 @@
+use joxide::jsonschema::Draft;
+use joxide::parser::Json;
+use joxide::pointer;
+use report::Severity;
+use schema::{self, Lint};
+
+// Schema object keywords OpenAPI adds to JSON Schema, and its `x-` extensions. The schemas of
+// 3.0 are checked as draft 4 ones, those of 3.1 as 2020-12 ones
+const SCHEMA_KEYWORDS: [&str; 9] = [
+    "deprecated",
+    "discriminator",
+    "example",
+    "externalDocs",
+    "nullable",
+    "readOnly",
+    "writeOnly",
+    "xml",
+    "x-",
+];
+
+// Everything checked outside the schemas is required by the specification
+fn missing(pointer: &str, message: &str, lints: &mut Vec<Lint>) {
+    lints.push(Lint {
+        pointer: pointer.to_string(),
+        key: false,
+        message: message.to_string(),
+        severity: Severity::Error,
+    });
+}
+
+fn refs(root: &Json, value: &Json, path: &str, lints: &mut Vec<Lint>) {
+    match value {
+        Json::Object(obj) => {
+            for (key, item) in obj {
+                let item_path = pointer::push(path, key);
+
+                match (key.as_ref(), item) {
+                    ("$ref", Json::String(reference)) => {
+                        if let Some(message) = schema::unresolved_ref(root, reference) {
+                            missing(&item_path, &message, lints);
+                        }
+                    }
+                    _ => refs(root, item, &item_path, lints),
+                }
+            }
+        }
+        Json::Array(arr) => {
+            for (index, item) in arr.iter().enumerate() {
+                refs(root, item, &pointer::push(path, &index.to_string()), lints);
+            }
+        }
+        _ => {}
+    }
+}
+
+// The type of a field of an OpenAPI object. Schemas are linted on their own and callbacks,
+// security requirements and OAuth flows are only checked to be objects
+enum Field {
+    Any,
+    Bool,
+    String,
+    Strings,
+    Enum(&'static [&'static str]),
+    Object(&'static Spec),
+    List(&'static Spec),
+    Map(&'static Spec),
+}
+
+// Checks an object with its pointer and the OpenAPI version
+type Rules = fn(&Json, &str, &str, &mut Vec<Lint>);
+
+// An object of the specification with its required fields and the fields it defines, plus the
+// checks that depend on the values of several fields or on the version
+struct Spec {
+    name: &'static str,
+    required: &'static [&'static str],
+    fields: &'static [(&'static str, Field)],
+    rules: Option<Rules>,
+}
+
+static ANY: Spec = Spec {
+    name: "",
+    required: &[],
+    fields: &[],
+    rules: None,
+};
+
+static OPENAPI: Spec = Spec {
+    name: "OpenAPI",
+    required: &["info"],
+    fields: &[
+        ("openapi", Field::String),
+        ("info", Field::Object(&INFO)),
+        ("jsonSchemaDialect", Field::String),
+        ("servers", Field::List(&SERVER)),
+        ("paths", Field::Map(&PATH_ITEM)),
+        ("webhooks", Field::Map(&PATH_ITEM)),
+        ("components", Field::Object(&COMPONENTS)),
+        ("security", Field::List(&ANY)),
+        ("tags", Field::List(&TAG)),
+        ("externalDocs", Field::Object(&EXTERNAL_DOCS)),
+    ],
+    rules: Some(openapi_rules),
+};
+
+static INFO: Spec = Spec {
+    name: "Info",
+    required: &["title", "version"],
+    fields: &[
+        ("title", Field::String),
+        ("summary", Field::String),
+        ("description", Field::String),
+        ("termsOfService", Field::String),
+        ("contact", Field::Object(&CONTACT)),
+        ("license", Field::Object(&LICENSE)),
+        ("version", Field::String),
+    ],
+    rules: None,
+};
+
+static CONTACT: Spec = Spec {
+    name: "Contact",
+    required: &[],
+    fields: &[
+        ("name", Field::String),
+        ("url", Field::String),
+        ("email", Field::String),
+    ],
+    rules: None,
+};
+
+static LICENSE: Spec = Spec {
+    name: "License",
+    required: &["name"],
+    fields: &[
+        ("name", Field::String),
+        ("identifier", Field::String),
+        ("url", Field::String),
+    ],
+    rules: None,
+};
+
+static SERVER: Spec = Spec {
+    name: "Server",
+    required: &["url"],
+    fields: &[
+        ("url", Field::String),
+        ("description", Field::String),
+        ("variables", Field::Map(&SERVER_VARIABLE)),
+    ],
+    rules: None,
+};
+
+static SERVER_VARIABLE: Spec = Spec {
+    name: "Server Variable",
+    required: &["default"],
+    fields: &[
+        ("enum", Field::Strings),
+        ("default", Field::String),
+        ("description", Field::String),
+    ],
+    rules: None,
+};
+
+static COMPONENTS: Spec = Spec {
+    name: "Components",
+    required: &[],
+    fields: &[
+        ("schemas", Field::Object(&ANY)),
+        ("responses", Field::Map(&RESPONSE)),
+        ("parameters", Field::Map(&PARAMETER)),
+        ("examples", Field::Map(&EXAMPLE)),
+        ("requestBodies", Field::Map(&REQUEST_BODY)),
+        ("headers", Field::Map(&HEADER)),
+        ("securitySchemes", Field::Map(&SECURITY_SCHEME)),
+        ("links", Field::Map(&LINK)),
+        ("callbacks", Field::Map(&ANY)),
+        ("pathItems", Field::Map(&PATH_ITEM)),
+    ],
+    rules: None,
+};
+
+static PATH_ITEM: Spec = Spec {
+    name: "Path Item",
+    required: &[],
+    fields: &[
+        ("$ref", Field::String),
+        ("summary", Field::String),
+        ("description", Field::String),
+        ("get", Field::Object(&OPERATION)),
+        ("put", Field::Object(&OPERATION)),
+        ("post", Field::Object(&OPERATION)),
+        ("delete", Field::Object(&OPERATION)),
+        ("options", Field::Object(&OPERATION)),
+        ("head", Field::Object(&OPERATION)),
+        ("patch", Field::Object(&OPERATION)),
+        ("trace", Field::Object(&OPERATION)),
+        ("servers", Field::List(&SERVER)),
+        ("parameters", Field::List(&PARAMETER)),
+    ],
+    rules: None,
+};
+
+static OPERATION: Spec = Spec {
+    name: "Operation",
+    required: &[],
+    fields: &[
+        ("tags", Field::Strings),
+        ("summary", Field::String),
+        ("description", Field::String),
+        ("externalDocs", Field::Object(&EXTERNAL_DOCS)),
+        ("operationId", Field::String),
+        ("parameters", Field::List(&PARAMETER)),
+        ("requestBody", Field::Object(&REQUEST_BODY)),
+        ("responses", Field::Map(&RESPONSE)),
+        ("callbacks", Field::Map(&ANY)),
+        ("deprecated", Field::Bool),
+        ("security", Field::List(&ANY)),
+        ("servers", Field::List(&SERVER)),
+    ],
+    rules: Some(operation_rules),
+};
+
+static PARAMETER: Spec = Spec {
+    name: "Parameter",
+    required: &["name", "in"],
+    fields: &[
+        ("name", Field::String),
+        ("in", Field::Enum(&["query", "header", "path", "cookie"])),
+        ("description", Field::String),
+        ("required", Field::Bool),
+        ("deprecated", Field::Bool),
+        ("allowEmptyValue", Field::Bool),
+        ("style", Field::String),
+        ("explode", Field::Bool),
+        ("allowReserved", Field::Bool),
+        ("schema", Field::Any),
+        ("example", Field::Any),
+        ("examples", Field::Map(&EXAMPLE)),
+        ("content", Field::Map(&MEDIA_TYPE)),
+    ],
+    rules: Some(parameter_rules),
+};
+
+static REQUEST_BODY: Spec = Spec {
+    name: "Request Body",
+    required: &["content"],
+    fields: &[
+        ("description", Field::String),
+        ("content", Field::Map(&MEDIA_TYPE)),
+        ("required", Field::Bool),
+    ],
+    rules: None,
+};
+
+static MEDIA_TYPE: Spec = Spec {
+    name: "Media Type",
+    required: &[],
+    fields: &[
+        ("schema", Field::Any),
+        ("example", Field::Any),
+        ("examples", Field::Map(&EXAMPLE)),
+        ("encoding", Field::Map(&ENCODING)),
+    ],
+    rules: None,
+};
+
+static ENCODING: Spec = Spec {
+    name: "Encoding",
+    required: &[],
+    fields: &[
+        ("contentType", Field::String),
+        ("headers", Field::Map(&HEADER)),
+        ("style", Field::String),
+        ("explode", Field::Bool),
+        ("allowReserved", Field::Bool),
+    ],
+    rules: None,
+};
+
+static RESPONSE: Spec = Spec {
+    name: "Response",
+    required: &["description"],
+    fields: &[
+        ("description", Field::String),
+        ("headers", Field::Map(&HEADER)),
+        ("content", Field::Map(&MEDIA_TYPE)),
+        ("links", Field::Map(&LINK)),
+    ],
+    rules: None,
+};
+
+static HEADER: Spec = Spec {
+    name: "Header",
+    required: &[],
+    fields: &[
+        ("description", Field::String),
+        ("required", Field::Bool),
+        ("deprecated", Field::Bool),
+        ("allowEmptyValue", Field::Bool),
+        ("style", Field::String),
+        ("explode", Field::Bool),
+        ("allowReserved", Field::Bool),
+        ("schema", Field::Any),
+        ("example", Field::Any),
+        ("examples", Field::Map(&EXAMPLE)),
+        ("content", Field::Map(&MEDIA_TYPE)),
+    ],
+    rules: None,
+};
+
+static EXAMPLE: Spec = Spec {
+    name: "Example",
+    required: &[],
+    fields: &[
+        ("summary", Field::String),
+        ("description", Field::String),
+        ("value", Field::Any),
+        ("externalValue", Field::String),
+    ],
+    rules: None,
+};
+
+static LINK: Spec = Spec {
+    name: "Link",
+    required: &[],
+    fields: &[
+        ("operationRef", Field::String),
+        ("operationId", Field::String),
+        ("parameters", Field::Object(&ANY)),
+        ("requestBody", Field::Any),
+        ("description", Field::String),
+        ("server", Field::Object(&SERVER)),
+    ],
+    rules: None,
+};
+
+static TAG: Spec = Spec {
+    name: "Tag",
+    required: &["name"],
+    fields: &[
+        ("name", Field::String),
+        ("description", Field::String),
+        ("externalDocs", Field::Object(&EXTERNAL_DOCS)),
+    ],
+    rules: None,
+};
+
+static EXTERNAL_DOCS: Spec = Spec {
+    name: "External Documentation",
+    required: &["url"],
+    fields: &[("description", Field::String), ("url", Field::String)],
+    rules: None,
+};
+
+static SECURITY_SCHEME: Spec = Spec {
+    name: "Security Scheme",
+    required: &["type"],
+    fields: &[
+        (
+            "type",
+            Field::Enum(&["apiKey", "http", "mutualTLS", "oauth2", "openIdConnect"]),
+        ),
+        ("description", Field::String),
+        ("name", Field::String),
+        ("in", Field::Enum(&["query", "header", "cookie"])),
+        ("scheme", Field::String),
+        ("bearerFormat", Field::String),
+        ("flows", Field::Object(&ANY)),
+        ("openIdConnectUrl", Field::String),
+    ],
+    rules: None,
+};
+
+fn openapi_rules(document: &Json, _path: &str, _version: &str, lints: &mut Vec<Lint>) {
+    if let Some(Json::Object(paths)) = document.pointer("/paths") {
+        for name in paths.keys() {
+            if !name.starts_with('/') && !name.starts_with("x-") {
+                lints.push(Lint {
+                    pointer: pointer::push("/paths", name),
+                    key: true,
+                    message: format!("Path '{}' should start with '/'", name),
+                    severity: Severity::Error,
+                });
+            }
+        }
+    }
+}
+
+// 3.1 made the responses of an operation optional
+fn operation_rules(operation: &Json, path: &str, version: &str, lints: &mut Vec<Lint>) {
+    if version.starts_with("3.0.") && operation.get("responses").is_none() {
+        missing(path, "Missing 'responses' in the Operation object", lints);
+    }
+}
+
+fn parameter_rules(parameter: &Json, path: &str, _version: &str, lints: &mut Vec<Lint>) {
+    if matches!(parameter.get("in"), Some(Json::String(location)) if location == "path")
+        && !matches!(parameter.get("required"), Some(Json::Bool(true)))
+    {
+        missing(path, "Path parameters should be 'required': true", lints);
+    }
+}
+
+fn expected(field: &Field) -> String {
+    match field {
+        Field::Any => "anything".to_string(),
+        Field::Bool => "a boolean".to_string(),
+        Field::String => "a string".to_string(),
+        Field::Strings => "an array of strings".to_string(),
+        Field::Enum(values) => format!("one of {}", values.join(", ")),
+        Field::Object(_) | Field::Map(_) => "an object".to_string(),
+        Field::List(_) => "an array".to_string(),
+    }
+}
+
+fn check_field(
+    name: &str,
+    value: &Json,
+    field: &Field,
+    path: &str,
+    version: &str,
+    lints: &mut Vec<Lint>,
+) {
+    let valid = match (field, value) {
+        (Field::Any, _) | (Field::Bool, Json::Bool(_)) | (Field::String, Json::String(_)) => true,
+        (Field::Strings, Json::Array(arr)) => {
+            arr.iter().all(|item| matches!(item, Json::String(_)))
+        }
+        (Field::Enum(values), Json::String(value)) => values.contains(&value.as_ref()),
+        (Field::Object(spec), Json::Object(_)) => {
+            check(value, spec, path, version, lints);
+            true
+        }
+        (Field::List(spec), Json::Array(arr)) => {
+            for (index, item) in arr.iter().enumerate() {
+                let index = index.to_string();
+                let item_path = pointer::push(path, &index);
+                check_field(
+                    &index,
+                    item,
+                    &Field::Object(spec),
+                    &item_path,
+                    version,
+                    lints,
+                );
+            }
+            true
+        }
+        (Field::Map(spec), Json::Object(obj)) => {
+            // Maps take `x-` extensions next to their entries
+            for (key, item) in obj {
+                if !key.starts_with("x-") {
+                    let item_path = pointer::push(path, key);
+                    check_field(key, item, &Field::Object(spec), &item_path, version, lints);
+                }
+            }
+            true
+        }
+        _ => false,
+    };
+
+    if !valid {
+        lints.push(Lint {
+            pointer: path.to_string(),
+            key: false,
+            message: format!("'{}' should be {}", name, expected(field)),
+            severity: Severity::Error,
+        });
+    }
+}
+
+// A `$ref` stands for the whole object, it is checked to resolve on its own
+fn check(value: &Json, spec: &Spec, path: &str, version: &str, lints: &mut Vec<Lint>) {
+    let obj = match value {
+        Json::Object(obj) if !obj.contains_key("$ref") => obj,
+        _ => return,
+    };
+
+    for field in spec.required {
+        if !obj.contains_key(*field) {
+            let message = format!("Missing '{}' in the {} object", field, spec.name);
+            missing(path, &message, lints);
+        }
+    }
+
+    for (key, item) in obj {
+        if let Some((_, field)) = spec.fields.iter().find(|(name, _)| name == key) {
+            check_field(key, item, field, &pointer::push(path, key), version, lints);
+        }
+    }
+
+    if let Some(rules) = spec.rules {
+        rules(value, path, version, lints);
+    }
+}
+
+// Checks the required fields and the types of the fields of the objects an OpenAPI 3.0 or 3.1
+// document defines, that every `$ref` within the document resolves and lints the schemas in
+// components/schemas. Unknown fields and the rules specific to each security scheme type are not
+// checked
+pub fn lint(document: &Json) -> Vec<Lint> {
+    let mut lints = vec![];
+
+    let version = match document.pointer("/openapi") {
+        Some(Json::String(version)) => version.to_string(),
+        Some(_) => {
+            missing(
+                "/openapi",
+                "'openapi' should be a version string",
+                &mut lints,
+            );
+            return lints;
+        }
+        None => {
+            missing(
+                "",
+                "Missing 'openapi' version, only OpenAPI 3.0 and 3.1 are supported",
+                &mut lints,
+            );
+            return lints;
+        }
+    };
+
+    if !version.starts_with("3.0.") && !version.starts_with("3.1.") {
+        let message = format!(
+            "Unsupported OpenAPI version '{}', expected 3.0 or 3.1",
+            version
+        );
+        missing("/openapi", &message, &mut lints);
+        return lints;
+    }
+
+    check(document, &OPENAPI, "", &version, &mut lints);
+
+    // 3.1 also accepts documents that only define components or webhooks
+    let sections: &[&str] = if version.starts_with("3.0.") {
+        &["paths"]
+    } else {
+        &["paths", "components", "webhooks"]
+    };
+
+    if sections
+        .iter()
+        .all(|section| document.pointer(&pointer::push("", section)).is_none())
+    {
+        let message = format!("Missing '{}'", sections.join("', '"));
+        missing("", &message, &mut lints);
+    }
+
+    refs(document, document, "", &mut lints);
+
+    // The schema object of 3.0 is not quite draft 4, only that of 3.1 has a meta-schema to check
+    if let Some(Json::Object(schemas)) = document.pointer("/components/schemas") {
+        let draft = match version.starts_with("3.0.") {
+            true => Draft::Draft4,
+            false => Draft::Draft2020_12,
+        };
+
+        for (name, item) in schemas {
+            let path = pointer::push("/components/schemas", name);
+            schema::lint_schema(document, item, &path, draft, &SCHEMA_KEYWORDS, &mut lints);
+
+            if draft == Draft::Draft2020_12 {
+                schema::lint_meta_schema(item, &path, draft, &mut lints);
+            }
+        }
+    }
+
+    // References inside schemas are found twice
+    let mut seen = vec![];
+    lints.retain(|lint| {
+        let key = (lint.pointer.clone(), lint.message.clone());
+        let duplicate = seen.contains(&key);
+        seen.push(key);
+        !duplicate
+    });
+
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::{lexer, parser};
+
+    fn lint_raw(raw: &str) -> Vec<(String, String)> {
+        let tokens = lexer::lex(raw).unwrap();
+        let document = parser::parse(&tokens).unwrap();
+
+        lint(&document)
+            .into_iter()
+            .map(|lint| (lint.pointer, lint.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let mut lints = lint_raw(
+            "{
+                \"openapi\": \"3.0.3\",
+                \"info\": {\"title\": \"API\"},
+                \"paths\": {
+                    \"/pets\": {\"get\": {\"responses\": {\"200\": {\"$ref\": \"#/components/responses/Pets\"}}}}
+                },
+                \"components\": {
+                    \"schemas\": {
+                        \"Pet\": {\"type\": \"object\", \"nullable\": true, \"x-id\": 1, \"properties\": {
+                            \"owner\": {\"$ref\": \"#/components/schemas/Owner\"},
+                            \"tag\": {\"tpye\": \"string\"}
+                        }}
+                    }
+                }
+            }",
+        );
+
+        let mut expected = vec![
+            ("/info", "Missing 'version' in the Info object"),
+            (
+                "/components/schemas/Pet/properties/owner/$ref",
+                "'$ref' to '#/components/schemas/Owner' does not resolve",
+            ),
+            (
+                "/paths/~1pets/get/responses/200/$ref",
+                "'$ref' to '#/components/responses/Pets' does not resolve",
+            ),
+            (
+                "/components/schemas/Pet/properties/tag/tpye",
+                "Unknown keyword 'tpye', did you mean 'type'?",
+            ),
+        ];
+
+        lints.sort();
+        expected.sort();
+
+        assert_eq!(
+            lints,
+            expected
+                .into_iter()
+                .map(|(pointer, message)| (pointer.to_string(), message.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_lint_version() {
+        let cases = vec![
+            (
+                "{}",
+                "Missing 'openapi' version, only OpenAPI 3.0 and 3.1 are supported",
+            ),
+            (
+                "{\"swagger\": \"2.0\", \"openapi\": \"2.0\"}",
+                "Unsupported OpenAPI version '2.0', expected 3.0 or 3.1",
+            ),
+            (
+                "{\"openapi\": \"3.1.0\", \"info\": {\"title\": \"a\", \"version\": \"1\"}}",
+                "Missing 'paths', 'components', 'webhooks'",
+            ),
+        ];
+
+        for (raw, message) in cases {
+            assert_eq!(lint_raw(raw)[0].1, message, "{}", raw);
+        }
+
+        assert_eq!(
+            lint_raw("{\"openapi\": \"3.1.0\", \"info\": {\"title\": \"a\", \"version\": \"1\"}, \"webhooks\": {}}"),
+            []
+        );
+    }
+
+    #[test]
+    fn test_lint_objects() {
+        let mut lints = lint_raw(
+            r##"{
+                "openapi": "3.0.0",
+                "info": {"title": 1, "version": 2, "license": {"url": "u"}},
+                "servers": [{"url": "/", "variables": {"v": {"enum": [1]}}}],
+                "paths": {
+                    "a": {"get": {"parameters": [{"in": "body"}]}},
+                    "/b/{id}": {"post": {
+                        "parameters": [{"name": "id", "in": "path"}, {"$ref": "#/components/parameters/q"}],
+                        "requestBody": {"content": {"application/json": {"example": 1}}},
+                        "responses": {"200": {"content": {}}, "x-note": 1},
+                        "deprecated": "no"
+                    }}
+                },
+                "components": {
+                    "parameters": {"q": {"name": "q", "in": "query"}},
+                    "securitySchemes": {"k": {"type": "key"}}
+                },
+                "tags": [{"description": "t"}, "t"]
+            }"##,
+        );
+
+        let mut expected = vec![
+            ("/info/title", "'title' should be a string"),
+            ("/info/version", "'version' should be a string"),
+            ("/info/license", "Missing 'name' in the License object"),
+            (
+                "/servers/0/variables/v",
+                "Missing 'default' in the Server Variable object",
+            ),
+            (
+                "/servers/0/variables/v/enum",
+                "'enum' should be an array of strings",
+            ),
+            ("/paths/a", "Path 'a' should start with '/'"),
+            (
+                "/paths/a/get",
+                "Missing 'responses' in the Operation object",
+            ),
+            (
+                "/paths/a/get/parameters/0",
+                "Missing 'name' in the Parameter object",
+            ),
+            (
+                "/paths/a/get/parameters/0/in",
+                "'in' should be one of query, header, path, cookie",
+            ),
+            (
+                "/paths/~1b~1{id}/post/parameters/0",
+                "Path parameters should be 'required': true",
+            ),
+            (
+                "/paths/~1b~1{id}/post/responses/200",
+                "Missing 'description' in the Response object",
+            ),
+            (
+                "/paths/~1b~1{id}/post/deprecated",
+                "'deprecated' should be a boolean",
+            ),
+            (
+                "/components/securitySchemes/k/type",
+                "'type' should be one of apiKey, http, mutualTLS, oauth2, openIdConnect",
+            ),
+            ("/tags/0", "Missing 'name' in the Tag object"),
+            ("/tags/1", "'1' should be an object"),
+        ];
+
+        lints.sort();
+        expected.sort();
+
+        assert_eq!(
+            lints,
+            expected
+                .into_iter()
+                .map(|(pointer, message)| (pointer.to_string(), message.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        // 3.1 operations do not need responses
+        assert_eq!(
+            lint_raw(
+                r#"{"openapi": "3.1.0", "info": {"title": "a", "version": "1"}, "paths": {"/": {"get": {}}}}"#
+            ),
+            []
+        );
+    }
+
+    #[test]
+    fn test_lint_schema_drafts() {
+        let schemas = r#"{"A": {"items": [{}], "const": 1, "nullable": true}}"#;
+        let document = |version: &str| {
+            format!(
+                r#"{{"openapi": "{}", "info": {{"title": "a", "version": "1"}}, "paths": {{}}, "components": {{"schemas": {}}}}}"#,
+                version, schemas
+            )
+        };
+
+        assert_eq!(
+            lint_raw(&document("3.0.3")),
+            [(
+                "/components/schemas/A/const".to_string(),
+                "'const' is not a keyword of draft 04".to_string()
+            )]
+        );
+        assert_eq!(
+            lint_raw(&document("3.1.0")),
+            [(
+                "/components/schemas/A/items".to_string(),
+                "Expected an object or a boolean, found an array".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_lint_severity() {
+        let tokens = lexer::lex(
+            r##"{"openapi": "3.0.3", "info": {"title": "a"}, "paths": {}, "components": {
+                "schemas": {"A": {"$ref": "#/B", "tpye": "object", "type": "objekt"}}
+            }}"##,
+        )
+        .unwrap();
+        let document = parser::parse(&tokens).unwrap();
+
+        let mut severities: Vec<_> = lint(&document)
+            .into_iter()
+            .map(|lint| (lint.pointer, lint.severity))
+            .collect();
+        severities.sort();
+
+        assert_eq!(
+            severities,
+            [
+                ("/components/schemas/A/$ref".to_string(), Severity::Error),
+                ("/components/schemas/A/tpye".to_string(), Severity::Warning),
+                ("/components/schemas/A/type".to_string(), Severity::Error),
+                ("/info".to_string(), Severity::Error),
+            ]
+        );
+    }
+}
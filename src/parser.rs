@@ -1,283 +1,2895 @@
-use crate::lexer::{Token, TokenType};
-use std::collections::BTreeMap;
+use crate::canonical::{self, CanonicalError};
+use crate::compare;
+use crate::diagnostic;
+use crate::encoding::{self, InvalidUtf8};
+use crate::escape;
+use crate::flatten::{self, FlattenOptions, UnflattenError};
+use crate::formatter;
+use crate::lexer::{self, LexError, LexErrorKind, Token, TokenType};
+use crate::merge::{self, MergeStrategy};
+use crate::normalize;
+use crate::number::Number;
+use crate::patch::{self, PatchError};
+use crate::pointer;
+use crate::stats::{self, Stats};
+use crate::visit;
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::mem;
+use std::ops::{Index, Range};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
-#[derive(Debug, PartialEq)]
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap;
+
+// Object keys keep the order of the input with the default `preserve_order` feature, and are
+// kept sorted without it
+#[cfg(not(feature = "preserve_order"))]
+pub type Map<K, V> = BTreeMap<K, V>;
+
+#[cfg(feature = "preserve_order")]
+pub type Map<K, V> = IndexMap<K, V>;
+
+// Strings and keys hold their decoded text, see `semantic_eq` for looser comparisons
+#[derive(Clone, Default, Eq)]
 pub enum Json<'a> {
+    #[default]
     Null,
     Bool(bool),
-    Number(f64),
-    String(&'a str),
-    Object(BTreeMap<&'a str, Json<'a>>),
+    Number(Number<'a>),
+    String(Cow<'a, str>),
+    Object(Map<Cow<'a, str>, Json<'a>>),
     Array(Vec<Json<'a>>),
+    // Stands for the part of the input `parse_lossy` could not make sense of, written as `null`
+    Error,
+    // The text of an array or object as it is in the input, kept by `ParserOptions::raw_values`,
+    // or of a string kept by `lazy_strings`, and written back as is
+    Raw(RawJson<'a>),
+}
+
+// The options of the document a `Json::Raw` was read from that matter to read its text again
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RawOptions {
+    allow_trailing_commas: bool,
+    duplicate_keys: DuplicateKeys,
+    allow_comments: bool,
+    allow_unquoted_keys: bool,
+    max_depth: usize,
+    raw_numbers: bool,
+    allow_non_finite: bool,
+}
+
+impl RawOptions {
+    fn of(options: &ParserOptions) -> RawOptions {
+        RawOptions {
+            allow_trailing_commas: options.allow_trailing_commas,
+            duplicate_keys: options.duplicate_keys,
+            allow_comments: options.allow_comments,
+            allow_unquoted_keys: options.allow_unquoted_keys,
+            max_depth: options.max_depth,
+            raw_numbers: options.raw_numbers,
+            allow_non_finite: options.allow_non_finite,
+        }
+    }
+
+    fn parser_options(self) -> ParserOptions<'static> {
+        ParserOptions {
+            allow_trailing_commas: self.allow_trailing_commas,
+            duplicate_keys: self.duplicate_keys,
+            allow_comments: self.allow_comments,
+            allow_unquoted_keys: self.allow_unquoted_keys,
+            max_depth: self.max_depth,
+            raw_numbers: self.raw_numbers,
+            allow_non_finite: self.allow_non_finite,
+            ..ParserOptions::default()
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RawState {
+    options: RawOptions,
+    // The value of the text, parsed the first time it is needed
+    parsed: OnceLock<JsonOwned>,
+}
+
+// The text of a `Json::Raw` with the options it was accepted with, which `Json::parse_raw` and
+// lookups read it again with. Raw arrays and objects compare and hash by their text, raw strings
+// like the string they hold
+#[derive(Clone)]
+pub struct RawJson<'a> {
+    text: Cow<'a, str>,
+    // Boxed so raw values do not make every `Json` larger. Strings kept by `lazy_strings` only get
+    // one when they are first read, as they read the same whatever the options
+    state: OnceLock<Box<RawState>>,
+}
+
+impl<'a> RawJson<'a> {
+    fn new(text: Cow<'a, str>, options: &ParserOptions) -> RawJson<'a> {
+        let state = Box::new(RawState {
+            options: RawOptions::of(options),
+            parsed: OnceLock::new(),
+        });
+
+        RawJson {
+            text,
+            state: OnceLock::from(state),
+        }
+    }
+
+    fn string(text: &'a str) -> RawJson<'a> {
+        RawJson {
+            text: Cow::Borrowed(text),
+            state: OnceLock::new(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_string(&self) -> bool {
+        self.text.trim_start().starts_with('"')
+    }
+
+    pub fn into_owned(self) -> RawJson<'static> {
+        RawJson {
+            text: Cow::Owned(self.text.into_owned()),
+            state: self.state,
+        }
+    }
+
+    fn value(&self) -> &JsonOwned {
+        let state = self.state.get_or_init(|| {
+            Box::new(RawState {
+                options: RawOptions::of(&ParserOptions::default()),
+                parsed: OnceLock::new(),
+            })
+        });
+
+        state.parsed.get_or_init(|| {
+            parse_str_with_options(&self.text, state.options.parser_options())
+                .map_or(Json::Error, Json::into_owned)
+        })
+    }
+}
+
+// Text that was not read by the parser, like a value built by hand, is read leniently
+impl<'a> From<&'a str> for RawJson<'a> {
+    fn from(text: &'a str) -> Self {
+        RawJson::new(Cow::Borrowed(text), &ParserOptions::lenient())
+    }
+}
+
+impl PartialEq for RawJson<'_> {
+    fn eq(&self, other: &RawJson) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for RawJson<'_> {}
+
+impl fmt::Debug for RawJson<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+impl<'a> Json<'a> {
+    // Looks up a value by JSON pointer, like `/items/0/name`, parsing the `Json::Raw` values on the
+    // way
+    pub fn pointer(&self, pointer: &str) -> Option<&Json<'a>> {
+        pointer::get(self, pointer)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Json<'a>> {
+        pointer::get_mut(self, pointer)
+    }
+
+    // See `pointer::set`, `create` adds missing parents as empty objects
+    pub fn set_at(
+        &mut self,
+        pointer: &str,
+        value: Json<'a>,
+        create: bool,
+    ) -> Result<Option<Json<'a>>, pointer::SetError> {
+        pointer::set(self, pointer, value, create)
+    }
+
+    // Removes and returns the value at the pointer, the root can not be removed
+    pub fn remove_at(&mut self, pointer: &str) -> Option<Json<'a>> {
+        pointer::remove(self, pointer)
+    }
+
+    // Compares values with the leniencies of `EqOptions`
+    pub fn semantic_eq(&self, other: &Json, options: compare::EqOptions) -> bool {
+        compare::semantic_eq(self, other, options)
+    }
+
+    // The RFC 8785 canonical text of the value, for signing and hashing
+    pub fn to_canonical_string(&self) -> Result<String, CanonicalError> {
+        canonical::canonicalize(self)
+    }
+
+    // Rewrites values in place, children first, see `visit::transform`
+    pub fn transform<F>(&mut self, f: F)
+    where
+        F: FnMut(&str, &mut Json<'a>) -> bool,
+    {
+        visit::transform(self, f)
+    }
+
+    // Sorts the keys of every object
+    pub fn sort_keys(&mut self) {
+        normalize::sort_keys(self)
+    }
+
+    // Removes null members of every object, null array items are kept
+    pub fn remove_nulls(&mut self) {
+        normalize::remove_nulls(self)
+    }
+
+    // Removes repeated items of every array, keeping the first one
+    pub fn dedupe_arrays(&mut self) {
+        normalize::dedupe_arrays(self)
+    }
+
+    // Every scalar and empty container by a path like `a.b[0].c`
+    pub fn flatten(&self, options: &FlattenOptions) -> Map<String, Json<'a>> {
+        flatten::flatten(self, options)
+    }
+
+    // Rebuilds a document from the paths of `flatten`
+    pub fn unflatten<I, S>(flat: I, options: &FlattenOptions) -> Result<Json<'a>, UnflattenError>
+    where
+        I: IntoIterator<Item = (S, Json<'a>)>,
+        S: AsRef<str>,
+    {
+        flatten::unflatten(flat, options)
+    }
+
+    // Depth, counts of each type of value and the largest array and object
+    pub fn stats(&self) -> Stats {
+        stats::stats(self)
+    }
+
+    // Layers `other` over this value, see `MergeStrategy` for arrays and nulls
+    pub fn merge(&mut self, other: Json<'a>, strategy: MergeStrategy) {
+        merge::merge(self, other, strategy)
+    }
+
+    // Applies an RFC 7396 JSON Merge Patch, where a null member removes the key
+    pub fn merge_patch(&mut self, patch: &Json) {
+        merge::merge_patch(self, patch)
+    }
+
+    // Applies an RFC 6902 JSON Patch document, nothing is changed when an operation fails
+    pub fn apply_patch(&mut self, patch: &Json) -> Result<(), PatchError> {
+        patch::apply_patch(self, patch)
+    }
+
+    // Leaves `Null` in place of the value
+    pub fn take(&mut self) -> Json<'a> {
+        mem::take(self)
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Map<Cow<'a, str>, Json<'a>>> {
+        match self {
+            Json::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Json<'a>>> {
+        match self {
+            Json::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Json<'a>> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut Json<'a>> {
+        self.as_array_mut()?.get_mut(index)
+    }
+
+    // Adds or replaces a member and returns the value it replaced. `Null` becomes an empty object
+    // first and any other value that is not an object panics
+    pub fn insert(&mut self, key: &str, value: Json<'a>) -> Option<Json<'a>> {
+        if let Json::Null = self {
+            *self = Json::Object(Map::new());
+        }
+
+        match self {
+            Json::Object(obj) => {
+                let key = pointer::find_key(obj, key);
+                obj.insert(key, value)
+            }
+            _ => panic!("Cannot insert a key into a {}", self.type_name()),
+        }
+    }
+
+    // Removes a member of an object, the others keep their order
+    pub fn remove(&mut self, key: &str) -> Option<Json<'a>> {
+        match self {
+            Json::Object(_) => pointer::remove(self, &pointer::push("", key)),
+            _ => None,
+        }
+    }
+
+    // `Null` becomes an empty array first and any other value that is not an array panics
+    pub fn push(&mut self, value: Json<'a>) {
+        if let Json::Null = self {
+            *self = Json::Array(vec![]);
+        }
+
+        match self {
+            Json::Array(arr) => arr.push(value),
+            _ => panic!("Cannot push onto a {}", self.type_name()),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Json<'a>> {
+        self.as_array_mut()?.pop()
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Json::Null | Json::Error => "null",
+            Json::Bool(_) => "boolean",
+            Json::Number(_) => "number",
+            Json::String(_) => "string",
+            Json::Object(_) => "object",
+            Json::Array(_) => "array",
+            Json::Raw(raw) if raw.is_string() => "string",
+            Json::Raw(_) => "raw JSON",
+        }
+    }
+
+    fn type_error(&self, expected: &'static str) -> TypeError {
+        TypeError {
+            expected,
+            found: self.type_name(),
+        }
+    }
+
+    // Strings without escape sequences are a slice of the input, the others are decoded copies,
+    // made here the first time for the ones `ParserOptions::lazy_strings` kept as `Json::Raw`
+    pub fn as_str(&self) -> Option<&str> {
+        match self.contents() {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Copies the strings and keys borrowed from the input, so the value can outlive it, be cached
+    // or be sent to another thread
+    pub fn into_owned(self) -> JsonOwned {
+        match self {
+            Json::Null => Json::Null,
+            Json::Bool(b) => Json::Bool(b),
+            Json::Number(n) => Json::Number(n.into_owned()),
+            Json::String(s) => Json::String(Cow::Owned(s.into_owned())),
+            Json::Object(obj) => Json::Object(
+                obj.into_iter()
+                    .map(|(key, item)| (Cow::Owned(key.into_owned()), item.into_owned()))
+                    .collect(),
+            ),
+            Json::Array(arr) => Json::Array(arr.into_iter().map(Json::into_owned).collect()),
+            Json::Error => Json::Error,
+            Json::Raw(raw) => Json::Raw(raw.into_owned()),
+        }
+    }
+
+    // The value a `Json::Raw` holds, parsed with the options of the document it was read from.
+    // Other values are returned as they are
+    pub fn parse_raw(&self) -> Cow<'_, Json<'a>> {
+        Cow::Borrowed(self.contents())
+    }
+
+    // Like `parse_raw`, the parsed value is kept so lookups can go through raw values
+    pub(crate) fn contents(&self) -> &Json<'a> {
+        match self {
+            Json::Raw(raw) => raw.value(),
+            _ => self,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json<'a>>> {
+        match self {
+            Json::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map<Cow<'a, str>, Json<'a>>> {
+        match self {
+            Json::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    // `Json::Error` is not `null`, even though it is written as one
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    // The items of an array, nothing for other values
+    pub fn members(&self) -> impl Iterator<Item = &Json<'a>> {
+        self.as_array().into_iter().flatten()
+    }
+
+    pub fn members_mut(&mut self) -> impl Iterator<Item = &mut Json<'a>> {
+        self.as_array_mut().into_iter().flatten()
+    }
+
+    // The members of an object, nothing for other values
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Json<'a>)> {
+        self.as_object()
+            .into_iter()
+            .flatten()
+            .map(|(key, item)| (key.as_ref(), item))
+    }
+
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = (&str, &mut Json<'a>)> {
+        self.as_object_mut()
+            .into_iter()
+            .flatten()
+            .map(|(key, item)| (key.as_ref(), item))
+    }
+
+    // Members of a `Json::Raw` object are found too, like with `pointer` and indexing
+    pub fn get(&self, key: &str) -> Option<&Json<'a>> {
+        self.contents().as_object()?.get(key)
+    }
+}
+
+static NULL: Json<'static> = Json::Null;
+
+// `value["items"][0]["name"]` is `null` when any part of it is missing, instead of panicking
+impl<'a> Index<&str> for Json<'a> {
+    type Output = Json<'a>;
+
+    fn index(&self, key: &str) -> &Json<'a> {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl<'a> Index<usize> for Json<'a> {
+    type Output = Json<'a>;
+
+    fn index(&self, index: usize) -> &Json<'a> {
+        self.contents()
+            .as_array()
+            .and_then(|arr| arr.get(index))
+            .unwrap_or(&NULL)
+    }
+}
+
+impl From<bool> for Json<'_> {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+macro_rules! from_number {
+    ($($t:ty => $via:ty),*) => {
+        $(
+            impl From<$t> for Json<'_> {
+                fn from(n: $t) -> Self {
+                    Json::Number(Number::from(n as $via))
+                }
+            }
+        )*
+    };
+}
+
+from_number!(
+    i8 => i64, i16 => i64, i32 => i64, i64 => i64, isize => i64,
+    u8 => u64, u16 => u64, u32 => u64, u64 => u64, usize => u64,
+    f32 => f64, f64 => f64
+);
+
+impl<'a> From<Number<'a>> for Json<'a> {
+    fn from(n: Number<'a>) -> Self {
+        Json::Number(n)
+    }
+}
+
+// Borrows the text
+impl<'a> From<&'a str> for Json<'a> {
+    fn from(s: &'a str) -> Self {
+        Json::String(Cow::Borrowed(s))
+    }
+}
+
+impl From<String> for Json<'_> {
+    fn from(s: String) -> Self {
+        Json::String(Cow::Owned(s))
+    }
+}
+
+// `None` is `null`
+impl<'a, T: Into<Json<'a>>> From<Option<T>> for Json<'a> {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<'a, T: Into<Json<'a>>> From<Vec<T>> for Json<'a> {
+    fn from(items: Vec<T>) -> Self {
+        Json::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+fn object_from<'a, K: AsRef<str>, V: Into<Json<'a>>>(
+    members: impl Iterator<Item = (K, V)>,
+) -> Json<'a> {
+    Json::Object(
+        members
+            .map(|(key, value)| {
+                let key = Cow::Owned(key.as_ref().to_owned());
+                (key, value.into())
+            })
+            .collect(),
+    )
+}
+
+// Sorted by key, a `HashMap` has no order of its own
+impl<'a, K: AsRef<str>, V: Into<Json<'a>>, S> From<HashMap<K, V, S>> for Json<'a> {
+    fn from(map: HashMap<K, V, S>) -> Self {
+        let mut members: Vec<_> = map.into_iter().collect();
+        members.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+        object_from(members.into_iter())
+    }
+}
+
+impl<'a, K: AsRef<str>, V: Into<Json<'a>>> From<BTreeMap<K, V>> for Json<'a> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        object_from(map.into_iter())
+    }
+}
+
+// A value of another type than the conversion out of `Json` asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+impl TryFrom<Json<'_>> for String {
+    type Error = TypeError;
+
+    fn try_from(value: Json) -> Result<Self, TypeError> {
+        match value {
+            Json::String(s) => Ok(s.into_owned()),
+            Json::Raw(ref raw) if raw.is_string() => String::try_from(raw.value().clone()),
+            _ => Err(value.type_error("string")),
+        }
+    }
+}
+
+impl TryFrom<Json<'_>> for bool {
+    type Error = TypeError;
+
+    fn try_from(value: Json) -> Result<Self, TypeError> {
+        value.as_bool().ok_or_else(|| value.type_error("boolean"))
+    }
+}
+
+impl TryFrom<Json<'_>> for i64 {
+    type Error = TypeError;
+
+    fn try_from(value: Json) -> Result<Self, TypeError> {
+        value
+            .as_i64()
+            .ok_or_else(|| value.type_error("64-bit integer"))
+    }
+}
+
+impl TryFrom<Json<'_>> for u64 {
+    type Error = TypeError;
+
+    fn try_from(value: Json) -> Result<Self, TypeError> {
+        value
+            .as_u64()
+            .ok_or_else(|| value.type_error("unsigned 64-bit integer"))
+    }
+}
+
+impl TryFrom<Json<'_>> for f64 {
+    type Error = TypeError;
+
+    fn try_from(value: Json) -> Result<Self, TypeError> {
+        value.as_f64().ok_or_else(|| value.type_error("number"))
+    }
+}
+
+impl<'a, T: TryFrom<Json<'a>, Error = TypeError>> TryFrom<Json<'a>> for Vec<T> {
+    type Error = TypeError;
+
+    fn try_from(value: Json<'a>) -> Result<Self, TypeError> {
+        match value {
+            Json::Array(arr) => arr.into_iter().map(T::try_from).collect(),
+            _ => Err(value.type_error("array")),
+        }
+    }
+}
+
+impl<'a, T: TryFrom<Json<'a>, Error = TypeError>> TryFrom<Json<'a>> for HashMap<String, T> {
+    type Error = TypeError;
+
+    fn try_from(value: Json<'a>) -> Result<Self, TypeError> {
+        match value {
+            Json::Object(obj) => obj
+                .into_iter()
+                .map(|(key, item)| Ok((key.into_owned(), T::try_from(item)?)))
+                .collect(),
+            _ => Err(value.type_error("object")),
+        }
+    }
+}
+
+// A value that does not borrow from the input, as returned by `Json::into_owned`
+pub type JsonOwned = Json<'static>;
+
+// Raw strings equal the string they hold, so documents read with and without
+// `ParserOptions::lazy_strings` are equal
+impl PartialEq for Json<'_> {
+    fn eq(&self, other: &Json) -> bool {
+        match (self, other) {
+            (Json::Null, Json::Null) | (Json::Error, Json::Error) => true,
+            (Json::Bool(a), Json::Bool(b)) => a == b,
+            (Json::Number(a), Json::Number(b)) => a == b,
+            (Json::String(a), Json::String(b)) => a == b,
+            (Json::Object(a), Json::Object(b)) => a == b,
+            (Json::Array(a), Json::Array(b)) => a == b,
+            (Json::Raw(a), Json::Raw(b)) if a == b => true,
+            (Json::Raw(raw), value) | (value, Json::Raw(raw)) if raw.is_string() => {
+                raw.value() == value
+            }
+            _ => false,
+        }
+    }
+}
+
+// Consistent with `==`, so values can be deduplicated in a `HashSet`. Numbers hash by value like
+// they compare, strings and keys by their text, and objects by the sum of the hashes of
+// their members so key order does not matter
+impl Hash for Json<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Json::Raw(raw) = self {
+            if raw.is_string() {
+                return raw.value().hash(state);
+            }
+        }
+
+        mem::discriminant(self).hash(state);
+
+        match self {
+            Json::Null | Json::Error => {}
+            Json::Bool(b) => b.hash(state),
+            Json::Number(n) => n.hash(state),
+            Json::String(s) => s.hash(state),
+            Json::Raw(raw) => raw.as_str().hash(state),
+            Json::Array(arr) => arr.hash(state),
+            Json::Object(obj) => {
+                let sum = obj.iter().fold(0u64, |sum, member| {
+                    let mut hasher = DefaultHasher::new();
+                    member.hash(&mut hasher);
+                    sum.wrapping_add(hasher.finish())
+                });
+
+                obj.len().hash(state);
+                sum.hash(state);
+            }
+        }
+    }
+}
+
+// Compact JSON, like `format_json` with no indentation
+impl fmt::Display for Json<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&formatter::format_json(self, 0))
+    }
+}
+
+// `{:#?}` prints the value as pretty JSON, `{:?}` keeps the usual enum form
+impl fmt::Debug for Json<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return f.write_str(&formatter::format_json(self, 4));
+        }
+
+        match self {
+            Json::Null => f.write_str("Null"),
+            Json::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            Json::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            Json::String(s) => f.debug_tuple("String").field(s).finish(),
+            Json::Object(obj) => f.debug_tuple("Object").field(obj).finish(),
+            Json::Array(arr) => f.debug_tuple("Array").field(arr).finish(),
+            Json::Error => f.write_str("Error"),
+            Json::Raw(raw) => f.debug_tuple("Raw").field(raw).finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseErrorType {
+    UnexpectedEnd,
+    UnexpectedToken,
+    DuplicateKey,
+    TrailingComma,
+    KeyNotInQuotes,
+    MissingColon,
+    MissingCloseCurly,
+    MissingCloseSquare,
+    Cancelled,
+    MemoryLimitExceeded,
+    Comment,
+    DepthLimitExceeded,
+    // Only for an `OwnedParseError`, the input could not be lexed
+    Lex(LexErrorKind),
+    // Only for an `OwnedParseError` of `parse_bytes`
+    InvalidUtf8(InvalidUtf8),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError<'a> {
+    pub error_type: ParseErrorType,
+    pub token: Option<&'a Token<'a>>,
+    pub expected: Option<&'a TokenType<'a>>,
+}
+
+impl<'a> ParseError<'a> {
+    pub fn new(
+        error_type: ParseErrorType,
+        token: Option<&'a Token<'a>>,
+        expected: Option<&'a TokenType<'a>>,
+    ) -> ParseError<'a> {
+        ParseError {
+            error_type,
+            token,
+            expected,
+        }
+    }
+}
+
+// A `ParseError` that does not borrow the tokens, as returned by `parse_str`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedParseError {
+    pub error_type: ParseErrorType,
+    // Zero based position of the offending token, `None` when the input ended too early
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+    // The English message `joxide validate` would print
+    pub message: String,
+}
+
+impl From<ParseError<'_>> for OwnedParseError {
+    fn from(parse_error: ParseError) -> Self {
+        let id = diagnostic::get_message_id(&parse_error);
+        let template = diagnostic::template(diagnostic::Locale::English, id);
+
+        OwnedParseError {
+            error_type: parse_error.error_type,
+            line: parse_error.token.map(|token| token.line),
+            col: parse_error.token.map(|token| token.col),
+            message: diagnostic::render(template, &parse_error, ""),
+        }
+    }
+}
+
+impl From<LexError> for OwnedParseError {
+    fn from(lex_error: LexError) -> Self {
+        OwnedParseError {
+            error_type: ParseErrorType::Lex(lex_error.kind),
+            line: Some(lex_error.line),
+            col: Some(lex_error.col),
+            message: lex_error.kind.to_string(),
+        }
+    }
+}
+
+impl From<InvalidUtf8> for OwnedParseError {
+    fn from(invalid: InvalidUtf8) -> Self {
+        OwnedParseError {
+            error_type: ParseErrorType::InvalidUtf8(invalid),
+            line: Some(invalid.line),
+            col: Some(invalid.col),
+            message: invalid.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for OwnedParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.col) {
+            (Some(line), Some(col)) => write!(f, "{} at {}:{}", self.message, line + 1, col + 1),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for OwnedParseError {}
+
+// A `ParseError` that owns its token, for `parse_tokens_with_options` which does not keep the
+// tokens it has parsed
+#[derive(Debug, PartialEq)]
+pub struct TokenError<'a> {
+    pub error_type: ParseErrorType,
+    pub token: Option<Token<'a>>,
+    pub expected: Option<&'static TokenType<'static>>,
+}
+
+impl TokenError<'_> {
+    pub fn parse_error(&self) -> ParseError<'_> {
+        ParseError::new(self.error_type, self.token.as_ref(), self.expected)
+    }
+}
+
+// Why parsing streamed tokens failed, lexing stops at its first error
+#[derive(Debug, PartialEq)]
+pub enum StreamError<'a> {
+    Lex(LexError),
+    Parse(Box<TokenError<'a>>),
+}
+
+impl From<StreamError<'_>> for OwnedParseError {
+    fn from(stream_error: StreamError) -> Self {
+        match stream_error {
+            StreamError::Lex(lex_error) => OwnedParseError::from(lex_error),
+            StreamError::Parse(token_error) => OwnedParseError::from(token_error.parse_error()),
+        }
+    }
+}
+
+// Progress reported to a `ParseObserver`, in bytes of the input. The length of the input is not
+// known when parsing the tokens of an iterator, token slices end with their last token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub bytes_parsed: usize,
+    pub total_bytes: Option<usize>,
+}
+
+pub trait ParseObserver {
+    fn progress(&mut self, progress: Progress);
+
+    // Number of bytes between two progress reports
+    fn interval(&self) -> usize {
+        1 << 16
+    }
+}
+
+// Checked by the parser every `CANCELLATION_INTERVAL` tokens
+pub enum Cancellation<'o> {
+    Flag(&'o AtomicBool),
+    Callback(&'o dyn Fn() -> bool),
+}
+
+impl Cancellation<'_> {
+    pub fn is_cancelled(&self) -> bool {
+        match self {
+            Cancellation::Flag(flag) => flag.load(Ordering::Relaxed),
+            Cancellation::Callback(callback) => callback(),
+        }
+    }
+}
+
+pub const CANCELLATION_INTERVAL: usize = 1024;
+
+// Parsing does not recurse, the limit keeps recursive code working on the result, like
+// formatting or dropping it, within the stack
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+// The deepest `max_depth` that recursive code handles on the 8 MiB stack of a main thread, debug
+// builds included
+pub const MAX_SAFE_DEPTH: usize = 4096;
+
+// What happens when a key appears more than once in the same object
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DuplicateKeys {
+    #[default]
+    Error,
+    FirstWins,
+    // Like `JSON.parse` in JavaScript
+    LastWins,
+}
+
+impl DuplicateKeys {
+    pub fn from_name(name: &str) -> Option<DuplicateKeys> {
+        match name {
+            "error" => Some(DuplicateKeys::Error),
+            "first-wins" => Some(DuplicateKeys::FirstWins),
+            "last-wins" => Some(DuplicateKeys::LastWins),
+            _ => None,
+        }
+    }
+}
+
+pub struct ParserOptions<'o> {
+    pub observer: Option<&'o mut dyn ParseObserver>,
+    pub cancellation: Option<Cancellation<'o>>,
+    // Approximate number of bytes the parsed tree may use
+    pub memory_limit: Option<usize>,
+    // Tolerances for config file style inputs, all of them are errors by default
+    pub allow_trailing_commas: bool,
+    pub duplicate_keys: DuplicateKeys,
+    pub allow_comments: bool,
+    // Keys made of letters, digits, `_` and `$` without quotes
+    pub allow_unquoted_keys: bool,
+    // Arrays and objects nested deeper than this are an error
+    pub max_depth: usize,
+    // Numbers keep their text, so formatting writes them back exactly as written
+    pub raw_numbers: bool,
+    // Tokens after the document are ignored instead of being an error, for inputs that carry
+    // something else after the JSON
+    pub allow_trailing_content: bool,
+    // JSON pointers, where `*` matches any key or index, of arrays and objects to keep as
+    // `Json::Raw`. Only `parse_str_with_options` has the input to take their text from
+    pub raw_values: &'o [&'o str],
+    // Arrays and objects inside the top level one are kept as `Json::Raw` as well, for large
+    // documents where only a few members are needed. `Json::parse_raw` builds them on demand
+    pub shallow: bool,
+    // `NaN`, `Infinity` and `-Infinity` as numbers, like JavaScript and Python write them
+    pub allow_non_finite: bool,
+    // Strings with escape sequences are kept as `Json::Raw` of their text and only decoded the
+    // first time they are read, for string heavy documents of which little is used. They equal,
+    // hash and convert like the string they hold. Strings without escapes point into the input
+    // either way. Needs `parse_str_with_options` as well
+    pub lazy_strings: bool,
+}
+
+impl Default for ParserOptions<'_> {
+    fn default() -> Self {
+        ParserOptions {
+            observer: None,
+            cancellation: None,
+            memory_limit: None,
+            allow_trailing_commas: false,
+            duplicate_keys: DuplicateKeys::Error,
+            allow_comments: false,
+            allow_unquoted_keys: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            raw_numbers: false,
+            allow_trailing_content: false,
+            raw_values: &[],
+            shallow: false,
+            allow_non_finite: false,
+            lazy_strings: false,
+        }
+    }
+}
+
+impl ParserOptions<'_> {
+    // Accepts trailing commas, duplicate keys, comments and unquoted keys
+    pub fn lenient() -> Self {
+        ParserOptions {
+            allow_trailing_commas: true,
+            duplicate_keys: DuplicateKeys::LastWins,
+            allow_comments: true,
+            allow_unquoted_keys: true,
+            ..ParserOptions::default()
+        }
+    }
+}
+
+// A key that appears more than once in the same object, with the key token of each occurrence
+#[derive(Debug, PartialEq)]
+pub struct DuplicateKey<'a> {
+    pub key: Cow<'a, str>,
+    pub occurrences: Vec<&'a Token<'a>>,
+}
+
+// A comment of the document with the JSON pointer of the value it comes before, or of the array or
+// object it ends when no value follows it there
+#[derive(Debug, PartialEq)]
+pub struct Comment<'a> {
+    pub token: &'a Token<'a>,
+    pub pointer: String,
+}
+
+enum Container<'s> {
+    Array(Vec<Json<'s>>),
+    Object {
+        object: Map<Cow<'s, str>, Json<'s>>,
+        // Every key with the index of its token, only kept by `find_duplicate_keys`
+        keys: Vec<(Cow<'s, str>, usize)>,
+        // Key of the value being parsed
+        key: Option<Cow<'s, str>>,
+    },
+}
+
+impl<'s> Container<'s> {
+    // Adds an item while recovering, items of objects are dropped when their key is not known
+    fn add(&mut self, value: Json<'s>) {
+        match self {
+            Container::Array(array) => array.push(value),
+            Container::Object { object, key, .. } => {
+                if let Some(key) = key.take() {
+                    object.insert(key, value);
+                }
+            }
+        }
+    }
+
+    fn into_json(self) -> Json<'s> {
+        match self {
+            Container::Array(array) => Json::Array(array),
+            Container::Object { object, .. } => Json::Object(object),
+        }
+    }
+}
+
+// An array or object that has been opened but not closed yet
+struct Frame<'s> {
+    container: Container<'s>,
+    last_comma: Option<usize>,
+    // First token of the item being parsed, its key for objects
+    item_start: usize,
+    // Set when recovering skipped to a bracket closing an outer array or object after the
+    // missing one of this frame was reported, so it is not reported again
+    unclosed: bool,
+}
+
+// What the parser does next, the open arrays and objects are kept on a stack instead of the call
+// stack so deep documents cannot overflow it
+enum State<'s> {
+    Value(usize),
+    // After an opening bracket or a comma
+    Item(usize),
+    Done(Json<'s>, usize),
+    // Where the closing bracket should be
+    Close(usize),
+}
+
+// An error of the parser with the index of its token, which only becomes a `ParseError` or a
+// `StreamError` once parsing is over since streamed tokens are not all kept
+#[derive(Debug)]
+struct Failure {
+    error_type: ParseErrorType,
+    at: Option<usize>,
+    expected: Option<&'static TokenType<'static>>,
+}
+
+impl Failure {
+    fn new(error_type: ParseErrorType, at: Option<usize>) -> Failure {
+        Failure {
+            error_type,
+            at,
+            expected: None,
+        }
+    }
+
+    fn to_parse_error<'t>(&self, tokens: &'t [Token]) -> ParseError<'t> {
+        ParseError::new(self.error_type, self.at.map(|i| &tokens[i]), self.expected)
+    }
+}
+
+// Streamed tokens are let go of in batches of at least this many
+const RELEASE_INTERVAL: usize = 1024;
+
+// Tokens of an iterator, lexed as the parser gets to them
+struct Stream<'s> {
+    tokens: Box<dyn Iterator<Item = Result<Token<'s>, LexError>> + 's>,
+    // The lexed tokens from index `first` on
+    window: VecDeque<Token<'s>>,
+    first: usize,
+    // Tokens before `first` that errors can still be reported at, by index
+    kept: Vec<(usize, Token<'s>)>,
+    ended: bool,
+    // The error lexing stopped at
+    error: Option<LexError>,
+}
+
+impl<'s> Stream<'s> {
+    fn get(&mut self, i: usize) -> Option<&Token<'s>> {
+        if i < self.first {
+            return self
+                .kept
+                .iter()
+                .find(|(index, _)| *index == i)
+                .map(|(_, token)| token);
+        }
+
+        while !self.ended && self.first + self.window.len() <= i {
+            match self.tokens.next() {
+                Some(Ok(token)) => self.window.push_back(token),
+                Some(Err(lex_error)) => {
+                    self.error = Some(lex_error);
+                    self.ended = true;
+                }
+                None => self.ended = true,
+            }
+        }
+
+        self.window.get(i - self.first)
+    }
+
+    // Drops the tokens before `before`, apart from those at the indexes of `keep`
+    fn release(&mut self, before: usize, keep: &[usize]) {
+        self.kept.retain(|(i, _)| keep.contains(i));
+
+        while self.first < before {
+            match self.window.pop_front() {
+                Some(token) if keep.contains(&self.first) => self.kept.push((self.first, token)),
+                Some(_) => {}
+                None => break,
+            }
+
+            self.first += 1;
+        }
+    }
+}
+
+// The tokens the parser reads, by index
+enum Tokens<'t, 's> {
+    Slice(&'t [Token<'s>]),
+    Stream(Stream<'s>),
+}
+
+impl<'t, 's> Tokens<'t, 's> {
+    fn stream<I>(tokens: I) -> Tokens<'t, 's>
+    where
+        I: IntoIterator<Item = Result<Token<'s>, LexError>>,
+        I::IntoIter: 's,
+    {
+        Tokens::Stream(Stream {
+            tokens: Box::new(tokens.into_iter()),
+            window: VecDeque::new(),
+            first: 0,
+            kept: vec![],
+            ended: false,
+            error: None,
+        })
+    }
+
+    // The token at `i`, lexing up to it, `None` past the end
+    fn get(&mut self, i: usize) -> Option<&Token<'s>> {
+        match self {
+            Tokens::Slice(tokens) => tokens.get(i),
+            Tokens::Stream(stream) => stream.get(i),
+        }
+    }
+
+    // The number of tokens, as far as they have been lexed
+    fn len(&self) -> usize {
+        match self {
+            Tokens::Slice(tokens) => tokens.len(),
+            Tokens::Stream(stream) => stream.first + stream.window.len(),
+        }
+    }
+
+    // The number of tokens, lexing the rest of them
+    fn end(&mut self) -> usize {
+        while self.get(self.len()).is_some() {}
+        self.len()
+    }
+}
+
+// Only for tokens that have already been read
+impl<'s> Index<usize> for Tokens<'_, 's> {
+    type Output = Token<'s>;
+
+    fn index(&self, i: usize) -> &Token<'s> {
+        match self {
+            Tokens::Slice(tokens) => &tokens[i],
+            Tokens::Stream(stream) if i < stream.first => {
+                let kept = stream.kept.iter().find(|(index, _)| *index == i);
+                &kept.expect("token already released").1
+            }
+            Tokens::Stream(stream) => &stream.window[i - stream.first],
+        }
+    }
+}
+
+struct Parser<'t, 's, 'o> {
+    tokens: Tokens<'t, 's>,
+    options: ParserOptions<'o>,
+    // Only collected by `find_duplicate_keys`, otherwise the first duplicate is an error. Each key
+    // comes with the index of its token for every occurrence
+    duplicates: Option<Vec<(Cow<'s, str>, Vec<usize>)>>,
+    // Only collected by `find_errors`, which carries on after an error
+    errors: Option<Vec<Failure>>,
+    // Only collected by `parse_with_comments`, with the index of their token. Those from `attached`
+    // on do not have their pointer
+    comments: Option<Vec<(usize, String)>>,
+    attached: usize,
+    // Length of the input for progress reports
+    total_bytes: Option<usize>,
+    next_report: usize,
+    next_cancellation_check: usize,
+    allocated: usize,
+    // The input, to take the text of raw values from
+    source: Option<&'s str>,
+    // Tokens of `ParserOptions::raw_values`
+    raw_patterns: Vec<Vec<String>>,
+    // Stack index and first byte of the raw value being parsed, nothing inside it is kept
+    raw: Option<(usize, usize)>,
+    // Set by `check` and `find_errors`, values are dropped as soon as they are read and only the
+    // keys of open objects are kept to find duplicates
+    check_only: bool,
+}
+
+impl<'t, 's, 'o> Parser<'t, 's, 'o> {
+    fn new(tokens: Tokens<'t, 's>, options: ParserOptions<'o>) -> Self {
+        let next_report = match options.observer {
+            Some(ref observer) => observer.interval().max(1),
+            None => usize::MAX,
+        };
+
+        let total_bytes = match tokens {
+            Tokens::Slice(tokens) => Some(tokens.last().map_or(0, |token| token.end)),
+            Tokens::Stream(_) => None,
+        };
+
+        Parser {
+            tokens,
+            options,
+            duplicates: None,
+            errors: None,
+            comments: None,
+            attached: 0,
+            total_bytes,
+            next_report,
+            next_cancellation_check: 0,
+            allocated: 0,
+            source: None,
+            raw_patterns: vec![],
+            raw: None,
+            check_only: false,
+        }
+    }
+
+    fn with_source(mut self, source: &'s str) -> Self {
+        self.total_bytes = Some(source.len());
+        self.raw_patterns = self
+            .options
+            .raw_values
+            .iter()
+            .filter_map(|pointer| pointer::split(pointer))
+            .collect();
+
+        if !self.raw_patterns.is_empty() || self.options.shallow || self.options.lazy_strings {
+            self.source = Some(source);
+        }
+
+        self
+    }
+
+    // Whether the array or object about to be opened is at one of the raw pointers
+    fn is_raw(&self, stack: &[Frame<'s>]) -> bool {
+        if self.options.shallow && stack.len() == 1 {
+            return true;
+        }
+
+        self.raw_patterns.iter().any(|pattern| {
+            pattern.len() == stack.len()
+                && pattern.iter().zip(stack).all(|(token, frame)| {
+                    token == "*"
+                        || match &frame.container {
+                            Container::Array(array) => *token == array.len().to_string(),
+                            Container::Object { key, .. } => key.as_deref() == Some(token.as_str()),
+                        }
+                })
+        })
+    }
+
+    // The input from byte `first` to the end of the closing bracket `last`
+    fn raw_text(&self, first: usize, last: usize) -> &'s str {
+        &self.source.unwrap()[first..self.tokens[last].end]
+    }
+
+    fn report(&mut self, bytes_parsed: usize) {
+        if let Some(observer) = self.options.observer.as_mut() {
+            observer.progress(Progress {
+                bytes_parsed,
+                total_bytes: self.total_bytes,
+            });
+        }
+    }
+
+    // Reports progress up to the start of the token at `i` and checks for cancellation
+    fn advance(&mut self, i: usize) -> Result<(), Failure> {
+        let bytes_parsed = self.tokens.get(i).map_or(0, |token| token.start);
+
+        if bytes_parsed >= self.next_report {
+            self.report(bytes_parsed);
+
+            let interval = self
+                .options
+                .observer
+                .as_ref()
+                .map_or(usize::MAX, |o| o.interval().max(1));
+            self.next_report = bytes_parsed.saturating_add(interval);
+        }
+
+        if i >= self.next_cancellation_check {
+            if let Some(ref cancellation) = self.options.cancellation {
+                if cancellation.is_cancelled() {
+                    return Err(Failure::new(ParseErrorType::Cancelled, None));
+                }
+            }
+
+            self.next_cancellation_check = i + CANCELLATION_INTERVAL;
+        }
+
+        Ok(())
+    }
+
+    // Accounts for `bytes` more of the tree, the token at `i` is where the limit is reported
+    fn allocate(&mut self, bytes: usize, i: usize) -> Result<(), Failure> {
+        self.allocated = self.allocated.saturating_add(bytes);
+
+        match self.options.memory_limit {
+            Some(limit) if self.allocated > limit => {
+                Err(Failure::new(ParseErrorType::MemoryLimitExceeded, Some(i)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Lets go of the streamed tokens before `i`, apart from those of the open arrays and objects
+    // errors can still be reported at
+    fn release(&mut self, stack: &[Frame<'s>], i: usize) {
+        if let Tokens::Stream(stream) = &mut self.tokens {
+            if i >= stream.first + RELEASE_INTERVAL {
+                let keep = stack
+                    .iter()
+                    .flat_map(|frame| iter::once(frame.item_start).chain(frame.last_comma))
+                    .collect::<Vec<_>>();
+
+                stream.release(i, &keep);
+            }
+        }
+    }
+
+    // Errors that do not change the shape of the document are only recorded when recovering. A
+    // token gets one error, a trailing comma found after recovering from the same comma replaces
+    // the less specific error it got first
+    fn tolerate(&mut self, failure: Failure) -> Result<(), Failure> {
+        match self.errors.as_mut() {
+            Some(errors) => {
+                match errors.last_mut() {
+                    Some(last) if last.at.is_some() && last.at == failure.at => {
+                        if failure.error_type == ParseErrorType::TrailingComma {
+                            *last = failure;
+                        }
+                    }
+                    _ => errors.push(failure),
+                }
+
+                Ok(())
+            }
+            None => Err(failure),
+        }
+    }
+
+    // Index of the first token from `i` that is not a comment, comments are errors unless allowed
+    fn skip_comments(&mut self, mut i: usize) -> Result<usize, Failure> {
+        while let Some(token) = self.tokens.get(i) {
+            if !matches!(token.token_type, TokenType::Comment(_)) {
+                break;
+            }
+
+            if !self.options.allow_comments {
+                self.tolerate(Failure::new(ParseErrorType::Comment, Some(i)))?;
+            }
+
+            if let Some(comments) = self.comments.as_mut() {
+                comments.push((i, String::new()));
+            }
+
+            i += 1;
+        }
+
+        Ok(i)
+    }
+
+    // Gives the comments read since the last call the pointer of the value at the top of the stack
+    fn attach_comments(&mut self, stack: &[Frame<'s>]) {
+        let comments = match self.comments.as_mut() {
+            Some(comments) if comments.len() > self.attached => comments,
+            _ => return,
+        };
+
+        let pointer = stack
+            .iter()
+            .fold(String::new(), |pointer, frame| match &frame.container {
+                Container::Array(array) => format!("{}/{}", pointer, array.len()),
+                Container::Object { key, .. } => {
+                    pointer::push(&pointer, key.as_deref().unwrap_or_default())
+                }
+            });
+
+        for (_, comment_pointer) in &mut comments[self.attached..] {
+            comment_pointer.clone_from(&pointer);
+        }
+
+        self.attached = comments.len();
+    }
+
+    fn expect(
+        &mut self,
+        token_type: &'static TokenType<'static>,
+        error_type: ParseErrorType,
+        i: usize,
+    ) -> Result<(), Failure> {
+        match self.tokens.get(i) {
+            Some(token) if token.token_type == *token_type => Ok(()),
+            Some(_) => Err(Failure {
+                error_type,
+                at: Some(i),
+                expected: Some(token_type),
+            }),
+            None => Err(Failure::new(ParseErrorType::UnexpectedEnd, None)),
+        }
+    }
+
+    // Comments between the comma and `i` have already been checked when the item was expected
+    fn check_trailing_comma(&mut self, last_comma: Option<usize>, i: usize) -> Result<(), Failure> {
+        let only_comments = |tokens: &mut Tokens| {
+            (last_comma.unwrap_or(i) + 1..i).all(|k| {
+                matches!(
+                    tokens.get(k).map(|token| &token.token_type),
+                    Some(TokenType::Comment(_))
+                )
+            })
+        };
+
+        match last_comma {
+            Some(index)
+                if !self.options.allow_trailing_commas && only_comments(&mut self.tokens) =>
+            {
+                self.tolerate(Failure::new(ParseErrorType::TrailingComma, Some(index)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn expect_key(&mut self, i: usize) -> Result<&'s str, Failure> {
+        match self.tokens.get(i) {
+            Some(token) => match token.token_type {
+                TokenType::String(s) => Ok(s),
+                TokenType::Invalid(s)
+                    if self.options.allow_unquoted_keys
+                        && s.chars()
+                            .all(|c| c.is_alphanumeric() || c == '_' || c == '$') =>
+                {
+                    Ok(s)
+                }
+                TokenType::Invalid(_) | TokenType::Number(..) | TokenType::Bool(_) => {
+                    Err(Failure::new(ParseErrorType::KeyNotInQuotes, Some(i)))
+                }
+                _ => Err(Failure::new(ParseErrorType::UnexpectedToken, Some(i))),
+            },
+            None => Err(Failure::new(ParseErrorType::UnexpectedEnd, None)),
+        }
+    }
+
+    fn collect_duplicates(&mut self, keys: Vec<(Cow<'s, str>, usize)>, unique: usize) {
+        if let Some(duplicates) = self.duplicates.as_mut() {
+            if keys.len() > unique {
+                let mut occurrences: Map<Cow<'s, str>, Vec<usize>> = Map::new();
+
+                for (key, i) in keys {
+                    occurrences.entry(key).or_default().push(i);
+                }
+
+                duplicates.extend(
+                    occurrences
+                        .into_iter()
+                        .filter(|(_, occurrences)| occurrences.len() > 1),
+                );
+            }
+        }
+    }
+
+    fn step(&mut self, stack: &mut Vec<Frame<'s>>, state: State<'s>) -> Result<State<'s>, Failure> {
+        match state {
+            State::Value(start) => {
+                let start = self.skip_comments(start)?;
+                self.attach_comments(stack);
+                self.advance(start)?;
+
+                if self.tokens.get(start).is_none() {
+                    return Err(Failure::new(ParseErrorType::UnexpectedEnd, None));
+                }
+
+                if self.raw.is_none() {
+                    self.allocate(mem::size_of::<Json>(), start)?;
+                }
+
+                let start_token = &self.tokens[start];
+                let container = match start_token.token_type {
+                    TokenType::Null => return Ok(State::Done(Json::Null, start + 1)),
+                    TokenType::Bool(x) => return Ok(State::Done(Json::Bool(x), start + 1)),
+                    TokenType::Number(ref x, text) => {
+                        let number = if self.options.raw_numbers {
+                            Number::Raw(Cow::Borrowed(text))
+                        } else {
+                            x.clone()
+                        };
+
+                        return Ok(State::Done(Json::Number(number), start + 1));
+                    }
+                    TokenType::Invalid(text) if self.options.allow_non_finite => {
+                        // Kept as written, a float would have no JSON text to format back to
+                        let number = match Number::parse_non_finite(text) {
+                            Some(_) => Number::Raw(Cow::Borrowed(text)),
+                            None => {
+                                return Err(Failure::new(
+                                    ParseErrorType::UnexpectedToken,
+                                    Some(start),
+                                ))
+                            }
+                        };
+
+                        return Ok(State::Done(Json::Number(number), start + 1));
+                    }
+                    TokenType::String(_) if self.check_only => {
+                        return Ok(State::Done(Json::Null, start + 1))
+                    }
+                    TokenType::String(x) => {
+                        let value = match self.source {
+                            Some(source) if self.options.lazy_strings && x.contains('\\') => {
+                                let text = &source[start_token.start..start_token.end];
+                                Json::Raw(RawJson::string(text))
+                            }
+                            _ => Json::String(escape::unescape(x)),
+                        };
+
+                        return Ok(State::Done(value, start + 1));
+                    }
+                    TokenType::OpenCurly => Container::Object {
+                        object: Map::new(),
+                        keys: vec![],
+                        key: None,
+                    },
+                    TokenType::OpenSquare => Container::Array(vec![]),
+                    _ => return Err(Failure::new(ParseErrorType::UnexpectedToken, Some(start))),
+                };
+
+                if stack.len() >= self.options.max_depth {
+                    return Err(Failure::new(
+                        ParseErrorType::DepthLimitExceeded,
+                        Some(start),
+                    ));
+                }
+
+                if self.raw.is_none() && self.source.is_some() && self.is_raw(stack) {
+                    self.raw = Some((stack.len(), start_token.start));
+                }
+
+                stack.push(Frame {
+                    container,
+                    last_comma: None,
+                    item_start: start + 1,
+                    unclosed: false,
+                });
+
+                Ok(State::Item(start + 1))
+            }
+            State::Item(start) => {
+                let start = self.skip_comments(start)?;
+                let frame = stack.last_mut().unwrap();
+                frame.item_start = start;
+
+                match frame.container {
+                    Container::Array(_) => Ok(State::Value(start)),
+                    Container::Object { ref mut key, .. } => {
+                        *key = Some(escape::unescape(self.expect_key(start)?));
+
+                        self.allocate(mem::size_of::<Cow<str>>(), start)?;
+
+                        let colon = self.skip_comments(start + 1)?;
+                        self.expect(&TokenType::Colon, ParseErrorType::MissingColon, colon)?;
+
+                        Ok(State::Value(colon + 1))
+                    }
+                }
+            }
+            State::Done(value, next) => {
+                let frame = stack.last_mut().unwrap();
+                let key_at = frame.item_start;
+
+                match frame.container {
+                    // Items of raw values are only checked
+                    Container::Object { ref mut key, .. } if self.raw.is_some() => {
+                        key.take();
+                    }
+                    Container::Array(_) if self.raw.is_some() || self.check_only => {}
+                    Container::Array(ref mut array) => array.push(value),
+                    Container::Object {
+                        ref mut object,
+                        ref mut keys,
+                        ref mut key,
+                    } => {
+                        let key = key.take().unwrap_or_default();
+                        let collect = self.duplicates.is_some();
+
+                        if collect {
+                            keys.push((key.clone(), key_at));
+                        }
+
+                        let policy = self.options.duplicate_keys;
+                        let value = if self.check_only { Json::Null } else { value };
+
+                        if policy == DuplicateKeys::FirstWins && object.contains_key(&key) {
+                            // The value of the first occurrence stays
+                        } else if object.insert(key, value).is_some()
+                            && !collect
+                            && policy == DuplicateKeys::Error
+                        {
+                            self.tolerate(Failure::new(
+                                ParseErrorType::DuplicateKey,
+                                Some(key_at),
+                            ))?;
+                        }
+                    }
+                }
+
+                self.release(stack, next);
+                let i = self.skip_comments(next)?;
+
+                match self.tokens.get(i) {
+                    Some(token) if token.token_type == TokenType::Comma => {
+                        stack.last_mut().unwrap().last_comma = Some(i);
+                        Ok(State::Item(i + 1))
+                    }
+                    _ => Ok(State::Close(i)),
+                }
+            }
+            State::Close(i) => {
+                let frame = stack.last().unwrap();
+                self.check_trailing_comma(frame.last_comma, i)?;
+                self.attach_comments(&stack[..stack.len() - 1]);
+
+                let frame = stack.last().unwrap();
+                let (close, error_type) = match frame.container {
+                    Container::Array(_) => {
+                        (&TokenType::CloseSquare, ParseErrorType::MissingCloseSquare)
+                    }
+                    Container::Object { .. } => {
+                        (&TokenType::CloseCurly, ParseErrorType::MissingCloseCurly)
+                    }
+                };
+
+                let next = match self.expect(close, error_type, i) {
+                    Ok(()) => i + 1,
+                    Err(failure)
+                        if self.errors.is_some()
+                            && matches!(
+                                self.tokens.get(i).map(|token| &token.token_type),
+                                Some(TokenType::CloseCurly | TokenType::CloseSquare)
+                            ) =>
+                    {
+                        let bracket = &self.tokens[i].token_type;
+                        let closes_outer = stack.iter().any(|frame| match frame.container {
+                            Container::Array(_) => *bracket == TokenType::CloseSquare,
+                            Container::Object { .. } => *bracket == TokenType::CloseCurly,
+                        });
+
+                        if !stack.last().unwrap().unclosed {
+                            self.tolerate(failure)?;
+                        }
+
+                        // A bracket nothing was opened for is skipped, otherwise it is left to
+                        // close the outer array or object
+                        if !closes_outer {
+                            return self
+                                .resync(stack, i + 1)
+                                .ok_or(Failure::new(ParseErrorType::UnexpectedEnd, None));
+                        }
+
+                        i
+                    }
+                    Err(failure) => return Err(failure),
+                };
+
+                let frame = stack.pop().unwrap();
+
+                if let Some((depth, first)) = self.raw {
+                    if depth == stack.len() {
+                        self.raw = None;
+                        let raw =
+                            RawJson::new(Cow::Borrowed(self.raw_text(first, i)), &self.options);
+                        return Ok(State::Done(Json::Raw(raw), next));
+                    }
+                }
+
+                let value = match frame.container {
+                    Container::Object { object, keys, .. } => {
+                        self.collect_duplicates(keys, object.len());
+                        Json::Object(object)
+                    }
+                    container => container.into_json(),
+                };
+
+                Ok(State::Done(value, next))
+            }
+        }
+    }
+
+    fn parse(&mut self) -> Result<Json<'s>, Failure> {
+        self.parse_at(0).map(|(value, _)| value)
+    }
+
+    // Parses the document starting at token `start`, returning it with the index of the token
+    // after it
+    fn parse_at(&mut self, start: usize) -> Result<(Json<'s>, usize), Failure> {
+        let mut stack = vec![];
+        let mut state = State::Value(start);
+
+        loop {
+            state = match state {
+                State::Done(value, next) if stack.is_empty() => {
+                    let mut bytes_parsed = self.tokens[next - 1].end;
+
+                    if !self.options.allow_trailing_content {
+                        // Comments after the document are checked like any other
+                        let next = self.skip_comments(next)?;
+                        self.attach_comments(&stack);
+
+                        if self.tokens.get(next).is_some() {
+                            let failure = Failure::new(ParseErrorType::UnexpectedToken, Some(next));
+                            self.tolerate(failure)?;
+                        } else {
+                            // Only whitespace is left
+                            bytes_parsed = self.total_bytes.unwrap_or(bytes_parsed);
+                        }
+                    }
+
+                    self.report(bytes_parsed);
+
+                    return Ok((value, next));
+                }
+                state => {
+                    let in_item = matches!(state, State::Value(_) | State::Item(_));
+
+                    match self.step(&mut stack, state) {
+                        Ok(state) => state,
+                        // A closing bracket where an item would have started ends the array or
+                        // object, other tokens are an error of their own
+                        Err(failure)
+                            if failure.error_type == ParseErrorType::UnexpectedToken
+                                && self.closes_item(&stack) =>
+                        {
+                            State::Close(stack.last().unwrap().item_start)
+                        }
+                        Err(failure) if self.errors.is_some() => {
+                            let state = self.recover(&mut stack, &failure, in_item);
+                            self.tolerate(failure)?;
+
+                            match state {
+                                Some(state) => state,
+                                None => return Ok((unwind(stack, in_item), self.tokens.end())),
+                            }
+                        }
+                        Err(failure) => return Err(failure),
+                    }
+                }
+            };
+        }
+    }
+
+    // Whether the token at `i` can be the next item of the frame, a value or the key of a member
+    fn starts_item(&self, frame: &Frame<'s>, i: usize) -> bool {
+        match (&frame.container, &self.tokens[i].token_type) {
+            (Container::Object { .. }, TokenType::String(_)) => true,
+            (Container::Object { .. }, _) => false,
+            (_, token_type) => matches!(
+                token_type,
+                TokenType::Null
+                    | TokenType::Bool(_)
+                    | TokenType::Number(..)
+                    | TokenType::String(_)
+                    | TokenType::OpenCurly
+                    | TokenType::OpenSquare
+            ),
+        }
+    }
+
+    fn closes_item(&mut self, stack: &[Frame<'s>]) -> bool {
+        let token = match stack.last() {
+            Some(frame) => self.tokens.get(frame.item_start),
+            None => None,
+        };
+
+        matches!(
+            token.map(|token| &token.token_type),
+            Some(TokenType::CloseCurly | TokenType::CloseSquare)
+        )
+    }
+
+    // Where to carry on after an error when recovering, `None` when the parser has to stop. The
+    // item that failed, or the tokens skipped to get back on track, become a `Json::Error`
+    fn recover(
+        &mut self,
+        stack: &mut [Frame<'s>],
+        failure: &Failure,
+        in_item: bool,
+    ) -> Option<State<'s>> {
+        if failure.error_type == ParseErrorType::MemoryLimitExceeded {
+            return None;
+        }
+
+        let from = failure.at?;
+
+        // A missing comma, the item after it is read like any other
+        if self.starts_item(stack.last()?, from)
+            && matches!(
+                failure.error_type,
+                ParseErrorType::MissingCloseCurly | ParseErrorType::MissingCloseSquare
+            )
+        {
+            return Some(State::Item(from));
+        }
+
+        let state = self.resync(stack, from)?;
+
+        let skipped = !matches!(
+            self.tokens[from].token_type,
+            TokenType::Comma | TokenType::CloseCurly | TokenType::CloseSquare
+        );
+
+        let frame = stack.last_mut().unwrap();
+
+        if in_item || skipped {
+            frame.container.add(Json::Error);
+        }
+
+        frame.unclosed = matches!(state, State::Close(_))
+            && matches!(
+                failure.error_type,
+                ParseErrorType::MissingCloseCurly | ParseErrorType::MissingCloseSquare
+            );
+
+        Some(state)
+    }
+
+    // The next comma or closing bracket from `from` of the innermost open array or object,
+    // skipping anything nested in between
+    fn resync(&mut self, stack: &mut [Frame<'s>], from: usize) -> Option<State<'s>> {
+        let frame = stack.last_mut()?;
+        let mut depth = 0;
+        let mut i = from;
+
+        while let Some(token) = self.tokens.get(i) {
+            match token.token_type {
+                TokenType::OpenCurly | TokenType::OpenSquare => depth += 1,
+                TokenType::CloseCurly | TokenType::CloseSquare if depth == 0 => {
+                    return Some(State::Close(i))
+                }
+                TokenType::CloseCurly | TokenType::CloseSquare => depth -= 1,
+                TokenType::Comma if depth == 0 => {
+                    frame.last_comma = Some(i);
+                    return Some(State::Item(i + 1));
+                }
+                _ => {}
+            }
+
+            i += 1;
+        }
+
+        None
+    }
+
+    // The error of a streamed parse, lexing stops at its first error and that comes before any
+    // error the parser made of the missing tokens
+    fn stream_error(&mut self, failure: Failure) -> StreamError<'s> {
+        let stream = match &mut self.tokens {
+            Tokens::Stream(stream) => stream,
+            Tokens::Slice(_) => unreachable!(),
+        };
+
+        if let Some(lex_error) = stream.error.take() {
+            return StreamError::Lex(lex_error);
+        }
+
+        StreamError::Parse(Box::new(TokenError {
+            error_type: failure.error_type,
+            token: failure.at.and_then(|i| stream.get(i).cloned()),
+            expected: failure.expected,
+        }))
+    }
+
+    // Parses streamed tokens, a lexing error the parser ran into after the document is an error as
+    // well
+    fn parse_stream(&mut self) -> Result<Json<'s>, StreamError<'s>> {
+        let value = self.parse().map_err(|failure| self.stream_error(failure))?;
+
+        match &mut self.tokens {
+            Tokens::Stream(stream) => match stream.error.take() {
+                Some(lex_error) => Err(StreamError::Lex(lex_error)),
+                None => Ok(value),
+            },
+            Tokens::Slice(_) => Ok(value),
+        }
+    }
+}
+
+// Closes the arrays and objects still open when recovery gives up, keeping what they hold
+fn unwind<'s>(mut stack: Vec<Frame<'s>>, in_item: bool) -> Json<'s> {
+    if let (true, Some(frame)) = (in_item, stack.last_mut()) {
+        frame.container.add(Json::Error);
+    }
+
+    let mut value = Json::Error;
+
+    while let Some(frame) = stack.pop() {
+        value = frame.container.into_json();
+
+        if let Some(parent) = stack.last_mut() {
+            parent.container.add(mem::take(&mut value));
+        }
+    }
+
+    value
+}
+
+pub fn parse<'t, 's>(tokens: &'t [Token<'s>]) -> Result<Json<'s>, ParseError<'t>> {
+    parse_with_options(tokens, ParserOptions::default())
+}
+
+// Lexes and parses in one go, strings of the result still point into `raw`. The tokens are
+// parsed as they are lexed, see `parse_tokens`
+pub fn parse_str(raw: &str) -> Result<Json<'_>, OwnedParseError> {
+    parse_tokens(lexer::tokens(raw))
+}
+
+// `parse_str` for input that has not been checked to be UTF-8, bytes that are not are an
+// `InvalidUtf8` error
+pub fn parse_bytes(bytes: &[u8]) -> Result<Json<'_>, OwnedParseError> {
+    parse_str(encoding::from_utf8(bytes)?)
+}
+
+// Like `parse_bytes`, with bytes that are not UTF-8 read as U+FFFD replacement characters
+pub fn parse_bytes_lossy(bytes: &[u8]) -> Result<JsonOwned, OwnedParseError> {
+    parse_str(&String::from_utf8_lossy(bytes)).map(Json::into_owned)
+}
+
+// An error at a position rather than at a token, for types whose message does not show the token
+pub(crate) fn error_at(error_type: ParseErrorType, line: usize, col: usize) -> OwnedParseError {
+    OwnedParseError {
+        line: Some(line),
+        col: Some(col),
+        ..OwnedParseError::from(ParseError::new(error_type, None, None))
+    }
+}
+
+// Parses tokens as they come, like those of `lexer::tokens`, with the default options. Unlike
+// `parse` the tokens are never all held at once, only the document being built is
+pub fn parse_tokens<'s, I>(tokens: I) -> Result<Json<'s>, OwnedParseError>
+where
+    I: IntoIterator<Item = Result<Token<'s>, LexError>>,
+    I::IntoIter: 's,
+{
+    parse_tokens_with_options(tokens, ParserOptions::default()).map_err(OwnedParseError::from)
+}
+
+// `parse_tokens` with `options`, the error owns its token as the tokens before it are gone
+pub fn parse_tokens_with_options<'s, I>(
+    tokens: I,
+    options: ParserOptions,
+) -> Result<Json<'s>, StreamError<'s>>
+where
+    I: IntoIterator<Item = Result<Token<'s>, LexError>>,
+    I::IntoIter: 's,
+{
+    Parser::new(Tokens::stream(tokens), options).parse_stream()
+}
+
+pub fn parse_str_with_options<'s>(
+    raw: &'s str,
+    options: ParserOptions,
+) -> Result<Json<'s>, OwnedParseError> {
+    Parser::new(Tokens::stream(lexer::tokens(raw)), options)
+        .with_source(raw)
+        .parse_stream()
+        .map_err(OwnedParseError::from)
+}
+
+// Like `parse`, reporting progress to `observer` every `observer.interval()` bytes and
+// once more when the document has been parsed
+pub fn parse_with_observer<'t, 's>(
+    tokens: &'t [Token<'s>],
+    observer: &mut dyn ParseObserver,
+) -> Result<Json<'s>, ParseError<'t>> {
+    let options = ParserOptions {
+        observer: Some(observer),
+        ..ParserOptions::default()
+    };
+
+    parse_with_options(tokens, options)
+}
+
+// Parses the whole document and returns every duplicated key, ordered by first occurrence,
+// instead of stopping at the first one
+pub fn find_duplicate_keys<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> Result<Vec<DuplicateKey<'t>>, ParseError<'t>> {
+    parse_with_duplicates(tokens, options).map(|(_, duplicates)| duplicates)
+}
+
+// Like `find_duplicate_keys`, also returning the value built with the `duplicate_keys` policy,
+// where `DuplicateKeys::Error` keeps the last value
+pub fn parse_with_duplicates<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> Result<(Json<'s>, Vec<DuplicateKey<'t>>), ParseError<'t>> {
+    let mut parser = Parser::new(Tokens::Slice(tokens), options);
+    parser.duplicates = Some(vec![]);
+    let value = parser
+        .parse()
+        .map_err(|failure| failure.to_parse_error(tokens))?;
+
+    let mut duplicates = parser
+        .duplicates
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, occurrences)| DuplicateKey {
+            key,
+            occurrences: occurrences.into_iter().map(|i| &tokens[i]).collect(),
+        })
+        .collect::<Vec<_>>();
+    duplicates.sort_by_key(|duplicate| {
+        let first = duplicate.occurrences[0];
+        (first.line, first.col)
+    });
+
+    Ok((value, duplicates))
+}
+
+// Commas right before a closing bracket, with only comments between them, which
+// `allow_trailing_commas` lets through
+pub fn find_trailing_commas<'t>(tokens: &'t [Token<'t>]) -> Vec<&'t Token<'t>> {
+    let mut significant = tokens
+        .iter()
+        .filter(|token| !matches!(token.token_type, TokenType::Comment(_)))
+        .peekable();
+    let mut commas = vec![];
+
+    while let Some(token) = significant.next() {
+        let closes = significant.peek().is_some_and(|next| {
+            matches!(
+                next.token_type,
+                TokenType::CloseCurly | TokenType::CloseSquare
+            )
+        });
+
+        if token.token_type == TokenType::Comma && closes {
+            commas.push(token);
+        }
+    }
+
+    commas
+}
+
+// Parses a JSONC document, with `//` and `/* */` comments whatever `allow_comments` is, and also
+// returns each comment in order, for tools that put them back after changing the document
+pub fn parse_with_comments<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> Result<(Json<'s>, Vec<Comment<'t>>), ParseError<'t>> {
+    let options = ParserOptions {
+        allow_comments: true,
+        ..options
+    };
+    let mut parser = Parser::new(Tokens::Slice(tokens), options);
+    parser.comments = Some(vec![]);
+    let value = parser
+        .parse()
+        .map_err(|failure| failure.to_parse_error(tokens))?;
+
+    let comments = parser
+        .comments
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(i, pointer)| Comment {
+            token: &tokens[i],
+            pointer,
+        })
+        .collect();
+
+    Ok((value, comments))
+}
+
+// Every error of the document in one pass, empty when it is valid. After an error the parser
+// skips to the next comma or closing bracket and carries on from there, so a mistake can cause
+// more errors close to it
+pub fn find_errors<'t, 's>(tokens: &'t [Token<'s>], options: ParserOptions) -> Vec<ParseError<'t>> {
+    let mut parser = Parser::new(Tokens::Slice(tokens), options);
+    parser.errors = Some(vec![]);
+    parser.check_only = true;
+    let result = parser.parse();
+
+    let mut errors = parser.errors.unwrap_or_default();
+    errors.extend(result.err());
+    errors
+        .iter()
+        .map(|failure| failure.to_parse_error(tokens))
+        .collect()
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ParseErrorType {
-    UnexpectedEnd,
-    UnexpectedToken,
-    DuplicateKey,
-    TrailingComma,
-    KeyNotInQuotes,
-    MissingColon,
-    MissingCloseCurly,
-    MissingCloseSquare,
+// Like `parse_with_options` without building the document, for when only whether it is valid
+// matters
+pub fn check<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> Result<(), ParseError<'t>> {
+    let mut parser = Parser::new(Tokens::Slice(tokens), options);
+    parser.check_only = true;
+    parser
+        .parse()
+        .map(|_| ())
+        .map_err(|failure| failure.to_parse_error(tokens))
 }
 
-#[derive(Debug, PartialEq)]
-pub struct ParseError<'a> {
-    pub error_type: ParseErrorType,
-    pub token: Option<&'a Token<'a>>,
-    pub expected: Option<&'a TokenType<'a>>,
+// Lexes and checks the input with the default options, returning every error like `find_errors`
+pub fn validate(raw: &str) -> Result<(), Vec<OwnedParseError>> {
+    let tokens = lexer::lex(raw).map_err(|lex_error| vec![OwnedParseError::from(lex_error)])?;
+    let errors = find_errors(&tokens, ParserOptions::default());
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.into_iter().map(OwnedParseError::from).collect())
+    }
 }
 
-impl<'a> ParseError<'a> {
-    pub fn new(
-        error_type: ParseErrorType,
-        token: Option<&'a Token<'a>>,
-        expected: Option<&'a TokenType<'a>>,
-    ) -> ParseError<'a> {
-        ParseError {
-            error_type,
-            token,
-            expected,
+// The best tree that can be made of a broken document, for editors that still want an outline or
+// completions, along with the errors `find_errors` reports. Each item that could not be parsed is
+// a `Json::Error` and the items after it are kept, items missing a comma before them are read as
+// if it were there, items of objects whose key could not be read are left out and arrays and
+// objects left open at the end of the input are closed
+pub fn parse_lossy<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> (Json<'s>, Vec<ParseError<'t>>) {
+    let mut parser = Parser::new(Tokens::Slice(tokens), options);
+    parser.errors = Some(vec![]);
+    let result = parser.parse();
+
+    let mut errors = parser.errors.unwrap_or_default();
+    let value = match result {
+        Ok(value) => value,
+        Err(failure) => {
+            errors.push(failure);
+            Json::Error
+        }
+    };
+
+    let errors = errors
+        .iter()
+        .map(|failure| failure.to_parse_error(tokens))
+        .collect();
+
+    (value, errors)
+}
+
+// Parses documents following each other, like `{} {} []` in log pipelines, with the range of
+// tokens of each. Parsing stops at the first error, which is the last item
+pub fn parse_documents<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> Vec<Result<(Json<'s>, Range<usize>), ParseError<'t>>> {
+    let options = ParserOptions {
+        allow_trailing_content: true,
+        ..options
+    };
+    let mut parser = Parser::new(Tokens::Slice(tokens), options);
+    let mut documents = vec![];
+    let mut next = 0;
+
+    loop {
+        let document = parser.skip_comments(next).and_then(|start| {
+            if start == tokens.len() {
+                return Ok(None);
+            }
+
+            let (value, end) = parser.parse_at(start)?;
+            Ok(Some((value, start..end)))
+        });
+
+        match document {
+            Ok(Some((value, range))) => {
+                next = range.end;
+                documents.push(Ok((value, range)));
+            }
+            Ok(None) => break,
+            Err(failure) => {
+                documents.push(Err(failure.to_parse_error(tokens)));
+                break;
+            }
         }
     }
+
+    documents
 }
 
-struct ParseContext<'a> {
-    key: &'a str,
-    value: Json<'a>,
-    next: usize,
+pub fn parse_with_options<'t, 's>(
+    tokens: &'t [Token<'s>],
+    options: ParserOptions,
+) -> Result<Json<'s>, ParseError<'t>> {
+    Parser::new(Tokens::Slice(tokens), options)
+        .parse()
+        .map_err(|failure| failure.to_parse_error(tokens))
 }
 
-impl<'a> ParseContext<'a> {
-    pub fn new(value: Json<'a>, next: usize) -> ParseContext<'a> {
-        ParseContext {
-            key: "",
-            value,
-            next,
-        }
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{self};
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_str() {
+        let raw = String::from("{\"a\": [1, \"b\"]}");
+        let value = parse_str(&raw).unwrap();
+        assert_eq!(value.pointer("/a/1").and_then(Json::as_str), Some("b"));
+
+        let error = parse_str("[1,\n 2,]").unwrap_err();
+        assert_eq!(error.error_type, ParseErrorType::TrailingComma);
+        assert_eq!((error.line, error.col), (Some(1), Some(2)));
+        assert_eq!(error.to_string(), "Trailing commas are not valid at 2:3");
+
+        let error = parse_str("[1").unwrap_err();
+        assert_eq!(error.to_string(), "File ended unexpectedly");
     }
 
-    pub fn key_value_pair(key: &'a str, value: Json<'a>, next: usize) -> ParseContext<'a> {
-        ParseContext { key, value, next }
+    #[test]
+    fn test_parse_bytes() {
+        let bytes = b"{\"a\": \"caf\xe9\"}";
+
+        let error = parse_bytes(bytes).unwrap_err();
+        assert!(matches!(error.error_type, ParseErrorType::InvalidUtf8(_)));
+        assert_eq!(
+            error.to_string(),
+            "Input is not valid UTF-8 at byte 10, it may be windows-1252 at 1:11"
+        );
+
+        let value = parse_bytes_lossy(bytes).unwrap();
+        assert_eq!(
+            value.pointer("/a").and_then(Json::as_str),
+            Some("caf\u{fffd}")
+        );
+
+        assert_eq!(
+            parse_bytes(b"[1]").unwrap(),
+            Json::Array(vec![Json::from(1)])
+        );
     }
-}
 
-fn expect<'a>(
-    token_type: &'a TokenType<'a>,
-    error_type: ParseErrorType,
-    tokens: &'a [Token],
-    i: usize,
-) -> Result<&'a Token<'a>, ParseError<'a>> {
-    match tokens.get(i) {
-        Some(token) => {
-            if token.token_type == *token_type {
-                Ok(token)
-            } else {
-                Err(ParseError::new(error_type, Some(token), Some(token_type)))
+    #[test]
+    fn test_parse_tokens() {
+        let deep = "[".repeat(DEFAULT_MAX_DEPTH + 1);
+        let cases = [
+            "{\"a\": [1, {\"b\": null}, \"\\u00e9\"], \"c\": {}}",
+            "[1, 2",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "{\"a\": 1, \"b\": {}, \"a\": 2}",
+            "{a: 1}",
+            "[1] [2]",
+            "[1 2]",
+            "[01]",
+            "\"a",
+            "[1, // one\n 2,]",
+            "[[[1]]]",
+            &deep,
+        ];
+        let options = [
+            ParserOptions::default,
+            ParserOptions::lenient,
+            || ParserOptions {
+                max_depth: 2,
+                duplicate_keys: DuplicateKeys::FirstWins,
+                ..ParserOptions::default()
+            },
+            || ParserOptions {
+                memory_limit: Some(256),
+                ..ParserOptions::default()
+            },
+        ];
+
+        // Streamed tokens are parsed like lexed ones, with the same options
+        for raw in cases {
+            for options in options {
+                let lexed = lexer::lex(raw)
+                    .map_err(OwnedParseError::from)
+                    .and_then(|tokens| {
+                        parse_with_options(&tokens, options()).map_err(OwnedParseError::from)
+                    });
+
+                assert_eq!(
+                    parse_tokens_with_options(lexer::tokens(raw), options())
+                        .map_err(OwnedParseError::from),
+                    lexed,
+                    "{}",
+                    raw
+                );
             }
         }
-        None => Err(ParseError::new(ParseErrorType::UnexpectedEnd, None, None)),
+
+        assert!(matches!(
+            parse_tokens_with_options(lexer::tokens(cases[0]), options[3]()),
+            Err(StreamError::Parse(token_error))
+                if token_error.error_type == ParseErrorType::MemoryLimitExceeded
+        ));
+        assert_eq!(
+            parse_tokens(lexer::tokens("[1, 2,]"))
+                .unwrap_err()
+                .error_type,
+            ParseErrorType::TrailingComma
+        );
+        assert!(matches!(
+            parse_tokens_with_options(lexer::tokens("[1] @"), ParserOptions::default()),
+            Err(StreamError::Lex(_))
+        ));
+
+        // Tokens are let go of as the parser goes, the key a duplicate is reported at is kept
+        let raw = format!(
+            "{{\"a\": 1,\n\"a\": [{}0]}}",
+            "0, ".repeat(10 * RELEASE_INTERVAL)
+        );
+        let mut parser = Parser::new(
+            Tokens::stream(lexer::tokens(&raw)),
+            ParserOptions::default(),
+        );
+        let failure = parser.parse().unwrap_err();
+        assert_eq!(failure.error_type, ParseErrorType::DuplicateKey);
+
+        let error = parser.stream_error(failure);
+        let token = match error {
+            StreamError::Parse(token_error) => token_error.token.unwrap(),
+            StreamError::Lex(_) => unreachable!(),
+        };
+        assert_eq!((token.line, token.col), (1, 0));
+
+        match parser.tokens {
+            Tokens::Stream(stream) => assert!(
+                stream.first + stream.window.len() > 10 * RELEASE_INTERVAL
+                    && stream.window.len() < 2 * RELEASE_INTERVAL
+            ),
+            Tokens::Slice(_) => unreachable!(),
+        }
     }
-}
 
-fn check_trailing_comma<'a>(
-    tokens: &'a [Token],
-    last_comma: Option<usize>,
-    i: usize,
-) -> Result<usize, ParseError<'a>> {
-    match last_comma {
-        Some(index) => {
-            if index == i - 1 {
-                Err(ParseError::new(
-                    ParseErrorType::TrailingComma,
-                    tokens.get(index),
-                    None,
-                ))
-            } else {
-                Ok(i)
-            }
-        }
-        None => Ok(i),
-    }
-}
-
-fn for_each_comma<'a, G, B>(
-    getter: G,
-    mut builder: B,
-    tokens: &'a Vec<Token>,
-    start: usize,
-) -> Result<usize, ParseError<'a>>
-where
-    G: Fn(&'a Vec<Token>, usize) -> Result<ParseContext<'a>, ParseError<'a>>,
-    B: FnMut(ParseContext<'a>, Option<&'a Token<'a>>) -> Result<(), ParseError<'a>>,
-{
-    let mut i = start;
-    let mut last_comma: Option<usize> = None;
+    #[test]
+    fn test_parse_lenient() {
+        let raw = "// settings\n{\n    name: \"a\", /* first */\n    \"name\": \"b\",\n    \"list\": [1, 2,],\n}\n";
 
-    loop {
-        match getter(tokens, i) {
-            Ok(parse_context) => {
-                let next = parse_context.next;
-
-                builder(parse_context, tokens.get(i))?;
-
-                i = next;
-
-                match expect(
-                    &TokenType::Comma,
-                    ParseErrorType::UnexpectedToken,
-                    tokens,
-                    i,
-                ) {
-                    Ok(_) => {
-                        last_comma = Some(i);
-                        i += 1;
-                    }
-                    Err(_) => break,
+        let strict = |raw| parse_str(raw).unwrap_err().error_type;
+        assert_eq!(strict(raw), ParseErrorType::Comment);
+        assert_eq!(strict("{a: 1}"), ParseErrorType::KeyNotInQuotes);
+        assert_eq!(strict("[1, /* x */]"), ParseErrorType::Comment);
+
+        assert_eq!(
+            parse_str_with_options(raw, ParserOptions::lenient()).unwrap(),
+            parse_str("{\"name\": \"b\", \"list\": [1, 2]}").unwrap()
+        );
+
+        let only_comments = ParserOptions {
+            allow_comments: true,
+            ..ParserOptions::default()
+        };
+        let error = parse_str_with_options("[1, /* x */]", only_comments).unwrap_err();
+        assert_eq!(error.error_type, ParseErrorType::TrailingComma);
+        assert_eq!(
+            parse_str_with_options("{\"a\": 1 // one\n}", ParserOptions::lenient()).unwrap(),
+            parse_str("{\"a\": 1}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_trailing_commas() {
+        let tokens =
+            lexer::lex("{\"a\": [1, 2,], \"b\": {\"c\": 3, /* x */ }, \"d\": \",]\",\n}").unwrap();
+        let commas: Vec<_> = find_trailing_commas(&tokens)
+            .iter()
+            .map(|token| (token.line, token.col))
+            .collect();
+
+        assert_eq!(commas, [(0, 11), (0, 27), (0, 49)]);
+    }
+
+    #[test]
+    fn test_parse_with_comments() {
+        let raw = "// settings\n{\n  // the name\n  \"name\": /* inline */ \"x\",\n  \"list\": [1, /* two */ 2 /* end */]\n  // last\n}";
+        let tokens = lexer::lex(raw).unwrap();
+        let (value, comments) = parse_with_comments(&tokens, ParserOptions::default()).unwrap();
+
+        assert_eq!(
+            value,
+            parse_str(r#"{"name": "x", "list": [1, 2]}"#).unwrap()
+        );
+
+        let found: Vec<_> = comments
+            .iter()
+            .map(|comment| {
+                (
+                    comment.token.token_type.to_string(),
+                    comment.pointer.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("// settings".to_string(), ""),
+                ("// the name".to_string(), "/name"),
+                ("/* inline */".to_string(), "/name"),
+                ("/* two */".to_string(), "/list/1"),
+                ("/* end */".to_string(), "/list"),
+                ("// last".to_string(), ""),
+            ]
+        );
+        assert_eq!((comments[1].token.line, comments[1].token.col), (2, 2));
+    }
+
+    #[test]
+    fn test_parse_deep() {
+        let raw = "[".repeat(100_000) + &"]".repeat(100_000);
+        let tokens = lexer::lex(&raw).unwrap();
+        assert_eq!(
+            parse(&tokens),
+            Err(ParseError::new(
+                ParseErrorType::DepthLimitExceeded,
+                Some(&tokens[DEFAULT_MAX_DEPTH]),
+                None
+            ))
+        );
+
+        let tokens = lexer::lex("{\"a\": [[1]]}").unwrap();
+        let with_limit = |max_depth| ParserOptions {
+            max_depth,
+            ..ParserOptions::default()
+        };
+        assert!(parse_with_options(&tokens, with_limit(3)).is_ok());
+        assert_eq!(
+            parse_with_options(&tokens, with_limit(2)),
+            Err(ParseError::new(
+                ParseErrorType::DepthLimitExceeded,
+                Some(&tokens[4]),
+                None
+            ))
+        );
+
+        // Without a limit the open arrays do not use the call stack either
+        let raw = "[".repeat(100_000);
+        let tokens = lexer::lex(&raw).unwrap();
+        let options = ParserOptions {
+            max_depth: usize::MAX,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            parse_with_options(&tokens, options),
+            Err(ParseError::new(ParseErrorType::UnexpectedEnd, None, None))
+        );
+
+        let raw = "{\"a\": [".repeat(200) + "1" + &"]}".repeat(200);
+        let value = parse_str(&raw).unwrap();
+        assert_eq!(
+            value.pointer(&"/a/0".repeat(200)),
+            Some(&Json::Number(1.0.into()))
+        );
+    }
+
+    #[test]
+    fn test_as_str() {
+        let raw = r#"["plain", "tab\there", "\u00e9\ud83d\ude00\/", 1]"#;
+        let tokens = lexer::lex(raw).unwrap();
+        let value = parse(&tokens).unwrap();
+
+        let items = match &value {
+            Json::Array(items) => items,
+            _ => unreachable!(),
+        };
+
+        // The parsed string points into the input
+        let plain = match &items[0] {
+            Json::String(s) => s,
+            _ => unreachable!(),
+        };
+        let range = raw.as_ptr() as usize..raw.as_ptr() as usize + raw.len();
+        assert!(range.contains(&(plain.as_ptr() as usize)));
+
+        assert_eq!(items[0].as_str(), Some("plain"));
+        assert!(matches!(items[1], Json::String(Cow::Owned(_))));
+        assert_eq!(items[1].as_str(), Some("tab\there"));
+        assert_eq!(items[2].as_str(), Some("é😀/"));
+        assert_eq!(items[3].as_str(), None);
+        assert_eq!(value.to_string(), r#"["plain","tab\there","é😀/",1]"#);
+    }
+
+    #[test]
+    fn test_lazy_strings() {
+        let raw = r#"{"plain": "x", "read": "tab\there", "unread": "\u00e9\/"}"#;
+        let options = || ParserOptions {
+            lazy_strings: true,
+            ..ParserOptions::default()
+        };
+        let value = parse_str_with_options(raw, options()).unwrap();
+
+        let decoded = |key| match &value[key] {
+            Json::Raw(raw) => raw.state.get().is_some(),
+            _ => unreachable!(),
+        };
+        assert!(!decoded("read"));
+        assert!(!decoded("unread"));
+        assert!(matches!(value["plain"], Json::String(Cow::Borrowed(_))));
+
+        assert_eq!(value["read"].as_str(), Some("tab\there"));
+        assert!(decoded("read"));
+        assert!(!decoded("unread"));
+
+        // Writing the document copies the text of the unread string without decoding it
+        assert_eq!(
+            value.to_string(),
+            r#"{"plain":"x","read":"tab\there","unread":"\u00e9\/"}"#
+        );
+        assert!(!decoded("unread"));
+
+        // Lazy strings are the strings they hold to everything but a match on `Json::String`
+        let eager = parse_str(raw).unwrap();
+        assert_eq!(value, eager);
+        assert_eq!(value["unread"], Json::from("é/"));
+        assert_eq!(
+            String::try_from(value["unread"].clone()),
+            Ok("é/".to_string())
+        );
+        assert_eq!(value.pointer("/unread").and_then(Json::as_str), Some("é/"));
+        assert_eq!(value["unread"].type_name(), "string");
+
+        let hash = |value: &Json| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&value), hash(&eager));
+        let set: HashSet<_> = eager.entries().map(|(_, member)| member.clone()).collect();
+        assert!(value.entries().all(|(_, member)| set.contains(member)));
+
+        // Without the input to point into, strings are decoded as they are parsed
+        let tokens = lexer::lex(raw).unwrap();
+        let value = parse_with_options(&tokens, options()).unwrap();
+        assert!(matches!(value["unread"], Json::String(Cow::Owned(_))));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let value = parse_str("{\"a\\u0062\": [true, null, 1.5], \"c\": {}}").unwrap();
+
+        assert_eq!(value["ab"][0].as_bool(), Some(true));
+        assert!(value["ab"][1].is_null());
+        assert_eq!(value["ab"][2].as_f64(), Some(1.5));
+        assert_eq!(value["ab"].as_array().map(Vec::len), Some(3));
+        assert_eq!(value["c"].as_object().map(Map::len), Some(0));
+        assert_eq!(value.as_object().map(Map::len), Some(2));
+        assert!(value["missing"][3]["x"].is_null());
+        assert!(value[0].is_null());
+        assert_eq!(value["ab"][0].as_array(), None);
+        assert_eq!(value.get("c"), Some(&Json::Object(Map::new())));
+        assert!(!Json::Error.is_null());
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut value = parse_str("{\"a\\tb\": [1, 2], \"c\": true}").unwrap();
+
+        let keys: Vec<_> = value.entries().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a\tb", "c"]);
+        assert_eq!(value.members().count(), 0);
+        assert_eq!(value["c"].entries().count(), 0);
+
+        for (key, item) in value.entries_mut() {
+            if key == "a\tb" {
+                for n in item.members_mut() {
+                    *n = Json::from(n.as_i64().unwrap() * 10);
                 }
             }
-            Err(parse_error) => match parse_error.error_type {
-                ParseErrorType::UnexpectedToken => break,
-                _ => return Err(parse_error),
-            },
         }
+
+        let items: Vec<_> = value["a\tb"].members().filter_map(Json::as_i64).collect();
+        assert_eq!(items, [10, 20]);
     }
 
-    check_trailing_comma(tokens, last_comma, i)
-}
+    #[test]
+    fn test_conversions() {
+        let mut map = HashMap::new();
+        map.insert("b\"", vec![Some(1.5), None]);
+        map.insert("a", vec![]);
 
-fn expect_key<'a>(tokens: &'a [Token], i: usize) -> Result<&'a str, ParseError<'a>> {
-    match tokens.get(i) {
-        Some(token) => match token.token_type {
-            TokenType::String(s) => Ok(s),
-            TokenType::Invalid(_) | TokenType::Number(_) | TokenType::Bool(_) => Err(
-                ParseError::new(ParseErrorType::KeyNotInQuotes, Some(token), None),
-            ),
-            _ => Err(ParseError::new(ParseErrorType::UnexpectedToken, None, None)),
-        },
-        None => Err(ParseError::new(ParseErrorType::UnexpectedEnd, None, None)),
+        let value = Json::from(map);
+        assert_eq!(
+            formatter::format_json(&value, 0),
+            "{\"a\":[],\"b\\\"\":[1.5,null]}"
+        );
+
+        let value = parse_str("{\"a\": [\"x\\ty\", \"z\"], \"b\": 2}").unwrap();
+        let lists: Result<HashMap<String, Vec<String>>, _> = HashMap::try_from(value.clone());
+        assert_eq!(
+            lists.unwrap_err(),
+            TypeError {
+                expected: "array",
+                found: "number"
+            }
+        );
+
+        let a = Vec::<String>::try_from(value["a"].clone()).unwrap();
+        assert_eq!(a, ["x\ty", "z"]);
+        assert_eq!(i64::try_from(value["b"].clone()), Ok(2));
+        assert_eq!(f64::try_from(value["b"].clone()), Ok(2.0));
+        assert_eq!(
+            bool::try_from(Json::from("x")).unwrap_err().to_string(),
+            "Expected boolean, found string"
+        );
+        assert!(u64::try_from(Json::from(-1)).is_err());
+        assert_eq!(Json::from(String::from("\n")), Json::String("\n".into()));
     }
-}
 
-fn key_value_pair<'a>(
-    tokens: &'a Vec<Token>,
-    start: usize,
-) -> Result<ParseContext<'a>, ParseError<'a>> {
-    let key = match expect_key(tokens, start) {
-        Ok(k) => k,
-        Err(parse_error) => return Err(parse_error),
-    };
+    #[test]
+    fn test_numbers() {
+        let raw = "[9007199254740993, -9223372036854775808, 18446744073709551615, 0.1, -0]";
+        let value = parse_str(raw).unwrap();
 
-    expect(
-        &TokenType::Colon,
-        ParseErrorType::MissingColon,
-        tokens,
-        start + 1,
-    )?;
+        assert_eq!(
+            value.pointer("/0").and_then(Json::as_i64),
+            Some(9007199254740993)
+        );
+        assert_eq!(value.pointer("/1").and_then(Json::as_i64), Some(i64::MIN));
+        assert_eq!(value.pointer("/2").and_then(Json::as_i64), None);
+        assert_eq!(value.pointer("/2").and_then(Json::as_u64), Some(u64::MAX));
+        assert_eq!(value.pointer("/3").and_then(Json::as_f64), Some(0.1));
+        assert_eq!(
+            formatter::format_json(&value, 0),
+            "[9007199254740993,-9223372036854775808,18446744073709551615,0.1,-0]"
+        );
+    }
 
-    let value_parse_context = value(tokens, start + 2)?;
+    #[test]
+    fn test_raw_numbers() {
+        let raw = "[0.1000000000000000055, 1e400, 1.50, -0, 12]";
+        let options = ParserOptions {
+            raw_numbers: true,
+            ..ParserOptions::default()
+        };
+        let value = parse_str_with_options(raw, options).unwrap();
 
-    return Ok(ParseContext::key_value_pair(
-        key,
-        value_parse_context.value,
-        value_parse_context.next,
-    ));
-}
+        assert_eq!(
+            formatter::format_json(&value, 0),
+            "[0.1000000000000000055,1e400,1.50,-0,12]"
+        );
+        assert_eq!(value.pointer("/4").and_then(Json::as_u64), Some(12));
+        assert_eq!(value, parse_str(raw).unwrap());
+    }
 
-fn object<'a>(tokens: &'a Vec<Token>, start: usize) -> Result<ParseContext<'a>, ParseError<'a>> {
-    let mut object = BTreeMap::new();
-    let builder = |parse_context: ParseContext<'a>, token: Option<&'a Token<'a>>| match object
-        .insert(parse_context.key, parse_context.value)
-    {
-        Some(_) => return Err(ParseError::new(ParseErrorType::DuplicateKey, token, None)),
-        None => Ok(()),
-    };
+    #[test]
+    fn test_non_finite() {
+        let raw = "[NaN, Infinity, -Infinity, 1]";
+        let options = |raw_numbers| ParserOptions {
+            allow_non_finite: true,
+            raw_numbers,
+            ..ParserOptions::default()
+        };
+        let value = parse_str_with_options(raw, options(false)).unwrap();
 
-    let i = match for_each_comma(key_value_pair, builder, tokens, start + 1) {
-        Ok(next) => next,
-        Err(parse_error) => return Err(parse_error),
-    };
+        assert!(value[0].as_f64().unwrap().is_nan());
+        assert_eq!(value[1].as_f64(), Some(f64::INFINITY));
+        assert_eq!(value[2].as_f64(), Some(f64::NEG_INFINITY));
+        assert_eq!(
+            formatter::format_json(&value, 0),
+            "[NaN,Infinity,-Infinity,1]"
+        );
 
-    let value = Json::Object(object);
+        let value = parse_str_with_options(raw, options(true)).unwrap();
+        assert_eq!(value[2], Json::Number(Number::Raw("-Infinity".into())));
 
-    match expect(
-        &TokenType::CloseCurly,
-        ParseErrorType::MissingCloseCurly,
-        tokens,
-        i,
-    ) {
-        Ok(_) => Ok(ParseContext::new(value, i + 1)),
-        Err(parse_error) => Err(parse_error),
+        assert!(parse_str(raw).is_err());
+        assert!(parse_str_with_options("[inf]", options(false)).is_err());
+
+        // Valid numbers too large for a double are not turned into `Infinity`
+        let value = parse_str("[1e400, -1e400]").unwrap();
+        assert_eq!(value[1].as_f64(), Some(f64::NEG_INFINITY));
+        assert_eq!(formatter::format_json(&value, 0), "[1e400,-1e400]");
+        assert!(parse_str_with_options("[-NaN]", options(false)).is_err());
     }
-}
 
-fn array<'a>(tokens: &'a Vec<Token>, start: usize) -> Result<ParseContext<'a>, ParseError<'a>> {
-    let mut array = vec![];
-    let builder = |parse_context: ParseContext<'a>, _| {
-        array.push(parse_context.value);
-        Ok(())
-    };
+    #[test]
+    fn test_raw_values() {
+        let raw = r#"{"id": 1, "payload": {"a": [1,  2]}, "list": [{"p": [3]}, {"p": 4}]}"#;
+        let options = || ParserOptions {
+            raw_values: &["/payload", "/list/*/p"],
+            ..ParserOptions::default()
+        };
+        let value = parse_str_with_options(raw, options()).unwrap();
 
-    let i = match for_each_comma(value, builder, tokens, start + 1) {
-        Ok(next) => next,
-        Err(parse_error) => return Err(parse_error),
-    };
+        assert_eq!(value["payload"], Json::Raw("{\"a\": [1,  2]}".into()));
+        assert_eq!(value["list"][0]["p"], Json::Raw("[3]".into()));
+        assert_eq!(value["list"][1]["p"], Json::from(4));
+        assert_eq!(value["id"], Json::from(1));
+        assert_eq!(value["payload"].to_string(), "{\"a\": [1,  2]}");
+        assert_eq!(value["list"].to_string(), r#"[{"p":[3]},{"p":4}]"#);
+        assert_eq!(
+            *value["payload"].parse_raw(),
+            parse_str(r#"{"a": [1, 2]}"#).unwrap()
+        );
+        assert!(value.semantic_eq(&parse_str(raw).unwrap(), Default::default()));
 
-    let value = Json::Array(array);
+        let invalid = parse_str_with_options(r#"{"payload": {"a": [1,]}}"#, options());
+        assert!(invalid.is_err());
+    }
 
-    match expect(
-        &TokenType::CloseSquare,
-        ParseErrorType::MissingCloseSquare,
-        tokens,
-        i,
-    ) {
-        Ok(_) => Ok(ParseContext::new(value, i + 1)),
-        Err(parse_error) => Err(parse_error),
+    #[test]
+    fn test_shallow() {
+        let raw = r#"{"name": "x", "items": [{"a": 1}, 2], "meta": {"b": [true]}}"#;
+        let options = ParserOptions {
+            shallow: true,
+            ..ParserOptions::default()
+        };
+        let value = parse_str_with_options(raw, options).unwrap();
+
+        assert_eq!(value["name"], Json::from("x"));
+        assert_eq!(value["items"], Json::Raw(r#"[{"a": 1}, 2]"#.into()));
+        assert_eq!(value["items"].parse_raw()[0]["a"], Json::from(1));
+        assert_eq!(value["meta"].parse_raw()["b"][0], Json::from(true));
+        assert!(value.semantic_eq(&parse_str(raw).unwrap(), Default::default()));
+
+        // Raw values are read again with the options of the document
+        let options = ParserOptions {
+            shallow: true,
+            allow_non_finite: true,
+            raw_numbers: true,
+            ..ParserOptions::default()
+        };
+        let value =
+            parse_str_with_options("{\"a\": [NaN, 0.1000000000000000055]}", options).unwrap();
+        let items = value["a"].parse_raw();
+
+        assert!(items[0].as_f64().unwrap().is_nan());
+        assert_eq!(items[1].to_string(), "0.1000000000000000055");
     }
-}
 
-fn value<'a>(tokens: &'a Vec<Token>, start: usize) -> Result<ParseContext<'a>, ParseError<'a>> {
-    let start_token = match tokens.get(start) {
-        Some(token) => token,
-        None => return Err(ParseError::new(ParseErrorType::UnexpectedEnd, None, None)),
-    };
+    #[test]
+    fn test_raw_lookups() {
+        let raw = r#"{"items": [{"a": 1}, 2], "meta": {"b": [true], "n": 0.1000000000000000055}}"#;
+        let options = ParserOptions {
+            shallow: true,
+            raw_numbers: true,
+            ..ParserOptions::default()
+        };
+        let value = parse_str_with_options(raw, options).unwrap();
+
+        assert!(matches!(value["meta"], Json::Raw(_)));
+        assert_eq!(value["items"][0]["a"], Json::from(1));
+        assert_eq!(value["items"][1], Json::from(2));
+        assert_eq!(value.pointer("/meta/b/0"), Some(&Json::from(true)));
+        assert_eq!(value.pointer("/meta/c"), None);
+        assert_eq!(
+            value
+                .get("meta")
+                .and_then(|meta| meta.get("b"))
+                .map(|b| b.to_string()),
+            Some("[true]".to_string())
+        );
+        assert_eq!(value["meta"]["n"].to_string(), "0.1000000000000000055");
 
-    match start_token.token_type {
-        TokenType::Null => Ok(ParseContext::new(Json::Null, start + 1)),
-        TokenType::Bool(x) => Ok(ParseContext::new(Json::Bool(x), start + 1)),
-        TokenType::Number(x) => Ok(ParseContext::new(Json::Number(x), start + 1)),
-        TokenType::String(x) => Ok(ParseContext::new(Json::String(x), start + 1)),
-        TokenType::OpenCurly => object(tokens, start),
-        TokenType::OpenSquare => array(tokens, start),
-        _ => Err(ParseError::new(
-            ParseErrorType::UnexpectedToken,
-            Some(start_token),
-            None,
-        )),
+        // Parsed once, later lookups borrow the same value
+        assert!(std::ptr::eq(&value["meta"]["b"], &value["meta"]["b"]));
+        assert_eq!(
+            value.clone().into_owned().pointer("/items/0/a"),
+            Some(&Json::from(1))
+        );
+        assert_eq!(
+            Json::Raw("[1, 2,]".into()).parse_raw().into_owned(),
+            parse_str("[1, 2]").unwrap()
+        );
     }
-}
 
-pub fn parse<'a>(tokens: &'a Vec<Token>) -> Result<Json<'a>, ParseError<'a>> {
-    match value(tokens, 0) {
-        Ok(parse_context) => Ok(parse_context.value),
-        Err(parse_error) => Err(parse_error),
+    #[test]
+    fn test_into_owned() {
+        let raw = String::from("{\"a\": [\"x\\ty\", 1.50, null]}");
+        let options = ParserOptions {
+            raw_numbers: true,
+            ..ParserOptions::default()
+        };
+        let owned = parse_str_with_options(&raw, options).unwrap().into_owned();
+        drop(raw);
+
+        let formatted = std::thread::spawn(move || formatter::format_json(&owned, 0))
+            .join()
+            .unwrap();
+        assert_eq!(formatted, "{\"a\":[\"x\\ty\",1.50,null]}");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::lexer::{self};
+    #[test]
+    fn test_edit() {
+        let mut value = parse_str("{\"a\\u0062\": [1], \"c\": 2}").unwrap();
 
-    use super::*;
+        value.get_mut("ab").unwrap().push(Json::Bool(true));
+        assert_eq!(
+            value.get_mut("ab").unwrap().get_index_mut(1),
+            Some(&mut Json::Bool(true))
+        );
+        assert_eq!(
+            value.insert("ab", Json::Null),
+            Some(parse_str("[1, true]").unwrap())
+        );
+        assert_eq!(value.insert("d\"", Json::Null), None);
+        assert_eq!(value.remove("c"), Some(Json::Number(2.0.into())));
+        assert_eq!(value.remove("x"), None);
+        assert_eq!(
+            formatter::format_json(&value, 0),
+            "{\"ab\":null,\"d\\\"\":null}"
+        );
+
+        let mut items = value.get_mut("ab").unwrap().take();
+        assert_eq!(value.pointer("/ab"), Some(&Json::Null));
+        items.push(Json::Null);
+        assert_eq!(items.pop(), Some(Json::Null));
+        assert_eq!(items.pop(), None);
+        assert_eq!(Json::Bool(true).pop(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot push onto a string")]
+    fn test_push_panics() {
+        Json::String("x".into()).push(Json::Null);
+    }
 
     #[test]
     fn test_value_unexpected_token() {
         let cases = vec![":", ",", "}", "]", "hello"];
 
         for raw in cases {
-            let tokens = lexer::lex(raw);
+            let tokens = lexer::lex(raw).unwrap();
             let value = parse(&tokens);
 
             let expected = Err(ParseError::new(
@@ -321,45 +2933,469 @@ mod tests {
             ("null  ", Ok(Json::Null)),
             ("true", Ok(Json::Bool(true))),
             ("  false ", Ok(Json::Bool(false))),
-            (" 1234", Ok(Json::Number(1234.0))),
-            ("\"foo\"", Ok(Json::String("foo"))),
+            (" 1234", Ok(Json::Number(1234.0.into()))),
+            ("\"foo\"", Ok(Json::String("foo".into()))),
             (
                 "{\"foo\":{   \"bar\":1234}   }",
-                Ok(Json::Object(BTreeMap::from([(
-                    "foo",
-                    Json::Object(BTreeMap::from([("bar", Json::Number(1234.0))])),
+                Ok(Json::Object(Map::from([(
+                    "foo".into(),
+                    Json::Object(Map::from([("bar".into(), Json::Number(1234.0.into()))])),
                 )]))),
             ),
             (
                 "{\"foo\":{   \"bar\":1234},  \"another\": \"testing\" }",
-                Ok(Json::Object(BTreeMap::from([
+                Ok(Json::Object(Map::from([
                     (
-                        "foo",
-                        Json::Object(BTreeMap::from([("bar", Json::Number(1234.0))])),
+                        "foo".into(),
+                        Json::Object(Map::from([("bar".into(), Json::Number(1234.0.into()))])),
                     ),
-                    ("another", Json::String("testing")),
+                    ("another".into(), Json::String("testing".into())),
                 ]))),
             ),
             (
                 "[1,   2,3  ,  4]",
                 Ok(Json::Array(vec![
-                    Json::Number(1.0),
-                    Json::Number(2.0),
-                    Json::Number(3.0),
-                    Json::Number(4.0),
+                    Json::Number(1.0.into()),
+                    Json::Number(2.0.into()),
+                    Json::Number(3.0.into()),
+                    Json::Number(4.0.into()),
                 ])),
             ),
         ];
 
         for case in cases {
             let (raw, expected) = case;
-            let tokens = lexer::lex(raw);
+            let tokens = lexer::lex(raw).unwrap();
             let value = parse(&tokens);
 
             assert_case(raw, value, expected)
         }
     }
 
+    #[test]
+    fn test_debug() {
+        let tokens = lexer::lex("{\"foo\": [1, true, null]}").unwrap();
+        let value = parse(&tokens).unwrap();
+
+        assert_eq!(
+            format!("{:?}", value),
+            "Object({\"foo\": Array([Number(PosInt(1)), Bool(true), Null])})"
+        );
+        assert_eq!(
+            format!("{:#?}", value),
+            "{\n    \"foo\": [\n        1,\n        true,\n        null\n    ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+
+        let values: HashSet<Json> = [
+            "{\"a\": 1, \"b\": [true, null]}",
+            "{\"b\": [true, null], \"a\": 1.0}",
+            "{\"a\": 1, \"b\": [null, true]}",
+            "\"\\u00e9\"",
+            "\"é\"",
+        ]
+        .iter()
+        .map(|raw| parse_str(raw).unwrap())
+        .collect();
+
+        assert_eq!(values.len(), 3);
+        assert!(values.contains(&parse_str("{\"b\":[true,null],\"a\":1}").unwrap()));
+    }
+
+    #[test]
+    fn test_display() {
+        let raw = "{\"a\\\"b\": [1.5, -2, \"\\u00e9\\n\", {}], \"c\": null, d: true}";
+        let value = parse_str_with_options(raw, ParserOptions::lenient()).unwrap();
+        let displayed = value.to_string();
+
+        assert_eq!(
+            displayed,
+            "{\"a\\\"b\":[1.5,-2,\"é\\n\",{}],\"c\":null,\"d\":true}"
+        );
+        assert_eq!(parse_str(&displayed).unwrap(), value);
+        assert_eq!(Json::from("tab\there").to_string(), "\"tab\\there\"");
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_parse_preserve_order() {
+        let tokens = lexer::lex("{\"b\": 1, \"a\": 2, \"c\": 3}").unwrap();
+
+        match parse(&tokens) {
+            Ok(Json::Object(object)) => {
+                assert_eq!(object.keys().collect::<Vec<_>>(), ["b", "a", "c"])
+            }
+            actual => panic!("expected an object, got {:?}", actual),
+        }
+    }
+
+    #[test]
+    fn test_parse_with_observer() {
+        struct Recorder(Vec<Progress>);
+
+        impl ParseObserver for Recorder {
+            fn progress(&mut self, progress: Progress) {
+                self.0.push(progress);
+            }
+
+            fn interval(&self) -> usize {
+                5
+            }
+        }
+
+        let raw = "[1, 2, 3, 4, 5, 6, 7, 8]\n";
+        let tokens = lexer::lex(raw).unwrap();
+        let mut recorder = Recorder(vec![]);
+
+        assert!(parse_with_observer(&tokens, &mut recorder).is_ok());
+        let bytes_parsed = recorder.0.iter().map(|progress| progress.bytes_parsed);
+        assert_eq!(bytes_parsed.collect::<Vec<_>>(), [7, 13, 19, 24]);
+        assert!(recorder
+            .0
+            .iter()
+            .all(|progress| progress.total_bytes == Some(24)));
+
+        // Streamed tokens are reported out of the whole input when it is known
+        let mut recorder = Recorder(vec![]);
+        let options = ParserOptions {
+            observer: Some(&mut recorder),
+            ..ParserOptions::default()
+        };
+        assert!(parse_str_with_options(raw, options).is_ok());
+        assert_eq!(
+            recorder.0.last(),
+            Some(&Progress {
+                bytes_parsed: 25,
+                total_bytes: Some(25)
+            })
+        );
+        assert_eq!(recorder.0.len(), 4);
+
+        let mut recorder = Recorder(vec![]);
+        let options = ParserOptions {
+            observer: Some(&mut recorder),
+            ..ParserOptions::default()
+        };
+        assert!(parse_tokens_with_options(lexer::tokens(raw), options).is_ok());
+        assert_eq!(
+            recorder.0.last(),
+            Some(&Progress {
+                bytes_parsed: 24,
+                total_bytes: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_cancelled() {
+        let raw = format!("[{}0]", "0, ".repeat(CANCELLATION_INTERVAL * 2));
+        let tokens = lexer::lex(&raw).unwrap();
+        let cancelled = || Err(ParseError::new(ParseErrorType::Cancelled, None, None));
+
+        let flag = AtomicBool::new(true);
+        let options = ParserOptions {
+            cancellation: Some(Cancellation::Flag(&flag)),
+            ..ParserOptions::default()
+        };
+        assert_eq!(parse_with_options(&tokens, options), cancelled());
+
+        // Cancelled after the first check, so the parser has to notice it part way through
+        let checks = std::cell::Cell::new(0);
+        let callback = || {
+            checks.set(checks.get() + 1);
+            checks.get() > 1
+        };
+        let options = ParserOptions {
+            cancellation: Some(Cancellation::Callback(&callback)),
+            ..ParserOptions::default()
+        };
+        assert_eq!(parse_with_options(&tokens, options), cancelled());
+        assert_eq!(checks.get(), 2);
+
+        flag.store(false, Ordering::Relaxed);
+        let options = ParserOptions {
+            cancellation: Some(Cancellation::Flag(&flag)),
+            ..ParserOptions::default()
+        };
+        assert!(parse_with_options(&tokens, options).is_ok());
+    }
+
+    #[test]
+    fn test_parse_memory_limit() {
+        let tokens = lexer::lex("{\"a\": [1, 2], \"b\": [3, 4]}").unwrap();
+        let with_limit = |memory_limit| ParserOptions {
+            memory_limit: Some(memory_limit),
+            ..ParserOptions::default()
+        };
+
+        // 7 values and 2 keys
+        let needed = 7 * mem::size_of::<Json>() + 2 * mem::size_of::<Cow<str>>();
+        assert!(parse_with_options(&tokens, with_limit(needed)).is_ok());
+
+        assert_eq!(
+            parse_with_options(&tokens, with_limit(needed - 1)),
+            Err(ParseError::new(
+                ParseErrorType::MemoryLimitExceeded,
+                Some(&tokens[14]),
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_find_duplicate_keys() {
+        let tokens =
+            lexer::lex("{\"a\": {\"b\": 1, \"b\": 2}, \"c\": 3, \"a\": 4, \"a\": 5}").unwrap();
+        let duplicates = find_duplicate_keys(&tokens, ParserOptions::default()).unwrap();
+
+        assert_eq!(
+            duplicates,
+            vec![
+                DuplicateKey {
+                    key: "a".into(),
+                    occurrences: vec![&tokens[1], &tokens[17], &tokens[21]],
+                },
+                DuplicateKey {
+                    key: "b".into(),
+                    occurrences: vec![&tokens[4], &tokens[8]],
+                },
+            ]
+        );
+
+        let tokens = lexer::lex("{\"a\": 1, \"b\": 2}").unwrap();
+        assert_eq!(
+            find_duplicate_keys(&tokens, ParserOptions::default()),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys_policy() {
+        let tokens = lexer::lex("{\"a\": 1, \"b\": {\"c\": 2, \"c\": 3}, \"a\": 4}").unwrap();
+        let with_policy = |duplicate_keys| ParserOptions {
+            duplicate_keys,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            parse_with_options(&tokens, with_policy(DuplicateKeys::Error)),
+            Err(ParseError::new(
+                ParseErrorType::DuplicateKey,
+                Some(&tokens[12]),
+                None
+            ))
+        );
+
+        let first = parse_with_options(&tokens, with_policy(DuplicateKeys::FirstWins)).unwrap();
+        assert_eq!(first.pointer("/a"), Some(&Json::Number(1.0.into())));
+        assert_eq!(first.pointer("/b/c"), Some(&Json::Number(2.0.into())));
+
+        let (last, duplicates) =
+            parse_with_duplicates(&tokens, with_policy(DuplicateKeys::LastWins)).unwrap();
+        assert_eq!(last.pointer("/a"), Some(&Json::Number(4.0.into())));
+        assert_eq!(last.pointer("/b/c"), Some(&Json::Number(3.0.into())));
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].occurrences, vec![&tokens[1], &tokens[17]]);
+
+        assert_eq!(
+            DuplicateKeys::from_name("first-wins"),
+            Some(DuplicateKeys::FirstWins)
+        );
+        assert_eq!(DuplicateKeys::from_name("first"), None);
+    }
+
+    #[test]
+    fn test_parse_trailing_content() {
+        let tokens = lexer::lex("{\"a\": 1} garbage [").unwrap();
+        assert_eq!(
+            parse(&tokens),
+            Err(ParseError::new(
+                ParseErrorType::UnexpectedToken,
+                Some(&tokens[5]),
+                None
+            ))
+        );
+
+        let options = ParserOptions {
+            allow_trailing_content: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            parse_with_options(&tokens, options).unwrap().pointer("/a"),
+            Some(&Json::Number(1.0.into()))
+        );
+
+        let tokens = lexer::lex("[1]]").unwrap();
+        assert_eq!(find_errors(&tokens, ParserOptions::default()).len(), 1);
+        assert!(parse_str("[1] \n").is_ok());
+    }
+
+    #[test]
+    fn test_parse_lossy() {
+        let shape = |raw| {
+            let tokens = lexer::lex(raw).unwrap();
+            let (value, errors) = parse_lossy(&tokens, ParserOptions::default());
+            let errors = errors
+                .iter()
+                .map(|error| error.error_type)
+                .collect::<Vec<_>>();
+            (format!("{:?}", value), errors)
+        };
+
+        // A hole or a word that is not a value becomes an `Error`, the items after it are kept
+        assert_eq!(
+            shape("[1, , 3]"),
+            (
+                "Array([Number(PosInt(1)), Error, Number(PosInt(3))])".to_string(),
+                vec![ParseErrorType::UnexpectedToken]
+            )
+        );
+        assert_eq!(
+            shape("[1, tru, 3]").0,
+            "Array([Number(PosInt(1)), Error, Number(PosInt(3))])"
+        );
+        assert_eq!(
+            shape("[1 2 3]"),
+            (
+                "Array([Number(PosInt(1)), Number(PosInt(2)), Number(PosInt(3))])".to_string(),
+                vec![ParseErrorType::MissingCloseSquare; 2]
+            )
+        );
+        assert_eq!(
+            shape("{\"a\": 1 \"b\": [2 x]}").0,
+            r#"Object({"a": Number(PosInt(1)), "b": Array([Number(PosInt(2)), Error])})"#
+        );
+
+        let lossy = |raw| {
+            let tokens = lexer::lex(raw).unwrap();
+            let (value, errors) = parse_lossy(&tokens, ParserOptions::default());
+            (value.into_owned(), errors.len())
+        };
+
+        let (value, errors) = lossy("{\"a\" 1, \"b\": [1 x, 2], c: 3, \"d\": {\"e\": true");
+        assert_eq!(errors, 4);
+        assert_eq!(value.pointer("/a"), Some(&Json::Error));
+        assert_eq!(
+            value.pointer("/b"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0.into()),
+                Json::Error,
+                Json::Number(2.0.into())
+            ]))
+        );
+        assert_eq!(value.pointer("/c"), None);
+        assert_eq!(value.pointer("/d/e"), Some(&Json::Bool(true)));
+
+        let (value, errors) = lossy("[1, {\"a\": ");
+        assert_eq!(errors, 1);
+        assert_eq!(value.pointer("/1/a"), Some(&Json::Error));
+        assert_eq!(formatter::format_json(&value, 0), "[1,{\"a\":null}]");
+
+        assert_eq!(
+            lossy("[1, [2]]"),
+            (parse_str("[1, [2]]").unwrap().into_owned(), 0)
+        );
+        assert_eq!(lossy("}"), (Json::Error, 1));
+        assert_eq!(lossy(""), (Json::Error, 1));
+    }
+
+    #[test]
+    fn test_find_errors() {
+        let errors_of = |raw| {
+            let tokens = lexer::lex(raw).unwrap();
+            find_errors(&tokens, ParserOptions::default())
+                .iter()
+                .map(|parse_error| {
+                    let token = parse_error.token.map(|token| (token.line, token.col));
+                    (parse_error.error_type, token)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(errors_of("{\"a\": [1, 2], \"b\": null}"), vec![]);
+        assert_eq!(
+            errors_of("{\"a\" 1, \"b\": [1 2], c: 3, \"d\": [4,], \"b\": 5}"),
+            vec![
+                (ParseErrorType::MissingColon, Some((0, 5))),
+                (ParseErrorType::MissingCloseSquare, Some((0, 16))),
+                (ParseErrorType::KeyNotInQuotes, Some((0, 20))),
+                (ParseErrorType::TrailingComma, Some((0, 33))),
+                (ParseErrorType::DuplicateKey, Some((0, 37))),
+            ]
+        );
+        assert_eq!(
+            errors_of("[[{\"a\": 1], [2}, 3"),
+            vec![
+                (ParseErrorType::MissingCloseCurly, Some((0, 9))),
+                (ParseErrorType::MissingCloseSquare, Some((0, 14))),
+                (ParseErrorType::UnexpectedEnd, None),
+            ]
+        );
+        assert_eq!(
+            errors_of("[1, /* x */ 2]"),
+            vec![(ParseErrorType::Comment, Some((0, 4)))]
+        );
+        assert_eq!(
+            errors_of("}"),
+            vec![(ParseErrorType::UnexpectedToken, Some((0, 0)))]
+        );
+
+        // One error at the token that cannot be an item, the comma before it is not trailing
+        assert_eq!(
+            errors_of("[1, tru, 3]"),
+            vec![(ParseErrorType::UnexpectedToken, Some((0, 4)))]
+        );
+        assert_eq!(
+            errors_of("[1, , 3]"),
+            vec![(ParseErrorType::UnexpectedToken, Some((0, 4)))]
+        );
+        assert_eq!(
+            errors_of("{,\"a\": 1}"),
+            vec![(ParseErrorType::UnexpectedToken, Some((0, 1)))]
+        );
+        assert_eq!(
+            errors_of("{\"a\": [1, 2, \"b\": 3}"),
+            vec![(ParseErrorType::MissingCloseSquare, Some((0, 16)))]
+        );
+        assert_eq!(
+            errors_of("[1, 2,]"),
+            vec![(ParseErrorType::TrailingComma, Some((0, 5)))]
+        );
+
+        // The second comma is both unexpected and trailing, it is reported once
+        assert_eq!(
+            errors_of("[1,,]"),
+            vec![(ParseErrorType::TrailingComma, Some((0, 3)))]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            validate(r#"{"a": [1, {"b": "\u00e9"}], "c": null}"#),
+            Ok(())
+        );
+
+        let errors = validate("{\"a\": [1,], \"b\": 1, \"\\u0062\": 2}").unwrap_err();
+        let error_types: Vec<_> = errors.iter().map(|error| error.error_type).collect();
+        assert_eq!(
+            error_types,
+            [ParseErrorType::TrailingComma, ParseErrorType::DuplicateKey]
+        );
+        assert_eq!((errors[1].line, errors[1].col), (Some(0), Some(20)));
+
+        let tokens = lexer::lex("[{\"a\": 1, \"a\": 2}]").unwrap();
+        assert_eq!(
+            check(&tokens, ParserOptions::default()).map_err(|error| error.error_type),
+            Err(ParseErrorType::DuplicateKey)
+        );
+        assert_eq!(check(&tokens, ParserOptions::lenient()), Ok(()));
+    }
+
     #[test]
     fn test_parse_located_error() {
         let cases: Vec<(&str, ParseErrorType, usize, Option<&TokenType>)> = vec![
@@ -429,7 +3465,7 @@ mod tests {
         for case in cases {
             let (raw, expected_error, token_location, expected_token_type) = case;
 
-            let tokens = lexer::lex(raw);
+            let tokens = lexer::lex(raw).unwrap();
             let expected = Err(ParseError::new(
                 expected_error,
                 Some(tokens.get(token_location).unwrap()),
@@ -0,0 +1,340 @@
+use compare::{self, EqOptions};
+use diff::Operation;
+use parser::{Json, JsonOwned};
+use pointer;
+use std::fmt;
+
+// Why an operation of a patch could not be applied
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchErrorKind {
+    // Not an object with a known `op` and the members it needs
+    Invalid(String),
+    // The pointer does not lead to a value, or for `add` to a new member or index of one
+    NotFound(String),
+    // A `move` into a child of the value it moves
+    MoveIntoChild,
+    // The value at `path` is not equal to the one the `test` expected
+    TestFailed {
+        path: String,
+        expected: Box<JsonOwned>,
+        actual: Box<JsonOwned>,
+    },
+}
+
+// The failing operation is the one at `index` of the patch, no other operation is applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchError {
+    pub index: usize,
+    pub kind: PatchErrorKind,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Operation {}: ", self.index)?;
+
+        match &self.kind {
+            PatchErrorKind::Invalid(message) => write!(f, "{}", message),
+            PatchErrorKind::NotFound(pointer) => write!(f, "Nothing found at '{}'", pointer),
+            PatchErrorKind::MoveIntoChild => write!(f, "Cannot move a value into itself"),
+            PatchErrorKind::TestFailed {
+                path,
+                expected,
+                actual,
+            } => write!(f, "Expected {} at '{}', found {}", expected, path, actual),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+fn member<'j, 'a>(obj: &'j Json<'a>, key: &str) -> Result<&'j Json<'a>, PatchErrorKind> {
+    obj.get(key)
+        .ok_or_else(|| PatchErrorKind::Invalid(format!("Missing \"{}\"", key)))
+}
+
+fn pointer_member(obj: &Json, key: &str) -> Result<String, PatchErrorKind> {
+    match member(obj, key)?.as_str() {
+        Some(pointer) if pointer::split(pointer).is_some() => Ok(pointer.to_string()),
+        _ => Err(PatchErrorKind::Invalid(format!(
+            "\"{}\" is not a JSON pointer",
+            key
+        ))),
+    }
+}
+
+fn operation(json: &Json) -> Result<Operation<'static>, PatchErrorKind> {
+    if json.as_object().is_none() {
+        return Err(PatchErrorKind::Invalid(format!(
+            "Expected an object, found {}",
+            json.type_name()
+        )));
+    }
+
+    let path = pointer_member(json, "path")?;
+    let value = || member(json, "value").map(|value| value.clone().into_owned());
+
+    Ok(match member(json, "op")?.as_str() {
+        Some("add") => Operation::Add {
+            path,
+            value: value()?,
+        },
+        Some("remove") => Operation::Remove { path },
+        Some("replace") => Operation::Replace {
+            path,
+            value: value()?,
+        },
+        Some("move") => Operation::Move {
+            from: pointer_member(json, "from")?,
+            path,
+        },
+        Some("copy") => Operation::Copy {
+            from: pointer_member(json, "from")?,
+            path,
+        },
+        Some("test") => Operation::Test {
+            path,
+            value: value()?,
+        },
+        _ => return Err(PatchErrorKind::Invalid("Unknown \"op\"".to_string())),
+    })
+}
+
+// The operations of a JSON Patch document, an array of objects like
+// `{"op": "add", "path": "/a", "value": 1}`
+pub fn parse(patch: &Json) -> Result<Vec<Operation<'static>>, PatchError> {
+    let items = patch.as_array().ok_or_else(|| PatchError {
+        index: 0,
+        kind: PatchErrorKind::Invalid("A patch is an array of operations".to_string()),
+    })?;
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| operation(item).map_err(|kind| PatchError { index, kind }))
+        .collect()
+}
+
+fn not_found(pointer: &str) -> PatchErrorKind {
+    PatchErrorKind::NotFound(pointer.to_string())
+}
+
+// Unlike `pointer::set`, an index of an array inserts before the item that is there
+fn add<'a>(value: &mut Json<'a>, path: &str, new: Json<'a>) -> Result<(), PatchErrorKind> {
+    let (parent, last) = match path.rfind('/') {
+        Some(i) => (
+            &path[..i],
+            pointer::split(&path[i..]).ok_or_else(|| not_found(path))?,
+        ),
+        None => {
+            *value = new;
+            return Ok(());
+        }
+    };
+
+    match pointer::get_mut(value, parent) {
+        Some(Json::Object(obj)) => {
+            let key = pointer::find_key(obj, &last[0]);
+            obj.insert(key, new);
+        }
+        Some(Json::Array(arr)) => {
+            let i = match last[0].as_str() {
+                "-" => arr.len(),
+                token => pointer::index(token)
+                    .filter(|i| *i <= arr.len())
+                    .ok_or_else(|| not_found(path))?,
+            };
+            arr.insert(i, new);
+        }
+        _ => return Err(not_found(path)),
+    }
+
+    Ok(())
+}
+
+fn apply_operation<'a>(
+    value: &mut Json<'a>,
+    operation: &Operation<'a>,
+) -> Result<(), PatchErrorKind> {
+    match operation {
+        Operation::Add { path, value: new } => add(value, path, new.clone()),
+        Operation::Remove { path } => pointer::remove(value, path)
+            .map(drop)
+            .ok_or_else(|| not_found(path)),
+        Operation::Replace { path, value: new } => {
+            *pointer::get_mut(value, path).ok_or_else(|| not_found(path))? = new.clone();
+            Ok(())
+        }
+        Operation::Move { from, path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(PatchErrorKind::MoveIntoChild);
+            }
+
+            if pointer::get(value, from).is_none() {
+                return Err(not_found(from));
+            }
+
+            if from == path {
+                return Ok(());
+            }
+
+            let moved = pointer::remove(value, from).ok_or_else(|| not_found(from))?;
+            add(value, path, moved)
+        }
+        Operation::Copy { from, path } => {
+            let copied = pointer::get(value, from).ok_or_else(|| not_found(from))?;
+            add(value, path, copied.clone())
+        }
+        Operation::Test {
+            path,
+            value: expected,
+        } => {
+            let actual = pointer::get(value, path).ok_or_else(|| not_found(path))?;
+
+            if compare::semantic_eq(actual, expected, EqOptions::default()) {
+                Ok(())
+            } else {
+                Err(PatchErrorKind::TestFailed {
+                    path: path.to_string(),
+                    expected: Box::new(expected.clone().into_owned()),
+                    actual: Box::new(actual.clone().into_owned()),
+                })
+            }
+        }
+    }
+}
+
+// Applies the operations in order, `value` is left as it was when one of them fails
+pub fn apply<'a>(value: &mut Json<'a>, operations: &[Operation<'a>]) -> Result<(), PatchError> {
+    let mut patched = value.clone();
+
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut patched, operation).map_err(|kind| PatchError { index, kind })?;
+    }
+
+    *value = patched;
+    Ok(())
+}
+
+// Applies a JSON Patch document, see `parse`
+pub fn apply_patch(value: &mut Json, patch: &Json) -> Result<(), PatchError> {
+    apply(value, &parse(patch)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diff::{self, DiffOptions};
+    use parser;
+
+    #[test]
+    fn test_apply_patch() {
+        let mut value = parser::parse_str(r#"{"a": {"b": [1, 2]}, "c": "x"}"#).unwrap();
+        let patch = parser::parse_str(
+            r#"[
+                {"op": "test", "path": "/c", "value": "x"},
+                {"op": "add", "path": "/a/b/1", "value": 5},
+                {"op": "add", "path": "/a/b/-", "value": 3},
+                {"op": "remove", "path": "/a/b/0"},
+                {"op": "replace", "path": "/c", "value": {"d": null}},
+                {"op": "copy", "from": "/a/b", "path": "/e"},
+                {"op": "move", "from": "/c", "path": "/a/f~1g"},
+                {"op": "test", "path": "/e", "value": [5, 2.0, 3]}
+            ]"#,
+        )
+        .unwrap();
+
+        value.apply_patch(&patch).unwrap();
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"a": {"b": [5, 2, 3], "f/g": {"d": null}}, "e": [5, 2, 3]}"#)
+                .unwrap()
+        );
+
+        let mut root = Json::from(1);
+        let patch = parser::parse_str(r#"[{"op": "replace", "path": "", "value": [true]}]"#);
+        root.apply_patch(&patch.unwrap()).unwrap();
+        assert_eq!(root, Json::from(vec![true]));
+    }
+
+    #[test]
+    fn test_apply_patch_errors() {
+        let original = parser::parse_str(r#"{"a": [1], "b": 1}"#).unwrap();
+        let error = |patch: &str| {
+            let mut value = original.clone();
+            let error = value
+                .apply_patch(&parser::parse_str(patch).unwrap())
+                .unwrap_err();
+            assert_eq!(value, original);
+            error
+        };
+
+        let failed = error(
+            r#"[{"op": "remove", "path": "/b"}, {"op": "test", "path": "/a/0", "value": 2}]"#,
+        );
+        assert_eq!(
+            failed,
+            PatchError {
+                index: 1,
+                kind: PatchErrorKind::TestFailed {
+                    path: "/a/0".to_string(),
+                    expected: Box::new(Json::from(2)),
+                    actual: Box::new(Json::from(1)),
+                },
+            }
+        );
+        assert_eq!(
+            failed.to_string(),
+            "Operation 1: Expected 2 at '/a/0', found 1"
+        );
+
+        assert_eq!(
+            error(r#"[{"op": "add", "path": "/a/2", "value": 1}]"#).kind,
+            PatchErrorKind::NotFound("/a/2".to_string())
+        );
+        assert_eq!(
+            error(r#"[{"op": "replace", "path": "/c", "value": 1}]"#).kind,
+            PatchErrorKind::NotFound("/c".to_string())
+        );
+        assert_eq!(
+            error(r#"[{"op": "move", "from": "/a", "path": "/a/0"}]"#).kind,
+            PatchErrorKind::MoveIntoChild
+        );
+        assert_eq!(
+            error(r#"[{"op": "add", "path": "/c"}]"#).kind,
+            PatchErrorKind::Invalid("Missing \"value\"".to_string())
+        );
+        assert_eq!(
+            error(r#"[{"op": "remove", "path": "a"}]"#).kind,
+            PatchErrorKind::Invalid("\"path\" is not a JSON pointer".to_string())
+        );
+        assert_eq!(
+            error(r#"[{"op": "undo", "path": ""}]"#).kind,
+            PatchErrorKind::Invalid("Unknown \"op\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_diff() {
+        let from = parser::parse_str(r#"{"a": [1, 2, 3, 4], "b": {"c": 1}, "d": 1}"#).unwrap();
+        let to =
+            parser::parse_str(r#"{"a": [0, 2, 4, 5, 6], "e": {"c": 1}, "f": 1, "d": 1}"#).unwrap();
+
+        for detect_moves in [false, true] {
+            let operations = diff::diff(
+                &from,
+                &to,
+                &DiffOptions {
+                    detect_moves,
+                    ..DiffOptions::default()
+                },
+            );
+            let mut value = from.clone();
+            apply(&mut value, &operations).unwrap();
+            assert_eq!(value, to);
+
+            let mut value = from.clone();
+            value.apply_patch(&diff::to_patch(&operations)).unwrap();
+            assert_eq!(value, to);
+        }
+    }
+}
@@ -0,0 +1,512 @@
+use escape::unescape;
+use lexer::{Token, TokenType};
+use parser::{Json, Map};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::mem;
+
+// Appends a key or index to a JSON pointer, escaping '~' and '/' as per RFC 6901
+pub fn push(pointer: &str, token: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        token.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+// A `~` has to be followed by `0` or `1`
+fn unescape_token(token: &str) -> Option<String> {
+    let mut result = String::with_capacity(token.len());
+    let mut chars = token.chars();
+
+    while let Some(c) = chars.next() {
+        result.push(match c {
+            '~' => match chars.next()? {
+                '0' => '~',
+                '1' => '/',
+                _ => return None,
+            },
+            c => c,
+        });
+    }
+
+    Some(result)
+}
+
+// Splits a JSON pointer into its unescaped reference tokens, `None` if it is not a valid pointer
+pub fn split(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(vec![]);
+    }
+
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(unescape_token)
+        .collect()
+}
+
+// Array indexes are plain decimal numbers without leading zeros
+pub(crate) fn index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    token.parse().ok()
+}
+
+pub fn get<'j, 'a>(value: &'j Json<'a>, pointer: &str) -> Option<&'j Json<'a>> {
+    let mut current = value;
+
+    for token in split(pointer)? {
+        current = match current.contents() {
+            Json::Object(obj) => obj.get(token.as_str())?,
+            Json::Array(arr) => arr.get(index(&token)?)?,
+            _ => return None,
+        };
+    }
+
+    Some(current)
+}
+
+// The key of `obj` that `token` refers to, keeping a borrowed one, or a copy when it is a new one
+pub(crate) fn find_key<'a>(obj: &Map<Cow<'a, str>, Json<'a>>, token: &str) -> Cow<'a, str> {
+    match obj.get_key_value(token) {
+        Some((key, _)) => key.clone(),
+        None => Cow::Owned(token.to_owned()),
+    }
+}
+
+// With `create`, a missing key of an object is added with an empty object
+fn child_mut<'j, 'a>(
+    value: &'j mut Json<'a>,
+    token: &str,
+    create: bool,
+) -> Option<&'j mut Json<'a>> {
+    match value {
+        Json::Object(obj) => {
+            let key = find_key(obj, token);
+
+            if !create && !obj.contains_key(&key) {
+                return None;
+            }
+
+            Some(obj.entry(key).or_insert_with(|| Json::Object(Map::new())))
+        }
+        Json::Array(arr) => arr.get_mut(index(token)?),
+        _ => None,
+    }
+}
+
+fn parent_mut<'j, 'a>(
+    value: &'j mut Json<'a>,
+    tokens: &[String],
+    create: bool,
+) -> Option<&'j mut Json<'a>> {
+    let mut current = value;
+
+    for token in tokens {
+        current = child_mut(current, token, create)?;
+    }
+
+    Some(current)
+}
+
+pub fn get_mut<'j, 'a>(value: &'j mut Json<'a>, pointer: &str) -> Option<&'j mut Json<'a>> {
+    parent_mut(value, &split(pointer)?, false)
+}
+
+// Why `set` could not put a value in place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetError {
+    InvalidPointer,
+    // A parent is missing or is not an array or object, or an array index is past the end
+    NotFound,
+}
+
+// Puts `new` at the pointer and returns the value it replaced. The last token may be a new key
+// of an object, or `-` or the length of an array to append. With `create`, missing parents are
+// added as empty objects, like `mkdir -p`
+pub fn set<'a>(
+    value: &mut Json<'a>,
+    pointer: &str,
+    new: Json<'a>,
+    create: bool,
+) -> Result<Option<Json<'a>>, SetError> {
+    let mut tokens = split(pointer).ok_or(SetError::InvalidPointer)?;
+
+    let last = match tokens.pop() {
+        Some(last) => last,
+        None => return Ok(Some(mem::replace(value, new))),
+    };
+
+    match parent_mut(value, &tokens, create).ok_or(SetError::NotFound)? {
+        Json::Object(obj) => {
+            let key = find_key(obj, &last);
+            Ok(obj.insert(key, new))
+        }
+        Json::Array(arr) => {
+            let i = match last.as_str() {
+                "-" => arr.len(),
+                _ => index(&last).ok_or(SetError::NotFound)?,
+            };
+
+            match i.cmp(&arr.len()) {
+                Ordering::Less => Ok(Some(mem::replace(&mut arr[i], new))),
+                Ordering::Equal => {
+                    arr.push(new);
+                    Ok(None)
+                }
+                Ordering::Greater => Err(SetError::NotFound),
+            }
+        }
+        _ => Err(SetError::NotFound),
+    }
+}
+
+// Removes the value at the pointer, the root can not be removed
+pub fn remove<'a>(value: &mut Json<'a>, pointer: &str) -> Option<Json<'a>> {
+    let mut tokens = split(pointer)?;
+    let last = tokens.pop()?;
+    let current = parent_mut(value, &tokens, false)?;
+
+    match current {
+        Json::Object(obj) => {
+            #[cfg(feature = "preserve_order")]
+            return obj.shift_remove(last.as_str());
+            #[cfg(not(feature = "preserve_order"))]
+            return obj.remove(last.as_str());
+        }
+        Json::Array(arr) => {
+            let index = index(&last)?;
+
+            if index < arr.len() {
+                Some(arr.remove(index))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn remove_matching(value: &mut Json, tokens: &[String]) -> usize {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return 0,
+    };
+    let wildcard = token == "*";
+
+    if !rest.is_empty() {
+        return match value {
+            Json::Object(obj) => obj
+                .iter_mut()
+                .filter(|(key, _)| wildcard || *key == token)
+                .map(|(_, item)| remove_matching(item, rest))
+                .sum(),
+            Json::Array(arr) => arr
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| wildcard || index(token) == Some(*i))
+                .map(|(_, item)| remove_matching(item, rest))
+                .sum(),
+            _ => 0,
+        };
+    }
+
+    match value {
+        Json::Object(obj) => {
+            let before = obj.len();
+            obj.retain(|key, _| !wildcard && key != token);
+            before - obj.len()
+        }
+        Json::Array(arr) if wildcard => arr.drain(..).count(),
+        Json::Array(arr) => match index(token).filter(|i| *i < arr.len()) {
+            Some(i) => {
+                arr.remove(i);
+                1
+            }
+            None => 0,
+        },
+        _ => 0,
+    }
+}
+
+// Removes every value at a pointer where a `*` token matches any key or index, like
+// `/items/*/updatedAt`, returning how many were removed
+pub fn remove_all(value: &mut Json, pattern: &str) -> usize {
+    match split(pattern) {
+        Some(tokens) => remove_matching(value, &tokens),
+        None => 0,
+    }
+}
+
+// Whether the pointer is one the pattern stands for, a `*` token matching any key or index
+pub fn matches(pattern: &str, pointer: &str) -> bool {
+    match (split(pattern), split(pointer)) {
+        (Some(pattern), Some(pointer)) => {
+            pattern.len() == pointer.len()
+                && pattern
+                    .iter()
+                    .zip(&pointer)
+                    .all(|(p, t)| p == "*" || p == t)
+        }
+        _ => false,
+    }
+}
+
+// Where a pointer leads in the token stream of a valid document
+#[derive(Debug, PartialEq)]
+pub struct Location<'a> {
+    // The key token, or first token of the array item, for each step of the pointer
+    pub steps: Vec<(String, &'a Token<'a>)>,
+    // First token of the value
+    pub token: &'a Token<'a>,
+}
+
+// Index of the token after the value starting at `i`
+fn skip_value(tokens: &[Token], i: usize) -> usize {
+    let mut depth: usize = 0;
+    let mut i = i;
+
+    while let Some(token) = tokens.get(i) {
+        match token.token_type {
+            TokenType::OpenCurly | TokenType::OpenSquare => depth += 1,
+            TokenType::CloseCurly | TokenType::CloseSquare => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        i += 1;
+
+        if depth == 0 {
+            break;
+        }
+    }
+
+    i
+}
+
+// Index of the token after the comma following the value at `i`, `None` at the end of the container
+fn next_item(tokens: &[Token], i: usize) -> Option<usize> {
+    let next = skip_value(tokens, i);
+
+    match tokens.get(next)?.token_type {
+        TokenType::Comma => Some(next + 1),
+        _ => None,
+    }
+}
+
+fn child(tokens: &[Token], i: usize, token: &str) -> Option<(usize, usize)> {
+    match tokens.get(i)?.token_type {
+        TokenType::OpenCurly => {
+            let mut j = i + 1;
+
+            loop {
+                match tokens.get(j)?.token_type {
+                    TokenType::String(key) if unescape(key) == token => return Some((j, j + 2)),
+                    TokenType::String(_) => j = next_item(tokens, j + 2)?,
+                    _ => return None,
+                }
+            }
+        }
+        TokenType::OpenSquare => {
+            let mut j = i + 1;
+
+            for _ in 0..index(token)? {
+                j = next_item(tokens, j)?;
+            }
+
+            match tokens.get(j)?.token_type {
+                TokenType::CloseSquare => None,
+                _ => Some((j, j)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Expects tokens that parse successfully
+pub fn locate<'a>(tokens: &'a [Token<'a>], pointer: &str) -> Option<Location<'a>> {
+    let mut steps = vec![];
+    let mut current = String::new();
+    let mut i = 0;
+
+    for token in split(pointer)? {
+        let (step, value) = child(tokens, i, &token)?;
+
+        current = push(&current, &token);
+        steps.push((current.clone(), &tokens[step]));
+        i = value;
+    }
+
+    Some(Location {
+        steps,
+        token: tokens.get(i)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formatter;
+    use lexer;
+    use parser;
+
+    const RAW: &str = "{\n  \"a/b\": [1, {\"c\": true}],\n  \"d\": {\"e\\u0021\": null}\n}";
+
+    #[test]
+    fn test_split() {
+        let cases = vec![
+            ("", Some(vec![])),
+            ("/", Some(vec![""])),
+            ("/a~1b/0", Some(vec!["a/b", "0"])),
+            ("/~01", Some(vec!["~1"])),
+            ("/m~0n//", Some(vec!["m~n", "", ""])),
+            ("a", None),
+            ("/a~2", None),
+            ("/a~", None),
+        ];
+
+        for (pointer, expected) in cases {
+            let expected = expected.map(|v| v.iter().map(|s| s.to_string()).collect());
+            assert_eq!(split(pointer), expected, "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let tokens = lexer::lex(RAW).unwrap();
+        let value = parser::parse(&tokens).unwrap();
+
+        assert_eq!(get(&value, ""), Some(&value));
+        assert_eq!(get(&value, "/a~1b/1/c"), Some(&Json::Bool(true)));
+        assert_eq!(get(&value, "/d/e!"), Some(&Json::Null));
+
+        for pointer in ["/a~1b/2", "/a~1b/01", "/a~1b/-", "/d/e!/f", "/x", "/a~2b"] {
+            assert_eq!(get(&value, pointer), None, "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let tokens = lexer::lex(RAW).unwrap();
+        let mut value = parser::parse(&tokens).unwrap();
+
+        assert_eq!(
+            remove(&mut value, "/a~1b/0"),
+            Some(Json::Number(1.0.into()))
+        );
+        assert_eq!(remove(&mut value, "/d/e!"), Some(Json::Null));
+        assert_eq!(remove(&mut value, "/d/e!"), None);
+        assert_eq!(remove(&mut value, ""), None);
+
+        let expected = lexer::lex("{\"a/b\": [{\"c\": true}], \"d\": {}}").unwrap();
+        assert_eq!(value, parser::parse(&expected).unwrap());
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let tokens = lexer::lex(
+            "{\"items\": [{\"id\": 1, \"at\": 2}, {\"at\": 3}, 4], \"at\": 5, \"x\": {\"y\": [1]}}",
+        )
+        .unwrap();
+        let mut value = parser::parse(&tokens).unwrap();
+
+        assert_eq!(remove_all(&mut value, "/items/*/at"), 2);
+        assert_eq!(remove_all(&mut value, "/x/*/*"), 1);
+        assert_eq!(remove_all(&mut value, "/nothing/*"), 0);
+        assert_eq!(remove_all(&mut value, ""), 0);
+
+        let expected =
+            lexer::lex("{\"items\": [{\"id\": 1}, {}, 4], \"at\": 5, \"x\": {\"y\": []}}").unwrap();
+        assert_eq!(value, parser::parse(&expected).unwrap());
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("/items/*/at", "/items/0/at"));
+        assert!(matches("/a~1b/*", "/a~1b/c"));
+        assert!(matches("", ""));
+        assert!(!matches("/items/*/at", "/items/0/at/x"));
+        assert!(!matches("/items/*", "/other/0"));
+        assert!(!matches("items", "items"));
+    }
+
+    #[test]
+    fn test_locate() {
+        let tokens = lexer::lex(RAW).unwrap();
+
+        let location = locate(&tokens, "/a~1b/1/c").unwrap();
+        let steps: Vec<_> = location
+            .steps
+            .iter()
+            .map(|(pointer, token)| (pointer.as_str(), token.line, token.col))
+            .collect();
+
+        assert_eq!(
+            steps,
+            [("/a~1b", 1, 2), ("/a~1b/1", 1, 13), ("/a~1b/1/c", 1, 14)]
+        );
+        assert_eq!((location.token.line, location.token.col), (1, 19));
+
+        assert_eq!(locate(&tokens, "/d/e!").unwrap().token.line, 2);
+        assert_eq!(locate(&tokens, "").unwrap().token, &tokens[0]);
+
+        for pointer in ["/a~1b/2", "/d/x", "/d/e!/f", "a"] {
+            assert_eq!(locate(&tokens, pointer), None, "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn test_set() {
+        let tokens = lexer::lex(RAW).unwrap();
+        let mut value = parser::parse(&tokens).unwrap();
+
+        assert_eq!(
+            value.set_at("/a~1b/0", Json::Bool(false), false),
+            Ok(Some(Json::Number(1.0.into())))
+        );
+        assert_eq!(value.set_at("/a~1b/-", Json::Null, false), Ok(None));
+        assert_eq!(value.set_at("/a~1b/3", Json::Null, false), Ok(None));
+        assert_eq!(
+            value.set_at("/a~1b/9", Json::Null, false),
+            Err(SetError::NotFound)
+        );
+        assert_eq!(
+            value.set_at("/d/e!", Json::Bool(true), false),
+            Ok(Some(Json::Null))
+        );
+        assert_eq!(
+            value.set_at("/x/y~1\"z", Json::Null, false),
+            Err(SetError::NotFound)
+        );
+        assert_eq!(value.set_at("/x/y~1\"z", Json::Null, true), Ok(None));
+        assert_eq!(
+            value.set_at("/x~", Json::Null, true),
+            Err(SetError::InvalidPointer)
+        );
+
+        *value.pointer_mut("/a~1b/1/c").unwrap() = Json::Number(2.0.into());
+        assert_eq!(value.pointer_mut("/d/f"), None);
+        assert_eq!(value.remove_at("/a~1b/3"), Some(Json::Null));
+
+        let expected = lexer::lex(
+            "{\"a/b\": [false, {\"c\": 2}, null], \"d\": {\"e\\u0021\": true}, \"x\": {\"y/\\\"z\": null}}",
+        ).unwrap();
+        assert_eq!(value, parser::parse(&expected).unwrap());
+        assert_eq!(
+            formatter::format_json(value.pointer("/x").unwrap(), 0),
+            "{\"y/\\\"z\":null}"
+        );
+
+        assert_eq!(
+            value.set_at("", Json::Null, false).map(|old| old.is_some()),
+            Ok(true)
+        );
+        assert_eq!(value, Json::Null);
+    }
+}
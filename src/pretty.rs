@@ -1,23 +1,22 @@
-use crate::lexer;
-
 fn get_line(content: &str, line_number: usize) -> Option<&str> {
     for (line_no, line) in content.split_terminator('\n').enumerate() {
         if line_no == line_number {
-            return Some(line);
+            // Without the `\r` of a `\r\n` line ending
+            return Some(line.strip_suffix('\r').unwrap_or(line));
         }
     }
 
     None
 }
 
-pub fn print_location(content: &str, token: &lexer::Token) {
-    let line = match get_line(content, token.line) {
-        None => return,
+// The line with a caret under the column, empty if the line does not exist
+pub fn location(content: &str, line_number: usize, col: usize) -> String {
+    let line = match get_line(content, line_number) {
+        None => return String::new(),
         Some(line) => line,
     };
 
-    let hint_carrot = " ".repeat(token.col) + "^";
+    let hint_carrot = " ".repeat(col) + "^";
 
-    println!("{}", line);
-    println!("{}", hint_carrot);
+    format!("{}\n{}\n", line, hint_carrot)
 }
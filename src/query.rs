@@ -0,0 +1,547 @@
+use assertion::Step;
+use joxide::encoding::{self, Encoding};
+use joxide::escape::unescape;
+use joxide::lexer::{self, Lexer, Token, TokenType};
+use joxide::parser::{DuplicateKeys, ParserOptions};
+use joxide::Number;
+use std::collections::HashSet;
+use std::io::Read;
+
+// Evaluates a path like `$.records[*].id` over the input as it is read, without building the
+// document. Paths never need to look back, so a single pass is enough: the input is read in
+// chunks, lexed and walked with an explicit stack, and only the text of matching values is kept
+// along with the keys of the objects open at the time, to find duplicates. Memory does not grow
+// with the size of the input or of the values that are skipped
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// What the walk expects next
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    // The first item of an array, or its end
+    ValueOrClose,
+    // The first key of an object, or its end
+    KeyOrClose,
+    Key,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+struct Frame<'p> {
+    object: bool,
+    index: usize,
+    // Steps left at this container, `None` when nothing inside it can match
+    steps: Option<&'p [Step]>,
+    keys: HashSet<String>,
+}
+
+// Steps left for an item of a container with `steps` left, by key for objects or by index
+fn child<'p>(steps: Option<&'p [Step]>, key: Option<&str>, index: usize) -> Option<&'p [Step]> {
+    match (steps?, key) {
+        ([Step::Key(k), rest @ ..], Some(key)) if k == key => Some(rest),
+        ([Step::Index(i), rest @ ..], None) if *i == index => Some(rest),
+        ([Step::Wildcard, rest @ ..], _) => Some(rest),
+        _ => None,
+    }
+}
+
+struct Walker<'p, 'o> {
+    stack: Vec<Frame<'p>>,
+    expect: Expect,
+    // Steps left for the next value
+    steps: Option<&'p [Step]>,
+    options: &'p ParserOptions<'o>,
+    // The text of the value being matched with the depth it started at, and where the rest of
+    // its text starts in the chunk being walked
+    matched: Option<(usize, String)>,
+    from: usize,
+}
+
+impl<'p, 'o> Walker<'p, 'o> {
+    fn new(steps: &'p [Step], options: &'p ParserOptions<'o>) -> Self {
+        Walker {
+            stack: vec![],
+            expect: Expect::Value,
+            steps: Some(steps),
+            options,
+            matched: None,
+            from: 0,
+        }
+    }
+
+    // False once the tokens are not a valid document
+    fn token<F: FnMut(String)>(&mut self, token: &Token, chunk: &str, on_match: &mut F) -> bool {
+        let token_type = &token.token_type;
+
+        // Comments inside a match are kept in its text, which is parsed with the same options
+        if let TokenType::Comment(_) = token_type {
+            return self.options.allow_comments;
+        }
+
+        match (self.expect, token_type) {
+            (Expect::ValueOrClose, TokenType::CloseSquare) => {
+                self.close(false, token, chunk, on_match)
+            }
+            (Expect::Value | Expect::ValueOrClose, _) => {
+                if self.steps == Some(&[]) && self.matched.is_none() {
+                    self.matched = Some((self.stack.len(), String::new()));
+                    self.from = token.start;
+                }
+
+                match token_type {
+                    TokenType::OpenCurly | TokenType::OpenSquare => {
+                        if self.stack.len() >= self.options.max_depth {
+                            return false;
+                        }
+
+                        // Nothing inside a value that matches matches again
+                        let steps = self.steps.filter(|steps| !steps.is_empty());
+                        let object = *token_type == TokenType::OpenCurly;
+                        self.stack.push(Frame {
+                            object,
+                            index: 0,
+                            steps,
+                            keys: HashSet::new(),
+                        });
+
+                        if object {
+                            self.expect = Expect::KeyOrClose;
+                        } else {
+                            self.steps = child(steps, None, 0);
+                            self.expect = Expect::ValueOrClose;
+                        }
+
+                        true
+                    }
+                    TokenType::Null
+                    | TokenType::Bool(_)
+                    | TokenType::Number(..)
+                    | TokenType::String(_) => {
+                        self.end_value(token, chunk, on_match);
+                        true
+                    }
+                    TokenType::Invalid(text)
+                        if self.options.allow_non_finite
+                            && Number::parse_non_finite(text).is_some() =>
+                    {
+                        self.end_value(token, chunk, on_match);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            (Expect::KeyOrClose, TokenType::CloseCurly) => self.close(true, token, chunk, on_match),
+            (Expect::KeyOrClose | Expect::Key, TokenType::String(key)) => self.key(&unescape(key)),
+            (Expect::KeyOrClose | Expect::Key, TokenType::Invalid(key))
+                if self.options.allow_unquoted_keys
+                    && key
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '$') =>
+            {
+                self.key(key)
+            }
+            (Expect::Colon, TokenType::Colon) => {
+                self.expect = Expect::Value;
+                true
+            }
+            (Expect::CommaOrClose, TokenType::Comma) => {
+                let frame = self.stack.last_mut().unwrap();
+                let trailing = self.options.allow_trailing_commas;
+
+                if frame.object {
+                    self.expect = if trailing {
+                        Expect::KeyOrClose
+                    } else {
+                        Expect::Key
+                    };
+                } else {
+                    frame.index += 1;
+                    self.steps = child(frame.steps, None, frame.index);
+                    self.expect = if trailing {
+                        Expect::ValueOrClose
+                    } else {
+                        Expect::Value
+                    };
+                }
+
+                true
+            }
+            (Expect::CommaOrClose, TokenType::CloseCurly) => {
+                self.close(true, token, chunk, on_match)
+            }
+            (Expect::CommaOrClose, TokenType::CloseSquare) => {
+                self.close(false, token, chunk, on_match)
+            }
+            _ => false,
+        }
+    }
+
+    // False for a key already in the object when duplicates are an error. Only the first value of
+    // a key can match otherwise
+    fn key(&mut self, key: &str) -> bool {
+        let frame = self.stack.last_mut().unwrap();
+
+        self.steps = if frame.keys.insert(key.to_string()) {
+            child(frame.steps, Some(key), 0)
+        } else if self.options.duplicate_keys == DuplicateKeys::Error {
+            return false;
+        } else {
+            None
+        };
+
+        self.expect = Expect::Colon;
+        true
+    }
+
+    fn close<F: FnMut(String)>(
+        &mut self,
+        object: bool,
+        token: &Token,
+        chunk: &str,
+        on_match: &mut F,
+    ) -> bool {
+        match self.stack.pop() {
+            Some(frame) if frame.object == object => {
+                self.end_value(token, chunk, on_match);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // After the last token of a value, hands out the match it completes
+    fn end_value<F: FnMut(String)>(&mut self, token: &Token, chunk: &str, on_match: &mut F) {
+        if self
+            .matched
+            .as_ref()
+            .is_some_and(|(depth, _)| *depth == self.stack.len())
+        {
+            let (_, mut text) = self.matched.take().unwrap();
+            text.push_str(&chunk[self.from..token.end]);
+            on_match(text);
+        }
+
+        self.expect = if self.stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrClose
+        };
+    }
+
+    // Keeps the text of the value being matched up to `end`, before the chunk is dropped up to it
+    fn flush(&mut self, chunk: &str, end: usize) {
+        if let Some((_, ref mut text)) = self.matched {
+            text.push_str(&chunk[self.from..end]);
+            self.from = 0;
+        }
+    }
+}
+
+// Decodes what was read, `read` is how many bytes were decoded before it for error offsets
+fn decode(encoding: Encoding, bytes: &[u8], lossy: bool, read: usize) -> Result<String, String> {
+    if lossy {
+        return Ok(encoding.decode_lossy(bytes));
+    }
+
+    match encoding {
+        Encoding::Utf8 => encoding::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|mut invalid| {
+                invalid.offset += read;
+                invalid.to_string()
+            }),
+        _ => encoding.decode(bytes).map_err(|mut err| {
+            err.offset += read;
+            err.to_string()
+        }),
+    }
+}
+
+// Calls `on_match` with the text of each value at the path in document order. `Ok(false)` if the
+// input is not a valid document with `options`, `Err` if it can not be read or decoded. Which
+// value of a repeated key `DuplicateKeys::LastWins` keeps is only known once its object ends, so
+// the walk keeps the first one like `FirstWins` and those documents are better parsed whole
+pub fn query<R: Read, F: FnMut(String)>(
+    mut reader: R,
+    encoding: Option<Encoding>,
+    lossy: bool,
+    steps: &[Step],
+    options: &ParserOptions,
+    mut on_match: F,
+) -> Result<bool, String> {
+    let mut walker = Walker::new(steps, options);
+    let mut encoding = encoding;
+    // Bytes read but not decoded yet, and decoded text from the last token that may go on
+    let mut bytes = vec![];
+    let mut text = String::new();
+    let mut read = 0;
+    let mut started = false;
+
+    loop {
+        let eof = (&mut reader)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|err| err.to_string())?
+            == 0;
+
+        let encoding = *encoding.get_or_insert_with(|| Encoding::detect(&bytes));
+        let complete = if eof {
+            bytes.len()
+        } else {
+            encoding.complete_len(&bytes)
+        };
+
+        text += &decode(encoding, &bytes[..complete], lossy, read)?;
+        bytes.drain(..complete);
+        read += complete;
+
+        // The lexer skips a byte order mark at the start of what it is given, which is only
+        // allowed at the start of the input
+        if started && text.starts_with(lexer::BOM) {
+            return Ok(false);
+        }
+
+        // Every token before the last is whole, the last one may go on in the next chunk. A cut
+        // quote can make more than one token at the same place, all of them wait
+        let tokens: Vec<_> = Lexer::new(&text).collect();
+        let end = match tokens.last() {
+            Some(last) if !eof => last.start,
+            _ => text.len(),
+        };
+
+        for token in tokens.iter().take_while(|token| token.start < end) {
+            // What follows the document is not even read when it is allowed
+            if walker.expect == Expect::End && options.allow_trailing_content {
+                return Ok(true);
+            }
+
+            if lexer::check(token).is_err() || !walker.token(token, &text, &mut on_match) {
+                return Ok(false);
+            }
+        }
+
+        walker.flush(&text, end);
+        text.drain(..end);
+        started |= end > 0;
+
+        if eof {
+            return Ok(walker.expect == Expect::End);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assertion;
+    use joxide::{formatter, parser};
+
+    fn run_with(expression: &str, raw: &[u8], options: &ParserOptions) -> (Vec<String>, bool) {
+        let steps = assertion::parse_path(expression).unwrap();
+        let mut found = vec![];
+
+        let valid = query(raw, None, false, &steps, options, |matched| {
+            let options = ParserOptions {
+                observer: None,
+                cancellation: None,
+                ..*options
+            };
+            let value = parser::parse_str_with_options(&matched, options).unwrap();
+            found.push(formatter::format_json(&value, 0));
+        })
+        .unwrap();
+
+        (found, valid)
+    }
+
+    fn run(expression: &str, raw: &str) -> (Vec<String>, bool) {
+        run_with(expression, raw.as_bytes(), &ParserOptions::default())
+    }
+
+    fn depth(max_depth: usize) -> ParserOptions<'static> {
+        ParserOptions {
+            max_depth,
+            ..ParserOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        let raw = r#"{
+            "records": [{"id": 1, "tags": ["a"]}, {"name": "x"}, {"id": {"n": 2}}],
+            "meta": {"id": 3}
+        }"#;
+
+        assert_eq!(
+            run("$.records[*].id", raw),
+            (vec!["1".to_string(), "{\"n\":2}".to_string()], true)
+        );
+        assert_eq!(
+            run("$.records[0].tags", raw),
+            (vec!["[\"a\"]".to_string()], true)
+        );
+        assert_eq!(run("$.*.id", raw), (vec!["3".to_string()], true));
+        assert_eq!(run("$", "[]"), (vec!["[]".to_string()], true));
+        assert_eq!(run("$.a", "[1, 2]"), (vec![], true));
+    }
+
+    #[test]
+    fn test_query_invalid() {
+        assert_eq!(run("$.a", "{\"a\": 1,}"), (vec!["1".to_string()], false));
+        assert_eq!(run("$.a", "{\"b\": [1 2]}"), (vec![], false));
+        assert_eq!(run("$.a", "{} {}"), (vec![], false));
+        assert_eq!(run("$.a", "{\"a\": [1}"), (vec![], false));
+        assert_eq!(run("$.a", "{\"a\": \"\\q\"}"), (vec![], false));
+    }
+
+    #[test]
+    fn test_query_options() {
+        let raw = "{\"a\": [1, /* two */ 2,], // end\n}";
+        assert_eq!(run("$.a[*]", raw), (vec!["1".to_string()], false));
+        assert_eq!(
+            run_with("$.a[*]", raw.as_bytes(), &ParserOptions::lenient()),
+            (vec!["1".to_string(), "2".to_string()], true)
+        );
+
+        let comments = ParserOptions {
+            allow_comments: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            run_with("$.a[1]", raw.as_bytes(), &comments),
+            (vec!["2".to_string()], false)
+        );
+        assert_eq!(
+            run_with(
+                "$.a",
+                "// a\n{\"a\": /* b */ [1 /* c */]}".as_bytes(),
+                &comments
+            ),
+            (vec!["[1]".to_string()], true)
+        );
+
+        let trailing_commas = ParserOptions {
+            allow_trailing_commas: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            run_with(
+                "$.a",
+                "{\"a\": [1,], \"b\": {\"c\": 2,},}".as_bytes(),
+                &trailing_commas
+            ),
+            (vec!["[1]".to_string()], true)
+        );
+        assert_eq!(
+            run_with("$.a", "[,]".as_bytes(), &trailing_commas),
+            (vec![], false)
+        );
+
+        let unquoted = ParserOptions {
+            allow_unquoted_keys: true,
+            allow_non_finite: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            run_with("$.a", "{a: NaN, b: -Infinity}".as_bytes(), &unquoted),
+            (vec!["NaN".to_string()], true)
+        );
+        assert_eq!(run("$.a", "{a: 1}"), (vec![], false));
+        assert_eq!(run("$.a", "{\"a\": NaN}"), (vec![], false));
+
+        let trailing_content = ParserOptions {
+            allow_trailing_content: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            run_with(
+                "$.a",
+                "{\"a\": 1} trailing \"text".as_bytes(),
+                &trailing_content
+            ),
+            (vec!["1".to_string()], true)
+        );
+
+        // Later values of a key never match, and are an error unless duplicates are allowed
+        let raw = "{\"a\": 1, \"a\": 2, \"b\": {\"a\": 3}}";
+        assert_eq!(run("$.a", raw), (vec!["1".to_string()], false));
+        let first_wins = ParserOptions {
+            duplicate_keys: parser::DuplicateKeys::FirstWins,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            run_with("$.a", raw.as_bytes(), &first_wins),
+            (vec!["1".to_string()], true)
+        );
+        assert_eq!(
+            run_with("$.b.a", raw.as_bytes(), &first_wins),
+            (vec!["3".to_string()], true)
+        );
+    }
+
+    #[test]
+    fn test_query_chunks() {
+        // Values, strings, numbers and characters cut between chunks
+        let mut raw = String::from("{\"skip\": [");
+        for i in 0..20_000 {
+            raw += &format!("{{\"n\": {}, \"s\": \"é {}\"}}, ", i, i);
+        }
+        raw += "1], \"a\": {\"long\": \"";
+        raw += &"x".repeat(CHUNK_SIZE * 2);
+        raw += "\"}, \"n\": 123456789}";
+
+        let (found, valid) = run("$.n", &raw);
+        assert!(valid);
+        assert_eq!(found, ["123456789"]);
+
+        let (found, valid) = run("$.skip[19999].s", &raw);
+        assert!(valid);
+        assert_eq!(found, ["\"é 19999\""]);
+
+        let (found, valid) = run("$.a", &raw);
+        assert!(valid);
+        assert_eq!(found[0].len(), CHUNK_SIZE * 2 + 11);
+    }
+
+    #[test]
+    fn test_query_depth() {
+        let deep = "[".repeat(100_000);
+        assert_eq!(
+            run_with("$[0]", deep.as_bytes(), &depth(512)),
+            (vec![], false)
+        );
+
+        let nested = "[[[1]]]";
+        assert!(run_with("$[0][0][0]", nested.as_bytes(), &depth(3)).1);
+        assert!(!run_with("$[0][0][0]", nested.as_bytes(), &depth(2)).1);
+    }
+
+    #[test]
+    fn test_query_encodings() {
+        let utf16: Vec<u8> = "{\"a\": \"é\"}"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(
+            run_with("$.a", &utf16, &ParserOptions::default()),
+            (vec!["\"é\"".to_string()], true)
+        );
+
+        let steps = assertion::parse_path("$.a").unwrap();
+        let options = ParserOptions::default();
+        let invalid = query(
+            &b"{\"a\": \"\xff\"}"[..],
+            None,
+            false,
+            &steps,
+            &options,
+            |_| {},
+        );
+        assert_eq!(
+            invalid,
+            Err("Input is not valid UTF-8 at byte 7, it may be windows-1252".to_string())
+        );
+    }
+}
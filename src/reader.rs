@@ -0,0 +1,456 @@
+use escape::unescape;
+use lexer::{self, LexError, Token, TokenType};
+use number::Number;
+use parser::{OwnedParseError, ParseError, ParseErrorType};
+use std::borrow::Cow;
+
+// A pull parser: reads a document as a sequence of events without building it, so memory use
+// only grows with the nesting depth. The syntax is checked as strictly as by `parse_str`, except
+// that duplicate keys are not noticed since the keys are not kept
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventType<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    // Keys and strings are decoded, and borrow the input when they have no escapes
+    Key(Cow<'a, str>),
+    Null,
+    Bool(bool),
+    Number(Number<'a>),
+    String(Cow<'a, str>),
+}
+
+// An event with the zero based position of the token it comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<'a> {
+    pub event_type: EventType<'a>,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    // The first item of an array, which may be its end instead
+    FirstItem,
+    Value,
+    FirstKey,
+    Key,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+pub struct Reader<'a> {
+    tokens: Box<dyn Iterator<Item = Result<Token<'a>, LexError>> + 'a>,
+    // Whether each open container is an object
+    stack: Vec<bool>,
+    expect: Expect,
+    // The comma before the item being read, a closing bracket after it is a trailing comma
+    comma: Option<Token<'a>>,
+    // The key of the member whose value is being read
+    key: Option<Token<'a>>,
+    failed: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(s: &'a str) -> Self {
+        Reader::from_tokens(lexer::tokens(s))
+    }
+
+    // Reads the tokens as they come, like those of `lexer::tokens`
+    pub fn from_tokens<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = Result<Token<'a>, LexError>>,
+        I::IntoIter: 'a,
+    {
+        Reader {
+            tokens: Box::new(tokens.into_iter()),
+            stack: vec![],
+            expect: Expect::Value,
+            comma: None,
+            key: None,
+            failed: false,
+        }
+    }
+
+    // Number of arrays and objects the next event is inside of
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn fail(
+        &mut self,
+        error_type: ParseErrorType,
+        token: Option<&Token<'a>>,
+        expected: Option<&TokenType<'a>>,
+    ) -> Option<Result<Event<'a>, OwnedParseError>> {
+        self.failed = true;
+        Some(Err(ParseError::new(error_type, token, expected).into()))
+    }
+
+    fn emit(
+        &mut self,
+        event_type: EventType<'a>,
+        token: &Token<'a>,
+    ) -> Option<Result<Event<'a>, OwnedParseError>> {
+        self.expect = match event_type {
+            EventType::StartObject => Expect::FirstKey,
+            EventType::StartArray => Expect::FirstItem,
+            EventType::Key(_) => Expect::Colon,
+            _ if self.stack.is_empty() => Expect::End,
+            _ => Expect::CommaOrClose,
+        };
+
+        Some(Ok(Event {
+            event_type,
+            line: token.line,
+            col: token.col,
+        }))
+    }
+
+    fn open(
+        &mut self,
+        object: bool,
+        token: &Token<'a>,
+    ) -> Option<Result<Event<'a>, OwnedParseError>> {
+        self.stack.push(object);
+
+        let event_type = if object {
+            EventType::StartObject
+        } else {
+            EventType::StartArray
+        };
+
+        self.emit(event_type, token)
+    }
+
+    fn close(&mut self, token: &Token<'a>) -> Option<Result<Event<'a>, OwnedParseError>> {
+        let event_type = if self.stack.pop() == Some(true) {
+            EventType::EndObject
+        } else {
+            EventType::EndArray
+        };
+
+        self.emit(event_type, token)
+    }
+
+    // A closing bracket where an item would have started ends the array or object, like in
+    // `parse_str`, other tokens that cannot start an item are an error of their own. The value of
+    // a member starts at its key
+    fn unexpected(&mut self, token: &Token<'a>) -> Option<Result<Event<'a>, OwnedParseError>> {
+        let bracket = matches!(
+            token.token_type,
+            TokenType::CloseCurly | TokenType::CloseSquare
+        );
+
+        let (close, error_type) = match self.stack.last() {
+            Some(true) if bracket && self.key.is_none() => {
+                (&TokenType::CloseCurly, ParseErrorType::MissingCloseCurly)
+            }
+            Some(false) if bracket && self.key.is_none() => {
+                (&TokenType::CloseSquare, ParseErrorType::MissingCloseSquare)
+            }
+            _ => return self.fail(ParseErrorType::UnexpectedToken, Some(token), None),
+        };
+
+        if let Some(comma) = self.comma.take() {
+            self.fail(ParseErrorType::TrailingComma, Some(&comma), None)
+        } else if token.token_type == *close {
+            self.close(token)
+        } else {
+            self.fail(error_type, Some(token), Some(close))
+        }
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<Event<'a>, OwnedParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            let token = match self.tokens.next() {
+                Some(Ok(token)) => token,
+                Some(Err(lex_error)) => {
+                    self.failed = true;
+                    return Some(Err(lex_error.into()));
+                }
+                None if self.expect == Expect::End => return None,
+                None => return self.fail(ParseErrorType::UnexpectedEnd, None, None),
+            };
+
+            if let TokenType::Comment(_) = token.token_type {
+                return self.fail(ParseErrorType::Comment, Some(&token), None);
+            }
+
+            let in_object = self.stack.last() == Some(&true);
+
+            return match (self.expect, &token.token_type) {
+                (Expect::End, _) => self.fail(ParseErrorType::UnexpectedToken, Some(&token), None),
+                (Expect::FirstItem | Expect::Value, token_type) => {
+                    let event_type = match *token_type {
+                        TokenType::Null => EventType::Null,
+                        TokenType::Bool(b) => EventType::Bool(b),
+                        TokenType::Number(ref number, _) => EventType::Number(number.clone()),
+                        TokenType::String(s) => EventType::String(unescape(s)),
+                        TokenType::OpenCurly => EventType::StartObject,
+                        TokenType::OpenSquare => EventType::StartArray,
+                        _ => return self.unexpected(&token),
+                    };
+
+                    self.comma = None;
+                    self.key = None;
+
+                    match event_type {
+                        EventType::StartObject => self.open(true, &token),
+                        EventType::StartArray => self.open(false, &token),
+                        _ => self.emit(event_type, &token),
+                    }
+                }
+                (Expect::FirstKey | Expect::Key, &TokenType::String(key)) => {
+                    let event = self.emit(EventType::Key(unescape(key)), &token);
+                    self.key = Some(token);
+                    event
+                }
+                (
+                    Expect::FirstKey | Expect::Key,
+                    TokenType::Invalid(_) | TokenType::Number(..) | TokenType::Bool(_),
+                ) => self.fail(ParseErrorType::KeyNotInQuotes, Some(&token), None),
+                (Expect::FirstKey | Expect::Key, _) => self.unexpected(&token),
+                (Expect::Colon, TokenType::Colon) => {
+                    self.expect = Expect::Value;
+                    continue;
+                }
+                (Expect::Colon, _) => self.fail(
+                    ParseErrorType::MissingColon,
+                    Some(&token),
+                    Some(&TokenType::Colon),
+                ),
+                (Expect::CommaOrClose, TokenType::Comma) => {
+                    self.expect = if in_object {
+                        Expect::Key
+                    } else {
+                        Expect::Value
+                    };
+                    self.comma = Some(token);
+                    continue;
+                }
+                (Expect::CommaOrClose, TokenType::CloseCurly) if in_object => self.close(&token),
+                (Expect::CommaOrClose, TokenType::CloseSquare) if !in_object => self.close(&token),
+                (Expect::CommaOrClose, _) if in_object => self.fail(
+                    ParseErrorType::MissingCloseCurly,
+                    Some(&token),
+                    Some(&TokenType::CloseCurly),
+                ),
+                (Expect::CommaOrClose, _) => self.fail(
+                    ParseErrorType::MissingCloseSquare,
+                    Some(&token),
+                    Some(&TokenType::CloseSquare),
+                ),
+            };
+        }
+    }
+}
+
+// Callbacks for `parse_with_handler`, each returning whether to carry on. Keys and strings are
+// decoded like in `EventType`
+pub trait JsonHandler<'a> {
+    fn start_object(&mut self) -> bool {
+        true
+    }
+
+    fn end_object(&mut self) -> bool {
+        true
+    }
+
+    fn start_array(&mut self) -> bool {
+        true
+    }
+
+    fn end_array(&mut self) -> bool {
+        true
+    }
+
+    fn key(&mut self, _key: Cow<'a, str>) -> bool {
+        true
+    }
+
+    fn null(&mut self) -> bool {
+        true
+    }
+
+    fn bool(&mut self, _value: bool) -> bool {
+        true
+    }
+
+    fn number(&mut self, _value: Number<'a>) -> bool {
+        true
+    }
+
+    fn string(&mut self, _value: Cow<'a, str>) -> bool {
+        true
+    }
+}
+
+// Reads the input with a `Reader`, passing each event to the handler. Returns `Ok(false)` when the
+// handler stopped early, the rest of the input is not checked then
+pub fn parse_with_handler<'a, H: JsonHandler<'a> + ?Sized>(
+    s: &'a str,
+    handler: &mut H,
+) -> Result<bool, OwnedParseError> {
+    for event in Reader::new(s) {
+        let carry_on = match event?.event_type {
+            EventType::StartObject => handler.start_object(),
+            EventType::EndObject => handler.end_object(),
+            EventType::StartArray => handler.start_array(),
+            EventType::EndArray => handler.end_array(),
+            EventType::Key(key) => handler.key(key),
+            EventType::Null => handler.null(),
+            EventType::Bool(value) => handler.bool(value),
+            EventType::Number(value) => handler.number(value),
+            EventType::String(value) => handler.string(value),
+        };
+
+        if !carry_on {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    fn events(s: &str) -> Vec<EventType<'_>> {
+        Reader::new(s)
+            .map(|event| event.unwrap().event_type)
+            .collect()
+    }
+
+    #[test]
+    fn test_reader() {
+        assert_eq!(
+            events(r#"{"a": [1, "x\n", {}], "b": null, "c": [[], true]}"#),
+            [
+                EventType::StartObject,
+                EventType::Key("a".into()),
+                EventType::StartArray,
+                EventType::Number(Number::PosInt(1)),
+                EventType::String("x\n".into()),
+                EventType::StartObject,
+                EventType::EndObject,
+                EventType::EndArray,
+                EventType::Key("b".into()),
+                EventType::Null,
+                EventType::Key("c".into()),
+                EventType::StartArray,
+                EventType::StartArray,
+                EventType::EndArray,
+                EventType::Bool(true),
+                EventType::EndArray,
+                EventType::EndObject,
+            ]
+        );
+        assert_eq!(events(" 12 "), [EventType::Number(Number::PosInt(12))]);
+
+        let mut reader = Reader::new("[\n  {\"k\": 1}]");
+        reader.next();
+        assert_eq!(reader.depth(), 1);
+        assert_eq!(
+            reader.next().unwrap(),
+            Ok(Event {
+                event_type: EventType::StartObject,
+                line: 1,
+                col: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let inputs = [
+            "",
+            "[1, 2,]",
+            r#"{"a": 1,}"#,
+            r#"{"a" 1}"#,
+            "{a: 1}",
+            "[1 2]",
+            r#"{"a": 1"#,
+            "[1] 2",
+            "[1, // one\n 2]",
+            "[nul]",
+            "[1,, 2]",
+            "{,}",
+            r#"{"a": }"#,
+            r#"{"a": 1, "b": ]"#,
+            "[{]",
+        ];
+
+        for input in &inputs {
+            let error = Reader::new(input)
+                .find_map(Result::err)
+                .unwrap_or_else(|| panic!("{:?} has no error", input));
+            assert_eq!(error, parser::parse_str(input).unwrap_err(), "{:?}", input);
+        }
+
+        let mut reader = Reader::new("[1,]");
+        assert!(reader.by_ref().any(|event| event.is_err()));
+        assert_eq!(reader.next(), None);
+    }
+
+    // Finds the top level "id", stopping as soon as it is read
+    #[derive(Default)]
+    struct FindId<'a> {
+        depth: usize,
+        in_id: bool,
+        id: Option<Cow<'a, str>>,
+    }
+
+    impl<'a> JsonHandler<'a> for FindId<'a> {
+        fn start_object(&mut self) -> bool {
+            self.depth += 1;
+            true
+        }
+
+        fn end_object(&mut self) -> bool {
+            self.depth -= 1;
+            true
+        }
+
+        fn key(&mut self, key: Cow<'a, str>) -> bool {
+            self.in_id = self.depth == 1 && key == "id";
+            true
+        }
+
+        fn string(&mut self, value: Cow<'a, str>) -> bool {
+            if self.in_id {
+                self.id = Some(value);
+            }
+
+            !self.in_id
+        }
+    }
+
+    #[test]
+    fn test_parse_with_handler() {
+        let mut handler = FindId::default();
+        let raw = r#"{"a": {"id": "inner"}, "id": "outer", "rest": [1,"#;
+        assert_eq!(parse_with_handler(raw, &mut handler), Ok(false));
+        assert_eq!(handler.id.as_deref(), Some("outer"));
+
+        let mut handler = FindId::default();
+        assert_eq!(parse_with_handler(r#"{"b": "x"}"#, &mut handler), Ok(true));
+        assert_eq!(handler.id, None);
+
+        let error = parse_with_handler(r#"{"b" "x"}"#, &mut FindId::default()).unwrap_err();
+        assert_eq!(error.error_type, ParseErrorType::MissingColon);
+    }
+}
@@ -0,0 +1,333 @@
+use joxide::escape::escape;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Sarif,
+    Junit,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "sarif" => Some(Format::Sarif),
+            "junit" => Some(Format::Junit),
+            _ => None,
+        }
+    }
+
+    // Guessed from the extension of the report file, JSON unless it ends in .sarif or .xml
+    pub fn from_path(path: &str) -> Format {
+        if path.ends_with(".sarif") {
+            Format::Sarif
+        } else if path.ends_with(".xml") {
+            Format::Junit
+        } else {
+            Format::Json
+        }
+    }
+}
+
+// Warnings are problems that do not stop the document from being read, like style lints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "warning" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    // One based, like in the human readable output
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+}
+
+// Collects the checked files and their diagnostics for a machine readable report, shared
+// between threads when lines are validated in parallel
+#[derive(Default)]
+pub struct Report {
+    files: Mutex<Vec<String>>,
+    diagnostics: Mutex<Vec<Diagnostic>>,
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn json_number(n: Option<usize>) -> String {
+    n.map_or("null".to_string(), |n| n.to_string())
+}
+
+impl Report {
+    pub fn file(&self, file: &str) {
+        let mut files = self.files.lock().unwrap();
+
+        if !files.iter().any(|f| f == file) {
+            files.push(file.to_string());
+        }
+    }
+
+    pub fn diagnostic(&self, diagnostic: Diagnostic) {
+        self.file(&diagnostic.file);
+        self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let files = self.files.lock().unwrap();
+        let mut diagnostics = self.diagnostics.lock().unwrap().clone();
+        diagnostics.sort_by_key(|d| (files.iter().position(|f| *f == d.file), d.line, d.col));
+
+        match format {
+            Format::Json => json(&files, &diagnostics),
+            Format::Sarif => sarif(&diagnostics),
+            Format::Junit => junit(&files, &diagnostics),
+        }
+    }
+}
+
+fn json(files: &[String], diagnostics: &[Diagnostic]) -> String {
+    let mut result = String::from("{\n    \"files\": [");
+
+    for (index, file) in files.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        write!(result, "{}\n        \"{}\"", separator, escape(file)).unwrap();
+    }
+
+    result += "\n    ],\n    \"diagnostics\": [";
+
+    for (index, d) in diagnostics.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        write!(
+            result,
+            "{}\n        {{\"file\": \"{}\", \"line\": {}, \"col\": {}, \"severity\": \"{}\", \"code\": \"{}\", \"message\": \"{}\"}}",
+            separator,
+            escape(&d.file),
+            json_number(d.line),
+            json_number(d.col),
+            d.severity.name(),
+            d.code,
+            escape(&d.message)
+        )
+        .unwrap();
+    }
+
+    result + "\n    ]\n}\n"
+}
+
+fn sarif(diagnostics: &[Diagnostic]) -> String {
+    let mut result = String::from(
+        "{
+    \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\",
+    \"version\": \"2.1.0\",
+    \"runs\": [
+        {
+            \"tool\": {\"driver\": {\"name\": \"joxide\", \"informationUri\": \"https://github.com/RainingComputers/joxide\"}},
+            \"results\": [",
+    );
+
+    for (index, d) in diagnostics.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        let region = match (d.line, d.col) {
+            (Some(line), Some(col)) => {
+                format!(
+                    ", \"region\": {{\"startLine\": {}, \"startColumn\": {}}}",
+                    line, col
+                )
+            }
+            _ => String::new(),
+        };
+
+        write!(
+            result,
+            "{}\n                {{\"ruleId\": \"{}\", \"level\": \"{}\", \"message\": {{\"text\": \"{}\"}}, \"locations\": [{{\"physicalLocation\": {{\"artifactLocation\": {{\"uri\": \"{}\"}}{}}}}}]}}",
+            separator,
+            d.code,
+            d.severity.name(),
+            escape(&d.message),
+            escape(&d.file),
+            region
+        )
+        .unwrap();
+    }
+
+    result + "\n            ]\n        }\n    ]\n}\n"
+}
+
+// Errors are failures, warnings are only written to the output of the test case
+fn junit(files: &[String], diagnostics: &[Diagnostic]) -> String {
+    let failures = files
+        .iter()
+        .filter(|file| {
+            diagnostics
+                .iter()
+                .any(|d| d.file == **file && d.severity == Severity::Error)
+        })
+        .count();
+
+    let mut result = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n  <testsuite name=\"joxide\" tests=\"{}\" failures=\"{}\">\n",
+        files.len(),
+        failures
+    );
+
+    for file in files {
+        let file_diagnostics: Vec<_> = diagnostics.iter().filter(|d| d.file == *file).collect();
+
+        if file_diagnostics.is_empty() {
+            writeln!(result, "    <testcase name=\"{}\"/>", xml_escape(file)).unwrap();
+            continue;
+        }
+
+        writeln!(result, "    <testcase name=\"{}\">", xml_escape(file)).unwrap();
+
+        let mut warnings = String::new();
+
+        for d in file_diagnostics {
+            let position = match (d.line, d.col) {
+                (Some(line), Some(col)) => format!("{}:{}:{}: ", d.file, line, col),
+                _ => format!("{}: ", d.file),
+            };
+
+            if d.severity == Severity::Warning {
+                writeln!(
+                    warnings,
+                    "{}warning: {}",
+                    xml_escape(&position),
+                    xml_escape(&d.message)
+                )
+                .unwrap();
+                continue;
+            }
+
+            writeln!(
+                result,
+                "      <failure type=\"{}\" message=\"{}\">{}{}</failure>",
+                d.code,
+                xml_escape(&d.message),
+                xml_escape(&position),
+                xml_escape(&d.message)
+            )
+            .unwrap();
+        }
+
+        if !warnings.is_empty() {
+            writeln!(result, "      <system-out>{}</system-out>", warnings).unwrap();
+        }
+
+        result += "    </testcase>\n";
+    }
+
+    result + "  </testsuite>\n</testsuites>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::{lexer, parser};
+
+    fn report() -> Report {
+        let report = Report::default();
+        report.file("a.json");
+        report.diagnostic(Diagnostic {
+            file: "b.json".to_string(),
+            line: Some(2),
+            col: Some(5),
+            severity: Severity::Error,
+            code: "trailing-comma",
+            message: "Trailing commas are not valid <here>".to_string(),
+        });
+        report.diagnostic(Diagnostic {
+            file: "c.json".to_string(),
+            line: None,
+            col: None,
+            severity: Severity::Warning,
+            code: "schema",
+            message: "Unknown keyword 'tpye'".to_string(),
+        });
+        report
+    }
+
+    #[test]
+    fn test_render() {
+        let report = report();
+
+        assert_eq!(
+            report.render(Format::Json),
+            "{
+    \"files\": [
+        \"a.json\",
+        \"b.json\",
+        \"c.json\"
+    ],
+    \"diagnostics\": [
+        {\"file\": \"b.json\", \"line\": 2, \"col\": 5, \"severity\": \"error\", \"code\": \"trailing-comma\", \"message\": \"Trailing commas are not valid <here>\"},
+        {\"file\": \"c.json\", \"line\": null, \"col\": null, \"severity\": \"warning\", \"code\": \"schema\", \"message\": \"Unknown keyword 'tpye'\"}
+    ]
+}
+"
+        );
+
+        assert_eq!(
+            report.render(Format::Junit),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites>
+  <testsuite name=\"joxide\" tests=\"3\" failures=\"1\">
+    <testcase name=\"a.json\"/>
+    <testcase name=\"b.json\">
+      <failure type=\"trailing-comma\" message=\"Trailing commas are not valid &lt;here&gt;\">b.json:2:5: Trailing commas are not valid &lt;here&gt;</failure>
+    </testcase>
+    <testcase name=\"c.json\">
+      <system-out>c.json: warning: Unknown keyword 'tpye'
+</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+"
+        );
+    }
+
+    #[test]
+    fn test_render_valid_json() {
+        let report = report();
+
+        for format in [Format::Json, Format::Sarif] {
+            let rendered = report.render(format);
+            let tokens = lexer::lex(&rendered).unwrap();
+            assert!(parser::parse(&tokens).is_ok(), "{}", rendered);
+        }
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Format::from_path("out.sarif"), Format::Sarif);
+        assert_eq!(Format::from_path("junit.xml"), Format::Junit);
+        assert_eq!(Format::from_path("report.json"), Format::Json);
+    }
+}
@@ -0,0 +1,633 @@
+use joxide::jsonschema::{self, Draft, Options, Validator, DRAFTS};
+use joxide::parser::Json;
+use joxide::pointer;
+use report::Severity;
+
+const TYPES: [&str; 7] = [
+    "array", "boolean", "integer", "null", "number", "object", "string",
+];
+
+// Keywords whose value is a schema
+const SCHEMA_KEYWORDS: [&str; 12] = [
+    "additionalItems",
+    "additionalProperties",
+    "contains",
+    "contentSchema",
+    "else",
+    "if",
+    "items",
+    "not",
+    "propertyNames",
+    "then",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+
+// Keywords whose value is an array of schemas
+const SCHEMA_ARRAY_KEYWORDS: [&str; 5] = ["allOf", "anyOf", "items", "oneOf", "prefixItems"];
+
+// Keywords whose value is an object of schemas
+const SCHEMA_MAP_KEYWORDS: [&str; 6] = [
+    "$defs",
+    "definitions",
+    "dependencies",
+    "dependentSchemas",
+    "patternProperties",
+    "properties",
+];
+
+#[derive(Debug, PartialEq)]
+pub struct Lint {
+    pub pointer: String,
+    // Whether the problem is the key at the pointer rather than its value
+    pub key: bool,
+    pub message: String,
+    // Errors break the specification, warnings are likely mistakes it still allows, like an
+    // unknown keyword
+    pub severity: Severity,
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+fn suggestion<'k>(word: &str, candidates: &[&'k str]) -> Option<&'k str> {
+    candidates
+        .iter()
+        .map(|candidate| {
+            (
+                distance(&word.to_lowercase(), &candidate.to_lowercase()),
+                *candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn unknown(kind: &str, word: &str, candidates: &[&str]) -> String {
+    match suggestion(word, candidates) {
+        Some(candidate) => format!("Unknown {} '{}', did you mean '{}'?", kind, word, candidate),
+        None => format!("Unknown {} '{}'", kind, word),
+    }
+}
+
+// Decodes the percent escapes of a URI fragment
+fn percent_decode(fragment: &str) -> String {
+    let bytes = fragment.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = fragment.get(i + 1..i + 3).filter(|_| bytes[i] == b'%');
+
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// A `$ref` to a JSON pointer in the same document that leads nowhere, other references are not checked
+pub fn unresolved_ref(root: &Json, reference: &str) -> Option<String> {
+    let fragment = reference.strip_prefix('#')?;
+
+    if !fragment.is_empty() && !fragment.starts_with('/') {
+        return None;
+    }
+
+    match root.pointer(&percent_decode(fragment)) {
+        Some(_) => None,
+        None => Some(format!("'$ref' to '{}' does not resolve", reference)),
+    }
+}
+
+fn lint_type(value: &Json, path: &str, lints: &mut Vec<Lint>) {
+    let names: Vec<(String, &Json)> = match value {
+        Json::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (pointer::push(path, &index.to_string()), item))
+            .collect(),
+        _ => vec![(path.to_string(), value)],
+    };
+
+    // Other values are left to the meta-schema
+    for (pointer, name) in names {
+        if let Json::String(name) = name {
+            if !TYPES.contains(&name.as_ref()) {
+                lints.push(Lint {
+                    pointer,
+                    key: false,
+                    message: unknown("type", name, &TYPES),
+                    severity: Severity::Error,
+                });
+            }
+        }
+    }
+}
+
+// A `pattern` or `patternProperties` key that is not an ECMA-262 regular expression
+fn invalid_pattern(pattern: &str) -> Option<String> {
+    match jsonschema::check_format("regex", pattern) {
+        Some(false) => Some(format!("'{}' is not a valid regular expression", pattern)),
+        _ => None,
+    }
+}
+
+// Lints the schema at `path` for the keywords of `draft`, `extra` keywords are allowed on top of
+// them and an extra keyword ending in '-' allows every key with that prefix. What the values of
+// the keywords should be is left to the meta-schema
+pub fn lint_schema(
+    root: &Json,
+    schema: &Json,
+    path: &str,
+    draft: Draft,
+    extra: &[&str],
+    lints: &mut Vec<Lint>,
+) {
+    let obj = match schema {
+        Json::Object(obj) => obj,
+        _ => return,
+    };
+
+    for (key, value) in obj {
+        let key = key.as_ref();
+        let item_path = pointer::push(path, key);
+
+        let allowed = extra
+            .iter()
+            .any(|extra| *extra == key || (extra.ends_with('-') && key.starts_with(extra)));
+
+        if !draft.has_keyword(key) && !allowed {
+            let message = match DRAFTS.iter().any(|other| other.has_keyword(key)) {
+                true => format!("'{}' is not a keyword of draft {}", key, draft.name()),
+                false => unknown("keyword", key, &draft.keywords()),
+            };
+
+            lints.push(Lint {
+                pointer: item_path,
+                key: true,
+                message,
+                severity: Severity::Warning,
+            });
+            continue;
+        }
+
+        match (key, value) {
+            ("type", _) => lint_type(value, &item_path, lints),
+            ("$ref", Json::String(reference)) => {
+                if let Some(message) = unresolved_ref(root, reference) {
+                    lints.push(Lint {
+                        pointer: item_path,
+                        key: false,
+                        message,
+                        severity: Severity::Error,
+                    });
+                }
+            }
+            ("pattern", Json::String(pattern)) => {
+                if let Some(message) = invalid_pattern(pattern) {
+                    lints.push(Lint {
+                        pointer: item_path,
+                        key: false,
+                        message,
+                        severity: Severity::Error,
+                    });
+                }
+            }
+            (keyword, Json::Array(arr)) if SCHEMA_ARRAY_KEYWORDS.contains(&keyword) => {
+                for (index, item) in arr.iter().enumerate() {
+                    let path = pointer::push(&item_path, &index.to_string());
+                    lint_schema(root, item, &path, draft, extra, lints);
+                }
+            }
+            (keyword, Json::Object(map)) if SCHEMA_MAP_KEYWORDS.contains(&keyword) => {
+                for (name, item) in map {
+                    let path = pointer::push(&item_path, name);
+
+                    if keyword == "patternProperties" {
+                        if let Some(message) = invalid_pattern(name) {
+                            lints.push(Lint {
+                                pointer: path.clone(),
+                                key: true,
+                                message,
+                                severity: Severity::Error,
+                            });
+                        }
+                    }
+
+                    lint_schema(root, item, &path, draft, extra, lints);
+                }
+            }
+            (keyword, _) if SCHEMA_KEYWORDS.contains(&keyword) => {
+                lint_schema(root, value, &item_path, draft, extra, lints)
+            }
+            _ => {}
+        }
+    }
+}
+
+// Validates the schema at `path` against the meta-schema of `draft`. A problem the other lints
+// already found at the same value, or at an item of it like a misspelled type name, is not
+// reported again
+pub fn lint_meta_schema(schema: &Json, path: &str, draft: Draft, lints: &mut Vec<Lint>) {
+    let validator = Validator::new(jsonschema::meta_schema(draft), Options::default());
+
+    for error in validator.validate(schema) {
+        let pointer = format!("{}{}", path, error.instance_path);
+        let found = lints.iter().any(|lint| {
+            let item = lint.pointer.strip_prefix(&pointer);
+            !lint.key
+                && lint.severity == Severity::Error
+                && item.is_some_and(|item| item.is_empty() || item.rfind('/') == Some(0))
+        });
+
+        if !found {
+            lints.push(Lint {
+                pointer,
+                key: false,
+                message: error.message,
+                severity: Severity::Error,
+            });
+        }
+    }
+}
+
+// Common authoring mistakes in a JSON Schema for `draft`, or else the draft its `$schema` gives
+// and 2020-12 without one: keywords of other drafts or of none, unknown type names, invalid
+// regular expressions, references within the document that do not resolve and whatever the
+// meta-schema of the draft does not allow
+pub fn lint(schema: &Json, draft: Option<Draft>) -> Vec<Lint> {
+    let mut lints = vec![];
+    let mut warn = |message| {
+        lints.push(Lint {
+            pointer: "/$schema".to_string(),
+            key: false,
+            message,
+            severity: Severity::Warning,
+        })
+    };
+
+    let declared = match schema.get("$schema") {
+        Some(Json::String(uri)) => Draft::from_uri(uri).or_else(|| {
+            let checked = draft.unwrap_or(Draft::Draft2020_12);
+            warn(format!(
+                "Unknown '$schema' '{}', checked as draft {}",
+                uri,
+                checked.name()
+            ));
+            None
+        }),
+        _ => None,
+    };
+
+    let draft = match (draft, declared) {
+        (Some(draft), Some(declared)) if draft != declared => {
+            warn(format!(
+                "'$schema' is draft {}, checked as draft {}",
+                declared.name(),
+                draft.name()
+            ));
+            draft
+        }
+        (Some(draft), _) | (None, Some(draft)) => draft,
+        (None, None) => Draft::Draft2020_12,
+    };
+
+    lint_schema(schema, schema, "", draft, &[], &mut lints);
+    lint_meta_schema(schema, "", draft, &mut lints);
+    lints
+}
+
+// The ways the instance fails the schema, at the value that failed with the pointer of the
+// keyword of the schema that failed it
+pub fn validate(instance: &Json, schema: &Json, options: Options) -> Vec<Lint> {
+    Validator::new(schema, options)
+        .validate(instance)
+        .into_iter()
+        .map(|error| Lint {
+            message: format!(
+                "{} (value at '{}', keyword at '{}' of the schema)",
+                error.message, error.instance_path, error.keyword_path
+            ),
+            pointer: error.instance_path,
+            key: false,
+            severity: Severity::Error,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::{lexer, parser};
+
+    #[test]
+    fn test_lint() {
+        let tokens = lexer::lex(
+            "{
+                \"type\": \"object\",
+                \"requird\": [\"a\"],
+                \"properties\": {
+                    \"a\": {\"type\": [\"string\", \"nul\"]},
+                    \"b\": {\"$ref\": \"#/definitions/B\"},
+                    \"c\": {\"$ref\": \"#/$defs/c%20d\"},
+                    \"d\": {\"items\": {\"tpye\": \"string\"}},
+                    \"e\": 1
+                },
+                \"$defs\": {\"c d\": true},
+                \"dependentRequired\": {\"a\": [\"b\"]}
+            }",
+        )
+        .unwrap();
+        let schema = parser::parse(&tokens).unwrap();
+
+        // Only unknown keywords are warnings
+        let expected = |pointer: &str, key, message: &str| Lint {
+            pointer: pointer.to_string(),
+            key,
+            message: message.to_string(),
+            severity: if key {
+                Severity::Warning
+            } else {
+                Severity::Error
+            },
+        };
+
+        let mut lints = lint(&schema, None);
+        lints.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+
+        assert_eq!(
+            lints,
+            [
+                expected(
+                    "/properties/a/type/1",
+                    false,
+                    "Unknown type 'nul', did you mean 'null'?"
+                ),
+                expected(
+                    "/properties/b/$ref",
+                    false,
+                    "'$ref' to '#/definitions/B' does not resolve"
+                ),
+                expected(
+                    "/properties/d/items/tpye",
+                    true,
+                    "Unknown keyword 'tpye', did you mean 'type'?"
+                ),
+                expected(
+                    "/properties/e",
+                    false,
+                    "Expected an object or a boolean, found a number"
+                ),
+                expected(
+                    "/requird",
+                    true,
+                    "Unknown keyword 'requird', did you mean 'required'?"
+                ),
+            ]
+        );
+    }
+
+    fn lint_str(raw: &str) -> Vec<String> {
+        let tokens = lexer::lex(raw).unwrap();
+        let mut lints: Vec<_> = lint(&parser::parse(&tokens).unwrap(), None)
+            .into_iter()
+            .map(|lint| format!("{} {}", lint.pointer, lint.message))
+            .collect();
+        lints.sort();
+        lints
+    }
+
+    #[test]
+    fn test_lint_values() {
+        assert_eq!(
+            lint_str(
+                r#"{
+                    "minimum": "0",
+                    "maxLength": -1,
+                    "required": "a",
+                    "properties": [],
+                    "enum": {},
+                    "anyOf": [],
+                    "multipleOf": 0,
+                    "title": 1,
+                    "uniqueItems": "yes",
+                    "exclusiveMaximum": true,
+                    "minItems": 2.0
+                }"#
+            ),
+            [
+                "/anyOf Expected at least 1 items, found 0",
+                "/enum Expected an array, found an object",
+                "/exclusiveMaximum Expected a number, found a boolean",
+                "/maxLength Expected at least 0",
+                "/minimum Expected a number, found a string",
+                "/multipleOf Expected more than 0",
+                "/properties Expected an object, found an array",
+                "/required Expected an array, found a string",
+                "/title Expected a string, found a number",
+                "/uniqueItems Expected a boolean, found a string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_drafts() {
+        let draft =
+            |uri: &str, rest: &str| lint_str(&format!(r#"{{"$schema": "{}", {}}}"#, uri, rest));
+
+        let draft4 = "http://json-schema.org/draft-04/schema#";
+        assert_eq!(
+            draft(draft4, r#""exclusiveMaximum": 1, "maximum": 2"#),
+            ["/exclusiveMaximum Expected a boolean, found a number"]
+        );
+        assert_eq!(
+            draft(draft4, r#""exclusiveMaximum": true"#),
+            [" Property 'exclusiveMaximum' requires 'maximum'"]
+        );
+        assert_eq!(
+            draft(draft4, r#""items": true, "minItems": 1.0"#),
+            [
+                "/items Expected an object or an array, found a boolean",
+                "/minItems Expected an integer, found a number",
+            ]
+        );
+        assert_eq!(
+            draft(draft4, r#""const": 1"#),
+            ["/const 'const' is not a keyword of draft 04"]
+        );
+
+        // The draft given wins over `$schema`
+        let raw = format!(r#"{{"$schema": "{}", "const": 1}}"#, draft4);
+        let schema = parser::parse_str(&raw).unwrap();
+        let messages = |draft| -> Vec<_> {
+            lint(&schema, Some(draft))
+                .into_iter()
+                .map(|lint| lint.message)
+                .collect()
+        };
+        assert_eq!(
+            messages(Draft::Draft2020_12),
+            ["'$schema' is draft 04, checked as draft 2020-12"]
+        );
+        assert_eq!(
+            messages(Draft::Draft4),
+            ["'const' is not a keyword of draft 04"]
+        );
+
+        let draft6 = "http://json-schema.org/draft-06/schema#";
+        assert_eq!(draft(draft6, r#""items": true, "minItems": 1.0"#), [""; 0]);
+        assert_eq!(
+            draft(draft6, r#""exclusiveMaximum": true, "examples": {}"#),
+            [
+                "/examples Expected an array, found an object",
+                "/exclusiveMaximum Expected a number, found a boolean",
+            ]
+        );
+        assert_eq!(
+            draft(draft6, r#""if": {}"#),
+            ["/if 'if' is not a keyword of draft 06"]
+        );
+
+        let draft7 = "http://json-schema.org/draft-07/schema#";
+        assert_eq!(
+            draft(draft7, r#""if": {"type": 1}, "$comment": []"#),
+            [
+                "/$comment Expected a string, found an array",
+                "/if/type Matches none of the 'anyOf' schemas",
+            ]
+        );
+        assert_eq!(
+            draft(
+                draft7,
+                r#""$defs": {}, "items": [{}], "dependencies": {"a": 1}"#
+            ),
+            [
+                "/$defs '$defs' is not a keyword of draft 07",
+                "/dependencies/a Expected an object, a boolean or an array, found a number",
+            ]
+        );
+
+        let draft2019 = "https://json-schema.org/draft/2019-09/schema";
+        assert_eq!(
+            draft(draft2019, r#""items": [{}], "additionalItems": 1"#),
+            ["/additionalItems Expected an object or a boolean, found a number"]
+        );
+        assert_eq!(
+            draft(
+                draft2019,
+                r#""$recursiveAnchor": "a", "dependentRequired": {"a": [1]}"#
+            ),
+            [
+                "/$recursiveAnchor Expected a boolean, found a string",
+                "/dependentRequired/a/0 Expected a string, found a number",
+            ]
+        );
+
+        // Array form `items` became `prefixItems`
+        let draft2020 = "https://json-schema.org/draft/2020-12/schema";
+        assert_eq!(
+            draft(draft2020, r##""items": [{}], "$recursiveRef": "#""##),
+            [
+                "/$recursiveRef '$recursiveRef' is not a keyword of draft 2020-12",
+                "/items Expected an object or a boolean, found an array",
+            ]
+        );
+        assert_eq!(
+            draft(draft2020, r#""prefixItems": [{}], "minContains": -1"#),
+            ["/minContains Expected at least 0"]
+        );
+
+        assert_eq!(
+            draft("https://example.com/schema", r#""type": "string""#),
+            ["/$schema Unknown '$schema' 'https://example.com/schema', checked as draft 2020-12"]
+        );
+    }
+
+    #[test]
+    fn test_lint_patterns() {
+        assert_eq!(
+            lint_str(
+                r#"{
+                    "pattern": "^[a-z]+(",
+                    "patternProperties": {"^x-": true, "[": {"pattern": "\\d{2,1}"}},
+                    "properties": {"a": {"pattern": "(?<year>\\d{4})-\\k<year>"}}
+                }"#
+            ),
+            [
+                "/pattern '^[a-z]+(' is not a valid regular expression",
+                "/patternProperties/[ '[' is not a valid regular expression",
+                "/patternProperties/[/pattern '\\d{2,1}' is not a valid regular expression",
+                "/properties/a/pattern '(?<year>\\d{4})-\\k<year>' is not a valid regular expression",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("requird", "required"), 1);
+        assert_eq!(distance("tpye", "type"), 2);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(suggestion("colour", &Draft::Draft2020_12.keywords()), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = parser::parse_str(
+            r##"{"properties": {"a": {"$ref": "#/$defs/a"}}, "required": ["b"], "$defs": {"a": {"type": "string"}}}"##,
+        )
+        .unwrap();
+        let instance = parser::parse_str(r#"{"a": 1}"#).unwrap();
+
+        let mut lints = validate(&instance, &schema, Options::default());
+        lints.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        let found: Vec<_> = lints
+            .iter()
+            .map(|lint| (lint.pointer.as_str(), lint.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                (
+                    "",
+                    "Missing required property 'b' (value at '', keyword at '/required' of the schema)"
+                ),
+                (
+                    "/a",
+                    "Expected a string, found a number (value at '/a', keyword at '/properties/a/$ref/type' of the schema)"
+                ),
+            ]
+        );
+        assert!(validate(
+            &parser::parse_str(r#"{"b": 1}"#).unwrap(),
+            &schema,
+            Options::default()
+        )
+        .is_empty());
+    }
+}
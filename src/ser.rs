@@ -0,0 +1,68 @@
+use number::Number;
+use parser::Json;
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+// `Json::Error` is written as null like the formatter does,
+// and `Json::Raw` as the value its text holds
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Json::Null | Json::Error => serializer.serialize_unit(),
+            Json::Raw(_) => self.parse_raw().serialize(serializer),
+            Json::Bool(b) => serializer.serialize_bool(*b),
+            Json::Number(n) => n.serialize(serializer),
+            Json::String(s) => serializer.serialize_str(s),
+            Json::Array(arr) => {
+                let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+
+                for item in arr {
+                    seq.serialize_element(item)?;
+                }
+
+                seq.end()
+            }
+            Json::Object(obj) => {
+                let mut map = serializer.serialize_map(Some(obj.len()))?;
+
+                for (key, item) in obj {
+                    map.serialize_entry(key, item)?;
+                }
+
+                map.end()
+            }
+        }
+    }
+}
+
+// Integers stay integers where they fit 64 bits, anything else is written as a float
+impl Serialize for Number<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.as_u64(), self.as_i64()) {
+            (Some(n), _) => serializer.serialize_u64(n),
+            (None, Some(n)) => serializer.serialize_i64(n),
+            (None, None) => serializer.serialize_f64(self.as_f64()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+
+    use parser;
+
+    #[test]
+    fn test_serialize() {
+        let value =
+            parser::parse_str(r#"{"a\tb": [1, -2, 2.5, "x\"y"], "c": {"d": true}}"#).unwrap();
+
+        let expected: toml::Value = toml::from_str(
+            r#"
+            "a	b" = [1, -2, 2.5, 'x"y']
+            c = { d = true }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(toml::Value::try_from(&value).unwrap(), expected);
+    }
+}
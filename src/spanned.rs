@@ -0,0 +1,372 @@
+use escape::unescape;
+use lexer::{self, Token, TokenType};
+use number::Number;
+use parser::{self, DuplicateKeys, Json, Map, OwnedParseError, ParserOptions};
+use pointer;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// A parsed document where every value knows where it is in the input, for tools that report
+// problems at a value rather than at a parse error, like linters and schema validators
+
+// Byte offsets of the first byte and one past the last, with the zero based line and column of the
+// start like tokens have
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member<'a> {
+    pub key: Cow<'a, str>,
+    pub key_span: Span,
+    pub value: SpannedJson<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue<'a> {
+    Null,
+    Bool(bool),
+    Number(Number<'a>),
+    String(Cow<'a, str>),
+    // Members in the order of the input
+    Object(Vec<Member<'a>>),
+    Array(Vec<SpannedJson<'a>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedJson<'a> {
+    pub span: Span,
+    pub value: SpannedValue<'a>,
+}
+
+impl<'a> SpannedJson<'a> {
+    // Looks up a value by JSON pointer, like `Json::pointer`
+    pub fn pointer(&self, pointer: &str) -> Option<&SpannedJson<'a>> {
+        let mut current = self;
+
+        for token in pointer::split(pointer)? {
+            current = match &current.value {
+                SpannedValue::Object(members) => members
+                    .iter()
+                    .find(|member| member.key == token)
+                    .map(|member| &member.value)?,
+                SpannedValue::Array(items) => items.get(pointer::index(&token)?)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    // The same value without the spans
+    pub fn to_json(&self) -> Json<'a> {
+        match &self.value {
+            SpannedValue::Null => Json::Null,
+            SpannedValue::Bool(b) => Json::Bool(*b),
+            SpannedValue::Number(n) => Json::Number(n.clone()),
+            SpannedValue::String(s) => Json::String(s.clone()),
+            SpannedValue::Object(members) => Json::Object(
+                members
+                    .iter()
+                    .map(|member| (member.key.clone(), member.value.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+            SpannedValue::Array(items) => {
+                Json::Array(items.iter().map(SpannedJson::to_json).collect())
+            }
+        }
+    }
+}
+
+// From the start of token `first` to the end of token `last`
+fn span(tokens: &[Token], first: usize, last: usize) -> Span {
+    Span {
+        start: tokens[first].start,
+        end: tokens[last].end,
+        line: tokens[first].line,
+        col: tokens[first].col,
+    }
+}
+
+struct Builder<'t, 's> {
+    tokens: &'t [Token<'s>],
+    pos: usize,
+    raw_numbers: bool,
+    duplicate_keys: DuplicateKeys,
+}
+
+impl<'t, 's> Builder<'t, 's> {
+    // Index of the next token that is not a comment
+    fn peek(&mut self) -> usize {
+        while let TokenType::Comment(_) = self.tokens[self.pos].token_type {
+            self.pos += 1;
+        }
+
+        self.pos
+    }
+
+    fn next(&mut self) -> usize {
+        let i = self.peek();
+        self.pos += 1;
+        i
+    }
+
+    fn span(&self, first: usize, last: usize) -> Span {
+        span(self.tokens, first, last)
+    }
+
+    // Skips the comma after an item, the index of the closing bracket once it is reached
+    fn close(&mut self) -> Option<usize> {
+        loop {
+            let i = self.peek();
+
+            match self.tokens[i].token_type {
+                TokenType::CloseCurly | TokenType::CloseSquare => {
+                    self.pos += 1;
+                    return Some(i);
+                }
+                TokenType::Comma => self.pos += 1,
+                _ => return None,
+            }
+        }
+    }
+
+    // Only called on tokens the parser accepted, nesting is limited by its `max_depth`
+    fn value(&mut self) -> SpannedJson<'s> {
+        let start = self.next();
+
+        let (value, last) = match self.tokens[start].token_type {
+            TokenType::Null => (SpannedValue::Null, start),
+            TokenType::Bool(b) => (SpannedValue::Bool(b), start),
+            TokenType::Number(ref n, text) => {
+                let n = if self.raw_numbers {
+                    Number::Raw(Cow::Borrowed(text))
+                } else {
+                    n.clone()
+                };
+                (SpannedValue::Number(n), start)
+            }
+            // `NaN` and `Infinity` with `allow_non_finite`
+            TokenType::Invalid(text) => {
+                let n = match Number::parse_non_finite(text) {
+                    Some(_) => Number::Raw(Cow::Borrowed(text)),
+                    None => unreachable!(),
+                };
+                (SpannedValue::Number(n), start)
+            }
+            TokenType::String(s) => (SpannedValue::String(unescape(s)), start),
+            TokenType::OpenSquare => {
+                let mut items = vec![];
+
+                let last = loop {
+                    if let Some(last) = self.close() {
+                        break last;
+                    }
+
+                    items.push(self.value());
+                };
+
+                (SpannedValue::Array(items), last)
+            }
+            TokenType::OpenCurly => {
+                let mut members: Vec<Member<'s>> = vec![];
+                let mut seen = HashMap::new();
+
+                let last = loop {
+                    if let Some(last) = self.close() {
+                        break last;
+                    }
+
+                    let key_index = self.next();
+                    let key = match self.tokens[key_index].token_type {
+                        TokenType::String(s) | TokenType::Invalid(s) => unescape(s),
+                        _ => unreachable!(),
+                    };
+
+                    self.next();
+
+                    let member = Member {
+                        key_span: self.span(key_index, key_index),
+                        value: self.value(),
+                        key,
+                    };
+
+                    if self.duplicate_keys == DuplicateKeys::Error {
+                        members.push(member);
+                        continue;
+                    }
+
+                    match seen.get(&member.key) {
+                        Some(_) if self.duplicate_keys == DuplicateKeys::FirstWins => {}
+                        Some(&index) => members[index] = member,
+                        None => {
+                            seen.insert(member.key.clone(), members.len());
+                            members.push(member);
+                        }
+                    }
+                };
+
+                (SpannedValue::Object(members), last)
+            }
+            _ => unreachable!(),
+        };
+
+        SpannedJson {
+            span: self.span(start, last),
+            value,
+        }
+    }
+}
+
+// Parses like `parse_str_with_options` and adds the span of every value and key. With
+// `first-wins` and `last-wins` duplicated keys keep the position of their first occurrence
+pub fn parse_spanned<'s>(
+    raw: &'s str,
+    options: ParserOptions,
+) -> Result<SpannedJson<'s>, OwnedParseError> {
+    let tokens = lexer::lex(raw)?;
+    let raw_numbers = options.raw_numbers;
+    let duplicate_keys = options.duplicate_keys;
+
+    parser::parse_with_options(&tokens, options).map_err(OwnedParseError::from)?;
+
+    let mut builder = Builder {
+        tokens: &tokens,
+        pos: 0,
+        raw_numbers,
+        duplicate_keys,
+    };
+
+    Ok(builder.value())
+}
+
+// A document of an input holding several
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document<'a> {
+    pub value: Json<'a>,
+    pub span: Span,
+}
+
+// Parses documents separated by whitespace or nothing at all, like `{} {} []` in logs or the
+// output of `kubectl`. Documents before an error are kept, the error is the last item
+pub fn parse_many<'s>(
+    raw: &'s str,
+    options: ParserOptions,
+) -> Vec<Result<Document<'s>, OwnedParseError>> {
+    let tokens = match lexer::lex(raw) {
+        Ok(tokens) => tokens,
+        Err(lex_error) => return vec![Err(OwnedParseError::from(lex_error))],
+    };
+
+    parser::parse_documents(&tokens, options)
+        .into_iter()
+        .map(|document| {
+            let (value, range) = document.map_err(OwnedParseError::from)?;
+
+            Ok(Document {
+                value,
+                span: span(&tokens, range.start, range.end - 1),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spanned() {
+        let raw = "{\n  \"name\": \"é\",\n  \"list\": [1, true, null]\n}";
+        let value = parse_spanned(raw, ParserOptions::default()).unwrap();
+
+        assert_eq!(
+            value.span,
+            Span {
+                start: 0,
+                end: raw.len(),
+                line: 0,
+                col: 0
+            }
+        );
+
+        let name = value.pointer("/name").unwrap();
+        assert_eq!(&raw[name.span.start..name.span.end], "\"é\"");
+        assert_eq!((name.span.line, name.span.col), (1, 10));
+
+        let list = value.pointer("/list").unwrap();
+        assert_eq!(&raw[list.span.start..list.span.end], "[1, true, null]");
+
+        let null = value.pointer("/list/2").unwrap();
+        assert_eq!(&raw[null.span.start..null.span.end], "null");
+
+        match &value.value {
+            SpannedValue::Object(members) => {
+                let key = members[1].key_span;
+                assert_eq!(&raw[key.start..key.end], "\"list\"");
+            }
+            _ => unreachable!(),
+        }
+
+        assert_eq!(value.to_json(), parser::parse_str(raw).unwrap());
+        assert!(parse_spanned("[1,]", ParserOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_spanned_lenient() {
+        let raw = "{a: 1, /* b */ \"a\": 2, \"c\": [3,],}";
+        let value = parse_spanned(raw, ParserOptions::lenient()).unwrap();
+
+        let a = value.pointer("/a").unwrap();
+        assert_eq!(&raw[a.span.start..a.span.end], "2");
+        assert_eq!(value.pointer("/c/0").unwrap().span.start, 29);
+
+        match &value.value {
+            SpannedValue::Object(members) => assert_eq!(members.len(), 2),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let raw = "{\"a\": 1}\n{\"a\": 2} []\"x\"\n";
+        let documents: Vec<_> = parse_many(raw, ParserOptions::default())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(documents.len(), 4);
+        assert_eq!(
+            documents[1].value.pointer("/a"),
+            Some(&Json::Number(2.0.into()))
+        );
+        assert_eq!(
+            documents[1].span,
+            Span {
+                start: 9,
+                end: 17,
+                line: 1,
+                col: 0
+            }
+        );
+        assert_eq!(&raw[documents[2].span.start..documents[2].span.end], "[]");
+        assert_eq!(
+            &raw[documents[3].span.start..documents[3].span.end],
+            "\"x\""
+        );
+
+        let documents = parse_many("[1] [2 {}", ParserOptions::default());
+        assert_eq!(documents.len(), 2);
+        assert!(documents[0].is_ok());
+        assert_eq!(
+            documents[1].as_ref().unwrap_err().error_type,
+            parser::ParseErrorType::MissingCloseSquare
+        );
+
+        assert!(parse_many(" \n", ParserOptions::default()).is_empty());
+    }
+}
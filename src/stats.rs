@@ -0,0 +1,163 @@
+use parser::Json;
+use visit::{self, Object, Visit};
+
+// What a document is made of, to get an idea of an unfamiliar payload. `Json::Error` counts as a
+// null and the contents of `Json::Raw` values are counted like parsed ones
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    // Arrays and objects nested in each other, 0 for a document that is a single scalar
+    pub max_depth: usize,
+    pub nulls: usize,
+    pub bools: usize,
+    pub numbers: usize,
+    pub strings: usize,
+    pub arrays: usize,
+    pub objects: usize,
+    // Bytes of the decoded string values, keys are not included
+    pub string_bytes: usize,
+    // JSON pointer and length of the array with the most items and the object with the most
+    // members, the first one found of equally large ones
+    pub largest_array: Option<(String, usize)>,
+    pub largest_object: Option<(String, usize)>,
+}
+
+impl Stats {
+    // Every value of the document, including arrays and objects
+    pub fn nodes(&self) -> usize {
+        self.nulls + self.bools + self.numbers + self.strings + self.arrays + self.objects
+    }
+}
+
+struct Collector<'s> {
+    stats: &'s mut Stats,
+    // Pointer of the `Json::Raw` value being walked, paths are relative to it
+    prefix: String,
+    depth: usize,
+}
+
+impl Collector<'_> {
+    fn enter(&mut self) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+    }
+}
+
+fn record(largest: &mut Option<(String, usize)>, pointer: String, len: usize) {
+    match largest {
+        Some((_, largest_len)) if *largest_len >= len => {}
+        _ => *largest = Some((pointer, len)),
+    }
+}
+
+impl<'a> Visit<'a> for Collector<'_> {
+    fn enter_object(&mut self, path: &str, obj: &Object<'a>) -> bool {
+        self.enter();
+        self.stats.objects += 1;
+        record(
+            &mut self.stats.largest_object,
+            self.prefix.clone() + path,
+            obj.len(),
+        );
+        true
+    }
+
+    fn exit_object(&mut self, _path: &str, _obj: &Object<'a>) {
+        self.depth -= 1;
+    }
+
+    fn enter_array(&mut self, path: &str, arr: &[Json<'a>]) -> bool {
+        self.enter();
+        self.stats.arrays += 1;
+        record(
+            &mut self.stats.largest_array,
+            self.prefix.clone() + path,
+            arr.len(),
+        );
+        true
+    }
+
+    fn exit_array(&mut self, _path: &str, _arr: &[Json<'a>]) {
+        self.depth -= 1;
+    }
+
+    fn scalar(&mut self, path: &str, value: &Json<'a>) {
+        match value {
+            Json::Null | Json::Error => self.stats.nulls += 1,
+            Json::Bool(_) => self.stats.bools += 1,
+            Json::Number(_) => self.stats.numbers += 1,
+            Json::String(s) => {
+                self.stats.strings += 1;
+                self.stats.string_bytes += s.len();
+            }
+            Json::Raw(_) => visit::walk(
+                &value.parse_raw(),
+                &mut Collector {
+                    stats: self.stats,
+                    prefix: self.prefix.clone() + path,
+                    depth: self.depth,
+                },
+            ),
+            Json::Array(_) | Json::Object(_) => unreachable!(),
+        }
+    }
+}
+
+pub fn stats(value: &Json) -> Stats {
+    let mut stats = Stats::default();
+
+    visit::walk(
+        value,
+        &mut Collector {
+            stats: &mut stats,
+            prefix: String::new(),
+            depth: 0,
+        },
+    );
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{self, ParserOptions};
+
+    #[test]
+    fn test_stats() {
+        let raw = r#"{"a": [1, 2.5, "é", null], "b": {"c": [[true], {}], "d": "xy"}, "e": [3, 4]}"#;
+        let value = parser::parse_str(raw).unwrap();
+
+        assert_eq!(
+            value.stats(),
+            Stats {
+                max_depth: 4,
+                nulls: 1,
+                bools: 1,
+                numbers: 4,
+                strings: 2,
+                arrays: 4,
+                objects: 3,
+                string_bytes: 4,
+                largest_array: Some(("/a".to_string(), 4)),
+                largest_object: Some(("".to_string(), 3)),
+            }
+        );
+        assert_eq!(value.stats().nodes(), 15);
+
+        let options = ParserOptions {
+            shallow: true,
+            ..ParserOptions::default()
+        };
+        let shallow = parser::parse_str_with_options(raw, options).unwrap();
+        assert_eq!(shallow.stats(), value.stats());
+
+        assert_eq!(
+            Json::from("x").stats(),
+            Stats {
+                strings: 1,
+                string_bytes: 1,
+                ..Stats::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// How many directories are listed as the worst offenders
+const WORST_DIRS: usize = 5;
+
+// Aggregate of a directory validation, to see which errors are the most common and where
+#[derive(Default)]
+pub struct Summary {
+    files: usize,
+    invalid: usize,
+    by_type: HashMap<&'static str, usize>,
+    by_dir: HashMap<PathBuf, usize>,
+}
+
+fn sorted<K: Ord + Clone>(counts: &HashMap<K, usize>) -> Vec<(K, usize)> {
+    let mut counts: Vec<_> = counts.iter().map(|(k, n)| (k.clone(), *n)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+impl Summary {
+    pub fn valid(&mut self) {
+        self.files += 1;
+    }
+
+    // `code` is the one of the error, like `MessageId::code`
+    pub fn invalid(&mut self, path: &Path, code: &'static str) {
+        self.files += 1;
+        self.invalid += 1;
+        *self.by_type.entry(code).or_default() += 1;
+
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        *self.by_dir.entry(dir).or_default() += 1;
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.invalid == 0
+    }
+
+    pub fn render(&self) -> String {
+        let mut result = format!("Checked {} files, {} invalid\n", self.files, self.invalid);
+
+        if self.invalid == 0 {
+            return result;
+        }
+
+        result += "By error type:\n";
+
+        for (code, count) in sorted(&self.by_type) {
+            result += &format!("  {}× {}\n", count, code);
+        }
+
+        result += "Directories with the most invalid files:\n";
+
+        for (dir, count) in sorted(&self.by_dir).into_iter().take(WORST_DIRS) {
+            result += &format!("  {} in {}\n", count, dir.display());
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joxide::diagnostic::MessageId;
+
+    #[test]
+    fn test_render() {
+        let mut summary = Summary::default();
+        assert_eq!(summary.render(), "Checked 0 files, 0 invalid\n");
+
+        summary.valid();
+        summary.invalid(Path::new("b/x.json"), MessageId::DuplicateKey.code());
+        summary.invalid(Path::new("a/x.json"), MessageId::TrailingComma.code());
+        summary.invalid(Path::new("b/y.json"), MessageId::TrailingComma.code());
+
+        assert!(!summary.is_valid());
+        assert_eq!(
+            summary.render(),
+            "Checked 4 files, 3 invalid
+By error type:
+  2× trailing-comma
+  1× duplicate-key
+Directories with the most invalid files:
+  2 in b
+  1 in a
+"
+        );
+    }
+}
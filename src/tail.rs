@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+// Reads the lines appended to a file since the last read, like `tail -f`. A line is only handed
+// out once its newline has been written, so a writer flushing half a record is not reported.
+pub struct Follower {
+    path: PathBuf,
+    offset: u64,
+    // The start of a line whose newline has not been read yet
+    partial: Vec<u8>,
+    line: usize,
+}
+
+fn trim_line(mut line: Vec<u8>) -> Vec<u8> {
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    line
+}
+
+impl Follower {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Follower {
+        Follower {
+            path: path.into(),
+            offset: 0,
+            partial: vec![],
+            line: 0,
+        }
+    }
+
+    // Complete lines with their zero based line numbers, starting over when the file was
+    // truncated. Empty lines are skipped but counted
+    pub fn read(&mut self) -> io::Result<Vec<(usize, Vec<u8>)>> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+            self.line = 0;
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = vec![];
+        file.take(len - self.offset).read_to_end(&mut bytes)?;
+        self.offset += bytes.len() as u64;
+
+        if self.line == 0 && self.partial.is_empty() && bytes.starts_with(b"\xef\xbb\xbf") {
+            bytes.drain(..3);
+        }
+
+        let mut lines = vec![];
+
+        for byte in bytes {
+            if byte != b'\n' {
+                self.partial.push(byte);
+                continue;
+            }
+
+            let line = trim_line(std::mem::take(&mut self.partial));
+
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                lines.push((self.line, line));
+            }
+
+            self.line += 1;
+        }
+
+        Ok(lines)
+    }
+
+    // The last line when the file does not end with a newline
+    pub fn partial(&self) -> Option<(usize, Vec<u8>)> {
+        if self.partial.iter().all(u8::is_ascii_whitespace) {
+            None
+        } else {
+            Some((self.line, trim_line(self.partial.clone())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_follower() {
+        let path = std::env::temp_dir().join(format!("joxide-tail-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "{\"a\": 1}\r\n\n{\"b\": 2}\n{\"c\"").unwrap();
+
+        let mut follower = Follower::new(&path);
+        assert_eq!(
+            follower.read().unwrap(),
+            [(0, b"{\"a\": 1}".to_vec()), (2, b"{\"b\": 2}".to_vec())]
+        );
+        assert_eq!(follower.partial(), Some((3, b"{\"c\"".to_vec())));
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b": 3}\n[]").unwrap();
+
+        assert_eq!(follower.read().unwrap(), [(3, b"{\"c\": 3}".to_vec())]);
+
+        std::fs::write(&path, "1\n").unwrap();
+        assert_eq!(follower.read().unwrap(), [(0, b"1".to_vec())]);
+        assert_eq!(follower.partial(), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
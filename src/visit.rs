@@ -0,0 +1,255 @@
+use parser::{Json, Map};
+use pointer;
+use std::borrow::Cow;
+
+// Walks a document depth first, calling the visitor when entering and leaving each object and
+// array and for every other value, with the JSON pointer of the value. Values of object members
+// come in the order of the map, array items by index
+
+pub type Object<'a> = Map<Cow<'a, str>, Json<'a>>;
+
+pub trait Visit<'a> {
+    // Returning `false` skips the members, `exit_object` is still called
+    fn enter_object(&mut self, _path: &str, _obj: &Object<'a>) -> bool {
+        true
+    }
+
+    fn exit_object(&mut self, _path: &str, _obj: &Object<'a>) {}
+
+    fn enter_array(&mut self, _path: &str, _arr: &[Json<'a>]) -> bool {
+        true
+    }
+
+    fn exit_array(&mut self, _path: &str, _arr: &[Json<'a>]) {}
+
+    // Null, booleans, numbers, strings, `Json::Error` and `Json::Raw`
+    fn scalar(&mut self, _path: &str, _value: &Json<'a>) {}
+}
+
+// Like `Visit`, with values that can be changed in place. Children are visited after
+// `enter_object` and `enter_array` return, so members they add are visited as well
+pub trait VisitMut<'a> {
+    fn enter_object(&mut self, _path: &str, _obj: &mut Object<'a>) -> bool {
+        true
+    }
+
+    fn exit_object(&mut self, _path: &str, _obj: &mut Object<'a>) {}
+
+    fn enter_array(&mut self, _path: &str, _arr: &mut Vec<Json<'a>>) -> bool {
+        true
+    }
+
+    fn exit_array(&mut self, _path: &str, _arr: &mut Vec<Json<'a>>) {}
+
+    // Can replace the value with any other, containers put here are not visited
+    fn scalar(&mut self, _path: &str, _value: &mut Json<'a>) {}
+}
+
+pub fn walk<'a, V: Visit<'a> + ?Sized>(value: &Json<'a>, visitor: &mut V) {
+    walk_at(value, "", visitor);
+}
+
+// Nesting is limited by the parser's `max_depth`, so the recursion stays within the stack
+fn walk_at<'a, V: Visit<'a> + ?Sized>(value: &Json<'a>, path: &str, visitor: &mut V) {
+    match value {
+        Json::Object(obj) => {
+            if visitor.enter_object(path, obj) {
+                for (key, item) in obj {
+                    walk_at(item, &pointer::push(path, key), visitor);
+                }
+            }
+
+            visitor.exit_object(path, obj);
+        }
+        Json::Array(arr) => {
+            if visitor.enter_array(path, arr) {
+                for (index, item) in arr.iter().enumerate() {
+                    walk_at(item, &format!("{}/{}", path, index), visitor);
+                }
+            }
+
+            visitor.exit_array(path, arr);
+        }
+        _ => visitor.scalar(path, value),
+    }
+}
+
+pub fn walk_mut<'a, V: VisitMut<'a> + ?Sized>(value: &mut Json<'a>, visitor: &mut V) {
+    walk_mut_at(value, "", visitor);
+}
+
+fn walk_mut_at<'a, V: VisitMut<'a> + ?Sized>(value: &mut Json<'a>, path: &str, visitor: &mut V) {
+    match value {
+        Json::Object(obj) => {
+            if visitor.enter_object(path, obj) {
+                for (key, item) in obj.iter_mut() {
+                    walk_mut_at(item, &pointer::push(path, key), visitor);
+                }
+            }
+
+            visitor.exit_object(path, obj);
+        }
+        Json::Array(arr) => {
+            if visitor.enter_array(path, arr) {
+                for (index, item) in arr.iter_mut().enumerate() {
+                    walk_mut_at(item, &format!("{}/{}", path, index), visitor);
+                }
+            }
+
+            visitor.exit_array(path, arr);
+        }
+        _ => visitor.scalar(path, value),
+    }
+}
+
+fn transform_at<'a, F>(value: &mut Json<'a>, path: &str, f: &mut F) -> bool
+where
+    F: FnMut(&str, &mut Json<'a>) -> bool,
+{
+    if let Json::Raw(_) = value {
+        *value = value.parse_raw().into_owned();
+    }
+
+    match value {
+        Json::Object(obj) => {
+            obj.retain(|key, item| transform_at(item, &pointer::push(path, key), f))
+        }
+        Json::Array(arr) => {
+            let mut index = 0;
+
+            arr.retain_mut(|item| {
+                let keep = transform_at(item, &format!("{}/{}", path, index), f);
+                index += 1;
+                keep
+            });
+        }
+        _ => {}
+    }
+
+    f(path, value)
+}
+
+// Calls `f` with the JSON pointer of every value, children before the array or object holding
+// them, so it sees them already rewritten. Returning `false` removes the value from its parent,
+// the root is never removed. The contents of `Json::Raw` values are parsed to reach them
+pub fn transform<'a, F>(value: &mut Json<'a>, mut f: F)
+where
+    F: FnMut(&str, &mut Json<'a>) -> bool,
+{
+    transform_at(value, "", &mut f);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser;
+
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl<'a> Visit<'a> for Events {
+        fn enter_object(&mut self, path: &str, obj: &Object<'a>) -> bool {
+            self.0.push(format!("enter {{}} {}", path));
+            !obj.contains_key("skip")
+        }
+
+        fn exit_object(&mut self, path: &str, _obj: &Object<'a>) {
+            self.0.push(format!("exit {{}} {}", path));
+        }
+
+        fn enter_array(&mut self, path: &str, _arr: &[Json<'a>]) -> bool {
+            self.0.push(format!("enter [] {}", path));
+            true
+        }
+
+        fn exit_array(&mut self, path: &str, _arr: &[Json<'a>]) {
+            self.0.push(format!("exit [] {}", path));
+        }
+
+        fn scalar(&mut self, path: &str, value: &Json<'a>) {
+            self.0.push(format!("{} {:?}", path, value));
+        }
+    }
+
+    #[test]
+    fn test_walk() {
+        let value = parser::parse_str(r#"{"a": [1, {"skip": 2}], "b/c": null}"#).unwrap();
+        let mut events = Events::default();
+        walk(&value, &mut events);
+
+        assert_eq!(
+            events.0,
+            [
+                "enter {} ",
+                "enter [] /a",
+                "/a/0 Number(PosInt(1))",
+                "enter {} /a/1",
+                "exit {} /a/1",
+                "exit [] /a",
+                "/b~1c Null",
+                "exit {} ",
+            ]
+        );
+    }
+
+    struct Redact;
+
+    impl<'a> VisitMut<'a> for Redact {
+        fn enter_object(&mut self, _path: &str, obj: &mut Object<'a>) -> bool {
+            obj.retain(|key, _| key != "password");
+            true
+        }
+
+        fn scalar(&mut self, path: &str, value: &mut Json<'a>) {
+            if path.ends_with("/token") {
+                *value = Json::String("***".into());
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        let mut value =
+            parser::parse_str(r#"[{"token": "x", "password": "y"}, {"token": 1}]"#).unwrap();
+        walk_mut(&mut value, &mut Redact);
+
+        assert_eq!(
+            value,
+            parser::parse_str(r#"[{"token": "***"}, {"token": "***"}]"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut value =
+            parser::parse_str(r#"{"A": [1.26, null, {"B": null, "c": 2}], "D": null}"#).unwrap();
+        let mut paths = vec![];
+
+        transform(&mut value, |path, value| {
+            paths.push(path.to_string());
+
+            match value {
+                Json::Null => return false,
+                Json::Number(n) => *value = Json::from((n.as_f64() * 10.0).round() / 10.0),
+                Json::Object(obj) => {
+                    *obj = std::mem::take(obj)
+                        .into_iter()
+                        .map(|(key, item)| (key.to_lowercase().into(), item))
+                        .collect()
+                }
+                _ => {}
+            }
+
+            true
+        });
+
+        assert_eq!(
+            value,
+            parser::parse_str(r#"{"a": [1.3, {"c": 2}]}"#).unwrap()
+        );
+        assert_eq!(
+            paths,
+            ["/A/0", "/A/1", "/A/2/B", "/A/2/c", "/A/2", "/A", "/D", ""]
+        );
+    }
+}
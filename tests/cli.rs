@@ -0,0 +1,127 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs the joxide binary with `args`, reading `raw` from standard input when the file is `-`
+fn joxide(args: &[&str], raw: &str) -> Output {
+    let stdin = args.contains(&"-");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_joxide"))
+        .args(args)
+        .stdin(if stdin { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    if stdin {
+        let mut input = child.stdin.take().unwrap();
+        input.write_all(raw.as_bytes()).unwrap();
+    }
+
+    child.wait_with_output().unwrap()
+}
+
+// A file in the temporary directory holding `contents`, named after the test writing it
+fn file(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!("joxide-cli-{}.json", name));
+    std::fs::write(&path, contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_query_allow_comments() {
+    let raw = "// items\n{\"a\": [1, /* two */ 2, 3]}";
+    let path = file("query-allow-comments", raw);
+
+    for input in [path.as_str(), "-"] {
+        let output = joxide(&["--allow-comments", "query", "$.a[*]", input], raw);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "1\n2\n3\n");
+
+        // Nothing is printed before the comment at the start, and nothing is printed twice
+        let output = joxide(&["query", "$.a[*]", input], raw);
+        assert!(!output.status.success());
+        assert_eq!(stdout(&output), "");
+    }
+}
+
+#[test]
+fn test_query_allow_trailing_commas() {
+    let raw = "{\"a\": [1, 2, 3,], \"b\": {\"c\": 4,},}";
+    let path = file("query-allow-trailing-commas", raw);
+
+    for input in [path.as_str(), "-"] {
+        let output = joxide(&["--allow-trailing-commas", "query", "$.a[*]", input], raw);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "1\n2\n3\n");
+
+        // The matches before the trailing comma are printed once, then the error
+        let output = joxide(&["query", "$.a[*]", input], raw);
+        assert!(!output.status.success());
+        assert_eq!(stdout(&output), "1\n2\n3\n");
+    }
+}
+
+#[test]
+fn test_merge_on_conflict() {
+    let ours = file("merge-ours", "{\"a\": 1, \"b\": 1}\n");
+    let theirs = file("merge-theirs", "{\"b\": 2, \"c\": 3}\n");
+
+    let output = joxide(&["merge", &ours, &theirs], "");
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("<<<<<<< ours"));
+
+    for (on_conflict, merged) in [
+        ("ours", "{\"a\":1,\"b\":1,\"c\":3}\n"),
+        ("theirs", "{\"a\":1,\"b\":2,\"c\":3}\n"),
+    ] {
+        let output = joxide(&["merge", "--on-conflict", on_conflict, &ours, &theirs], "");
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), merged);
+    }
+}
+
+#[test]
+fn test_validate_allowed_syntax() {
+    let path = file("validate-allowed-syntax", "{\"a\": [1, 2,], \"a\": 3}\n");
+    let args = [
+        "--allow-trailing-commas",
+        "--duplicate-keys",
+        "first-wins",
+        "validate",
+    ];
+
+    // Warned about but valid, for a single file and for a list of files alike
+    let output = joxide(&[&args[..], &[path.as_str()]].concat(), "");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("railing comma"));
+    assert!(stderr.contains("uplicate"));
+
+    let list = file("validate-allowed-syntax-list", &path);
+    let output = joxide(&[&args[..], &["--files-from", list.as_str()]].concat(), "");
+    assert!(output.status.success());
+
+    // A warning that was asked for still fails with the default threshold
+    let bom = file("validate-allowed-syntax-bom", "\u{feff}{\"a\": [1, 2,]}\n");
+    let output = joxide(
+        &["--allow-trailing-commas", "--warn-bom", "validate", &bom],
+        "",
+    );
+    assert!(!output.status.success());
+    let output = joxide(
+        &[
+            "--allow-trailing-commas",
+            "--warn-bom",
+            "validate",
+            "--min-severity",
+            "error",
+            &bom,
+        ],
+        "",
+    );
+    assert!(output.status.success());
+}